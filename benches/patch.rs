@@ -0,0 +1,76 @@
+//! JSON Patch throughput on large documents
+//!
+//! Run with `cargo bench --bench patch`. Operations edit one working copy in
+//! place, so a patch costs a single copy of the document plus a little per
+//! operation: adding operations should barely move the total.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use dtx::core::patcher::{self, PatchOperation};
+use serde_json::{json, Value as JsonValue};
+
+/// Document with `records` objects under `/items`
+fn document(records: usize) -> JsonValue {
+    let items: Vec<JsonValue> = (0..records)
+        .map(|i| json!({"id": i, "name": format!("item-{}", i), "tags": ["a", "b"]}))
+        .collect();
+    json!({"metadata": {"version": 1}, "items": items})
+}
+
+/// A mix of operations spread across the document
+fn operations(records: usize, count: usize) -> Vec<PatchOperation> {
+    (0..count)
+        .map(|i| {
+            let index = (i * 7919) % records;
+            match i % 4 {
+                0 => PatchOperation::Replace {
+                    path: format!("/items/{}/name", index),
+                    value: json!(format!("renamed-{}", i)),
+                },
+                1 => PatchOperation::Add {
+                    path: format!("/items/{}/tags/-", index),
+                    value: json!("c"),
+                },
+                2 => PatchOperation::Copy {
+                    from: format!("/items/{}/id", index),
+                    path: format!("/items/{}/copy", index),
+                },
+                _ => PatchOperation::Test {
+                    path: "/metadata/version".to_string(),
+                    value: json!(1),
+                },
+            }
+        })
+        .collect()
+}
+
+fn measure(records: usize, count: usize) -> Duration {
+    let doc = document(records);
+    let patch = operations(records, count);
+    let iterations = 5;
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(patcher::apply_patch(black_box(&doc), &patch).expect("patch applies"));
+    }
+    start.elapsed() / iterations
+}
+
+fn main() {
+    println!(
+        "{:>10} {:>8} {:>12} {:>12}",
+        "records", "ops", "total", "per op"
+    );
+    for records in [1_000, 10_000, 100_000] {
+        for count in [100, 1_000] {
+            let elapsed = measure(records, count);
+            println!(
+                "{:>10} {:>8} {:>12.2?} {:>12.2?}",
+                records,
+                count,
+                elapsed,
+                elapsed / count as u32
+            );
+        }
+    }
+}
@@ -1,8 +1,57 @@
 //! CLI argument definitions using clap
 
-use clap::{Parser, Subcommand};
-use clap_complete::Shell;
-use std::path::PathBuf;
+use clap::builder::PossibleValuesParser;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::core::differ;
+use crate::formats::registry;
+use crate::formats::yaml::YAML_VERSIONS;
+use crate::utils::encoding::{ENCODING_NAMES, EOL_NAMES};
+
+/// Format names accepted by format options (also used for shell completion)
+pub const FORMAT_NAMES: [&str; 9] = [
+    "json", "yaml", "yml", "toml", "csv", "tsv", "xml", "ndjson", "jsonl",
+];
+
+/// Format names accepted where binary formats can be read or written as well
+pub const CONVERT_FORMAT_NAMES: [&str; 23] = [
+    "json",
+    "yaml",
+    "yml",
+    "toml",
+    "csv",
+    "tsv",
+    "xml",
+    "ndjson",
+    "jsonl",
+    "msgpack",
+    "parquet",
+    "xlsx",
+    "html",
+    "htm",
+    "sql",
+    "plist",
+    "bplist",
+    "properties",
+    "hcl",
+    "proto",
+    "avro",
+    "bson",
+    "sqlite",
+];
+
+/// Parser for `--from` and `--to`: the built-in names plus registered formats
+fn convert_format_names() -> PossibleValuesParser {
+    PossibleValuesParser::new(CONVERT_FORMAT_NAMES.into_iter().chain(registry::names()))
+}
+
+/// SQL dialect names (also used for shell completion)
+pub const SQL_DIALECTS: [&str; 3] = ["sqlite", "postgres", "mysql"];
+
+/// Merge strategy names (also used for shell completion)
+pub const MERGE_STRATEGIES: [&str; 5] = ["deep", "shallow", "concat", "union", "strategic"];
 
 /// dtx - Data Transformation Swiss Army Knife
 #[derive(Parser, Debug)]
@@ -13,6 +62,76 @@ pub struct Cli {
     #[arg(long, global = true)]
     pub no_color: bool,
 
+    /// Copy output to the system clipboard instead of printing it
+    #[arg(long, global = true)]
+    pub clipboard: bool,
+
+    /// Encoding of input without a byte order mark (default: utf-8)
+    #[arg(long, global = true, value_parser = ENCODING_NAMES, ignore_case = true)]
+    pub encoding: Option<String>,
+
+    /// Encoding of output written to stdout or files (default: utf-8)
+    #[arg(long, global = true, value_parser = ENCODING_NAMES, ignore_case = true)]
+    pub output_encoding: Option<String>,
+
+    /// Line endings for output (default: same as the input)
+    #[arg(long, global = true, value_parser = EOL_NAMES, ignore_case = true)]
+    pub eol: Option<String>,
+
+    /// Spaces per indentation level in JSON, XML and TOML output (default: 2)
+    #[arg(long, global = true, value_name = "N")]
+    pub indent: Option<usize>,
+
+    /// Indent JSON, XML and TOML output with tabs
+    #[arg(long, global = true, conflicts_with = "indent")]
+    pub tabs: bool,
+
+    /// Round floats in output to N digits after the decimal point
+    #[arg(long, global = true, value_name = "N")]
+    pub float_precision: Option<usize>,
+
+    /// Write floats in scientific notation once their decimal exponent reaches
+    /// ±EXP (3 writes 1500.0 as 1.5e3)
+    #[arg(long, global = true, value_name = "EXP")]
+    pub float_sci: Option<u32>,
+
+    /// Keep trailing zeros up to --float-precision digits (1.50 instead of 1.5)
+    #[arg(long, global = true, requires = "float_precision")]
+    pub float_trailing_zeros: bool,
+
+    /// YAML revision for plain scalars: 1.1 reads yes/no/on/off as booleans and
+    /// 1:30 as a base-60 number; 1.2 keeps them strings
+    #[arg(long, global = true, value_parser = YAML_VERSIONS, default_value = "1.2")]
+    pub yaml_version: String,
+
+    /// Format used to report errors on stderr
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
+
+    /// Increase log verbosity (-v info, -vv debug, -vvv trace)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+
+    /// Format used for log messages on stderr
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    /// Print stable status lines for scripts instead of decorative messages
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "tsv"
+    )]
+    pub porcelain: Option<PorcelainFormat>,
+
+    /// Report how long each phase (read, detect, parse, ...) took on stderr
+    #[arg(long, global = true)]
+    pub timing: bool,
+
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -40,6 +159,9 @@ pub enum Commands {
     /// Convert between formats
     Convert(ConvertArgs),
 
+    /// Show an array of objects as an aligned table
+    Table(TableArgs),
+
     /// Query and transform data using JSONPath and filters
     Query(QueryArgs),
 
@@ -58,16 +180,98 @@ pub enum Commands {
     /// Apply JSON Patch (RFC 6902) to a document
     Patch(PatchArgs),
 
+    /// Read or edit the front matter of Markdown files
+    Frontmatter(FrontmatterArgs),
+
     /// Render template with variable substitution
     Template(TemplateArgs),
 
     /// Execute batch jobs from config file
     Batch(BatchArgs),
 
+    /// Serve conversion, query and validation over HTTP
+    Serve(ServeArgs),
+
+    /// Compare parse/serialize time and output size across formats
+    Bench(BenchArgs),
+
     /// Generate shell completion scripts
     Completions(CompletionsArgs),
 }
 
+/// Error reporting format
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Human-readable error chain
+    Text,
+    /// Structured JSON object (code, message, file, line, column)
+    Json,
+}
+
+/// Status line format for `--porcelain`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PorcelainFormat {
+    /// Event name and fields separated by tabs
+    Tsv,
+    /// One JSON object per line
+    Json,
+}
+
+/// Log message format
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable log lines
+    Text,
+    /// One JSON object per log event
+    Json,
+}
+
+/// Keep `template -v FILE` working as short for `--vars FILE`, as it was before
+/// `-v` became the global verbosity flag: after the template subcommand, a `-v`
+/// followed by a value rather than an option names the variables file
+pub fn expand_template_vars(mut args: Vec<OsString>) -> Vec<OsString> {
+    let Some(template_pos) = args.iter().position(|arg| arg == "template") else {
+        return args;
+    };
+    for i in template_pos + 1..args.len().saturating_sub(1) {
+        if args[i] == "--" {
+            break;
+        }
+        let value = args[i + 1].to_str();
+        if args[i] == "-v" && !value.is_some_and(|s| s.starts_with('-')) {
+            args[i] = "--vars".into();
+        }
+    }
+    args
+}
+
+impl Commands {
+    /// Primary input file of the command, if any
+    pub fn input_path(&self) -> Option<&Path> {
+        match self {
+            Commands::Json(args) => args.input.as_deref(),
+            Commands::Yaml(args) => args.input.as_deref(),
+            Commands::Toml(args) => args.input.as_deref(),
+            Commands::Csv(args) => args.input.as_deref(),
+            Commands::Xml(args) => args.input.as_deref(),
+            Commands::Auto(args) => args.input.as_deref(),
+            Commands::Convert(args) => args.input.as_deref(),
+            Commands::Table(args) => args.input.as_deref(),
+            Commands::Query(args) => args.input.as_deref(),
+            Commands::Validate(args) => args.input.as_deref(),
+            Commands::Diff(args) => Some(args.file1.as_path()),
+            Commands::Schema(args) => args.input.as_deref(),
+            Commands::Merge(args) => args.files.first().map(|p| p.as_path()),
+            Commands::Patch(args) => args.input.as_deref(),
+            Commands::Frontmatter(args) => args.input.as_deref(),
+            Commands::Template(args) => args.template.as_deref(),
+            Commands::Batch(args) => args.config.as_deref(),
+            Commands::Bench(args) => Some(args.input.as_path()),
+            Commands::Serve(_) | Commands::Completions(_) => None,
+        }
+    }
+}
+
 /// Arguments for the json subcommand
 #[derive(Parser, Debug)]
 pub struct JsonArgs {
@@ -77,6 +281,14 @@ pub struct JsonArgs {
     /// Output compact JSON (no pretty printing)
     #[arg(short, long)]
     pub compact: bool,
+
+    /// Verify the input is already formatted; exit nonzero with a diff if not
+    #[arg(long)]
+    pub check: bool,
+
+    /// Emit the highlighted output as an HTML snippet with inline styles
+    #[arg(long)]
+    pub html: bool,
 }
 
 /// Arguments for the yaml subcommand
@@ -84,6 +296,14 @@ pub struct JsonArgs {
 pub struct YamlArgs {
     /// Input file (reads from stdin if not provided)
     pub input: Option<PathBuf>,
+
+    /// Verify the input is already formatted; exit nonzero with a diff if not
+    #[arg(long)]
+    pub check: bool,
+
+    /// Emit the highlighted output as an HTML snippet with inline styles
+    #[arg(long)]
+    pub html: bool,
 }
 
 /// Arguments for the toml subcommand
@@ -95,6 +315,22 @@ pub struct TomlArgs {
     /// Output compact TOML (no pretty printing)
     #[arg(short, long)]
     pub compact: bool,
+
+    /// Write tables nested deeper than N as inline tables instead of [sections]
+    #[arg(long, value_name = "N", conflicts_with = "compact")]
+    pub inline_depth: Option<usize>,
+
+    /// Write arrays of tables as inline arrays instead of [[sections]]
+    #[arg(long, conflicts_with = "compact")]
+    pub inline_arrays: bool,
+
+    /// Verify the input is already formatted; exit nonzero with a diff if not
+    #[arg(long)]
+    pub check: bool,
+
+    /// Emit the highlighted output as an HTML snippet with inline styles
+    #[arg(long)]
+    pub html: bool,
 }
 
 /// Arguments for the csv subcommand
@@ -107,11 +343,42 @@ pub struct CsvArgs {
     #[arg(long)]
     pub no_headers: bool,
 
+    /// Skip this many lines at the start of the input (e.g. a metadata preamble)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub skip_rows: usize,
+
+    /// Ignore lines starting with this character (e.g. '#')
+    #[arg(long, value_name = "CHAR")]
+    pub comment: Option<String>,
+
+    /// Field delimiter (e.g. ';' or 'tab'); detected from the content when omitted
+    #[arg(short, long, value_name = "CHAR")]
+    pub delimiter: Option<String>,
+
     /// Output raw CSV instead of table format
     #[arg(short, long)]
     pub raw: bool,
 }
 
+/// Arguments for the table subcommand
+#[derive(Parser, Debug)]
+pub struct TableArgs {
+    /// Input file (reads from stdin if not provided)
+    pub input: Option<PathBuf>,
+
+    /// Columns to show, comma-separated (nested fields as parent.child)
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// Truncate cells wider than N columns (the table is also fit to the terminal)
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_width: Option<usize>,
+}
+
 /// Arguments for the xml subcommand
 #[derive(Parser, Debug)]
 pub struct XmlArgs {
@@ -121,6 +388,10 @@ pub struct XmlArgs {
     /// Output compact XML (no pretty printing)
     #[arg(short, long)]
     pub compact: bool,
+
+    /// Emit the highlighted output as an HTML snippet with inline styles
+    #[arg(long)]
+    pub html: bool,
 }
 
 /// Arguments for the auto subcommand
@@ -132,29 +403,145 @@ pub struct AutoArgs {
     /// Suppress format detection message
     #[arg(short, long)]
     pub quiet: bool,
+
+    /// Print a JSON summary of the format and document structure instead of the content
+    #[arg(long)]
+    pub info: bool,
 }
 
 /// Arguments for the convert subcommand
 #[derive(Parser, Debug)]
 pub struct ConvertArgs {
-    /// Input file (reads from stdin if not provided)
+    /// Input file or glob pattern (reads from stdin if not provided)
     pub input: Option<PathBuf>,
 
-    /// Target format(s), comma-separated (e.g., yaml,toml,csv)
-    #[arg(short, long, required = true)]
-    pub to: String,
+    /// Target format(s), comma-separated (e.g., yaml,toml,csv); inferred from --output if omitted
+    #[arg(
+        short,
+        long,
+        required_unless_present = "output",
+        value_delimiter = ',',
+        value_parser = convert_format_names(),
+        ignore_case = true
+    )]
+    pub to: Vec<String>,
 
     /// Source format (auto-detected if not specified)
-    #[arg(short, long)]
+    #[arg(short, long, value_parser = convert_format_names(), ignore_case = true)]
     pub from: Option<String>,
 
     /// Output file (outputs to stdout if not specified)
-    #[arg(short, long)]
+    #[arg(short, long, conflicts_with = "out_dir")]
     pub output: Option<PathBuf>,
 
+    /// Directory to write converted files to (required for glob inputs)
+    #[arg(long, value_name = "DIR")]
+    pub out_dir: Option<PathBuf>,
+
+    /// Mirror the input directory layout under --out-dir
+    #[arg(long, requires = "out_dir")]
+    pub preserve_structure: bool,
+
+    /// Repeating XML element to use as CSV rows (e.g. books/book); auto-detected if omitted
+    #[arg(long, value_name = "PATH")]
+    pub record_path: Option<String>,
+
+    /// Group CSV rows sharing this column's value into one parent object
+    #[arg(long, value_name = "COLUMN")]
+    pub group_by: Option<String>,
+
+    /// Name of the nested array of child records when grouping [default: items]
+    #[arg(long, value_name = "NAME", requires = "group_by")]
+    pub children: Option<String>,
+
+    /// Skip this many lines at the start of CSV input (e.g. a metadata preamble)
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    pub skip_rows: usize,
+
+    /// Ignore CSV lines starting with this character (e.g. '#')
+    #[arg(long, value_name = "CHAR")]
+    pub comment: Option<String>,
+
+    /// CSV field delimiter for input and output (e.g. ';' or 'tab'); input is
+    /// sniffed when omitted
+    #[arg(long, value_name = "CHAR")]
+    pub delimiter: Option<String>,
+
+    /// Worksheet to read from XLSX input, by name or 1-based position (default: the first);
+    /// also names the worksheet of XLSX output (default: Sheet1)
+    #[arg(long, value_name = "NAME|N")]
+    pub sheet: Option<String>,
+
+    /// Table to read from SQLite input, by name or 1-based position (default: every
+    /// table, keyed by name), or from HTML input by 1-based position (default: the first)
+    #[arg(long, value_name = "NAME|N")]
+    pub table: Option<String>,
+
+    /// Read CSV dates in this strftime format and emit ISO 8601; COLUMN=FORMAT limits it to one column
+    #[arg(long, value_name = "[COLUMN=]FORMAT", action = clap::ArgAction::Append)]
+    pub date_format: Vec<String>,
+
+    /// Write null and missing values in CSV output as this text (e.g. NULL or '\N')
+    /// so they differ from empty strings
+    #[arg(long, value_name = "TEXT")]
+    pub null_as: Option<String>,
+
+    /// Table name for SQL output (default: the input file name, or "data")
+    #[arg(long, value_name = "NAME")]
+    pub table_name: Option<String>,
+
+    /// SQL dialect for SQL output
+    #[arg(long, value_parser = SQL_DIALECTS, default_value = "sqlite")]
+    pub dialect: String,
+
+    /// Protobuf descriptor set (protoc --descriptor_set_out) describing proto input or output
+    #[arg(long, value_name = "FILE", requires = "message")]
+    pub descriptor: Option<PathBuf>,
+
+    /// Fully qualified protobuf message type of the payload (e.g. my.pkg.Msg)
+    #[arg(long, value_name = "NAME", requires = "descriptor")]
+    pub message: Option<String>,
+
+    /// Avro schema (.avsc) to encode Avro output with
+    #[arg(long, value_name = "FILE")]
+    pub schema: Option<PathBuf>,
+
+    /// Write TOML tables nested deeper than N as inline tables instead of [sections]
+    #[arg(long, value_name = "N")]
+    pub inline_depth: Option<usize>,
+
+    /// Write TOML arrays of tables as inline arrays instead of [[sections]]
+    #[arg(long)]
+    pub inline_arrays: bool,
+
+    /// Write XML text containing markup characters (<, >, &) as CDATA instead of escaping it
+    #[arg(long)]
+    pub cdata: bool,
+
+    /// Always write the text of these XML elements as CDATA (e.g. book/description)
+    #[arg(long, value_name = "PATH", action = clap::ArgAction::Append)]
+    pub cdata_path: Vec<String>,
+
+    /// XML elements that always become JSON arrays, even when they occur once
+    /// (e.g. item,book/author)
+    #[arg(long, value_name = "PATHS", value_delimiter = ',')]
+    pub force_array: Vec<String>,
+
+    /// Cast values to the types declared in this JSON Schema (file or HTTP(S) URL)
+    #[arg(long, value_name = "FILE|URL")]
+    pub coerce_schema: Option<String>,
+
+    /// Verify output files are up to date without writing; exit nonzero with a diff if not
+    #[arg(long)]
+    pub check: bool,
+
     /// Suppress conversion messages
     #[arg(long)]
     pub quiet: bool,
+
+    /// Operate on the front matter of a Markdown file (implied for .md files)
+    #[arg(long)]
+    pub front_matter: bool,
 }
 
 /// Arguments for the query subcommand
@@ -163,7 +550,7 @@ pub struct QueryArgs {
     /// Input file (reads from stdin if not provided)
     pub input: Option<PathBuf>,
 
-    /// JSONPath query (e.g., '$.users[*].name')
+    /// JSONPath query (e.g., '$.users[*].name') or JSON Pointer (e.g., '/users/0/name')
     #[arg(short, long)]
     pub query: Option<String>,
 
@@ -183,11 +570,19 @@ pub struct QueryArgs {
     #[arg(long)]
     pub separator: Option<String>,
 
+    /// Flatten at most N levels of nesting, keeping deeper values as they are
+    #[arg(long, value_name = "N", requires = "flatten")]
+    pub depth: Option<usize>,
+
+    /// Array positions in flattened keys: index (a.b.[0].c), bracket (a.b[0].c) or dot (a.b.0.c)
+    #[arg(long, value_parser = ["index", "bracket", "dot"], default_value = "index", requires = "flatten")]
+    pub array_notation: String,
+
     /// Sort object keys alphabetically
     #[arg(long)]
     pub sort_keys: bool,
 
-    /// Filter array elements (e.g., 'age > 20')
+    /// Filter array elements (e.g., 'age > 20', 'email exists', 'status in [a, b]', 'name !~ ^x')
     #[arg(long)]
     pub filter: Option<String>,
 
@@ -195,6 +590,19 @@ pub struct QueryArgs {
     #[arg(long)]
     pub select: Option<String>,
 
+    /// Apply string operations to a field (e.g., 'name=trim|lower'; repeatable)
+    #[arg(long, value_name = "FIELD=OPS", action = clap::ArgAction::Append)]
+    pub map: Vec<String>,
+
+    /// Set the value at a JSONPath or JSON Pointer, creating missing keys, before
+    /// the query runs (e.g., '$.server.port=8080'; repeatable)
+    #[arg(long, value_name = "PATH=VALUE", action = clap::ArgAction::Append)]
+    pub set: Vec<String>,
+
+    /// Add a field computed from others (e.g., 'total = price * qty'; repeatable)
+    #[arg(long, value_name = "FIELD=EXPR", action = clap::ArgAction::Append)]
+    pub add_field: Vec<String>,
+
     /// Get unique values from array
     #[arg(long)]
     pub unique: bool,
@@ -215,6 +623,35 @@ pub struct QueryArgs {
     #[arg(long)]
     pub last: Option<usize>,
 
+    /// Get the elements in a range, negative indices counting from the end
+    /// (e.g., 2:5, -10:, ::2, ::-1)
+    #[arg(long, value_name = "START:END[:STEP]", allow_hyphen_values = true)]
+    pub slice: Option<String>,
+
+    /// Sum a numeric field across array elements (repeatable)
+    #[arg(long, value_name = "FIELD", action = clap::ArgAction::Append)]
+    pub sum: Vec<String>,
+
+    /// Average a numeric field across array elements (repeatable)
+    #[arg(long, value_name = "FIELD", action = clap::ArgAction::Append)]
+    pub avg: Vec<String>,
+
+    /// Minimum of a numeric field across array elements (repeatable)
+    #[arg(long, value_name = "FIELD", action = clap::ArgAction::Append)]
+    pub min: Vec<String>,
+
+    /// Maximum of a numeric field across array elements (repeatable)
+    #[arg(long, value_name = "FIELD", action = clap::ArgAction::Append)]
+    pub max: Vec<String>,
+
+    /// Count the distinct values of a field, most frequent first
+    #[arg(long, value_name = "FIELD", conflicts_with_all = ["sum", "avg", "min", "max"])]
+    pub frequency: Option<String>,
+
+    /// Keep only the N most frequent values
+    #[arg(long, value_name = "N", requires = "frequency")]
+    pub top: Option<usize>,
+
     /// Apply operations recursively
     #[arg(short, long)]
     pub recursive: bool,
@@ -226,25 +663,73 @@ pub struct QueryArgs {
     /// Output without syntax highlighting
     #[arg(long)]
     pub raw: bool,
+
+    /// Emit array results as NDJSON, one unhighlighted JSON value per line
+    #[arg(long, conflicts_with = "compact")]
+    pub ndjson: bool,
+
+    /// Stream elements of a top-level JSON array, one result per line
+    /// (supports --filter, --add-field, --select, --map, --sort-keys, --first, --count)
+    #[arg(long)]
+    pub stream: bool,
+
+    /// Edit the JSONPath query and filter in a prompt with a live preview
+    #[arg(short, long, requires = "input", conflicts_with = "stream")]
+    pub interactive: bool,
+
+    /// Operate on the front matter of a Markdown file (implied for .md files)
+    #[arg(long)]
+    pub front_matter: bool,
 }
 
 /// Arguments for the validate subcommand
 #[derive(Parser, Debug)]
 pub struct ValidateArgs {
-    /// Input file (reads from stdin if not provided)
+    /// Input file or glob pattern (reads from stdin if not provided)
     pub input: Option<PathBuf>,
 
-    /// JSON Schema file to validate against
-    #[arg(short, long)]
-    pub schema: Option<PathBuf>,
+    /// JSON Schema file or HTTP(S) URL to validate against
+    #[arg(short, long, value_name = "FILE|URL")]
+    pub schema: Option<String>,
+
+    /// Use only cached copies of remote schemas
+    #[arg(long)]
+    pub offline: bool,
 
     /// Specify input format (auto-detected if not specified)
-    #[arg(short, long)]
+    #[arg(short, long, value_parser = FORMAT_NAMES, ignore_case = true)]
     pub format: Option<String>,
 
     /// Treat first row as data (for CSV)
     #[arg(long)]
     pub no_headers: bool,
+
+    /// Validate as an OpenAPI 3.0/3.1 document
+    #[arg(long, conflicts_with = "schema")]
+    pub openapi: bool,
+
+    /// Validate Kubernetes manifests (optionally for a version, e.g. --kubernetes=1.29)
+    #[arg(
+        long,
+        value_name = "VERSION",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = crate::core::kubernetes::DEFAULT_VERSION,
+        conflicts_with_all = ["schema", "openapi"]
+    )]
+    pub kubernetes: Option<String>,
+
+    /// Directory of Kubernetes JSON schemas (kubernetes-json-schema layout)
+    #[arg(long, value_name = "DIR", requires = "kubernetes")]
+    pub kubernetes_schemas: Option<PathBuf>,
+
+    /// Operate on the front matter of a Markdown file (implied for .md files)
+    #[arg(long)]
+    pub front_matter: bool,
+
+    /// Emit a report instead of the terminal summary (md: Markdown for PR comments)
+    #[arg(long, value_name = "FORMAT", value_parser = ["md"])]
+    pub report: Option<String>,
 }
 
 /// Arguments for the diff subcommand
@@ -264,15 +749,60 @@ pub struct DiffArgs {
     #[arg(short, long)]
     pub side_by_side: bool,
 
+    /// Line width of the side-by-side view (defaults to the terminal width)
+    #[arg(long, value_name = "N", requires = "side_by_side")]
+    pub max_width: Option<usize>,
+
     /// Show only summary of changes
     #[arg(long)]
     pub summary: bool,
+
+    /// Lines of unchanged context around each change in unified diffs
+    #[arg(
+        short = 'U',
+        long,
+        value_name = "N",
+        default_value_t = differ::DEFAULT_CONTEXT,
+        conflicts_with_all = ["patch", "side_by_side", "summary"]
+    )]
+    pub context: usize,
+
+    /// Write the diff to a file instead of stdout (uncolored)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Match array elements by this field instead of by position
+    #[arg(long, value_name = "FIELD")]
+    pub array_key: Option<String>,
+
+    /// Path to leave out of the comparison (JSON Pointer or dot path, `*` matches any segment; repeatable)
+    #[arg(long, value_name = "PATH", action = clap::ArgAction::Append)]
+    pub ignore: Vec<String>,
+
+    /// Treat values matching this regex on both sides as equal (e.g. timestamps; the
+    /// whole value must match; repeatable)
+    #[arg(long, value_name = "REGEX", action = clap::ArgAction::Append)]
+    pub ignore_value_pattern: Vec<String>,
+
+    /// Emit a report instead of a diff (md: Markdown for PR comments)
+    #[arg(
+        long,
+        value_name = "FORMAT",
+        value_parser = ["md"],
+        conflicts_with_all = ["patch", "side_by_side", "summary"]
+    )]
+    pub report: Option<String>,
 }
 
 /// Arguments for the schema subcommand
 #[derive(Parser, Debug)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct SchemaArgs {
-    /// Input file (reads from stdin if not provided)
+    #[command(subcommand)]
+    pub command: Option<SchemaCommand>,
+
+    /// Input file or glob pattern; several files are inferred as one schema
+    /// (reads from stdin if not provided)
     pub input: Option<PathBuf>,
 
     /// Output file (outputs to stdout if not specified)
@@ -287,6 +817,95 @@ pub struct SchemaArgs {
     #[arg(long)]
     pub name: Option<String>,
 
+    /// Infer from N records only (NDJSON files are read no further than needed)
+    #[arg(long, value_name = "N")]
+    pub sample: Option<usize>,
+
+    /// Sample records at random from the whole input instead of taking the first N
+    #[arg(long, requires = "sample")]
+    pub random: bool,
+
+    /// Seed for --random, for a reproducible sample
+    #[arg(long, value_name = "N", requires = "random")]
+    pub seed: Option<u64>,
+
+    /// Output without syntax highlighting
+    #[arg(long)]
+    pub raw: bool,
+}
+
+/// Schema operations other than generation
+#[derive(Subcommand, Debug)]
+pub enum SchemaCommand {
+    /// Bundle a multi-file JSON Schema into a single self-contained schema
+    Bundle(SchemaBundleArgs),
+
+    /// Classify changes between two schemas as compatible or breaking
+    Diff(SchemaDiffArgs),
+
+    /// Merge JSON Schemas into one that accepts data valid under any of them
+    Merge(SchemaMergeArgs),
+}
+
+/// Arguments for the schema bundle subcommand
+#[derive(Parser, Debug)]
+pub struct SchemaBundleArgs {
+    /// Root JSON Schema file or HTTP(S) URL
+    #[arg(value_name = "FILE|URL")]
+    pub schema: String,
+
+    /// Replace every $ref with its target instead of collecting them under $defs
+    #[arg(long)]
+    pub dereference: bool,
+
+    /// Use only cached copies of remote schemas
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Output file (outputs to stdout if not specified)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Output without syntax highlighting
+    #[arg(long)]
+    pub raw: bool,
+}
+
+/// Arguments for the schema diff subcommand
+#[derive(Parser, Debug)]
+pub struct SchemaDiffArgs {
+    /// Old JSON Schema file or HTTP(S) URL
+    #[arg(value_name = "OLD")]
+    pub old: String,
+
+    /// New JSON Schema file or HTTP(S) URL
+    #[arg(value_name = "NEW")]
+    pub new: String,
+
+    /// Use only cached copies of remote schemas
+    #[arg(long)]
+    pub offline: bool,
+}
+
+/// Arguments for the schema merge subcommand
+#[derive(Parser, Debug)]
+pub struct SchemaMergeArgs {
+    /// JSON Schema files or HTTP(S) URLs to merge (at least 2 required)
+    #[arg(required = true, num_args = 2.., value_name = "FILE|URL")]
+    pub schemas: Vec<String>,
+
+    /// How required lists combine: intersection (required everywhere) or union
+    #[arg(long, value_parser = ["intersection", "union"], default_value = "intersection")]
+    pub required: String,
+
+    /// Use only cached copies of remote schemas
+    #[arg(long)]
+    pub offline: bool,
+
+    /// Output file (outputs to stdout if not specified)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
     /// Output without syntax highlighting
     #[arg(long)]
     pub raw: bool,
@@ -295,20 +914,32 @@ pub struct SchemaArgs {
 /// Arguments for the merge subcommand
 #[derive(Parser, Debug)]
 pub struct MergeArgs {
-    /// Files to merge (at least 2 required)
-    #[arg(required = true, num_args = 2..)]
+    /// Files or glob patterns to merge, in order (at least 2 files required)
+    #[arg(required_unless_present = "base", conflicts_with = "base", num_args = 1..)]
     pub files: Vec<PathBuf>,
 
+    /// Base file of a layered config, merged with the overlays for --env
+    #[arg(long, value_name = "FILE", requires = "env")]
+    pub base: Option<PathBuf>,
+
+    /// Environment whose overlays (BASE.ENV.yaml, overlays/ENV/*) are merged onto --base
+    #[arg(long, value_name = "NAME", requires = "base")]
+    pub env: Option<String>,
+
     /// Output file (outputs to stdout if not specified)
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
-    /// Merge strategy: deep, shallow, concat, union
-    #[arg(short, long)]
+    /// Merge strategy: deep, shallow, concat, union, strategic (Kubernetes)
+    #[arg(short, long, value_parser = MERGE_STRATEGIES)]
     pub strategy: Option<String>,
 
+    /// Remove keys set to null in a later file (JSON Merge Patch semantics)
+    #[arg(long)]
+    pub null_deletes: bool,
+
     /// Output format (json, yaml, toml)
-    #[arg(short, long)]
+    #[arg(short, long, value_parser = ["json", "yaml", "yml", "toml"], ignore_case = true)]
     pub format: Option<String>,
 
     /// Suppress output messages
@@ -326,6 +957,14 @@ pub struct PatchArgs {
     #[arg(short, long, required = true)]
     pub patch: PathBuf,
 
+    /// Skip operations on this path (JSON Pointer or dot path, `*` matches any segment; repeatable)
+    #[arg(long, value_name = "PATH", action = clap::ArgAction::Append)]
+    pub ignore: Vec<String>,
+
+    /// Allow JSONPath `path`s ('$.servers[*].tls') that apply to every match (non-RFC 6902)
+    #[arg(long)]
+    pub extended: bool,
+
     /// Output file (outputs to stdout if not specified)
     #[arg(short, long)]
     pub output: Option<PathBuf>,
@@ -339,6 +978,45 @@ pub struct PatchArgs {
     pub raw: bool,
 }
 
+/// Arguments for the frontmatter subcommand
+#[derive(Parser, Debug)]
+pub struct FrontmatterArgs {
+    /// Markdown file (reads from stdin if not provided)
+    pub input: Option<PathBuf>,
+
+    /// Set a front matter field (dot paths allowed, value parsed as JSON if possible)
+    #[arg(long, value_name = "KEY=VALUE", action = clap::ArgAction::Append)]
+    pub set: Vec<String>,
+
+    /// Remove a front matter field (dot paths allowed)
+    #[arg(long, value_name = "KEY", action = clap::ArgAction::Append)]
+    pub unset: Vec<String>,
+
+    /// Replace the whole front matter with the contents of a data file
+    #[arg(long, value_name = "FILE")]
+    pub replace: Option<PathBuf>,
+
+    /// Print the front matter in another format instead of its own
+    #[arg(short, long, value_parser = FORMAT_NAMES, ignore_case = true)]
+    pub to: Option<String>,
+
+    /// Print only the Markdown body
+    #[arg(long, conflicts_with = "to")]
+    pub body: bool,
+
+    /// Write the edited document back to the input file
+    #[arg(short, long, requires = "input", conflicts_with = "output")]
+    pub in_place: bool,
+
+    /// Output file (outputs to stdout if not specified)
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Output without syntax highlighting
+    #[arg(long)]
+    pub raw: bool,
+}
+
 /// Arguments for the template subcommand
 #[derive(Parser, Debug)]
 pub struct TemplateArgs {
@@ -346,7 +1024,7 @@ pub struct TemplateArgs {
     pub template: Option<PathBuf>,
 
     /// Variables file (JSON or YAML)
-    #[arg(short, long)]
+    #[arg(long)]
     pub vars: Option<PathBuf>,
 
     /// Set individual variables (key=value)
@@ -357,12 +1035,38 @@ pub struct TemplateArgs {
     #[arg(short, long)]
     pub env: bool,
 
-    /// Output file (outputs to stdout if not specified)
+    /// Only include environment variables with this prefix (implies --env)
+    #[arg(long)]
+    pub env_prefix: Option<String>,
+
+    /// Strip the --env-prefix from variable names
+    #[arg(long, requires = "env_prefix")]
+    pub env_strip_prefix: bool,
+
+    /// Lowercase environment variable names
+    #[arg(long)]
+    pub env_lowercase: bool,
+
+    /// Output file (outputs to stdout if not specified); a path template with --each
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
+    /// Render once per element of this array variable, writing one file per element
+    #[arg(long, value_name = "VAR", requires = "output")]
+    pub each: Option<String>,
+
+    /// Render once per row of the --vars file (CSV, NDJSON or an array of records),
+    /// with the row's fields as variables; --output may be a path template
+    #[arg(long, requires = "vars", conflicts_with = "each")]
+    pub each_row: bool,
+
+    /// Line written between rows when --each-row output is concatenated
+    /// [default: --- for YAML, a blank line for JSON]
+    #[arg(long, value_name = "TEXT", requires = "each_row")]
+    pub separator: Option<String>,
+
     /// Output format (json, yaml)
-    #[arg(short, long)]
+    #[arg(short, long, value_parser = ["json", "yaml", "yml"], ignore_case = true)]
     pub format: Option<String>,
 
     /// Fail on missing variables
@@ -386,7 +1090,12 @@ pub struct TemplateArgs {
 #[derive(Parser, Debug)]
 pub struct BatchArgs {
     /// Batch config file (YAML, JSON, or TOML)
-    pub config: PathBuf,
+    #[arg(required_unless_present = "schema")]
+    pub config: Option<PathBuf>,
+
+    /// Print the JSON Schema of the batch config format and exit
+    #[arg(long)]
+    pub schema: bool,
 
     /// Set variables for batch jobs (key=value)
     #[arg(long, action = clap::ArgAction::Append)]
@@ -396,15 +1105,140 @@ pub struct BatchArgs {
     #[arg(long)]
     pub continue_on_error: bool,
 
+    /// Skip jobs whose inputs, action and variables are unchanged since their last
+    /// successful run, tracked in this file (e.g. .dtx-cache.json)
+    #[arg(long, value_name = "FILE")]
+    pub cache: Option<PathBuf>,
+
     /// Suppress output messages
     #[arg(short, long)]
     pub quiet: bool,
 }
 
+/// Arguments for the serve subcommand
+#[derive(Parser, Debug)]
+pub struct ServeArgs {
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 8080)]
+    pub port: u16,
+
+    /// Address to bind to
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: String,
+
+    /// Directory of JSON Schemas selectable with `/validate?schema=NAME`
+    #[arg(long, value_name = "DIR")]
+    pub schema_dir: Option<PathBuf>,
+}
+
+/// Arguments for the bench subcommand
+#[derive(Parser, Debug)]
+pub struct BenchArgs {
+    /// Input file to measure with
+    pub input: PathBuf,
+
+    /// Target formats, comma-separated (all formats if omitted)
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        value_parser = convert_format_names(),
+        ignore_case = true
+    )]
+    pub to: Vec<String>,
+
+    /// Source format (auto-detected if not specified)
+    #[arg(short, long, value_parser = convert_format_names(), ignore_case = true)]
+    pub from: Option<String>,
+
+    /// Runs averaged for each measurement
+    #[arg(short = 'n', long, value_name = "N", default_value_t = 10)]
+    pub iterations: usize,
+}
+
+/// Shells that completion scripts can be generated for
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    #[value(name = "powershell")]
+    PowerShell,
+    Elvish,
+    Nushell,
+}
+
 /// Arguments for the completions subcommand
 #[derive(Parser, Debug)]
 pub struct CompletionsArgs {
     /// Shell to generate completions for
     #[arg(value_enum)]
-    pub shell: Shell,
+    pub shell: CompletionShell,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        let args = [&["dtx"], args].concat().into_iter().map(OsString::from);
+        Cli::try_parse_from(expand_template_vars(args.collect())).unwrap()
+    }
+
+    #[test]
+    fn test_verbosity_flags() {
+        assert_eq!(parse(&["convert", "a.json", "--to", "yaml"]).verbose, 0);
+        assert_eq!(
+            parse(&["-v", "convert", "a.json", "--to", "yaml"]).verbose,
+            1
+        );
+        assert_eq!(
+            parse(&["convert", "a.json", "--to", "yaml", "-vv"]).verbose,
+            2
+        );
+
+        // -v before an option is the global flag, not short for --vars
+        let cli = parse(&["template", "t.json", "-v", "--vars", "vars.yaml"]);
+        assert_eq!(cli.verbose, 1);
+        let Commands::Template(args) = cli.command else {
+            panic!("expected the template command");
+        };
+        assert_eq!(args.vars, Some(PathBuf::from("vars.yaml")));
+    }
+
+    #[test]
+    fn test_template_short_vars() {
+        let template = |args: &[&str]| match parse(args) {
+            Cli {
+                verbose,
+                command: Commands::Template(args),
+                ..
+            } => (verbose, args.template, args.vars),
+            _ => panic!("expected the template command"),
+        };
+        let vars = Some(PathBuf::from("vars.json"));
+        let file = Some(PathBuf::from("t.json"));
+        assert_eq!(
+            template(&["template", "t.json", "-v", "vars.json"]),
+            (0, file.clone(), vars.clone())
+        );
+        assert_eq!(
+            template(&["-v", "template", "-v", "vars.json", "t.json"]),
+            (1, file.clone(), vars)
+        );
+        assert_eq!(
+            template(&["template", "t.json", "-vv"]),
+            (2, file.clone(), None)
+        );
+        assert_eq!(template(&["template", "t.json", "-v"]), (1, file, None));
+    }
+
+    #[test]
+    fn test_quiet_flag() {
+        let Commands::Auto(args) = parse(&["auto", "a.json", "-q"]).command else {
+            panic!("expected the auto command");
+        };
+        assert!(args.quiet);
+        assert_eq!(parse(&["-v", "auto", "a.json", "-q"]).verbose, 1);
+    }
 }
@@ -1,79 +1,137 @@
 //! Auto-detect format subcommand implementation
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use colored::Colorize;
 
 use crate::cli::args::AutoArgs;
+use crate::cli::globals::GlobalOptions;
 use crate::cli::output::write_output;
-use crate::formats::detect::{detect, Format};
+use crate::core::{converter, inspect};
+use crate::formats::detect::{detect, detect_from_extension, Format};
 use crate::formats::{
-    csv as csv_format, json as json_format, toml as toml_format, xml as xml_format,
-    yaml as yaml_format,
+    csv as csv_format, json as json_format, ndjson as ndjson_format, toml as toml_format,
+    xml as xml_format, yaml as yaml_format,
 };
-use crate::utils::highlight;
+use crate::utils::{encoding, highlight};
 
 /// Execute the auto subcommand
-pub fn execute(args: AutoArgs) -> Result<()> {
-    // Read content first
-    let content = match &args.input {
-        Some(path) => std::fs::read_to_string(path)?,
-        None => {
-            let mut buf = String::new();
-            std::io::Read::read_to_string(&mut std::io::stdin(), &mut buf)?;
-            buf
+pub fn execute(args: AutoArgs, globals: &GlobalOptions) -> Result<()> {
+    // Binary formats are recognized by extension only and shown as JSON
+    if let Some(format) = args
+        .input
+        .as_deref()
+        .and_then(detect_from_extension)
+        .filter(Format::is_binary)
+    {
+        if !args.quiet {
+            let name = format.as_str().to_uppercase();
+            eprintln!("{} {}", "Detected format:".dimmed(), name.cyan());
         }
-    };
+        let bytes = encoding::read_bytes(args.input.as_deref())?;
+        let value = converter::parse_bytes_to_json_value(&bytes, format, globals.yaml_version)?;
+        let output = json_format::to_pretty(&value, &globals.style)?;
+        write_output(&highlight::highlight_json(&output), &globals.encoding)?;
+        return Ok(());
+    }
+
+    // Read content first
+    let content = globals.encoding.read_input(args.input.as_deref())?;
 
     // Detect format
     let format = detect(args.input.as_deref(), &content);
 
+    if args.info {
+        let format = format.context("Could not detect format")?;
+        let report = inspect::structure_report(&content, format, globals.yaml_version)?;
+        let output = serde_json::to_string_pretty(&report)?;
+        write_output(&highlight::highlight_json(&output), &globals.encoding)?;
+        return Ok(());
+    }
+
     match format {
         Some(Format::Json) => {
             if !args.quiet {
                 eprintln!("{} {}", "Detected format:".dimmed(), "JSON".cyan());
             }
             let value = json_format::parse(&content)?;
-            let output = json_format::to_pretty(&value)?;
+            let output = json_format::to_pretty(&value, &globals.style)?;
             let highlighted = highlight::highlight_json(&output);
-            write_output(&highlighted)?;
+            write_output(&highlighted, &globals.encoding)?;
+        }
+        Some(Format::Ndjson) => {
+            if !args.quiet {
+                eprintln!("{} {}", "Detected format:".dimmed(), "NDJSON".cyan());
+            }
+            let values = ndjson_format::parse(&content)?;
+            let output = ndjson_format::to_string(&values, &globals.style)?;
+            let highlighted = highlight::highlight_json(output.trim_end());
+            write_output(&highlighted, &globals.encoding)?;
         }
         Some(Format::Yaml) => {
             if !args.quiet {
                 eprintln!("{} {}", "Detected format:".dimmed(), "YAML".cyan());
             }
-            let value = yaml_format::parse(&content)?;
-            let output = yaml_format::to_pretty(&value)?;
-            let highlighted = highlight::highlight_yaml(&output);
-            write_output(&highlighted)?;
+            let value = yaml_format::parse(&content, globals.yaml_version)?;
+            let output = yaml_format::to_pretty(&value, &globals.style)?;
+            let highlighted = highlight::highlight_yaml(&output, globals.yaml_version);
+            write_output(&highlighted, &globals.encoding)?;
         }
         Some(Format::Toml) => {
             if !args.quiet {
                 eprintln!("{} {}", "Detected format:".dimmed(), "TOML".cyan());
             }
             let value = toml_format::parse(&content)?;
-            let output = toml_format::to_pretty(&value)?;
+            let output = toml_format::to_pretty(&value, &globals.style)?;
             let highlighted = highlight::highlight_toml(&output);
-            write_output(&highlighted)?;
+            write_output(&highlighted, &globals.encoding)?;
         }
-        Some(Format::Csv) => {
+        Some(format @ (Format::Csv | Format::Tsv)) => {
             if !args.quiet {
-                eprintln!("{} {}", "Detected format:".dimmed(), "CSV".cyan());
+                let name = format.as_str().to_uppercase();
+                eprintln!("{} {}", "Detected format:".dimmed(), name.cyan());
             }
             let data = csv_format::parse(&content, true)?;
             let output = csv_format::to_table(&data)?;
             let highlighted = highlight::highlight_csv(&output, false);
-            write_output(&highlighted)?;
+            write_output(&highlighted, &globals.encoding)?;
         }
         Some(Format::Xml) => {
             if !args.quiet {
                 eprintln!("{} {}", "Detected format:".dimmed(), "XML".cyan());
             }
             xml_format::validate(&content)?;
-            let output = xml_format::to_pretty(&content)?;
+            let output = xml_format::to_pretty(&content, &globals.style)?;
             let highlighted = highlight::highlight_xml(&output);
-            write_output(&highlighted)?;
+            write_output(&highlighted, &globals.encoding)?;
+        }
+        Some(Format::Plist) => {
+            if !args.quiet {
+                eprintln!("{} {}", "Detected format:".dimmed(), "PLIST".cyan());
+            }
+            let output = converter::convert(&content, Format::Plist, Format::Plist)?;
+            write_output(
+                &highlight::highlight_xml(output.trim_end()),
+                &globals.encoding,
+            )?;
+        }
+        Some(Format::Properties) => {
+            if !args.quiet {
+                eprintln!("{} {}", "Detected format:".dimmed(), "PROPERTIES".cyan());
+            }
+            let output = converter::convert(&content, Format::Properties, Format::Properties)?;
+            write_output(output.trim_end(), &globals.encoding)?;
+        }
+        // HTML tables, HCL and registered text formats are shown as JSON
+        Some(format) if !format.is_binary() && format != Format::Sql => {
+            if !args.quiet {
+                let name = format.as_str().to_uppercase();
+                eprintln!("{} {}", "Detected format:".dimmed(), name.cyan());
+            }
+            let value = converter::parse_to_json_value(&content, format, globals.yaml_version)?;
+            let output = json_format::to_pretty(&value, &globals.style)?;
+            write_output(&highlight::highlight_json(&output), &globals.encoding)?;
         }
-        None => {
+        Some(_) | None => {
             bail!("Could not detect format. Please specify the format explicitly using a subcommand (json, yaml, toml, csv, xml).");
         }
     }
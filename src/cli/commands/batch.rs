@@ -1,25 +1,26 @@
 //! Batch subcommand implementation
 
 use anyhow::{Context, Result};
-use std::fs;
+use serde_json::json;
 
 use crate::cli::args::BatchArgs;
+use crate::cli::globals::GlobalOptions;
 use crate::cli::output::write_output;
-use crate::core::batch::{self, BatchConfig};
-use crate::formats::detect::detect;
+use crate::cli::porcelain;
+use crate::core::batch::{self, BatchCache, BatchConfig};
 
 /// Execute the batch subcommand
-pub fn execute(args: BatchArgs) -> Result<()> {
-    // Read config file
-    let config_content = fs::read_to_string(&args.config)
-        .with_context(|| format!("Failed to read config file: {}", args.config.display()))?;
+pub fn execute(args: BatchArgs, globals: &GlobalOptions) -> Result<()> {
+    if args.schema {
+        let schema = serde_json::to_string_pretty(&batch::config_schema())?;
+        write_output(&schema, &globals.encoding)?;
+        return Ok(());
+    }
 
-    // Detect config format
-    let config_format = detect(Some(args.config.as_path()), &config_content)
-        .context("Could not detect config file format")?;
+    let config_path = args.config.context("No batch config file specified")?;
 
-    // Parse config
-    let mut config: BatchConfig = batch::parse_config(&config_content, config_format)?;
+    // Read and parse config, following includes
+    let mut config: BatchConfig = batch::load_config(&config_path)?;
 
     // Override continue_on_error if specified
     if args.continue_on_error {
@@ -45,22 +46,63 @@ pub fn execute(args: BatchArgs) -> Result<()> {
     }
 
     // Get base directory for relative paths
-    let base_dir = args
-        .config
+    let base_dir = config_path
         .parent()
         .map(|p| p.to_path_buf())
         .unwrap_or_else(|| std::env::current_dir().unwrap_or_default());
 
-    if !args.quiet {
-        eprintln!("Running batch with {} jobs...", config.jobs.len());
+    if !args.quiet && globals.porcelain.is_none() {
+        eprintln!("Running batch with {} jobs...", config.job_count());
     }
 
-    // Execute batch
-    let results = batch::execute_batch(&config, &base_dir);
+    // Execute batch, skipping unchanged jobs with --cache
+    let mut cache = args.cache.as_deref().map(BatchCache::load).transpose()?;
+    let options = globals.convert_options();
+    let results = batch::execute_batch_with_cache(
+        &config,
+        &base_dir,
+        cache.as_mut(),
+        &options,
+        &globals.encoding,
+    );
+    if let Some(ref cache) = cache {
+        cache.save()?;
+    }
 
     // Format and output results
-    let output = batch::format_results(&results);
-    write_output(&output)?;
+    if globals.porcelain.is_some() {
+        for result in &results {
+            let status = match (result.cached, result.success) {
+                (true, _) => "cached",
+                (false, true) => "pass",
+                (false, false) => "fail",
+            };
+            porcelain::record(
+                globals,
+                "job",
+                &[
+                    ("name", json!(result.job_name)),
+                    ("status", json!(status)),
+                    ("duration_ms", json!(result.duration_ms as u64)),
+                    ("message", json!(result.message)),
+                ],
+            )?;
+        }
+        let passed = results.iter().filter(|r| r.success).count();
+        porcelain::record(
+            globals,
+            "summary",
+            &[
+                ("total", json!(results.len())),
+                ("passed", json!(passed)),
+                ("failed", json!(results.len() - passed)),
+                ("cached", json!(results.iter().filter(|r| r.cached).count())),
+            ],
+        )?;
+    } else {
+        let output = batch::format_results(&results);
+        write_output(&output, &globals.encoding)?;
+    }
 
     // Exit with error if any job failed
     let has_failures = results.iter().any(|r| !r.success);
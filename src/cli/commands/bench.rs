@@ -0,0 +1,122 @@
+//! Bench subcommand implementation
+
+use anyhow::{Context, Result};
+
+use crate::cli::args::BenchArgs;
+use crate::cli::globals::GlobalOptions;
+use crate::cli::output::write_output;
+use crate::core::{bench, converter};
+use crate::formats::csv::{self as csv_format, CsvData};
+use crate::formats::detect::{detect, detect_from_extension, Format};
+use crate::utils::{encoding, highlight};
+
+/// Targets measured when `--to` is not given
+const ALL_FORMATS: [Format; 9] = [
+    Format::Json,
+    Format::Yaml,
+    Format::Toml,
+    Format::Csv,
+    Format::Tsv,
+    Format::Xml,
+    Format::Ndjson,
+    Format::MsgPack,
+    Format::Bson,
+];
+
+/// Execute the bench subcommand
+pub fn execute(args: BenchArgs, globals: &GlobalOptions) -> Result<()> {
+    let named = match args.from.as_deref() {
+        Some(name) => Some(parse_format(name)?),
+        None => detect_from_extension(&args.input),
+    };
+    let (content, format) = match named {
+        Some(format) if format.is_binary() => (encoding::read_bytes(Some(&args.input))?, format),
+        _ => {
+            let text = globals.encoding.read_file(&args.input)?;
+            let format = match named {
+                Some(format) => format,
+                None => detect(Some(args.input.as_path()), &text)
+                    .context("Could not detect input format. Use --from to specify.")?,
+            };
+            (text.into_bytes(), format)
+        }
+    };
+    let targets = if args.to.is_empty() {
+        ALL_FORMATS.to_vec()
+    } else {
+        args.to
+            .iter()
+            .map(|name| parse_format(name))
+            .collect::<Result<_>>()?
+    };
+
+    let iterations = args.iterations.max(1);
+    let value = converter::parse_bytes_to_json_value(&content, format, globals.yaml_version)?;
+    let input_parse = bench::average(iterations, || {
+        converter::parse_bytes_to_json_value(&content, format, globals.yaml_version)
+    })?;
+    eprintln!(
+        "{} ({}, {} bytes): parsed in {:.2?}, {} iterations",
+        args.input.display(),
+        format,
+        content.len(),
+        input_parse,
+        iterations
+    );
+
+    let results = bench::run(&value, &targets, iterations);
+    let mut headers = vec!["format", "size", "vs input", "serialize", "parse"];
+    let has_errors = results.iter().any(|(_, result)| result.is_err());
+    if has_errors {
+        headers.push("note");
+    }
+    let rows = results
+        .into_iter()
+        .map(|(format, result)| {
+            let mut row = match result {
+                Ok(ref result) => vec![
+                    format.to_string(),
+                    result.size.to_string(),
+                    format!(
+                        "{:.0}%",
+                        result.size as f64 * 100.0 / content.len().max(1) as f64
+                    ),
+                    format!("{:.2?}", result.serialize),
+                    format!("{:.2?}", result.parse),
+                ],
+                Err(_) => vec![
+                    format.to_string(),
+                    "-".into(),
+                    "-".into(),
+                    "-".into(),
+                    "-".into(),
+                ],
+            };
+            if has_errors {
+                row.push(
+                    result
+                        .err()
+                        .map_or(String::new(), |e| format!("unsupported: {}", e)),
+                );
+            }
+            row
+        })
+        .collect();
+
+    let headers = headers.into_iter().map(String::from).collect();
+    let data = CsvData::with_headers(headers, rows);
+    let output = csv_format::to_table(&data)?;
+    write_output(&highlight::highlight_csv(&output, false), &globals.encoding)?;
+
+    Ok(())
+}
+
+fn parse_format(s: &str) -> Result<Format> {
+    Format::from_name(s).with_context(|| {
+        format!(
+            "Unknown format: {}. Supported: {}",
+            s,
+            Format::supported_names().join(", ")
+        )
+    })
+}
@@ -3,16 +3,14 @@
 use anyhow::Result;
 use clap::CommandFactory;
 use clap_complete::{generate, Shell};
-use std::io;
+use clap_complete_nushell::Nushell;
+use std::io::{self, Write};
 
-use crate::cli::args::{Cli, CompletionsArgs};
+use crate::cli::args::{Cli, CompletionShell, CompletionsArgs};
 
 /// Execute the completions subcommand
 pub fn execute(args: CompletionsArgs) -> Result<()> {
-    let mut cmd = Cli::command();
-    let cmd_name = cmd.get_name().to_string();
-
-    generate(args.shell, &mut cmd, cmd_name, &mut io::stdout());
+    write_completions(args.shell, &mut io::stdout());
 
     // Print installation instructions to stderr
     print_installation_instructions(args.shell);
@@ -20,20 +18,34 @@ pub fn execute(args: CompletionsArgs) -> Result<()> {
     Ok(())
 }
 
-fn print_installation_instructions(shell: Shell) {
+fn write_completions(shell: CompletionShell, out: &mut dyn Write) {
+    let mut cmd = Cli::command();
+    let cmd_name = cmd.get_name().to_string();
+
+    match shell {
+        CompletionShell::Bash => generate(Shell::Bash, &mut cmd, cmd_name, out),
+        CompletionShell::Zsh => generate(Shell::Zsh, &mut cmd, cmd_name, out),
+        CompletionShell::Fish => generate(Shell::Fish, &mut cmd, cmd_name, out),
+        CompletionShell::PowerShell => generate(Shell::PowerShell, &mut cmd, cmd_name, out),
+        CompletionShell::Elvish => generate(Shell::Elvish, &mut cmd, cmd_name, out),
+        CompletionShell::Nushell => generate(Nushell, &mut cmd, cmd_name, out),
+    }
+}
+
+fn print_installation_instructions(shell: CompletionShell) {
     eprintln!();
     eprintln!("# Installation instructions for {:?}:", shell);
     eprintln!();
 
     match shell {
-        Shell::Bash => {
+        CompletionShell::Bash => {
             eprintln!("# Add to ~/.bashrc:");
             eprintln!("# eval \"$(dtx completions bash)\"");
             eprintln!();
             eprintln!("# Or save to a file:");
             eprintln!("# dtx completions bash > ~/.local/share/bash-completion/completions/dtx");
         }
-        Shell::Zsh => {
+        CompletionShell::Zsh => {
             eprintln!("# Add to ~/.zshrc (before compinit):");
             eprintln!("# eval \"$(dtx completions zsh)\"");
             eprintln!();
@@ -41,25 +53,48 @@ fn print_installation_instructions(shell: Shell) {
             eprintln!("# dtx completions zsh > ~/.zsh/completions/_dtx");
             eprintln!("# Then add to ~/.zshrc: fpath=(~/.zsh/completions $fpath)");
         }
-        Shell::Fish => {
+        CompletionShell::Fish => {
             eprintln!("# Save to fish completions directory:");
             eprintln!("# dtx completions fish > ~/.config/fish/completions/dtx.fish");
         }
-        Shell::PowerShell => {
+        CompletionShell::PowerShell => {
             eprintln!("# Add to your PowerShell profile:");
             eprintln!("# dtx completions powershell | Out-String | Invoke-Expression");
             eprintln!();
             eprintln!("# Or save to a file and source it in your profile:");
             eprintln!("# dtx completions powershell > dtx.ps1");
         }
-        Shell::Elvish => {
+        CompletionShell::Elvish => {
             eprintln!("# Save to elvish completions directory:");
             eprintln!("# dtx completions elvish > ~/.elvish/lib/dtx.elv");
         }
-        _ => {
-            eprintln!("# Please refer to your shell's documentation for completion setup.");
+        CompletionShell::Nushell => {
+            eprintln!("# Save the completions and source them from config.nu:");
+            eprintln!("# dtx completions nushell | save -f ~/.config/nushell/completions/dtx.nu");
+            eprintln!("# Then add to config.nu: source ~/.config/nushell/completions/dtx.nu");
         }
     }
     eprintln!();
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_completions_include_format_values() {
+        let mut buf = Vec::new();
+        write_completions(CompletionShell::Bash, &mut buf);
+        let script = String::from_utf8(buf).unwrap();
+        assert!(script.contains("json yaml yml toml csv tsv xml"));
+        assert!(script.contains("deep shallow concat union"));
+    }
+
+    #[test]
+    fn test_nushell_completions() {
+        let mut buf = Vec::new();
+        write_completions(CompletionShell::Nushell, &mut buf);
+        let script = String::from_utf8(buf).unwrap();
+        assert!(script.contains("export extern \"dtx convert\""));
+    }
+}
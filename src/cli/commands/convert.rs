@@ -2,39 +2,108 @@
 
 use anyhow::{bail, Context, Result};
 use colored::Colorize;
-use std::fs;
-use std::path::Path;
+use serde_json::json;
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
 
 use crate::cli::args::ConvertArgs;
-use crate::cli::output::write_output;
-use crate::core::converter;
-use crate::formats::detect::{detect, Format};
-use crate::utils::highlight;
+use crate::cli::globals::GlobalOptions;
+use crate::cli::output::{check_content, write_binary_output, write_output};
+use crate::cli::porcelain;
+use crate::cli::progress::Progress;
+use crate::core::converter::{self, ConvertOptions};
+use crate::core::validator;
+use crate::formats::avro as avro_format;
+use crate::formats::csv::CsvReadOptions;
+use crate::formats::detect::{detect, detect_from_extension, Format};
+use crate::formats::frontmatter;
+use crate::formats::proto as proto_format;
+use crate::formats::sql::{SqlDialect, SqlOptions};
+use crate::formats::toml::TomlLayout;
+use crate::formats::xml::CdataOptions;
+use crate::formats::yaml::YamlVersion;
+use crate::utils::compression::{self, Compression};
+use crate::utils::diagnostics::SourceContext;
+use crate::utils::encoding::{self, EncodingOptions};
+use crate::utils::{highlight, paths, timing};
 
 /// Execute the convert subcommand
-pub fn execute(args: ConvertArgs) -> Result<()> {
-    // Read input
-    let content = read_input(args.input.as_deref())?;
+pub fn execute(mut args: ConvertArgs, globals: &GlobalOptions) -> Result<()> {
+    args.quiet |= globals.porcelain.is_some();
+    let to_formats = target_formats(&args)?;
 
-    // Detect source format
-    let from_format = if let Some(ref from) = args.from {
-        parse_format(from)?
-    } else {
-        detect(args.input.as_deref(), &content)
-            .context("Could not detect source format. Use --from to specify.")?
-    };
+    let is_pattern = args
+        .input
+        .as_deref()
+        .is_some_and(|p| paths::is_glob(&p.to_string_lossy()));
+    if is_pattern || args.out_dir.is_some() {
+        return execute_many(&args, &to_formats, globals);
+    }
 
-    // Parse target formats
-    let to_formats = parse_target_formats(&args.to)?;
+    if args.check && args.output.is_none() {
+        bail!("--check requires --output or --out-dir");
+    }
 
-    if to_formats.is_empty() {
-        bail!("No target format specified. Use --to to specify output format(s).");
+    // CSV <-> NDJSON is converted record by record without loading the whole input
+    if let [to_format] = to_formats[..] {
+        if let Some(from_format) =
+            streaming_source(&args, args.input.as_deref(), to_format, globals)?
+        {
+            let progress = (!args.quiet)
+                .then(|| Progress::start("Converting", args.input.as_deref(), globals))
+                .flatten();
+            let count = convert_streaming(
+                args.input.as_deref(),
+                args.output.as_deref(),
+                from_format,
+                to_format,
+                &convert_options(&args, globals)?,
+                &globals.encoding,
+                progress.as_ref(),
+            )?;
+            drop(progress);
+            if let Some(ref output_path) = args.output {
+                report_file(
+                    args.input.as_deref(),
+                    output_path,
+                    to_format,
+                    Some(count),
+                    true,
+                    &args,
+                    globals,
+                )?;
+            }
+            if let (Some(output_path), false) = (&args.output, args.quiet) {
+                eprintln!(
+                    "{} {} -> {} ({} records)",
+                    "Converted:".green(),
+                    from_format.as_str().cyan(),
+                    output_path.display().to_string().cyan(),
+                    count
+                );
+            }
+            return Ok(());
+        }
     }
 
-    // Perform conversion(s)
-    for to_format in &to_formats {
-        let result = converter::convert(&content, from_format, *to_format)?;
+    // Read input
+    let progress = (!args.quiet)
+        .then(|| Progress::start("Converting", args.input.as_deref(), globals))
+        .flatten();
+    let (content, from_format) = read_source(&args, args.input.as_deref(), globals)?;
+
+    let options = convert_options(&args, globals)?;
+    let results = to_formats
+        .iter()
+        .map(|to_format| converter::convert_bytes(&content, from_format, *to_format, &options))
+        .collect::<Result<Vec<_>>>()?;
+    drop(progress);
+    let mut stale = 0;
 
+    // Write the conversion(s)
+    for (to_format, result) in to_formats.iter().zip(results) {
         if let Some(ref output_path) = args.output {
             // Write to file
             let output_file = if to_formats.len() > 1 {
@@ -49,10 +118,22 @@ pub fn execute(args: ConvertArgs) -> Result<()> {
                 output_path.clone()
             };
 
-            fs::write(&output_file, &result)
-                .with_context(|| format!("Failed to write to {}", output_file.display()))?;
-
-            if !args.quiet {
+            let up_to_date = timing::time(globals.timings.as_deref(), "write", || {
+                write_or_check(&output_file, &result, *to_format, args.check, globals)
+            })?;
+            let input = args.input.as_deref();
+            report_file(
+                input,
+                &output_file,
+                *to_format,
+                None,
+                up_to_date,
+                &args,
+                globals,
+            )?;
+            if !up_to_date {
+                stale += 1;
+            } else if !args.quiet && !args.check {
                 eprintln!(
                     "{} {} -> {}",
                     "Converted:".green(),
@@ -70,8 +151,20 @@ pub fn execute(args: ConvertArgs) -> Result<()> {
                 );
             }
 
-            let highlighted = highlight_output(&result, *to_format);
-            write_output(&highlighted)?;
+            if to_format.is_binary() {
+                timing::time(globals.timings.as_deref(), "write", || {
+                    write_binary_output(&result)
+                })?;
+            } else {
+                let highlighted = highlight_output(
+                    std::str::from_utf8(&result)?,
+                    *to_format,
+                    globals.yaml_version,
+                );
+                timing::time(globals.timings.as_deref(), "write", || {
+                    write_output(&highlighted, &globals.encoding)
+                })?;
+            }
 
             if to_formats.len() > 1 {
                 println!(); // Separator between outputs
@@ -79,43 +172,496 @@ pub fn execute(args: ConvertArgs) -> Result<()> {
         }
     }
 
+    if stale > 0 {
+        bail!("{} file(s) out of date", stale);
+    }
+
+    Ok(())
+}
+
+/// Convert every file matched by the input pattern into --out-dir
+fn execute_many(args: &ConvertArgs, to_formats: &[Format], globals: &GlobalOptions) -> Result<()> {
+    let out_dir = args
+        .out_dir
+        .as_deref()
+        .context("Converting multiple files requires --out-dir")?;
+    let input = args
+        .input
+        .as_deref()
+        .context("--out-dir requires an input file or glob pattern")?;
+
+    let pattern = input.to_string_lossy();
+    let files = paths::expand_inputs(&[input.to_path_buf()])?;
+    let base = paths::glob_base(&pattern);
+
+    let mut written = HashSet::new();
+    let mut failed = 0;
+    let mut stale = 0;
+
+    for file in &files {
+        match convert_to_dir(
+            args,
+            file,
+            &base,
+            out_dir,
+            to_formats,
+            &mut written,
+            globals,
+        ) {
+            Ok(outputs) => {
+                for (output, to_format, up_to_date) in outputs {
+                    report_file(
+                        Some(file),
+                        &output,
+                        to_format,
+                        None,
+                        up_to_date,
+                        args,
+                        globals,
+                    )?;
+                    if !up_to_date {
+                        stale += 1;
+                    } else if !args.quiet && !args.check {
+                        eprintln!(
+                            "{} {} -> {}",
+                            "Converted:".green(),
+                            file.display(),
+                            output.display().to_string().cyan()
+                        );
+                    }
+                }
+            }
+            Err(err) => {
+                failed += 1;
+                porcelain::record(
+                    globals,
+                    "failed",
+                    &[
+                        ("input", json!(file.display().to_string())),
+                        ("message", json!(format!("{:#}", err))),
+                    ],
+                )?;
+                if globals.porcelain.is_none() {
+                    eprintln!("{} {}: {:#}", "Failed:".red(), file.display(), err);
+                }
+            }
+        }
+    }
+
+    porcelain::record(
+        globals,
+        "summary",
+        &[
+            ("converted", json!(files.len() - failed)),
+            ("failed", json!(failed)),
+            ("stale", json!(stale)),
+            ("total", json!(files.len())),
+        ],
+    )?;
+
+    if !args.quiet {
+        if args.check {
+            eprintln!(
+                "{} {} out of date, {} failed ({} file(s) total)",
+                "Summary:".bold(),
+                stale,
+                failed,
+                files.len()
+            );
+        } else {
+            eprintln!(
+                "{} {} converted, {} failed ({} file(s) total)",
+                "Summary:".bold(),
+                files.len() - failed,
+                failed,
+                files.len()
+            );
+        }
+    }
+
+    if failed > 0 {
+        bail!("{} of {} file(s) failed to convert", failed, files.len());
+    }
+    if stale > 0 {
+        bail!("{} file(s) out of date", stale);
+    }
+
     Ok(())
 }
 
-fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
+fn convert_to_dir(
+    args: &ConvertArgs,
+    file: &Path,
+    base: &Path,
+    out_dir: &Path,
+    to_formats: &[Format],
+    written: &mut HashSet<PathBuf>,
+    globals: &GlobalOptions,
+) -> Result<Vec<(PathBuf, Format, bool)>> {
+    let relative = if args.preserve_structure {
+        file.strip_prefix(base).unwrap_or(file)
+    } else {
+        Path::new(file.file_name().context("Input has no file name")?)
+    };
+
+    let mut output_paths = Vec::new();
+    for to_format in to_formats {
+        let output_path = out_dir.join(relative).with_extension(to_format.as_str());
+        if !written.insert(output_path.clone()) {
+            bail!(
+                "Output {} would be overwritten; use --preserve-structure",
+                output_path.display()
+            );
+        }
+        output_paths.push(output_path);
+    }
+
+    if let [to_format] = to_formats {
+        if let Some(from_format) = streaming_source(args, Some(file), *to_format, globals)? {
+            let output_path = output_paths.remove(0);
+            if let Some(parent) = output_path.parent() {
+                fs::create_dir_all(parent)
+                    .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+            }
+            convert_streaming(
+                Some(file),
+                Some(&output_path),
+                from_format,
+                *to_format,
+                &convert_options(args, globals)?,
+                &globals.encoding,
+                None,
+            )?;
+            return Ok(vec![(output_path, *to_format, true)]);
+        }
+    }
+
+    let (content, from_format) = read_source(args, Some(file), globals)?;
+
+    let options = convert_options(args, globals)?;
+    let mut outputs = Vec::new();
+    for (to_format, output_path) in to_formats.iter().zip(output_paths) {
+        let result = converter::convert_bytes(&content, from_format, *to_format, &options)
+            .in_source(Some(file))?;
+        let up_to_date = timing::time(globals.timings.as_deref(), "write", || {
+            write_or_check(&output_path, &result, *to_format, args.check, globals)
+        })?;
+        outputs.push((output_path, *to_format, up_to_date));
+    }
+
+    Ok(outputs)
+}
+
+/// Write a converted file, or with --check compare it against the file on disk
+///
+/// Returns whether the file on disk is up to date (always true when writing).
+fn write_or_check(
+    path: &Path,
+    content: &[u8],
+    format: Format,
+    check: bool,
+    globals: &GlobalOptions,
+) -> Result<bool> {
+    if check && format.is_binary() {
+        let up_to_date = compression::read_file(path).is_ok_and(|existing| existing == content);
+        if !up_to_date && globals.porcelain.is_none() {
+            println!("Binary file {} differs from expected", path.display());
+        }
+        return Ok(up_to_date);
+    }
+    if check {
+        // Compare what a write would produce, line endings and encoding included
+        let expected = globals
+            .encoding
+            .encode_output(std::str::from_utf8(content)?)?;
+        let existing = compression::read_file(path).ok();
+        let up_to_date = existing.as_deref() == Some(&expected[..]);
+        if up_to_date || globals.porcelain.is_some() {
+            return Ok(up_to_date);
+        }
+        let as_text = |bytes: &[u8]| {
+            encoding::decode(bytes, globals.encoding.output)
+                .unwrap_or_else(|_| String::from_utf8_lossy(bytes).into_owned())
+        };
+        // A missing file is reported as a diff against empty content
+        let existing = existing.as_deref().map(as_text).unwrap_or_default();
+        if check_content(&path.display().to_string(), &existing, &as_text(&expected))? {
+            // Same text in different bytes, such as a missing byte order mark
+            println!("{} differs from expected in its encoding", path.display());
+        }
+        return Ok(false);
+    }
+
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+    }
+    if format.is_binary() {
+        compression::write_file(path, content)?;
+    } else {
+        globals
+            .encoding
+            .write_file(path, std::str::from_utf8(content)?)?;
+    }
+    Ok(true)
+}
+
+/// Emit the porcelain record for a file written or checked
+fn report_file(
+    input: Option<&Path>,
+    output: &Path,
+    to_format: Format,
+    records: Option<usize>,
+    up_to_date: bool,
+    args: &ConvertArgs,
+    globals: &GlobalOptions,
+) -> io::Result<()> {
+    if args.check {
+        let status = if up_to_date { "current" } else { "stale" };
+        return porcelain::record(
+            globals,
+            "checked",
+            &[
+                ("output", json!(output.display().to_string())),
+                ("status", json!(status)),
+            ],
+        );
+    }
+    let input = input.map_or("-".to_string(), |path| path.display().to_string());
+    porcelain::record(
+        globals,
+        "converted",
+        &[
+            ("input", json!(input)),
+            ("output", json!(output.display().to_string())),
+            ("format", json!(to_format.as_str())),
+            ("records", json!(records)),
+        ],
+    )
+}
+
+/// Source format when the conversion can be streamed without reading the input first
+fn streaming_source(
+    args: &ConvertArgs,
+    path: Option<&Path>,
+    to: Format,
+    globals: &GlobalOptions,
+) -> Result<Option<Format>> {
+    if args.check
+        || args.front_matter
+        || args.group_by.is_some()
+        || args.skip_rows > 0
+        || args.comment.is_some()
+        || !args.date_format.is_empty()
+        || args.coerce_schema.is_some()
+        || frontmatter::is_markdown(path)
+        || globals.encoding.output_encoding().is_some()
+    {
+        return Ok(None);
+    }
+
+    let from = match args.from {
+        Some(ref from) => parse_format(from)?,
+        None => match path.and_then(detect_from_extension) {
+            Some(format) => format,
+            None => return Ok(None),
+        },
+    };
+
+    Ok(converter::supports_streaming(from, to).then_some(from))
+}
+
+fn convert_streaming(
+    input: Option<&Path>,
+    output: Option<&Path>,
+    from: Format,
+    to: Format,
+    options: &ConvertOptions,
+    encoding: &EncodingOptions,
+    progress: Option<&Progress>,
+) -> Result<usize> {
+    let reader: Box<dyn BufRead> = match input {
         Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
+            Box::new(BufReader::new(File::open(p).with_context(|| {
+                format!("Failed to read file: {}", p.display())
+            })?))
         }
-        None => {
-            use std::io::Read;
-            let mut buffer = String::new();
-            std::io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
+        None => Box::new(io::stdin().lock()),
+    };
+    let reader = match progress {
+        Some(progress) => Box::new(progress.reader(reader)),
+        None => reader,
+    };
+    let reader = encoding.utf8_reader(reader)?;
+    let mut encoder = None;
+    let writer: Box<dyn Write + '_> = match output {
+        Some(p) => {
+            let file =
+                File::create(p).with_context(|| format!("Failed to write to {}", p.display()))?;
+            match Compression::from_path(p) {
+                Some(compression) => Box::new(encoder.insert(compression.encoder(file)?)),
+                None => Box::new(file),
+            }
         }
+        None => Box::new(io::stdout().lock()),
+    };
+    let writer = encoding.eol_writer(writer);
+
+    // Reading, parsing, serializing and writing are interleaved record by record
+    let records = timing::time(options.timings.as_deref(), "stream", || {
+        converter::convert_stream_with_options(reader, writer, from, to, options)
+    })?;
+    if let (Some(encoder), Some(p)) = (encoder, output) {
+        encoder
+            .finish()
+            .with_context(|| format!("Failed to write to {}", p.display()))?;
     }
+    Ok(records)
+}
+
+fn convert_options(args: &ConvertArgs, globals: &GlobalOptions) -> Result<ConvertOptions> {
+    Ok(ConvertOptions {
+        record_path: args.record_path.clone(),
+        group_by: args.group_by.clone(),
+        children: args.children.clone(),
+        csv: CsvReadOptions::new(args.skip_rows, args.comment.as_deref())?
+            .with_date_formats(&args.date_format)?
+            .with_delimiter(args.delimiter.as_deref())?,
+        toml: TomlLayout {
+            inline_depth: args.inline_depth,
+            inline_arrays: args.inline_arrays,
+        },
+        cdata: CdataOptions {
+            markup: args.cdata,
+            paths: args.cdata_path.clone(),
+        },
+        force_array: args.force_array.clone(),
+        null_as: args.null_as.clone(),
+        sql: SqlOptions {
+            table: args.table_name.clone().or_else(|| {
+                let stem = args.input.as_deref()?.file_stem()?.to_str()?;
+                Some(stem.to_string())
+            }),
+            dialect: SqlDialect::from_name(&args.dialect)?,
+        },
+        sheet: args.sheet.clone(),
+        table: args.table.clone(),
+        proto: match (&args.descriptor, &args.message) {
+            (Some(path), Some(message)) => {
+                let descriptor_set = fs::read(path).with_context(|| {
+                    format!("Failed to read descriptor set: {}", path.display())
+                })?;
+                Some(proto_format::load_message(&descriptor_set, message)?)
+            }
+            _ => None,
+        },
+        avro_schema: args
+            .schema
+            .as_deref()
+            .map(|path| -> Result<_> {
+                let text = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read Avro schema: {}", path.display()))?;
+                let json = serde_json::from_str(&text).with_context(|| {
+                    format!("Avro schema is not valid JSON: {}", path.display())
+                })?;
+                avro_format::Schema::parse(&json)
+            })
+            .transpose()?,
+        coerce_schema: args
+            .coerce_schema
+            .as_deref()
+            .map(|source| validator::load_schema(source, false))
+            .transpose()?,
+        ..globals.convert_options()
+    })
 }
 
-fn parse_format(s: &str) -> Result<Format> {
-    match s.to_lowercase().as_str() {
-        "json" => Ok(Format::Json),
-        "yaml" | "yml" => Ok(Format::Yaml),
-        "toml" => Ok(Format::Toml),
-        "csv" => Ok(Format::Csv),
-        "xml" => Ok(Format::Xml),
-        _ => bail!(
-            "Unknown format: {}. Supported: json, yaml, toml, csv, xml",
-            s
+/// Read the input and determine its format; binary formats are read as raw bytes
+fn read_source(
+    args: &ConvertArgs,
+    path: Option<&Path>,
+    globals: &GlobalOptions,
+) -> Result<(Vec<u8>, Format)> {
+    let binary = match args.from {
+        Some(ref from) => Some(parse_format(from)?),
+        None => path.and_then(detect_from_extension),
+    }
+    .filter(Format::is_binary);
+    if let Some(format) = binary {
+        return Ok((
+            timing::time(globals.timings.as_deref(), "read", || {
+                encoding::read_bytes(path)
+            })?,
+            format,
+        ));
+    }
+
+    let content = timing::time(globals.timings.as_deref(), "read", || {
+        globals.encoding.read_input(path)
+    })?;
+    let (content, format) = timing::time(globals.timings.as_deref(), "detect", || {
+        prepare_source(args, path, content)
+    })?;
+    Ok((content.into_bytes(), format))
+}
+
+/// Extract front matter if needed and determine the source format
+fn prepare_source(
+    args: &ConvertArgs,
+    path: Option<&Path>,
+    content: String,
+) -> Result<(String, Format)> {
+    if args.front_matter || frontmatter::is_markdown(path) {
+        return frontmatter::extract(&content);
+    }
+
+    let format = if let Some(ref from) = args.from {
+        parse_format(from)?
+    } else {
+        detect(path, &content).context("Could not detect source format. Use --from to specify.")?
+    };
+    Ok((content, format))
+}
+
+pub(crate) fn parse_format(s: &str) -> Result<Format> {
+    Format::from_name(s).with_context(|| {
+        format!(
+            "Unknown format: {}. Supported: {}",
+            s,
+            Format::supported_names().join(", ")
+        )
+    })
+}
+
+/// Resolve --to, falling back to the --output file extension
+fn target_formats(args: &ConvertArgs) -> Result<Vec<Format>> {
+    let to_formats = parse_target_formats(&args.to)?;
+    let output_format = args.output.as_deref().and_then(detect_from_extension);
+
+    match (&to_formats[..], output_format) {
+        ([], Some(format)) => Ok(vec![format]),
+        ([], None) => match args.output {
+            Some(ref path) => bail!(
+                "Cannot infer target format from {}. Use --to to specify output format(s).",
+                path.display()
+            ),
+            None => bail!("No target format specified. Use --to to specify output format(s)."),
+        },
+        // Binary property lists share the .plist extension
+        ([Format::BinaryPlist], Some(Format::Plist)) => Ok(to_formats),
+        ([to_format], Some(format)) if *to_format != format => bail!(
+            "--to {} does not match the output file extension ({}); drop --to or rename the output",
+            to_format.as_str(),
+            format.as_str()
         ),
+        _ => Ok(to_formats),
     }
 }
 
-fn parse_target_formats(to: &str) -> Result<Vec<Format>> {
+fn parse_target_formats(to: &[String]) -> Result<Vec<Format>> {
     let mut formats = Vec::new();
 
-    for part in to.split(',') {
+    for part in to {
         let trimmed = part.trim();
         if !trimmed.is_empty() {
             formats.push(parse_format(trimmed)?);
@@ -125,13 +671,17 @@ fn parse_target_formats(to: &str) -> Result<Vec<Format>> {
     Ok(formats)
 }
 
-fn highlight_output(content: &str, format: Format) -> String {
+fn highlight_output(content: &str, format: Format, version: YamlVersion) -> String {
     match format {
-        Format::Json => highlight::highlight_json(content),
-        Format::Yaml => highlight::highlight_yaml(content),
+        Format::Json | Format::Ndjson => highlight::highlight_json(content),
+        Format::Yaml => highlight::highlight_yaml(content, version),
         Format::Toml => highlight::highlight_toml(content),
-        Format::Csv => highlight::highlight_csv(content, true),
+        Format::Csv | Format::Tsv => highlight::highlight_csv(content, true),
         Format::Xml => highlight::highlight_xml(content),
+        Format::Plist => highlight::highlight_xml(content.trim_end_matches('\n')),
+        // write_output ends the output with its own newline
+        Format::Sql | Format::Properties => content.trim_end_matches('\n').to_string(),
+        _ => content.to_string(),
     }
 }
 
@@ -146,8 +696,80 @@ pub fn get_output_path(
     }
 
     input.map(|p| {
-        let stem = p.file_stem().and_then(|s| s.to_str()).unwrap_or("output");
+        let stem = compression::uncompressed_name(p)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("output");
         let parent = p.parent().unwrap_or(Path::new("."));
         parent.join(format!("{}.{}", stem, format.as_str()))
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::encoding::{Encoding, LineEnding};
+
+    #[test]
+    fn test_target_formats() {
+        use clap::Parser;
+        let targets = |args: &[&str]| {
+            let args = ConvertArgs::try_parse_from([&["convert", "in.json"], args].concat());
+            target_formats(&args.unwrap())
+        };
+
+        assert_eq!(targets(&["-o", "out.yaml"]).unwrap(), [Format::Yaml]);
+        assert_eq!(targets(&["-o", "out.tsv.gz"]).unwrap(), [Format::Tsv]);
+        assert_eq!(
+            targets(&["-o", "out.toml", "--to", "toml"]).unwrap(),
+            [Format::Toml]
+        );
+        assert_eq!(
+            targets(&["-o", "out.plist", "--to", "bplist"]).unwrap(),
+            [Format::BinaryPlist]
+        );
+        assert_eq!(
+            targets(&["--to", "yaml,csv"]).unwrap(),
+            [Format::Yaml, Format::Csv]
+        );
+
+        let err = targets(&["-o", "out.yaml", "--to", "json"]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "--to json does not match the output file extension (yaml); drop --to or rename \
+             the output"
+        );
+        let err = targets(&["-o", "out.unknown"]).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Cannot infer target format from out.unknown"));
+    }
+
+    #[test]
+    fn test_check_mode() {
+        let dir = std::env::temp_dir().join(format!("dtx-convert-check-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("out.yaml");
+        let encodings = [
+            EncodingOptions::default(),
+            EncodingOptions::new(None, None, Some(LineEnding::Crlf)),
+            EncodingOptions::new(None, Some(Encoding::Utf16Le), None),
+        ];
+        for encoding in encodings {
+            let globals = GlobalOptions {
+                encoding,
+                ..Default::default()
+            };
+            let write = |content: &str, check| {
+                write_or_check(&path, content.as_bytes(), Format::Yaml, check, &globals).unwrap()
+            };
+
+            let _ = fs::remove_file(&path);
+            assert!(!write("a: 1\n", true), "missing file");
+            assert!(write("a: 1\n", false));
+            assert!(write("a: 1\n", true), "up-to-date file");
+            assert!(!write("a: 2\n", true), "stale file");
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
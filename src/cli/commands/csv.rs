@@ -3,23 +3,30 @@
 use anyhow::Result;
 
 use crate::cli::args::CsvArgs;
+use crate::cli::globals::GlobalOptions;
 use crate::cli::output::write_output;
-use crate::formats::csv as csv_format;
-use crate::utils::highlight;
+use crate::core::table;
+use crate::formats::csv::{self as csv_format, CsvReadOptions};
+use crate::utils::{highlight, width};
 
 /// Execute the csv subcommand
-pub fn execute(args: CsvArgs) -> Result<()> {
-    let content = csv_format::read_input(args.input.as_deref())?;
-    let data = csv_format::parse(&content, !args.no_headers)?;
+pub fn execute(args: CsvArgs, globals: &GlobalOptions) -> Result<()> {
+    let content = csv_format::read_input(args.input.as_deref(), &globals.encoding)?;
+    let options = CsvReadOptions::new(args.skip_rows, args.comment.as_deref())?
+        .with_delimiter(args.delimiter.as_deref())?;
+    let mut data = csv_format::parse_with_options(&content, !args.no_headers, &options)?;
 
     let output = if args.raw {
-        csv_format::to_csv(&data)?
+        csv_format::to_delimited(&data, options.delimiter.unwrap_or(b','))?
     } else {
+        if let Some(terminal_width) = width::terminal_width() {
+            table::fit_to_width(&mut data, terminal_width);
+        }
         csv_format::to_table(&data)?
     };
 
     let highlighted = highlight::highlight_csv(&output, args.raw);
-    write_output(&highlighted)?;
+    write_output(&highlighted, &globals.encoding)?;
 
     Ok(())
 }
@@ -1,20 +1,22 @@
 //! Diff subcommand implementation
 
 use anyhow::{Context, Result};
-use std::fs;
 
 use crate::cli::args::DiffArgs;
+use crate::cli::globals::GlobalOptions;
 use crate::cli::output::write_output;
+use crate::core::converter;
 use crate::core::differ::{self, DiffFormat};
+use crate::core::patcher::PatchOptions;
 use crate::formats::detect::detect;
+use crate::utils::diagnostics::SourceContext;
+use crate::utils::width;
 
 /// Execute the diff subcommand
-pub fn execute(args: DiffArgs) -> Result<()> {
+pub fn execute(args: DiffArgs, globals: &GlobalOptions) -> Result<()> {
     // Read both files
-    let content1 = fs::read_to_string(&args.file1)
-        .with_context(|| format!("Failed to read file: {}", args.file1.display()))?;
-    let content2 = fs::read_to_string(&args.file2)
-        .with_context(|| format!("Failed to read file: {}", args.file2.display()))?;
+    let content1 = globals.encoding.read_file(&args.file1)?;
+    let content2 = globals.encoding.read_file(&args.file2)?;
 
     // Detect formats
     let format1 = detect(Some(args.file1.as_path()), &content1)
@@ -26,19 +28,83 @@ pub fn execute(args: DiffArgs) -> Result<()> {
     let diff_format = if args.patch {
         DiffFormat::JsonPatch
     } else if args.side_by_side {
-        DiffFormat::SideBySide
+        let width = args
+            .max_width
+            .or_else(|| args.output.is_none().then(width::terminal_width).flatten())
+            .unwrap_or(differ::DEFAULT_SIDE_BY_SIDE_WIDTH);
+        DiffFormat::SideBySide { width }
     } else {
-        DiffFormat::Unified
+        DiffFormat::Unified {
+            context: args.context,
+        }
     };
 
+    let options = PatchOptions {
+        array_key: args.array_key.clone(),
+        ignore: args.ignore.clone(),
+        ignore_values: args
+            .ignore_value_pattern
+            .iter()
+            .map(|pattern| differ::value_pattern(pattern))
+            .collect::<Result<_>>()?,
+        yaml_version: globals.yaml_version,
+        ..Default::default()
+    };
+
+    // Files get plain text so a saved patch can be fed straight to `dtx patch`
+    if args.output.is_some() {
+        colored::control::set_override(false);
+    }
+
     // Generate diff
-    let output = if args.summary {
-        differ::diff_summary(&content1, &content2, format1, format2)?
+    let output = if args.report.as_deref() == Some("md") {
+        let labels = (
+            args.file1.display().to_string(),
+            args.file2.display().to_string(),
+        );
+        differ::diff_markdown(
+            &content1,
+            &content2,
+            format1,
+            format2,
+            (&labels.0, &labels.1),
+            &options,
+        )
+    } else if args.summary {
+        differ::diff_summary(&content1, &content2, format1, format2, &options)
     } else {
-        differ::diff(&content1, &content2, format1, format2, diff_format)?
+        differ::diff(
+            &content1,
+            &content2,
+            format1,
+            format2,
+            diff_format,
+            &options,
+        )
+    };
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => {
+            // Name the file a parse error came from; the first is parsed first
+            let parsed = converter::parse_to_json_value(&content1, format1, globals.yaml_version);
+            let failed = match parsed {
+                Ok(_) => &args.file2,
+                Err(_) => &args.file1,
+            };
+            return Err(err).in_source(Some(failed));
+        }
     };
 
-    write_output(&output)?;
+    match args.output {
+        Some(ref path) => {
+            let mut content = output;
+            if !content.ends_with('\n') {
+                content.push('\n');
+            }
+            globals.encoding.write_file(path, &content)?;
+        }
+        None => write_output(&output, &globals.encoding)?,
+    }
 
     Ok(())
 }
@@ -0,0 +1,177 @@
+//! Frontmatter subcommand implementation
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value as JsonValue;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::cli::args::FrontmatterArgs;
+use crate::cli::globals::GlobalOptions;
+use crate::cli::output::write_output;
+use crate::core::converter;
+use crate::formats::detect::{detect, Format};
+use crate::formats::frontmatter;
+use crate::utils::highlight;
+
+/// Execute the frontmatter subcommand
+pub fn execute(args: FrontmatterArgs, globals: &GlobalOptions) -> Result<()> {
+    let content = globals.encoding.read_input(args.input.as_deref())?;
+    let options = globals.convert_options();
+
+    // Documents without front matter get a new YAML block when edited
+    let (format, mut data, body) = match frontmatter::split(&content) {
+        Some(fm) => (fm.format, frontmatter::to_json(&fm, &options)?, fm.body),
+        None => (
+            Format::Yaml,
+            JsonValue::Object(serde_json::Map::new()),
+            content.as_str(),
+        ),
+    };
+
+    let editing = args.replace.is_some() || !args.set.is_empty() || !args.unset.is_empty();
+
+    if !editing {
+        if args.body {
+            return write_document(body);
+        }
+        let target = match args.to {
+            Some(ref to) => parse_format(to)?,
+            None => format,
+        };
+        let output =
+            converter::convert_with_options(&data.to_string(), Format::Json, target, &options)?;
+        return emit(&args, &output, target, globals);
+    }
+
+    if let Some(ref path) = args.replace {
+        let replacement = globals.encoding.read_file(path)?;
+        let replacement_format =
+            detect(Some(path), &replacement).context("Could not detect format of replacement")?;
+        let json = converter::convert_with_options(
+            &replacement,
+            replacement_format,
+            Format::Json,
+            &options,
+        )?;
+        data = serde_json::from_str(&json)?;
+    }
+
+    for assignment in &args.set {
+        let (key, value) = assignment
+            .split_once('=')
+            .with_context(|| format!("Invalid assignment: '{}'. Use: key=value", assignment))?;
+        // Try to parse as JSON, otherwise treat as string
+        let value = serde_json::from_str(value.trim())
+            .unwrap_or_else(|_| JsonValue::String(value.trim().to_string()));
+        set_field(&mut data, key.trim(), value)?;
+    }
+
+    for key in &args.unset {
+        remove_field(&mut data, key);
+    }
+
+    if !data.is_object() {
+        bail!("Front matter must be a mapping");
+    }
+
+    let document = frontmatter::render(format, &data, body, &options)?;
+
+    if args.in_place {
+        // `in_place` requires an input path
+        let path = args.input.as_deref().unwrap_or(Path::new("-"));
+        globals.encoding.write_file(path, &document)?;
+        eprintln!("Updated front matter in {}", path.display());
+        Ok(())
+    } else if let Some(ref output_path) = args.output {
+        globals.encoding.write_file(output_path, &document)?;
+        eprintln!("Output written to {}", output_path.display());
+        Ok(())
+    } else {
+        write_document(&document)
+    }
+}
+
+fn emit(
+    args: &FrontmatterArgs,
+    output: &str,
+    format: Format,
+    globals: &GlobalOptions,
+) -> Result<()> {
+    if let Some(ref output_path) = args.output {
+        globals.encoding.write_file(output_path, output)?;
+        return Ok(());
+    }
+
+    if args.raw {
+        write_output(output, &globals.encoding)?;
+        return Ok(());
+    }
+
+    let highlighted = match format {
+        Format::Json | Format::Ndjson => highlight::highlight_json(output),
+        Format::Yaml => highlight::highlight_yaml(output, globals.yaml_version),
+        Format::Toml => highlight::highlight_toml(output),
+        Format::Csv | Format::Tsv => highlight::highlight_csv(output, true),
+        Format::Xml => highlight::highlight_xml(output),
+        Format::Plist => highlight::highlight_xml(output.trim_end_matches('\n')),
+        _ => output.to_string(),
+    };
+    write_output(&highlighted, &globals.encoding)?;
+    Ok(())
+}
+
+/// Write a document to stdout exactly as rendered
+fn write_document(document: &str) -> Result<()> {
+    io::stdout()
+        .lock()
+        .write_all(document.as_bytes())
+        .context("Failed to write to stdout")
+}
+
+/// Set a value at a dot path, creating intermediate objects
+fn set_field(data: &mut JsonValue, path: &str, value: JsonValue) -> Result<()> {
+    let mut current = data;
+    let mut parts = path.split('.').peekable();
+
+    while let Some(part) = parts.next() {
+        let obj = current
+            .as_object_mut()
+            .with_context(|| format!("Cannot set '{}': '{}' is not a mapping", path, part))?;
+        if parts.peek().is_none() {
+            obj.insert(part.to_string(), value);
+            return Ok(());
+        }
+        current = obj
+            .entry(part.to_string())
+            .or_insert_with(|| JsonValue::Object(serde_json::Map::new()));
+    }
+
+    Ok(())
+}
+
+/// Remove the value at a dot path, if present
+fn remove_field(data: &mut JsonValue, path: &str) {
+    let (parent, key) = match path.rsplit_once('.') {
+        Some((parent, key)) => (
+            parent.split('.').try_fold(data, |v, part| v.get_mut(part)),
+            key,
+        ),
+        None => (Some(data), path),
+    };
+    if let Some(JsonValue::Object(obj)) = parent {
+        obj.shift_remove(key);
+    }
+}
+
+fn parse_format(s: &str) -> Result<Format> {
+    match s.to_lowercase().as_str() {
+        "json" => Ok(Format::Json),
+        "yaml" | "yml" => Ok(Format::Yaml),
+        "toml" => Ok(Format::Toml),
+        "csv" => Ok(Format::Csv),
+        "tsv" => Ok(Format::Tsv),
+        "xml" => Ok(Format::Xml),
+        "ndjson" | "jsonl" => Ok(Format::Ndjson),
+        _ => bail!("Unknown format: {}", s),
+    }
+}
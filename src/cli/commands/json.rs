@@ -3,23 +3,32 @@
 use anyhow::Result;
 
 use crate::cli::args::JsonArgs;
-use crate::cli::output::write_output;
+use crate::cli::globals::GlobalOptions;
+use crate::cli::output::{check_formatted, write_output};
 use crate::formats::json as json_format;
-use crate::utils::highlight;
+use crate::utils::{highlight, html};
 
 /// Execute the json subcommand
-pub fn execute(args: JsonArgs) -> Result<()> {
-    let content = json_format::read_input(args.input.as_deref())?;
+pub fn execute(args: JsonArgs, globals: &GlobalOptions) -> Result<()> {
+    let content = json_format::read_input(args.input.as_deref(), &globals.encoding)?;
     let value = json_format::parse(&content)?;
 
     let output = if args.compact {
-        json_format::to_compact(&value)?
+        json_format::to_compact(&value, &globals.style)?
     } else {
-        json_format::to_pretty(&value)?
+        json_format::to_pretty(&value, &globals.style)?
     };
 
-    let highlighted = highlight::highlight_json(&output);
-    write_output(&highlighted)?;
+    if args.check {
+        return check_formatted(args.input.as_deref(), &content, &output);
+    }
+
+    let highlighted = if args.html {
+        html::highlighted_html(&output, highlight::highlight_json)
+    } else {
+        highlight::highlight_json(&output)
+    };
+    write_output(&highlighted, &globals.encoding)?;
 
     Ok(())
 }
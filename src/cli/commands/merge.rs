@@ -1,29 +1,56 @@
 //! Merge subcommand implementation
 
 use anyhow::{Context, Result};
-use std::fs;
+use serde_json::json;
 
 use crate::cli::args::MergeArgs;
+use crate::cli::globals::GlobalOptions;
 use crate::cli::output::write_output;
+use crate::cli::porcelain;
 use crate::core::converter;
-use crate::core::merger::{self, MergeStrategy};
+use crate::core::merger::{self, MergeOptions, MergeStrategy};
 use crate::formats::detect::{detect, Format};
-use crate::utils::highlight;
+use crate::formats::{json as json_format, toml as toml_format, OutputStyle};
+use crate::utils::diagnostics::SourceContext;
+use crate::utils::{highlight, paths};
 
 /// Execute the merge subcommand
-pub fn execute(args: MergeArgs) -> Result<()> {
+pub fn execute(args: MergeArgs, globals: &GlobalOptions) -> Result<()> {
+    let files = match (&args.base, &args.env) {
+        (Some(base), Some(env)) => {
+            let layers = merger::env_layers(base, env)?;
+            if !args.quiet && globals.porcelain.is_none() {
+                let names: Vec<String> = layers.iter().map(|p| p.display().to_string()).collect();
+                eprintln!("Layers: {}", names.join(" <- "));
+            }
+            layers
+        }
+        _ => {
+            let files = paths::expand_inputs(&args.files)?;
+            if files.len() < 2 {
+                anyhow::bail!(
+                    "At least 2 files are required to merge, got {}",
+                    files.len()
+                );
+            }
+            files
+        }
+    };
+
     // Read all input files
+    let convert_options = globals.convert_options();
     let mut values = Vec::new();
 
-    for input_path in &args.files {
-        let content = fs::read_to_string(input_path)
-            .with_context(|| format!("Failed to read file: {}", input_path.display()))?;
+    for input_path in &files {
+        let content = globals.encoding.read_file(input_path)?;
 
         let format = detect(Some(input_path.as_path()), &content)
             .with_context(|| format!("Could not detect format of: {}", input_path.display()))?;
 
         // Convert to JSON for merging
-        let json_str = converter::convert(&content, format, Format::Json)?;
+        let json_str =
+            converter::convert_with_options(&content, format, Format::Json, &convert_options)
+                .in_source(Some(input_path))?;
         let value: serde_json::Value = serde_json::from_str(&json_str)?;
         values.push(value);
     }
@@ -33,12 +60,20 @@ pub fn execute(args: MergeArgs) -> Result<()> {
         Some("shallow") => MergeStrategy::Shallow,
         Some("concat") => MergeStrategy::ConcatArrays,
         Some("union") => MergeStrategy::UnionArrays,
+        Some("strategic") => MergeStrategy::Strategic,
         Some("deep") | None => MergeStrategy::Deep,
-        Some(s) => anyhow::bail!("Unknown merge strategy: {}. Use: deep, shallow, concat, union", s),
+        Some(s) => anyhow::bail!(
+            "Unknown merge strategy: {}. Use: deep, shallow, concat, union, strategic",
+            s
+        ),
     };
 
     // Merge all values
-    let merged = merger::merge_all(&values, strategy)?;
+    let options = MergeOptions {
+        strategy,
+        null_deletes: args.null_deletes,
+    };
+    let merged = merger::merge_all_with_options(&values, &options)?;
 
     // Determine output format
     let output_format = if let Some(ref fmt) = args.format {
@@ -50,23 +85,37 @@ pub fn execute(args: MergeArgs) -> Result<()> {
     };
 
     // Convert to output format
-    let output = format_output(&merged, output_format)?;
+    let output = format_output(&merged, output_format, &globals.style)?;
 
     // Write output
     if let Some(ref output_path) = args.output {
-        fs::write(output_path, &output)
-            .with_context(|| format!("Failed to write to {}", output_path.display()))?;
-        if !args.quiet {
-            eprintln!("Merged {} files -> {}", args.files.len(), output_path.display());
+        globals.encoding.write_file(output_path, &output)?;
+        porcelain::record(
+            globals,
+            "merged",
+            &[
+                ("output", json!(output_path.display().to_string())),
+                ("files", json!(files.len())),
+                (
+                    "sources",
+                    json!(files
+                        .iter()
+                        .map(|p| p.display().to_string())
+                        .collect::<Vec<_>>()),
+                ),
+            ],
+        )?;
+        if !args.quiet && globals.porcelain.is_none() {
+            eprintln!("Merged {} files -> {}", files.len(), output_path.display());
         }
     } else {
         let highlighted = match output_format {
             Format::Json => highlight::highlight_json(&output),
-            Format::Yaml => highlight::highlight_yaml(&output),
+            Format::Yaml => highlight::highlight_yaml(&output, globals.yaml_version),
             Format::Toml => highlight::highlight_toml(&output),
             _ => output.clone(),
         };
-        write_output(&highlighted)?;
+        write_output(&highlighted, &globals.encoding)?;
     }
 
     Ok(())
@@ -81,11 +130,14 @@ fn parse_format(s: &str) -> Result<Format> {
     }
 }
 
-fn format_output(value: &serde_json::Value, format: Format) -> Result<String> {
+fn format_output(value: &serde_json::Value, format: Format, style: &OutputStyle) -> Result<String> {
     match format {
-        Format::Json => serde_json::to_string_pretty(value).context("Failed to serialize JSON"),
+        Format::Json => json_format::to_pretty(value, style),
         Format::Yaml => serde_yaml::to_string(value).context("Failed to serialize YAML"),
-        Format::Toml => toml::to_string_pretty(value).context("Failed to serialize TOML"),
+        Format::Toml => toml_format::to_pretty(
+            &toml::Value::try_from(value).context("Failed to serialize TOML")?,
+            style,
+        ),
         _ => anyhow::bail!("Unsupported output format for merge"),
     }
 }
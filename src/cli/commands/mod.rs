@@ -2,15 +2,19 @@
 
 pub mod auto;
 pub mod batch;
+pub mod bench;
 pub mod completions;
 pub mod convert;
 pub mod csv;
 pub mod diff;
+pub mod frontmatter;
 pub mod json;
 pub mod merge;
 pub mod patch;
 pub mod query;
 pub mod schema;
+pub mod serve;
+pub mod table;
 pub mod template;
 pub mod toml;
 pub mod validate;
@@ -2,40 +2,49 @@
 
 use anyhow::{Context, Result};
 use std::fs;
-use std::io::{self, Read};
-use std::path::Path;
 
 use crate::cli::args::PatchArgs;
+use crate::cli::globals::GlobalOptions;
 use crate::cli::output::write_output;
-use crate::core::patcher;
+use crate::core::patcher::{self, PatchOptions};
+use crate::formats::json as json_format;
+use crate::utils::diagnostics::SourceContext;
 use crate::utils::highlight;
 
 /// Execute the patch subcommand
-pub fn execute(args: PatchArgs) -> Result<()> {
+pub fn execute(args: PatchArgs, globals: &GlobalOptions) -> Result<()> {
     // Read input document
-    let doc_content = read_input(args.input.as_deref())?;
-    let doc: serde_json::Value = serde_json::from_str(&doc_content)
-        .context("Input must be valid JSON")?;
+    let doc_content = globals.encoding.read_input(args.input.as_deref())?;
+    let doc = json_format::parse(&doc_content)
+        .context("Input must be valid JSON")
+        .in_source(args.input.as_deref())?;
 
     // Read patch
     let patch_content = fs::read_to_string(&args.patch)
         .with_context(|| format!("Failed to read patch file: {}", args.patch.display()))?;
-    let patch_value: serde_json::Value = serde_json::from_str(&patch_content)
-        .context("Patch must be valid JSON")?;
+    let patch_value = json_format::parse(&patch_content)
+        .context("Patch must be valid JSON")
+        .in_source(Some(&args.patch))?;
 
     // Parse patch operations
     let operations = patcher::parse_patch(&patch_value)?;
 
     // Apply patch
-    let result = patcher::apply_patch(&doc, &operations)?;
+    let options = PatchOptions {
+        ignore: args.ignore.clone(),
+        extended: args.extended,
+        ..Default::default()
+    };
+    let result = patcher::apply_patch_with_options(&doc, &operations, &options)?;
 
     // Format output
-    let output = serde_json::to_string_pretty(&result)?;
+    let output = json_format::to_pretty(&result, &globals.style)?;
 
     // Write output
     if let Some(ref output_path) = args.output {
-        fs::write(output_path, &output)
-            .with_context(|| format!("Failed to write to {}", output_path.display()))?;
+        globals
+            .encoding
+            .write_file(output_path, &format!("{}\n", output))?;
         if !args.quiet {
             eprintln!("Patched output written to {}", output_path.display());
         }
@@ -45,24 +54,9 @@ pub fn execute(args: PatchArgs) -> Result<()> {
         } else {
             highlight::highlight_json(&output)
         };
-        write_output(&highlighted)?;
+        write_output(&highlighted, &globals.encoding)?;
     }
 
     Ok(())
 }
 
-fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
-        Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
-        }
-        None => {
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
-        }
-    }
-}
-
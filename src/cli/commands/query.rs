@@ -1,26 +1,64 @@
 //! Query subcommand implementation
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use std::fs;
-use std::io::{self, Read};
-use std::path::Path;
+use std::io::{self, BufRead, BufReader};
 
 use crate::cli::args::QueryArgs;
+use crate::cli::globals::GlobalOptions;
+use crate::cli::interactive::{self, QueryExpressions};
 use crate::cli::output::write_output;
-use crate::core::query;
+use crate::cli::progress::Progress;
+use crate::core::converter;
+use crate::core::query::{self, AggregateOp};
 use crate::formats::detect::{detect, Format};
-use crate::formats::{json as json_format, yaml as yaml_format};
+use crate::formats::yaml::YamlVersion;
+use crate::formats::{
+    frontmatter, json as json_format, ndjson as ndjson_format, yaml as yaml_format, OutputStyle,
+};
 use crate::utils::highlight;
 
 /// Execute the query subcommand
-pub fn execute(args: QueryArgs) -> Result<()> {
+pub fn execute(mut args: QueryArgs, globals: &GlobalOptions) -> Result<()> {
+    if args.stream {
+        return execute_stream(args, globals);
+    }
+
     // Read input
-    let content = read_input(args.input.as_deref())?;
+    let content = globals.encoding.read_input(args.input.as_deref())?;
 
     // Detect format and parse to JSON
-    let format = detect(args.input.as_deref(), &content).unwrap_or(Format::Json);
+    let (content, format) = if args.front_matter || frontmatter::is_markdown(args.input.as_deref())
+    {
+        frontmatter::extract(&content)?
+    } else {
+        let format = detect(args.input.as_deref(), &content).unwrap_or(Format::Json);
+        (content, format)
+    };
 
-    let mut value = parse_to_json(&content, format)?;
+    let mut value = parse_to_json(&content, format, globals.yaml_version)?;
+
+    if !args.set.is_empty() {
+        let assignments = args
+            .set
+            .iter()
+            .map(|expr| query::parse_assignment(expr))
+            .collect::<Result<Vec<_>>>()?;
+        value = query::set_values(&value, &assignments)?;
+    }
+
+    if args.interactive {
+        let initial = QueryExpressions {
+            path: args.query.clone().unwrap_or_default(),
+            filter: args.filter.clone().unwrap_or_default(),
+        };
+        let Some(accepted) = interactive::run(&value, &initial)? else {
+            return Ok(());
+        };
+        args.query = Some(accepted.path).filter(|p| !p.trim().is_empty());
+        args.filter = Some(accepted.filter).filter(|f| !f.trim().is_empty());
+        eprintln!("{}", equivalent_flags(&args));
+    }
 
     // Apply JSONPath query if provided
     if let Some(ref path) = args.query {
@@ -37,8 +75,16 @@ pub fn execute(args: QueryArgs) -> Result<()> {
     }
 
     if args.flatten {
-        let separator = args.separator.as_deref().unwrap_or(".");
-        value = query::flatten(&value, separator);
+        let options = query::FlattenOptions {
+            separator: args.separator.clone().unwrap_or_else(|| ".".to_string()),
+            depth: args.depth,
+            arrays: match args.array_notation.as_str() {
+                "bracket" => query::ArrayNotation::Bracket,
+                "dot" => query::ArrayNotation::Dot,
+                _ => query::ArrayNotation::Index,
+            },
+        };
+        value = query::flatten_with_options(&value, &options);
     }
 
     if args.sort_keys {
@@ -49,11 +95,29 @@ pub fn execute(args: QueryArgs) -> Result<()> {
         value = query::filter_array(&value, expr)?;
     }
 
+    if !args.add_field.is_empty() {
+        let computed = args
+            .add_field
+            .iter()
+            .map(|definition| query::parse_computed_field(definition))
+            .collect::<Result<Vec<_>>>()?;
+        value = query::add_fields(&value, &computed)?;
+    }
+
     if let Some(ref fields) = args.select {
         let field_list: Vec<String> = fields.split(',').map(|s| s.trim().to_string()).collect();
         value = query::select_fields(&value, &field_list)?;
     }
 
+    if !args.map.is_empty() {
+        let mappings = args
+            .map
+            .iter()
+            .map(|expr| query::parse_mapping(expr))
+            .collect::<Result<Vec<_>>>()?;
+        value = query::map_fields(&value, &mappings)?;
+    }
+
     if args.unique {
         value = query::unique(&value)?;
     }
@@ -74,11 +138,40 @@ pub fn execute(args: QueryArgs) -> Result<()> {
         value = query::last(&value, n)?;
     }
 
+    if let Some(ref expr) = args.slice {
+        value = query::slice(&value, &query::Slice::parse(expr)?)?;
+    }
+
+    if let Some(ref field) = args.frequency {
+        value = query::frequency(&value, field, args.top)?;
+    }
+
+    let aggregates: Vec<(AggregateOp, String)> = [
+        (AggregateOp::Sum, &args.sum),
+        (AggregateOp::Avg, &args.avg),
+        (AggregateOp::Min, &args.min),
+        (AggregateOp::Max, &args.max),
+    ]
+    .into_iter()
+    .flat_map(|(op, fields)| fields.iter().map(move |f| (op, f.clone())))
+    .collect();
+
+    if !aggregates.is_empty() {
+        value = query::aggregate(&value, &aggregates)?;
+    }
+
     // Output
+    if args.ndjson {
+        if let Some(lines) = ndjson_lines(value, &globals.style)? {
+            write_output(&lines, &globals.encoding)?;
+        }
+        return Ok(());
+    }
+
     let output = if args.compact {
-        serde_json::to_string(&value)?
+        json_format::to_compact(&value, &globals.style)?
     } else {
-        serde_json::to_string_pretty(&value)?
+        json_format::to_pretty(&value, &globals.style)?
     };
 
     let highlighted = if args.raw {
@@ -87,44 +180,176 @@ pub fn execute(args: QueryArgs) -> Result<()> {
         highlight::highlight_json(&output)
     };
 
-    write_output(&highlighted)?;
+    write_output(&highlighted, &globals.encoding)?;
 
     Ok(())
 }
 
-fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
+/// Process a top-level JSON array element by element
+fn execute_stream(args: QueryArgs, globals: &GlobalOptions) -> Result<()> {
+    let unsupported = [
+        ("--query", args.query.is_some()),
+        ("--keys", args.keys),
+        ("--values", args.values),
+        ("--flatten", args.flatten),
+        ("--unique", args.unique),
+        ("--reverse", args.reverse),
+        ("--last", args.last.is_some()),
+        ("--slice", args.slice.is_some()),
+        ("--set", !args.set.is_empty()),
+        ("--sum", !args.sum.is_empty()),
+        ("--avg", !args.avg.is_empty()),
+        ("--min", !args.min.is_empty()),
+        ("--max", !args.max.is_empty()),
+        ("--frequency", args.frequency.is_some()),
+        ("--front-matter", args.front_matter),
+    ];
+    if let Some((flag, _)) = unsupported.iter().find(|(_, used)| *used) {
+        bail!("{} cannot be combined with --stream", flag);
+    }
+
+    let reader: Box<dyn BufRead> = match args.input.as_deref() {
         Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
+            Box::new(BufReader::new(fs::File::open(p).with_context(|| {
+                format!("Failed to read file: {}", p.display())
+            })?))
         }
-        None => {
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
+        None => Box::new(BufReader::new(io::stdin())),
+    };
+    let progress = Progress::start("Querying", args.input.as_deref(), globals);
+    let reader = match progress {
+        Some(ref progress) => Box::new(progress.reader(reader)),
+        None => reader,
+    };
+    let reader = globals.encoding.utf8_reader(reader)?;
+
+    let filter = args
+        .filter
+        .as_deref()
+        .map(query::Filter::parse)
+        .transpose()?;
+    let computed = args
+        .add_field
+        .iter()
+        .map(|definition| query::parse_computed_field(definition))
+        .collect::<Result<Vec<_>>>()?;
+    let fields: Option<Vec<String>> = args
+        .select
+        .as_ref()
+        .map(|f| f.split(',').map(|s| s.trim().to_string()).collect());
+    let mappings = args
+        .map
+        .iter()
+        .map(|expr| query::parse_mapping(expr))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut matched = 0usize;
+    for item in json_format::ArrayStream::new(reader) {
+        if args.first.is_some_and(|n| matched >= n) {
+            break;
+        }
+
+        let mut value = item?;
+        if filter.as_ref().is_some_and(|f| !f.matches(&value)) {
+            continue;
+        }
+        matched += 1;
+
+        if args.count {
+            continue;
+        }
+        if !computed.is_empty() {
+            value = query::add_fields(&value, &computed)?;
+        }
+        if let Some(ref fields) = fields {
+            value = query::select_fields(&value, fields)?;
+        }
+        if !mappings.is_empty() {
+            value = query::map_fields(&value, &mappings)?;
+        }
+        if args.sort_keys {
+            value = query::sort_keys(&value);
+        }
+
+        let line = json_format::to_compact(&value, &globals.style)?;
+        if args.raw || args.ndjson {
+            write_output(&line, &globals.encoding)?;
+        } else {
+            write_output(&highlight::highlight_json(&line), &globals.encoding)?;
         }
     }
+
+    if args.count {
+        write_output(&matched.to_string(), &globals.encoding)?;
+    }
+
+    Ok(())
 }
 
-fn parse_to_json(content: &str, format: Format) -> Result<serde_json::Value> {
+/// Results as unhighlighted NDJSON: one line per item of an array, or the
+/// single line of any other value; nothing for an empty array
+fn ndjson_lines(value: serde_json::Value, style: &OutputStyle) -> Result<Option<String>> {
+    let values = match value {
+        serde_json::Value::Array(items) => items,
+        other => vec![other],
+    };
+    if values.is_empty() {
+        return Ok(None);
+    }
+    let lines = ndjson_format::to_string(&values, style)?;
+    Ok(Some(lines.trim_end_matches('\n').to_string()))
+}
+
+/// Command-line flags reproducing the query accepted interactively
+fn equivalent_flags(args: &QueryArgs) -> String {
+    let mut flags = Vec::new();
+    if let Some(ref path) = args.query {
+        flags.push(format!("-q {}", shell_words::quote(path)));
+    }
+    if let Some(ref filter) = args.filter {
+        flags.push(format!("--filter {}", shell_words::quote(filter)));
+    }
+    if flags.is_empty() {
+        "No query or filter applied".to_string()
+    } else {
+        format!("Equivalent flags: {}", flags.join(" "))
+    }
+}
+
+fn parse_to_json(content: &str, format: Format, version: YamlVersion) -> Result<serde_json::Value> {
     match format {
         Format::Json => json_format::parse(content)
             .map(|v| serde_json::from_str(&serde_json::to_string(&v).unwrap()).unwrap()),
         Format::Yaml => {
-            let yaml_value = yaml_format::parse(content)?;
+            let yaml_value = yaml_format::parse(content, version)?;
             let json_str = serde_json::to_string(&yaml_value)?;
             serde_json::from_str(&json_str).context("Failed to convert YAML to JSON")
         }
-        _ => {
-            // For other formats, try JSON first, then YAML
-            if let Ok(v) = json_format::parse(content) {
-                Ok(serde_json::from_str(&serde_json::to_string(&v).unwrap()).unwrap())
-            } else {
-                let yaml_value = yaml_format::parse(content)?;
-                let json_str = serde_json::to_string(&yaml_value)?;
-                serde_json::from_str(&json_str).context("Failed to parse input")
-            }
-        }
+        // Other formats are read by their own handler
+        _ => converter::parse_to_json_value(content, format, version),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_ndjson_lines() {
+        let style = OutputStyle::default();
+        let array = json!([{"id": 1, "tags": ["a"]}, {"id": 2}, "three"]);
+        // Plain compact lines; highlighting would add escape codes
+        assert_eq!(
+            ndjson_lines(array, &style).unwrap().unwrap(),
+            "{\"id\":1,\"tags\":[\"a\"]}\n{\"id\":2}\n\"three\""
+        );
+        assert_eq!(
+            ndjson_lines(json!({"count": 3}), &style).unwrap().unwrap(),
+            "{\"count\":3}"
+        );
+        assert_eq!(ndjson_lines(json!(42), &style).unwrap().unwrap(), "42");
+        assert_eq!(ndjson_lines(json!(null), &style).unwrap().unwrap(), "null");
+        assert_eq!(ndjson_lines(json!([]), &style).unwrap(), None);
     }
 }
@@ -1,40 +1,77 @@
 //! Schema subcommand implementation
 
-use anyhow::{Context, Result};
-use std::fs;
-use std::io::{self, Read};
-use std::path::Path;
+use anyhow::{bail, Context, Result};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::cli::args::SchemaArgs;
+use crate::cli::args::{
+    SchemaArgs, SchemaBundleArgs, SchemaCommand, SchemaDiffArgs, SchemaMergeArgs,
+};
+use crate::cli::globals::GlobalOptions;
 use crate::cli::output::write_output;
-use crate::core::schema;
-use crate::formats::detect::{detect, Format};
-use crate::utils::highlight;
+use crate::core::schema::Sampling;
+use crate::core::{compat, refs, schema};
+use crate::formats::detect::{detect, detect_from_extension, Format};
+use crate::formats::ndjson::LineStream;
+use crate::formats::yaml::YamlVersion;
+use crate::formats::{json as json_format, yaml as yaml_format};
+use crate::utils::diagnostics::SourceContext;
+use crate::utils::{highlight, paths};
 
 /// Execute the schema subcommand
-pub fn execute(args: SchemaArgs) -> Result<()> {
-    // Read input
-    let content = read_input(args.input.as_deref())?;
-
-    // Detect format and parse to JSON
-    let format = detect(args.input.as_deref(), &content).unwrap_or(Format::Json);
-    let value = parse_to_json(&content, format)?;
+pub fn execute(args: SchemaArgs, globals: &GlobalOptions) -> Result<()> {
+    if let Some(command) = args.command {
+        return match command {
+            SchemaCommand::Bundle(args) => execute_bundle(args, globals),
+            SchemaCommand::Diff(args) => execute_diff(args, globals),
+            SchemaCommand::Merge(args) => execute_merge(args, globals),
+        };
+    }
 
-    // Generate schema
-    let json_schema = schema::generate_schema(&value);
+    let inputs = match args.input {
+        Some(ref input) => paths::expand_inputs(std::slice::from_ref(input))?,
+        None => Vec::new(),
+    };
+    let json_schema = match &inputs[..] {
+        // One schema that every matched file is valid against
+        [_, _, ..] => {
+            if args.sample.is_some() {
+                bail!("--sample reads a single input, not {} files", inputs.len());
+            }
+            let schemas = inputs
+                .iter()
+                .map(|path| {
+                    let value = read_value(Some(path), globals)
+                        .with_context(|| format!("Failed to read {}", path.display()))?;
+                    Ok(schema::generate_schema(&value))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            schema::merge_schemas(&schemas, schema::RequiredMode::Intersection)
+        }
+        single => {
+            let input = single.first().map(PathBuf::as_path);
+            let value = match args.sample {
+                Some(size) => sampled_records(&args, input, size, globals)?,
+                None => read_value(input, globals)?,
+            };
+            schema::generate_schema(&value)
+        }
+    };
 
     // Output based on format
     let output = if args.typescript {
-        let name = args
-            .name
-            .as_deref()
-            .unwrap_or_else(|| {
-                args.input
-                    .as_ref()
-                    .and_then(|p| p.file_stem())
-                    .and_then(|s| s.to_str())
-                    .unwrap_or("Data")
-            });
+        let name = args.name.as_deref().unwrap_or_else(|| {
+            let input = match &inputs[..] {
+                [input] => Some(input),
+                _ => None,
+            };
+            input
+                .and_then(|p| p.file_stem())
+                .and_then(|s| s.to_str())
+                .unwrap_or("Data")
+        });
         // Capitalize first letter
         let name = capitalize_first(name);
         schema::schema_to_typescript(&json_schema, &name)
@@ -49,40 +86,162 @@ pub fn execute(args: SchemaArgs) -> Result<()> {
 
     // Write output
     if let Some(ref output_path) = args.output {
-        fs::write(output_path, &output)
-            .with_context(|| format!("Failed to write to {}", output_path.display()))?;
+        globals.encoding.write_file(output_path, &output)?;
     } else {
-        write_output(&output)?;
+        write_output(&output, &globals.encoding)?;
     }
 
     Ok(())
 }
 
-fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
-        Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
+/// Read the input and parse it to JSON, detecting its format
+fn read_value(input: Option<&Path>, globals: &GlobalOptions) -> Result<serde_json::Value> {
+    let content = globals.encoding.read_input(input)?;
+    let format = detect(input, &content).unwrap_or(Format::Json);
+    parse_to_json(&content, format, globals.yaml_version).in_source(input)
+}
+
+/// Sample records of an NDJSON stream or a top-level array, reporting field coverage on stderr
+fn sampled_records(
+    args: &SchemaArgs,
+    input: Option<&Path>,
+    size: usize,
+    globals: &GlobalOptions,
+) -> Result<serde_json::Value> {
+    let sampling = if args.random {
+        let seed = args.seed.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or(1)
+        });
+        Sampling::Random { seed }
+    } else {
+        Sampling::First
+    };
+
+    let streamed = input.filter(|p| detect_from_extension(p) == Some(Format::Ndjson));
+    let (sample, seen) = match streamed {
+        Some(path) => {
+            let file = File::open(path)
+                .with_context(|| format!("Failed to read file: {}", path.display()))?;
+            let reader = globals
+                .encoding
+                .utf8_reader(Box::new(BufReader::new(file)))?;
+            schema::sample_records(LineStream::new(reader), size, sampling)?
         }
         None => {
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
+            let records = match read_value(input, globals)? {
+                serde_json::Value::Array(records) => records,
+                _ => bail!("--sample needs NDJSON input or a top-level array"),
+            };
+            schema::sample_records(records.into_iter().map(Ok), size, sampling)?
         }
+    };
+
+    match sampling {
+        Sampling::First => eprintln!("Inferred from the first {} records", sample.len()),
+        Sampling::Random { .. } => eprintln!(
+            "Inferred from {} records sampled at random out of {}",
+            sample.len(),
+            seen
+        ),
+    }
+    let counts = schema::field_counts(&sample);
+    let width = counts.keys().map(|k| k.len()).max().unwrap_or(0);
+    for (field, count) in &counts {
+        eprintln!(
+            "  {:width$}  {:>8}  {:5.1}%",
+            field,
+            count,
+            *count as f64 * 100.0 / sample.len().max(1) as f64,
+            width = width
+        );
+    }
+
+    Ok(serde_json::Value::Array(sample))
+}
+
+/// Bundle or dereference a multi-file schema
+fn execute_bundle(args: SchemaBundleArgs, globals: &GlobalOptions) -> Result<()> {
+    let bundled = if args.dereference {
+        refs::dereference(&args.schema, args.offline)?
+    } else {
+        refs::bundle(&args.schema, args.offline)?
+    };
+    write_schema(&bundled, args.output.as_deref(), args.raw, globals)
+}
+
+/// Merge several schemas into one
+fn execute_merge(args: SchemaMergeArgs, globals: &GlobalOptions) -> Result<()> {
+    let required = match args.required.as_str() {
+        "union" => schema::RequiredMode::Union,
+        _ => schema::RequiredMode::Intersection,
+    };
+    let schemas = args
+        .schemas
+        .iter()
+        .map(|source| {
+            refs::dereference(source, args.offline)
+                .with_context(|| format!("Failed to load schema: {}", source))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let merged = schema::merge_schemas(&schemas, required);
+    write_schema(&merged, args.output.as_deref(), args.raw, globals)
+}
+
+/// Write a schema to a file, or highlighted to stdout
+fn write_schema(
+    value: &serde_json::Value,
+    output: Option<&Path>,
+    raw: bool,
+    globals: &GlobalOptions,
+) -> Result<()> {
+    let json_str = serde_json::to_string_pretty(value)?;
+
+    if let Some(output_path) = output {
+        globals
+            .encoding
+            .write_file(output_path, &format!("{}\n", json_str))?;
+    } else if raw {
+        write_output(&json_str, &globals.encoding)?;
+    } else {
+        write_output(&highlight::highlight_json(&json_str), &globals.encoding)?;
     }
+
+    Ok(())
+}
+
+/// Report compatibility between two schema versions, failing on breaking changes
+fn execute_diff(args: SchemaDiffArgs, globals: &GlobalOptions) -> Result<()> {
+    let old = refs::dereference(&args.old, args.offline)
+        .with_context(|| format!("Failed to load schema: {}", args.old))?;
+    let new = refs::dereference(&args.new, args.offline)
+        .with_context(|| format!("Failed to load schema: {}", args.new))?;
+
+    let changes = compat::compare(&old, &new);
+    write_output(&compat::format_report(&changes), &globals.encoding)?;
+
+    if compat::has_breaking(&changes) {
+        std::process::exit(1);
+    }
+
+    Ok(())
 }
 
-fn parse_to_json(content: &str, format: Format) -> Result<serde_json::Value> {
+fn parse_to_json(content: &str, format: Format, version: YamlVersion) -> Result<serde_json::Value> {
     match format {
-        Format::Json => serde_json::from_str(content).context("Failed to parse JSON"),
+        Format::Json => json_format::parse(content),
+        Format::Ndjson => Ok(serde_json::Value::Array(crate::formats::ndjson::parse(
+            content,
+        )?)),
         Format::Yaml => {
-            let yaml: serde_yaml::Value =
-                serde_yaml::from_str(content).context("Failed to parse YAML")?;
+            let yaml = yaml_format::parse(content, version)?;
             let json_str = serde_json::to_string(&yaml)?;
             serde_json::from_str(&json_str).context("Failed to convert to JSON")
         }
-        Format::Csv => {
+        Format::Csv | Format::Tsv => {
             let data = crate::formats::csv::parse(content, true)?;
             let headers = data.headers.as_ref().context("CSV must have headers")?;
 
@@ -0,0 +1,350 @@
+//! Serve subcommand implementation: an HTTP API over the core modules
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use serde_json::{json, Value as JsonValue};
+use tiny_http::{Header, Response, Server};
+use tracing::info;
+
+use crate::cli::args::ServeArgs;
+use crate::cli::commands::convert::parse_format;
+use crate::cli::globals::GlobalOptions;
+use crate::core::converter::{self, ConvertOptions};
+use crate::core::{query, validator};
+use crate::formats::detect::{detect, Format};
+
+/// Largest request body accepted, in bytes
+const MAX_BODY_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Response produced by the router, independent of the HTTP library
+#[derive(Debug)]
+struct ApiResponse {
+    status: u16,
+    content_type: &'static str,
+    body: String,
+}
+
+impl ApiResponse {
+    fn ok(content_type: &'static str, body: String) -> Self {
+        Self {
+            status: 200,
+            content_type,
+            body,
+        }
+    }
+
+    fn json(status: u16, value: &JsonValue) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_string_pretty(value).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self::json(status, &json!({ "error": message }))
+    }
+}
+
+/// Execute the serve subcommand
+pub fn execute(args: ServeArgs, globals: &GlobalOptions) -> Result<()> {
+    let options = globals.convert_options();
+    let address = format!("{}:{}", args.host, args.port);
+    let server =
+        Server::http(&address).map_err(|e| anyhow!("Failed to listen on {}: {}", address, e))?;
+    eprintln!("Listening on http://{}", address);
+
+    for mut request in server.incoming_requests() {
+        let response = match read_body(request.as_reader()) {
+            Ok(body) => route(
+                request.method().as_str(),
+                request.url(),
+                &body,
+                args.schema_dir.as_deref(),
+                &options,
+            ),
+            Err(response) => response,
+        };
+
+        info!(method = %request.method(), url = request.url(), status = response.status, "request");
+
+        let header = Header::from_bytes("Content-Type", response.content_type)
+            .map_err(|_| anyhow!("Invalid Content-Type header"))?;
+        let reply = Response::from_string(response.body)
+            .with_status_code(response.status)
+            .with_header(header);
+        if let Err(e) = request.respond(reply) {
+            tracing::warn!("Failed to send response: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// Read a request body of at most `MAX_BODY_SIZE` bytes, or the error response
+fn read_body(reader: impl Read) -> Result<String, ApiResponse> {
+    let mut body = Vec::new();
+    reader
+        .take(MAX_BODY_SIZE + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| ApiResponse::error(400, &format!("Failed to read request body: {}", e)))?;
+    if body.len() as u64 > MAX_BODY_SIZE {
+        return Err(ApiResponse::error(
+            413,
+            &format!("Request body exceeds {} bytes", MAX_BODY_SIZE),
+        ));
+    }
+    String::from_utf8(body).map_err(|_| ApiResponse::error(400, "Request body is not valid UTF-8"))
+}
+
+/// Dispatch a request to the matching endpoint; conversions start from `options`
+fn route(
+    method: &str,
+    url: &str,
+    body: &str,
+    schema_dir: Option<&Path>,
+    options: &ConvertOptions,
+) -> ApiResponse {
+    let (path, query_string) = url.split_once('?').unwrap_or((url, ""));
+    let params = parse_query_string(query_string);
+
+    let result = match (method, path) {
+        ("GET", "/health") => Ok(ApiResponse::json(
+            200,
+            &json!({ "status": "ok", "version": env!("CARGO_PKG_VERSION") }),
+        )),
+        ("POST", "/convert") => handle_convert(&params, body, options),
+        ("POST", "/query") => handle_query(&params, body, options),
+        ("POST", "/validate") => handle_validate(&params, body, schema_dir, options),
+        (_, "/health" | "/convert" | "/query" | "/validate") => {
+            return ApiResponse::error(405, "Method not allowed");
+        }
+        _ => return ApiResponse::error(404, "Not found"),
+    };
+
+    result.unwrap_or_else(|e| ApiResponse::error(400, &format!("{:#}", e)))
+}
+
+/// POST /convert?from=json&to=yaml
+fn handle_convert(
+    params: &HashMap<String, String>,
+    body: &str,
+    options: &ConvertOptions,
+) -> Result<ApiResponse> {
+    let from = source_format(params, "from", body)?;
+    let to = params
+        .get("to")
+        .context("Missing 'to' parameter")
+        .and_then(|name| parse_format(name))?;
+    let options = ConvertOptions {
+        record_path: params.get("record_path").cloned(),
+        group_by: params.get("group_by").cloned(),
+        children: params.get("children").cloned(),
+        ..options.clone()
+    };
+
+    let output = converter::convert_with_options(body, from, to, &options)?;
+    Ok(ApiResponse::ok(content_type(to), output))
+}
+
+/// POST /query?q=$.items[*]&filter=...&select=a,b
+fn handle_query(
+    params: &HashMap<String, String>,
+    body: &str,
+    options: &ConvertOptions,
+) -> Result<ApiResponse> {
+    let from = source_format(params, "from", body)?;
+    let mut value = converter::parse_to_json_value(body, from, options.yaml_version)?;
+
+    if let Some(path) = params.get("q") {
+        value = query::jsonpath_query(&value, path)?;
+    }
+    if let Some(expr) = params.get("filter") {
+        value = query::filter_array(&value, expr)?;
+    }
+    if let Some(fields) = params.get("select") {
+        let fields: Vec<String> = fields.split(',').map(|s| s.trim().to_string()).collect();
+        value = query::select_fields(&value, &fields)?;
+    }
+
+    Ok(ApiResponse::json(200, &value))
+}
+
+/// POST /validate?format=yaml&schema=name
+fn handle_validate(
+    params: &HashMap<String, String>,
+    body: &str,
+    schema_dir: Option<&Path>,
+    options: &ConvertOptions,
+) -> Result<ApiResponse> {
+    let format = source_format(params, "format", body)?;
+
+    let result = match params.get("schema") {
+        Some(name) => {
+            let path = schema_path(schema_dir, name)?;
+            let schema = validator::load_schema(&path.to_string_lossy(), true)?;
+            if format == Format::Ndjson {
+                validator::validate_ndjson_schema(body, &schema)?
+            } else {
+                let data = converter::parse_to_json_value(body, format, options.yaml_version)?;
+                let mut result = validator::validate_json_schema(&data, &schema)?;
+                result.locate_errors(body, format, 0);
+                result
+            }
+        }
+        None => validator::lint(body, format, true)?,
+    };
+
+    Ok(ApiResponse::json(200, &serde_json::to_value(&result)?))
+}
+
+/// Resolve a schema name against --schema-dir, refusing anything outside it
+fn schema_path(schema_dir: Option<&Path>, name: &str) -> Result<PathBuf> {
+    let dir =
+        schema_dir.context("Schema validation requires the server to run with --schema-dir")?;
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        bail!("Invalid schema name: {}", name);
+    }
+
+    let path = dir.join(name);
+    if path.is_file() {
+        return Ok(path);
+    }
+    let with_extension = dir.join(format!("{}.json", name));
+    if with_extension.is_file() {
+        return Ok(with_extension);
+    }
+    bail!("Unknown schema: {}", name)
+}
+
+/// Format named by `param`, or detected from the body
+fn source_format(params: &HashMap<String, String>, param: &str, body: &str) -> Result<Format> {
+    match params.get(param) {
+        Some(name) => parse_format(name),
+        None => detect(None, body)
+            .with_context(|| format!("Could not detect format. Use the '{}' parameter.", param)),
+    }
+}
+
+fn content_type(format: Format) -> &'static str {
+    format
+        .handler()
+        .map_or("application/octet-stream", |handler| handler.media_type())
+}
+
+/// Parse `a=1&b=x%20y` into a map, decoding percent escapes and `+`
+fn parse_query_string(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_route_convert() {
+        let options = ConvertOptions::default();
+        let response = route(
+            "POST",
+            "/convert?from=json&to=yaml",
+            r#"{"a": 1}"#,
+            None,
+            &options,
+        );
+        assert_eq!(response.status, 200);
+        assert_eq!(response.content_type, "application/yaml");
+        assert_eq!(response.body.trim(), "a: 1");
+
+        let response = route("POST", "/convert", r#"{"a": 1}"#, None, &options);
+        assert_eq!(response.status, 400);
+        assert!(response.body.contains("Missing 'to' parameter"));
+    }
+
+    #[test]
+    fn test_route_query() {
+        let body = r#"{"users": [{"name": "a", "age": 30}, {"name": "b", "age": 20}]}"#;
+        let response = route(
+            "POST",
+            "/query?q=%24.users&filter=age+%3E+25&select=name",
+            body,
+            None,
+            &ConvertOptions::default(),
+        );
+        assert_eq!(response.status, 200);
+        let value: JsonValue = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(value, json!([{"name": "a"}]));
+    }
+
+    #[test]
+    fn test_route_validate_and_errors() {
+        let options = ConvertOptions::default();
+        let response = route(
+            "POST",
+            "/validate?format=json",
+            "{\"a\": 1}",
+            None,
+            &options,
+        );
+        let value: JsonValue = serde_json::from_str(&response.body).unwrap();
+        assert_eq!(value["valid"], true);
+
+        let response = route(
+            "POST",
+            "/validate?schema=../secret",
+            "{}",
+            Some(Path::new(".")),
+            &options,
+        );
+        assert_eq!(response.status, 400);
+
+        assert_eq!(route("GET", "/convert", "", None, &options).status, 405);
+        assert_eq!(route("GET", "/nope", "", None, &options).status, 404);
+    }
+
+    #[test]
+    fn test_read_body_limit() {
+        assert_eq!(read_body(&b"{}"[..]).unwrap(), "{}");
+        let oversized = std::io::repeat(b' ').take(MAX_BODY_SIZE + 1);
+        assert_eq!(read_body(oversized).unwrap_err().status, 413);
+        assert_eq!(read_body(&[0xff][..]).unwrap_err().status, 400);
+    }
+}
@@ -0,0 +1,29 @@
+//! Table subcommand implementation
+
+use anyhow::{Context, Result};
+
+use crate::cli::args::TableArgs;
+use crate::cli::globals::GlobalOptions;
+use crate::cli::output::write_output;
+use crate::core::{converter, table};
+use crate::formats::csv as csv_format;
+use crate::formats::detect::detect;
+use crate::utils::{highlight, width};
+
+/// Execute the table subcommand
+pub fn execute(args: TableArgs, globals: &GlobalOptions) -> Result<()> {
+    let content = globals.encoding.read_input(args.input.as_deref())?;
+    let format =
+        detect(args.input.as_deref(), &content).context("Could not detect input format")?;
+    let value = converter::parse_to_json_value(&content, format, globals.yaml_version)?;
+
+    let mut data = table::records_to_table(&value, &args.columns, args.max_width)?;
+    if let Some(terminal_width) = width::terminal_width() {
+        table::fit_to_width(&mut data, terminal_width);
+    }
+    let output = csv_format::to_table(&data)?;
+
+    write_output(&highlight::highlight_csv(&output, false), &globals.encoding)?;
+
+    Ok(())
+}
@@ -1,20 +1,28 @@
 //! Template subcommand implementation
 
 use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::HashSet;
 use std::fs;
-use std::io::{self, Read};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 use crate::cli::args::TemplateArgs;
+use crate::cli::globals::GlobalOptions;
 use crate::cli::output::write_output;
-use crate::core::template::{self, TemplateOptions};
+use crate::cli::porcelain;
+use crate::core::converter;
+use crate::core::template::{self, EnvFilter, MissingVariable, TemplateOptions};
 use crate::formats::detect::{detect, Format};
+use crate::formats::json as json_format;
+use crate::formats::yaml as yaml_format;
+use crate::formats::OutputStyle;
+use crate::utils::diagnostics::SourceContext;
 use crate::utils::highlight;
 
 /// Execute the template subcommand
-pub fn execute(args: TemplateArgs) -> Result<()> {
+pub fn execute(args: TemplateArgs, globals: &GlobalOptions) -> Result<()> {
     // Read template
-    let template_content = read_input(args.template.as_deref())?;
+    let template_content = globals.encoding.read_input(args.template.as_deref())?;
 
     // Detect template format
     let template_format = detect(args.template.as_deref(), &template_content)
@@ -22,10 +30,11 @@ pub fn execute(args: TemplateArgs) -> Result<()> {
 
     // Parse template as JSON value for processing
     let template_value: serde_json::Value = match template_format {
-        Format::Json => serde_json::from_str(&template_content)
-            .context("Template must be valid JSON")?,
+        Format::Json => {
+            json_format::parse(&template_content).context("Template must be valid JSON")?
+        }
         Format::Yaml => {
-            let yaml: serde_yaml::Value = serde_yaml::from_str(&template_content)
+            let yaml = yaml_format::parse(&template_content, globals.yaml_version)
                 .context("Template must be valid YAML")?;
             serde_json::to_value(yaml)?
         }
@@ -36,25 +45,45 @@ pub fn execute(args: TemplateArgs) -> Result<()> {
     let mut vars = serde_json::Map::new();
 
     // Add environment variables if requested
-    if args.env {
-        if let serde_json::Value::Object(env_vars) = template::env_to_json() {
+    if args.env || args.env_prefix.is_some() {
+        let filter = EnvFilter {
+            prefix: args.env_prefix.clone(),
+            strip_prefix: args.env_strip_prefix,
+            lowercase: args.env_lowercase,
+        };
+        if let serde_json::Value::Object(env_vars) = template::env_to_json_filtered(&filter) {
             for (k, v) in env_vars {
                 vars.insert(k, v);
             }
         }
     }
 
-    // Load variables from file
-    if let Some(ref vars_path) = args.vars {
-        let vars_content = fs::read_to_string(vars_path)
+    // Load variables from file; with --each-row it holds the rows instead
+    let mut rows = None;
+    if let (Some(vars_path), true) = (&args.vars, args.each_row) {
+        let content = globals
+            .encoding
+            .read_file(vars_path)
+            .with_context(|| format!("Failed to read vars file: {}", vars_path.display()))?;
+        let format = detect(Some(vars_path.as_path()), &content)
+            .context("Could not detect vars file format")?;
+        rows = Some(
+            converter::parse_to_json_value(&content, format, globals.yaml_version)
+                .in_source(Some(vars_path))?,
+        );
+    } else if let Some(ref vars_path) = args.vars {
+        let vars_content = globals
+            .encoding
+            .read_file(vars_path)
             .with_context(|| format!("Failed to read vars file: {}", vars_path.display()))?;
         let vars_format = detect(Some(vars_path.as_path()), &vars_content)
             .context("Could not detect vars file format")?;
 
         let file_vars: serde_json::Value = match vars_format {
-            Format::Json => serde_json::from_str(&vars_content)?,
+            Format::Json => json_format::parse(&vars_content).in_source(Some(vars_path))?,
             Format::Yaml => {
-                let yaml: serde_yaml::Value = serde_yaml::from_str(&vars_content)?;
+                let yaml = yaml_format::parse(&vars_content, globals.yaml_version)
+                    .in_source(Some(vars_path))?;
                 serde_json::to_value(yaml)?
             }
             _ => anyhow::bail!("Variables file must be JSON or YAML"),
@@ -90,40 +119,135 @@ pub fn execute(args: TemplateArgs) -> Result<()> {
         ..Default::default()
     };
 
+    // One variable set per output file with --each, or per row with --each-row
+    let var_sets = match (&args.each, &rows) {
+        (Some(path), _) => template::each_vars(&vars_value, path)?,
+        (None, Some(rows)) => {
+            template::row_vars(&vars_value, rows).context("--each-row needs a vars file of rows")?
+        }
+        (None, None) => vec![vars_value],
+    };
+
     // Validate template if requested
     if args.validate {
-        let missing = template::validate_template(&template_value, &vars_value, &options)?;
-        if missing.is_empty() {
+        let mut missing: Vec<String> = Vec::new();
+        for vars in &var_sets {
+            for var in template::validate_template(&template_value, vars, &options)? {
+                if !missing.contains(&var) {
+                    missing.push(var);
+                }
+            }
+        }
+        if globals.porcelain.is_some() {
+            for var in &missing {
+                porcelain::record(globals, "missing", &[("variable", json!(var))])?;
+            }
+        } else if missing.is_empty() {
             eprintln!("Template validation passed. All variables are defined.");
-            return Ok(());
         } else {
             eprintln!("Missing variables:");
             for var in &missing {
                 eprintln!("  - {}", var);
             }
+        }
+        if !missing.is_empty() {
             std::process::exit(1);
         }
+        return Ok(());
+    }
+
+    let path_template = args
+        .output
+        .as_deref()
+        .map(|p| p.to_string_lossy().into_owned());
+    let per_file = path_template
+        .as_deref()
+        .is_some_and(|p| !template::extract_variables(p, &options).is_empty());
+    if args.each_row && !per_file {
+        return render_concat(
+            &args,
+            &template_value,
+            &template_content,
+            template_format,
+            &var_sets,
+            &options,
+            globals,
+        );
+    }
+
+    if args.each.is_some() || args.each_row {
+        return render_each(
+            &args,
+            &template_value,
+            &template_content,
+            template_format,
+            &var_sets,
+            &options,
+            globals,
+        );
     }
 
     // Render template
-    let rendered = template::render_value(&template_value, &vars_value, &options)?;
+    let rendered = template::render_value(&template_value, &var_sets[0], &options)
+        .map_err(|e| locate_error(e, &template_content, args.template.as_deref(), false))?;
 
     // Format output
-    let output_format = if let Some(ref fmt) = args.format {
-        parse_format(fmt)?
-    } else if let Some(ref output_path) = args.output {
-        detect(Some(output_path.as_path()), "").unwrap_or(template_format)
-    } else {
-        template_format
+    let output_format = output_format(&args, args.output.as_deref(), template_format)?;
+    let output = format_output(&rendered, output_format, &globals.style)?;
+
+    write_rendered(&args, output, output_format, globals)
+}
+
+/// Render once per variable set and join the documents with --separator lines
+fn render_concat(
+    args: &TemplateArgs,
+    template_value: &serde_json::Value,
+    template_content: &str,
+    template_format: Format,
+    var_sets: &[serde_json::Value],
+    options: &TemplateOptions,
+    globals: &GlobalOptions,
+) -> Result<()> {
+    let output_format = output_format(args, args.output.as_deref(), template_format)?;
+    let separator = match (&args.separator, output_format) {
+        (Some(separator), _) => separator.as_str(),
+        (None, Format::Yaml) => "---",
+        (None, _) => "",
     };
 
-    let output = format_output(&rendered, output_format)?;
+    let documents = var_sets
+        .iter()
+        .map(|vars| {
+            let rendered = template::render_value(template_value, vars, options)
+                .map_err(|e| locate_error(e, template_content, args.template.as_deref(), false))?;
+            Ok(format_output(&rendered, output_format, &globals.style)?
+                .trim_end()
+                .to_string())
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut output = documents.join(&format!("\n{}\n", separator));
+    if output_format == Format::Yaml {
+        output.push('\n');
+    }
+
+    write_rendered(args, output, output_format, globals)
+}
 
-    // Write output
+/// Write rendered output to --output, or highlighted to stdout
+fn write_rendered(
+    args: &TemplateArgs,
+    output: String,
+    output_format: Format,
+    globals: &GlobalOptions,
+) -> Result<()> {
     if let Some(ref output_path) = args.output {
-        fs::write(output_path, &output)
-            .with_context(|| format!("Failed to write to {}", output_path.display()))?;
-        if !args.quiet {
+        globals.encoding.write_file(output_path, &output)?;
+        porcelain::record(
+            globals,
+            "rendered",
+            &[("output", json!(output_path.display().to_string()))],
+        )?;
+        if !args.quiet && globals.porcelain.is_none() {
             eprintln!("Rendered template written to {}", output_path.display());
         }
     } else {
@@ -132,28 +256,103 @@ pub fn execute(args: TemplateArgs) -> Result<()> {
         } else {
             match output_format {
                 Format::Json => highlight::highlight_json(&output),
-                Format::Yaml => highlight::highlight_yaml(&output),
+                Format::Yaml => highlight::highlight_yaml(&output, globals.yaml_version),
                 _ => output.clone(),
             }
         };
-        write_output(&highlighted)?;
+        write_output(&highlighted, &globals.encoding)?;
     }
 
     Ok(())
 }
 
-fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
-        Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
+/// Render one file per variable set, naming each from the --output path template
+fn render_each(
+    args: &TemplateArgs,
+    template_value: &serde_json::Value,
+    template_content: &str,
+    template_format: Format,
+    var_sets: &[serde_json::Value],
+    options: &TemplateOptions,
+    globals: &GlobalOptions,
+) -> Result<()> {
+    let path_template = args
+        .output
+        .as_deref()
+        .context("--each requires --output")?
+        .to_string_lossy()
+        .into_owned();
+    let path_options = TemplateOptions {
+        strict: true,
+        ..options.clone()
+    };
+
+    let mut written = HashSet::new();
+    for vars in var_sets {
+        let output_path = PathBuf::from(
+            template::render_string(&path_template, vars, &path_options)
+                .map_err(|e| locate_error(e, &path_template, None, true))
+                .context("Failed to render --output path")?,
+        );
+        if !written.insert(output_path.clone()) {
+            anyhow::bail!(
+                "Several elements render to {}; include a distinguishing variable in --output",
+                output_path.display()
+            );
         }
-        None => {
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
+
+        let rendered = template::render_value(template_value, vars, options)
+            .map_err(|e| locate_error(e, template_content, args.template.as_deref(), false))?;
+        let output_format = output_format(args, Some(&output_path), template_format)?;
+        let output = format_output(&rendered, output_format, &globals.style)?;
+
+        if let Some(parent) = output_path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
         }
+        globals.encoding.write_file(&output_path, &output)?;
+        porcelain::record(
+            globals,
+            "rendered",
+            &[("output", json!(output_path.display().to_string()))],
+        )?;
+    }
+
+    if !args.quiet && globals.porcelain.is_none() {
+        eprintln!("Rendered {} files from template", written.len());
+    }
+
+    Ok(())
+}
+
+/// Point a strict-mode missing variable at its placeholder in the template source
+fn locate_error(
+    err: anyhow::Error,
+    source: &str,
+    file: Option<&Path>,
+    at_offset: bool,
+) -> anyhow::Error {
+    match err.downcast::<MissingVariable>() {
+        Ok(missing) => {
+            let file = file.map(|p| p.display().to_string());
+            missing.locate(source, file, at_offset).into()
+        }
+        Err(err) => err,
+    }
+}
+
+/// Output format from --format, the output extension, or the template itself
+fn output_format(
+    args: &TemplateArgs,
+    output_path: Option<&Path>,
+    template_format: Format,
+) -> Result<Format> {
+    if let Some(ref fmt) = args.format {
+        parse_format(fmt)
+    } else if let Some(path) = output_path {
+        Ok(detect(Some(path), "").unwrap_or(template_format))
+    } else {
+        Ok(template_format)
     }
 }
 
@@ -165,11 +364,10 @@ fn parse_format(s: &str) -> Result<Format> {
     }
 }
 
-fn format_output(value: &serde_json::Value, format: Format) -> Result<String> {
+fn format_output(value: &serde_json::Value, format: Format, style: &OutputStyle) -> Result<String> {
     match format {
-        Format::Json => serde_json::to_string_pretty(value).context("Failed to serialize JSON"),
         Format::Yaml => serde_yaml::to_string(value).context("Failed to serialize YAML"),
-        _ => serde_json::to_string_pretty(value).context("Failed to serialize"),
+        _ => json_format::to_pretty(value, style),
     }
 }
 
@@ -3,23 +3,36 @@
 use anyhow::Result;
 
 use crate::cli::args::TomlArgs;
-use crate::cli::output::write_output;
-use crate::formats::toml as toml_format;
-use crate::utils::highlight;
+use crate::cli::globals::GlobalOptions;
+use crate::cli::output::{check_formatted, write_output};
+use crate::formats::toml::{self as toml_format, TomlLayout};
+use crate::utils::{highlight, html};
 
 /// Execute the toml subcommand
-pub fn execute(args: TomlArgs) -> Result<()> {
-    let content = toml_format::read_input(args.input.as_deref())?;
+pub fn execute(args: TomlArgs, globals: &GlobalOptions) -> Result<()> {
+    let content = toml_format::read_input(args.input.as_deref(), &globals.encoding)?;
     let value = toml_format::parse(&content)?;
 
     let output = if args.compact {
         toml_format::to_compact(&value)?
     } else {
-        toml_format::to_pretty(&value)?
+        let layout = TomlLayout {
+            inline_depth: args.inline_depth,
+            inline_arrays: args.inline_arrays,
+        };
+        toml_format::to_pretty_with_layout(&value, &layout, &globals.style)?
     };
 
-    let highlighted = highlight::highlight_toml(&output);
-    write_output(&highlighted)?;
+    if args.check {
+        return check_formatted(args.input.as_deref(), &content, &output);
+    }
+
+    let highlighted = if args.html {
+        html::highlighted_html(&output, highlight::highlight_toml)
+    } else {
+        highlight::highlight_toml(&output)
+    };
+    write_output(&highlighted, &globals.encoding)?;
 
     Ok(())
 }
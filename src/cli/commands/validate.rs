@@ -1,76 +1,138 @@
 //! Validate subcommand implementation
 
 use anyhow::{Context, Result};
-use std::fs;
-use std::io::{self, Read};
-use std::path::Path;
+use colored::Colorize;
+use std::path::{Path, PathBuf};
 
 use crate::cli::args::ValidateArgs;
+use crate::cli::globals::GlobalOptions;
 use crate::cli::output::write_output;
-use crate::core::validator;
+use crate::cli::progress::Progress;
+use crate::core::validator::ValidationResult;
+use crate::core::{converter, kubernetes, openapi, validator};
 use crate::formats::detect::{detect, Format};
+use crate::formats::frontmatter;
+use crate::formats::yaml::YamlVersion;
+use crate::formats::{json as json_format, yaml as yaml_format};
+use crate::utils::diagnostics::SourceContext;
+use crate::utils::paths;
 
 /// Execute the validate subcommand
-pub fn execute(args: ValidateArgs) -> Result<()> {
+pub fn execute(args: ValidateArgs, globals: &GlobalOptions) -> Result<()> {
+    let inputs: Vec<Option<PathBuf>> = match args.input {
+        Some(ref input) => paths::expand_inputs(std::slice::from_ref(input))?
+            .into_iter()
+            .map(Some)
+            .collect(),
+        None => vec![None],
+    };
+    if let [input] = &inputs[..] {
+        let valid = validate_and_report(&args, input.as_deref(), false, globals)?;
+        if !valid {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // Every file matched by a pattern is checked, even after one fails
+    let (mut invalid, mut failed) = (0, 0);
+    for input in inputs.iter().flatten() {
+        match validate_and_report(&args, Some(input), true, globals) {
+            Ok(true) => {}
+            Ok(false) => invalid += 1,
+            Err(err) => {
+                failed += 1;
+                eprintln!("{} {}: {:#}", "Failed:".red(), input.display(), err);
+            }
+        }
+    }
+    eprintln!(
+        "{} {} valid, {} invalid, {} failed ({} file(s) total)",
+        "Summary:".bold(),
+        inputs.len() - invalid - failed,
+        invalid,
+        failed,
+        inputs.len()
+    );
+    if invalid + failed > 0 {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+/// Validate one input and print its result, headed by the file name when
+/// `named`, returning whether it is valid
+fn validate_and_report(
+    args: &ValidateArgs,
+    input: Option<&Path>,
+    named: bool,
+    globals: &GlobalOptions,
+) -> Result<bool> {
+    let result = validate(args, input, globals).in_source(input)?;
+    let subject = input.map_or("stdin".into(), |path| path.display().to_string());
+    let output = match args.report.as_deref() {
+        Some("md") => result.format_markdown(&subject),
+        _ if named => format!("{}\n{}", subject.bold(), result.format_output()),
+        _ => result.format_output(),
+    };
+    write_output(&output, &globals.encoding)?;
+    Ok(result.valid)
+}
+
+fn validate(
+    args: &ValidateArgs,
+    input: Option<&Path>,
+    globals: &GlobalOptions,
+) -> Result<ValidationResult> {
     // Read input
-    let content = read_input(args.input.as_deref())?;
+    let progress = Progress::start("Validating", input, globals);
+    let mut content = globals.encoding.read_input(input)?;
+    let mut front_matter_format = None;
+    if args.front_matter || frontmatter::is_markdown(input) {
+        let (data, format) = frontmatter::extract(&content)?;
+        content = data;
+        front_matter_format = Some(format);
+    }
 
     // Detect format
-    let format = if let Some(ref fmt) = args.format {
+    let format = if let Some(format) = front_matter_format {
+        format
+    } else if let Some(ref fmt) = args.format {
         parse_format(fmt)?
     } else {
-        detect(args.input.as_deref(), &content)
-            .context("Could not detect format. Use --format to specify.")?
+        detect(input, &content).context("Could not detect format. Use --format to specify.")?
     };
 
-    let result = if let Some(ref schema_path) = args.schema {
+    let mut result = if let Some(ref version) = args.kubernetes {
+        let docs = match format {
+            Format::Json | Format::Yaml => kubernetes::parse_manifests(&content)?,
+            _ => anyhow::bail!("Kubernetes validation only supports JSON and YAML"),
+        };
+        kubernetes::validate_manifests(&docs, version, args.kubernetes_schemas.as_deref())?
+    } else if args.openapi {
+        let doc = parse_to_json(&content, format, globals.yaml_version)?;
+        openapi::validate_openapi(&doc)?
+    } else if let Some(ref schema_source) = args.schema {
         // Validate against JSON Schema
-        let schema_content = fs::read_to_string(schema_path)
-            .with_context(|| format!("Failed to read schema file: {}", schema_path.display()))?;
-        let schema: serde_json::Value = serde_json::from_str(&schema_content)
-            .context("Failed to parse schema as JSON")?;
-        let data: serde_json::Value = parse_to_json(&content, format)?;
-        validator::validate_json_schema(&data, &schema)?
+        let schema = validator::load_schema(schema_source, args.offline)?;
+        if format == Format::Ndjson {
+            validator::validate_ndjson_schema(&content, &schema)?
+        } else {
+            let data: serde_json::Value = parse_to_json(&content, format, globals.yaml_version)?;
+            let mut result = validator::validate_json_schema(&data, &schema)?;
+            // Front matter starts below its opening delimiter
+            let line_offset = usize::from(front_matter_format.is_some());
+            result.locate_errors(&content, format, line_offset);
+            result
+        }
     } else {
         // Lint the format
-        match format {
-            Format::Json => validator::lint_json(&content)?,
-            Format::Yaml => validator::lint_yaml(&content)?,
-            Format::Toml => validator::lint_toml(&content)?,
-            Format::Csv => validator::validate_csv(&content, !args.no_headers)?,
-            Format::Xml => {
-                // For XML, just validate it can be parsed
-                crate::formats::xml::validate(&content)?;
-                let mut result = validator::ValidationResult::new();
-                result.valid = true;
-                result
-            }
-        }
+        validator::lint(&content, format, !args.no_headers)?
     };
 
-    let output = result.format_output();
-    write_output(&output)?;
-
-    if !result.valid {
-        std::process::exit(1);
-    }
-
-    Ok(())
-}
-
-fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
-        Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
-        }
-        None => {
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
-        }
-    }
+    drop(progress);
+    result.source = Some(input.map_or("<stdin>".into(), |path| path.display().to_string()));
+    Ok(result)
 }
 
 fn parse_format(s: &str) -> Result<Format> {
@@ -79,21 +141,23 @@ fn parse_format(s: &str) -> Result<Format> {
         "yaml" | "yml" => Ok(Format::Yaml),
         "toml" => Ok(Format::Toml),
         "csv" => Ok(Format::Csv),
+        "tsv" => Ok(Format::Tsv),
         "xml" => Ok(Format::Xml),
+        "ndjson" | "jsonl" => Ok(Format::Ndjson),
         _ => anyhow::bail!("Unknown format: {}", s),
     }
 }
 
-fn parse_to_json(content: &str, format: Format) -> Result<serde_json::Value> {
+fn parse_to_json(content: &str, format: Format, version: YamlVersion) -> Result<serde_json::Value> {
     match format {
-        Format::Json => serde_json::from_str(content).context("Failed to parse JSON"),
+        Format::Json => json_format::parse(content),
         Format::Yaml => {
-            let yaml: serde_yaml::Value =
-                serde_yaml::from_str(content).context("Failed to parse YAML")?;
+            let yaml = yaml_format::parse(content, version)?;
             let json_str = serde_json::to_string(&yaml)?;
             serde_json::from_str(&json_str).context("Failed to convert YAML to JSON")
         }
-        _ => anyhow::bail!("Schema validation only supports JSON and YAML"),
+        Format::Toml => converter::parse_to_json_value(content, format, version),
+        _ => anyhow::bail!("Schema validation only supports JSON, YAML and TOML"),
     }
 }
 
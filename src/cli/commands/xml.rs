@@ -3,13 +3,14 @@
 use anyhow::Result;
 
 use crate::cli::args::XmlArgs;
+use crate::cli::globals::GlobalOptions;
 use crate::cli::output::write_output;
 use crate::formats::xml as xml_format;
-use crate::utils::highlight;
+use crate::utils::{highlight, html};
 
 /// Execute the xml subcommand
-pub fn execute(args: XmlArgs) -> Result<()> {
-    let content = xml_format::read_input(args.input.as_deref())?;
+pub fn execute(args: XmlArgs, globals: &GlobalOptions) -> Result<()> {
+    let content = xml_format::read_input(args.input.as_deref(), &globals.encoding)?;
 
     // Validate XML first
     xml_format::validate(&content)?;
@@ -17,11 +18,15 @@ pub fn execute(args: XmlArgs) -> Result<()> {
     let output = if args.compact {
         xml_format::to_compact(&content)?
     } else {
-        xml_format::to_pretty(&content)?
+        xml_format::to_pretty(&content, &globals.style)?
     };
 
-    let highlighted = highlight::highlight_xml(&output);
-    write_output(&highlighted)?;
+    let highlighted = if args.html {
+        html::highlighted_html(&output, highlight::highlight_xml)
+    } else {
+        highlight::highlight_xml(&output)
+    };
+    write_output(&highlighted, &globals.encoding)?;
 
     Ok(())
 }
@@ -3,18 +3,29 @@
 use anyhow::Result;
 
 use crate::cli::args::YamlArgs;
-use crate::cli::output::write_output;
+use crate::cli::globals::GlobalOptions;
+use crate::cli::output::{check_formatted, write_output};
 use crate::formats::yaml as yaml_format;
-use crate::utils::highlight;
+use crate::utils::{highlight, html};
 
 /// Execute the yaml subcommand
-pub fn execute(args: YamlArgs) -> Result<()> {
-    let content = yaml_format::read_input(args.input.as_deref())?;
-    let value = yaml_format::parse(&content)?;
-    let output = yaml_format::to_pretty(&value)?;
+pub fn execute(args: YamlArgs, globals: &GlobalOptions) -> Result<()> {
+    let content = yaml_format::read_input(args.input.as_deref(), &globals.encoding)?;
+    let value = yaml_format::parse(&content, globals.yaml_version)?;
+    let output = yaml_format::to_pretty(&value, &globals.style)?;
 
-    let highlighted = highlight::highlight_yaml(&output);
-    write_output(&highlighted)?;
+    if args.check {
+        return check_formatted(args.input.as_deref(), &content, &output);
+    }
+
+    let highlighted = if args.html {
+        html::highlighted_html(&output, |yaml| {
+            highlight::highlight_yaml(yaml, globals.yaml_version)
+        })
+    } else {
+        highlight::highlight_yaml(&output, globals.yaml_version)
+    };
+    write_output(&highlighted, &globals.encoding)?;
 
     Ok(())
 }
@@ -0,0 +1,157 @@
+//! User and project configuration files
+//!
+//! Settings are read from `$DTX_CONFIG`, `$XDG_CONFIG_HOME/dtx/config.toml` or
+//! `~/.config/dtx/config.toml`, then from the nearest `.dtx.toml` in the current
+//! directory or its ancestors, which takes precedence.
+
+use anyhow::{bail, Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the per-project config file
+pub const PROJECT_CONFIG_FILE: &str = ".dtx.toml";
+
+/// dtx configuration
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Named query pipelines, invoked as `dtx query FILE @name`
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl Config {
+    /// Load the user config overlaid with the nearest project config
+    pub fn load() -> Result<Self> {
+        let mut config = Config::default();
+        let project = std::env::current_dir()
+            .ok()
+            .and_then(|dir| find_project_config(&dir));
+        for path in user_config_path().into_iter().chain(project) {
+            if path.is_file() {
+                config.extend(Self::from_file(&path)?);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Parse a single config file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        toml::from_str(&content).with_context(|| format!("Invalid config file: {}", path.display()))
+    }
+
+    fn extend(&mut self, other: Config) {
+        self.aliases.extend(other.aliases);
+    }
+
+    /// Replace `@name` arguments of the query subcommand with their alias definitions
+    pub fn expand_aliases(&self, args: Vec<OsString>) -> Result<Vec<OsString>> {
+        let Some(query_pos) = args.iter().position(|arg| arg == "query") else {
+            return Ok(args);
+        };
+
+        let mut expanded = Vec::with_capacity(args.len());
+        for (i, arg) in args.into_iter().enumerate() {
+            let name = match arg.to_str().and_then(|s| s.strip_prefix('@')) {
+                Some(name) if i > query_pos && !name.is_empty() => name.to_string(),
+                _ => {
+                    expanded.push(arg);
+                    continue;
+                }
+            };
+
+            let Some(definition) = self.aliases.get(&name) else {
+                let known: Vec<&str> = self.aliases.keys().map(String::as_str).collect();
+                if known.is_empty() {
+                    bail!(
+                        "Unknown query alias '@{}' (no aliases defined; add an [aliases] table to {})",
+                        name,
+                        PROJECT_CONFIG_FILE
+                    );
+                }
+                bail!(
+                    "Unknown query alias '@{}'. Defined aliases: {}",
+                    name,
+                    known.join(", ")
+                );
+            };
+            let words = shell_words::split(definition)
+                .with_context(|| format!("Invalid definition of alias '@{}'", name))?;
+            expanded.extend(words.into_iter().map(OsString::from));
+        }
+        Ok(expanded)
+    }
+}
+
+/// User-level config file location
+fn user_config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("DTX_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir).join("dtx").join("config.toml"));
+    }
+    std::env::var_os("HOME").map(|home| {
+        PathBuf::from(home)
+            .join(".config")
+            .join("dtx")
+            .join("config.toml")
+    })
+}
+
+/// Nearest `.dtx.toml` in `dir` or its ancestors
+fn find_project_config(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|d| d.join(PROJECT_CONFIG_FILE))
+        .find(|p| p.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<OsString> {
+        list.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let config: Config = toml::from_str(
+            r#"
+[aliases]
+top-errors = "--filter 'level == \"error\"' --first 10"
+"#,
+        )
+        .unwrap();
+
+        let expanded = config
+            .expand_aliases(args(&["dtx", "query", "logs.json", "@top-errors", "--raw"]))
+            .unwrap();
+        assert_eq!(
+            expanded,
+            args(&[
+                "dtx",
+                "query",
+                "logs.json",
+                "--filter",
+                "level == \"error\"",
+                "--first",
+                "10",
+                "--raw"
+            ])
+        );
+
+        let untouched = args(&["dtx", "convert", "@file.json", "--to", "yaml"]);
+        assert_eq!(config.expand_aliases(untouched.clone()).unwrap(), untouched);
+
+        let err = config
+            .expand_aliases(args(&["dtx", "query", "@missing"]))
+            .unwrap_err();
+        assert!(err.to_string().contains("top-errors"));
+    }
+}
@@ -0,0 +1,70 @@
+//! Settings of the global flags, passed to the commands that use them
+
+use anyhow::Result;
+use std::sync::Arc;
+
+use crate::cli::args::{Cli, PorcelainFormat};
+use crate::core::converter::ConvertOptions;
+use crate::formats::float::FloatFormat;
+use crate::formats::indent::Indent;
+use crate::formats::yaml::YamlVersion;
+use crate::formats::OutputStyle;
+use crate::utils::encoding::{Encoding, EncodingOptions, LineEnding};
+use crate::utils::timing::Timings;
+
+/// Settings selected with the global flags
+#[derive(Debug, Clone, Default)]
+pub struct GlobalOptions {
+    /// Indentation of JSON, XML and TOML output and the text of floats
+    pub style: OutputStyle,
+    /// YAML revision used to read YAML input and highlight YAML output
+    pub yaml_version: YamlVersion,
+    /// Record format selected with `--porcelain`; decorative messages are
+    /// suppressed when set
+    pub porcelain: Option<PorcelainFormat>,
+    /// Input and output encodings and the output line ending
+    pub encoding: EncodingOptions,
+    /// Phase durations, recorded when `--timing` is given
+    pub timings: Option<Arc<Timings>>,
+}
+
+impl GlobalOptions {
+    /// Read the global flags, failing on values they do not accept
+    pub fn from_cli(cli: &Cli) -> Result<Self> {
+        let indent = match (cli.tabs, cli.indent) {
+            (true, _) => Some(Indent::Tab),
+            (false, indent) => indent.map(Indent::Spaces),
+        };
+        let float =
+            (cli.float_precision.is_some() || cli.float_sci.is_some()).then_some(FloatFormat {
+                precision: cli.float_precision,
+                sci_threshold: cli.float_sci,
+                trailing_zeros: cli.float_trailing_zeros,
+            });
+        Ok(GlobalOptions {
+            style: OutputStyle { indent, float },
+            yaml_version: YamlVersion::from_name(&cli.yaml_version)?,
+            porcelain: cli.porcelain,
+            encoding: EncodingOptions::new(
+                parse_encoding(&cli.encoding)?,
+                parse_encoding(&cli.output_encoding)?,
+                cli.eol.as_deref().map(LineEnding::from_name).transpose()?,
+            ),
+            timings: cli.timing.then(|| Arc::new(Timings::new())),
+        })
+    }
+
+    /// Conversion options with these settings and nothing else set
+    pub fn convert_options(&self) -> ConvertOptions {
+        ConvertOptions {
+            style: self.style,
+            yaml_version: self.yaml_version,
+            timings: self.timings.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+fn parse_encoding(name: &Option<String>) -> Result<Option<Encoding>> {
+    name.as_deref().map(Encoding::from_name).transpose()
+}
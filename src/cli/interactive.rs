@@ -0,0 +1,255 @@
+//! Interactive query builder with a live preview
+//!
+//! The JSONPath and filter expressions are edited in a full-screen prompt on
+//! stderr; results are re-evaluated on every keystroke. Stdout is left untouched
+//! so the accepted query's output can still be redirected.
+
+use anyhow::Result;
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::style::{Print, Stylize};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{execute, queue};
+use serde_json::Value as JsonValue;
+use std::io::{self, Write};
+
+use crate::core::query;
+use crate::utils::highlight;
+
+/// Expressions accepted in the interactive prompt
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryExpressions {
+    pub path: String,
+    pub filter: String,
+}
+
+/// Apply a JSONPath query and filter expression, treating empty ones as no-ops
+pub fn evaluate(value: &JsonValue, expressions: &QueryExpressions) -> Result<JsonValue> {
+    let mut result = if expressions.path.trim().is_empty() {
+        value.clone()
+    } else {
+        query::jsonpath_query(value, expressions.path.trim())?
+    };
+    if !expressions.filter.trim().is_empty() {
+        result = query::filter_array(&result, expressions.filter.trim())?;
+    }
+    Ok(result)
+}
+
+/// Single-line text input with a cursor
+#[derive(Debug, Default)]
+struct LineEditor {
+    text: String,
+    /// Cursor position in characters
+    cursor: usize,
+}
+
+impl LineEditor {
+    fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            cursor: text.chars().count(),
+        }
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(i, _)| i)
+    }
+
+    /// Apply an editing key; returns whether the text changed
+    fn handle(&mut self, key: KeyCode) -> bool {
+        let len = self.text.chars().count();
+        match key {
+            KeyCode::Char(c) => {
+                let at = self.byte_index(self.cursor);
+                self.text.insert(at, c);
+                self.cursor += 1;
+                true
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                let at = self.byte_index(self.cursor);
+                self.text.remove(at);
+                true
+            }
+            KeyCode::Delete if self.cursor < len => {
+                let at = self.byte_index(self.cursor);
+                self.text.remove(at);
+                true
+            }
+            KeyCode::Left => {
+                self.cursor = self.cursor.saturating_sub(1);
+                false
+            }
+            KeyCode::Right => {
+                self.cursor = (self.cursor + 1).min(len);
+                false
+            }
+            KeyCode::Home => {
+                self.cursor = 0;
+                false
+            }
+            KeyCode::End => {
+                self.cursor = len;
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Run the interactive prompt; returns the accepted expressions, or `None` if cancelled
+pub fn run(value: &JsonValue, initial: &QueryExpressions) -> Result<Option<QueryExpressions>> {
+    let mut stderr = io::stderr();
+    terminal::enable_raw_mode()?;
+    execute!(stderr, EnterAlternateScreen)?;
+
+    let result = event_loop(&mut stderr, value, initial);
+
+    execute!(stderr, Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn event_loop(
+    out: &mut impl Write,
+    value: &JsonValue,
+    initial: &QueryExpressions,
+) -> Result<Option<QueryExpressions>> {
+    let mut fields = [
+        LineEditor::new(&initial.path),
+        LineEditor::new(&initial.filter),
+    ];
+    let mut active = 0;
+
+    loop {
+        let expressions = QueryExpressions {
+            path: fields[0].text.clone(),
+            filter: fields[1].text.clone(),
+        };
+        draw(out, value, &expressions, &fields, active)?;
+
+        let Event::Key(KeyEvent {
+            code,
+            modifiers,
+            kind: KeyEventKind::Press,
+            ..
+        }) = event::read()?
+        else {
+            continue;
+        };
+
+        match code {
+            KeyCode::Esc => return Ok(None),
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(None),
+            KeyCode::Enter => return Ok(Some(expressions)),
+            KeyCode::Tab | KeyCode::BackTab | KeyCode::Up | KeyCode::Down => {
+                active = 1 - active;
+            }
+            other => {
+                fields[active].handle(other);
+            }
+        }
+    }
+}
+
+fn draw(
+    out: &mut impl Write,
+    value: &JsonValue,
+    expressions: &QueryExpressions,
+    fields: &[LineEditor; 2],
+    active: usize,
+) -> Result<()> {
+    let (width, height) = terminal::size()?;
+    let width = width as usize;
+
+    queue!(out, Hide, MoveTo(0, 0), Clear(ClearType::All))?;
+    let labels = ["JSONPath", "Filter  "];
+    for (row, (label, field)) in labels.iter().zip(fields).enumerate() {
+        let marker = if row == active { ">" } else { " " };
+        queue!(
+            out,
+            MoveTo(0, row as u16),
+            Print(format!("{}{} ", marker, label).cyan()),
+            Print(&field.text)
+        )?;
+    }
+
+    let (status, preview) = match evaluate(value, expressions) {
+        Ok(result) => {
+            let count = match &result {
+                JsonValue::Array(items) => format!("{} results", items.len()),
+                _ => "1 result".to_string(),
+            };
+            let pretty = serde_json::to_string_pretty(&result)?;
+            (count.green(), pretty)
+        }
+        Err(e) => (format!("{:#}", e).red(), String::new()),
+    };
+    queue!(
+        out,
+        MoveTo(0, 2),
+        Print(status),
+        MoveTo(0, 3),
+        Print("-".repeat(width).dark_grey())
+    )?;
+
+    let preview_rows = (height as usize).saturating_sub(5);
+    for (i, line) in preview.lines().take(preview_rows).enumerate() {
+        let line: String = line.chars().take(width).collect();
+        queue!(
+            out,
+            MoveTo(0, 4 + i as u16),
+            Print(highlight::highlight_json(&line))
+        )?;
+    }
+
+    queue!(
+        out,
+        MoveTo(0, height.saturating_sub(1)),
+        Print("Tab: switch field  Enter: accept  Esc: cancel".dark_grey())
+    )?;
+
+    let cursor_column = 10 + fields[active].cursor;
+    queue!(out, MoveTo(cursor_column as u16, active as u16), Show)?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_evaluate() {
+        let data = json!({"users": [{"name": "a", "age": 30}, {"name": "b", "age": 20}]});
+        let mut expressions = QueryExpressions::default();
+        assert_eq!(evaluate(&data, &expressions).unwrap(), data);
+
+        expressions.path = "$.users".to_string();
+        expressions.filter = "age > 25".to_string();
+        assert_eq!(
+            evaluate(&data, &expressions).unwrap(),
+            json!([{"name": "a", "age": 30}])
+        );
+
+        expressions.path = "$.users[".to_string();
+        assert!(evaluate(&data, &expressions).is_err());
+    }
+
+    #[test]
+    fn test_line_editor() {
+        let mut editor = LineEditor::new("$.a");
+        editor.handle(KeyCode::Left);
+        editor.handle(KeyCode::Char('é'));
+        assert_eq!(editor.text, "$.éa");
+        editor.handle(KeyCode::Backspace);
+        editor.handle(KeyCode::Home);
+        editor.handle(KeyCode::Delete);
+        assert_eq!(editor.text, ".a");
+    }
+}
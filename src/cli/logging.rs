@@ -0,0 +1,27 @@
+//! Logging setup for verbose and structured diagnostics
+
+use tracing::Level;
+
+use crate::cli::args::LogFormat;
+
+/// Initialize the global tracing subscriber
+///
+/// Logs are written to stderr so they never mix with command output.
+pub fn init(verbose: u8, format: LogFormat) {
+    let level = match verbose {
+        0 => Level::WARN,
+        1 => Level::INFO,
+        2 => Level::DEBUG,
+        _ => Level::TRACE,
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .with_target(false);
+
+    match format {
+        LogFormat::Text => builder.without_time().init(),
+        LogFormat::Json => builder.json().init(),
+    }
+}
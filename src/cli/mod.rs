@@ -2,4 +2,10 @@
 
 pub mod args;
 pub mod commands;
+pub mod config;
+pub mod globals;
+pub mod interactive;
+pub mod logging;
 pub mod output;
+pub mod porcelain;
+pub mod progress;
@@ -1,11 +1,255 @@
 //! Output formatting utilities
 
-use std::io::{self, Write};
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard};
+
+use anyhow::Context;
+
+use serde_json::{json, Value as JsonValue};
+
+use crate::cli::args::ErrorFormat;
+use crate::core::differ;
+use crate::core::template::MissingVariable;
+use crate::utils::diagnostics;
+use crate::utils::encoding::{self, EncodingOptions};
+
+/// Output collected for the clipboard when `--clipboard` is active
+static CLIPBOARD: Capture = Capture::new();
+
+/// Output lines held back from stdout while capturing is on
+struct Capture(Mutex<Option<String>>);
+
+impl Capture {
+    const fn new() -> Self {
+        Capture(Mutex::new(None))
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<String>> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn start(&self) {
+        *self.lock() = Some(String::new());
+    }
+
+    fn is_active(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    /// Add a line of output, returning `false` when not capturing
+    fn push(&self, content: &str) -> bool {
+        let mut buffer = self.lock();
+        let Some(buffer) = buffer.as_mut() else {
+            return false;
+        };
+        buffer.push_str(content);
+        buffer.push('\n');
+        true
+    }
+
+    /// Stop capturing, returning the output without its trailing newlines
+    fn finish(&self) -> Option<String> {
+        let text = self.lock().take()?;
+        Some(text.trim_end_matches('\n').to_string())
+    }
+}
+
+/// Whether anything has been written to stdout through [`write_output`]
+static STDOUT_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Collect everything passed to [`write_output`] for the clipboard instead of stdout
+pub fn capture_for_clipboard() {
+    CLIPBOARD.start();
+}
+
+/// Place captured output on the system clipboard
+pub fn flush_clipboard() -> anyhow::Result<()> {
+    let Some(text) = CLIPBOARD.finish() else {
+        return Ok(());
+    };
+
+    let mut clipboard = arboard::Clipboard::new().context("Failed to access the clipboard")?;
+    clipboard
+        .set_text(text.as_str())
+        .context("Failed to copy output to the clipboard")?;
+    eprintln!("Copied {} lines to the clipboard", text.lines().count());
+    Ok(())
+}
+
+/// Write output to stdout in the output encoding
+pub fn write_output(content: &str, encodings: &EncodingOptions) -> io::Result<()> {
+    if CLIPBOARD.push(content) {
+        return Ok(());
+    }
 
-/// Write output to stdout
-pub fn write_output(content: &str) -> io::Result<()> {
     let stdout = io::stdout();
     let mut handle = stdout.lock();
-    writeln!(handle, "{}", content)?;
+    let line = format!("{}\n", content);
+    let line = encodings.with_line_endings(&line);
+    if let Some(output_encoding) = encodings.output_encoding() {
+        // Only the first write to stdout starts with a byte order mark
+        let bom = !STDOUT_STARTED.swap(true, Ordering::Relaxed);
+        let bytes = encoding::encode(&line, output_encoding, bom)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        return handle.write_all(&bytes);
+    }
+    handle.write_all(line.as_bytes())
+}
+
+/// Write binary output (e.g. MessagePack) to stdout unchanged
+///
+/// Refuses when stdout is a terminal or the clipboard, which would mangle the bytes.
+pub fn write_binary_output(content: &[u8]) -> io::Result<()> {
+    let stdout = io::stdout();
+    if CLIPBOARD.is_active() || stdout.is_terminal() {
+        return Err(io::Error::other(
+            "refusing to write binary output to a terminal; use --output or redirect stdout",
+        ));
+    }
+    stdout.lock().write_all(content)
+}
+
+/// Compare expected content with what exists, printing a diff when they differ
+///
+/// Returns `true` when `existing` is already up to date.
+pub fn check_content(label: &str, existing: &str, expected: &str) -> io::Result<bool> {
+    if existing == expected {
+        return Ok(true);
+    }
+    let diff = differ::text_diff(existing, expected, label, &format!("{} (expected)", label));
+    io::stdout().lock().write_all(diff.as_bytes())?;
+    Ok(false)
+}
+
+/// Verify the input already matches the formatted output, printing a diff and
+/// failing, so the process exits nonzero, when it does not
+pub fn check_formatted(path: Option<&Path>, content: &str, formatted: &str) -> anyhow::Result<()> {
+    let label = path.map_or_else(|| "<stdin>".to_string(), |p| p.display().to_string());
+    let expected = format!("{}\n", formatted.trim_end_matches('\n'));
+    if !check_content(&label, content, &expected)? {
+        anyhow::bail!("{} is not formatted", label);
+    }
     Ok(())
 }
+
+/// Write an error to stderr in the requested format
+pub fn write_error(err: &anyhow::Error, format: ErrorFormat, file: Option<&Path>) {
+    match format {
+        ErrorFormat::Text => {
+            eprintln!("Error: {:?}", err);
+            if let Some(diagnostic) = diagnostics::diagnose(err, file) {
+                eprintln!("\n{}", diagnostic);
+            }
+        }
+        ErrorFormat::Json => eprintln!("{}", error_to_json(err, file)),
+    }
+}
+
+/// Build a structured error object from an error chain
+pub fn error_to_json(err: &anyhow::Error, file: Option<&Path>) -> JsonValue {
+    let mut code = "error";
+    let mut location: Option<(usize, usize)> = None;
+
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<serde_json::Error>() {
+            code = "parse_error";
+            location = location.or(Some((e.line(), e.column())));
+        } else if let Some(e) = cause.downcast_ref::<serde_yaml::Error>() {
+            code = "parse_error";
+            location = location.or(e.location().map(|l| (l.line(), l.column())));
+        } else if let Some(e) = cause.downcast_ref::<csv::Error>() {
+            code = "parse_error";
+            location = location.or(e.position().map(|p| (p.line() as usize, 0)));
+        } else if let Some(e) = cause.downcast_ref::<MissingVariable>() {
+            code = "template_error";
+            location = location.or(e.location);
+        } else if cause.is::<toml::de::Error>() || cause.is::<quick_xml::Error>() {
+            code = "parse_error";
+        } else if cause.is::<io::Error>() && code == "error" {
+            code = "io_error";
+        }
+    }
+
+    let causes: Vec<String> = err.chain().skip(1).map(|c| c.to_string()).collect();
+    // The input that actually failed to parse, when it is known
+    let diagnostic = diagnostics::diagnose(err, file);
+    let file = match diagnostic {
+        Some(ref d) => Some(d.label.clone()),
+        None => file.map(|p| p.display().to_string()),
+    };
+    if let Some(ref d) = diagnostic {
+        code = "parse_error";
+        location = Some((d.span.line, d.span.column));
+    }
+
+    json!({
+        "code": code,
+        "message": err.to_string(),
+        "causes": causes,
+        "file": file,
+        "byte": diagnostic.map(|d| d.span.offset),
+        "line": location.map(|(line, _)| line),
+        "column": location.and_then(|(_, column)| (column > 0).then_some(column)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_error_to_json_parse_error() {
+        let err = serde_json::from_str::<JsonValue>("{\n  \"a\": }")
+            .context("Failed to parse JSON")
+            .unwrap_err();
+        let value = error_to_json(&err, Some(Path::new("data.json")));
+        assert_eq!(value["code"], "parse_error");
+        assert_eq!(value["message"], "Failed to parse JSON");
+        assert_eq!(value["file"], "data.json");
+        assert_eq!(value["line"], 2);
+    }
+
+    #[test]
+    fn test_check_formatted() {
+        let formatted = "{\n  \"a\": 1\n}";
+        assert!(check_formatted(None, "{\n  \"a\": 1\n}\n", formatted).is_ok());
+        let err = check_formatted(Some(Path::new("a.json")), "{\"a\":1}\n", formatted).unwrap_err();
+        assert_eq!(err.to_string(), "a.json is not formatted");
+    }
+
+    #[test]
+    fn test_capture() {
+        let capture = Capture::new();
+        assert!(!capture.is_active());
+        assert!(!capture.push("before"));
+
+        capture.start();
+        assert!(capture.is_active());
+        assert!(capture.push("{\n  \"a\": 1\n}"));
+        assert!(capture.push("second\n"));
+        assert_eq!(
+            capture.finish().as_deref(),
+            Some("{\n  \"a\": 1\n}\nsecond")
+        );
+
+        // Finishing hands output back to stdout
+        assert!(!capture.is_active());
+        assert!(!capture.push("after"));
+        assert_eq!(capture.finish(), None);
+
+        capture.start();
+        assert_eq!(capture.finish().as_deref(), Some(""));
+    }
+
+    #[test]
+    fn test_error_to_json_plain_error() {
+        let err = anyhow::anyhow!("Unknown format: foo");
+        let value = error_to_json(&err, None);
+        assert_eq!(value["code"], "error");
+        assert!(value["file"].is_null());
+        assert!(value["line"].is_null());
+    }
+}
@@ -0,0 +1,83 @@
+//! Stable status lines for scripts (`--porcelain`)
+//!
+//! Each line is an event name followed by its fields in a fixed order, either
+//! tab-separated or as a JSON object. Records go to stdout; commands that print
+//! their data to stdout emit no records for it.
+
+use std::io;
+
+use serde_json::Value as JsonValue;
+
+use crate::cli::args::PorcelainFormat;
+use crate::cli::globals::GlobalOptions;
+use crate::cli::output::write_output;
+
+/// Emit one status record; does nothing unless `--porcelain` is active
+pub fn record(
+    globals: &GlobalOptions,
+    event: &str,
+    fields: &[(&str, JsonValue)],
+) -> io::Result<()> {
+    let Some(format) = globals.porcelain else {
+        return Ok(());
+    };
+    write_output(&format_record(format, event, fields), &globals.encoding)
+}
+
+fn format_record(format: PorcelainFormat, event: &str, fields: &[(&str, JsonValue)]) -> String {
+    match format {
+        PorcelainFormat::Tsv => {
+            let mut line = event.to_string();
+            for (_, value) in fields {
+                line.push('\t');
+                line.push_str(&tsv_field(value));
+            }
+            line
+        }
+        PorcelainFormat::Json => {
+            // Built by hand to keep the documented field order
+            let mut parts = vec![format!("\"event\":{}", JsonValue::from(event))];
+            for (key, value) in fields {
+                parts.push(format!("{}:{}", JsonValue::from(*key), value));
+            }
+            format!("{{{}}}", parts.join(","))
+        }
+    }
+}
+
+/// A field value with tabs, newlines and backslashes escaped; null is empty
+fn tsv_field(value: &JsonValue) -> String {
+    let text = match value {
+        JsonValue::Null => return String::new(),
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_format_record() {
+        let fields = [
+            ("output", json!("out dir/a.yaml")),
+            ("format", json!("yaml")),
+            ("records", JsonValue::Null),
+            ("message", json!("bad\tvalue\n")),
+        ];
+        assert_eq!(
+            format_record(PorcelainFormat::Tsv, "converted", &fields),
+            "converted\tout dir/a.yaml\tyaml\t\tbad\\tvalue\\n"
+        );
+        assert_eq!(
+            format_record(PorcelainFormat::Json, "converted", &fields),
+            r#"{"event":"converted","output":"out dir/a.yaml","format":"yaml","records":null,"message":"bad\tvalue\n"}"#
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Progress indicator for long-running operations on large inputs
+//!
+//! The indicator is drawn on stderr, only when stderr is a terminal, the input
+//! is at least [`THRESHOLD`] bytes (or of unknown size), and the operation has
+//! been running for a moment, so quick runs never flash a status line.
+
+use std::io::{self, BufRead, IsTerminal, Read, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::cli::globals::GlobalOptions;
+
+/// Inputs smaller than this never show progress
+pub const THRESHOLD: u64 = 16 * 1024 * 1024;
+
+const DELAY: Duration = Duration::from_millis(750);
+const INTERVAL: Duration = Duration::from_millis(200);
+const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+
+struct State {
+    label: String,
+    total: Option<u64>,
+    bytes: AtomicU64,
+    records: AtomicU64,
+    counting: AtomicBool,
+    done: AtomicBool,
+    started: Instant,
+}
+
+/// Status line that refreshes in the background until dropped
+pub struct Progress {
+    state: Arc<State>,
+    ticker: Option<JoinHandle<()>>,
+}
+
+impl Progress {
+    /// Start an indicator for `input` (stdin when `None`), if it is worth showing;
+    /// never with `--porcelain`
+    pub fn start(label: &str, input: Option<&Path>, globals: &GlobalOptions) -> Option<Self> {
+        if !io::stderr().is_terminal() || globals.porcelain.is_some() {
+            return None;
+        }
+        let total = match input {
+            Some(path) => Some(std::fs::metadata(path).ok()?.len()),
+            None if io::stdin().is_terminal() => return None,
+            None => None,
+        };
+        if total.is_some_and(|size| size < THRESHOLD) {
+            return None;
+        }
+
+        let state = Arc::new(State {
+            label: label.to_string(),
+            total,
+            bytes: AtomicU64::new(0),
+            records: AtomicU64::new(0),
+            counting: AtomicBool::new(false),
+            done: AtomicBool::new(false),
+            started: Instant::now(),
+        });
+        let ticker_state = Arc::clone(&state);
+        let ticker = thread::spawn(move || tick(&ticker_state));
+        Some(Self {
+            state,
+            ticker: Some(ticker),
+        })
+    }
+
+    /// Wrap a reader so bytes and records (lines) read are reported
+    pub fn reader<R>(&self, inner: R) -> ProgressReader<R> {
+        self.state.counting.store(true, Ordering::Relaxed);
+        ProgressReader {
+            inner,
+            state: Arc::clone(&self.state),
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.state.done.store(true, Ordering::Relaxed);
+        if let Some(ticker) = self.ticker.take() {
+            ticker.thread().unpark();
+            let _ = ticker.join();
+        }
+    }
+}
+
+fn tick(state: &State) {
+    let mut drawn = false;
+    let mut frame = 0;
+    while !state.done.load(Ordering::Relaxed) {
+        thread::park_timeout(INTERVAL);
+        if state.done.load(Ordering::Relaxed) || state.started.elapsed() < DELAY {
+            continue;
+        }
+        let mut stderr = io::stderr().lock();
+        let _ = write!(
+            stderr,
+            "\r\x1b[2K{} {}",
+            SPINNER[frame % SPINNER.len()],
+            status_line(state)
+        );
+        let _ = stderr.flush();
+        drawn = true;
+        frame += 1;
+    }
+    if drawn {
+        let _ = write!(io::stderr().lock(), "\r\x1b[2K");
+    }
+}
+
+fn status_line(state: &State) -> String {
+    let elapsed = state.started.elapsed().as_secs_f64();
+    if !state.counting.load(Ordering::Relaxed) {
+        let size = state.total.map(|t| format!(" ({})", format_bytes(t)));
+        return format!(
+            "{}{} · {}",
+            state.label,
+            size.unwrap_or_default(),
+            format_duration(elapsed)
+        );
+    }
+
+    let bytes = state.bytes.load(Ordering::Relaxed);
+    let records = state.records.load(Ordering::Relaxed);
+    let rate = bytes as f64 / elapsed.max(0.001);
+    let mut line = match state.total {
+        Some(total) if total > 0 => format!(
+            "{} {} / {} ({:.0}%)",
+            state.label,
+            format_bytes(bytes),
+            format_bytes(total),
+            bytes as f64 * 100.0 / total as f64
+        ),
+        _ => format!("{} {}", state.label, format_bytes(bytes)),
+    };
+    line.push_str(&format!(
+        " · {}/s · {:.0} records/s",
+        format_bytes(rate as u64),
+        records as f64 / elapsed.max(0.001)
+    ));
+    if let Some(total) = state.total.filter(|&t| t > bytes && rate > 0.0) {
+        line.push_str(&format!(
+            " · ETA {}",
+            format_duration((total - bytes) as f64 / rate)
+        ));
+    }
+    line
+}
+
+/// Reader counting the bytes and lines consumed through it
+pub struct ProgressReader<R> {
+    inner: R,
+    state: Arc<State>,
+}
+
+fn count(state: &State, data: &[u8]) {
+    let lines = data.iter().filter(|&&b| b == b'\n').count();
+    state.bytes.fetch_add(data.len() as u64, Ordering::Relaxed);
+    state.records.fetch_add(lines as u64, Ordering::Relaxed);
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        count(&self.state, &buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for ProgressReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        if let Ok(buf) = self.inner.fill_buf() {
+            count(&self.state, &buf[..amt.min(buf.len())]);
+        }
+        self.inner.consume(amt);
+    }
+}
+
+/// Human-readable byte count (e.g. `12.5 MiB`)
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+fn format_duration(seconds: f64) -> String {
+    let total = seconds.round() as u64;
+    format!("{}:{:02}", total / 60, total % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(20 * 1024 * 1024), "20.0 MiB");
+        assert_eq!(format_duration(125.4), "2:05");
+    }
+}
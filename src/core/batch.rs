@@ -1,17 +1,24 @@
 //! Batch processing engine
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use colored::Colorize;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, info};
 
-use crate::core::converter;
+use crate::core::converter::{self, ConvertOptions};
 use crate::formats::detect::{detect, Format};
+use crate::formats::json as json_format;
+use crate::utils::encoding::EncodingOptions;
 
 /// Batch job configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BatchConfig {
     /// List of jobs to execute
     pub jobs: Vec<BatchJob>,
@@ -24,10 +31,21 @@ pub struct BatchConfig {
     /// Variables for template substitution
     #[serde(default)]
     pub variables: Option<JsonValue>,
+    /// Other config files whose jobs run first and whose variables act as defaults
+    /// (paths relative to this file)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+}
+
+impl BatchConfig {
+    /// Number of jobs a run executes, counting each matrix combination
+    pub fn job_count(&self) -> usize {
+        expand_jobs(self).len()
+    }
 }
 
 /// Individual batch job
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct BatchJob {
     /// Job name for logging
     pub name: String,
@@ -37,10 +55,38 @@ pub struct BatchJob {
     /// Condition to run this job (optional)
     #[serde(default)]
     pub condition: Option<String>,
+    /// Run the job once per combination of these values, exposed as variables
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub matrix: BTreeMap<String, Vec<JsonValue>>,
+    /// Hook run after the job succeeds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_success: Option<BatchHook>,
+    /// Hook run after the job fails
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub on_failure: Option<BatchHook>,
+    /// Directory the job's relative paths are resolved from (relative to the config file)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Environment for the job, available as `{{ env.NAME }}` and passed to hook commands
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+}
+
+/// Follow-up step run with the job result available as `job`, `status`,
+/// `message` and `duration_ms` variables
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(untagged)]
+pub enum BatchHook {
+    /// Shell command, run from the job directory; variables are substituted
+    /// shell-quoted and the result is also passed as DTX_JOB, DTX_STATUS,
+    /// DTX_MESSAGE and DTX_DURATION_MS
+    Command(String),
+    /// Another batch action
+    Action(BatchAction),
 }
 
 /// Batch action types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(tag = "action", rename_all = "lowercase")]
 pub enum BatchAction {
     /// Convert files between formats
@@ -57,6 +103,9 @@ pub enum BatchAction {
         output: String,
         #[serde(default)]
         strategy: Option<String>,
+        /// Remove keys set to null in a later input
+        #[serde(default)]
+        null_deletes: bool,
     },
     /// Validate files
     Validate {
@@ -81,49 +130,284 @@ pub struct BatchResult {
     pub success: bool,
     pub message: String,
     pub duration_ms: u128,
+    /// Directory the job ran from, when set with `cwd`
+    pub cwd: Option<PathBuf>,
+    /// Job environment, with variables rendered
+    pub env: BTreeMap<String, String>,
+    /// Skipped because its inputs, action and variables match the last successful run
+    pub cached: bool,
+}
+
+/// Fingerprints of jobs that last succeeded, for skipping unchanged jobs (`--cache`)
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BatchCache {
+    #[serde(skip)]
+    path: PathBuf,
+    /// Hash of each job's action, variables and input contents, by job name
+    #[serde(default)]
+    jobs: BTreeMap<String, String>,
+    /// Size, modification time and content hash of input files, so files whose
+    /// size and mtime are unchanged are not read again
+    #[serde(default)]
+    files: BTreeMap<String, FileStamp>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileStamp {
+    size: u64,
+    modified_ns: u64,
+    hash: String,
+}
+
+impl BatchCache {
+    /// Load the cache at `path`; a missing file is an empty cache
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut cache: BatchCache = match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content)
+                .with_context(|| format!("Invalid batch cache: {}", path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BatchCache::default(),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read batch cache: {}", path.display()))
+            }
+        };
+        cache.path = path.to_path_buf();
+        Ok(cache)
+    }
+
+    /// Write the cache back to the file it was loaded from
+    pub fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(&self.path, content + "\n")
+            .with_context(|| format!("Failed to write batch cache: {}", self.path.display()))
+    }
+
+    /// Hash of a job's action, directory, variables, environment, run settings
+    /// and input contents, or `None` when an input cannot be read (the job then
+    /// always runs)
+    fn fingerprint(
+        &mut self,
+        action: &BatchAction,
+        job_dir: &Path,
+        variables: &Option<JsonValue>,
+        env: &BTreeMap<String, String>,
+        settings: &str,
+    ) -> Option<String> {
+        let (inputs, _) = action_paths(action, job_dir, variables);
+        let mut key = serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "action": action,
+            "dir": job_dir,
+            "variables": variables,
+            "env": env,
+            "settings": settings,
+        })
+        .to_string();
+        for input in inputs {
+            let hash = self.file_hash(&input)?;
+            key.push_str(&format!("\n{}={}", input.display(), hash));
+        }
+        Some(format!("{:016x}", fnv1a(key.as_bytes())))
+    }
+
+    fn file_hash(&mut self, path: &Path) -> Option<String> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        let modified_ns = modified.as_nanos() as u64;
+        let name = path.display().to_string();
+        if let Some(stamp) = self.files.get(&name) {
+            if stamp.size == metadata.len() && stamp.modified_ns == modified_ns {
+                return Some(stamp.hash.clone());
+            }
+        }
+
+        let hash = format!("{:016x}", fnv1a(&fs::read(path).ok()?));
+        let stamp = FileStamp {
+            size: metadata.len(),
+            modified_ns,
+            hash: hash.clone(),
+        };
+        self.files.insert(name, stamp);
+        Some(hash)
+    }
+
+    /// Whether the job last succeeded with this fingerprint and its outputs still exist
+    fn is_fresh(&self, job: &str, fingerprint: &str, outputs: &[PathBuf]) -> bool {
+        self.jobs.get(job).is_some_and(|last| last == fingerprint)
+            && outputs.iter().all(|output| output.exists())
+    }
+}
+
+/// 64-bit FNV-1a hash, stable across builds unlike `DefaultHasher`
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Files an action reads and writes
+fn action_paths(
+    action: &BatchAction,
+    base_dir: &Path,
+    variables: &Option<JsonValue>,
+) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let resolve = |path: &String| resolve_path(path, base_dir, variables);
+    match action {
+        BatchAction::Convert { input, output, .. }
+        | BatchAction::Copy { input, output }
+        | BatchAction::Transform { input, output, .. } => {
+            (vec![resolve(input)], vec![resolve(output)])
+        }
+        BatchAction::Merge { inputs, output, .. } => {
+            (inputs.iter().map(resolve).collect(), vec![resolve(output)])
+        }
+        BatchAction::Validate { input, schema } => {
+            let mut inputs = vec![resolve(input)];
+            if let Some(schema) = schema {
+                let rendered = render_variables(schema, variables);
+                if !crate::core::remote::is_url(&rendered) {
+                    inputs.push(resolve_path(&rendered, base_dir, &None));
+                }
+            }
+            (inputs, Vec::new())
+        }
+    }
 }
 
 /// Execute batch jobs from config
 pub fn execute_batch(config: &BatchConfig, base_dir: &Path) -> Vec<BatchResult> {
+    execute_batch_with_cache(
+        config,
+        base_dir,
+        None,
+        &ConvertOptions::default(),
+        &EncodingOptions::default(),
+    )
+}
+
+/// Execute batch jobs from config, skipping jobs the cache shows are unchanged
+///
+/// Successful jobs are recorded in the cache; call [`BatchCache::save`] afterwards.
+/// Conversions use `options`; files are read and written with `encoding`.
+pub fn execute_batch_with_cache(
+    config: &BatchConfig,
+    base_dir: &Path,
+    mut cache: Option<&mut BatchCache>,
+    options: &ConvertOptions,
+    encoding: &EncodingOptions,
+) -> Vec<BatchResult> {
     let mut results = Vec::new();
+    let settings = run_settings(options, encoding);
+
+    for (name, job, variables) in expand_jobs(config) {
+        let job = &BatchJob {
+            name,
+            ..job.clone()
+        };
 
-    for job in &config.jobs {
         // Check condition if present
         if let Some(ref condition) = job.condition {
-            if !evaluate_condition(condition, &config.variables) {
+            if !evaluate_condition(condition, &variables) {
+                debug!(job = %job.name, %condition, "skipping job, condition not met");
                 results.push(BatchResult {
                     job_name: job.name.clone(),
                     success: true,
                     message: "Skipped (condition not met)".to_string(),
                     duration_ms: 0,
+                    cwd: None,
+                    env: BTreeMap::new(),
+                    cached: false,
                 });
                 continue;
             }
         }
 
+        let cwd = job
+            .cwd
+            .as_deref()
+            .map(|cwd| resolve_path(cwd, base_dir, &variables));
+        let job_dir = cwd.clone().unwrap_or_else(|| base_dir.to_path_buf());
+        let env: BTreeMap<String, String> = job
+            .env
+            .iter()
+            .map(|(key, value)| (key.clone(), render_variables(value, &variables)))
+            .collect();
+        let variables = with_env(variables, &env);
+
+        let fingerprint = cache.as_deref_mut().and_then(|cache| {
+            cache.fingerprint(&job.action, &job_dir, &variables, &env, &settings)
+        });
+        if let (Some(cache), Some(fingerprint)) = (cache.as_deref(), &fingerprint) {
+            let (_, outputs) = action_paths(&job.action, &job_dir, &variables);
+            if cache.is_fresh(&job.name, fingerprint, &outputs) {
+                debug!(job = %job.name, "skipping job, inputs unchanged since last run");
+                results.push(BatchResult {
+                    job_name: job.name.clone(),
+                    success: true,
+                    message: "Cached (inputs unchanged)".to_string(),
+                    duration_ms: 0,
+                    cwd,
+                    env,
+                    cached: true,
+                });
+                continue;
+            }
+        }
+
+        info!(job = %job.name, "starting job");
         let start = std::time::Instant::now();
-        let result = execute_job(job, base_dir, &config.variables);
+        let result = execute_action(&job.action, &job_dir, &variables, options, encoding);
         let duration = start.elapsed().as_millis();
 
-        let batch_result = match result {
+        match &result {
+            Ok(msg) => info!(job = %job.name, duration_ms = duration as u64, "{}", msg),
+            // Failures are reported in the results, so they only show with -v
+            Err(e) => {
+                info!(job = %job.name, duration_ms = duration as u64, error = %e, "job failed")
+            }
+        }
+
+        let mut batch_result = match result {
             Ok(msg) => BatchResult {
                 job_name: job.name.clone(),
                 success: true,
                 message: msg,
                 duration_ms: duration,
+                cwd,
+                env,
+                cached: false,
             },
             Err(e) => BatchResult {
                 job_name: job.name.clone(),
                 success: false,
                 message: format!("Error: {}", e),
                 duration_ms: duration,
+                cwd,
+                env,
+                cached: false,
             },
         };
+        run_hooks(
+            job,
+            &mut batch_result,
+            &job_dir,
+            &variables,
+            options,
+            encoding,
+        );
+        if let (Some(cache), Some(fingerprint)) = (cache.as_deref_mut(), fingerprint) {
+            if batch_result.success {
+                cache.jobs.insert(job.name.clone(), fingerprint);
+            } else {
+                cache.jobs.remove(&job.name);
+            }
+        }
 
         let should_stop = !batch_result.success && !config.continue_on_error;
         results.push(batch_result);
 
         if should_stop {
+            debug!("stopping batch after failure (continue_on_error is off)");
             break;
         }
     }
@@ -131,8 +415,184 @@ pub fn execute_batch(config: &BatchConfig, base_dir: &Path) -> Vec<BatchResult>
     results
 }
 
-fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -> Result<String> {
-    match &job.action {
+/// Add a job's environment to its variables as `env`
+fn with_env(variables: Option<JsonValue>, env: &BTreeMap<String, String>) -> Option<JsonValue> {
+    if env.is_empty() {
+        return variables;
+    }
+    let env_vars = serde_json::json!({ "env": env });
+    Some(match variables {
+        Some(vars) => crate::core::template::merge_vars(&[&vars, &env_vars]),
+        None => env_vars,
+    })
+}
+
+/// Conversion and encoding settings of a run, which change what its jobs write
+fn run_settings(options: &ConvertOptions, encoding: &EncodingOptions) -> String {
+    let options = ConvertOptions {
+        timings: None,
+        ..options.clone()
+    };
+    format!(
+        "{:?} {:?} {:?} {:?}",
+        options, encoding.input, encoding.output, encoding.eol
+    )
+}
+
+/// Expand matrix jobs into one (name, job, variables) entry per combination
+fn expand_jobs(config: &BatchConfig) -> Vec<(String, &BatchJob, Option<JsonValue>)> {
+    let mut expanded = Vec::new();
+
+    for job in &config.jobs {
+        if job.matrix.is_empty() {
+            expanded.push((job.name.clone(), job, config.variables.clone()));
+            continue;
+        }
+
+        for combination in matrix_combinations(&job.matrix) {
+            let label: Vec<String> = combination
+                .iter()
+                .map(|(key, value)| match value {
+                    JsonValue::String(s) => format!("{}={}", key, s),
+                    other => format!("{}={}", key, other),
+                })
+                .collect();
+            let matrix_vars = JsonValue::Object(combination.into_iter().collect());
+            let variables = match config.variables {
+                Some(ref vars) => crate::core::template::merge_vars(&[vars, &matrix_vars]),
+                None => matrix_vars,
+            };
+            expanded.push((
+                format!("{} ({})", job.name, label.join(", ")),
+                job,
+                Some(variables),
+            ));
+        }
+    }
+
+    expanded
+}
+
+/// Cartesian product of matrix values, in key order
+fn matrix_combinations(matrix: &BTreeMap<String, Vec<JsonValue>>) -> Vec<Vec<(String, JsonValue)>> {
+    let mut combinations = vec![Vec::new()];
+    for (key, values) in matrix {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination: Vec<(String, JsonValue)>| {
+                values.iter().map(move |value| {
+                    let mut next = combination.clone();
+                    next.push((key.clone(), value.clone()));
+                    next
+                })
+            })
+            .collect();
+    }
+    combinations
+}
+
+/// Run the job's `on_success` or `on_failure` hook; a failing hook fails the job
+fn run_hooks(
+    job: &BatchJob,
+    result: &mut BatchResult,
+    base_dir: &Path,
+    variables: &Option<JsonValue>,
+    options: &ConvertOptions,
+    encoding: &EncodingOptions,
+) {
+    let (name, hook) = if result.success {
+        ("on_success", &job.on_success)
+    } else {
+        ("on_failure", &job.on_failure)
+    };
+    let Some(hook) = hook else {
+        return;
+    };
+
+    let status = if result.success { "success" } else { "failure" };
+    let result_vars = serde_json::json!({
+        "job": result.job_name,
+        "status": status,
+        "message": result.message,
+        "duration_ms": result.duration_ms as u64,
+    });
+    let hook_vars = match variables {
+        Some(vars) => crate::core::template::merge_vars(&[vars, &result_vars]),
+        None => result_vars,
+    };
+
+    debug!(job = %job.name, hook = name, "running hook");
+    let outcome = match hook {
+        BatchHook::Command(command) => run_command(command, base_dir, &result.env, &hook_vars),
+        BatchHook::Action(action) => {
+            execute_action(action, base_dir, &Some(hook_vars), options, encoding).map(drop)
+        }
+    };
+    if let Err(e) = outcome {
+        info!(job = %job.name, hook = name, error = %e, "hook failed");
+        if result.success {
+            result.success = false;
+            result.message = format!("Error: {} hook failed: {}", name, e);
+        } else {
+            result.message = format!("{} ({} hook failed: {})", result.message, name, e);
+        }
+    }
+}
+
+/// Run a shell command with the job environment and result in its environment
+fn run_command(
+    command: &str,
+    base_dir: &Path,
+    env: &BTreeMap<String, String>,
+    variables: &JsonValue,
+) -> Result<()> {
+    // Values are quoted so a job name or message cannot inject shell syntax
+    let command = render_variables(command, &Some(shell_quoted(variables)));
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    if !base_dir.as_os_str().is_empty() {
+        shell.current_dir(base_dir);
+    }
+    shell.envs(env);
+    for (key, name) in [
+        ("job", "DTX_JOB"),
+        ("status", "DTX_STATUS"),
+        ("message", "DTX_MESSAGE"),
+        ("duration_ms", "DTX_DURATION_MS"),
+    ] {
+        let value = match &variables[key] {
+            JsonValue::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        shell.env(name, value);
+    }
+
+    let status = shell
+        .arg(&command)
+        .stdin(Stdio::null())
+        .status()
+        .with_context(|| format!("Failed to run '{}'", command))?;
+    if !status.success() {
+        bail!("'{}' exited with {}", command, status);
+    }
+    Ok(())
+}
+
+fn execute_action(
+    action: &BatchAction,
+    base_dir: &Path,
+    variables: &Option<JsonValue>,
+    options: &ConvertOptions,
+    encoding: &EncodingOptions,
+) -> Result<String> {
+    match action {
         BatchAction::Convert {
             input,
             output,
@@ -142,8 +602,7 @@ fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -
             let input_path = resolve_path(input, base_dir, variables);
             let output_path = resolve_path(output, base_dir, variables);
 
-            let content = fs::read_to_string(&input_path)
-                .with_context(|| format!("Failed to read: {}", input_path.display()))?;
+            let content = encoding.read_file(&input_path)?;
 
             let from_format = if let Some(f) = from {
                 parse_format(f)?
@@ -153,12 +612,13 @@ fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -
             };
 
             let to_format = parse_format(to)?;
-            let converted = converter::convert(&content, from_format, to_format)?;
+            let converted =
+                converter::convert_with_options(&content, from_format, to_format, options)?;
 
             if let Some(parent) = output_path.parent() {
                 fs::create_dir_all(parent)?;
             }
-            fs::write(&output_path, converted)?;
+            encoding.write_file(&output_path, &converted)?;
 
             Ok(format!(
                 "Converted {} -> {}",
@@ -171,18 +631,19 @@ fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -
             inputs,
             output,
             strategy,
+            null_deletes,
         } => {
             let mut values = Vec::new();
 
             for input in inputs {
                 let input_path = resolve_path(input, base_dir, variables);
-                let content = fs::read_to_string(&input_path)
-                    .with_context(|| format!("Failed to read: {}", input_path.display()))?;
+                let content = encoding.read_file(&input_path)?;
 
                 let format = detect(Some(&input_path), &content)
                     .context("Could not detect format")?;
 
-                let json_str = converter::convert(&content, format, Format::Json)?;
+                let json_str =
+                    converter::convert_with_options(&content, format, Format::Json, options)?;
                 let value: JsonValue = serde_json::from_str(&json_str)?;
                 values.push(value);
             }
@@ -191,10 +652,15 @@ fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -
                 Some("shallow") => crate::core::merger::MergeStrategy::Shallow,
                 Some("concat") => crate::core::merger::MergeStrategy::ConcatArrays,
                 Some("union") => crate::core::merger::MergeStrategy::UnionArrays,
+                Some("strategic") => crate::core::merger::MergeStrategy::Strategic,
                 _ => crate::core::merger::MergeStrategy::Deep,
             };
 
-            let merged = crate::core::merger::merge_all(&values, merge_strategy)?;
+            let merge_options = crate::core::merger::MergeOptions {
+                strategy: merge_strategy,
+                null_deletes: *null_deletes,
+            };
+            let merged = crate::core::merger::merge_all_with_options(&values, &merge_options)?;
 
             let output_path = resolve_path(output, base_dir, variables);
             let output_format = detect(Some(&output_path), "")
@@ -203,13 +669,13 @@ fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -
             let output_content = match output_format {
                 Format::Yaml => serde_yaml::to_string(&merged)?,
                 Format::Toml => toml::to_string_pretty(&merged)?,
-                _ => serde_json::to_string_pretty(&merged)?,
+                _ => json_format::to_pretty(&merged, &options.style)?,
             };
 
             if let Some(parent) = output_path.parent() {
                 fs::create_dir_all(parent)?;
             }
-            fs::write(&output_path, output_content)?;
+            encoding.write_file(&output_path, &output_content)?;
 
             Ok(format!(
                 "Merged {} files -> {}",
@@ -220,23 +686,33 @@ fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -
 
         BatchAction::Validate { input, schema } => {
             let input_path = resolve_path(input, base_dir, variables);
-            let content = fs::read_to_string(&input_path)
-                .with_context(|| format!("Failed to read: {}", input_path.display()))?;
+            let content = encoding.read_file(&input_path)?;
 
             let format = detect(Some(&input_path), &content)
                 .context("Could not detect format")?;
 
-            if let Some(schema_path) = schema {
-                let schema_path = resolve_path(schema_path, base_dir, variables);
-                let schema_content = fs::read_to_string(&schema_path)?;
-                let schema: JsonValue = serde_json::from_str(&schema_content)?;
+            if let Some(schema_source) = schema {
+                let rendered = render_variables(schema_source, variables);
+                let schema_source = if crate::core::remote::is_url(&rendered) {
+                    rendered
+                } else {
+                    resolve_path(&rendered, base_dir, &None)
+                        .to_string_lossy()
+                        .into_owned()
+                };
+                let schema = crate::core::validator::load_schema(&schema_source, false)?;
 
-                let json_str = converter::convert(&content, format, Format::Json)?;
+                let json_str =
+                    converter::convert_with_options(&content, format, Format::Json, options)?;
                 let data: JsonValue = serde_json::from_str(&json_str)?;
 
                 let result = crate::core::validator::validate_json_schema(&data, &schema)?;
                 if result.valid {
-                    Ok(format!("Validated: {} (schema: {})", input_path.display(), schema_path.display()))
+                    Ok(format!(
+                        "Validated: {} (schema: {})",
+                        input_path.display(),
+                        schema_source
+                    ))
                 } else {
                     anyhow::bail!("Validation failed: {} errors", result.errors.len())
                 }
@@ -246,7 +722,9 @@ fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -
                     Format::Json => crate::core::validator::lint_json(&content)?,
                     Format::Yaml => crate::core::validator::lint_yaml(&content)?,
                     Format::Toml => crate::core::validator::lint_toml(&content)?,
-                    Format::Csv => crate::core::validator::validate_csv(&content, true)?,
+                    Format::Csv | Format::Tsv => {
+                        crate::core::validator::validate_csv(&content, true)?
+                    }
                     _ => {
                         let mut r = crate::core::validator::ValidationResult::new();
                         r.valid = true;
@@ -284,12 +762,13 @@ fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -
             query,
         } => {
             let input_path = resolve_path(input, base_dir, variables);
-            let content = fs::read_to_string(&input_path)?;
+            let content = encoding.read_file(&input_path)?;
 
             let format = detect(Some(&input_path), &content)
                 .context("Could not detect format")?;
 
-            let json_str = converter::convert(&content, format, Format::Json)?;
+            let json_str =
+                converter::convert_with_options(&content, format, Format::Json, options)?;
             let value: JsonValue = serde_json::from_str(&json_str)?;
 
             let result = crate::core::query::jsonpath_query(&value, query)?;
@@ -300,13 +779,13 @@ fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -
 
             let output_content = match output_format {
                 Format::Yaml => serde_yaml::to_string(&result)?,
-                _ => serde_json::to_string_pretty(&result)?,
+                _ => json_format::to_pretty(&result, &options.style)?,
             };
 
             if let Some(parent) = output_path.parent() {
                 fs::create_dir_all(parent)?;
             }
-            fs::write(&output_path, output_content)?;
+            encoding.write_file(&output_path, &output_content)?;
 
             Ok(format!(
                 "Transformed {} -> {}",
@@ -317,14 +796,48 @@ fn execute_job(job: &BatchJob, base_dir: &Path, variables: &Option<JsonValue>) -
     }
 }
 
-fn resolve_path(path: &str, base_dir: &Path, variables: &Option<JsonValue>) -> PathBuf {
-    let resolved = if let Some(vars) = variables {
+fn render_variables(s: &str, variables: &Option<JsonValue>) -> String {
+    if let Some(vars) = variables {
         let options = crate::core::template::TemplateOptions::default();
-        crate::core::template::render_string(path, vars, &options)
-            .unwrap_or_else(|_| path.to_string())
+        crate::core::template::render_string(s, vars, &options).unwrap_or_else(|_| s.to_string())
     } else {
-        path.to_string()
-    };
+        s.to_string()
+    }
+}
+
+/// Variables with every string, number and boolean quoted for the shell
+fn shell_quoted(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Array(items) => JsonValue::Array(items.iter().map(shell_quoted).collect()),
+        JsonValue::Object(map) => JsonValue::Object(
+            map.iter()
+                .map(|(key, value)| (key.clone(), shell_quoted(value)))
+                .collect(),
+        ),
+        JsonValue::String(s) => JsonValue::String(shell_quote(s)),
+        JsonValue::Null => JsonValue::Null,
+        other => JsonValue::String(shell_quote(&other.to_string())),
+    }
+}
+
+/// Quote one argument for `sh -c`, or escape it for `cmd /C` on Windows
+fn shell_quote(s: &str) -> String {
+    if cfg!(windows) {
+        let mut quoted = String::with_capacity(s.len() + 2);
+        for c in s.chars() {
+            if "^&|<>()%!\"".contains(c) {
+                quoted.push('^');
+            }
+            quoted.push(c);
+        }
+        quoted
+    } else {
+        format!("'{}'", s.replace('\'', r"'\''"))
+    }
+}
+
+fn resolve_path(path: &str, base_dir: &Path, variables: &Option<JsonValue>) -> PathBuf {
+    let resolved = render_variables(path, variables);
 
     let path = PathBuf::from(&resolved);
     if path.is_absolute() {
@@ -340,7 +853,9 @@ fn parse_format(s: &str) -> Result<Format> {
         "yaml" | "yml" => Ok(Format::Yaml),
         "toml" => Ok(Format::Toml),
         "csv" => Ok(Format::Csv),
+        "tsv" => Ok(Format::Tsv),
         "xml" => Ok(Format::Xml),
+        "ndjson" | "jsonl" => Ok(Format::Ndjson),
         _ => anyhow::bail!("Unknown format: {}", s),
     }
 }
@@ -368,6 +883,7 @@ pub fn format_results(results: &[BatchResult]) -> String {
     let total = results.len();
     let passed = results.iter().filter(|r| r.success).count();
     let failed = total - passed;
+    let cached = results.iter().filter(|r| r.cached).count();
 
     output.push_str(&format!(
         "\n{}\n",
@@ -375,7 +891,9 @@ pub fn format_results(results: &[BatchResult]) -> String {
     ));
 
     for result in results {
-        let status = if result.success {
+        let status = if result.cached {
+            "CACHED".dimmed()
+        } else if result.success {
             "PASS".green()
         } else {
             "FAIL".red()
@@ -388,10 +906,21 @@ pub fn format_results(results: &[BatchResult]) -> String {
             result.duration_ms,
             result.message
         ));
+        if let Some(ref cwd) = result.cwd {
+            output.push_str(&format!("       cwd: {}\n", cwd.display()));
+        }
+        if !result.env.is_empty() {
+            let env: Vec<String> = result
+                .env
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, value))
+                .collect();
+            output.push_str(&format!("       env: {}\n", env.join(" ")));
+        }
     }
 
     output.push_str(&format!(
-        "\n{}: {} total, {} passed, {} failed\n",
+        "\n{}: {} total, {} passed, {} failed",
         "Summary".bold(),
         total,
         passed.to_string().green(),
@@ -401,23 +930,181 @@ pub fn format_results(results: &[BatchResult]) -> String {
             "0".to_string()
         }
     ));
+    if cached > 0 {
+        output.push_str(&format!(" ({} cached)", cached));
+    }
+    output.push('\n');
 
     output
 }
 
+/// Generate a JSON Schema describing the batch config format
+pub fn config_schema() -> JsonValue {
+    serde_json::to_value(schemars::schema_for!(BatchConfig)).unwrap_or_default()
+}
+
 /// Parse batch config from file
+///
+/// `${VAR}` references in string values are resolved from the environment
+/// before the config is interpreted.
 pub fn parse_config(content: &str, format: Format) -> Result<BatchConfig> {
-    match format {
-        Format::Yaml => serde_yaml::from_str(content).context("Failed to parse batch config as YAML"),
-        Format::Json => serde_json::from_str(content).context("Failed to parse batch config as JSON"),
-        Format::Toml => toml::from_str(content).context("Failed to parse batch config as TOML"),
+    let mut raw: JsonValue = match format {
+        Format::Yaml => {
+            serde_yaml::from_str(content).context("Failed to parse batch config as YAML")?
+        }
+        Format::Json => {
+            serde_json::from_str(content).context("Failed to parse batch config as JSON")?
+        }
+        Format::Toml => toml::from_str(content).context("Failed to parse batch config as TOML")?,
         _ => anyhow::bail!("Batch config must be YAML, JSON, or TOML"),
+    };
+
+    interpolate_env(&mut raw, &|name| std::env::var(name).ok())?;
+
+    serde_json::from_value(raw).context("Invalid batch config")
+}
+
+/// Load a batch config file, resolving `include` entries recursively
+///
+/// Included jobs run before the including file's own jobs, and its variables
+/// override included ones. Job paths stay relative to the top-level config.
+pub fn load_config(path: &Path) -> Result<BatchConfig> {
+    load_config_recursive(path, &mut Vec::new())
+}
+
+fn load_config_recursive(path: &Path, stack: &mut Vec<PathBuf>) -> Result<BatchConfig> {
+    let canonical = fs::canonicalize(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    if stack.contains(&canonical) {
+        let chain: Vec<String> = stack
+            .iter()
+            .chain(std::iter::once(&canonical))
+            .map(|p| p.display().to_string())
+            .collect();
+        anyhow::bail!("Include cycle in batch config: {}", chain.join(" -> "));
+    }
+
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    let format = detect(Some(path), &content).context("Could not detect config file format")?;
+    let mut config = parse_config(&content, format)
+        .with_context(|| format!("Invalid batch config: {}", path.display()))?;
+    if config.include.is_empty() {
+        return Ok(config);
+    }
+
+    stack.push(canonical);
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mut jobs = Vec::new();
+    let mut variables = Vec::new();
+    for include in std::mem::take(&mut config.include) {
+        let included = load_config_recursive(&base_dir.join(&include), stack)
+            .with_context(|| format!("Failed to include {}", include))?;
+        jobs.extend(included.jobs);
+        variables.extend(included.variables);
+    }
+    stack.pop();
+
+    jobs.append(&mut config.jobs);
+    config.jobs = jobs;
+    variables.extend(config.variables.take());
+    if !variables.is_empty() {
+        let sources: Vec<&JsonValue> = variables.iter().collect();
+        config.variables = Some(crate::core::template::merge_vars(&sources));
+    }
+
+    Ok(config)
+}
+
+/// Replace `${VAR}` and `${VAR:-default}` references in all strings of a value
+///
+/// `$$` produces a literal `$`. Fails listing every undefined variable.
+pub fn interpolate_env(
+    value: &mut JsonValue,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<()> {
+    let mut missing = Vec::new();
+    interpolate_env_recursive(value, lookup, &mut missing);
+
+    if !missing.is_empty() {
+        anyhow::bail!(
+            "Undefined environment variable(s) in batch config: {}",
+            missing.join(", ")
+        );
     }
+    Ok(())
+}
+
+fn interpolate_env_recursive(
+    value: &mut JsonValue,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    missing: &mut Vec<String>,
+) {
+    match value {
+        JsonValue::String(s) if s.contains('$') => {
+            *s = interpolate_env_string(s, lookup, missing);
+        }
+        JsonValue::Array(arr) => {
+            for item in arr {
+                interpolate_env_recursive(item, lookup, missing);
+            }
+        }
+        JsonValue::Object(obj) => {
+            for item in obj.values_mut() {
+                interpolate_env_recursive(item, lookup, missing);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn interpolate_env_string(
+    s: &str,
+    lookup: &dyn Fn(&str) -> Option<String>,
+    missing: &mut Vec<String>,
+) -> String {
+    let mut result = String::with_capacity(s.len());
+    let mut rest = s;
+
+    while let Some(pos) = rest.find('$') {
+        result.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+
+        if let Some(stripped) = after.strip_prefix('$') {
+            result.push('$');
+            rest = stripped;
+        } else if let Some(end) = after.strip_prefix('{').and_then(|a| a.find('}')) {
+            let expr = &after[1..end + 1];
+            let (name, default) = match expr.split_once(":-") {
+                Some((name, default)) => (name, Some(default)),
+                None => (expr, None),
+            };
+
+            match lookup(name).or_else(|| default.map(str::to_string)) {
+                Some(val) => result.push_str(&val),
+                None => {
+                    if !missing.iter().any(|m| m == name) {
+                        missing.push(name.to_string());
+                    }
+                }
+            }
+            rest = &after[end + 2..];
+        } else {
+            result.push('$');
+            rest = after;
+        }
+    }
+
+    result.push_str(rest);
+    result
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::formats::indent::Indent;
+    use crate::formats::OutputStyle;
+    use crate::utils::encoding::LineEnding;
 
     #[test]
     fn test_parse_config() {
@@ -435,5 +1122,256 @@ continue_on_error: true
         assert_eq!(config.jobs.len(), 1);
         assert!(config.continue_on_error);
     }
+
+    #[test]
+    fn test_config_schema() {
+        let schema = config_schema();
+        assert_eq!(schema["title"], "BatchConfig");
+        assert!(schema["required"]
+            .as_array()
+            .unwrap()
+            .contains(&serde_json::json!("jobs")));
+        let schema_str = schema.to_string();
+        for action in ["convert", "merge", "validate", "copy", "transform"] {
+            assert!(schema_str.contains(&format!("\"{}\"", action)));
+        }
+    }
+
+    #[test]
+    fn test_load_config_include() {
+        let dir = std::env::temp_dir().join(format!("dtx-batch-include-{}", std::process::id()));
+        fs::create_dir_all(dir.join("shared")).unwrap();
+        fs::write(
+            dir.join("shared/steps.yaml"),
+            "variables:\n  out: build\n  stage: dev\njobs:\n  - name: shared\n    action: copy\n    input: a\n    output: b\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("main.yaml"),
+            "include: [shared/steps.yaml]\nvariables:\n  stage: prod\njobs:\n  - name: own\n    action: copy\n    input: c\n    output: d\n",
+        )
+        .unwrap();
+
+        let config = load_config(&dir.join("main.yaml")).unwrap();
+        let names: Vec<&str> = config.jobs.iter().map(|j| j.name.as_str()).collect();
+        assert_eq!(names, ["shared", "own"]);
+        let vars = config.variables.unwrap();
+        assert_eq!(vars["out"], "build");
+        assert_eq!(vars["stage"], "prod");
+
+        fs::write(
+            dir.join("shared/steps.yaml"),
+            "include: [../main.yaml]\njobs: []\n",
+        )
+        .unwrap();
+        let err = load_config(&dir.join("main.yaml")).unwrap_err();
+        assert!(format!("{:#}", err).contains("Include cycle"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_expand_matrix_jobs() {
+        let yaml = r#"
+variables:
+  out: dist
+jobs:
+  - name: build
+    action: convert
+    input: "config.{{ env }}.json"
+    output: "{{ out }}/{{ env }}-{{ arch }}.yaml"
+    to: yaml
+    matrix:
+      env: [dev, prod]
+      arch: [x86, arm]
+"#;
+        let config = parse_config(yaml, Format::Yaml).unwrap();
+        let jobs = expand_jobs(&config);
+        assert_eq!(jobs.len(), 4);
+        assert_eq!(config.job_count(), 4);
+        assert_eq!(jobs[0].0, "build (arch=x86, env=dev)");
+        assert_eq!(jobs[3].0, "build (arch=arm, env=prod)");
+
+        let variables = &jobs[1].2;
+        let path = resolve_path(
+            "{{ out }}/{{ env }}-{{ arch }}.yaml",
+            Path::new(""),
+            variables,
+        );
+        assert_eq!(path, PathBuf::from("dist/prod-x86.yaml"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hooks() {
+        let dir = std::env::temp_dir().join(format!("dtx-batch-hooks-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("in.json"), "{\"a\": 1}").unwrap();
+        let yaml = r#"
+continue_on_error: true
+jobs:
+  - name: ok
+    action: copy
+    input: in.json
+    output: out.json
+    on_success: echo {{ job }} "$DTX_STATUS" > success.txt
+  - name: broken
+    action: copy
+    input: missing.json
+    output: out2.json
+    on_failure:
+      action: convert
+      input: in.json
+      output: "{{ status }}.yaml"
+      to: yaml
+  - name: bad-hook
+    action: copy
+    input: in.json
+    output: out3.json
+    on_success: exit 3
+"#;
+        let config = parse_config(yaml, Format::Yaml).unwrap();
+        let results = execute_batch(&config, &dir);
+
+        assert!(results[0].success);
+        assert_eq!(
+            fs::read_to_string(dir.join("success.txt")).unwrap(),
+            "ok success\n"
+        );
+        assert!(!results[1].success);
+        assert!(dir.join("failure.yaml").exists());
+        assert!(!results[2].success);
+        assert!(results[2].message.contains("on_success hook failed"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_hook_values_are_quoted() {
+        let dir = std::env::temp_dir().join(format!("dtx-batch-quote-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("in.json"), "{\"a\": 1}").unwrap();
+        let yaml = r#"
+jobs:
+  - name: "it's; touch injected $(touch injected)"
+    action: copy
+    input: in.json
+    output: out.json
+    on_success: echo {{ job }} > name.txt
+"#;
+        let config = parse_config(yaml, Format::Yaml).unwrap();
+        let results = execute_batch(&config, &dir);
+
+        assert!(results[0].success, "{}", results[0].message);
+        assert_eq!(
+            fs::read_to_string(dir.join("name.txt")).unwrap(),
+            "it's; touch injected $(touch injected)\n"
+        );
+        assert!(!dir.join("injected").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_job_cwd_and_env() {
+        let dir = std::env::temp_dir().join(format!("dtx-batch-cwd-{}", std::process::id()));
+        fs::create_dir_all(dir.join("site")).unwrap();
+        fs::write(dir.join("site/in.json"), "{\"a\": 1}").unwrap();
+        let yaml = r#"
+variables:
+  name: site
+jobs:
+  - name: build
+    action: convert
+    cwd: "{{ name }}"
+    env:
+      STAGE: prod-{{ name }}
+    input: in.json
+    output: "{{ env.STAGE }}.yaml"
+    to: yaml
+"#;
+        let config = parse_config(yaml, Format::Yaml).unwrap();
+        let results = execute_batch(&config, &dir);
+
+        assert!(results[0].success, "{}", results[0].message);
+        assert!(dir.join("site/prod-site.yaml").exists());
+        assert_eq!(results[0].cwd, Some(dir.join("site")));
+        assert_eq!(results[0].env["STAGE"], "prod-site");
+        assert!(format_results(&results).contains("env: STAGE=prod-site"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_batch_cache() {
+        let dir = std::env::temp_dir().join(format!("dtx-batch-cache-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("in.json"), "{\"a\": 1}").unwrap();
+        let yaml = r#"
+jobs:
+  - name: build
+    action: convert
+    input: in.json
+    output: out.yaml
+    to: yaml
+"#;
+        let config = parse_config(yaml, Format::Yaml).unwrap();
+        let cache_path = dir.join("cache.json");
+        let run_with = |options: &ConvertOptions, encoding: &EncodingOptions| {
+            let mut cache = BatchCache::load(&cache_path).unwrap();
+            let results =
+                execute_batch_with_cache(&config, &dir, Some(&mut cache), options, encoding);
+            cache.save().unwrap();
+            results[0].cached
+        };
+        let run = || run_with(&ConvertOptions::default(), &EncodingOptions::default());
+
+        assert!(!run());
+        assert!(run());
+
+        fs::write(dir.join("in.json"), "{\"a\": 22}").unwrap();
+        assert!(!run());
+        assert!(run());
+
+        fs::remove_file(dir.join("out.yaml")).unwrap();
+        assert!(!run());
+
+        // Changed output settings rerun the job
+        let indented = ConvertOptions {
+            style: OutputStyle {
+                indent: Some(Indent::Spaces(8)),
+                float: None,
+            },
+            ..Default::default()
+        };
+        assert!(!run_with(&indented, &EncodingOptions::default()));
+        assert!(run_with(&indented, &EncodingOptions::default()));
+        let crlf = EncodingOptions::new(None, None, Some(LineEnding::Crlf));
+        assert!(!run_with(&indented, &crlf));
+        assert!(!run());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_interpolate_env() {
+        let lookup = |name: &str| (name == "OUT_DIR").then(|| "dist".to_string());
+
+        let mut value = serde_json::json!({
+            "output": "${OUT_DIR}/data.yaml",
+            "fallback": "${STAGE:-dev}",
+            "query": "$.users[*]",
+            "price": "$$5"
+        });
+        interpolate_env(&mut value, &lookup).unwrap();
+        assert_eq!(value["output"], "dist/data.yaml");
+        assert_eq!(value["fallback"], "dev");
+        assert_eq!(value["query"], "$.users[*]");
+        assert_eq!(value["price"], "$5");
+
+        let mut undefined = serde_json::json!({"input": "${MISSING_ONE}/${MISSING_TWO}"});
+        let err = interpolate_env(&mut undefined, &lookup).unwrap_err();
+        assert!(err.to_string().contains("MISSING_ONE, MISSING_TWO"));
+    }
 }
 
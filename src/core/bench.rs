@@ -0,0 +1,89 @@
+//! Parse and serialize timings per format (`dtx bench`)
+
+use anyhow::Result;
+use serde_json::Value as JsonValue;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use crate::core::converter;
+use crate::formats::detect::Format;
+use crate::formats::yaml::YamlVersion;
+
+/// Measurements for one target format
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub format: Format,
+    /// Average time to write the document in this format
+    pub serialize: Duration,
+    /// Average time to read it back
+    pub parse: Duration,
+    /// Bytes of the serialized document
+    pub size: usize,
+}
+
+/// Time writing `value` as each target format and parsing the result back
+///
+/// Each step is averaged over `iterations` runs. A target that cannot hold the
+/// document (e.g. CSV for nested data) yields the error instead of a result.
+pub fn run(
+    value: &JsonValue,
+    targets: &[Format],
+    iterations: usize,
+) -> Vec<(Format, Result<BenchResult>)> {
+    let iterations = iterations.max(1);
+    targets
+        .iter()
+        .map(|&format| (format, measure(value, format, iterations)))
+        .collect()
+}
+
+fn measure(value: &JsonValue, format: Format, iterations: usize) -> Result<BenchResult> {
+    let output = converter::format_json_value_bytes(value, format)?;
+    let serialize = average(iterations, || {
+        converter::format_json_value_bytes(value, format)
+    })?;
+    let parse = average(iterations, || {
+        converter::parse_bytes_to_json_value(&output, format, YamlVersion::default())
+    })?;
+    Ok(BenchResult {
+        format,
+        serialize,
+        parse,
+        size: output.len(),
+    })
+}
+
+/// Mean duration of `iterations` calls of `f`
+pub fn average<T>(iterations: usize, mut f: impl FnMut() -> Result<T>) -> Result<Duration> {
+    let iterations = iterations.max(1);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        black_box(f()?);
+    }
+    Ok(start.elapsed() / iterations as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_run() {
+        let value = json!([{"id": 1, "name": "a"}, {"id": 2, "name": "b"}]);
+        let results = run(&value, &[Format::Json, Format::Yaml, Format::Toml], 2);
+
+        assert_eq!(results.len(), 3);
+        let json = results[0].1.as_ref().unwrap();
+        assert_eq!(json.size, json_size(&value));
+        assert!(results[1].1.is_ok());
+        // TOML documents must be tables
+        assert!(results[2].1.is_err());
+    }
+
+    fn json_size(value: &JsonValue) -> usize {
+        converter::format_json_value(value, Format::Json)
+            .unwrap()
+            .len()
+    }
+}
@@ -0,0 +1,335 @@
+//! Schema-guided type coercion
+//!
+//! Casts values to the types a JSON Schema declares for them, restoring the
+//! typing that text-only sources such as CSV and XML lose: `"42"` becomes an
+//! integer, `"true"` a boolean, a lone XML element a one-item array, and
+//! strings with a `date` or `date-time` format are normalized to ISO 8601.
+//! Values that cannot be cast are reported with their path.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, SecondsFormat};
+use serde_json::{Number, Value as JsonValue};
+
+/// Hops through `$ref` without descending into the value before giving up
+const MAX_REF_DEPTH: usize = 32;
+
+/// Cast `value` in place to the types declared by `schema`
+///
+/// Only local `$ref`s (`#/...`) are followed; bundle remote schemas first.
+pub fn coerce(value: &mut JsonValue, schema: &JsonValue) -> Result<()> {
+    Coercer { root: schema }.apply(value, schema, "$", 0)
+}
+
+struct Coercer<'a> {
+    root: &'a JsonValue,
+}
+
+impl Coercer<'_> {
+    fn apply(
+        &self,
+        value: &mut JsonValue,
+        schema: &JsonValue,
+        path: &str,
+        refs: usize,
+    ) -> Result<()> {
+        let Some(obj) = schema.as_object() else {
+            return Ok(());
+        };
+
+        if let Some(reference) = obj.get("$ref").and_then(JsonValue::as_str) {
+            if refs >= MAX_REF_DEPTH {
+                bail!("{}: $ref {} does not resolve to a schema", path, reference);
+            }
+            let target = self.resolve(reference)?;
+            return self.apply(value, target, path, refs + 1);
+        }
+
+        if let Some(JsonValue::Array(schemas)) = obj.get("allOf") {
+            for sub in schemas {
+                self.apply(value, sub, path, refs)?;
+            }
+        }
+
+        for key in ["anyOf", "oneOf"] {
+            if let Some(JsonValue::Array(schemas)) = obj.get(key) {
+                let coerced = schemas.iter().find_map(|sub| {
+                    let mut candidate = value.clone();
+                    self.apply(&mut candidate, sub, path, refs)
+                        .ok()
+                        .map(|_| candidate)
+                });
+                match coerced {
+                    Some(coerced) => *value = coerced,
+                    None => bail!(
+                        "{}: {} matches none of the {} schemas",
+                        path,
+                        describe(value),
+                        key
+                    ),
+                }
+            }
+        }
+
+        if let Some(types) = obj.get("type") {
+            let types: Vec<&str> = match types {
+                JsonValue::String(t) => vec![t.as_str()],
+                JsonValue::Array(ts) => ts.iter().filter_map(JsonValue::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.is_empty() {
+                coerce_type(value, &types, path)?;
+            }
+        }
+
+        if let (JsonValue::String(s), Some(format)) = (&*value, obj.get("format")) {
+            if let Some(normalized) = normalize_format(s, format.as_str().unwrap_or_default()) {
+                *value = JsonValue::String(normalized.with_context(|| {
+                    format!(
+                        "{}: cannot read {:?} as a {}",
+                        path,
+                        s,
+                        format.as_str().unwrap_or_default()
+                    )
+                })?);
+            }
+        }
+
+        match value {
+            JsonValue::Object(map) => {
+                let properties = obj.get("properties").and_then(JsonValue::as_object);
+                for (key, item) in map.iter_mut() {
+                    let sub = match properties.and_then(|p| p.get(key)) {
+                        Some(sub) => sub,
+                        None => match obj.get("additionalProperties") {
+                            Some(sub) => sub,
+                            None => continue,
+                        },
+                    };
+                    self.apply(item, sub, &format!("{}.{}", path, key), 0)?;
+                }
+            }
+            JsonValue::Array(items) => {
+                let prefix = match (obj.get("prefixItems"), obj.get("items")) {
+                    (Some(JsonValue::Array(prefix)), _)
+                    | (None, Some(JsonValue::Array(prefix))) => prefix.as_slice(),
+                    _ => &[],
+                };
+                let rest = match obj.get("items") {
+                    Some(JsonValue::Array(_)) => obj.get("additionalItems"),
+                    items => items,
+                };
+                for (i, item) in items.iter_mut().enumerate() {
+                    let sub = match prefix.get(i).or(rest) {
+                        Some(sub) => sub,
+                        None => continue,
+                    };
+                    self.apply(item, sub, &format!("{}[{}]", path, i), 0)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    fn resolve(&self, reference: &str) -> Result<&JsonValue> {
+        let Some(fragment) = reference.strip_prefix('#') else {
+            bail!(
+                "Only local $ref is supported when coercing ({}); bundle the schema first with `dtx schema bundle`",
+                reference
+            );
+        };
+        self.root
+            .pointer(fragment)
+            .with_context(|| format!("$ref {} does not resolve to a schema", reference))
+    }
+}
+
+/// Cast `value` to the first of `types` it can represent, unless it already has one of them
+fn coerce_type(value: &mut JsonValue, types: &[&str], path: &str) -> Result<()> {
+    if types.iter().any(|ty| has_type(value, ty)) {
+        return Ok(());
+    }
+    match types.iter().find_map(|ty| cast(value, ty)) {
+        Some(cast) => {
+            *value = cast;
+            Ok(())
+        }
+        None => bail!(
+            "{}: cannot coerce {} to {}",
+            path,
+            describe(value),
+            types.join(" or ")
+        ),
+    }
+}
+
+fn has_type(value: &JsonValue, ty: &str) -> bool {
+    match ty {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "number" => value.is_number(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        _ => false,
+    }
+}
+
+fn cast(value: &JsonValue, ty: &str) -> Option<JsonValue> {
+    match (ty, value) {
+        ("null", JsonValue::String(s)) if s.trim().is_empty() || s.trim() == "null" => {
+            Some(JsonValue::Null)
+        }
+        ("boolean", JsonValue::String(s)) => match s.trim().to_lowercase().as_str() {
+            "true" | "yes" | "1" => Some(JsonValue::Bool(true)),
+            "false" | "no" | "0" => Some(JsonValue::Bool(false)),
+            _ => None,
+        },
+        ("boolean", JsonValue::Number(n)) => match n.as_u64() {
+            Some(1) => Some(JsonValue::Bool(true)),
+            Some(0) => Some(JsonValue::Bool(false)),
+            _ => None,
+        },
+        ("integer", JsonValue::String(s)) => parse_integer(s.trim()),
+        ("integer", JsonValue::Number(n)) => n.as_f64().and_then(whole_number),
+        ("number", JsonValue::String(s)) => {
+            let s = s.trim();
+            parse_integer(s).or_else(|| {
+                s.parse::<f64>()
+                    .ok()
+                    .and_then(Number::from_f64)
+                    .map(JsonValue::Number)
+            })
+        }
+        ("string", JsonValue::Number(n)) => Some(JsonValue::String(n.to_string())),
+        ("string", JsonValue::Bool(b)) => Some(JsonValue::String(b.to_string())),
+        // A repeated XML element that occurs only once parses as a single value
+        ("array", JsonValue::Null) => None,
+        ("array", other) => Some(JsonValue::Array(vec![other.clone()])),
+        _ => None,
+    }
+}
+
+fn parse_integer(s: &str) -> Option<JsonValue> {
+    if let Ok(i) = s.parse::<i64>() {
+        return Some(JsonValue::from(i));
+    }
+    if let Ok(u) = s.parse::<u64>() {
+        return Some(JsonValue::from(u));
+    }
+    s.parse::<f64>().ok().and_then(whole_number)
+}
+
+fn whole_number(f: f64) -> Option<JsonValue> {
+    (f.is_finite() && f.fract() == 0.0 && f.abs() < 9.007_199_254_740_992e15)
+        .then(|| JsonValue::from(f as i64))
+}
+
+/// Normalized form of `s` for `date` and `date-time` formats; `None` for other formats
+fn normalize_format(s: &str, format: &str) -> Option<Option<String>> {
+    let s = s.trim();
+    match format {
+        "date" => Some(parse_date(s).map(|d| d.format("%Y-%m-%d").to_string())),
+        "date-time" => Some(parse_date_time(s)),
+        _ => None,
+    }
+}
+
+fn parse_date(s: &str) -> Option<NaiveDate> {
+    ["%Y-%m-%d", "%Y/%m/%d", "%Y%m%d", "%d.%m.%Y"]
+        .iter()
+        .find_map(|fmt| NaiveDate::parse_from_str(s, fmt).ok())
+        .or_else(|| parse_date_time_naive(s).map(|dt| dt.date()))
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|dt| dt.date_naive())
+        })
+}
+
+fn parse_date_time_naive(s: &str) -> Option<NaiveDateTime> {
+    [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%d %H:%M",
+    ]
+    .iter()
+    .find_map(|fmt| NaiveDateTime::parse_from_str(s, fmt).ok())
+}
+
+/// RFC 3339 form of a datetime; values without an offset are taken as UTC
+fn parse_date_time(s: &str) -> Option<String> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s)
+        .or_else(|_| DateTime::parse_from_rfc3339(&s.replacen(' ', "T", 1)))
+    {
+        return Some(dt.to_rfc3339_opts(SecondsFormat::AutoSi, true));
+    }
+    parse_date_time_naive(s)
+        .or_else(|| parse_date(s).and_then(|d| d.and_hms_opt(0, 0, 0)))
+        .map(|dt| dt.and_utc().to_rfc3339_opts(SecondsFormat::AutoSi, true))
+}
+
+fn describe(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => format!("{:?}", s),
+        JsonValue::Array(_) => "array".to_string(),
+        JsonValue::Object(_) => "object".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_coerce_records() {
+        let schema = json!({
+            "type": "array",
+            "items": { "$ref": "#/$defs/row" },
+            "$defs": {
+                "row": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "integer" },
+                        "price": { "type": "number" },
+                        "active": { "type": "boolean" },
+                        "note": { "type": ["integer", "null"] },
+                        "joined": { "type": "string", "format": "date" },
+                        "seen": { "type": "string", "format": "date-time" },
+                        "tags": { "type": "array", "items": { "type": "string" } }
+                    }
+                }
+            }
+        });
+        let mut value = json!([{
+            "id": "42", "price": "9.50", "active": "TRUE", "note": "",
+            "joined": "2024/03/05", "seen": "2024-03-05 10:30:00", "tags": "a"
+        }]);
+
+        coerce(&mut value, &schema).unwrap();
+        assert_eq!(
+            value,
+            json!([{
+                "id": 42, "price": 9.5, "active": true, "note": null,
+                "joined": "2024-03-05", "seen": "2024-03-05T10:30:00Z", "tags": ["a"]
+            }])
+        );
+    }
+
+    #[test]
+    fn test_coerce_error_path() {
+        let schema = json!({ "items": { "properties": { "age": { "type": "integer" } } } });
+        let mut value = json!([{ "age": "1" }, { "age": "old" }]);
+
+        let err = coerce(&mut value, &schema).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "$[1].age: cannot coerce \"old\" to integer"
+        );
+    }
+}
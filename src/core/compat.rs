@@ -0,0 +1,493 @@
+//! JSON Schema compatibility checks
+//!
+//! A change is breaking when data that was valid under the old schema may be
+//! rejected by the new one, or when a required property disappears.
+
+use colored::Colorize;
+use serde::Serialize;
+use serde_json::Value as JsonValue;
+use std::collections::BTreeSet;
+
+/// Whether a schema change can break existing data or consumers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Breaking,
+    Compatible,
+}
+
+/// A single difference between two schemas
+#[derive(Debug, Serialize)]
+pub struct SchemaChange {
+    pub kind: ChangeKind,
+    pub path: String,
+    pub message: String,
+}
+
+/// Lower bounds: raising or adding one rejects previously valid data
+const LOWER_BOUNDS: [&str; 5] = [
+    "minimum",
+    "exclusiveMinimum",
+    "minLength",
+    "minItems",
+    "minProperties",
+];
+
+/// Upper bounds: lowering or adding one rejects previously valid data
+const UPPER_BOUNDS: [&str; 5] = [
+    "maximum",
+    "exclusiveMaximum",
+    "maxLength",
+    "maxItems",
+    "maxProperties",
+];
+
+/// Keywords where any new or changed value may reject previously valid data
+const RESTRICTIONS: [&str; 3] = ["pattern", "format", "multipleOf"];
+
+/// Compare two (dereferenced) schemas
+pub fn compare(old: &JsonValue, new: &JsonValue) -> Vec<SchemaChange> {
+    let mut changes = Vec::new();
+    compare_at(old, new, "$", &mut changes);
+    changes
+}
+
+/// Whether any change is breaking
+pub fn has_breaking(changes: &[SchemaChange]) -> bool {
+    changes.iter().any(|c| c.kind == ChangeKind::Breaking)
+}
+
+/// Human-readable report grouped by kind
+pub fn format_report(changes: &[SchemaChange]) -> String {
+    let mut output = String::new();
+    if changes.is_empty() {
+        output.push_str(&format!("{}\n", "No schema changes".green().bold()));
+    } else if has_breaking(changes) {
+        output.push_str(&format!("{}\n", "Breaking changes found".red().bold()));
+    } else {
+        output.push_str(&format!("{}\n", "Schemas are compatible".green().bold()));
+    }
+
+    for (kind, title, marker) in [
+        (ChangeKind::Breaking, "Breaking".red(), "x".red()),
+        (ChangeKind::Compatible, "Compatible".green(), "+".green()),
+    ] {
+        let group: Vec<_> = changes.iter().filter(|c| c.kind == kind).collect();
+        if group.is_empty() {
+            continue;
+        }
+        output.push_str(&format!("\n{} ({}):\n", title, group.len()));
+        for change in group {
+            output.push_str(&format!(
+                "  {} {}: {}\n",
+                marker,
+                change.path.cyan(),
+                change.message
+            ));
+        }
+    }
+    output
+}
+
+fn push(changes: &mut Vec<SchemaChange>, kind: ChangeKind, path: &str, message: String) {
+    changes.push(SchemaChange {
+        kind,
+        path: path.to_string(),
+        message,
+    });
+}
+
+fn compare_at(old: &JsonValue, new: &JsonValue, path: &str, changes: &mut Vec<SchemaChange>) {
+    // `true` accepts everything, like an empty schema
+    let empty = JsonValue::Object(Default::default());
+    let (old, new) = match (old, new) {
+        (JsonValue::Bool(false), JsonValue::Bool(false)) => return,
+        (JsonValue::Bool(false), _) => {
+            push(
+                changes,
+                ChangeKind::Compatible,
+                path,
+                "schema now accepts values".into(),
+            );
+            return;
+        }
+        (_, JsonValue::Bool(false)) => {
+            push(
+                changes,
+                ChangeKind::Breaking,
+                path,
+                "schema no longer accepts any value".into(),
+            );
+            return;
+        }
+        (JsonValue::Bool(true), new) => (&empty, new),
+        (old, JsonValue::Bool(true)) => (old, &empty),
+        other => other,
+    };
+
+    compare_types(old, new, path, changes);
+    compare_enum(old, new, path, changes);
+    compare_keywords(old, new, path, changes);
+    compare_properties(old, new, path, changes);
+
+    if let (Some(old_items), Some(new_items)) = (old.get("items"), new.get("items")) {
+        compare_at(old_items, new_items, &format!("{}[]", path), changes);
+    }
+}
+
+fn types(schema: &JsonValue) -> Option<BTreeSet<String>> {
+    match schema.get("type")? {
+        JsonValue::String(t) => Some(BTreeSet::from([t.clone()])),
+        JsonValue::Array(items) => Some(
+            items
+                .iter()
+                .filter_map(|t| t.as_str().map(String::from))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+fn accepts_type(types: &BTreeSet<String>, t: &str) -> bool {
+    types.contains(t) || (t == "integer" && types.contains("number"))
+}
+
+fn type_list(types: &BTreeSet<String>) -> String {
+    types.iter().cloned().collect::<Vec<_>>().join(" | ")
+}
+
+fn compare_types(old: &JsonValue, new: &JsonValue, path: &str, changes: &mut Vec<SchemaChange>) {
+    match (types(old), types(new)) {
+        (None, Some(new_types)) => push(
+            changes,
+            ChangeKind::Breaking,
+            path,
+            format!("type restricted to {}", type_list(&new_types)),
+        ),
+        (Some(_), None) => push(
+            changes,
+            ChangeKind::Compatible,
+            path,
+            "type restriction removed".into(),
+        ),
+        (Some(old_types), Some(new_types)) => {
+            let narrowed = old_types.iter().any(|t| !accepts_type(&new_types, t));
+            let widened = new_types.iter().any(|t| !accepts_type(&old_types, t));
+            let message = format!(
+                "type changed from {} to {}",
+                type_list(&old_types),
+                type_list(&new_types)
+            );
+            if narrowed {
+                push(
+                    changes,
+                    ChangeKind::Breaking,
+                    path,
+                    message.replace("changed", "narrowed"),
+                );
+            } else if widened {
+                push(
+                    changes,
+                    ChangeKind::Compatible,
+                    path,
+                    message.replace("changed", "widened"),
+                );
+            }
+        }
+        (None, None) => {}
+    }
+}
+
+fn compare_enum(old: &JsonValue, new: &JsonValue, path: &str, changes: &mut Vec<SchemaChange>) {
+    let old_enum = old.get("enum").and_then(JsonValue::as_array);
+    let new_enum = new.get("enum").and_then(JsonValue::as_array);
+    match (old_enum, new_enum) {
+        (None, Some(_)) => push(changes, ChangeKind::Breaking, path, "enum added".into()),
+        (Some(_), None) => push(changes, ChangeKind::Compatible, path, "enum removed".into()),
+        (Some(old_values), Some(new_values)) => {
+            for value in old_values.iter().filter(|v| !new_values.contains(v)) {
+                push(
+                    changes,
+                    ChangeKind::Breaking,
+                    path,
+                    format!("enum value {} removed", value),
+                );
+            }
+            for value in new_values.iter().filter(|v| !old_values.contains(v)) {
+                push(
+                    changes,
+                    ChangeKind::Compatible,
+                    path,
+                    format!("enum value {} added", value),
+                );
+            }
+        }
+        (None, None) => {}
+    }
+
+    match (old.get("const"), new.get("const")) {
+        (old_const, Some(new_const)) if old_const != Some(new_const) => push(
+            changes,
+            ChangeKind::Breaking,
+            path,
+            format!("const set to {}", new_const),
+        ),
+        (Some(_), None) => push(
+            changes,
+            ChangeKind::Compatible,
+            path,
+            "const removed".into(),
+        ),
+        _ => {}
+    }
+}
+
+fn compare_keywords(old: &JsonValue, new: &JsonValue, path: &str, changes: &mut Vec<SchemaChange>) {
+    let bounds = LOWER_BOUNDS
+        .iter()
+        .map(|k| (k, true))
+        .chain(UPPER_BOUNDS.iter().map(|k| (k, false)));
+    for (keyword, lower) in bounds {
+        let old_bound = old.get(*keyword).and_then(JsonValue::as_f64);
+        let new_bound = new.get(*keyword).and_then(JsonValue::as_f64);
+        let (kind, message) = match (old_bound, new_bound) {
+            (None, Some(_)) => (ChangeKind::Breaking, format!("{} added", keyword)),
+            (Some(_), None) => (ChangeKind::Compatible, format!("{} removed", keyword)),
+            (Some(a), Some(b)) if a != b => {
+                let tightened = if lower { b > a } else { b < a };
+                let verb = if b > a { "raised" } else { "lowered" };
+                let kind = if tightened {
+                    ChangeKind::Breaking
+                } else {
+                    ChangeKind::Compatible
+                };
+                (
+                    kind,
+                    format!(
+                        "{} {} from {} to {}",
+                        keyword, verb, old[*keyword], new[*keyword]
+                    ),
+                )
+            }
+            _ => continue,
+        };
+        push(changes, kind, path, message);
+    }
+
+    for keyword in RESTRICTIONS {
+        match (old.get(keyword), new.get(keyword)) {
+            (None, Some(value)) => push(
+                changes,
+                ChangeKind::Breaking,
+                path,
+                format!("{} {} added", keyword, value),
+            ),
+            (Some(a), Some(b)) if a != b => push(
+                changes,
+                ChangeKind::Breaking,
+                path,
+                format!("{} changed from {} to {}", keyword, a, b),
+            ),
+            (Some(_), None) => push(
+                changes,
+                ChangeKind::Compatible,
+                path,
+                format!("{} removed", keyword),
+            ),
+            _ => {}
+        }
+    }
+
+    let old_unique = old.get("uniqueItems") == Some(&JsonValue::Bool(true));
+    let new_unique = new.get("uniqueItems") == Some(&JsonValue::Bool(true));
+    if !old_unique && new_unique {
+        push(
+            changes,
+            ChangeKind::Breaking,
+            path,
+            "uniqueItems required".into(),
+        );
+    } else if old_unique && !new_unique {
+        push(
+            changes,
+            ChangeKind::Compatible,
+            path,
+            "uniqueItems no longer required".into(),
+        );
+    }
+
+    let old_closed = old.get("additionalProperties") == Some(&JsonValue::Bool(false));
+    let new_closed = new.get("additionalProperties") == Some(&JsonValue::Bool(false));
+    if !old_closed && new_closed {
+        push(
+            changes,
+            ChangeKind::Breaking,
+            path,
+            "additional properties no longer allowed".into(),
+        );
+    } else if old_closed && !new_closed {
+        push(
+            changes,
+            ChangeKind::Compatible,
+            path,
+            "additional properties now allowed".into(),
+        );
+    }
+}
+
+fn required(schema: &JsonValue) -> BTreeSet<&str> {
+    schema
+        .get("required")
+        .and_then(JsonValue::as_array)
+        .map(|r| r.iter().filter_map(JsonValue::as_str).collect())
+        .unwrap_or_default()
+}
+
+fn compare_properties(
+    old: &JsonValue,
+    new: &JsonValue,
+    path: &str,
+    changes: &mut Vec<SchemaChange>,
+) {
+    let empty = serde_json::Map::new();
+    let old_props = old
+        .get("properties")
+        .and_then(JsonValue::as_object)
+        .unwrap_or(&empty);
+    let new_props = new
+        .get("properties")
+        .and_then(JsonValue::as_object)
+        .unwrap_or(&empty);
+    let old_required = required(old);
+    let new_required = required(new);
+    let new_closed = new.get("additionalProperties") == Some(&JsonValue::Bool(false));
+
+    for (name, old_schema) in old_props {
+        let prop_path = format!("{}.{}", path, name);
+        match new_props.get(name) {
+            Some(new_schema) => compare_at(old_schema, new_schema, &prop_path, changes),
+            None if old_required.contains(name.as_str()) => push(
+                changes,
+                ChangeKind::Breaking,
+                &prop_path,
+                "required property removed".into(),
+            ),
+            None if new_closed => push(
+                changes,
+                ChangeKind::Breaking,
+                &prop_path,
+                "property removed (additional properties not allowed)".into(),
+            ),
+            None => push(
+                changes,
+                ChangeKind::Compatible,
+                &prop_path,
+                "property removed".into(),
+            ),
+        }
+    }
+    for name in new_props
+        .keys()
+        .filter(|name| !old_props.contains_key(*name))
+    {
+        push(
+            changes,
+            ChangeKind::Compatible,
+            &format!("{}.{}", path, name),
+            "property added".into(),
+        );
+    }
+
+    for name in new_required.difference(&old_required) {
+        push(
+            changes,
+            ChangeKind::Breaking,
+            &format!("{}.{}", path, name),
+            "property is now required".into(),
+        );
+    }
+    for name in old_required.difference(&new_required) {
+        if new_props.contains_key(*name) {
+            push(
+                changes,
+                ChangeKind::Compatible,
+                &format!("{}.{}", path, name),
+                "property is no longer required".into(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn breaking(changes: &[SchemaChange]) -> Vec<String> {
+        changes
+            .iter()
+            .filter(|c| c.kind == ChangeKind::Breaking)
+            .map(|c| format!("{}: {}", c.path, c.message))
+            .collect()
+    }
+
+    #[test]
+    fn test_breaking_changes() {
+        let old = json!({
+            "type": "object",
+            "required": ["id", "name"],
+            "properties": {
+                "id": {"type": "integer"},
+                "name": {"type": "string"},
+                "score": {"type": "number", "maximum": 100},
+                "status": {"enum": ["active", "inactive"]},
+                "tags": {"type": "array", "items": {"type": ["string", "null"]}}
+            }
+        });
+        let new = json!({
+            "type": "object",
+            "required": ["id", "status"],
+            "properties": {
+                "id": {"type": "integer"},
+                "score": {"type": "integer", "maximum": 10},
+                "status": {"enum": ["active"]},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }
+        });
+
+        assert_eq!(
+            breaking(&compare(&old, &new)),
+            vec![
+                "$.name: required property removed",
+                "$.score: type narrowed from number to integer",
+                "$.score: maximum lowered from 100 to 10",
+                "$.status: enum value \"inactive\" removed",
+                "$.tags[]: type narrowed from null | string to string",
+                "$.status: property is now required",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compatible_changes() {
+        let old = json!({
+            "type": "object",
+            "required": ["id"],
+            "properties": {"id": {"type": "integer"}, "kind": {"enum": ["a"]}},
+            "additionalProperties": false
+        });
+        let new = json!({
+            "type": "object",
+            "properties": {
+                "id": {"type": "number"},
+                "kind": {"enum": ["a", "b"]},
+                "note": {"type": "string"}
+            }
+        });
+
+        let changes = compare(&old, &new);
+        assert!(!has_breaking(&changes));
+        assert_eq!(changes.len(), 5);
+        assert!(compare(&old, &old).is_empty());
+    }
+}
@@ -1,194 +1,324 @@
 //! Format conversion engine
 //!
 //! Provides conversion between all supported formats using serde_json::Value as
-//! the intermediate representation.
+//! the intermediate representation. Conversions between JSON, YAML and TOML use
+//! the lossless [`DtxValue`] instead.
+//!
+//! TOML dates and times stay typed through [`DtxValue`]; in
+//! serde_json::Value they are RFC 3339 strings, which are written back as
+//! strings.
 
 use anyhow::{bail, Context, Result};
 use serde_json::Value as JsonValue;
+use std::io::{BufRead, BufWriter, Read, Write};
+use std::sync::Arc;
+use tracing::debug;
 
-use crate::formats::detect::Format;
+use crate::core::coerce;
+use crate::core::value::DtxValue;
+use crate::formats::avro::{self as avro_format, Schema as AvroSchema};
+use crate::formats::csv::{CsvData, CsvReadOptions};
+use crate::formats::detect::{self, Format};
+use crate::formats::float::{self, FloatFormat};
+use crate::formats::proto::{self as proto_format, MessageDescriptor};
+use crate::formats::registry::FormatHandler;
+use crate::formats::sql::{self as sql_format, SqlOptions};
+use crate::formats::toml::TomlLayout;
+use crate::formats::xml::{CdataOptions, Entities};
+use crate::formats::yaml::YamlVersion;
 use crate::formats::{
-    csv as csv_format, json as json_format, toml as toml_format, yaml as yaml_format,
+    bson as bson_format, csv as csv_format, hcl as hcl_format, html as html_format,
+    json as json_format, msgpack as msgpack_format, ndjson as ndjson_format,
+    parquet as parquet_format, plist as plist_format, properties as properties_format,
+    sqlite as sqlite_format, toml as toml_format, xlsx as xlsx_format, xml as xml_format,
+    yaml as yaml_format, OutputStyle,
 };
+use crate::utils::diagnostics;
+use crate::utils::timing::{self, Timings};
+
+/// Options that adjust individual conversions
+#[derive(Debug, Clone, Default)]
+pub struct ConvertOptions {
+    /// Slash-separated path to the repeating XML element that becomes CSV rows
+    /// (e.g. `books/book`); detected automatically when unset
+    pub record_path: Option<String>,
+    /// CSV column whose value groups rows into one parent object
+    pub group_by: Option<String>,
+    /// Name of the nested array holding grouped child records
+    pub children: Option<String>,
+    /// Preamble and comment handling for CSV input
+    pub csv: CsvReadOptions,
+    /// Inline table and array-of-tables placement for TOML output
+    pub toml: TomlLayout,
+    /// Indentation of JSON, XML and TOML output and the text of floats
+    pub style: OutputStyle,
+    /// YAML revision whose rules type the plain scalars of YAML input
+    pub yaml_version: YamlVersion,
+    /// Phase durations are added here when `--timing` is given
+    pub timings: Option<Arc<Timings>>,
+    /// CDATA sections for text in XML output
+    pub cdata: CdataOptions,
+    /// Slash-separated XML element paths that always become JSON arrays, even
+    /// when the element occurs once (matched like [`CdataOptions::paths`])
+    pub force_array: Vec<String>,
+    /// JSON Schema whose declared types values are cast to
+    pub coerce_schema: Option<JsonValue>,
+    /// Cell text for null and missing values in CSV output (e.g. `\N`); empty when unset
+    pub null_as: Option<String>,
+    /// Worksheet of XLSX input, by name or 1-based position; the first when unset
+    pub sheet: Option<String>,
+    /// Table of SQLite input, by name or 1-based position, every table when
+    /// unset; or of HTML input, by 1-based position, the first when unset
+    pub table: Option<String>,
+    /// Table name and dialect for SQL output
+    pub sql: SqlOptions,
+    /// Message type of protobuf input and output, from a descriptor set
+    pub proto: Option<MessageDescriptor>,
+    /// Schema that Avro output is encoded with
+    pub avro_schema: Option<AvroSchema>,
+}
 
 /// Convert content from one format to another
 pub fn convert(content: &str, from: Format, to: Format) -> Result<String> {
-    if from == to {
+    convert_with_options(content, from, to, &ConvertOptions::default())
+}
+
+/// Convert content from one format to another with conversion options
+pub fn convert_with_options(
+    content: &str,
+    from: Format,
+    to: Format,
+    options: &ConvertOptions,
+) -> Result<String> {
+    if from == to && options.coerce_schema.is_none() {
         // Same format, just return formatted version
-        return format_content(content, to);
+        debug!(format = %to, "source and target format match, reformatting only");
+        return timing::time(options.timings.as_deref(), "reformat", || {
+            format_content(content, to, options)
+        });
+    }
+
+    if DtxValue::supports(from, to) && options.coerce_schema.is_none() {
+        debug!(%from, %to, "converting through the lossless intermediate value");
+        let value = timing::time(options.timings.as_deref(), "parse", || {
+            DtxValue::parse(content, from, options.yaml_version)
+        })?;
+        return timing::time(options.timings.as_deref(), "serialize", || {
+            value.write(to, &options.toml, &options.style)
+        });
+    }
+
+    if from == Format::Xml && is_delimited(to) && options.coerce_schema.is_none() {
+        return timing::time(options.timings.as_deref(), "convert", || {
+            xml_to_csv(content, options, write_delimiter(to, options))
+        });
     }
 
     // Convert to intermediate JSON Value
-    let value = parse_to_json_value(content, from)?;
+    debug!(%from, bytes = content.len(), "parsing input into intermediate value");
+    let mut value = timing::time(options.timings.as_deref(), "parse", || {
+        from.handler()?.parse(content.as_bytes(), options)
+    })?;
+    timing::time(options.timings.as_deref(), "transform", || {
+        transform(&mut value, from, options)
+    })?;
 
     // Convert from JSON Value to target format
-    json_value_to_format(&value, to)
+    debug!(%to, "serializing intermediate value");
+    timing::time(options.timings.as_deref(), "serialize", || {
+        json_value_to_format(&value, to, options)
+    })
 }
 
-/// Parse content into serde_json::Value (intermediate representation)
-fn parse_to_json_value(content: &str, format: Format) -> Result<JsonValue> {
-    match format {
-        Format::Json => serde_json::from_str(content).context("Failed to parse JSON"),
-        Format::Yaml => {
-            let yaml_value: serde_yaml::Value =
-                serde_yaml::from_str(content).context("Failed to parse YAML")?;
-            yaml_to_json_value(yaml_value)
-        }
-        Format::Toml => {
-            let toml_value: toml::Value = content.parse().context("Failed to parse TOML")?;
-            toml_to_json_value(toml_value)
-        }
-        Format::Csv => csv_to_json_value(content),
-        Format::Xml => xml_to_json_value(content),
+/// Convert between any two formats, binary ones such as MessagePack included
+///
+/// Text input must be UTF-8; text output is returned as UTF-8 bytes.
+pub fn convert_bytes(
+    input: &[u8],
+    from: Format,
+    to: Format,
+    options: &ConvertOptions,
+) -> Result<Vec<u8>> {
+    if is_plist(from) && is_plist(to) && options.coerce_schema.is_none() {
+        // Dates and data survive a direct conversion, but not the intermediate value
+        return timing::time(options.timings.as_deref(), "convert", || {
+            plist_format::transcode(input, to == Format::BinaryPlist)
+        });
+    }
+    if !from.is_binary() && !to.is_binary() {
+        return convert_with_options(utf8(input)?, from, to, options).map(String::into_bytes);
     }
+
+    debug!(%from, bytes = input.len(), "parsing input into intermediate value");
+    let mut value = timing::time(options.timings.as_deref(), "parse", || {
+        from.handler()?.parse(input, options)
+    })?;
+    timing::time(options.timings.as_deref(), "transform", || {
+        transform(&mut value, from, options)
+    })?;
+
+    debug!(%to, "serializing intermediate value");
+    timing::time(options.timings.as_deref(), "serialize", || {
+        json_value_to_bytes(&value, to, options)
+    })
 }
 
-/// Convert serde_json::Value to target format string
-fn json_value_to_format(value: &JsonValue, format: Format) -> Result<String> {
-    match format {
-        Format::Json => serde_json::to_string_pretty(value).context("Failed to serialize JSON"),
-        Format::Yaml => serde_yaml::to_string(value).context("Failed to serialize YAML"),
-        Format::Toml => {
-            let toml_value = json_to_toml_value(value)?;
-            toml::to_string_pretty(&toml_value).context("Failed to serialize TOML")
-        }
-        Format::Csv => json_to_csv(value),
-        Format::Xml => json_to_xml(value),
+/// Group CSV rows and coerce values to the schema, as requested
+fn transform(value: &mut JsonValue, from: Format, options: &ConvertOptions) -> Result<()> {
+    let tabular = is_delimited(from) || matches!(from, Format::Xlsx | Format::Html);
+    if let (true, Some(key)) = (tabular, options.group_by.as_deref()) {
+        let children = options.children.as_deref().unwrap_or("items");
+        *value = group_rows(value, key, children)?;
     }
-}
 
-/// Format content in same format (just pretty print)
-fn format_content(content: &str, format: Format) -> Result<String> {
-    match format {
-        Format::Json => {
-            let value = json_format::parse(content)?;
-            json_format::to_pretty(&value)
-        }
-        Format::Yaml => {
-            let value = yaml_format::parse(content)?;
-            yaml_format::to_pretty(&value)
-        }
-        Format::Toml => {
-            let value = toml_format::parse(content)?;
-            toml_format::to_pretty(&value)
-        }
-        Format::Csv => {
-            let data = csv_format::parse(content, true)?;
-            csv_format::to_csv(&data)
-        }
-        Format::Xml => crate::formats::xml::to_pretty(content),
+    if let Some(ref schema) = options.coerce_schema {
+        debug!("coercing values to schema types");
+        coerce::coerce(value, schema).context("Failed to coerce values to schema types")?;
     }
+    Ok(())
 }
 
-// ============================================================================
-// YAML <-> JSON conversion
-// ============================================================================
+/// The HTML table to read; unlike SQLite tables they have no names
+fn html_table(options: &ConvertOptions) -> Result<Option<usize>> {
+    options
+        .table
+        .as_deref()
+        .map(|table| {
+            table.parse().with_context(|| {
+                format!(
+                    "HTML tables are selected by 1-based position, not '{}'",
+                    table
+                )
+            })
+        })
+        .transpose()
+}
 
-fn yaml_to_json_value(yaml: serde_yaml::Value) -> Result<JsonValue> {
-    match yaml {
-        serde_yaml::Value::Null => Ok(JsonValue::Null),
-        serde_yaml::Value::Bool(b) => Ok(JsonValue::Bool(b)),
-        serde_yaml::Value::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Ok(JsonValue::Number(i.into()))
-            } else if let Some(f) = n.as_f64() {
-                Ok(serde_json::Number::from_f64(f)
-                    .map(JsonValue::Number)
-                    .unwrap_or(JsonValue::Null))
-            } else {
-                Ok(JsonValue::Null)
-            }
-        }
-        serde_yaml::Value::String(s) => Ok(JsonValue::String(s)),
-        serde_yaml::Value::Sequence(arr) => {
-            let json_arr: Result<Vec<JsonValue>> =
-                arr.into_iter().map(yaml_to_json_value).collect();
-            Ok(JsonValue::Array(json_arr?))
-        }
-        serde_yaml::Value::Mapping(map) => {
-            let mut json_obj = serde_json::Map::new();
-            for (k, v) in map {
-                let key = match k {
-                    serde_yaml::Value::String(s) => s,
-                    other => serde_yaml::to_string(&other)
-                        .unwrap_or_default()
-                        .trim()
-                        .to_string(),
-                };
-                json_obj.insert(key, yaml_to_json_value(v)?);
-            }
-            Ok(JsonValue::Object(json_obj))
-        }
-        serde_yaml::Value::Tagged(tagged) => yaml_to_json_value(tagged.value),
+/// The protobuf message type, which binary payloads cannot be read or written without
+fn proto_message(options: &ConvertOptions) -> Result<&MessageDescriptor> {
+    options
+        .proto
+        .as_ref()
+        .context("Protobuf needs its message type; use --descriptor and --message")
+}
+
+/// The schema for Avro output; input files carry their own
+fn avro_schema(options: &ConvertOptions) -> Result<&AvroSchema> {
+    options
+        .avro_schema
+        .as_ref()
+        .context("Writing Avro needs a schema; use --schema FILE.avsc")
+}
+
+fn is_plist(format: Format) -> bool {
+    matches!(format, Format::Plist | Format::BinaryPlist)
+}
+
+fn utf8(input: &[u8]) -> Result<&str> {
+    std::str::from_utf8(input).context("Input is not valid UTF-8")
+}
+
+/// Parse content into serde_json::Value (intermediate representation)
+///
+/// YAML is read by the rules of `version`.
+pub fn parse_to_json_value(
+    content: &str,
+    format: Format,
+    version: YamlVersion,
+) -> Result<JsonValue> {
+    if format.is_binary() {
+        bail!("{} is a binary format and cannot be read as text", format)
     }
+    parse_bytes_to_json_value(content.as_bytes(), format, version)
 }
 
-// ============================================================================
-// TOML <-> JSON conversion
-// ============================================================================
+/// Parse content in any format, binary ones included, into serde_json::Value
+pub fn parse_bytes_to_json_value(
+    input: &[u8],
+    format: Format,
+    version: YamlVersion,
+) -> Result<JsonValue> {
+    let options = ConvertOptions {
+        yaml_version: version,
+        ..ConvertOptions::default()
+    };
+    format.handler()?.parse(input, &options)
+}
 
-fn toml_to_json_value(toml: toml::Value) -> Result<JsonValue> {
-    match toml {
-        toml::Value::String(s) => Ok(JsonValue::String(s)),
-        toml::Value::Integer(i) => Ok(JsonValue::Number(i.into())),
-        toml::Value::Float(f) => Ok(serde_json::Number::from_f64(f)
-            .map(JsonValue::Number)
-            .unwrap_or(JsonValue::Null)),
-        toml::Value::Boolean(b) => Ok(JsonValue::Bool(b)),
-        toml::Value::Datetime(dt) => Ok(JsonValue::String(dt.to_string())),
-        toml::Value::Array(arr) => {
-            let json_arr: Result<Vec<JsonValue>> =
-                arr.into_iter().map(toml_to_json_value).collect();
-            Ok(JsonValue::Array(json_arr?))
-        }
-        toml::Value::Table(table) => {
-            let mut json_obj = serde_json::Map::new();
-            for (k, v) in table {
-                json_obj.insert(k, toml_to_json_value(v)?);
-            }
-            Ok(JsonValue::Object(json_obj))
-        }
+/// Write a value in `format` with default conversion options
+pub fn format_json_value(value: &JsonValue, format: Format) -> Result<String> {
+    json_value_to_format(value, format, &ConvertOptions::default())
+}
+
+/// Write a value in any format, binary ones included, with default conversion options
+pub fn format_json_value_bytes(value: &JsonValue, format: Format) -> Result<Vec<u8>> {
+    json_value_to_bytes(value, format, &ConvertOptions::default())
+}
+
+fn json_value_to_bytes(
+    value: &JsonValue,
+    format: Format,
+    options: &ConvertOptions,
+) -> Result<Vec<u8>> {
+    format.handler()?.serialize(value, options)
+}
+
+/// Convert serde_json::Value to target format string
+fn json_value_to_format(
+    value: &JsonValue,
+    format: Format,
+    options: &ConvertOptions,
+) -> Result<String> {
+    if format.is_binary() {
+        bail!(
+            "{} is a binary format and cannot be written as text",
+            format
+        )
     }
+    String::from_utf8(json_value_to_bytes(value, format, options)?)
+        .with_context(|| format!("{} output is not valid UTF-8", format))
 }
 
-fn json_to_toml_value(json: &JsonValue) -> Result<toml::Value> {
-    match json {
-        JsonValue::Null => {
-            // TOML doesn't have null, convert to empty string
-            Ok(toml::Value::String(String::new()))
-        }
-        JsonValue::Bool(b) => Ok(toml::Value::Boolean(*b)),
-        JsonValue::Number(n) => {
-            if let Some(i) = n.as_i64() {
-                Ok(toml::Value::Integer(i))
-            } else if let Some(f) = n.as_f64() {
-                Ok(toml::Value::Float(f))
-            } else {
-                bail!("Invalid number in JSON")
-            }
-        }
-        JsonValue::String(s) => Ok(toml::Value::String(s.clone())),
-        JsonValue::Array(arr) => {
-            let toml_arr: Result<Vec<toml::Value>> = arr.iter().map(json_to_toml_value).collect();
-            Ok(toml::Value::Array(toml_arr?))
-        }
-        JsonValue::Object(obj) => {
-            let mut table = toml::map::Map::new();
-            for (k, v) in obj {
-                table.insert(k.clone(), json_to_toml_value(v)?);
-            }
-            Ok(toml::Value::Table(table))
-        }
+/// Format content in same format (just pretty print)
+fn format_content(content: &str, format: Format, options: &ConvertOptions) -> Result<String> {
+    if format.is_binary() {
+        bail!("{} is a binary format and cannot be read as text", format)
     }
+    format.handler()?.reformat(content, options)
 }
 
 // ============================================================================
 // CSV <-> JSON conversion
 // ============================================================================
 
-fn csv_to_json_value(content: &str) -> Result<JsonValue> {
-    let data = csv_format::parse(content, true)?;
+/// Whether `format` is CSV or TSV
+fn is_delimited(format: Format) -> bool {
+    matches!(format, Format::Csv | Format::Tsv)
+}
+
+/// CSV read options for `format`; TSV is always split on tabs
+fn read_options(format: Format, options: &ConvertOptions) -> CsvReadOptions {
+    let mut read = options.csv.clone();
+    if format == Format::Tsv {
+        read.delimiter = Some(b'\t');
+    }
+    read
+}
+
+/// Field delimiter written for `format`: tab for TSV, `--delimiter` or a comma for CSV
+fn write_delimiter(format: Format, options: &ConvertOptions) -> u8 {
+    match format {
+        Format::Tsv => b'\t',
+        _ => options.csv.delimiter.unwrap_or(b','),
+    }
+}
+
+fn csv_to_json_value(content: &str, options: &CsvReadOptions) -> Result<JsonValue> {
+    csv_data_to_json_value(csv_format::parse_with_options(content, true, options)?)
+}
 
+/// Turn table rows into an array of objects keyed by the headers
+fn csv_data_to_json_value(data: CsvData) -> Result<JsonValue> {
     let headers = data
         .headers
         .as_ref()
@@ -204,24 +334,7 @@ fn csv_to_json_value(content: &str) -> Result<JsonValue> {
                 .cloned()
                 .unwrap_or_else(|| format!("column_{}", i));
 
-            // Try to parse as number or boolean
-            let value = if let Ok(n) = cell.parse::<i64>() {
-                JsonValue::Number(n.into())
-            } else if let Ok(f) = cell.parse::<f64>() {
-                serde_json::Number::from_f64(f)
-                    .map(JsonValue::Number)
-                    .unwrap_or(JsonValue::String(cell.clone()))
-            } else if cell.eq_ignore_ascii_case("true") {
-                JsonValue::Bool(true)
-            } else if cell.eq_ignore_ascii_case("false") {
-                JsonValue::Bool(false)
-            } else if cell.is_empty() || cell.eq_ignore_ascii_case("null") {
-                JsonValue::Null
-            } else {
-                JsonValue::String(cell.clone())
-            };
-
-            obj.insert(key, value);
+            obj.insert(key, csv_cell_to_json(cell));
         }
         records.push(JsonValue::Object(obj));
     }
@@ -229,7 +342,105 @@ fn csv_to_json_value(content: &str) -> Result<JsonValue> {
     Ok(JsonValue::Array(records))
 }
 
-fn json_to_csv(value: &JsonValue) -> Result<String> {
+/// Interpret a CSV cell as a number, boolean, null or string
+fn csv_cell_to_json(cell: &str) -> JsonValue {
+    if let Ok(n) = cell.parse::<i64>() {
+        JsonValue::Number(n.into())
+    } else if let Ok(f) = cell.parse::<f64>() {
+        serde_json::Number::from_f64(f)
+            .map(JsonValue::Number)
+            .unwrap_or_else(|| JsonValue::String(cell.to_string()))
+    } else if cell.eq_ignore_ascii_case("true") {
+        JsonValue::Bool(true)
+    } else if cell.eq_ignore_ascii_case("false") {
+        JsonValue::Bool(false)
+    } else if cell.is_empty() || cell.eq_ignore_ascii_case("null") {
+        JsonValue::Null
+    } else {
+        JsonValue::String(cell.to_string())
+    }
+}
+
+/// Nest denormalized rows: rows sharing a `key` value become one parent object
+///
+/// Columns that are constant within every group stay on the parent; the rest
+/// move into child records under `children`.
+fn group_rows(value: &JsonValue, key: &str, children: &str) -> Result<JsonValue> {
+    let rows = value
+        .as_array()
+        .context("Grouping requires tabular input")?;
+    if let Some(first) = rows.first() {
+        if first.get(key).is_none() {
+            bail!("Group-by column '{}' not found", key);
+        }
+    }
+
+    // Group rows by key value, keeping first-appearance order
+    let mut groups: Vec<Vec<&serde_json::Map<String, JsonValue>>> = Vec::new();
+    let mut index = std::collections::HashMap::new();
+    for row in rows {
+        let obj = row.as_object().context("Grouping requires tabular input")?;
+        let group_key = obj.get(key).map(JsonValue::to_string).unwrap_or_default();
+        let position = *index.entry(group_key).or_insert_with(|| {
+            groups.push(Vec::new());
+            groups.len() - 1
+        });
+        groups[position].push(obj);
+    }
+
+    let columns: Vec<&String> = rows
+        .iter()
+        .filter_map(|r| r.as_object())
+        .flat_map(|obj| obj.keys())
+        .fold(Vec::new(), |mut acc, k| {
+            if !acc.contains(&k) {
+                acc.push(k);
+            }
+            acc
+        });
+    let is_parent_column = |column: &str| {
+        column == key
+            || groups.iter().all(|members| {
+                members
+                    .windows(2)
+                    .all(|pair| pair[0].get(column) == pair[1].get(column))
+            })
+    };
+    let (parent_columns, child_columns): (Vec<&String>, Vec<&String>) =
+        columns.into_iter().partition(|c| is_parent_column(c));
+
+    let mut result = Vec::new();
+    for members in &groups {
+        let mut parent = serde_json::Map::new();
+        for column in &parent_columns {
+            if let Some(value) = members[0].get(column.as_str()) {
+                parent.insert(column.to_string(), value.clone());
+            }
+        }
+
+        let items: Vec<JsonValue> = members
+            .iter()
+            .map(|row| {
+                child_columns
+                    .iter()
+                    .filter_map(|c| row.get(c.as_str()).map(|v| (c.to_string(), v.clone())))
+                    .collect::<serde_json::Map<_, _>>()
+            })
+            // Rows without any child data (e.g. from an outer join) add no child
+            .filter(|child| child.values().any(|v| !v.is_null()))
+            .map(JsonValue::Object)
+            .collect();
+
+        parent.insert(children.to_string(), JsonValue::Array(items));
+        result.push(JsonValue::Object(parent));
+    }
+
+    Ok(JsonValue::Array(result))
+}
+
+fn json_to_csv(value: &JsonValue, options: &ConvertOptions, delimiter: u8) -> Result<String> {
+    let null_as = options.null_as.as_deref().unwrap_or_default();
+    let float = options.style.float;
     let array = value
         .as_array()
         .context("JSON must be an array for CSV conversion")?;
@@ -254,16 +465,20 @@ fn json_to_csv(value: &JsonValue) -> Result<String> {
 
     if all_keys.is_empty() {
         // Array of primitives - single column
-        let mut writer = csv::Writer::from_writer(Vec::new());
+        let mut writer = csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .from_writer(Vec::new());
         writer.write_record(["value"])?;
         for item in array {
-            writer.write_record([json_value_to_string(item)])?;
+            writer.write_record([csv_cell(Some(item), null_as, float)])?;
         }
         let bytes = writer.into_inner()?;
         return String::from_utf8(bytes).context("Invalid UTF-8 in CSV output");
     }
 
-    let mut writer = csv::Writer::from_writer(Vec::new());
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
 
     // Write headers
     writer.write_record(&all_keys)?;
@@ -272,7 +487,7 @@ fn json_to_csv(value: &JsonValue) -> Result<String> {
     for item in array {
         let row: Vec<String> = all_keys
             .iter()
-            .map(|key| item.get(key).map(json_value_to_string).unwrap_or_default())
+            .map(|key| csv_cell(item.get(key), null_as, float))
             .collect();
         writer.write_record(&row)?;
     }
@@ -281,25 +496,192 @@ fn json_to_csv(value: &JsonValue) -> Result<String> {
     String::from_utf8(bytes).context("Invalid UTF-8 in CSV output")
 }
 
-fn json_value_to_string(value: &JsonValue) -> String {
+/// Text of a CSV cell; null and missing values are written as `null_as`
+fn csv_cell(value: Option<&JsonValue>, null_as: &str, float: Option<FloatFormat>) -> String {
+    match value {
+        None | Some(JsonValue::Null) => null_as.to_string(),
+        Some(value) => json_value_to_string(value, float),
+    }
+}
+
+/// Text of a value, writing floats with `float` when set
+fn json_value_to_string(value: &JsonValue, float: Option<FloatFormat>) -> String {
     match value {
         JsonValue::Null => String::new(),
         JsonValue::Bool(b) => b.to_string(),
-        JsonValue::Number(n) => n.to_string(),
+        JsonValue::Number(n) => float::number_text(n, float),
         JsonValue::String(s) => s.clone(),
         JsonValue::Array(arr) => {
-            let items: Vec<String> = arr.iter().map(json_value_to_string).collect();
+            let items: Vec<String> = arr.iter().map(|v| json_value_to_string(v, float)).collect();
             items.join(";")
         }
         JsonValue::Object(_) => serde_json::to_string(value).unwrap_or_default(),
     }
 }
 
+// ============================================================================
+// Streaming CSV <-> NDJSON conversion
+// ============================================================================
+
+/// Whether a conversion has a record-at-a-time streaming implementation
+pub fn supports_streaming(from: Format, to: Format) -> bool {
+    matches!(
+        (from, to),
+        (Format::Csv | Format::Tsv, Format::Ndjson) | (Format::Ndjson, Format::Csv | Format::Tsv)
+    )
+}
+
+/// Convert between CSV and NDJSON one record at a time
+///
+/// Returns the number of records written.
+pub fn convert_stream<R: BufRead, W: Write>(
+    reader: R,
+    writer: W,
+    from: Format,
+    to: Format,
+) -> Result<usize> {
+    convert_stream_with_options(reader, writer, from, to, &ConvertOptions::default())
+}
+
+/// Convert between CSV and NDJSON one record at a time with conversion options
+pub fn convert_stream_with_options<R: BufRead, W: Write>(
+    reader: R,
+    writer: W,
+    from: Format,
+    to: Format,
+    options: &ConvertOptions,
+) -> Result<usize> {
+    debug!(%from, %to, "streaming conversion");
+    match (from, to) {
+        (Format::Csv | Format::Tsv, Format::Ndjson) => {
+            let mut reader = reader;
+            let delimiter = match read_options(from, options).delimiter {
+                Some(delimiter) => delimiter,
+                None => sniff_buffered(&mut reader)?,
+            };
+            stream_csv_to_ndjson(reader, writer, delimiter, &options.style)
+        }
+        (Format::Ndjson, Format::Csv | Format::Tsv) => {
+            stream_ndjson_to_csv(reader, writer, options, write_delimiter(to, options))
+        }
+        _ => bail!(
+            "Streaming conversion from {} to {} is not supported",
+            from,
+            to
+        ),
+    }
+}
+
+/// Delimiter sniffed from the complete lines already buffered in `reader`
+fn sniff_buffered<R: BufRead>(reader: &mut R) -> Result<u8> {
+    let buffered = reader.fill_buf().context("Failed to read input")?;
+    let complete = match buffered.iter().rposition(|&b| b == b'\n') {
+        Some(end) => &buffered[..end],
+        None => buffered,
+    };
+    Ok(csv_format::sniff_delimiter(&String::from_utf8_lossy(complete)).unwrap_or(b','))
+}
+
+fn stream_csv_to_ndjson<R: Read, W: Write>(
+    reader: R,
+    writer: W,
+    delimiter: u8,
+    style: &OutputStyle,
+) -> Result<usize> {
+    let mut csv_reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(reader);
+    let headers = csv_reader
+        .headers()
+        .context("Failed to read CSV headers")?
+        .clone();
+
+    let mut writer = BufWriter::new(writer);
+    let mut record = csv::StringRecord::new();
+    let mut count = 0;
+
+    while csv_reader
+        .read_record(&mut record)
+        .context("Failed to parse CSV")?
+    {
+        let mut obj = serde_json::Map::new();
+        for (i, cell) in record.iter().enumerate() {
+            let key = headers
+                .get(i)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("column_{}", i));
+            obj.insert(key, csv_cell_to_json(cell));
+        }
+        ndjson_format::write_line(&mut writer, &JsonValue::Object(obj), style)?;
+        count += 1;
+    }
+
+    writer.flush().context("Failed to write output")?;
+    Ok(count)
+}
+
+fn stream_ndjson_to_csv<R: BufRead, W: Write>(
+    reader: R,
+    writer: W,
+    options: &ConvertOptions,
+    delimiter: u8,
+) -> Result<usize> {
+    let null_as = options.null_as.as_deref().unwrap_or_default();
+    let float = options.style.float;
+    let mut csv_writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(writer);
+    let mut columns: Option<Vec<String>> = None;
+    let mut count = 0;
+
+    for value in ndjson_format::LineStream::new(reader) {
+        let value = value?;
+        count += 1;
+
+        // Columns come from the first record since later ones aren't known yet
+        let columns = match columns {
+            Some(ref columns) => columns,
+            None => {
+                let header: Vec<String> = match value.as_object() {
+                    Some(obj) => obj.keys().cloned().collect(),
+                    None => vec!["value".to_string()],
+                };
+                csv_writer.write_record(&header)?;
+                columns.insert(header)
+            }
+        };
+
+        match value.as_object() {
+            Some(obj) => {
+                if let Some(extra) = obj.keys().find(|k| !columns.contains(k)) {
+                    bail!(
+                        "Record {} has field '{}' not present in the first record \
+                         (streaming takes CSV columns from the first record)",
+                        count,
+                        extra
+                    );
+                }
+                let row: Vec<String> = columns
+                    .iter()
+                    .map(|key| csv_cell(obj.get(key), null_as, float))
+                    .collect();
+                csv_writer.write_record(&row)?;
+            }
+            None => csv_writer.write_record([csv_cell(Some(&value), null_as, float)])?,
+        }
+    }
+
+    csv_writer.flush().context("Failed to write output")?;
+    Ok(count)
+}
+
 // ============================================================================
 // XML <-> JSON conversion
 // ============================================================================
 
-fn xml_to_json_value(content: &str) -> Result<JsonValue> {
+/// Parse XML into JSON; elements matching a `force_array` path are always arrays
+fn xml_to_json_value(content: &str, force_array: &[String]) -> Result<JsonValue> {
     use quick_xml::events::Event;
     use quick_xml::Reader;
 
@@ -309,6 +691,7 @@ fn xml_to_json_value(content: &str) -> Result<JsonValue> {
     let mut stack: Vec<(String, serde_json::Map<String, JsonValue>)> = Vec::new();
     let mut root: Option<JsonValue> = None;
     let mut current_text = String::new();
+    let mut entities = Entities::default();
 
     loop {
         match reader.read_event() {
@@ -319,7 +702,7 @@ fn xml_to_json_value(content: &str) -> Result<JsonValue> {
                 // Parse attributes
                 for attr in e.attributes().flatten() {
                     let key = format!("@{}", String::from_utf8_lossy(attr.key.as_ref()));
-                    let value = String::from_utf8_lossy(&attr.value).to_string();
+                    let value = entities.unescape(&String::from_utf8_lossy(&attr.value))?;
                     attrs.insert(key, JsonValue::String(value));
                 }
 
@@ -328,6 +711,7 @@ fn xml_to_json_value(content: &str) -> Result<JsonValue> {
             }
             Ok(Event::End(_)) => {
                 if let Some((name, mut attrs)) = stack.pop() {
+                    let force = forces_array(&stack, &name, force_array);
                     // Add text content if present
                     let trimmed_text = current_text.trim();
                     if !trimmed_text.is_empty() {
@@ -335,7 +719,7 @@ fn xml_to_json_value(content: &str) -> Result<JsonValue> {
                             // Just text content, use string value
                             let value = parse_xml_text_value(trimmed_text);
                             if let Some((_, parent_attrs)) = stack.last_mut() {
-                                add_to_xml_object(parent_attrs, &name, value);
+                                add_to_xml_object(parent_attrs, &name, value, force);
                             } else {
                                 let mut obj = serde_json::Map::new();
                                 obj.insert(name, value);
@@ -346,7 +730,7 @@ fn xml_to_json_value(content: &str) -> Result<JsonValue> {
                             attrs.insert("#text".to_string(), parse_xml_text_value(trimmed_text));
                             let value = JsonValue::Object(attrs);
                             if let Some((_, parent_attrs)) = stack.last_mut() {
-                                add_to_xml_object(parent_attrs, &name, value);
+                                add_to_xml_object(parent_attrs, &name, value, force);
                             } else {
                                 let mut obj = serde_json::Map::new();
                                 obj.insert(name, value);
@@ -356,7 +740,7 @@ fn xml_to_json_value(content: &str) -> Result<JsonValue> {
                     } else if !attrs.is_empty() {
                         let value = JsonValue::Object(attrs);
                         if let Some((_, parent_attrs)) = stack.last_mut() {
-                            add_to_xml_object(parent_attrs, &name, value);
+                            add_to_xml_object(parent_attrs, &name, value, force);
                         } else {
                             let mut obj = serde_json::Map::new();
                             obj.insert(name, value);
@@ -365,7 +749,7 @@ fn xml_to_json_value(content: &str) -> Result<JsonValue> {
                     } else {
                         // Empty element
                         if let Some((_, parent_attrs)) = stack.last_mut() {
-                            add_to_xml_object(parent_attrs, &name, JsonValue::Null);
+                            add_to_xml_object(parent_attrs, &name, JsonValue::Null, force);
                         } else {
                             let mut obj = serde_json::Map::new();
                             obj.insert(name, JsonValue::Null);
@@ -381,7 +765,7 @@ fn xml_to_json_value(content: &str) -> Result<JsonValue> {
 
                 for attr in e.attributes().flatten() {
                     let key = format!("@{}", String::from_utf8_lossy(attr.key.as_ref()));
-                    let value = String::from_utf8_lossy(&attr.value).to_string();
+                    let value = entities.unescape(&String::from_utf8_lossy(&attr.value))?;
                     attrs.insert(key, JsonValue::String(value));
                 }
 
@@ -390,9 +774,10 @@ fn xml_to_json_value(content: &str) -> Result<JsonValue> {
                 } else {
                     JsonValue::Object(attrs)
                 };
+                let force = forces_array(&stack, &name, force_array);
 
                 if let Some((_, parent_attrs)) = stack.last_mut() {
-                    add_to_xml_object(parent_attrs, &name, value);
+                    add_to_xml_object(parent_attrs, &name, value, force);
                 } else {
                     let mut obj = serde_json::Map::new();
                     obj.insert(name, value);
@@ -400,23 +785,206 @@ fn xml_to_json_value(content: &str) -> Result<JsonValue> {
                 }
             }
             Ok(Event::Text(e)) => {
-                let text = e.unescape().unwrap_or_default();
+                let text = entities.unescape(&String::from_utf8_lossy(&e))?;
                 current_text.push_str(&text);
             }
+            Ok(Event::DocType(e)) => {
+                entities = Entities::from_doctype(&String::from_utf8_lossy(&e));
+            }
             Ok(Event::CData(e)) => {
                 let text = String::from_utf8_lossy(&e);
                 current_text.push_str(&text);
             }
             Ok(Event::Eof) => break,
             Ok(_) => {}
-            Err(e) => bail!("XML parse error: {}", e),
+            Err(e) => {
+                let position = reader.error_position() as usize;
+                return Err(diagnostics::located_at(
+                    e,
+                    content,
+                    position,
+                    "Failed to parse XML",
+                ));
+            }
         }
     }
 
     root.context("Empty XML document")
 }
 
-fn add_to_xml_object(obj: &mut serde_json::Map<String, JsonValue>, key: &str, value: JsonValue) {
+/// Convert XML to CSV with one row per repeating record element
+fn xml_to_csv(content: &str, options: &ConvertOptions, delimiter: u8) -> Result<String> {
+    let value = xml_to_json_value(content, &options.force_array)?;
+    let record_path = options.record_path.as_deref();
+
+    let records = match record_path {
+        Some(path) => find_xml_records(&value, path)?,
+        None => {
+            let records = detect_xml_records(&value)
+                .context("Could not find repeating XML elements; use --record-path")?;
+            debug!(
+                count = records.len(),
+                "detected repeating XML record elements"
+            );
+            records
+        }
+    };
+
+    let rows: Vec<JsonValue> = records.into_iter().map(flatten_xml_record).collect();
+    json_to_csv(&JsonValue::Array(rows), options, delimiter)
+}
+
+/// Resolve a record path like `books/book`, with or without the root element name
+fn find_xml_records<'a>(value: &'a JsonValue, path: &str) -> Result<Vec<&'a JsonValue>> {
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+
+    let (root_name, root) = value
+        .as_object()
+        .and_then(|obj| obj.iter().next())
+        .context("Empty XML document")?;
+    let start = if segments.first() == Some(&root_name.as_str()) {
+        &segments[1..]
+    } else {
+        &segments[..]
+    };
+
+    // Segments step into every element of a repeated list
+    let mut current = vec![root];
+    for segment in start {
+        current = current
+            .into_iter()
+            .filter_map(|node| node.get(segment))
+            .flat_map(|child| match child {
+                JsonValue::Array(items) => items.iter().collect(),
+                other => vec![other],
+            })
+            .collect();
+        if current.is_empty() {
+            bail!("Record path '{}' not found in XML", path);
+        }
+    }
+
+    Ok(current)
+}
+
+/// Find the largest list of repeated elements, preferring the shallowest on ties
+fn detect_xml_records(value: &JsonValue) -> Option<Vec<&JsonValue>> {
+    fn visit<'a>(
+        value: &'a JsonValue,
+        depth: usize,
+        best: &mut Option<(usize, usize, &'a [JsonValue])>,
+    ) {
+        match value {
+            JsonValue::Array(items) => {
+                let better = match best {
+                    Some((len, best_depth, _)) => {
+                        items.len() > *len || (items.len() == *len && depth < *best_depth)
+                    }
+                    None => true,
+                };
+                if better {
+                    *best = Some((items.len(), depth, items));
+                }
+                for item in items {
+                    visit(item, depth + 1, best);
+                }
+            }
+            JsonValue::Object(obj) => {
+                for child in obj.values() {
+                    visit(child, depth + 1, best);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut best = None;
+    visit(value, 0, &mut best);
+    best.map(|(_, _, items)| items.iter().collect())
+}
+
+/// Flatten a record element into columns: attributes lose their `@` prefix,
+/// nested children become `parent.child`, and element text uses the element name
+fn flatten_xml_record(record: &JsonValue) -> JsonValue {
+    let mut columns = serde_json::Map::new();
+    match record {
+        JsonValue::Object(_) => flatten_xml_into(record, "", &mut columns),
+        other => {
+            columns.insert("value".to_string(), other.clone());
+        }
+    }
+    JsonValue::Object(columns)
+}
+
+fn flatten_xml_into(
+    value: &JsonValue,
+    prefix: &str,
+    columns: &mut serde_json::Map<String, JsonValue>,
+) {
+    let join = |name: &str| {
+        if prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}.{}", prefix, name)
+        }
+    };
+
+    match value {
+        JsonValue::Object(obj) => {
+            for (key, child) in obj {
+                let column = if key == "#text" {
+                    if prefix.is_empty() {
+                        "text".to_string()
+                    } else {
+                        prefix.to_string()
+                    }
+                } else {
+                    // Keep the `@` only when an attribute and child element share a name
+                    match key.strip_prefix('@') {
+                        Some(attr) if !obj.contains_key(attr) => join(attr),
+                        _ => join(key),
+                    }
+                };
+                flatten_xml_into(child, &column, columns);
+            }
+        }
+        JsonValue::Array(items) if items.iter().any(|i| i.is_object() || i.is_array()) => {
+            for (i, item) in items.iter().enumerate() {
+                flatten_xml_into(item, &join(&i.to_string()), columns);
+            }
+        }
+        _ => {
+            columns.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+/// Whether the element `name` below the open elements in `stack` matches a `--force-array` path
+fn forces_array(
+    stack: &[(String, serde_json::Map<String, JsonValue>)],
+    name: &str,
+    force_array: &[String],
+) -> bool {
+    if force_array.is_empty() || stack.is_empty() {
+        return false;
+    }
+    let path = stack
+        .iter()
+        .map(|(parent, _)| parent.as_str())
+        .chain(std::iter::once(name))
+        .collect::<Vec<_>>()
+        .join("/");
+    force_array
+        .iter()
+        .any(|p| xml_format::path_matches(&path, p))
+}
+
+fn add_to_xml_object(
+    obj: &mut serde_json::Map<String, JsonValue>,
+    key: &str,
+    value: JsonValue,
+    force_array: bool,
+) {
     if let Some(existing) = obj.get_mut(key) {
         // Key already exists, convert to array or append to existing array
         match existing {
@@ -428,6 +996,8 @@ fn add_to_xml_object(obj: &mut serde_json::Map<String, JsonValue>, key: &str, va
                 *existing = JsonValue::Array(vec![old, value]);
             }
         }
+    } else if force_array {
+        obj.insert(key.to_string(), JsonValue::Array(vec![value]));
     } else {
         obj.insert(key.to_string(), value);
     }
@@ -452,7 +1022,7 @@ fn parse_xml_text_value(text: &str) -> JsonValue {
     }
 }
 
-fn json_to_xml(value: &JsonValue) -> Result<String> {
+fn json_to_xml(value: &JsonValue, options: &ConvertOptions) -> Result<String> {
     let mut output = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
 
     match value {
@@ -460,12 +1030,12 @@ fn json_to_xml(value: &JsonValue) -> Result<String> {
             if obj.len() == 1 {
                 // Single root element
                 let (key, val) = obj.iter().next().unwrap();
-                json_to_xml_element(&mut output, key, val, 0)?;
+                json_to_xml_element(&mut output, key, val, 0, "", options)?;
             } else {
                 // Wrap in root element
                 output.push_str("<root>\n");
                 for (key, val) in obj {
-                    json_to_xml_element(&mut output, key, val, 1)?;
+                    json_to_xml_element(&mut output, key, val, 1, "root", options)?;
                 }
                 output.push_str("</root>");
             }
@@ -473,13 +1043,17 @@ fn json_to_xml(value: &JsonValue) -> Result<String> {
         JsonValue::Array(arr) => {
             output.push_str("<root>\n");
             for item in arr {
-                json_to_xml_element(&mut output, "item", item, 1)?;
+                json_to_xml_element(&mut output, "item", item, 1, "root", options)?;
             }
             output.push_str("</root>");
         }
         _ => {
             output.push_str("<root>");
-            output.push_str(&escape_xml(&json_value_to_string(value)));
+            output.push_str(&xml_text(
+                &json_value_to_string(value, options.style.float),
+                "root",
+                &options.cdata,
+            ));
             output.push_str("</root>");
         }
     }
@@ -492,8 +1066,17 @@ fn json_to_xml_element(
     tag: &str,
     value: &JsonValue,
     indent: usize,
+    parent: &str,
+    options: &ConvertOptions,
 ) -> Result<()> {
-    let indent_str = "  ".repeat(indent);
+    let unit = options.style.indent.unwrap_or_default().unit();
+    let indent_str = unit.repeat(indent);
+    let cdata = &options.cdata;
+    let path = if parent.is_empty() {
+        tag.to_string()
+    } else {
+        format!("{}/{}", parent, tag)
+    };
 
     // Skip attribute keys when processing as elements
     if tag.starts_with('@') {
@@ -508,6 +1091,7 @@ fn json_to_xml_element(
             output.push_str(&format!("{}<{}>{}</{}>\n", indent_str, tag, b, tag));
         }
         JsonValue::Number(n) => {
+            let n = float::number_text(n, options.style.float);
             output.push_str(&format!("{}<{}>{}</{}>\n", indent_str, tag, n, tag));
         }
         JsonValue::String(s) => {
@@ -515,13 +1099,13 @@ fn json_to_xml_element(
                 "{}<{}>{}</{}>\n",
                 indent_str,
                 tag,
-                escape_xml(s),
+                xml_text(s, &path, cdata),
                 tag
             ));
         }
         JsonValue::Array(arr) => {
             for item in arr {
-                json_to_xml_element(output, tag, item, indent)?;
+                json_to_xml_element(output, tag, item, indent, parent, options)?;
             }
         }
         JsonValue::Object(obj) => {
@@ -539,12 +1123,12 @@ fn json_to_xml_element(
                         attrs.push_str(&format!(
                             " {}=\"{}\"",
                             attr_name,
-                            json_value_to_string(val)
+                            json_value_to_string(val, options.style.float)
                         ));
                     }
                 } else if key == "#text" {
                     // Text content
-                    text_content = Some(json_value_to_string(val));
+                    text_content = Some(json_value_to_string(val, options.style.float));
                 } else {
                     children.push((key.clone(), val.clone()));
                 }
@@ -557,7 +1141,7 @@ fn json_to_xml_element(
                         indent_str,
                         tag,
                         attrs,
-                        escape_xml(&text),
+                        xml_text(&text, &path, cdata),
                         tag
                     ));
                 } else {
@@ -566,10 +1150,15 @@ fn json_to_xml_element(
             } else {
                 output.push_str(&format!("{}<{}{}>\n", indent_str, tag, attrs));
                 if let Some(text) = text_content {
-                    output.push_str(&format!("{}  {}\n", indent_str, escape_xml(&text)));
+                    output.push_str(&format!(
+                        "{}{}{}\n",
+                        indent_str,
+                        unit,
+                        xml_text(&text, &path, cdata)
+                    ));
                 }
                 for (key, val) in children {
-                    json_to_xml_element(output, &key, &val, indent + 1)?;
+                    json_to_xml_element(output, &key, &val, indent + 1, &path, options)?;
                 }
                 output.push_str(&format!("{}</{}>\n", indent_str, tag));
             }
@@ -579,6 +1168,15 @@ fn json_to_xml_element(
     Ok(())
 }
 
+/// Escaped text, or a CDATA section when the options select it
+fn xml_text(text: &str, path: &str, cdata: &CdataOptions) -> String {
+    if cdata.applies(path, text) {
+        xml_format::cdata(text)
+    } else {
+        escape_xml(text)
+    }
+}
+
 fn escape_xml(s: &str) -> String {
     s.replace('&', "&amp;")
         .replace('<', "&lt;")
@@ -589,40 +1187,919 @@ fn escape_xml_attr(s: &str) -> String {
     escape_xml(s).replace('"', "&quot;").replace('\'', "&apos;")
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+// ============================================================================
+// Built-in format handlers
+// ============================================================================
 
-    #[test]
-    fn test_json_to_yaml() {
-        let json = r#"{"name": "test", "value": 42}"#;
-        let result = convert(json, Format::Json, Format::Yaml).unwrap();
-        assert!(result.contains("name:"));
-        assert!(result.contains("test"));
+/// Handlers of the built-in formats, in the order content detection asks them
+pub(crate) fn builtin_handlers() -> Vec<Arc<dyn FormatHandler>> {
+    vec![
+        Arc::new(XmlHandler),
+        Arc::new(JsonHandler),
+        Arc::new(NdjsonHandler),
+        Arc::new(TomlHandler),
+        Arc::new(DelimitedHandler(Format::Tsv)),
+        Arc::new(DelimitedHandler(Format::Csv)),
+        Arc::new(YamlHandler),
+        Arc::new(MsgPackHandler),
+        Arc::new(ParquetHandler),
+        Arc::new(XlsxHandler),
+        Arc::new(HtmlHandler),
+        Arc::new(SqlHandler),
+        Arc::new(PlistHandler),
+        Arc::new(BinaryPlistHandler),
+        Arc::new(PropertiesHandler),
+        Arc::new(HclHandler),
+        Arc::new(ProtobufHandler),
+        Arc::new(AvroHandler),
+        Arc::new(BsonHandler),
+        Arc::new(SqliteHandler),
+    ]
+}
+
+struct JsonHandler;
+
+impl FormatHandler for JsonHandler {
+    fn name(&self) -> &'static str {
+        "json"
     }
 
-    #[test]
-    fn test_yaml_to_json() {
-        let yaml = "name: test\nvalue: 42";
-        let result = convert(yaml, Format::Yaml, Format::Json).unwrap();
-        assert!(result.contains("\"name\""));
-        assert!(result.contains("\"test\""));
+    fn extensions(&self) -> &[&'static str] {
+        &["json"]
     }
 
-    #[test]
-    fn test_json_to_toml() {
-        let json = r#"{"section": {"key": "value"}}"#;
-        let result = convert(json, Format::Json, Format::Toml).unwrap();
-        assert!(result.contains("[section]"));
-        assert!(result.contains("key"));
+    fn media_type(&self) -> &'static str {
+        "application/json"
     }
 
-    #[test]
-    fn test_json_to_csv() {
-        let json = r#"[{"name": "a", "value": 1}, {"name": "b", "value": 2}]"#;
-        let result = convert(json, Format::Json, Format::Csv).unwrap();
-        assert!(result.contains("name"));
-        assert!(result.contains("value"));
+    fn detect(&self, content: &str) -> bool {
+        detect::is_likely_json(content)
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        json_format::parse(utf8(input)?)
+    }
+
+    fn serialize(&self, value: &JsonValue, options: &ConvertOptions) -> Result<Vec<u8>> {
+        json_format::to_pretty(value, &options.style).map(String::into_bytes)
+    }
+}
+
+struct YamlHandler;
+
+impl FormatHandler for YamlHandler {
+    fn name(&self) -> &'static str {
+        "yaml"
+    }
+
+    fn aliases(&self) -> &[&'static str] {
+        &["yml"]
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["yaml", "yml"]
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/yaml"
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        detect::is_likely_yaml(content)
+    }
+
+    fn parse(&self, input: &[u8], options: &ConvertOptions) -> Result<JsonValue> {
+        let yaml_value = yaml_format::parse(utf8(input)?, options.yaml_version)?;
+        Ok(DtxValue::from(yaml_value).into_json())
+    }
+
+    fn serialize(&self, value: &JsonValue, options: &ConvertOptions) -> Result<Vec<u8>> {
+        let yaml = if options.style.float.is_some() {
+            yaml_format::to_pretty(&serde_yaml::to_value(value)?, &options.style)?
+        } else {
+            serde_yaml::to_string(value).context("Failed to serialize YAML")?
+        };
+        Ok(yaml.into_bytes())
+    }
+
+    fn reformat(&self, content: &str, options: &ConvertOptions) -> Result<String> {
+        let value = yaml_format::parse(content, options.yaml_version)?;
+        yaml_format::to_pretty(&value, &options.style)
+    }
+}
+
+struct TomlHandler;
+
+impl FormatHandler for TomlHandler {
+    fn name(&self) -> &'static str {
+        "toml"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["toml"]
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/toml"
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        detect::is_likely_toml(content)
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        let toml_value = toml_format::parse(utf8(input)?)?;
+        Ok(DtxValue::from(toml_value).into_json())
+    }
+
+    fn serialize(&self, value: &JsonValue, options: &ConvertOptions) -> Result<Vec<u8>> {
+        let toml_value = DtxValue::from(value.clone()).to_toml();
+        toml_format::to_pretty_with_layout(&toml_value, &options.toml, &options.style)
+            .map(String::into_bytes)
+    }
+
+    fn reformat(&self, content: &str, options: &ConvertOptions) -> Result<String> {
+        let value = toml_format::parse(content)?;
+        toml_format::to_pretty_with_layout(&value, &options.toml, &options.style)
+    }
+}
+
+/// CSV or TSV, which differ only in their delimiter
+struct DelimitedHandler(Format);
+
+impl FormatHandler for DelimitedHandler {
+    fn name(&self) -> &'static str {
+        self.0.as_str()
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        match self.0 {
+            Format::Tsv => &["tsv", "tab"],
+            _ => &["csv"],
+        }
+    }
+
+    fn media_type(&self) -> &'static str {
+        match self.0 {
+            Format::Tsv => "text/tab-separated-values",
+            _ => "text/csv",
+        }
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        match detect::csv_delimiter(content) {
+            Some(delimiter) => (delimiter == b'\t') == (self.0 == Format::Tsv),
+            None => false,
+        }
+    }
+
+    fn parse(&self, input: &[u8], options: &ConvertOptions) -> Result<JsonValue> {
+        csv_to_json_value(utf8(input)?, &read_options(self.0, options))
+    }
+
+    fn serialize(&self, value: &JsonValue, options: &ConvertOptions) -> Result<Vec<u8>> {
+        json_to_csv(value, options, write_delimiter(self.0, options)).map(String::into_bytes)
+    }
+
+    fn reformat(&self, content: &str, options: &ConvertOptions) -> Result<String> {
+        let read = read_options(self.0, options);
+        let data = csv_format::parse_with_options(content, true, &read)?;
+        csv_format::to_delimited(&data, write_delimiter(self.0, options))
+    }
+}
+
+struct XmlHandler;
+
+impl FormatHandler for XmlHandler {
+    fn name(&self) -> &'static str {
+        "xml"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["xml", "xhtml", "svg", "xsd", "xsl"]
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/xml"
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        detect::is_likely_xml(content)
+    }
+
+    fn parse(&self, input: &[u8], options: &ConvertOptions) -> Result<JsonValue> {
+        xml_to_json_value(utf8(input)?, &options.force_array)
+    }
+
+    fn serialize(&self, value: &JsonValue, options: &ConvertOptions) -> Result<Vec<u8>> {
+        json_to_xml(value, options).map(String::into_bytes)
+    }
+
+    fn reformat(&self, content: &str, options: &ConvertOptions) -> Result<String> {
+        xml_format::to_pretty(content, &options.style)
+    }
+}
+
+struct NdjsonHandler;
+
+impl FormatHandler for NdjsonHandler {
+    fn name(&self) -> &'static str {
+        "ndjson"
+    }
+
+    fn aliases(&self) -> &[&'static str] {
+        &["jsonl"]
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["ndjson", "jsonl"]
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/x-ndjson"
+    }
+
+    fn detect(&self, content: &str) -> bool {
+        detect::is_likely_ndjson(content)
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        Ok(JsonValue::Array(ndjson_format::parse(utf8(input)?)?))
+    }
+
+    fn serialize(&self, value: &JsonValue, options: &ConvertOptions) -> Result<Vec<u8>> {
+        let ndjson = match value {
+            JsonValue::Array(items) => ndjson_format::to_string(items, &options.style)?,
+            other => ndjson_format::to_string(std::slice::from_ref(other), &options.style)?,
+        };
+        Ok(ndjson.into_bytes())
+    }
+}
+
+struct MsgPackHandler;
+
+impl FormatHandler for MsgPackHandler {
+    fn name(&self) -> &'static str {
+        "msgpack"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["msgpack", "mpk"]
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        msgpack_format::parse(input)
+    }
+
+    fn serialize(&self, value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+        Ok(msgpack_format::to_vec(value))
+    }
+}
+
+struct ParquetHandler;
+
+impl FormatHandler for ParquetHandler {
+    fn name(&self) -> &'static str {
+        "parquet"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["parquet"]
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/vnd.apache.parquet"
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        parquet_format::parse(input)
+    }
+
+    fn serialize(&self, _value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+        bail!("Writing Parquet is not supported; it can only be read")
+    }
+}
+
+struct XlsxHandler;
+
+impl FormatHandler for XlsxHandler {
+    fn name(&self) -> &'static str {
+        "xlsx"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["xlsx"]
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"
+    }
+
+    fn parse(&self, input: &[u8], options: &ConvertOptions) -> Result<JsonValue> {
+        csv_data_to_json_value(xlsx_format::read(input, options.sheet.as_deref())?)
+    }
+
+    fn serialize(&self, value: &JsonValue, options: &ConvertOptions) -> Result<Vec<u8>> {
+        xlsx_format::write(value, options.sheet.as_deref())
+    }
+}
+
+struct HtmlHandler;
+
+impl FormatHandler for HtmlHandler {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn aliases(&self) -> &[&'static str] {
+        &["htm"]
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["html", "htm"]
+    }
+
+    fn media_type(&self) -> &'static str {
+        "text/html"
+    }
+
+    fn parse(&self, input: &[u8], options: &ConvertOptions) -> Result<JsonValue> {
+        csv_data_to_json_value(html_format::read_table(utf8(input)?, html_table(options)?)?)
+    }
+
+    fn serialize(&self, _value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+        bail!("Writing HTML is not supported; tables can only be read from it")
+    }
+
+    fn reformat(&self, _content: &str, _options: &ConvertOptions) -> Result<String> {
+        bail!("Writing HTML is not supported; tables can only be read from it")
+    }
+}
+
+struct SqlHandler;
+
+impl FormatHandler for SqlHandler {
+    fn name(&self) -> &'static str {
+        "sql"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["sql"]
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/sql"
+    }
+
+    fn parse(&self, _input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        bail!("Reading SQL is not supported; it can only be written")
+    }
+
+    fn serialize(&self, value: &JsonValue, options: &ConvertOptions) -> Result<Vec<u8>> {
+        sql_format::to_string(value, &options.sql).map(String::into_bytes)
+    }
+}
+
+struct PlistHandler;
+
+impl FormatHandler for PlistHandler {
+    fn name(&self) -> &'static str {
+        "plist"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["plist"]
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/x-plist"
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        plist_format::parse_str(utf8(input)?)
+    }
+
+    fn serialize(&self, value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+        Ok(plist_format::to_xml(value).into_bytes())
+    }
+
+    fn reformat(&self, content: &str, _options: &ConvertOptions) -> Result<String> {
+        String::from_utf8(plist_format::transcode(content.as_bytes(), false)?)
+            .context("Plist output is not valid UTF-8")
+    }
+}
+
+struct BinaryPlistHandler;
+
+impl FormatHandler for BinaryPlistHandler {
+    fn name(&self) -> &'static str {
+        "bplist"
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/x-bplist"
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        plist_format::parse(input)
+    }
+
+    fn serialize(&self, value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+        Ok(plist_format::to_binary(value))
+    }
+}
+
+struct PropertiesHandler;
+
+impl FormatHandler for PropertiesHandler {
+    fn name(&self) -> &'static str {
+        "properties"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["properties"]
+    }
+
+    fn media_type(&self) -> &'static str {
+        "text/x-java-properties"
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        properties_format::parse(utf8(input)?)
+    }
+
+    fn serialize(&self, value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+        properties_format::to_string(value).map(String::into_bytes)
+    }
+}
+
+struct HclHandler;
+
+impl FormatHandler for HclHandler {
+    fn name(&self) -> &'static str {
+        "hcl"
+    }
+
+    fn aliases(&self) -> &[&'static str] {
+        &["tf"]
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["hcl", "tf", "tfvars"]
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/hcl"
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        hcl_format::parse(utf8(input)?)
+    }
+
+    fn serialize(&self, _value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+        bail!("Writing HCL is not supported; it can only be read")
+    }
+}
+
+struct ProtobufHandler;
+
+impl FormatHandler for ProtobufHandler {
+    fn name(&self) -> &'static str {
+        "proto"
+    }
+
+    fn aliases(&self) -> &[&'static str] {
+        &["protobuf"]
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["pb"]
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/x-protobuf"
+    }
+
+    fn parse(&self, input: &[u8], options: &ConvertOptions) -> Result<JsonValue> {
+        proto_format::decode(input, proto_message(options)?)
+    }
+
+    fn serialize(&self, value: &JsonValue, options: &ConvertOptions) -> Result<Vec<u8>> {
+        proto_format::encode(value, proto_message(options)?)
+    }
+}
+
+struct AvroHandler;
+
+impl FormatHandler for AvroHandler {
+    fn name(&self) -> &'static str {
+        "avro"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["avro"]
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/avro"
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        avro_format::parse(input)
+    }
+
+    fn serialize(&self, value: &JsonValue, options: &ConvertOptions) -> Result<Vec<u8>> {
+        avro_format::to_vec(value, avro_schema(options)?)
+    }
+}
+
+struct BsonHandler;
+
+impl FormatHandler for BsonHandler {
+    fn name(&self) -> &'static str {
+        "bson"
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["bson"]
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/bson"
+    }
+
+    fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+        bson_format::parse(input)
+    }
+
+    fn serialize(&self, value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+        bson_format::to_vec(value)
+    }
+}
+
+struct SqliteHandler;
+
+impl FormatHandler for SqliteHandler {
+    fn name(&self) -> &'static str {
+        "sqlite"
+    }
+
+    fn aliases(&self) -> &[&'static str] {
+        &["sqlite3"]
+    }
+
+    fn extensions(&self) -> &[&'static str] {
+        &["db", "sqlite", "sqlite3"]
+    }
+
+    fn is_binary(&self) -> bool {
+        true
+    }
+
+    fn media_type(&self) -> &'static str {
+        "application/vnd.sqlite3"
+    }
+
+    fn parse(&self, input: &[u8], options: &ConvertOptions) -> Result<JsonValue> {
+        sqlite_format::read(input, options.table.as_deref())
+    }
+
+    fn serialize(&self, _value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+        bail!("Writing SQLite is not supported; use --to sql for INSERT statements")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_to_yaml() {
+        let json = r#"{"name": "test", "value": 42}"#;
+        let result = convert(json, Format::Json, Format::Yaml).unwrap();
+        assert!(result.contains("name:"));
+        assert!(result.contains("test"));
+    }
+
+    #[test]
+    fn test_yaml_to_json() {
+        let yaml = "name: test\nvalue: 42";
+        let result = convert(yaml, Format::Yaml, Format::Json).unwrap();
+        assert!(result.contains("\"name\""));
+        assert!(result.contains("\"test\""));
+    }
+
+    #[test]
+    fn test_json_to_toml() {
+        let json = r#"{"section": {"key": "value"}}"#;
+        let result = convert(json, Format::Json, Format::Toml).unwrap();
+        assert!(result.contains("[section]"));
+        assert!(result.contains("key"));
+    }
+
+    #[test]
+    fn test_toml_datetime_round_trip() {
+        let toml = "created = 2024-01-01T00:00:00Z\nday = 1979-05-27\nlabel = \"v1\"\n";
+        let json = convert(toml, Format::Toml, Format::Json).unwrap();
+        assert!(json.contains("\"2024-01-01T00:00:00Z\""));
+
+        // Through the lossless value the dates stay dates
+        let value = DtxValue::parse(toml, Format::Toml, YamlVersion::default()).unwrap();
+        let back = value
+            .write(
+                Format::Toml,
+                &TomlLayout::default(),
+                &OutputStyle::default(),
+            )
+            .unwrap();
+        assert_eq!(back, toml);
+
+        // JSON has no dates, so its strings stay strings
+        let back = convert(&json, Format::Json, Format::Toml).unwrap();
+        assert!(back.contains("created = \"2024-01-01T00:00:00Z\""));
+        assert!(back.contains("day = \"1979-05-27\""));
+    }
+
+    #[test]
+    fn test_json_to_csv() {
+        let json = r#"[{"name": "a", "value": 1}, {"name": "b", "value": 2}]"#;
+        let result = convert(json, Format::Json, Format::Csv).unwrap();
+        assert!(result.contains("name"));
+        assert!(result.contains("value"));
+    }
+
+    #[test]
+    fn test_csv_null_as() {
+        let json = r#"[{"id": 1, "note": null}, {"id": 2, "note": ""}, {"id": 3}]"#;
+        let options = ConvertOptions {
+            null_as: Some("\\N".to_string()),
+            ..Default::default()
+        };
+        let result = convert_with_options(json, Format::Json, Format::Csv, &options).unwrap();
+        assert_eq!(result, "id,note\n1,\\N\n2,\n3,\\N\n");
+    }
+
+    #[test]
+    fn test_stream_csv_ndjson_round_trip() {
+        let csv = "name,age,active\nAlice,30,true\nBob,,false\n";
+        let mut ndjson = Vec::new();
+        let count =
+            convert_stream(csv.as_bytes(), &mut ndjson, Format::Csv, Format::Ndjson).unwrap();
+        assert_eq!(count, 2);
+        let ndjson = String::from_utf8(ndjson).unwrap();
+        assert_eq!(
+            ndjson.lines().next().unwrap(),
+            r#"{"name":"Alice","age":30,"active":true}"#
+        );
+
+        let mut back = Vec::new();
+        convert_stream(ndjson.as_bytes(), &mut back, Format::Ndjson, Format::Csv).unwrap();
+        assert_eq!(
+            String::from_utf8(back).unwrap(),
+            "name,age,active\nAlice,30,true\nBob,,false\n"
+        );
+
+        let mixed = "{\"a\":1}\n{\"a\":2,\"b\":3}\n";
+        assert!(convert_stream(mixed.as_bytes(), Vec::new(), Format::Ndjson, Format::Csv).is_err());
+    }
+
+    #[test]
+    fn test_convert_bytes_msgpack() {
+        let options = ConvertOptions::default();
+        let packed = convert_bytes(
+            br#"{"a": [1, "x"]}"#,
+            Format::Json,
+            Format::MsgPack,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(packed, [0x81, 0xa1, b'a', 0x92, 0x01, 0xa1, b'x']);
+
+        let yaml = convert_bytes(&packed, Format::MsgPack, Format::Yaml, &options).unwrap();
+        assert_eq!(String::from_utf8(yaml).unwrap(), "a:\n- 1\n- x\n");
+        assert!(convert(r#"{"a": 1}"#, Format::Json, Format::MsgPack).is_err());
+    }
+
+    #[test]
+    fn test_tsv_and_delimiters() {
+        let tsv = "name\tcity\nAlice\tTokyo, JP\n";
+        let json = convert(tsv, Format::Tsv, Format::Json).unwrap();
+        let value: JsonValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["city"], "Tokyo, JP");
+        assert_eq!(
+            convert(&json, Format::Json, Format::Tsv).unwrap(),
+            "name\tcity\nAlice\tTokyo, JP\n"
+        );
+
+        // Semicolons are sniffed without --delimiter
+        let json = convert("a;b\n1;2\n", Format::Csv, Format::Json).unwrap();
+        assert_eq!(serde_json::from_str::<JsonValue>(&json).unwrap()[0]["b"], 2);
+
+        let options = ConvertOptions {
+            csv: CsvReadOptions::default().with_delimiter(Some("|")).unwrap(),
+            ..Default::default()
+        };
+        let csv = convert_with_options(&json, Format::Json, Format::Csv, &options).unwrap();
+        assert_eq!(csv, "a|b\n1|2\n");
+        assert!(CsvReadOptions::default()
+            .with_delimiter(Some("ab"))
+            .is_err());
+
+        let mut ndjson = Vec::new();
+        convert_stream(tsv.as_bytes(), &mut ndjson, Format::Csv, Format::Ndjson).unwrap();
+        assert_eq!(
+            String::from_utf8(ndjson).unwrap(),
+            "{\"name\":\"Alice\",\"city\":\"Tokyo, JP\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_xml_to_csv_records() {
+        let xml = r#"<library><name>City</name><books>
+            <book id="1"><title>A</title><author><name>X</name></author><tag>a</tag><tag>b</tag></book>
+            <book id="2"><title lang="fr">B</title><author><name>Y</name></author></book>
+        </books></library>"#;
+
+        let csv = convert(xml, Format::Xml, Format::Csv).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), "id,title,author.name,tag,title.lang");
+        assert_eq!(lines.next().unwrap(), "1,A,X,a;b,");
+        assert_eq!(lines.next().unwrap(), "2,B,Y,,fr");
+
+        let options = ConvertOptions {
+            record_path: Some("library/books/book".to_string()),
+            ..Default::default()
+        };
+        let explicit = convert_with_options(xml, Format::Xml, Format::Csv, &options).unwrap();
+        assert_eq!(explicit, csv);
+
+        let options = ConvertOptions {
+            record_path: Some("books/missing".to_string()),
+            ..Default::default()
+        };
+        assert!(convert_with_options(xml, Format::Xml, Format::Csv, &options).is_err());
+    }
+
+    #[test]
+    fn test_csv_skip_rows_and_comments() {
+        let csv = "Exported 2024-01-01\nsource: crm\nname,age\n# disabled\nAlice,30\n";
+        let options = ConvertOptions {
+            csv: CsvReadOptions::new(2, Some("#")).unwrap(),
+            ..Default::default()
+        };
+        let json = convert_with_options(csv, Format::Csv, Format::Json, &options).unwrap();
+        let value: JsonValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(value, serde_json::json!([{"name": "Alice", "age": 30}]));
+
+        assert!(CsvReadOptions::new(0, Some("//")).is_err());
+    }
+
+    #[test]
+    fn test_csv_date_formats() {
+        let csv = "name,born,seen\nAlice,03/04/1990,03/04/2024 10:15\n";
+        let options = ConvertOptions {
+            csv: CsvReadOptions::default()
+                .with_date_formats(&["%d/%m/%Y".to_string(), "seen=%d/%m/%Y %H:%M".to_string()])
+                .unwrap(),
+            ..Default::default()
+        };
+        let json = convert_with_options(csv, Format::Csv, Format::Json, &options).unwrap();
+        let value: JsonValue = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!([{"name": "Alice", "born": "1990-04-03", "seen": "2024-04-03T10:15:00"}])
+        );
+
+        let bad = "seen\nyesterday\n";
+        assert!(convert_with_options(bad, Format::Csv, Format::Json, &options).is_err());
+    }
+
+    #[test]
+    fn test_json_to_xml_cdata() {
+        let value =
+            serde_json::json!({"book": {"title": "A & B", "note": "plain", "html": "<b>]]></b>"}});
+        let options = ConvertOptions {
+            cdata: CdataOptions {
+                markup: true,
+                paths: vec!["book/note".to_string()],
+            },
+            ..Default::default()
+        };
+        let xml = json_to_xml(&value, &options).unwrap();
+        assert!(xml.contains("<title><![CDATA[A & B]]></title>"));
+        assert!(xml.contains("<note><![CDATA[plain]]></note>"));
+        assert!(xml.contains("<html><![CDATA[<b>]]]]><![CDATA[></b>]]></html>"));
+
+        let xml = json_to_xml(&value, &ConvertOptions::default()).unwrap();
+        assert!(xml.contains("<title>A &amp; B</title>"));
+    }
+
+    #[test]
+    fn test_xml_force_array() {
+        let xml = "<library><book><title>A</title><author>X</author></book><tag>t</tag></library>";
+        let force = vec!["book".to_string(), "book/author".to_string()];
+
+        let value = xml_to_json_value(xml, &force).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"library": {
+                "book": [{"title": "A", "author": ["X"]}],
+                "tag": "t"
+            }})
+        );
+
+        let plain = xml_to_json_value(xml, &[]).unwrap();
+        assert_eq!(plain["library"]["book"]["author"], "X");
+    }
+
+    #[test]
+    fn test_xml_attribute_order() {
+        let xml = "<r z=\"1\" b=\"2\" m=\"3\"><c y=\"a\" a=\"b\"/></r>";
+        let yaml = convert(xml, Format::Xml, Format::Yaml).unwrap();
+        assert_eq!(
+            yaml,
+            "r:\n  '@z': '1'\n  '@b': '2'\n  '@m': '3'\n  c:\n    '@y': a\n    '@a': b\n"
+        );
+        let back = convert(&yaml, Format::Yaml, Format::Xml).unwrap();
+        assert!(back.contains("<r z=\"1\" b=\"2\" m=\"3\">"));
+        assert!(back.contains("<c y=\"a\" a=\"b\"/>"));
+    }
+
+    #[test]
+    fn test_xml_entities() {
+        let xml = "<!DOCTYPE note [<!ENTITY co \"Acme &amp; Co\">]><note by=\"&co;\"><to>Hi &co;</to></note>";
+        let value = xml_to_json_value(xml, &[]).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({"note": {"@by": "Acme & Co", "to": "Hi Acme & Co"}})
+        );
+
+        let external = "<!DOCTYPE r [<!ENTITY x SYSTEM \"file:///etc/passwd\">]><r>&x;</r>";
+        assert!(xml_to_json_value(external, &[]).is_err());
+
+        let mut laughs = String::from("<!DOCTYPE r [<!ENTITY l0 \"lollollollol\">");
+        for i in 1..10 {
+            let refs = format!("&l{};", i - 1).repeat(10);
+            laughs.push_str(&format!("<!ENTITY l{} \"{}\">", i, refs));
+        }
+        laughs.push_str("]><r>&l9;</r>");
+        let err = xml_to_json_value(&laughs, &[]).unwrap_err();
+        assert!(err.to_string().contains("billion laughs"));
+    }
+
+    #[test]
+    fn test_group_rows() {
+        let csv = "order_id,customer,sku,qty\n1,Alice,A1,2\n1,Alice,B2,1\n2,Bob,A1,5\n3,Carol,,\n";
+        let options = ConvertOptions {
+            group_by: Some("order_id".to_string()),
+            ..Default::default()
+        };
+        let json = convert_with_options(csv, Format::Csv, Format::Json, &options).unwrap();
+        let value: JsonValue = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!([
+                {"order_id": 1, "customer": "Alice", "items": [
+                    {"sku": "A1", "qty": 2},
+                    {"sku": "B2", "qty": 1}
+                ]},
+                {"order_id": 2, "customer": "Bob", "items": [{"sku": "A1", "qty": 5}]},
+                {"order_id": 3, "customer": "Carol", "items": []}
+            ])
+        );
     }
 
     #[test]
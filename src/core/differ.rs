@@ -1,20 +1,34 @@
 //! Diff calculation engine for comparing data structures
 
 use anyhow::{Context, Result};
-use colored::Colorize;
+use colored::{Color, Colorize};
+use regex::Regex;
 use serde_json::Value as JsonValue;
-use similar::{ChangeTag, TextDiff};
+use similar::{ChangeTag, InlineChange, TextDiff};
 
-use crate::core::converter;
+use crate::core::converter::{self, ConvertOptions};
+use crate::core::patcher::PatchOptions;
+use crate::core::table;
 use crate::formats::detect::Format;
+use crate::formats::json as json_format;
+use crate::utils::{markdown, width};
+
+/// Unchanged lines shown around each change in unified diffs by default
+pub const DEFAULT_CONTEXT: usize = 3;
+
+/// Line width of side-by-side diffs when the terminal width is unknown
+pub const DEFAULT_SIDE_BY_SIDE_WIDTH: usize = 83;
+
+/// Characters of a value shown in a Markdown report cell before it is cut
+const MARKDOWN_VALUE_WIDTH: usize = 60;
 
 /// Diff output format
 #[derive(Debug, Clone, Copy)]
 pub enum DiffFormat {
-    /// Unified diff format
-    Unified,
-    /// Side-by-side comparison
-    SideBySide,
+    /// Unified diff format with this many lines of context
+    Unified { context: usize },
+    /// Side-by-side comparison fit to this many columns
+    SideBySide { width: usize },
     /// JSON patch format (RFC 6902)
     JsonPatch,
 }
@@ -26,56 +40,154 @@ pub fn diff(
     format1: Format,
     format2: Format,
     output_format: DiffFormat,
+    options: &PatchOptions,
 ) -> Result<String> {
     // Normalize both to JSON for comparison
-    let json1 = normalize_to_json(content1, format1)?;
-    let json2 = normalize_to_json(content2, format2)?;
+    let json1 = normalize_to_json(content1, format1, options)?;
+    let json2 = normalize_to_json(content2, format2, options)?;
 
     match output_format {
-        DiffFormat::Unified => unified_diff(&json1, &json2),
-        DiffFormat::SideBySide => side_by_side_diff(&json1, &json2),
-        DiffFormat::JsonPatch => json_patch_diff(&json1, &json2),
+        DiffFormat::Unified { context } => {
+            let (text1, text2) = comparable(&json1, &json2, options)?;
+            unified_diff(&text1, &text2, context)
+        }
+        DiffFormat::SideBySide { width } => {
+            let (text1, text2) = comparable(&json1, &json2, options)?;
+            side_by_side_diff(&text1, &text2, width)
+        }
+        DiffFormat::JsonPatch => json_patch_diff(&json1, &json2, options),
     }
 }
 
-fn normalize_to_json(content: &str, format: Format) -> Result<String> {
+/// Compile an `--ignore-value-pattern`, which has to match the whole value
+pub fn value_pattern(pattern: &str) -> Result<Regex> {
+    Regex::new(&format!("^(?:{})$", pattern))
+        .with_context(|| format!("Invalid value pattern: {}", pattern))
+}
+
+fn normalize_to_json(content: &str, format: Format, options: &PatchOptions) -> Result<String> {
     if format == Format::Json {
         // Parse and re-serialize for consistent formatting
-        let value: JsonValue = serde_json::from_str(content).context("Failed to parse JSON")?;
+        let value = json_format::parse(content)?;
         serde_json::to_string_pretty(&value).context("Failed to serialize JSON")
     } else {
         // Convert to JSON
-        converter::convert(content, format, Format::Json)
+        let options = ConvertOptions {
+            yaml_version: options.yaml_version,
+            ..Default::default()
+        };
+        converter::convert_with_options(content, format, Format::Json, &options)
+    }
+}
+
+/// Normalized JSON of both sides without ignored paths, and with masked values
+/// of the second side replaced by their counterparts from the first, so
+/// neither shows up as a change
+fn comparable(json1: &str, json2: &str, options: &PatchOptions) -> Result<(String, String)> {
+    if options.ignore.is_empty() && options.ignore_values.is_empty() {
+        return Ok((json1.to_string(), json2.to_string()));
+    }
+    let value1: JsonValue = serde_json::from_str(json1).context("Failed to parse JSON")?;
+    let value2: JsonValue = serde_json::from_str(json2).context("Failed to parse JSON")?;
+    let value1 = strip_ignored(&value1, "", options);
+    let mut value2 = strip_ignored(&value2, "", options);
+    if !options.ignore_values.is_empty() {
+        unmask_values(&value1, &mut value2, options);
+    }
+    Ok((
+        serde_json::to_string_pretty(&value1).context("Failed to serialize JSON")?,
+        serde_json::to_string_pretty(&value2).context("Failed to serialize JSON")?,
+    ))
+}
+
+/// Copy values that match an ignored value pattern on both sides from `old` into `new`
+fn unmask_values(old: &JsonValue, new: &mut JsonValue, options: &PatchOptions) {
+    match (old, new) {
+        (JsonValue::Object(old_obj), JsonValue::Object(new_obj)) => {
+            for (key, new_val) in new_obj.iter_mut() {
+                if let Some(old_val) = old_obj.get(key) {
+                    unmask_values(old_val, new_val, options);
+                }
+            }
+        }
+        (JsonValue::Array(old_arr), JsonValue::Array(new_arr)) => {
+            for (old_val, new_val) in old_arr.iter().zip(new_arr.iter_mut()) {
+                unmask_values(old_val, new_val, options);
+            }
+        }
+        (old, new) => {
+            if options.is_masked(old) && options.is_masked(new) {
+                *new = old.clone();
+            }
+        }
+    }
+}
+
+fn strip_ignored(value: &JsonValue, path: &str, options: &PatchOptions) -> JsonValue {
+    match value {
+        JsonValue::Object(obj) => JsonValue::Object(
+            obj.iter()
+                .filter_map(|(key, val)| {
+                    let child = format!("{}/{}", path, escape_json_pointer(key));
+                    (!options.is_ignored(&child))
+                        .then(|| (key.clone(), strip_ignored(val, &child, options)))
+                })
+                .collect(),
+        ),
+        JsonValue::Array(arr) => JsonValue::Array(
+            arr.iter()
+                .enumerate()
+                .map(|(i, val)| strip_ignored(val, &format!("{}/{}", path, i), options))
+                .collect(),
+        ),
+        _ => value.clone(),
     }
 }
 
-fn unified_diff(text1: &str, text2: &str) -> Result<String> {
+fn unified_diff(text1: &str, text2: &str, context: usize) -> Result<String> {
+    Ok(text_diff_with_context(text1, text2, "a", "b", context))
+}
+
+/// Colored unified line diff between two texts with the given header labels
+pub fn text_diff(text1: &str, text2: &str, label1: &str, label2: &str) -> String {
+    text_diff_with_context(text1, text2, label1, label2, DEFAULT_CONTEXT)
+}
+
+/// Colored unified line diff showing `context` unchanged lines around each change
+pub fn text_diff_with_context(
+    text1: &str,
+    text2: &str,
+    label1: &str,
+    label2: &str,
+    context: usize,
+) -> String {
     let diff = TextDiff::from_lines(text1, text2);
     let mut output = String::new();
 
     // Header
-    output.push_str(&format!("{}\n", "--- a".red()));
-    output.push_str(&format!("{}\n", "+++ b".green()));
+    output.push_str(&format!("{}\n", format!("--- {}", label1).red()));
+    output.push_str(&format!("{}\n", format!("+++ {}", label2).green()));
 
-    for (idx, group) in diff.grouped_ops(3).iter().enumerate() {
+    for (idx, group) in diff.grouped_ops(context).iter().enumerate() {
         if idx > 0 {
             output.push_str(&format!("{}\n", "...".dimmed()));
         }
 
         for op in group {
-            for change in diff.iter_changes(op) {
-                let (sign, style) = match change.tag() {
-                    ChangeTag::Delete => ("-", "red"),
-                    ChangeTag::Insert => ("+", "green"),
-                    ChangeTag::Equal => (" ", "white"),
-                };
-
-                let line_content = change.value();
-
-                let formatted = match style {
-                    "red" => format!("{}{}", sign.red(), line_content.red()),
-                    "green" => format!("{}{}", sign.green(), line_content.green()),
-                    _ => format!("{}{}", sign.dimmed(), line_content),
+            for change in diff.iter_inline_changes(op) {
+                let segments = inline_segments(&change);
+                let formatted = match change.tag() {
+                    ChangeTag::Delete => format!(
+                        "{}{}",
+                        "-".red(),
+                        paint_segments(&segments, Color::Red, None)
+                    ),
+                    ChangeTag::Insert => format!(
+                        "{}{}",
+                        "+".green(),
+                        paint_segments(&segments, Color::Green, None)
+                    ),
+                    ChangeTag::Equal => format!("{}{}", " ".dimmed(), plain_text(&segments)),
                 };
 
                 output.push_str(&formatted);
@@ -86,51 +198,49 @@ fn unified_diff(text1: &str, text2: &str) -> Result<String> {
         }
     }
 
-    Ok(output)
+    output
 }
 
-fn side_by_side_diff(text1: &str, text2: &str) -> Result<String> {
+fn side_by_side_diff(text1: &str, text2: &str, line_width: usize) -> Result<String> {
     let diff = TextDiff::from_lines(text1, text2);
     let mut output = String::new();
 
-    let width = 40;
     let separator = " | ";
+    // Each side needs room for at least a character and the `...` marker
+    let width = (line_width.saturating_sub(separator.len()) / 2).max(4);
 
     // Header
     output.push_str(&format!(
-        "{:^width$}{}{:^width$}\n",
-        "Left".bold(),
+        "{}{}{}\n",
+        width::center("Left", width).bold(),
         separator,
-        "Right".bold(),
-        width = width
+        width::center("Right", width).bold(),
     ));
     output.push_str(&format!("{}\n", "-".repeat(width * 2 + separator.len())));
 
     for op in diff.ops() {
-        for change in diff.iter_changes(op) {
-            let line = change.value().trim_end();
+        for change in diff.iter_inline_changes(op) {
+            let segments = inline_segments(&change);
 
             match change.tag() {
                 ChangeTag::Delete => {
-                    let left = truncate_or_pad(line, width);
                     output.push_str(&format!(
                         "{}{}{}\n",
-                        left.red(),
+                        paint_segments(&segments, Color::Red, Some(width)),
                         separator.dimmed(),
                         " ".repeat(width)
                     ));
                 }
                 ChangeTag::Insert => {
-                    let right = truncate_or_pad(line, width);
                     output.push_str(&format!(
                         "{}{}{}\n",
                         " ".repeat(width),
                         separator.dimmed(),
-                        right.green()
+                        paint_segments(&segments, Color::Green, Some(width))
                     ));
                 }
                 ChangeTag::Equal => {
-                    let content = truncate_or_pad(line, width);
+                    let content = truncate_or_pad(plain_text(&segments).trim_end(), width);
                     output.push_str(&format!(
                         "{}{}{}\n",
                         content.dimmed(),
@@ -145,27 +255,107 @@ fn side_by_side_diff(text1: &str, text2: &str) -> Result<String> {
     Ok(output)
 }
 
-fn truncate_or_pad(s: &str, width: usize) -> String {
-    if s.len() > width {
-        format!("{}...", &s[..width - 3])
-    } else {
-        format!("{:width$}", s, width = width)
+/// Segments of a line without its line break; `true` marks tokens that changed
+fn inline_segments(change: &InlineChange<'_, str>) -> Vec<(bool, String)> {
+    let mut segments: Vec<(bool, String)> = change
+        .iter_strings_lossy()
+        .map(|(emphasized, text)| (emphasized, text.into_owned()))
+        .collect();
+    while let Some((_, last)) = segments.last_mut() {
+        let trimmed_len = last.trim_end_matches(['\r', '\n']).len();
+        last.truncate(trimmed_len);
+        if !last.is_empty() {
+            break;
+        }
+        segments.pop();
+    }
+    segments
+}
+
+fn plain_text(segments: &[(bool, String)]) -> String {
+    segments.iter().map(|(_, text)| text.as_str()).collect()
+}
+
+/// Color a changed line, highlighting changed tokens; optionally fit it to `width` columns
+fn paint_segments(segments: &[(bool, String)], color: Color, width: Option<usize>) -> String {
+    let total: usize = segments
+        .iter()
+        .map(|(_, text)| width::str_width(text))
+        .sum();
+    let limit = match width {
+        Some(width) if total > width => width - 3,
+        _ => usize::MAX,
+    };
+
+    let mut output = String::new();
+    let mut used = 0;
+    let mut cut = false;
+    for (emphasized, text) in segments {
+        let mut kept = String::new();
+        for c in text.chars() {
+            let w = width::char_width(c);
+            if used + w > limit {
+                cut = true;
+                break;
+            }
+            used += w;
+            kept.push(c);
+        }
+        let painted = kept.color(color);
+        let painted = if *emphasized {
+            painted.bold().reversed()
+        } else {
+            painted
+        };
+        output.push_str(&painted.to_string());
+        if cut {
+            break;
+        }
     }
+    match width {
+        Some(_) if cut => {
+            // A wide character that did not fit leaves a column to fill
+            output.push_str(&" ".repeat(limit - used));
+            output.push_str(&"...".color(color).to_string());
+        }
+        Some(width) => output.push_str(&" ".repeat(width - used)),
+        None => {}
+    }
+    output
+}
+
+/// `s` fit to exactly `width` columns, cut with `...` when it is wider
+fn truncate_or_pad(s: &str, width: usize) -> String {
+    width::pad(&width::truncate(s, width, "..."), width)
 }
 
-fn json_patch_diff(text1: &str, text2: &str) -> Result<String> {
+fn json_patch_diff(text1: &str, text2: &str, options: &PatchOptions) -> Result<String> {
     let value1: JsonValue = serde_json::from_str(text1).context("Failed to parse JSON 1")?;
     let value2: JsonValue = serde_json::from_str(text2).context("Failed to parse JSON 2")?;
 
-    let mut patches = Vec::new();
-    generate_json_patches(&value1, &value2, "", &mut patches);
-
-    let result = JsonValue::Array(patches);
+    let result = JsonValue::Array(json_patch(&value1, &value2, options));
     serde_json::to_string_pretty(&result).context("Failed to serialize patches")
 }
 
-fn generate_json_patches(old: &JsonValue, new: &JsonValue, path: &str, patches: &mut Vec<JsonValue>) {
-    if old == new {
+/// Generate RFC 6902 operations that turn `old` into `new`
+///
+/// Applying the result with the same options reproduces `new` exactly,
+/// apart from ignored paths, which keep their values from `old`.
+pub fn json_patch(old: &JsonValue, new: &JsonValue, options: &PatchOptions) -> Vec<JsonValue> {
+    let mut patches = Vec::new();
+    generate_json_patches(old, new, "", options, &mut patches);
+    patches
+}
+
+fn generate_json_patches(
+    old: &JsonValue,
+    new: &JsonValue,
+    path: &str,
+    options: &PatchOptions,
+    patches: &mut Vec<JsonValue>,
+) {
+    if old == new || options.is_ignored(path) || (options.is_masked(old) && options.is_masked(new))
+    {
         return;
     }
 
@@ -173,10 +363,11 @@ fn generate_json_patches(old: &JsonValue, new: &JsonValue, path: &str, patches:
         (JsonValue::Object(old_obj), JsonValue::Object(new_obj)) => {
             // Check for removed keys
             for key in old_obj.keys() {
-                if !new_obj.contains_key(key) {
+                let key_path = format!("{}/{}", path, escape_json_pointer(key));
+                if !new_obj.contains_key(key) && !options.is_ignored(&key_path) {
                     patches.push(serde_json::json!({
                         "op": "remove",
-                        "path": format!("{}/{}", path, escape_json_pointer(key))
+                        "path": key_path
                     }));
                 }
             }
@@ -185,8 +376,8 @@ fn generate_json_patches(old: &JsonValue, new: &JsonValue, path: &str, patches:
             for (key, new_val) in new_obj {
                 let new_path = format!("{}/{}", path, escape_json_pointer(key));
                 if let Some(old_val) = old_obj.get(key) {
-                    generate_json_patches(old_val, new_val, &new_path, patches);
-                } else {
+                    generate_json_patches(old_val, new_val, &new_path, options, patches);
+                } else if !options.is_ignored(&new_path) {
                     patches.push(serde_json::json!({
                         "op": "add",
                         "path": new_path,
@@ -196,42 +387,128 @@ fn generate_json_patches(old: &JsonValue, new: &JsonValue, path: &str, patches:
             }
         }
         (JsonValue::Array(old_arr), JsonValue::Array(new_arr)) => {
-            // Simple array diff - could be optimized with LCS
-            let max_len = old_arr.len().max(new_arr.len());
-            for i in 0..max_len {
-                let item_path = format!("{}/{}", path, i);
-                match (old_arr.get(i), new_arr.get(i)) {
-                    (Some(old_val), Some(new_val)) => {
-                        generate_json_patches(old_val, new_val, &item_path, patches);
-                    }
-                    (Some(_), None) => {
-                        patches.push(serde_json::json!({
-                            "op": "remove",
-                            "path": item_path
-                        }));
-                    }
-                    (None, Some(new_val)) => {
-                        patches.push(serde_json::json!({
-                            "op": "add",
-                            "path": item_path,
-                            "value": new_val
-                        }));
-                    }
-                    (None, None) => {}
+            match options.array_key.as_deref() {
+                Some(key) if is_keyed(old_arr, key) && is_keyed(new_arr, key) => {
+                    keyed_array_patches(old_arr, new_arr, key, path, options, patches)
                 }
+                _ => indexed_array_patches(old_arr, new_arr, path, options, patches),
             }
         }
         _ => {
             // Different types or different primitive values
             patches.push(serde_json::json!({
                 "op": "replace",
-                "path": if path.is_empty() { "/" } else { path },
+                "path": path,
                 "value": new
             }));
         }
     }
 }
 
+/// Compare arrays position by position
+fn indexed_array_patches(
+    old_arr: &[JsonValue],
+    new_arr: &[JsonValue],
+    path: &str,
+    options: &PatchOptions,
+    patches: &mut Vec<JsonValue>,
+) {
+    for (i, (old_val, new_val)) in old_arr.iter().zip(new_arr).enumerate() {
+        generate_json_patches(
+            old_val,
+            new_val,
+            &format!("{}/{}", path, i),
+            options,
+            patches,
+        );
+    }
+
+    // Remove from the end so earlier indices stay valid
+    for i in (new_arr.len()..old_arr.len()).rev() {
+        patches.push(serde_json::json!({
+            "op": "remove",
+            "path": format!("{}/{}", path, i)
+        }));
+    }
+
+    for (i, new_val) in new_arr.iter().enumerate().skip(old_arr.len()) {
+        patches.push(serde_json::json!({
+            "op": "add",
+            "path": format!("{}/{}", path, i),
+            "value": new_val
+        }));
+    }
+}
+
+/// Whether every element is an object with a unique value for `key`
+fn is_keyed(arr: &[JsonValue], key: &str) -> bool {
+    let mut seen = Vec::with_capacity(arr.len());
+    arr.iter().all(|item| match item.get(key) {
+        Some(id) if !seen.contains(&id) => {
+            seen.push(id);
+            true
+        }
+        _ => false,
+    })
+}
+
+/// Compare arrays by matching elements on `key`, emitting removes, moves and adds
+fn keyed_array_patches(
+    old_arr: &[JsonValue],
+    new_arr: &[JsonValue],
+    key: &str,
+    path: &str,
+    options: &PatchOptions,
+    patches: &mut Vec<JsonValue>,
+) {
+    let mut working: Vec<&JsonValue> = old_arr.iter().collect();
+
+    // Remove from the end so earlier indices stay valid
+    for i in (0..old_arr.len()).rev() {
+        if !new_arr
+            .iter()
+            .any(|item| item.get(key) == old_arr[i].get(key))
+        {
+            patches.push(serde_json::json!({
+                "op": "remove",
+                "path": format!("{}/{}", path, i)
+            }));
+            working.remove(i);
+        }
+    }
+
+    for (i, new_val) in new_arr.iter().enumerate() {
+        let item_path = format!("{}/{}", path, i);
+        let found = working[i..]
+            .iter()
+            .position(|item| item.get(key) == new_val.get(key))
+            .map(|offset| i + offset);
+
+        match found {
+            Some(j) => {
+                if j != i {
+                    patches.push(serde_json::json!({
+                        "op": "move",
+                        "from": format!("{}/{}", path, j),
+                        "path": item_path
+                    }));
+                    let item = working.remove(j);
+                    working.insert(i, item);
+                }
+                generate_json_patches(working[i], new_val, &item_path, options, patches);
+            }
+            None => {
+                patches.push(serde_json::json!({
+                    "op": "add",
+                    "path": item_path,
+                    "value": new_val
+                }));
+                working.insert(i, new_val);
+            }
+        }
+    }
+}
+
 fn escape_json_pointer(s: &str) -> String {
     s.replace('~', "~0").replace('/', "~1")
 }
@@ -259,9 +536,18 @@ pub fn structural_equal(value1: &JsonValue, value2: &JsonValue) -> bool {
 }
 
 /// Generate a summary of differences
-pub fn diff_summary(content1: &str, content2: &str, format1: Format, format2: Format) -> Result<String> {
-    let json1 = normalize_to_json(content1, format1)?;
-    let json2 = normalize_to_json(content2, format2)?;
+pub fn diff_summary(
+    content1: &str,
+    content2: &str,
+    format1: Format,
+    format2: Format,
+    options: &PatchOptions,
+) -> Result<String> {
+    let (json1, json2) = comparable(
+        &normalize_to_json(content1, format1, options)?,
+        &normalize_to_json(content2, format2, options)?,
+        options,
+    )?;
 
     let value1: JsonValue = serde_json::from_str(&json1)?;
     let value2: JsonValue = serde_json::from_str(&json2)?;
@@ -292,6 +578,110 @@ pub fn diff_summary(content1: &str, content2: &str, format1: Format, format2: Fo
     Ok(output)
 }
 
+/// Markdown report of the differences, for posting as a pull request comment
+///
+/// Contains a table of change counts, a collapsible list of the individual
+/// changes, and the plain unified diff in a collapsible `diff` block.
+pub fn diff_markdown(
+    content1: &str,
+    content2: &str,
+    format1: Format,
+    format2: Format,
+    labels: (&str, &str),
+    options: &PatchOptions,
+) -> Result<String> {
+    let json1 = normalize_to_json(content1, format1, options)?;
+    let json2 = normalize_to_json(content2, format2, options)?;
+    let value1: JsonValue = serde_json::from_str(&json1)?;
+    let value2: JsonValue = serde_json::from_str(&json2)?;
+    let (text1, text2) = comparable(&json1, &json2, options)?;
+
+    let mut added = 0;
+    let mut removed = 0;
+    let mut modified = 0;
+    count_changes(
+        &serde_json::from_str(&text1)?,
+        &serde_json::from_str(&text2)?,
+        &mut added,
+        &mut removed,
+        &mut modified,
+    );
+
+    let title = format!(
+        "{} → {}",
+        markdown::code_cell(labels.0),
+        markdown::code_cell(labels.1)
+    );
+    if added + removed + modified == 0 {
+        return Ok(format!("### ✅ No differences: {}\n", title));
+    }
+
+    let mut output = format!("### 📝 Differences: {}\n\n", title);
+    output.push_str(&markdown::table(
+        &["Change", "Count"],
+        &[
+            vec!["Additions".to_string(), added.to_string()],
+            vec!["Removals".to_string(), removed.to_string()],
+            vec!["Modifications".to_string(), modified.to_string()],
+        ],
+    ));
+
+    let patches = json_patch(&value1, &value2, options);
+    let rows: Vec<Vec<String>> = patches.iter().map(|op| change_row(op, &value1)).collect();
+    output.push('\n');
+    output.push_str(&markdown::details(
+        &format!("Changes ({})", rows.len()),
+        &markdown::table(&["Op", "Path", "Old", "New"], &rows),
+        false,
+    ));
+
+    let (text1, text2) = (text1 + "\n", text2 + "\n");
+    let unified = TextDiff::from_lines(&text1, &text2)
+        .unified_diff()
+        .context_radius(DEFAULT_CONTEXT)
+        .header(labels.0, labels.1)
+        .to_string();
+    output.push('\n');
+    output.push_str(&markdown::details(
+        "Unified diff",
+        &format!("```diff\n{}```", unified),
+        false,
+    ));
+
+    Ok(output)
+}
+
+/// Table row describing one JSON Patch operation against `old`
+fn change_row(op: &JsonValue, old: &JsonValue) -> Vec<String> {
+    let name = op["op"].as_str().unwrap_or_default();
+    let path = op["path"].as_str().unwrap_or_default();
+    let value_cell = |value: Option<&JsonValue>| {
+        value
+            .map(|v| table::truncate(&v.to_string(), Some(MARKDOWN_VALUE_WIDTH)))
+            .map(|v| markdown::code_cell(&v))
+            .unwrap_or_default()
+    };
+
+    let path_cell = match op["from"].as_str() {
+        Some(from) => format!(
+            "{} → {}",
+            markdown::code_cell(from),
+            markdown::code_cell(path)
+        ),
+        None => markdown::code_cell(path),
+    };
+    let old_value = match name {
+        "remove" | "replace" => old.pointer(path),
+        _ => None,
+    };
+    vec![
+        name.to_string(),
+        path_cell,
+        value_cell(old_value),
+        value_cell(op.get("value")),
+    ]
+}
+
 fn count_changes(old: &JsonValue, new: &JsonValue, added: &mut usize, removed: &mut usize, modified: &mut usize) {
     match (old, new) {
         (JsonValue::Object(old_obj), JsonValue::Object(new_obj)) => {
@@ -332,8 +722,55 @@ fn count_changes(old: &JsonValue, new: &JsonValue, added: &mut usize, removed: &
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::core::patcher;
     use serde_json::json;
 
+    #[test]
+    fn test_inline_segments() {
+        let diff = TextDiff::from_lines("  \"port\": 8080\n", "  \"port\": 8081\n");
+        let changes: Vec<_> = diff
+            .ops()
+            .iter()
+            .flat_map(|op| diff.iter_inline_changes(op))
+            .map(|change| inline_segments(&change))
+            .collect();
+
+        assert_eq!(changes.len(), 2);
+        let emphasized: Vec<&str> = changes[1]
+            .iter()
+            .filter(|(emphasized, _)| *emphasized)
+            .map(|(_, text)| text.as_str())
+            .collect();
+        assert_eq!(emphasized, vec!["8081"]);
+        assert_eq!(plain_text(&changes[1]), "  \"port\": 8081");
+    }
+
+    #[test]
+    fn test_side_by_side_width() {
+        let old = "{\n  \"name\": \"日本語のテキストがここに入ります\"\n}\n";
+        let new = "{\n  \"name\": \"日本語のテキストがここに入りますよ\"\n}\n";
+        let output = side_by_side_diff(old, new, 31).unwrap();
+        let ansi = Regex::new("\x1b\\[[0-9;]*m").unwrap();
+        for line in ansi.replace_all(&output, "").lines().skip(2) {
+            assert_eq!(width::str_width(line), 31, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn test_context_lines() {
+        let old = "a\nb\nc\nd\ne\n";
+        let new = "a\nb\nX\nd\ne\n";
+        let lines = |context| {
+            text_diff_with_context(old, new, "a", "b", context)
+                .lines()
+                .count()
+        };
+        // Two header lines plus the removed and added line
+        assert_eq!(lines(0), 4);
+        assert_eq!(lines(1), 6);
+        assert_eq!(lines(100), 8);
+    }
+
     #[test]
     fn test_structural_equal() {
         let v1 = json!({"a": 1, "b": 2});
@@ -348,8 +785,143 @@ mod tests {
     fn test_json_patch() {
         let old = r#"{"name": "Alice"}"#;
         let new = r#"{"name": "Bob"}"#;
-        let patch = json_patch_diff(old, new).unwrap();
+        let patch = json_patch_diff(old, new, &PatchOptions::default()).unwrap();
         assert!(patch.contains("replace"));
     }
+
+    #[test]
+    fn test_diff_markdown() {
+        let old = r#"{"name": "a|b", "port": 8080, "old": 1}"#;
+        let new = r#"{"name": "a|c", "port": 8080, "new": true}"#;
+        let report = diff_markdown(
+            old,
+            new,
+            Format::Json,
+            Format::Json,
+            ("old.json", "new.json"),
+            &PatchOptions::default(),
+        )
+        .unwrap();
+
+        assert!(report.starts_with("### 📝 Differences: `old.json` → `new.json`"));
+        assert!(report.contains("| Additions | 1 |\n| Removals | 1 |\n| Modifications | 1 |"));
+        assert!(report.contains("| replace | `/name` | `\"a\\|b\"` | `\"a\\|c\"` |"));
+        assert!(report.contains("```diff\n--- old.json\n+++ new.json\n"));
+        assert!(!report.contains("\x1b["));
+
+        let same = diff_markdown(
+            old,
+            old,
+            Format::Json,
+            Format::Json,
+            ("old.json", "new.json"),
+            &PatchOptions::default(),
+        )
+        .unwrap();
+        assert!(same.starts_with("### ✅ No differences"));
+    }
+
+    fn round_trip(old: &JsonValue, new: &JsonValue, options: &PatchOptions) -> JsonValue {
+        let patch = JsonValue::Array(json_patch(old, new, options));
+        let operations = patcher::parse_patch(&patch).unwrap();
+        patcher::apply_patch_with_options(old, &operations, options).unwrap()
+    }
+
+    #[test]
+    fn test_patch_round_trip() {
+        let cases = [
+            (
+                json!({"a": 1, "b": [1, 2, 3, 4]}),
+                json!({"a": 2, "b": [1]}),
+            ),
+            (json!([1, 2]), json!([3, 1, 2, 5, 6])),
+            (json!({"a": {"b": 1}}), json!({"c": null, "a/b": {"~": 2}})),
+            (json!({"a": 1}), json!([1, 2])),
+            (json!("x"), json!({"x": true})),
+            // Removing a key must not reorder the keys after it
+            (json!({"a": 1, "b": 2, "c": 3}), json!({"b": 2, "c": 3})),
+            (
+                json!({"a": 1, "b": {"x": 1, "y": 2, "z": 3}}),
+                json!({"b": {"y": 2, "z": 3}}),
+            ),
+        ];
+        for (old, new) in &cases {
+            let result = round_trip(old, new, &PatchOptions::default());
+            assert_eq!(
+                serde_json::to_string_pretty(&result).unwrap(),
+                serde_json::to_string_pretty(new).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_patch_round_trip_array_key() {
+        let old = json!({"items": [
+            {"id": 1, "qty": 1}, {"id": 2, "qty": 2}, {"id": 3, "qty": 3}, {"id": 4, "qty": 4}
+        ]});
+        let new = json!({"items": [
+            {"id": 3, "qty": 3}, {"id": 5, "qty": 5}, {"id": 1, "qty": 10}, {"id": 4, "qty": 4}
+        ]});
+        let options = PatchOptions {
+            array_key: Some("id".to_string()),
+            ..Default::default()
+        };
+        let patch = json_patch(&old, &new, &options);
+        // Element 1 is moved and updated in place rather than rewritten
+        assert!(patch.iter().any(|op| op["op"] == "move"));
+        assert!(patch.iter().any(|op| op["path"] == "/items/2/qty"));
+        assert_eq!(round_trip(&old, &new, &options), new);
+    }
+
+    #[test]
+    fn test_ignore_value_pattern() {
+        let old = r#"{"built": "2024-01-01T10:00:00Z", "sha": "abc123", "v": 1}"#;
+        let new = r#"{"built": "2024-02-03T08:30:00Z", "sha": "v2", "v": 1}"#;
+        let options = PatchOptions {
+            ignore_values: vec![
+                value_pattern(r"\d{4}-\d{2}-\d{2}T.*").unwrap(),
+                value_pattern("[0-9a-f]{6}").unwrap(),
+            ],
+            ..Default::default()
+        };
+
+        let patch = diff(
+            old,
+            new,
+            Format::Json,
+            Format::Json,
+            DiffFormat::JsonPatch,
+            &options,
+        )
+        .unwrap();
+        let patch: JsonValue = serde_json::from_str(&patch).unwrap();
+        assert_eq!(
+            patch,
+            json!([{"op": "replace", "path": "/sha", "value": "v2"}])
+        );
+
+        let summary = diff_summary(old, new, Format::Json, Format::Json, &options).unwrap();
+        assert!(
+            summary.contains("~1") && !summary.contains("+1"),
+            "{}",
+            summary
+        );
+    }
+
+    #[test]
+    fn test_patch_round_trip_ignore() {
+        let old = json!({"name": "a", "meta": {"updated": 1}, "rows": [{"ts": 1, "v": 1}]});
+        let new = json!({"name": "b", "meta": {"updated": 2}, "rows": [{"ts": 2, "v": 2}]});
+        let options = PatchOptions {
+            ignore: vec!["/meta/updated".to_string(), "rows.*.ts".to_string()],
+            ..Default::default()
+        };
+        let patch = json_patch(&old, &new, &options);
+        assert_eq!(patch.len(), 2);
+        assert_eq!(
+            round_trip(&old, &new, &options),
+            json!({"name": "b", "meta": {"updated": 1}, "rows": [{"ts": 1, "v": 2}]})
+        );
+    }
 }
 
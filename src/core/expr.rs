@@ -0,0 +1,372 @@
+//! Arithmetic and text expressions evaluated against a JSON value
+//!
+//! Expressions combine field paths (`price`, `user.name`, `` `unit price` ``),
+//! literals (`2`, `0.5`, `"text"`, `true`, `null`), the operators `+ - * / %`
+//! and parentheses. `+` joins text when either side is a string, treating a
+//! missing field as empty; arithmetic with a missing field gives null.
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value as JsonValue;
+
+use super::query::{get_nested_value, number_value};
+
+/// Deepest nesting of parentheses and negations an expression may use
+const MAX_DEPTH: usize = 256;
+
+/// Binary operator
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinOp {
+    fn symbol(self) -> char {
+        match self {
+            BinOp::Add => '+',
+            BinOp::Sub => '-',
+            BinOp::Mul => '*',
+            BinOp::Div => '/',
+            BinOp::Rem => '%',
+        }
+    }
+}
+
+/// Parsed expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(JsonValue),
+    /// Dotted field path, looked up like `--filter` fields
+    Field(String),
+    Neg(Box<Expr>),
+    Binary(BinOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(JsonValue),
+    Field(String),
+    Op(char),
+    Open,
+    Close,
+}
+
+impl Expr {
+    /// Parse an expression (e.g., `first + " " + last`, `price * (1 + tax)`)
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input).with_context(|| format!("Invalid expression: {}", input))?;
+        let mut parser = Parser {
+            tokens,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser
+            .sum()
+            .with_context(|| format!("Invalid expression: {}", input))?;
+        if parser.pos < parser.tokens.len() {
+            bail!(
+                "Invalid expression: {}. Unexpected input after a complete expression",
+                input
+            );
+        }
+        Ok(expr)
+    }
+
+    /// Evaluate the expression with fields read from `item`
+    pub fn eval(&self, item: &JsonValue) -> Result<JsonValue> {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Field(path) => Ok(get_nested_value(item, path)
+                .cloned()
+                .unwrap_or(JsonValue::Null)),
+            Expr::Neg(inner) => match inner.eval(item)? {
+                JsonValue::Null => Ok(JsonValue::Null),
+                JsonValue::Number(n) => Ok(match n.as_i64().and_then(i64::checked_neg) {
+                    Some(i) => JsonValue::from(i),
+                    None => number_value(-n.as_f64().unwrap_or(0.0)),
+                }),
+                other => bail!("Cannot negate {}", type_name(&other)),
+            },
+            Expr::Binary(op, left, right) => apply(*op, left.eval(item)?, right.eval(item)?),
+        }
+    }
+}
+
+fn apply(op: BinOp, left: JsonValue, right: JsonValue) -> Result<JsonValue> {
+    if op == BinOp::Add && (left.is_string() || right.is_string()) {
+        return Ok(JsonValue::String(text(&left) + &text(&right)));
+    }
+    let (a, b) = match (&left, &right) {
+        (JsonValue::Null, _) | (_, JsonValue::Null) => return Ok(JsonValue::Null),
+        (JsonValue::Number(a), JsonValue::Number(b)) => (a, b),
+        _ => bail!(
+            "Cannot apply '{}' to {} and {}",
+            op.symbol(),
+            type_name(&left),
+            type_name(&right)
+        ),
+    };
+
+    let zero = b.as_f64() == Some(0.0);
+    if zero && matches!(op, BinOp::Div | BinOp::Rem) {
+        bail!("Division by zero");
+    }
+    // Integers stay integers unless the result overflows or has a fraction
+    if let (Some(a), Some(b)) = (a.as_i64(), b.as_i64()) {
+        let exact = match op {
+            BinOp::Add => a.checked_add(b),
+            BinOp::Sub => a.checked_sub(b),
+            BinOp::Mul => a.checked_mul(b),
+            BinOp::Div => a.checked_rem(b).filter(|r| *r == 0).and(a.checked_div(b)),
+            BinOp::Rem => a.checked_rem(b),
+        };
+        if let Some(result) = exact {
+            return Ok(JsonValue::from(result));
+        }
+    }
+    let (a, b) = (a.as_f64().unwrap_or(0.0), b.as_f64().unwrap_or(0.0));
+    Ok(number_value(match op {
+        BinOp::Add => a + b,
+        BinOp::Sub => a - b,
+        BinOp::Mul => a * b,
+        BinOp::Div => a / b,
+        BinOp::Rem => a % b,
+    }))
+}
+
+/// Text of a value joined by `+`: strings unquoted, null empty
+fn text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::String(s) => s.clone(),
+        JsonValue::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "a boolean",
+        JsonValue::Number(_) => "a number",
+        JsonValue::String(_) => "a string",
+        JsonValue::Array(_) => "an array",
+        JsonValue::Object(_) => "an object",
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '+' | '-' | '*' | '/' | '%' => {
+                chars.next();
+                tokens.push(Token::Op(c));
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' | '\'' | '`' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => bail!("Unterminated {}...{}", c, c),
+                        },
+                        Some((_, end)) if end == c => break,
+                        Some((_, other)) => text.push(other),
+                        None => bail!("Unterminated {}...{}", c, c),
+                    }
+                }
+                tokens.push(match c {
+                    '`' => Token::Field(text),
+                    _ => Token::Literal(JsonValue::String(text)),
+                });
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = start;
+                while let Some(&(i, d)) = chars.peek() {
+                    if !(d.is_ascii_digit() || d == '.') {
+                        break;
+                    }
+                    end = i + d.len_utf8();
+                    chars.next();
+                }
+                let number = &input[start..end];
+                let value = match number.parse::<i64>() {
+                    Ok(i) => JsonValue::from(i),
+                    Err(_) => number
+                        .parse::<f64>()
+                        .ok()
+                        .map(number_value)
+                        .with_context(|| format!("Invalid number '{}'", number))?,
+                };
+                tokens.push(Token::Literal(value));
+            }
+            c if c.is_alphabetic() || c == '_' || c == '$' => {
+                let mut end = start;
+                while let Some(&(i, d)) = chars.peek() {
+                    if !(d.is_alphanumeric() || d == '_' || d == '$' || d == '.') {
+                        break;
+                    }
+                    end = i + d.len_utf8();
+                    chars.next();
+                }
+                tokens.push(match &input[start..end] {
+                    "true" => Token::Literal(JsonValue::Bool(true)),
+                    "false" => Token::Literal(JsonValue::Bool(false)),
+                    "null" => Token::Literal(JsonValue::Null),
+                    field => Token::Field(field.to_string()),
+                });
+            }
+            other => bail!("Unexpected character '{}'", other),
+        }
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser; `*`, `/` and `%` bind tighter than `+` and `-`
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    /// Parentheses and negations around the current position
+    depth: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn next_op(&mut self, ops: &[char]) -> Option<BinOp> {
+        let op = match self.tokens.get(self.pos) {
+            Some(Token::Op(c)) if ops.contains(c) => *c,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(match op {
+            '+' => BinOp::Add,
+            '-' => BinOp::Sub,
+            '*' => BinOp::Mul,
+            '/' => BinOp::Div,
+            _ => BinOp::Rem,
+        })
+    }
+
+    fn sum(&mut self) -> Result<Expr> {
+        let mut expr = self.product()?;
+        while let Some(op) = self.next_op(&['+', '-']) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while let Some(op) = self.next_op(&['*', '/', '%']) {
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Op('-')) => Ok(Expr::Neg(Box::new(self.nested(Self::unary)?))),
+            Some(Token::Literal(value)) => Ok(Expr::Literal(value)),
+            Some(Token::Field(path)) => Ok(Expr::Field(path)),
+            Some(Token::Open) => {
+                let expr = self.nested(Self::sum)?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => bail!("Missing ')'"),
+                }
+            }
+            Some(Token::Op(op)) => bail!("Unexpected '{}'", op),
+            Some(Token::Close) => bail!("Unexpected ')'"),
+            None => bail!("Expression ends too early"),
+        }
+    }
+
+    /// Parse one level deeper, failing past [`MAX_DEPTH`] rather than
+    /// overflowing the stack
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Expr>) -> Result<Expr> {
+        if self.depth >= MAX_DEPTH {
+            bail!("Expression nested deeper than {} levels", MAX_DEPTH);
+        }
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+        expr
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn eval(expr: &str, item: &JsonValue) -> Result<JsonValue> {
+        Expr::parse(expr)?.eval(item)
+    }
+
+    #[test]
+    fn test_eval() {
+        let item = json!({
+            "first": "Ann", "last": "Lee", "price": 10, "qty": 3, "tax": 0.5,
+            "unit price": 2, "tags": ["a", "b"]
+        });
+        assert_eq!(
+            eval("first + \" \" + last", &item).unwrap(),
+            json!("Ann Lee")
+        );
+        assert_eq!(eval("price * qty - 1", &item).unwrap(), json!(29));
+        assert_eq!(eval("price * (1 + tax)", &item).unwrap(), json!(15.0));
+        assert_eq!(eval("price / 4", &item).unwrap(), json!(2.5));
+        assert_eq!(eval("price / 5 % 3", &item).unwrap(), json!(2));
+        assert_eq!(eval("-price + tags.1", &item).unwrap(), json!("-10b"));
+        assert_eq!(eval("`unit price` * qty", &item).unwrap(), json!(6));
+        assert_eq!(eval("missing * 2", &item).unwrap(), json!(null));
+        assert_eq!(eval("'id-' + missing", &item).unwrap(), json!("id-"));
+        assert_eq!(
+            eval("9223372036854775807 + 1", &item).unwrap(),
+            json!(9223372036854775808.0)
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let item = json!({"n": 1, "flag": true});
+        assert!(eval("n / 0", &item).is_err());
+        assert!(eval("n * flag", &item).is_err());
+        assert!(Expr::parse("n +").is_err());
+        assert!(Expr::parse("(n + 1").is_err());
+        assert!(Expr::parse("n n").is_err());
+        assert!(Expr::parse("\"open").is_err());
+        assert!(Expr::parse("n == 1").is_err());
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let item = json!({"n": 1});
+        let nested = |depth| format!("{}n{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(eval(&nested(MAX_DEPTH), &item).unwrap(), json!(1));
+        let negated = format!("{}n", "-".repeat(MAX_DEPTH));
+        assert_eq!(eval(&negated, &item).unwrap(), json!(1));
+
+        let err = Expr::parse(&nested(20_000)).unwrap_err();
+        assert!(format!("{:#}", err).contains("nested deeper than 256 levels"));
+        let err = Expr::parse(&format!("{}n", "-".repeat(20_000))).unwrap_err();
+        assert!(format!("{:#}", err).contains("nested deeper than 256 levels"));
+    }
+}
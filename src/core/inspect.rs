@@ -0,0 +1,121 @@
+//! Structure report for documents of unknown shape
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use anyhow::Result;
+use serde_json::{json, Value as JsonValue};
+
+use crate::core::converter;
+use crate::formats::detect::Format;
+use crate::formats::yaml::YamlVersion;
+
+/// Summarize a document: format, size, nesting depth, keys, records and field types
+///
+/// Records are the elements of a top-level array (CSV rows, NDJSON lines).
+/// Field types come from the records when they are objects, otherwise from
+/// the members of a top-level object. YAML is read by the rules of `version`.
+pub fn structure_report(content: &str, format: Format, version: YamlVersion) -> Result<JsonValue> {
+    let value = converter::parse_to_json_value(content, format, version)?;
+
+    let records = value.as_array().map(|items| items.len());
+    let mut fields: BTreeMap<String, BTreeSet<&'static str>> = BTreeMap::new();
+    match &value {
+        JsonValue::Array(items) => {
+            for item in items.iter().filter_map(|item| item.as_object()) {
+                for (key, val) in item {
+                    fields
+                        .entry(key.clone())
+                        .or_default()
+                        .insert(type_name(val));
+                }
+            }
+        }
+        JsonValue::Object(obj) => {
+            for (key, val) in obj {
+                fields
+                    .entry(key.clone())
+                    .or_default()
+                    .insert(type_name(val));
+            }
+        }
+        _ => {}
+    }
+
+    Ok(json!({
+        "format": format.as_str(),
+        "size": content.len(),
+        "lines": content.lines().count(),
+        "type": type_name(&value),
+        "depth": depth(&value),
+        "keys": count_keys(&value),
+        "records": records,
+        "fields": fields,
+    }))
+}
+
+/// JSON type name, distinguishing integers from other numbers
+pub fn type_name(value: &JsonValue) -> &'static str {
+    match value {
+        JsonValue::Null => "null",
+        JsonValue::Bool(_) => "boolean",
+        JsonValue::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        JsonValue::Number(_) => "number",
+        JsonValue::String(_) => "string",
+        JsonValue::Array(_) => "array",
+        JsonValue::Object(_) => "object",
+    }
+}
+
+/// Nesting depth: scalars are 0, each array or object level adds 1
+fn depth(value: &JsonValue) -> usize {
+    match value {
+        JsonValue::Array(items) => 1 + items.iter().map(depth).max().unwrap_or(0),
+        JsonValue::Object(obj) => 1 + obj.values().map(depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Total number of object keys anywhere in the document
+fn count_keys(value: &JsonValue) -> usize {
+    match value {
+        JsonValue::Array(items) => items.iter().map(count_keys).sum(),
+        JsonValue::Object(obj) => obj.len() + obj.values().map(count_keys).sum::<usize>(),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structure_report_csv() {
+        let report = structure_report(
+            "id,name,score\n1,a,1.5\n2,b,\n",
+            Format::Csv,
+            YamlVersion::default(),
+        )
+        .unwrap();
+        assert_eq!(report["format"], "csv");
+        assert_eq!(report["records"], 2);
+        assert_eq!(report["depth"], 2);
+        assert_eq!(report["keys"], 6);
+        assert_eq!(report["fields"]["id"], json!(["integer"]));
+        assert_eq!(report["fields"]["score"], json!(["null", "number"]));
+    }
+
+    #[test]
+    fn test_structure_report_object() {
+        let report = structure_report(
+            r#"{"a": {"b": [1, 2]}, "c": "x"}"#,
+            Format::Json,
+            YamlVersion::default(),
+        )
+        .unwrap();
+        assert_eq!(report["type"], "object");
+        assert_eq!(report["depth"], 3);
+        assert_eq!(report["keys"], 3);
+        assert!(report["records"].is_null());
+        assert_eq!(report["fields"]["a"], json!(["object"]));
+    }
+}
@@ -0,0 +1,578 @@
+//! Kubernetes manifest validation
+//!
+//! Manifests are checked against bundled schemas for common resource kinds,
+//! or against a local copy of the kubernetes-json-schema repository.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::validator::{self, ValidationResult};
+
+/// Kubernetes version assumed when none is given
+pub const DEFAULT_VERSION: &str = "1.30";
+
+/// API versions removed from Kubernetes: (apiVersion, kind, removed in minor, replacement)
+const REMOVED_APIS: [(&str, &str, u32, &str); 12] = [
+    ("extensions/v1beta1", "Deployment", 16, "apps/v1"),
+    ("extensions/v1beta1", "DaemonSet", 16, "apps/v1"),
+    ("extensions/v1beta1", "ReplicaSet", 16, "apps/v1"),
+    ("apps/v1beta1", "Deployment", 16, "apps/v1"),
+    ("apps/v1beta2", "Deployment", 16, "apps/v1"),
+    ("apps/v1beta1", "StatefulSet", 16, "apps/v1"),
+    ("extensions/v1beta1", "Ingress", 22, "networking.k8s.io/v1"),
+    (
+        "networking.k8s.io/v1beta1",
+        "Ingress",
+        22,
+        "networking.k8s.io/v1",
+    ),
+    ("batch/v1beta1", "CronJob", 25, "batch/v1"),
+    ("policy/v1beta1", "PodDisruptionBudget", 25, "policy/v1"),
+    (
+        "autoscaling/v2beta2",
+        "HorizontalPodAutoscaler",
+        26,
+        "autoscaling/v2",
+    ),
+    (
+        "flowcontrol.apiserver.k8s.io/v1beta2",
+        "FlowSchema",
+        29,
+        "flowcontrol.apiserver.k8s.io/v1",
+    ),
+];
+
+/// Parse every document of a (possibly multi-document) YAML or JSON stream
+pub fn parse_manifests(content: &str) -> Result<Vec<JsonValue>> {
+    let mut docs = Vec::new();
+    for document in serde_yaml::Deserializer::from_str(content) {
+        let value = serde_yaml::Value::deserialize(document).context("Failed to parse YAML")?;
+        let json = serde_json::to_value(&value).context("Failed to convert YAML to JSON")?;
+        // Empty documents (e.g. a trailing `---`) are skipped
+        if !json.is_null() {
+            docs.push(json);
+        }
+    }
+    Ok(docs)
+}
+
+/// Validate Kubernetes manifests for the given cluster version
+pub fn validate_manifests(
+    docs: &[JsonValue],
+    version: &str,
+    schema_dir: Option<&Path>,
+) -> Result<ValidationResult> {
+    let minor = parse_minor_version(version)?;
+    let mut result = ValidationResult::new();
+
+    for (i, doc) in docs.iter().enumerate() {
+        validate_resource(
+            doc,
+            &format!("[{}]", i),
+            version,
+            minor,
+            schema_dir,
+            &mut result,
+        )?;
+    }
+
+    Ok(result)
+}
+
+fn validate_resource(
+    doc: &JsonValue,
+    location: &str,
+    version: &str,
+    minor: u32,
+    schema_dir: Option<&Path>,
+    result: &mut ValidationResult,
+) -> Result<()> {
+    let api_version = doc.get("apiVersion").and_then(|v| v.as_str());
+    let kind = doc.get("kind").and_then(|v| v.as_str());
+
+    let (Some(api_version), Some(kind)) = (api_version, kind) else {
+        result.add_error(
+            location,
+            "Manifest must have string 'apiVersion' and 'kind' fields",
+        );
+        return Ok(());
+    };
+
+    // `kind: List` (and `*List` kinds) wrap other resources
+    if kind.ends_with("List") {
+        if let Some(items) = doc.get("items").and_then(|v| v.as_array()) {
+            for (i, item) in items.iter().enumerate() {
+                let item_location = format!("{}/items/{}", location, i);
+                validate_resource(item, &item_location, version, minor, schema_dir, result)?;
+            }
+            return Ok(());
+        }
+    }
+
+    let name = doc
+        .pointer("/metadata/name")
+        .or_else(|| doc.pointer("/metadata/generateName"))
+        .and_then(|v| v.as_str())
+        .unwrap_or("<unnamed>");
+    let location = format!("{} {}/{}", location, kind, name);
+
+    if let Some((_, _, removed, replacement)) = REMOVED_APIS
+        .iter()
+        .find(|(api, k, _, _)| *api == api_version && *k == kind)
+    {
+        if minor >= *removed {
+            result.add_error(
+                &location,
+                &format!(
+                    "{} {} was removed in Kubernetes 1.{}; use {}",
+                    api_version, kind, removed, replacement
+                ),
+            );
+            return Ok(());
+        }
+        result.add_warning(
+            &location,
+            &format!(
+                "{} {} is deprecated and removed in 1.{}; use {}",
+                api_version, kind, removed, replacement
+            ),
+        );
+    }
+
+    let schema = match schema_dir {
+        Some(dir) => load_schema(dir, version, api_version, kind)?,
+        None => None,
+    }
+    .or_else(|| bundled_schema(api_version, kind));
+
+    let Some(schema) = schema else {
+        result.add_warning(
+            &location,
+            &format!("No schema available for {} {}; skipped", api_version, kind),
+        );
+        return Ok(());
+    };
+
+    let schema_result = validator::validate_json_schema(doc, &schema)?;
+    for error in schema_result.errors {
+        let path = if error.path == "$" {
+            location.clone()
+        } else {
+            format!("{}{}", location, error.path)
+        };
+        result.add_error(&path, &error.message);
+    }
+
+    Ok(())
+}
+
+/// Accepts "1.29", "v1.29" or "1.29.3" and returns the minor version
+fn parse_minor_version(version: &str) -> Result<u32> {
+    let trimmed = version.trim_start_matches('v');
+    let mut parts = trimmed.split('.');
+    match (parts.next(), parts.next()) {
+        (Some("1"), Some(minor)) => minor
+            .parse()
+            .with_context(|| format!("Invalid Kubernetes version: {}", version)),
+        _ => anyhow::bail!(
+            "Invalid Kubernetes version: {} (expected e.g. 1.30)",
+            version
+        ),
+    }
+}
+
+/// Candidate schema file names in kubernetes-json-schema layout
+/// (e.g. `v1.30.0-standalone-strict/deployment-apps-v1.json`)
+fn schema_candidates(dir: &Path, version: &str, api_version: &str, kind: &str) -> Vec<PathBuf> {
+    let kind = kind.to_lowercase();
+    let file = match api_version.split_once('/') {
+        Some((group, ver)) => {
+            let group = group.split('.').next().unwrap_or(group);
+            format!("{}-{}-{}.json", kind, group, ver)
+        }
+        None => format!("{}-{}.json", kind, api_version),
+    };
+
+    let version = version.trim_start_matches('v');
+    let full_version = if version.matches('.').count() == 1 {
+        format!("{}.0", version)
+    } else {
+        version.to_string()
+    };
+
+    vec![
+        dir.join(format!("v{}-standalone-strict", full_version))
+            .join(&file),
+        dir.join(format!("v{}-standalone", full_version))
+            .join(&file),
+        dir.join(&file),
+    ]
+}
+
+fn load_schema(
+    dir: &Path,
+    version: &str,
+    api_version: &str,
+    kind: &str,
+) -> Result<Option<JsonValue>> {
+    for path in schema_candidates(dir, version, api_version, kind) {
+        if path.is_file() {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read schema file: {}", path.display()))?;
+            let schema = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse schema: {}", path.display()))?;
+            return Ok(Some(schema));
+        }
+    }
+    Ok(None)
+}
+
+fn object_meta() -> JsonValue {
+    json!({
+        "type": "object",
+        "anyOf": [{"required": ["name"]}, {"required": ["generateName"]}],
+        "properties": {
+            "name": {"type": "string", "pattern": "^[a-z0-9]([-a-z0-9.]*[a-z0-9])?$", "maxLength": 253},
+            "generateName": {"type": "string"},
+            "namespace": {"type": "string"},
+            "labels": {"type": "object", "additionalProperties": {"type": "string"}},
+            "annotations": {"type": "object", "additionalProperties": {"type": "string"}}
+        }
+    })
+}
+
+fn label_selector() -> JsonValue {
+    json!({
+        "type": "object",
+        "properties": {
+            "matchLabels": {"type": "object", "additionalProperties": {"type": "string"}},
+            "matchExpressions": {"type": "array"}
+        }
+    })
+}
+
+fn container() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["name"],
+        "properties": {
+            "name": {"type": "string"},
+            "image": {"type": "string"},
+            "command": {"type": "array", "items": {"type": "string"}},
+            "args": {"type": "array", "items": {"type": "string"}},
+            "ports": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["containerPort"],
+                    "properties": {
+                        "containerPort": {"type": "integer", "minimum": 1, "maximum": 65535},
+                        "protocol": {"enum": ["TCP", "UDP", "SCTP"]}
+                    }
+                }
+            },
+            "env": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["name"],
+                    "properties": {"name": {"type": "string"}, "value": {"type": "string"}}
+                }
+            },
+            "imagePullPolicy": {"enum": ["Always", "IfNotPresent", "Never"]},
+            "resources": {"type": "object"}
+        }
+    })
+}
+
+fn pod_spec() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["containers"],
+        "properties": {
+            "containers": {"type": "array", "minItems": 1, "items": container()},
+            "initContainers": {"type": "array", "items": container()},
+            "restartPolicy": {"enum": ["Always", "OnFailure", "Never"]},
+            "serviceAccountName": {"type": "string"},
+            "nodeSelector": {"type": "object", "additionalProperties": {"type": "string"}},
+            "volumes": {"type": "array", "items": {"type": "object", "required": ["name"]}}
+        }
+    })
+}
+
+fn pod_template() -> JsonValue {
+    json!({
+        "type": "object",
+        "required": ["spec"],
+        "properties": {
+            "metadata": {"type": "object"},
+            "spec": pod_spec()
+        }
+    })
+}
+
+fn resource(spec: Option<JsonValue>, extra: JsonValue) -> JsonValue {
+    let mut schema = json!({
+        "type": "object",
+        "required": ["apiVersion", "kind", "metadata"],
+        "properties": {
+            "apiVersion": {"type": "string"},
+            "kind": {"type": "string"},
+            "metadata": object_meta()
+        }
+    });
+    if let Some(spec) = spec {
+        schema["required"]
+            .as_array_mut()
+            .unwrap()
+            .push(json!("spec"));
+        schema["properties"]["spec"] = spec;
+    }
+    if let JsonValue::Object(extra) = extra {
+        for (key, value) in extra {
+            schema["properties"][key] = value;
+        }
+    }
+    schema
+}
+
+/// Bundled structural schemas for common built-in resource kinds
+fn bundled_schema(api_version: &str, kind: &str) -> Option<JsonValue> {
+    let workload = |extra: JsonValue| {
+        let mut spec = json!({
+            "type": "object",
+            "required": ["selector", "template"],
+            "properties": {
+                "replicas": {"type": "integer", "minimum": 0},
+                "selector": label_selector(),
+                "template": pod_template()
+            }
+        });
+        if let JsonValue::Object(extra) = extra {
+            for (key, value) in extra {
+                spec["properties"][key] = value;
+            }
+        }
+        spec
+    };
+    let string_map = json!({"type": "object", "additionalProperties": {"type": "string"}});
+
+    let schema = match (api_version, kind) {
+        ("v1", "Pod") => resource(Some(pod_spec()), json!({})),
+        ("v1", "Service") => resource(
+            Some(json!({
+                "type": "object",
+                "properties": {
+                    "type": {"enum": ["ClusterIP", "NodePort", "LoadBalancer", "ExternalName"]},
+                    "selector": {"type": "object", "additionalProperties": {"type": "string"}},
+                    "ports": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "required": ["port"],
+                            "properties": {
+                                "name": {"type": "string"},
+                                "port": {"type": "integer", "minimum": 1, "maximum": 65535},
+                                "targetPort": {"type": ["integer", "string"]},
+                                "nodePort": {"type": "integer"},
+                                "protocol": {"enum": ["TCP", "UDP", "SCTP"]}
+                            }
+                        }
+                    }
+                }
+            })),
+            json!({}),
+        ),
+        ("v1", "ConfigMap") => resource(
+            None,
+            json!({"data": string_map, "binaryData": string_map, "immutable": {"type": "boolean"}}),
+        ),
+        ("v1", "Secret") => resource(
+            None,
+            json!({
+                "type": {"type": "string"},
+                "data": string_map,
+                "stringData": string_map,
+                "immutable": {"type": "boolean"}
+            }),
+        ),
+        ("v1", "Namespace") | ("v1", "ServiceAccount") => resource(None, json!({})),
+        ("v1", "PersistentVolumeClaim") => resource(
+            Some(json!({
+                "type": "object",
+                "properties": {
+                    "accessModes": {
+                        "type": "array",
+                        "items": {"enum": ["ReadWriteOnce", "ReadOnlyMany", "ReadWriteMany", "ReadWriteOncePod"]}
+                    },
+                    "resources": {"type": "object"},
+                    "storageClassName": {"type": "string"}
+                }
+            })),
+            json!({}),
+        ),
+        ("apps/v1", "Deployment") => resource(
+            Some(workload(json!({"strategy": {"type": "object"}}))),
+            json!({}),
+        ),
+        ("apps/v1", "ReplicaSet") | ("apps/v1", "DaemonSet") => {
+            resource(Some(workload(json!({}))), json!({}))
+        }
+        ("apps/v1", "StatefulSet") => resource(
+            Some(workload(json!({"serviceName": {"type": "string"}}))),
+            json!({}),
+        ),
+        ("batch/v1", "Job") => resource(
+            Some(json!({
+                "type": "object",
+                "required": ["template"],
+                "properties": {
+                    "template": pod_template(),
+                    "backoffLimit": {"type": "integer", "minimum": 0},
+                    "completions": {"type": "integer", "minimum": 0},
+                    "parallelism": {"type": "integer", "minimum": 0}
+                }
+            })),
+            json!({}),
+        ),
+        ("batch/v1", "CronJob") => resource(
+            Some(json!({
+                "type": "object",
+                "required": ["schedule", "jobTemplate"],
+                "properties": {
+                    "schedule": {"type": "string"},
+                    "suspend": {"type": "boolean"},
+                    "jobTemplate": {
+                        "type": "object",
+                        "required": ["spec"],
+                        "properties": {
+                            "spec": {
+                                "type": "object",
+                                "required": ["template"],
+                                "properties": {"template": pod_template()}
+                            }
+                        }
+                    }
+                }
+            })),
+            json!({}),
+        ),
+        ("networking.k8s.io/v1", "Ingress") => resource(
+            Some(json!({
+                "type": "object",
+                "properties": {
+                    "ingressClassName": {"type": "string"},
+                    "rules": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "host": {"type": "string"},
+                                "http": {
+                                    "type": "object",
+                                    "required": ["paths"],
+                                    "properties": {
+                                        "paths": {
+                                            "type": "array",
+                                            "items": {
+                                                "type": "object",
+                                                "required": ["pathType", "backend"],
+                                                "properties": {
+                                                    "path": {"type": "string"},
+                                                    "pathType": {"enum": ["Exact", "Prefix", "ImplementationSpecific"]},
+                                                    "backend": {"type": "object"}
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    "tls": {"type": "array"}
+                }
+            })),
+            json!({}),
+        ),
+        _ => return None,
+    };
+
+    Some(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_manifests() {
+        let content = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata:
+  name: web
+spec:
+  replicas: "3"
+  selector:
+    matchLabels: {app: web}
+  template:
+    spec:
+      containers:
+        - name: web
+          image: nginx
+---
+apiVersion: v1
+kind: Service
+metadata:
+  name: web
+spec:
+  ports:
+    - port: 80
+---
+"#;
+        let docs = parse_manifests(content).unwrap();
+        assert_eq!(docs.len(), 2);
+
+        let result = validate_manifests(&docs, DEFAULT_VERSION, None).unwrap();
+        assert!(!result.valid);
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].path, "[0] Deployment/web/spec/replicas");
+    }
+
+    #[test]
+    fn test_removed_api_versions() {
+        let docs = vec![json!({
+            "apiVersion": "batch/v1beta1",
+            "kind": "CronJob",
+            "metadata": {"name": "nightly"}
+        })];
+
+        let result = validate_manifests(&docs, "1.24", None).unwrap();
+        assert!(result.valid);
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.message.contains("deprecated")));
+
+        let result = validate_manifests(&docs, "v1.25.2", None).unwrap();
+        assert!(!result.valid);
+        assert!(result.errors[0].message.contains("batch/v1"));
+    }
+
+    #[test]
+    fn test_schema_candidates() {
+        let paths = schema_candidates(
+            Path::new("schemas"),
+            "1.29",
+            "networking.k8s.io/v1",
+            "Ingress",
+        );
+        assert_eq!(
+            paths[0],
+            Path::new("schemas/v1.29.0-standalone-strict/ingress-networking-v1.json")
+        );
+    }
+}
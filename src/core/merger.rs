@@ -1,7 +1,8 @@
 //! Merge engine for combining data structures
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use serde_json::{Map, Value as JsonValue};
+use std::path::{Path, PathBuf};
 
 /// Merge strategy
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -14,44 +15,152 @@ pub enum MergeStrategy {
     ConcatArrays,
     /// Union arrays (unique values only)
     UnionArrays,
+    /// Kubernetes strategic merge patch: lists merged by key, `$patch` directives
+    Strategic,
+}
+
+/// Kubernetes list fields merged by a key field rather than replaced
+const STRATEGIC_MERGE_KEYS: &[(&str, &str)] = &[
+    ("containers", "name"),
+    ("initContainers", "name"),
+    ("ephemeralContainers", "name"),
+    ("volumes", "name"),
+    ("env", "name"),
+    ("imagePullSecrets", "name"),
+    ("volumeMounts", "mountPath"),
+    ("volumeDevices", "devicePath"),
+    ("hostAliases", "ip"),
+    ("topologySpreadConstraints", "topologyKey"),
+    ("conditions", "type"),
+];
+
+/// Options controlling how values are merged
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MergeOptions {
+    pub strategy: MergeStrategy,
+    /// An explicit `null` in the overlay removes the key (JSON Merge Patch semantics)
+    pub null_deletes: bool,
+}
+
+impl From<MergeStrategy> for MergeOptions {
+    fn from(strategy: MergeStrategy) -> Self {
+        Self {
+            strategy,
+            null_deletes: false,
+        }
+    }
 }
 
 /// Merge two JSON values with the specified strategy
 pub fn merge(base: &JsonValue, overlay: &JsonValue, strategy: MergeStrategy) -> Result<JsonValue> {
-    match strategy {
-        MergeStrategy::Deep => deep_merge(base, overlay),
-        MergeStrategy::Shallow => shallow_merge(base, overlay),
-        MergeStrategy::ConcatArrays => deep_merge_with_array_concat(base, overlay),
-        MergeStrategy::UnionArrays => deep_merge_with_array_union(base, overlay),
-    }
+    merge_with_options(base, overlay, &strategy.into())
+}
+
+/// Merge two JSON values with the specified options
+///
+/// The strategic strategy always treats `null` as a deletion.
+pub fn merge_with_options(
+    base: &JsonValue,
+    overlay: &JsonValue,
+    options: &MergeOptions,
+) -> Result<JsonValue> {
+    let arrays = match options.strategy {
+        MergeStrategy::Deep => ArrayMerge::Replace,
+        MergeStrategy::Shallow => return shallow_merge(base, overlay, options.null_deletes),
+        MergeStrategy::ConcatArrays => ArrayMerge::Concat,
+        MergeStrategy::UnionArrays => ArrayMerge::Union,
+        MergeStrategy::Strategic => return strategic_merge(base, overlay),
+    };
+    Ok(deep_merge(base, overlay, arrays, options.null_deletes))
+}
+
+/// How deep merges combine two arrays
+#[derive(Clone, Copy)]
+enum ArrayMerge {
+    /// Overlay array wins
+    Replace,
+    /// Overlay items are appended
+    Concat,
+    /// Overlay items are appended unless already present
+    Union,
 }
 
-/// Deep merge two values
-fn deep_merge(base: &JsonValue, overlay: &JsonValue) -> Result<JsonValue> {
+/// Deep merge two values, recursively merging objects; later values win
+fn deep_merge(
+    base: &JsonValue,
+    overlay: &JsonValue,
+    arrays: ArrayMerge,
+    null_deletes: bool,
+) -> JsonValue {
     match (base, overlay) {
         (JsonValue::Object(base_obj), JsonValue::Object(overlay_obj)) => {
             let mut result = base_obj.clone();
             for (key, overlay_value) in overlay_obj {
-                if let Some(base_value) = base_obj.get(key) {
-                    result.insert(key.clone(), deep_merge(base_value, overlay_value)?);
-                } else {
-                    result.insert(key.clone(), overlay_value.clone());
+                if null_deletes && overlay_value.is_null() {
+                    result.shift_remove(key);
+                    continue;
                 }
+                let merged = match base_obj.get(key) {
+                    Some(base_value) => deep_merge(base_value, overlay_value, arrays, null_deletes),
+                    // New objects can still carry nulls that must not end up in the result
+                    None if null_deletes => without_nulls(overlay_value),
+                    None => overlay_value.clone(),
+                };
+                result.insert(key.clone(), merged);
             }
-            Ok(JsonValue::Object(result))
+            JsonValue::Object(result)
         }
+        (JsonValue::Array(base_arr), JsonValue::Array(overlay_arr)) => match arrays {
+            ArrayMerge::Replace => overlay.clone(),
+            ArrayMerge::Concat => {
+                let mut result = base_arr.clone();
+                result.extend(overlay_arr.iter().cloned());
+                JsonValue::Array(result)
+            }
+            ArrayMerge::Union => {
+                let mut seen = std::collections::HashSet::new();
+                let mut result = Vec::new();
+
+                for item in base_arr.iter().chain(overlay_arr.iter()) {
+                    let key = serde_json::to_string(item).unwrap_or_default();
+                    if seen.insert(key) {
+                        result.push(item.clone());
+                    }
+                }
+                JsonValue::Array(result)
+            }
+        },
+        // New objects can still carry nulls that must not end up in the result
+        (_, JsonValue::Object(_)) if null_deletes => without_nulls(overlay),
         // For non-objects, overlay wins
-        (_, overlay) => Ok(overlay.clone()),
+        (_, overlay) => overlay.clone(),
+    }
+}
+
+/// Copy of `value` with null object members removed at every depth (arrays are kept as is)
+fn without_nulls(value: &JsonValue) -> JsonValue {
+    match value {
+        JsonValue::Object(obj) => JsonValue::Object(
+            obj.iter()
+                .filter(|(_, v)| !v.is_null())
+                .map(|(k, v)| (k.clone(), without_nulls(v)))
+                .collect(),
+        ),
+        other => other.clone(),
     }
 }
 
 /// Shallow merge - only top level
-fn shallow_merge(base: &JsonValue, overlay: &JsonValue) -> Result<JsonValue> {
+fn shallow_merge(base: &JsonValue, overlay: &JsonValue, null_deletes: bool) -> Result<JsonValue> {
     match (base, overlay) {
         (JsonValue::Object(base_obj), JsonValue::Object(overlay_obj)) => {
             let mut result = base_obj.clone();
             for (key, value) in overlay_obj {
-                result.insert(key.clone(), value.clone());
+                if null_deletes && value.is_null() {
+                    result.shift_remove(key);
+                } else {
+                    result.insert(key.clone(), value.clone());
+                }
             }
             Ok(JsonValue::Object(result))
         }
@@ -59,74 +168,139 @@ fn shallow_merge(base: &JsonValue, overlay: &JsonValue) -> Result<JsonValue> {
     }
 }
 
-/// Deep merge with array concatenation
-fn deep_merge_with_array_concat(base: &JsonValue, overlay: &JsonValue) -> Result<JsonValue> {
+/// Strategic merge: `null` deletes a key, keyed lists merge element-wise
+fn strategic_merge(base: &JsonValue, overlay: &JsonValue) -> Result<JsonValue> {
     match (base, overlay) {
         (JsonValue::Object(base_obj), JsonValue::Object(overlay_obj)) => {
-            let mut result = base_obj.clone();
-            for (key, overlay_value) in overlay_obj {
-                if let Some(base_value) = base_obj.get(key) {
-                    result.insert(
-                        key.clone(),
-                        deep_merge_with_array_concat(base_value, overlay_value)?,
-                    );
-                } else {
-                    result.insert(key.clone(), overlay_value.clone());
-                }
-            }
-            Ok(JsonValue::Object(result))
-        }
-        (JsonValue::Array(base_arr), JsonValue::Array(overlay_arr)) => {
-            let mut result = base_arr.clone();
-            result.extend(overlay_arr.iter().cloned());
-            Ok(JsonValue::Array(result))
+            strategic_merge_maps(base_obj, overlay_obj)
         }
+        // New objects still need their directives resolved
+        (_, JsonValue::Object(overlay_obj)) => strategic_merge_maps(&Map::new(), overlay_obj),
         (_, overlay) => Ok(overlay.clone()),
     }
 }
 
-/// Deep merge with array union (unique values)
-fn deep_merge_with_array_union(base: &JsonValue, overlay: &JsonValue) -> Result<JsonValue> {
-    match (base, overlay) {
-        (JsonValue::Object(base_obj), JsonValue::Object(overlay_obj)) => {
-            let mut result = base_obj.clone();
-            for (key, overlay_value) in overlay_obj {
-                if let Some(base_value) = base_obj.get(key) {
-                    result.insert(
-                        key.clone(),
-                        deep_merge_with_array_union(base_value, overlay_value)?,
-                    );
-                } else {
-                    result.insert(key.clone(), overlay_value.clone());
-                }
+fn strategic_merge_maps(
+    base: &Map<String, JsonValue>,
+    overlay: &Map<String, JsonValue>,
+) -> Result<JsonValue> {
+    let mut result = match overlay.get("$patch").and_then(|v| v.as_str()) {
+        None | Some("merge") => base.clone(),
+        Some("replace") => Map::new(),
+        Some(other) => bail!("Unsupported $patch directive on a map: {}", other),
+    };
+
+    if let Some(JsonValue::Array(retain)) = overlay.get("$retainKeys") {
+        result.retain(|key, _| retain.iter().any(|k| k.as_str() == Some(key)));
+    }
+
+    for (key, overlay_value) in overlay {
+        if key == "$patch" || key == "$retainKeys" || key.starts_with("$setElementOrder/") {
+            continue;
+        }
+        if let Some(field) = key.strip_prefix("$deleteFromPrimitiveList/") {
+            if let (Some(JsonValue::Array(list)), JsonValue::Array(remove)) =
+                (result.get_mut(field), overlay_value)
+            {
+                list.retain(|item| !remove.contains(item));
             }
-            Ok(JsonValue::Object(result))
+            continue;
         }
-        (JsonValue::Array(base_arr), JsonValue::Array(overlay_arr)) => {
-            let mut seen = std::collections::HashSet::new();
-            let mut result = Vec::new();
-
-            for item in base_arr.iter().chain(overlay_arr.iter()) {
-                let key = serde_json::to_string(item).unwrap_or_default();
-                if seen.insert(key) {
-                    result.push(item.clone());
-                }
+        if overlay_value.is_null() || is_delete_directive(overlay_value) {
+            result.shift_remove(key);
+            continue;
+        }
+
+        let merged = match (result.get(key), overlay_value) {
+            (Some(JsonValue::Array(base_arr)), JsonValue::Array(overlay_arr)) => {
+                strategic_merge_lists(key, base_arr, overlay_arr)?
             }
-            Ok(JsonValue::Array(result))
+            (None, JsonValue::Array(overlay_arr)) => strategic_merge_lists(key, &[], overlay_arr)?,
+            (Some(base_value), _) => strategic_merge(base_value, overlay_value)?,
+            (None, _) => strategic_merge(&JsonValue::Null, overlay_value)?,
+        };
+        result.insert(key.clone(), merged);
+    }
+
+    Ok(JsonValue::Object(result))
+}
+
+fn strategic_merge_lists(
+    field: &str,
+    base: &[JsonValue],
+    overlay: &[JsonValue],
+) -> Result<JsonValue> {
+    let replace = overlay
+        .iter()
+        .any(|item| item.get("$patch").and_then(|v| v.as_str()) == Some("replace"));
+    let key = merge_key(field, base, overlay).filter(|_| !replace);
+
+    let Some(key) = key else {
+        // Lists without a merge key are replaced
+        let items = overlay
+            .iter()
+            .filter(|item| item.get("$patch").is_none())
+            .map(|item| strategic_merge(&JsonValue::Null, item))
+            .collect::<Result<Vec<_>>>()?;
+        return Ok(JsonValue::Array(items));
+    };
+
+    let mut result = base.to_vec();
+    for item in overlay {
+        let position = result.iter().position(|b| b.get(key) == item.get(key));
+        if is_delete_directive(item) {
+            if let Some(index) = position {
+                result.remove(index);
+            }
+            continue;
+        }
+        match position {
+            Some(index) => result[index] = strategic_merge(&result[index], item)?,
+            None => result.push(strategic_merge(&JsonValue::Null, item)?),
         }
-        (_, overlay) => Ok(overlay.clone()),
     }
+    Ok(JsonValue::Array(result))
+}
+
+/// Merge key for a list field: the Kubernetes key for known fields, else `name`
+fn merge_key(field: &str, base: &[JsonValue], overlay: &[JsonValue]) -> Option<&'static str> {
+    let has_key = |key: &str| {
+        base.iter()
+            .chain(overlay)
+            .all(|item| item.get(key).is_some())
+    };
+
+    let key = match field {
+        // Container ports and Service ports share a field name
+        "ports" if has_key("containerPort") => "containerPort",
+        "ports" => "port",
+        _ => STRATEGIC_MERGE_KEYS
+            .iter()
+            .find(|(name, _)| *name == field)
+            .map(|(_, key)| *key)
+            .unwrap_or("name"),
+    };
+    (!overlay.is_empty() && has_key(key)).then_some(key)
+}
+
+fn is_delete_directive(value: &JsonValue) -> bool {
+    value.get("$patch").and_then(|v| v.as_str()) == Some("delete")
 }
 
 /// Merge multiple values sequentially
 pub fn merge_all(values: &[JsonValue], strategy: MergeStrategy) -> Result<JsonValue> {
+    merge_all_with_options(values, &strategy.into())
+}
+
+/// Merge multiple values sequentially with the specified options
+pub fn merge_all_with_options(values: &[JsonValue], options: &MergeOptions) -> Result<JsonValue> {
     if values.is_empty() {
         return Ok(JsonValue::Null);
     }
 
     let mut result = values[0].clone();
     for value in values.iter().skip(1) {
-        result = merge(&result, value, strategy)?;
+        result = merge_with_options(&result, value, options)?;
     }
     Ok(result)
 }
@@ -198,11 +372,104 @@ fn merge_at_path_recursive(
     }
 }
 
+/// Extensions of files picked up as environment overlays
+const OVERLAY_EXTENSIONS: &[&str] = &["yaml", "yml", "json", "toml"];
+
+/// Layers of an environment-specific config, in merge order
+///
+/// For `config/base.yaml` and `prod` these are the base itself, then
+/// `config/base.prod.yaml` (with any supported extension), then every file in
+/// `config/overlays/prod/` sorted by name. At least one overlay must exist.
+pub fn env_layers(base: &Path, env: &str) -> Result<Vec<PathBuf>> {
+    if !base.is_file() {
+        bail!("Base file not found: {}", base.display());
+    }
+    let dir = base.parent().unwrap_or(Path::new(""));
+    let stem = base
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .context("Base file has no name")?;
+
+    let mut layers = vec![base.to_path_buf()];
+    layers.extend(
+        OVERLAY_EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{}.{}.{}", stem, env, ext)))
+            .filter(|path| path.is_file()),
+    );
+
+    let overlay_dir = dir.join("overlays").join(env);
+    if overlay_dir.is_dir() {
+        let mut overlays: Vec<PathBuf> = std::fs::read_dir(&overlay_dir)
+            .with_context(|| format!("Failed to read {}", overlay_dir.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| {
+                path.is_file()
+                    && path
+                        .extension()
+                        .and_then(|e| e.to_str())
+                        .is_some_and(|e| OVERLAY_EXTENSIONS.contains(&e))
+            })
+            .collect();
+        overlays.sort();
+        layers.extend(overlays);
+    }
+
+    if layers.len() == 1 {
+        bail!(
+            "No overlays for environment '{}' (looked for {} and {})",
+            env,
+            dir.join(format!("{}.{}.*", stem, env)).display(),
+            overlay_dir.join("*").display()
+        );
+    }
+    Ok(layers)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_env_layers() {
+        let dir = std::env::temp_dir().join(format!("dtx-merge-env-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("overlays/prod")).unwrap();
+        for file in [
+            "base.yaml",
+            "base.prod.yaml",
+            "base.dev.yaml",
+            "overlays/prod/b.json",
+            "overlays/prod/a.yaml",
+            "overlays/prod/notes.txt",
+        ] {
+            std::fs::write(dir.join(file), "{}").unwrap();
+        }
+
+        let layers = env_layers(&dir.join("base.yaml"), "prod").unwrap();
+        let names: Vec<_> = layers
+            .iter()
+            .map(|path| {
+                path.strip_prefix(&dir)
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "base.yaml",
+                "base.prod.yaml",
+                "overlays/prod/a.yaml",
+                "overlays/prod/b.json"
+            ]
+        );
+        assert!(env_layers(&dir.join("base.yaml"), "staging").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
     #[test]
     fn test_deep_merge() {
         let base = json!({
@@ -233,6 +500,32 @@ mod tests {
         assert!(result["a"].get("b").is_none());
     }
 
+    #[test]
+    fn test_null_deletes() {
+        let base = json!({"a": 1, "b": {"c": 2, "d": 3}, "e": [1, null]});
+        let overlay = json!({"a": null, "b": {"c": null}, "f": {"g": null, "h": 4}});
+
+        let options = MergeOptions {
+            strategy: MergeStrategy::Deep,
+            null_deletes: true,
+        };
+        let result = merge_with_options(&base, &overlay, &options).unwrap();
+        assert_eq!(
+            result,
+            json!({"b": {"d": 3}, "e": [1, null], "f": {"h": 4}})
+        );
+        // Deleting a key keeps the order of the rest
+        assert_eq!(
+            result.to_string(),
+            r#"{"b":{"d":3},"e":[1,null],"f":{"h":4}}"#
+        );
+
+        // Without the option null is an ordinary value
+        let result = merge(&base, &overlay, MergeStrategy::Deep).unwrap();
+        assert_eq!(result["a"], JsonValue::Null);
+        assert_eq!(result["b"]["c"], JsonValue::Null);
+    }
+
     #[test]
     fn test_array_concat() {
         let base = json!({"items": [1, 2]});
@@ -242,6 +535,50 @@ mod tests {
         assert_eq!(result["items"], json!([1, 2, 3, 4]));
     }
 
+    #[test]
+    fn test_strategic_merge() {
+        let base = json!({
+            "metadata": {"name": "web", "labels": {"app": "web", "tier": "frontend"}},
+            "spec": {"containers": [
+                {"name": "app", "image": "app:1", "env": [{"name": "A", "value": "1"}, {"name": "B", "value": "2"}]},
+                {"name": "sidecar", "image": "proxy:1"}
+            ]}
+        });
+        let overlay = json!({
+            "metadata": {"labels": {"tier": null}},
+            "spec": {"containers": [
+                {"name": "app", "image": "app:2", "env": [{"name": "B", "$patch": "delete"}, {"name": "C", "value": "3"}]},
+                {"name": "sidecar", "$patch": "delete"},
+                {"name": "logger", "image": "log:1"}
+            ]}
+        });
+
+        let result = merge(&base, &overlay, MergeStrategy::Strategic).unwrap();
+        assert_eq!(result["metadata"]["labels"], json!({"app": "web"}));
+        assert_eq!(
+            result["spec"]["containers"],
+            json!([
+                {"name": "app", "image": "app:2", "env": [{"name": "A", "value": "1"}, {"name": "C", "value": "3"}]},
+                {"name": "logger", "image": "log:1"}
+            ])
+        );
+    }
+
+    #[test]
+    fn test_strategic_merge_directives() {
+        let base = json!({"spec": {"selector": {"a": "1", "b": "2"}, "ports": [{"port": 80}], "args": ["x", "y"]}});
+        let overlay = json!({"spec": {
+            "selector": {"$patch": "replace", "c": "3"},
+            "ports": [{"$patch": "replace"}, {"port": 443}],
+            "$deleteFromPrimitiveList/args": ["x"]
+        }});
+
+        let result = merge(&base, &overlay, MergeStrategy::Strategic).unwrap();
+        assert_eq!(result["spec"]["selector"], json!({"c": "3"}));
+        assert_eq!(result["spec"]["ports"], json!([{"port": 443}]));
+        assert_eq!(result["spec"]["args"], json!(["y"]));
+    }
+
     #[test]
     fn test_array_union() {
         let base = json!({"items": [1, 2, 3]});
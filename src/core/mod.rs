@@ -2,21 +2,43 @@
 //!
 //! This module includes:
 //! - converter.rs: Cross-format conversion engine
+//! - coerce.rs: Schema-guided type coercion
+//! - compat.rs: JSON Schema compatibility checks
 //! - query.rs: JSONPath and data transformation queries
+//! - expr.rs: Expressions computing new fields in queries
 //! - validator.rs: Schema validation and linting
 //! - differ.rs: Diff calculation
 //! - schema.rs: JSON Schema generation
+//! - table.rs: Tables from arrays of objects
 //! - merger.rs: Merge logic
+//! - inspect.rs: Structure reports for unknown documents
+//! - kubernetes.rs: Kubernetes manifest validation
+//! - openapi.rs: OpenAPI document validation
 //! - patcher.rs: JSON Patch (RFC 6902)
+//! - refs.rs: JSON Schema $ref bundling and dereferencing
+//! - remote.rs: Remote fetching with an on-disk cache
 //! - template.rs: Template variable substitution
 //! - batch.rs: Batch processing
+//! - bench.rs: Parse/serialize timings per format
+//! - value.rs: Lossless intermediate value for JSON, YAML and TOML
 
 pub mod batch;
+pub mod bench;
+pub mod coerce;
+pub mod compat;
 pub mod converter;
 pub mod differ;
+pub mod expr;
+pub mod inspect;
+pub mod kubernetes;
 pub mod merger;
+pub mod openapi;
 pub mod patcher;
 pub mod query;
+pub mod refs;
+pub mod remote;
 pub mod schema;
+pub mod table;
 pub mod template;
 pub mod validator;
+pub mod value;
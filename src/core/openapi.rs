@@ -0,0 +1,324 @@
+//! OpenAPI document validation
+//!
+//! Checks documents against a structural subset of the OpenAPI 3.0/3.1
+//! meta-schema and reports common authoring issues.
+
+use anyhow::Result;
+use serde_json::{json, Value as JsonValue};
+use std::collections::{BTreeSet, HashMap};
+
+use crate::core::validator::{self, ValidationResult};
+
+const HTTP_METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+const COMPONENT_SECTIONS: [&str; 9] = [
+    "schemas",
+    "responses",
+    "parameters",
+    "examples",
+    "requestBodies",
+    "headers",
+    "securitySchemes",
+    "links",
+    "callbacks",
+];
+
+/// Structural meta-schema covering the parts of OpenAPI 3.0/3.1 shared by both versions
+fn meta_schema(version_3_1: bool) -> JsonValue {
+    let operation = json!({
+        "type": "object",
+        "properties": {
+            "operationId": {"type": "string"},
+            "summary": {"type": "string"},
+            "description": {"type": "string"},
+            "tags": {"type": "array", "items": {"type": "string"}},
+            "parameters": {"type": "array", "items": {"$ref": "#/$defs/parameterOrRef"}},
+            "requestBody": {"type": "object"},
+            "responses": {"$ref": "#/$defs/responses"},
+            "deprecated": {"type": "boolean"},
+            "security": {"type": "array", "items": {"type": "object"}}
+        },
+        "required": if version_3_1 { json!([]) } else { json!(["responses"]) }
+    });
+
+    let mut path_item = json!({
+        "type": "object",
+        "properties": {
+            "$ref": {"type": "string"},
+            "summary": {"type": "string"},
+            "description": {"type": "string"},
+            "parameters": {"type": "array", "items": {"$ref": "#/$defs/parameterOrRef"}}
+        }
+    });
+    for method in HTTP_METHODS {
+        path_item["properties"][method] = json!({"$ref": "#/$defs/operation"});
+    }
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "type": "object",
+        "required": if version_3_1 { json!(["openapi", "info"]) } else { json!(["openapi", "info", "paths"]) },
+        "properties": {
+            "openapi": {"type": "string", "pattern": "^3\\.[01]\\.\\d+(-.+)?$"},
+            "info": {
+                "type": "object",
+                "required": ["title", "version"],
+                "properties": {
+                    "title": {"type": "string"},
+                    "version": {"type": "string"},
+                    "description": {"type": "string"}
+                }
+            },
+            "servers": {
+                "type": "array",
+                "items": {"type": "object", "required": ["url"], "properties": {"url": {"type": "string"}}}
+            },
+            "paths": {
+                "type": "object",
+                "propertyNames": {"pattern": "^(/|x-)"},
+                "additionalProperties": {"$ref": "#/$defs/pathItem"}
+            },
+            "webhooks": {"type": "object"},
+            "components": {
+                "type": "object",
+                "properties": COMPONENT_SECTIONS
+                    .iter()
+                    .map(|s| (s.to_string(), json!({"type": "object"})))
+                    .collect::<serde_json::Map<_, _>>()
+            },
+            "security": {"type": "array", "items": {"type": "object"}},
+            "tags": {
+                "type": "array",
+                "items": {"type": "object", "required": ["name"]}
+            }
+        },
+        "$defs": {
+            "pathItem": path_item,
+            "operation": operation,
+            "responses": {
+                "type": "object",
+                "minProperties": 1,
+                "propertyNames": {"pattern": "^([1-5](\\d\\d|XX)|default|x-.*)$"}
+            },
+            "parameterOrRef": {
+                "type": "object",
+                "if": {"required": ["$ref"]},
+                "then": {"properties": {"$ref": {"type": "string"}}},
+                "else": {
+                    "required": ["name", "in"],
+                    "properties": {
+                        "name": {"type": "string"},
+                        "in": {"enum": ["query", "header", "path", "cookie"]},
+                        "required": {"type": "boolean"}
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Validate an OpenAPI 3.0/3.1 document
+pub fn validate_openapi(doc: &JsonValue) -> Result<ValidationResult> {
+    let version = doc.get("openapi").and_then(|v| v.as_str()).unwrap_or("");
+    if doc.get("swagger").is_some() {
+        let mut result = ValidationResult::new();
+        result.add_error(
+            "$.swagger",
+            "Swagger 2.0 documents are not supported; expected OpenAPI 3.x",
+        );
+        return Ok(result);
+    }
+
+    let mut result =
+        validator::validate_json_schema(doc, &meta_schema(version.starts_with("3.1")))?;
+
+    check_operations(doc, &mut result);
+    check_unused_components(doc, &mut result);
+
+    Ok(result)
+}
+
+/// Collect (path, method, path item, operation) tuples
+fn operations(doc: &JsonValue) -> Vec<(&str, &str, &JsonValue, &JsonValue)> {
+    let mut ops = Vec::new();
+    if let Some(paths) = doc.get("paths").and_then(|p| p.as_object()) {
+        for (path, item) in paths {
+            for method in HTTP_METHODS {
+                if let Some(op) = item.get(method).filter(|op| op.is_object()) {
+                    ops.push((path.as_str(), method, item, op));
+                }
+            }
+        }
+    }
+    ops
+}
+
+fn check_operations(doc: &JsonValue, result: &mut ValidationResult) {
+    let mut seen_ids: HashMap<&str, String> = HashMap::new();
+
+    for (path, method, item, op) in operations(doc) {
+        let location = format!("$.paths['{}'].{}", path, method);
+
+        match op.get("operationId").and_then(|id| id.as_str()) {
+            Some(id) => {
+                if let Some(first) = seen_ids.get(id) {
+                    result.add_error(
+                        &location,
+                        &format!("Duplicate operationId '{}' (first used at {})", id, first),
+                    );
+                } else {
+                    seen_ids.insert(id, location.clone());
+                }
+            }
+            None => result.add_warning(&location, "Missing operationId"),
+        }
+
+        // Every {param} in the path template must be declared as a path parameter
+        let declared: BTreeSet<&str> = item
+            .get("parameters")
+            .into_iter()
+            .chain(op.get("parameters"))
+            .filter_map(|p| p.as_array())
+            .flatten()
+            .filter(|p| p.get("in").and_then(|v| v.as_str()) == Some("path"))
+            .filter_map(|p| p.get("name").and_then(|v| v.as_str()))
+            .collect();
+        let has_refs = op
+            .get("parameters")
+            .and_then(|p| p.as_array())
+            .is_some_and(|params| params.iter().any(|p| p.get("$ref").is_some()));
+
+        for name in path_template_params(path) {
+            if !declared.contains(name) && !has_refs {
+                result.add_warning(
+                    &location,
+                    &format!(
+                        "Path parameter '{{{}}}' is not declared in parameters",
+                        name
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn path_template_params(path: &str) -> Vec<&str> {
+    path.split('{')
+        .skip(1)
+        .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+        .collect()
+}
+
+fn check_unused_components(doc: &JsonValue, result: &mut ValidationResult) {
+    let Some(components) = doc.get("components").and_then(|c| c.as_object()) else {
+        return;
+    };
+
+    let mut refs = BTreeSet::new();
+    collect_refs(doc, &mut refs);
+
+    for section in COMPONENT_SECTIONS {
+        // Security schemes are referenced by name from security requirements
+        if section == "securitySchemes" {
+            continue;
+        }
+        if let Some(entries) = components.get(section).and_then(|s| s.as_object()) {
+            for name in entries.keys() {
+                let pointer = format!("#/components/{}/{}", section, escape_pointer(name));
+                let used = refs
+                    .iter()
+                    .any(|r| r == &pointer || r.starts_with(&format!("{}/", pointer)));
+                if !used {
+                    result.add_warning(
+                        &format!("$.components.{}.{}", section, name),
+                        "Unused component",
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn collect_refs(value: &JsonValue, refs: &mut BTreeSet<String>) {
+    match value {
+        JsonValue::Object(obj) => {
+            for (key, val) in obj {
+                match val {
+                    JsonValue::String(s) if key == "$ref" => {
+                        // Only local references count; strip any document prefix
+                        if let Some(pos) = s.find('#') {
+                            refs.insert(s[pos..].to_string());
+                        }
+                    }
+                    _ => collect_refs(val, refs),
+                }
+            }
+        }
+        JsonValue::Array(arr) => {
+            for item in arr {
+                collect_refs(item, refs);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn escape_pointer(s: &str) -> String {
+    s.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_openapi() {
+        let doc = json!({
+            "openapi": "3.0.3",
+            "info": {"title": "Pets", "version": "1.0"},
+            "paths": {
+                "/pets/{id}": {
+                    "get": {
+                        "operationId": "getPet",
+                        "parameters": [{"name": "id", "in": "path", "required": true}],
+                        "responses": {"200": {"$ref": "#/components/responses/Pet"}}
+                    },
+                    "delete": {
+                        "responses": {"204": {"description": "Deleted"}}
+                    }
+                }
+            },
+            "components": {
+                "responses": {"Pet": {"description": "A pet"}},
+                "schemas": {"Unused": {"type": "object"}}
+            }
+        });
+
+        let result = validate_openapi(&doc).unwrap();
+        assert!(result.valid);
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.message == "Missing operationId" && w.path.contains("delete")));
+        assert!(result
+            .warnings
+            .iter()
+            .any(|w| w.message == "Unused component" && w.path.ends_with("Unused")));
+        assert!(!result.warnings.iter().any(|w| w.path.ends_with("Pet")));
+    }
+
+    #[test]
+    fn test_validate_openapi_structure() {
+        let doc = json!({
+            "openapi": "3.0.0",
+            "info": {"title": "Broken"},
+            "paths": {"pets": {}}
+        });
+
+        let result = validate_openapi(&doc).unwrap();
+        assert!(!result.valid);
+        assert!(result.errors.len() >= 2);
+    }
+}
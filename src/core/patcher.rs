@@ -1,9 +1,16 @@
 //! JSON Patch (RFC 6902) implementation
+//!
+//! As a non-standard extension (enabled with `PatchOptions::extended`), a `path`
+//! starting with `$` is a JSONPath and the operation applies to every match.
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value as JsonValue};
 
+use crate::core::query;
+use crate::formats::yaml::YamlVersion;
+
 /// JSON Patch operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "op", rename_all = "lowercase")]
@@ -16,23 +23,160 @@ pub enum PatchOperation {
     Test { path: String, value: JsonValue },
 }
 
+impl PatchOperation {
+    fn path(&self) -> &str {
+        match self {
+            PatchOperation::Add { path, .. }
+            | PatchOperation::Remove { path }
+            | PatchOperation::Replace { path, .. }
+            | PatchOperation::Move { path, .. }
+            | PatchOperation::Copy { path, .. }
+            | PatchOperation::Test { path, .. } => path,
+        }
+    }
+
+    fn path_mut(&mut self) -> &mut String {
+        match self {
+            PatchOperation::Add { path, .. }
+            | PatchOperation::Remove { path }
+            | PatchOperation::Replace { path, .. }
+            | PatchOperation::Move { path, .. }
+            | PatchOperation::Copy { path, .. }
+            | PatchOperation::Test { path, .. } => path,
+        }
+    }
+}
+
+/// Options shared by patch generation (`diff --patch`) and patch application
+#[derive(Debug, Clone, Default)]
+pub struct PatchOptions {
+    /// Match elements of object arrays by this field instead of by position
+    pub array_key: Option<String>,
+    /// Paths left untouched: JSON Pointers or dot paths, `*` matches any segment
+    pub ignore: Vec<String>,
+    /// Diffs treat two values as equal when both match one of these patterns
+    /// (timestamps, build hashes)
+    pub ignore_values: Vec<Regex>,
+    /// Allow JSONPath expressions in `path` (non-RFC extension)
+    pub extended: bool,
+    /// YAML revision used to read YAML inputs to a diff
+    pub yaml_version: YamlVersion,
+}
+
+impl PatchOptions {
+    /// Whether `pointer` is at or below one of the ignored paths
+    pub fn is_ignored(&self, pointer: &str) -> bool {
+        let parts = parse_path(pointer);
+        self.ignore.iter().any(|pattern| {
+            let pattern = ignore_segments(pattern);
+            pattern.len() <= parts.len()
+                && pattern.iter().zip(&parts).all(|(p, s)| p == "*" || p == s)
+        })
+    }
+
+    /// Whether `value` is a string, number or boolean matching an ignored value pattern
+    pub fn is_masked(&self, value: &JsonValue) -> bool {
+        let text = match value {
+            JsonValue::String(s) => s.clone(),
+            JsonValue::Number(n) => n.to_string(),
+            JsonValue::Bool(b) => b.to_string(),
+            _ => return false,
+        };
+        self.ignore_values.iter().any(|re| re.is_match(&text))
+    }
+
+    fn skips(&self, op: &PatchOperation) -> bool {
+        match op {
+            PatchOperation::Move { from, path } | PatchOperation::Copy { from, path } => {
+                self.is_ignored(from) || self.is_ignored(path)
+            }
+            PatchOperation::Add { path, .. }
+            | PatchOperation::Remove { path }
+            | PatchOperation::Replace { path, .. }
+            | PatchOperation::Test { path, .. } => self.is_ignored(path),
+        }
+    }
+}
+
+fn ignore_segments(pattern: &str) -> Vec<String> {
+    if pattern.starts_with('/') {
+        parse_path(pattern)
+    } else {
+        pattern
+            .split('.')
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect()
+    }
+}
+
 /// Apply a JSON Patch to a document
 pub fn apply_patch(doc: &JsonValue, patch: &[PatchOperation]) -> Result<JsonValue> {
+    apply_patch_with_options(doc, patch, &PatchOptions::default())
+}
+
+/// Apply a JSON Patch, skipping operations that touch ignored paths
+pub fn apply_patch_with_options(
+    doc: &JsonValue,
+    patch: &[PatchOperation],
+    options: &PatchOptions,
+) -> Result<JsonValue> {
+    // Operations edit one working copy in place; each checks its target before
+    // changing anything, and an error drops the copy, so `doc` is never half-patched
     let mut result = doc.clone();
 
     for (i, op) in patch.iter().enumerate() {
-        result = apply_operation(&result, op)
+        let expanded = expand_operation(&result, op, options.extended)
             .with_context(|| format!("Failed to apply patch operation {} ({:?})", i, op))?;
+        for op in expanded.iter().filter(|op| !options.skips(op)) {
+            apply_operation(&mut result, op)
+                .with_context(|| format!("Failed to apply patch operation {} ({:?})", i, op))?;
+        }
     }
 
     Ok(result)
 }
 
-fn apply_operation(doc: &JsonValue, op: &PatchOperation) -> Result<JsonValue> {
+/// Replace a JSONPath `path` with one operation per matched location
+fn expand_operation(
+    doc: &JsonValue,
+    op: &PatchOperation,
+    extended: bool,
+) -> Result<Vec<PatchOperation>> {
+    let path = op.path();
+    if !path.starts_with('$') {
+        return Ok(vec![op.clone()]);
+    }
+    if !extended {
+        bail!(
+            "'{}' is a JSONPath, not a JSON Pointer; use --extended to allow it",
+            path
+        );
+    }
+    if let PatchOperation::Move { .. } = op {
+        bail!("JSONPath targets are not supported for move");
+    }
+
+    let mut pointers = query::jsonpath_pointers(doc, path)?;
+    if let PatchOperation::Remove { .. } = op {
+        // Remove later array elements first so earlier indices stay valid
+        pointers.reverse();
+    }
+    Ok(pointers
+        .into_iter()
+        .map(|pointer| {
+            let mut expanded = op.clone();
+            *expanded.path_mut() = pointer;
+            expanded
+        })
+        .collect())
+}
+
+fn apply_operation(doc: &mut JsonValue, op: &PatchOperation) -> Result<()> {
     match op {
-        PatchOperation::Add { path, value } => add_value(doc, path, value),
-        PatchOperation::Remove { path } => remove_value(doc, path),
-        PatchOperation::Replace { path, value } => replace_value(doc, path, value),
+        PatchOperation::Add { path, value } => add_value(doc, path, value.clone()),
+        PatchOperation::Remove { path } => remove_value(doc, path).map(drop),
+        PatchOperation::Replace { path, value } => replace_value(doc, path, value.clone()),
         PatchOperation::Move { from, path } => move_value(doc, from, path),
         PatchOperation::Copy { from, path } => copy_value(doc, from, path),
         PatchOperation::Test { path, value } => test_value(doc, path, value),
@@ -75,203 +219,150 @@ fn get_value<'a>(doc: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
     Some(current)
 }
 
-/// Add value at path
-fn add_value(doc: &JsonValue, path: &str, value: &JsonValue) -> Result<JsonValue> {
-    if path.is_empty() {
-        return Ok(value.clone());
+/// Array index named by a path segment, which must be below `len`
+fn array_index(key: &str, len: usize) -> Result<usize> {
+    let index: usize = key.parse().context("Invalid array index")?;
+    if index >= len {
+        bail!("Array index {} out of bounds", index);
     }
-
-    let parts = parse_path(path);
-    add_value_recursive(doc, &parts, value)
+    Ok(index)
 }
 
-fn add_value_recursive(doc: &JsonValue, path: &[String], value: &JsonValue) -> Result<JsonValue> {
-    if path.is_empty() {
-        return Ok(value.clone());
+/// Existing value at `parts`; `non_container` describes a step into a scalar
+fn value_mut<'a>(
+    doc: &'a mut JsonValue,
+    parts: &[String],
+    non_container: &str,
+) -> Result<&'a mut JsonValue> {
+    let mut current = doc;
+    for key in parts {
+        current = match current {
+            JsonValue::Object(obj) => obj
+                .get_mut(key)
+                .with_context(|| format!("Key '{}' not found", key))?,
+            JsonValue::Array(arr) => {
+                let index = array_index(key, arr.len())?;
+                &mut arr[index]
+            }
+            _ => bail!("{}", non_container),
+        };
     }
+    Ok(current)
+}
 
-    let key = &path[0];
+/// Add value at path, creating missing intermediate objects
+fn add_value(doc: &mut JsonValue, path: &str, value: JsonValue) -> Result<()> {
+    let parts = parse_path(path);
+    let Some((last, parents)) = parts.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+
+    let mut parent = doc;
+    for key in parents {
+        parent = match parent {
+            // Everything below a created object is created too, so nothing after
+            // this point can fail
+            JsonValue::Object(obj) => obj
+                .entry(key.clone())
+                .or_insert_with(|| JsonValue::Object(Map::new())),
+            JsonValue::Array(arr) => {
+                if key == "-" {
+                    bail!("Cannot navigate into '-' (append position)");
+                }
+                let index = array_index(key, arr.len())?;
+                &mut arr[index]
+            }
+            _ => bail!("Cannot add to non-container at path"),
+        };
+    }
 
-    match doc {
+    match parent {
         JsonValue::Object(obj) => {
-            let mut result = obj.clone();
-            if path.len() == 1 {
-                result.insert(key.clone(), value.clone());
-            } else if let Some(existing) = obj.get(key) {
-                result.insert(key.clone(), add_value_recursive(existing, &path[1..], value)?);
-            } else {
-                // Create path
-                let new_obj = add_value_recursive(&JsonValue::Object(Map::new()), &path[1..], value)?;
-                result.insert(key.clone(), new_obj);
-            }
-            Ok(JsonValue::Object(result))
+            obj.insert(last.clone(), value);
         }
+        JsonValue::Array(arr) if last == "-" => arr.push(value),
         JsonValue::Array(arr) => {
-            let mut result = arr.clone();
-            if key == "-" {
-                // Append to array
-                if path.len() == 1 {
-                    result.push(value.clone());
-                } else {
-                    anyhow::bail!("Cannot navigate into '-' (append position)");
-                }
-            } else {
-                let index: usize = key.parse().context("Invalid array index")?;
-                if path.len() == 1 {
-                    if index > arr.len() {
-                        anyhow::bail!("Array index {} out of bounds", index);
-                    }
-                    result.insert(index, value.clone());
-                } else if index < arr.len() {
-                    result[index] = add_value_recursive(&arr[index], &path[1..], value)?;
-                } else {
-                    anyhow::bail!("Array index {} out of bounds", index);
-                }
+            let index: usize = last.parse().context("Invalid array index")?;
+            if index > arr.len() {
+                bail!("Array index {} out of bounds", index);
             }
-            Ok(JsonValue::Array(result))
+            arr.insert(index, value);
         }
+        // A scalar parent is replaced by an object holding the new key
         _ => {
-            if path.len() == 1 {
-                // Create object with key
-                let mut obj = Map::new();
-                obj.insert(key.clone(), value.clone());
-                Ok(JsonValue::Object(obj))
-            } else {
-                anyhow::bail!("Cannot add to non-container at path");
-            }
+            let mut obj = Map::new();
+            obj.insert(last.clone(), value);
+            *parent = JsonValue::Object(obj);
         }
     }
+    Ok(())
 }
 
-/// Remove value at path
-fn remove_value(doc: &JsonValue, path: &str) -> Result<JsonValue> {
-    if path.is_empty() {
-        anyhow::bail!("Cannot remove root");
-    }
-
+/// Remove value at path, returning it
+fn remove_value(doc: &mut JsonValue, path: &str) -> Result<JsonValue> {
     let parts = parse_path(path);
-    remove_value_recursive(doc, &parts)
-}
-
-fn remove_value_recursive(doc: &JsonValue, path: &[String]) -> Result<JsonValue> {
-    if path.is_empty() {
-        anyhow::bail!("Cannot remove root");
-    }
-
-    let key = &path[0];
-
-    match doc {
-        JsonValue::Object(obj) => {
-            let mut result = obj.clone();
-            if path.len() == 1 {
-                if result.remove(key).is_none() {
-                    anyhow::bail!("Key '{}' not found", key);
-                }
-            } else if let Some(existing) = obj.get(key) {
-                result.insert(key.clone(), remove_value_recursive(existing, &path[1..])?);
-            } else {
-                anyhow::bail!("Key '{}' not found", key);
-            }
-            Ok(JsonValue::Object(result))
-        }
+    let Some((last, parents)) = parts.split_last() else {
+        bail!("Cannot remove root");
+    };
+
+    match value_mut(doc, parents, "Cannot remove from non-container")? {
+        JsonValue::Object(obj) => obj
+            .shift_remove(last)
+            .with_context(|| format!("Key '{}' not found", last)),
         JsonValue::Array(arr) => {
-            let index: usize = key.parse().context("Invalid array index")?;
-            if index >= arr.len() {
-                anyhow::bail!("Array index {} out of bounds", index);
-            }
-
-            let mut result = arr.clone();
-            if path.len() == 1 {
-                result.remove(index);
-            } else {
-                result[index] = remove_value_recursive(&arr[index], &path[1..])?;
-            }
-            Ok(JsonValue::Array(result))
+            let index = array_index(last, arr.len())?;
+            Ok(arr.remove(index))
         }
-        _ => anyhow::bail!("Cannot remove from non-container"),
+        _ => bail!("Cannot remove from non-container"),
     }
 }
 
 /// Replace value at path
-fn replace_value(doc: &JsonValue, path: &str, value: &JsonValue) -> Result<JsonValue> {
-    if path.is_empty() {
-        return Ok(value.clone());
-    }
-
+fn replace_value(doc: &mut JsonValue, path: &str, value: JsonValue) -> Result<()> {
     let parts = parse_path(path);
-    replace_value_recursive(doc, &parts, value)
-}
-
-fn replace_value_recursive(
-    doc: &JsonValue,
-    path: &[String],
-    value: &JsonValue,
-) -> Result<JsonValue> {
-    if path.is_empty() {
-        return Ok(value.clone());
-    }
-
-    let key = &path[0];
-
-    match doc {
-        JsonValue::Object(obj) => {
-            let mut result = obj.clone();
-            if path.len() == 1 {
-                if !obj.contains_key(key) {
-                    anyhow::bail!("Key '{}' not found for replace", key);
-                }
-                result.insert(key.clone(), value.clone());
-            } else if let Some(existing) = obj.get(key) {
-                result.insert(
-                    key.clone(),
-                    replace_value_recursive(existing, &path[1..], value)?,
-                );
-            } else {
-                anyhow::bail!("Key '{}' not found", key);
-            }
-            Ok(JsonValue::Object(result))
-        }
+    let Some((last, parents)) = parts.split_last() else {
+        *doc = value;
+        return Ok(());
+    };
+
+    let target = match value_mut(doc, parents, "Cannot replace in non-container")? {
+        JsonValue::Object(obj) => obj
+            .get_mut(last)
+            .with_context(|| format!("Key '{}' not found for replace", last))?,
         JsonValue::Array(arr) => {
-            let index: usize = key.parse().context("Invalid array index")?;
-            if index >= arr.len() {
-                anyhow::bail!("Array index {} out of bounds", index);
-            }
-
-            let mut result = arr.clone();
-            if path.len() == 1 {
-                result[index] = value.clone();
-            } else {
-                result[index] = replace_value_recursive(&arr[index], &path[1..], value)?;
-            }
-            Ok(JsonValue::Array(result))
+            let index = array_index(last, arr.len())?;
+            &mut arr[index]
         }
-        _ => anyhow::bail!("Cannot replace in non-container"),
-    }
+        _ => bail!("Cannot replace in non-container"),
+    };
+    *target = value;
+    Ok(())
 }
 
 /// Move value from one path to another
-fn move_value(doc: &JsonValue, from: &str, to: &str) -> Result<JsonValue> {
-    let value = get_value(doc, from)
-        .context(format!("Source path '{}' not found", from))?
-        .clone();
-    let without_source = remove_value(doc, from)?;
-    add_value(&without_source, to, &value)
+fn move_value(doc: &mut JsonValue, from: &str, to: &str) -> Result<()> {
+    if get_value(doc, from).is_none() {
+        bail!("Source path '{}' not found", from);
+    }
+    let value = remove_value(doc, from)?;
+    add_value(doc, to, value)
 }
 
 /// Copy value from one path to another
-fn copy_value(doc: &JsonValue, from: &str, to: &str) -> Result<JsonValue> {
+fn copy_value(doc: &mut JsonValue, from: &str, to: &str) -> Result<()> {
     let value = get_value(doc, from)
         .context(format!("Source path '{}' not found", from))?
         .clone();
-    add_value(doc, to, &value)
+    add_value(doc, to, value)
 }
 
 /// Test that value at path equals expected value
-fn test_value(doc: &JsonValue, path: &str, expected: &JsonValue) -> Result<JsonValue> {
+fn test_value(doc: &JsonValue, path: &str, expected: &JsonValue) -> Result<()> {
     let actual = get_value(doc, path).context(format!("Path '{}' not found", path))?;
 
     if actual == expected {
-        Ok(doc.clone())
+        Ok(())
     } else {
         anyhow::bail!(
             "Test failed at '{}': expected {}, got {}",
@@ -302,6 +393,27 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_ignored_operations_are_skipped() {
+        let doc = json!({"name": "Alice", "meta": {"updated": 1}});
+        let patch = vec![
+            PatchOperation::Replace {
+                path: "/name".to_string(),
+                value: json!("Bob"),
+            },
+            PatchOperation::Replace {
+                path: "/meta/updated".to_string(),
+                value: json!(2),
+            },
+        ];
+        let options = PatchOptions {
+            ignore: vec!["meta.updated".to_string()],
+            ..Default::default()
+        };
+        let result = apply_patch_with_options(&doc, &patch, &options).unwrap();
+        assert_eq!(result, json!({"name": "Bob", "meta": {"updated": 1}}));
+    }
+
     #[test]
     fn test_add_operation() {
         let doc = json!({"foo": "bar"});
@@ -325,6 +437,14 @@ mod tests {
         let result = apply_patch(&doc, &patch).unwrap();
         assert_eq!(result["foo"], "bar");
         assert!(result.get("baz").is_none());
+
+        // Remaining keys keep their order
+        let doc = json!({"a": 1, "b": 2, "c": 3, "d": 4});
+        let patch = vec![PatchOperation::Remove {
+            path: "/a".to_string(),
+        }];
+        let result = apply_patch(&doc, &patch).unwrap();
+        assert_eq!(result.to_string(), r#"{"b":2,"c":3,"d":4}"#);
     }
 
     #[test]
@@ -352,6 +472,25 @@ mod tests {
         assert!(result["foo"].get("bar").is_none());
     }
 
+    #[test]
+    fn test_failed_patch_leaves_document_unchanged() {
+        let doc = json!({"items": [1, 2], "name": "a"});
+        let patch: Vec<PatchOperation> = serde_json::from_value(json!([
+            {"op": "add", "path": "/items/-", "value": 3},
+            {"op": "add", "path": "/new/nested/key", "value": true},
+            {"op": "remove", "path": "/items/5"}
+        ]))
+        .unwrap();
+
+        let err = apply_patch(&doc, &patch).unwrap_err();
+        assert!(format!("{:#}", err).contains("Array index 5 out of bounds"));
+        assert_eq!(doc, json!({"items": [1, 2], "name": "a"}));
+
+        let result = apply_patch(&doc, &patch[..2]).unwrap();
+        assert_eq!(result["items"], json!([1, 2, 3]));
+        assert_eq!(result["new"]["nested"]["key"], true);
+    }
+
     #[test]
     fn test_test_operation() {
         let doc = json!({"foo": "bar"});
@@ -370,5 +509,36 @@ mod tests {
         let result_fail = apply_patch(&doc, &patch_fail);
         assert!(result_fail.is_err());
     }
+
+    #[test]
+    fn test_extended_jsonpath_operations() {
+        let doc = json!({"servers": [
+            {"name": "a", "tls": false},
+            {"name": "b"},
+            {"name": "c", "tls": false}
+        ]});
+        let patch = parse_patch(&json!([
+            {"op": "replace", "path": "$.servers[*].tls", "value": true}
+        ]))
+        .unwrap();
+
+        assert!(apply_patch(&doc, &patch).is_err());
+
+        let options = PatchOptions {
+            extended: true,
+            ..Default::default()
+        };
+        let result = apply_patch_with_options(&doc, &patch, &options).unwrap();
+        assert_eq!(result["servers"][0]["tls"], true);
+        assert!(result["servers"][1].get("tls").is_none());
+        assert_eq!(result["servers"][2]["tls"], true);
+
+        let patch = parse_patch(&json!([
+            {"op": "remove", "path": "$.servers[?(@.tls == false)]"}
+        ]))
+        .unwrap();
+        let result = apply_patch_with_options(&doc, &patch, &options).unwrap();
+        assert_eq!(result, json!({"servers": [{"name": "b"}]}));
+    }
 }
 
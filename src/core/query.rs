@@ -1,12 +1,24 @@
 //! Query engine for JSONPath and data transformation operations
 
 use anyhow::{bail, Context, Result};
-use jsonpath_rust::JsonPath;
+use jsonpath_rust::{JsonPath, JsonPathValue};
+use regex::Regex;
 use serde_json::{Map, Value as JsonValue};
 use std::str::FromStr;
 
+use super::expr::Expr;
+
 /// Execute a JSONPath query on JSON data
+///
+/// Paths starting with `/` are treated as RFC 6901 JSON Pointers (`/users/0/name`).
 pub fn jsonpath_query(value: &JsonValue, path: &str) -> Result<JsonValue> {
+    if path.starts_with('/') {
+        return value
+            .pointer(path)
+            .cloned()
+            .with_context(|| format!("JSON Pointer does not match any value: {}", path));
+    }
+
     let json_path =
         JsonPath::from_str(path).with_context(|| format!("Invalid JSONPath: {}", path))?;
 
@@ -19,6 +31,49 @@ pub fn jsonpath_query(value: &JsonValue, path: &str) -> Result<JsonValue> {
     }
 }
 
+/// JSON Pointers of every location a JSONPath selects, in document order
+pub fn jsonpath_pointers(value: &JsonValue, path: &str) -> Result<Vec<String>> {
+    let json_path =
+        JsonPath::from_str(path).with_context(|| format!("Invalid JSONPath: {}", path))?;
+    let targets: Vec<*const JsonValue> = json_path
+        .find_slice(value)
+        .into_iter()
+        .filter_map(|found| match found {
+            JsonPathValue::Slice(target, _) => Some(target as *const JsonValue),
+            _ => None,
+        })
+        .collect();
+
+    let mut pointers = Vec::new();
+    collect_pointers(value, String::new(), &targets, &mut pointers);
+    Ok(pointers)
+}
+
+fn collect_pointers(
+    value: &JsonValue,
+    pointer: String,
+    targets: &[*const JsonValue],
+    pointers: &mut Vec<String>,
+) {
+    if targets.contains(&(value as *const JsonValue)) {
+        pointers.push(pointer.clone());
+    }
+    match value {
+        JsonValue::Object(obj) => {
+            for (key, child) in obj {
+                let escaped = key.replace('~', "~0").replace('/', "~1");
+                collect_pointers(child, format!("{}/{}", pointer, escaped), targets, pointers);
+            }
+        }
+        JsonValue::Array(arr) => {
+            for (i, child) in arr.iter().enumerate() {
+                collect_pointers(child, format!("{}/{}", pointer, i), targets, pointers);
+            }
+        }
+        _ => {}
+    }
+}
+
 /// Extract all keys from a JSON object (recursive)
 pub fn extract_keys(value: &JsonValue, recursive: bool) -> JsonValue {
     let mut keys = Vec::new();
@@ -97,19 +152,67 @@ fn collect_values(value: &JsonValue, recursive: bool, values: &mut Vec<JsonValue
     }
 }
 
+/// How array positions appear in flattened keys
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayNotation {
+    /// `a.b.[0].c`
+    #[default]
+    Index,
+    /// `a.b[0].c`
+    Bracket,
+    /// `a.b.0.c`
+    Dot,
+}
+
+/// Options for [`flatten_with_options`]
+#[derive(Debug, Clone)]
+pub struct FlattenOptions {
+    /// Joins object keys (and array positions, except with bracket notation)
+    pub separator: String,
+    /// Nesting levels merged into keys; deeper values are kept as they are
+    pub depth: Option<usize>,
+    pub arrays: ArrayNotation,
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        Self {
+            separator: ".".to_string(),
+            depth: None,
+            arrays: ArrayNotation::default(),
+        }
+    }
+}
+
 /// Flatten a nested JSON structure
 pub fn flatten(value: &JsonValue, separator: &str) -> JsonValue {
+    let options = FlattenOptions {
+        separator: separator.to_string(),
+        ..Default::default()
+    };
+    flatten_with_options(value, &options)
+}
+
+/// Flatten a nested JSON structure up to a depth, with a choice of array notation
+pub fn flatten_with_options(value: &JsonValue, options: &FlattenOptions) -> JsonValue {
     let mut result = Map::new();
-    flatten_recursive(value, String::new(), separator, &mut result);
+    flatten_recursive(value, String::new(), 0, options, &mut result);
     JsonValue::Object(result)
 }
 
 fn flatten_recursive(
     value: &JsonValue,
     prefix: String,
-    separator: &str,
+    level: usize,
+    options: &FlattenOptions,
     result: &mut Map<String, JsonValue>,
 ) {
+    let separator = &options.separator;
+    if level > 0 && options.depth.is_some_and(|depth| level > depth) {
+        result.insert(prefix, value.clone());
+        return;
+    }
+
     match value {
         JsonValue::Object(obj) => {
             for (key, val) in obj {
@@ -118,17 +221,18 @@ fn flatten_recursive(
                 } else {
                     format!("{}{}{}", prefix, separator, key)
                 };
-                flatten_recursive(val, new_key, separator, result);
+                flatten_recursive(val, new_key, level + 1, options, result);
             }
         }
         JsonValue::Array(arr) => {
             for (i, item) in arr.iter().enumerate() {
-                let new_key = if prefix.is_empty() {
-                    format!("{}", i)
-                } else {
-                    format!("{}{}[{}]", prefix, separator, i)
+                let new_key = match (options.arrays, prefix.is_empty()) {
+                    (ArrayNotation::Bracket, _) => format!("{}[{}]", prefix, i),
+                    (_, true) => format!("{}", i),
+                    (ArrayNotation::Index, false) => format!("{}{}[{}]", prefix, separator, i),
+                    (ArrayNotation::Dot, false) => format!("{}{}{}", prefix, separator, i),
                 };
-                flatten_recursive(item, new_key, separator, result);
+                flatten_recursive(item, new_key, level + 1, options, result);
             }
         }
         _ => {
@@ -163,16 +267,32 @@ pub fn filter_array(value: &JsonValue, expression: &str) -> Result<JsonValue> {
         .as_array()
         .context("Filter can only be applied to arrays")?;
 
-    let filter = parse_filter_expression(expression)?;
+    let filter = Filter::parse(expression)?;
     let filtered: Vec<JsonValue> = arr
         .iter()
-        .filter(|item| evaluate_filter(item, &filter))
+        .filter(|item| filter.matches(item))
         .cloned()
         .collect();
 
     Ok(JsonValue::Array(filtered))
 }
 
+/// Compiled filter expression, reusable across elements
+#[derive(Debug)]
+pub struct Filter(FilterExpression);
+
+impl Filter {
+    /// Parse a filter expression (e.g., `age > 20`)
+    pub fn parse(expression: &str) -> Result<Self> {
+        parse_filter_expression(expression).map(Filter)
+    }
+
+    /// Check whether an element satisfies the filter
+    pub fn matches(&self, item: &JsonValue) -> bool {
+        evaluate_filter(item, &self.0)
+    }
+}
+
 #[derive(Debug)]
 enum FilterOp {
     Eq,
@@ -182,8 +302,19 @@ enum FilterOp {
     Ge,
     Le,
     Contains,
+    NotContains,
     StartsWith,
     EndsWith,
+    /// Equal to one of the listed values
+    In(Vec<String>),
+    NotIn(Vec<String>),
+    /// Matches a regular expression
+    Matches(Regex),
+    NotMatches(Regex),
+    /// The field is present, even if null
+    Exists,
+    /// The field is absent (a null field is not missing)
+    Missing,
 }
 
 #[derive(Debug)]
@@ -193,38 +324,111 @@ struct FilterExpression {
     value: String,
 }
 
+/// Binary filter operators; the leftmost one in an expression wins
+const FILTER_OPERATORS: [&str; 14] = [
+    ">=",
+    "<=",
+    "!=",
+    "==",
+    "=~",
+    "!~",
+    ">",
+    "<",
+    " not contains ",
+    " contains ",
+    " startswith ",
+    " endswith ",
+    " not in ",
+    " in ",
+];
+
 fn parse_filter_expression(expr: &str) -> Result<FilterExpression> {
     let expr = expr.trim();
 
-    // Try to match operators (order matters - longer operators first)
-    let operators = [
-        (">=", FilterOp::Ge),
-        ("<=", FilterOp::Le),
-        ("!=", FilterOp::Ne),
-        ("==", FilterOp::Eq),
-        (">", FilterOp::Gt),
-        ("<", FilterOp::Lt),
-        (" contains ", FilterOp::Contains),
-        (" startswith ", FilterOp::StartsWith),
-        (" endswith ", FilterOp::EndsWith),
-    ];
+    // Presence checks take no value: `email exists`, `deleted_at missing`
+    for (op_str, op) in [
+        (" exists", FilterOp::Exists),
+        (" missing", FilterOp::Missing),
+    ] {
+        if expr.to_lowercase().ends_with(op_str) {
+            let field = expr[..expr.len() - op_str.len()].trim();
+            if !field.is_empty() && !field.contains(char::is_whitespace) {
+                return Ok(FilterExpression {
+                    field: field.to_string(),
+                    op,
+                    value: String::new(),
+                });
+            }
+        }
+    }
 
-    for (op_str, op) in operators {
-        if let Some(pos) = expr.to_lowercase().find(op_str) {
-            let field = expr[..pos].trim().to_string();
-            let value = expr[pos + op_str.len()..].trim().to_string();
+    // The operator starting first follows the field name, so operator characters
+    // inside values (`status in [">"]`) are left alone; longer operators win ties
+    let lower = expr.to_lowercase();
+    let found = FILTER_OPERATORS
+        .iter()
+        .filter_map(|op_str| lower.find(op_str).map(|pos| (pos, *op_str)))
+        .min_by_key(|(pos, op_str)| (*pos, std::cmp::Reverse(op_str.len())));
 
-            // Remove quotes from value if present
-            let value = value.trim_matches('"').trim_matches('\'').to_string();
+    let Some((pos, op_str)) = found else {
+        bail!(
+            "Invalid filter expression: {}. Use format: field op value (e.g., age > 20, name == \"test\", email exists)",
+            expr
+        )
+    };
 
-            return Ok(FilterExpression { field, op, value });
-        }
-    }
+    let field = expr[..pos].trim().to_string();
+    let raw = expr[pos + op_str.len()..].trim();
+    // Remove quotes from value if present
+    let value = raw.trim_matches('"').trim_matches('\'').to_string();
+
+    let op = match op_str.trim() {
+        ">=" => FilterOp::Ge,
+        "<=" => FilterOp::Le,
+        "!=" => FilterOp::Ne,
+        "==" => FilterOp::Eq,
+        ">" => FilterOp::Gt,
+        "<" => FilterOp::Lt,
+        "=~" => FilterOp::Matches(filter_regex(&value)?),
+        "!~" => FilterOp::NotMatches(filter_regex(&value)?),
+        "not contains" => FilterOp::NotContains,
+        "contains" => FilterOp::Contains,
+        "startswith" => FilterOp::StartsWith,
+        "endswith" => FilterOp::EndsWith,
+        "not in" => FilterOp::NotIn(parse_filter_list(raw)?),
+        _ => FilterOp::In(parse_filter_list(raw)?),
+    };
+
+    Ok(FilterExpression { field, op, value })
+}
 
-    bail!(
-        "Invalid filter expression: {}. Use format: field op value (e.g., age > 20, name == \"test\")",
-        expr
-    )
+fn filter_regex(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern)
+        .with_context(|| format!("Invalid regular expression in filter: {}", pattern))
+}
+
+/// Values of an `in` list: a JSON array (`["a", 1]`) or bare comma-separated items (`[a, b]`)
+fn parse_filter_list(raw: &str) -> Result<Vec<String>> {
+    if !(raw.starts_with('[') && raw.ends_with(']')) {
+        bail!(
+            "Expected a list after 'in', e.g. status in [\"active\", \"trial\"]: {}",
+            raw
+        );
+    }
+    if let Ok(JsonValue::Array(items)) = serde_json::from_str::<JsonValue>(raw) {
+        return Ok(items
+            .iter()
+            .map(|item| match item {
+                JsonValue::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect());
+    }
+    Ok(raw[1..raw.len() - 1]
+        .split(',')
+        .map(|item| item.trim().trim_matches('"').trim_matches('\'').to_string())
+        .filter(|item| !item.is_empty())
+        .collect())
 }
 
 fn evaluate_filter(item: &JsonValue, filter: &FilterExpression) -> bool {
@@ -253,6 +457,10 @@ fn evaluate_filter(item: &JsonValue, filter: &FilterExpression) -> bool {
                 .as_str()
                 .map(|s| s.to_lowercase().contains(&filter.value.to_lowercase()))
                 .unwrap_or(false),
+            FilterOp::NotContains => val
+                .as_str()
+                .map(|s| !s.to_lowercase().contains(&filter.value.to_lowercase()))
+                .unwrap_or(false),
             FilterOp::StartsWith => val
                 .as_str()
                 .map(|s| s.to_lowercase().starts_with(&filter.value.to_lowercase()))
@@ -261,12 +469,33 @@ fn evaluate_filter(item: &JsonValue, filter: &FilterExpression) -> bool {
                 .as_str()
                 .map(|s| s.to_lowercase().ends_with(&filter.value.to_lowercase()))
                 .unwrap_or(false),
+            FilterOp::In(values) => in_list(val, values),
+            FilterOp::NotIn(values) => !in_list(val, values),
+            FilterOp::Matches(re) => regex_matches(val, re),
+            FilterOp::NotMatches(re) => !regex_matches(val, re),
+            FilterOp::Exists => true,
+            FilterOp::Missing => false,
         },
-        None => false,
+        None => matches!(filter.op, FilterOp::Missing),
     }
 }
 
-fn get_nested_value<'a>(value: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+fn in_list(val: &JsonValue, values: &[String]) -> bool {
+    values
+        .iter()
+        .any(|v| compare_values(val, v) == Some(std::cmp::Ordering::Equal))
+}
+
+/// Regex match against strings, or the JSON text of numbers and booleans
+fn regex_matches(val: &JsonValue, re: &Regex) -> bool {
+    match val {
+        JsonValue::String(s) => re.is_match(s),
+        JsonValue::Number(_) | JsonValue::Bool(_) => re.is_match(&val.to_string()),
+        _ => false,
+    }
+}
+
+pub(crate) fn get_nested_value<'a>(value: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
     let parts: Vec<&str> = path.split('.').collect();
     let mut current = value;
 
@@ -294,11 +523,18 @@ fn compare_values(json_val: &JsonValue, filter_val: &str) -> Option<std::cmp::Or
                     jn.partial_cmp(&filter_num)
                         .unwrap_or(std::cmp::Ordering::Equal)
                 })
+            } else if let Some(filter_ts) = parse_timestamp(filter_val) {
+                // Numeric field compared against a date: treat it as an epoch timestamp
+                n.as_f64()
+                    .and_then(|jn| epoch_seconds(jn).partial_cmp(&filter_ts))
             } else {
                 None
             }
         }
-        JsonValue::String(s) => Some(s.cmp(&filter_val.to_string())),
+        JsonValue::String(s) => match (parse_timestamp(s), parse_timestamp_or_epoch(filter_val)) {
+            (Some(field_ts), Some(filter_ts)) => field_ts.partial_cmp(&filter_ts),
+            _ => Some(s.cmp(&filter_val.to_string())),
+        },
         JsonValue::Bool(b) => {
             let filter_bool = filter_val.to_lowercase() == "true";
             Some(b.cmp(&filter_bool))
@@ -314,6 +550,50 @@ fn compare_values(json_val: &JsonValue, filter_val: &str) -> Option<std::cmp::Or
     }
 }
 
+/// Parse an ISO 8601 date or datetime into seconds since the Unix epoch
+///
+/// Datetimes without an offset are interpreted as UTC.
+fn parse_timestamp(s: &str) -> Option<f64> {
+    use chrono::{DateTime, NaiveDate, NaiveDateTime};
+
+    let s = s.trim();
+    if s.len() < 10 || !s.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(s) {
+        return Some(dt.timestamp_millis() as f64 / 1000.0);
+    }
+
+    for fmt in [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M",
+    ] {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(s, fmt) {
+            return Some(dt.and_utc().timestamp_millis() as f64 / 1000.0);
+        }
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc().timestamp() as f64)
+}
+
+fn parse_timestamp_or_epoch(s: &str) -> Option<f64> {
+    parse_timestamp(s).or_else(|| s.trim().parse::<f64>().ok().map(epoch_seconds))
+}
+
+/// Normalize an epoch timestamp to seconds (values beyond year ~5138 are taken as milliseconds)
+fn epoch_seconds(n: f64) -> f64 {
+    if n.abs() >= 1e11 {
+        n / 1000.0
+    } else {
+        n
+    }
+}
+
 /// Select specific fields from objects in an array
 pub fn select_fields(value: &JsonValue, fields: &[String]) -> Result<JsonValue> {
     match value {
@@ -372,6 +652,42 @@ pub fn count(value: &JsonValue) -> JsonValue {
     }
 }
 
+/// Distinct values of `field` across array elements with how often each occurs
+///
+/// Yields `[{"value": ..., "count": n}, ...]`, most frequent first, ties in order
+/// of first appearance, cut to the `top` entries. Elements without the field
+/// are not counted.
+pub fn frequency(value: &JsonValue, field: &str, top: Option<usize>) -> Result<JsonValue> {
+    let arr = value
+        .as_array()
+        .context("Frequency can only be applied to arrays")?;
+
+    let mut index: std::collections::HashMap<String, usize> = Default::default();
+    let mut counts: Vec<(&JsonValue, usize)> = Vec::new();
+    for item in arr {
+        let Some(field_value) = get_nested_value(item, field) else {
+            continue;
+        };
+        let key = serde_json::to_string(field_value).unwrap_or_default();
+        match index.get(&key) {
+            Some(&i) => counts[i].1 += 1,
+            None => {
+                index.insert(key, counts.len());
+                counts.push((field_value, 1));
+            }
+        }
+    }
+
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+    counts.truncate(top.unwrap_or(usize::MAX));
+    Ok(JsonValue::Array(
+        counts
+            .into_iter()
+            .map(|(value, count)| serde_json::json!({"value": value, "count": count}))
+            .collect(),
+    ))
+}
+
 /// Reverse array elements
 pub fn reverse(value: &JsonValue) -> Result<JsonValue> {
     let arr = value
@@ -401,6 +717,548 @@ pub fn last(value: &JsonValue, n: usize) -> Result<JsonValue> {
     Ok(JsonValue::Array(taken))
 }
 
+/// Array slice `START:END[:STEP]` as in Python: bounds may be negative to count
+/// from the end, either may be omitted, and a negative step walks backwards
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Slice {
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub step: i64,
+}
+
+impl Slice {
+    /// Parse a slice expression (e.g., `2:5`, `-10:`, `::2`, `::-1`)
+    pub fn parse(expr: &str) -> Result<Self> {
+        let parts: Vec<&str> = expr.split(':').collect();
+        if !(2..=3).contains(&parts.len()) {
+            bail!("Invalid slice: {}. Use: START:END[:STEP]", expr);
+        }
+        let bound = |part: &str| -> Result<Option<i64>> {
+            let part = part.trim();
+            if part.is_empty() {
+                return Ok(None);
+            }
+            part.parse()
+                .map(Some)
+                .with_context(|| format!("Invalid slice index '{}' in {}", part, expr))
+        };
+        let step = match parts.get(2) {
+            Some(part) => bound(part)?.unwrap_or(1),
+            None => 1,
+        };
+        if step == 0 {
+            bail!("Invalid slice: {}. Step cannot be zero", expr);
+        }
+        Ok(Slice {
+            start: bound(parts[0])?,
+            end: bound(parts[1])?,
+            step,
+        })
+    }
+}
+
+/// Get the elements selected by a slice
+pub fn slice(value: &JsonValue, slice: &Slice) -> Result<JsonValue> {
+    let arr = value
+        .as_array()
+        .context("Slice can only be applied to arrays")?;
+    let len = arr.len() as i64;
+    // Indices are clamped to the array; walking backwards, -1 is before the start
+    let (lower, upper) = if slice.step > 0 {
+        (0, len)
+    } else {
+        (-1, len - 1)
+    };
+    let clamp = |index: Option<i64>, default: i64| match index {
+        None => default,
+        Some(i) if i < 0 => (i + len).max(lower),
+        Some(i) => i.min(upper),
+    };
+    let (start, end) = if slice.step > 0 {
+        (clamp(slice.start, lower), clamp(slice.end, upper))
+    } else {
+        (clamp(slice.start, upper), clamp(slice.end, lower))
+    };
+
+    let before_end = |i: i64| if slice.step > 0 { i < end } else { i > end };
+    let mut taken = Vec::new();
+    let mut i = Some(start);
+    // A step past the end of i64 leaves nothing more to take
+    while let Some(index) = i.filter(|&i| before_end(i)) {
+        taken.push(arr[index as usize].clone());
+        i = index.checked_add(slice.step);
+    }
+    Ok(JsonValue::Array(taken))
+}
+
+/// String operation applied by `--map`
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringOp {
+    Trim,
+    Lower,
+    Upper,
+    Replace(String, String),
+    Split(String),
+    Substring(usize, Option<usize>),
+}
+
+/// Field mapping: a field path and the string operations applied to it in order
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldMapping {
+    pub field: String,
+    pub ops: Vec<StringOp>,
+}
+
+/// Parse a mapping expression (e.g., `name=trim|lower`, `tags=split(";")`)
+pub fn parse_mapping(expr: &str) -> Result<FieldMapping> {
+    let (field, ops_str) = expr
+        .split_once('=')
+        .with_context(|| format!("Invalid map expression: {}. Use: field=op|op", expr))?;
+
+    let field = field.trim().to_string();
+    if field.is_empty() {
+        bail!("Invalid map expression: {}. Field name is empty", expr);
+    }
+
+    let ops = split_outside_parens(ops_str, '|')
+        .iter()
+        .map(|op| parse_string_op(op.trim()))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(FieldMapping { field, ops })
+}
+
+fn parse_string_op(op: &str) -> Result<StringOp> {
+    let (name, args) = match op.find('(') {
+        Some(pos) if op.ends_with(')') => {
+            let args = split_outside_parens(&op[pos + 1..op.len() - 1], ',')
+                .iter()
+                .map(|a| a.trim().trim_matches('"').trim_matches('\'').to_string())
+                .collect::<Vec<_>>();
+            (op[..pos].trim(), args)
+        }
+        _ => (op, Vec::new()),
+    };
+
+    let index = |i: usize| -> Result<usize> {
+        args[i]
+            .parse()
+            .with_context(|| format!("Invalid index '{}' in {}", args[i], op))
+    };
+
+    match (name.to_lowercase().as_str(), args.len()) {
+        ("trim", 0) => Ok(StringOp::Trim),
+        ("lower", 0) => Ok(StringOp::Lower),
+        ("upper", 0) => Ok(StringOp::Upper),
+        ("replace", 2) => Ok(StringOp::Replace(args[0].clone(), args[1].clone())),
+        ("split", 0) => Ok(StringOp::Split(",".to_string())),
+        ("split", 1) => Ok(StringOp::Split(args[0].clone())),
+        ("substring", 1) => Ok(StringOp::Substring(index(0)?, None)),
+        ("substring", 2) => Ok(StringOp::Substring(index(0)?, Some(index(1)?))),
+        _ => bail!(
+            "Invalid string operation: {}. Supported: trim, lower, upper, replace(from,to), split(sep), substring(start[,end])",
+            op
+        ),
+    }
+}
+
+fn split_outside_parens(s: &str, separator: char) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut quote: Option<char> = None;
+
+    for ch in s.chars() {
+        match ch {
+            '"' | '\'' if quote == Some(ch) => quote = None,
+            '"' | '\'' if quote.is_none() => quote = Some(ch),
+            '(' if quote.is_none() => depth += 1,
+            ')' if quote.is_none() => depth = depth.saturating_sub(1),
+            _ if ch == separator && depth == 0 && quote.is_none() => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(ch);
+    }
+    parts.push(current);
+    parts
+}
+
+/// Apply field mappings to an object or to every object in an array
+pub fn map_fields(value: &JsonValue, mappings: &[FieldMapping]) -> Result<JsonValue> {
+    match value {
+        JsonValue::Array(arr) => Ok(JsonValue::Array(
+            arr.iter().map(|item| map_object(item, mappings)).collect(),
+        )),
+        JsonValue::Object(_) => Ok(map_object(value, mappings)),
+        _ => bail!("Map can only be applied to objects or arrays of objects"),
+    }
+}
+
+fn map_object(item: &JsonValue, mappings: &[FieldMapping]) -> JsonValue {
+    let mut result = item.clone();
+    for mapping in mappings {
+        if let Some(target) = get_nested_value_mut(&mut result, &mapping.field) {
+            for op in &mapping.ops {
+                *target = apply_string_op(target, op);
+            }
+        }
+    }
+    result
+}
+
+/// Field computed by `--add-field`: a field path and the expression giving its value
+#[derive(Debug, Clone, PartialEq)]
+pub struct ComputedField {
+    pub field: String,
+    pub expr: Expr,
+}
+
+/// Parse a computed field (e.g., `full_name = first + " " + last`)
+pub fn parse_computed_field(definition: &str) -> Result<ComputedField> {
+    let (field, expr) = definition.split_once('=').with_context(|| {
+        format!(
+            "Invalid field definition: {}. Use: field = expression",
+            definition
+        )
+    })?;
+    let field = field.trim().to_string();
+    if field.is_empty() {
+        bail!(
+            "Invalid field definition: {}. Field name is empty",
+            definition
+        );
+    }
+    Ok(ComputedField {
+        field,
+        expr: Expr::parse(expr.trim())?,
+    })
+}
+
+/// Add computed fields to an object or to every object in an array, in order,
+/// so later fields can use earlier ones; other array elements are kept as is
+pub fn add_fields(value: &JsonValue, fields: &[ComputedField]) -> Result<JsonValue> {
+    match value {
+        JsonValue::Array(arr) => arr
+            .iter()
+            .map(|item| match item {
+                JsonValue::Object(_) => add_fields_to_object(item, fields),
+                _ => Ok(item.clone()),
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(JsonValue::Array),
+        JsonValue::Object(_) => add_fields_to_object(value, fields),
+        _ => bail!("Fields can only be added to objects or arrays of objects"),
+    }
+}
+
+fn add_fields_to_object(item: &JsonValue, fields: &[ComputedField]) -> Result<JsonValue> {
+    let mut result = item.clone();
+    for computed in fields {
+        let value = computed
+            .expr
+            .eval(&result)
+            .with_context(|| format!("Failed to compute '{}'", computed.field))?;
+        let segments: Vec<String> = computed.field.split('.').map(String::from).collect();
+        set_at(&mut result, &computed.field, &segments, value)?;
+    }
+    Ok(result)
+}
+
+/// Set the value at a path of object keys and array indices, creating
+/// objects for missing parents; an index one past the end appends
+fn set_at(target: &mut JsonValue, path: &str, segments: &[String], value: JsonValue) -> Result<()> {
+    let Some((last, parents)) = segments.split_last() else {
+        *target = value;
+        return Ok(());
+    };
+    let mut current = target;
+    for part in parents {
+        if current.is_null() {
+            *current = JsonValue::Object(Map::new());
+        }
+        current = match current {
+            JsonValue::Object(obj) => obj.entry(part.as_str()).or_insert(JsonValue::Null),
+            JsonValue::Array(arr) => part
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| arr.get_mut(i))
+                .with_context(|| format!("Cannot set '{}': no element {}", path, part))?,
+            _ => bail!(
+                "Cannot set '{}': a parent of '{}' is not an object",
+                path,
+                part
+            ),
+        };
+    }
+    if current.is_null() {
+        *current = JsonValue::Object(Map::new());
+    }
+    match current {
+        JsonValue::Object(obj) => {
+            obj.insert(last.clone(), value);
+        }
+        JsonValue::Array(arr) => match last.parse::<usize>() {
+            Ok(i) if i < arr.len() => arr[i] = value,
+            Ok(i) if i == arr.len() => arr.push(value),
+            _ => bail!("Cannot set '{}': no element {}", path, last),
+        },
+        _ => bail!("Cannot set '{}': its parent is not an object", path),
+    }
+    Ok(())
+}
+
+/// Assignment made by `--set`: a JSONPath or JSON Pointer and the value
+/// written at every location it selects
+#[derive(Debug, Clone, PartialEq)]
+pub struct Assignment {
+    pub path: String,
+    pub value: JsonValue,
+}
+
+/// Parse an assignment (e.g., `$.server.port=8080`, `/name="dtx"`); the
+/// trimmed value is read as JSON when it parses and as a string otherwise
+pub fn parse_assignment(expr: &str) -> Result<Assignment> {
+    let split = assignment_split(expr)
+        .with_context(|| format!("Invalid assignment: {}. Use: PATH=VALUE", expr))?;
+    let path = expr[..split].trim();
+    if path.is_empty() {
+        bail!("Invalid assignment: {}. Path is empty", expr);
+    }
+    let raw = expr[split + 1..].trim();
+    let value = serde_json::from_str(raw).unwrap_or_else(|_| JsonValue::String(raw.to_string()));
+    Ok(Assignment {
+        path: path.to_string(),
+        value,
+    })
+}
+
+/// Position of the `=` ending the path, skipping comparisons inside filters
+fn assignment_split(expr: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut quote = None;
+    for (i, c) in expr.char_indices() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '[' | '(') => depth += 1,
+            (None, ']' | ')') => depth = depth.saturating_sub(1),
+            (None, '=') if depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Apply assignments in order. Every location a JSONPath selects is set; a
+/// path of plain keys and indices (`$.a.b`, `$['k'][0]`) or a JSON Pointer
+/// that selects nothing is created, along with missing parent objects.
+pub fn set_values(value: &JsonValue, assignments: &[Assignment]) -> Result<JsonValue> {
+    let mut result = value.clone();
+    for assignment in assignments {
+        let path = assignment.path.as_str();
+        let targets: Vec<Vec<String>> = if path.starts_with('/') {
+            vec![pointer_segments(path)]
+        } else {
+            let pointers = jsonpath_pointers(&result, path)?;
+            if pointers.is_empty() {
+                vec![simple_path_segments(path)
+                    .with_context(|| format!("JSONPath does not match any value: {}", path))?]
+            } else {
+                pointers.iter().map(|p| pointer_segments(p)).collect()
+            }
+        };
+        for segments in targets {
+            set_at(&mut result, path, &segments, assignment.value.clone())?;
+        }
+    }
+    Ok(result)
+}
+
+/// Unescaped reference tokens of a JSON Pointer
+fn pointer_segments(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+/// Keys and indices of a JSONPath without wildcards, filters or slices
+fn simple_path_segments(path: &str) -> Option<Vec<String>> {
+    let mut rest = path.trim().strip_prefix('$')?;
+    let mut segments = Vec::new();
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[']).unwrap_or(after.len());
+            let key = &after[..end];
+            if key.is_empty() || key == "*" {
+                return None;
+            }
+            segments.push(key.to_string());
+            rest = &after[end..];
+        } else {
+            let after = rest.strip_prefix('[')?;
+            let end = after.find(']')?;
+            let inner = after[..end].trim();
+            let quoted = ['\'', '"']
+                .iter()
+                .find_map(|&q| inner.strip_prefix(q)?.strip_suffix(q));
+            match quoted {
+                Some(key) => segments.push(key.to_string()),
+                None if inner.parse::<usize>().is_ok() => segments.push(inner.to_string()),
+                None => return None,
+            }
+            rest = &after[end + 1..];
+        }
+    }
+    Some(segments)
+}
+
+fn get_nested_value_mut<'a>(value: &'a mut JsonValue, path: &str) -> Option<&'a mut JsonValue> {
+    let mut current = value;
+
+    for part in path.split('.') {
+        current = match current {
+            JsonValue::Object(obj) => obj.get_mut(part)?,
+            JsonValue::Array(arr) => arr.get_mut(part.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+
+    Some(current)
+}
+
+fn apply_string_op(value: &JsonValue, op: &StringOp) -> JsonValue {
+    let s = match value {
+        JsonValue::String(s) => s,
+        // Results of split are mapped element-wise
+        JsonValue::Array(arr) => {
+            return JsonValue::Array(arr.iter().map(|v| apply_string_op(v, op)).collect())
+        }
+        _ => return value.clone(),
+    };
+
+    match op {
+        StringOp::Trim => JsonValue::String(s.trim().to_string()),
+        StringOp::Lower => JsonValue::String(s.to_lowercase()),
+        StringOp::Upper => JsonValue::String(s.to_uppercase()),
+        StringOp::Replace(from, to) => JsonValue::String(s.replace(from.as_str(), to)),
+        StringOp::Split(sep) => JsonValue::Array(
+            s.split(sep.as_str())
+                .map(|part| JsonValue::String(part.to_string()))
+                .collect(),
+        ),
+        StringOp::Substring(start, end) => {
+            let chars = s.chars().skip(*start);
+            let taken: String = match end {
+                Some(end) => chars.take(end.saturating_sub(*start)).collect(),
+                None => chars.collect(),
+            };
+            JsonValue::String(taken)
+        }
+    }
+}
+
+/// Aggregate operation over a numeric field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateOp {
+    Sum,
+    Avg,
+    Min,
+    Max,
+}
+
+impl AggregateOp {
+    /// Get operation name as string
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AggregateOp::Sum => "sum",
+            AggregateOp::Avg => "avg",
+            AggregateOp::Min => "min",
+            AggregateOp::Max => "max",
+        }
+    }
+}
+
+/// Reduce an array of objects with aggregate operations
+///
+/// A single aggregate yields a plain number; several yield an object keyed by
+/// operation and field (e.g. `{"sum": {"price": 10}, "max": {"qty": 3}}`).
+/// Non-numeric and missing values are ignored.
+pub fn aggregate(value: &JsonValue, aggregates: &[(AggregateOp, String)]) -> Result<JsonValue> {
+    let arr = value
+        .as_array()
+        .context("Aggregation can only be applied to arrays")?;
+
+    if let [(op, field)] = aggregates {
+        return Ok(aggregate_field(arr, *op, field));
+    }
+
+    let mut result = Map::new();
+    for (op, field) in aggregates {
+        let entry = result
+            .entry(op.as_str())
+            .or_insert_with(|| JsonValue::Object(Map::new()));
+        if let JsonValue::Object(fields) = entry {
+            fields.insert(field.clone(), aggregate_field(arr, *op, field));
+        }
+    }
+    Ok(JsonValue::Object(result))
+}
+
+fn aggregate_field(arr: &[JsonValue], op: AggregateOp, field: &str) -> JsonValue {
+    let numbers: Vec<&serde_json::Number> = arr
+        .iter()
+        .filter_map(|item| match get_nested_value(item, field) {
+            Some(JsonValue::Number(n)) => Some(n),
+            _ => None,
+        })
+        .collect();
+
+    let floats = numbers.iter().filter_map(|n| n.as_f64());
+
+    match op {
+        // Sums of integers stay integers unless they overflow
+        AggregateOp::Sum => match numbers
+            .iter()
+            .try_fold(0i64, |total, n| total.checked_add(n.as_i64()?))
+        {
+            Some(total) => JsonValue::from(total),
+            None => number_value(floats.sum()),
+        },
+        AggregateOp::Avg if numbers.is_empty() => JsonValue::Null,
+        AggregateOp::Avg => number_value(floats.sum::<f64>() / numbers.len() as f64),
+        AggregateOp::Min | AggregateOp::Max => {
+            let pick = |a: &&serde_json::Number, b: &&serde_json::Number| {
+                let ord = a
+                    .as_f64()
+                    .partial_cmp(&b.as_f64())
+                    .unwrap_or(std::cmp::Ordering::Equal);
+                if op == AggregateOp::Min {
+                    ord
+                } else {
+                    ord.reverse()
+                }
+            };
+            numbers
+                .into_iter()
+                .min_by(pick)
+                .map(|n| JsonValue::Number(n.clone()))
+                .unwrap_or(JsonValue::Null)
+        }
+    }
+}
+
+pub(crate) fn number_value(f: f64) -> JsonValue {
+    serde_json::Number::from_f64(f)
+        .map(JsonValue::Number)
+        .unwrap_or(JsonValue::Null)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -417,6 +1275,38 @@ mod tests {
 
         let result = jsonpath_query(&data, "$.users[*].name").unwrap();
         assert_eq!(result, json!(["Alice", "Bob"]));
+
+        let result = jsonpath_query(&data, "/users/1/name").unwrap();
+        assert_eq!(result, json!("Bob"));
+        assert!(jsonpath_query(&data, "/users/2").is_err());
+    }
+
+    #[test]
+    fn test_frequency() {
+        let data = json!([
+            {"status": "ok", "meta": {"region": "eu"}},
+            {"status": "error", "meta": {"region": "us"}},
+            {"status": "ok"},
+            {"status": "pending"},
+            {"status": "error"},
+            {"status": "ok"},
+            {"id": 7}
+        ]);
+
+        let result = frequency(&data, "status", None).unwrap();
+        assert_eq!(
+            result,
+            json!([
+                {"value": "ok", "count": 3},
+                {"value": "error", "count": 2},
+                {"value": "pending", "count": 1}
+            ])
+        );
+        let result = frequency(&data, "status", Some(1)).unwrap();
+        assert_eq!(result, json!([{"value": "ok", "count": 3}]));
+        let result = frequency(&data, "meta.region", None).unwrap();
+        assert_eq!(result.as_array().unwrap().len(), 2);
+        assert!(frequency(&json!({"a": 1}), "a", None).is_err());
     }
 
     #[test]
@@ -434,6 +1324,27 @@ mod tests {
         assert_eq!(flat, json!({"a.b": 1}));
     }
 
+    #[test]
+    fn test_flatten_with_options() {
+        let data = json!({"a": {"b": [{"c": 1}], "d": {"e": {"f": 2}}}});
+        let mut options = FlattenOptions {
+            arrays: ArrayNotation::Bracket,
+            ..Default::default()
+        };
+        assert_eq!(
+            flatten_with_options(&data, &options),
+            json!({"a.b[0].c": 1, "a.d.e.f": 2})
+        );
+
+        options.arrays = ArrayNotation::Dot;
+        options.separator = "_".to_string();
+        options.depth = Some(2);
+        assert_eq!(
+            flatten_with_options(&data, &options),
+            json!({"a_b_0": {"c": 1}, "a_d_e": {"f": 2}})
+        );
+    }
+
     #[test]
     fn test_sort_keys() {
         let data = json!({"c": 3, "a": 1, "b": 2});
@@ -454,9 +1365,231 @@ mod tests {
         assert_eq!(filtered[0]["name"], "Alice");
     }
 
+    #[test]
+    fn test_filter_presence() {
+        let data = json!([
+            {"id": 1, "email": "a@example.com"},
+            {"id": 2, "email": null, "user": {"deleted_at": null}},
+            {"id": 3, "note": "exists"}
+        ]);
+        let ids = |expr: &str| {
+            let filtered = filter_array(&data, expr).unwrap();
+            filtered
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["id"].as_i64().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids("email exists"), vec![1, 2]);
+        assert_eq!(ids("email missing"), vec![3]);
+        assert_eq!(ids("user.deleted_at EXISTS"), vec![2]);
+        assert_eq!(ids("note contains exists"), vec![3]);
+    }
+
+    #[test]
+    fn test_filter_lists_and_negation() {
+        let data = json!([
+            {"id": 1, "status": "active", "email": "a@example.com"},
+            {"id": 2, "status": "trial", "email": "b@test.org"},
+            {"id": 3, "status": "closed", "email": "c@example.com"}
+        ]);
+        let ids = |expr: &str| {
+            let filtered = filter_array(&data, expr).unwrap();
+            filtered
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|item| item["id"].as_i64().unwrap())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(ids(r#"status in ["active","trial"]"#), vec![1, 2]);
+        assert_eq!(ids("status not in [active, trial]"), vec![3]);
+        assert_eq!(ids("id in [1, 3]"), vec![1, 3]);
+        assert_eq!(ids("email not contains example"), vec![2]);
+        assert_eq!(ids(r"email =~ ^[ab]@"), vec![1, 2]);
+        assert_eq!(ids(r"email !~ \.com$"), vec![2]);
+        // Operator characters inside the value do not split the expression
+        assert_eq!(ids(r#"status in [">", "closed"]"#), vec![3]);
+
+        assert!(filter_array(&data, "status in active").is_err());
+        assert!(filter_array(&data, "email =~ (").is_err());
+    }
+
+    #[test]
+    fn test_filter_dates() {
+        let data = json!([
+            {"id": 1, "created_at": "2023-12-31T23:00:00Z"},
+            {"id": 2, "created_at": "2024-01-01T09:00:00+09:00"},
+            {"id": 3, "created_at": "2024-03-05"},
+            {"id": 4, "created_at": 1717200000},
+            {"id": 5, "created_at": 1704067200000i64}
+        ]);
+
+        let filtered = filter_array(&data, "created_at > 2024-01-01").unwrap();
+        let ids: Vec<&JsonValue> = filtered
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|v| &v["id"])
+            .collect();
+        assert_eq!(ids, vec![&json!(3), &json!(4)]);
+
+        // Offsets and millisecond epochs land exactly on the boundary
+        let filtered = filter_array(&data, "created_at >= 2024-01-01T00:00:00Z").unwrap();
+        assert_eq!(filtered.as_array().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_map_fields() {
+        let data = json!([
+            {"name": "  Alice ", "tags": "a;b", "code": "ABC-123"},
+            {"name": "BOB", "tags": "c", "code": "XYZ-9"}
+        ]);
+        let mappings = vec![
+            parse_mapping("name=trim|lower").unwrap(),
+            parse_mapping("tags=split(\";\")|upper").unwrap(),
+            parse_mapping("code=replace(\"-\", \"\")|substring(0,3)").unwrap(),
+        ];
+
+        let result = map_fields(&data, &mappings).unwrap();
+        assert_eq!(result[0]["name"], "alice");
+        assert_eq!(result[0]["tags"], json!(["A", "B"]));
+        assert_eq!(result[0]["code"], "ABC");
+        assert_eq!(result[1]["name"], "bob");
+        assert_eq!(result[1]["code"], "XYZ");
+
+        assert!(parse_mapping("name=reverse").is_err());
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let data = json!([
+            {"price": 10, "score": 1.5},
+            {"price": 5, "score": 2.5},
+            {"price": "n/a"}
+        ]);
+
+        let sum = aggregate(&data, &[(AggregateOp::Sum, "price".to_string())]).unwrap();
+        assert_eq!(sum, json!(15));
+
+        let large = json!([{"p": i64::MAX}, {"p": 1}]);
+        let sum = aggregate(&large, &[(AggregateOp::Sum, "p".to_string())]).unwrap();
+        assert_eq!(sum, json!(9223372036854775808.0));
+
+        let multi = aggregate(
+            &data,
+            &[
+                (AggregateOp::Avg, "score".to_string()),
+                (AggregateOp::Min, "price".to_string()),
+                (AggregateOp::Max, "price".to_string()),
+            ],
+        )
+        .unwrap();
+        assert_eq!(
+            multi,
+            json!({"avg": {"score": 2.0}, "min": {"price": 5}, "max": {"price": 10}})
+        );
+    }
+
     #[test]
     fn test_count() {
         let data = json!([1, 2, 3, 4, 5]);
         assert_eq!(count(&data), json!(5));
     }
+
+    #[test]
+    fn test_add_fields() {
+        let data = json!([
+            {"first": "Ann", "last": "Lee", "price": 4, "qty": 2},
+            {"first": "Bo", "price": 1.5, "qty": 2},
+            "not an object"
+        ]);
+        let fields = [
+            parse_computed_field("full_name = first + \" \" + last").unwrap(),
+            parse_computed_field("order.total=price * qty").unwrap(),
+            parse_computed_field("order.double = order.total * 2").unwrap(),
+        ];
+        assert_eq!(
+            add_fields(&data, &fields).unwrap(),
+            json!([
+                {"first": "Ann", "last": "Lee", "price": 4, "qty": 2,
+                 "full_name": "Ann Lee", "order": {"total": 8, "double": 16}},
+                {"first": "Bo", "price": 1.5, "qty": 2,
+                 "full_name": "Bo ", "order": {"total": 3.0, "double": 6.0}},
+                "not an object"
+            ])
+        );
+
+        assert!(parse_computed_field("= 1").is_err());
+        assert!(parse_computed_field("total").is_err());
+        let nested = [parse_computed_field("first.x = 1").unwrap()];
+        assert!(add_fields(&data, &nested).is_err());
+        assert!(add_fields(&json!(1), &fields).is_err());
+    }
+
+    #[test]
+    fn test_set_values() {
+        let data = json!({
+            "server": {"host": "localhost"},
+            "users": [{"name": "a", "active": false}, {"name": "b", "active": false}]
+        });
+        let assignments = [
+            parse_assignment("$.server.port=8080").unwrap(),
+            parse_assignment("$.server.host = example.com").unwrap(),
+            parse_assignment("$.users[?(@.name == 'b')].active=true").unwrap(),
+            parse_assignment("/users/2={\"name\": \"c\"}").unwrap(),
+            parse_assignment("$['tls']['cert']=\"a=b\"").unwrap(),
+        ];
+        assert_eq!(
+            set_values(&data, &assignments).unwrap(),
+            json!({
+                "server": {"host": "example.com", "port": 8080},
+                "users": [
+                    {"name": "a", "active": false},
+                    {"name": "b", "active": true},
+                    {"name": "c"}
+                ],
+                "tls": {"cert": "a=b"}
+            })
+        );
+
+        let all = [parse_assignment("$.users[*].active=null").unwrap()];
+        let result = set_values(&data, &all).unwrap();
+        assert_eq!(result["users"][1], json!({"name": "b", "active": null}));
+
+        assert!(parse_assignment("$.a").is_err());
+        assert!(parse_assignment("=1").is_err());
+        let unmatched = [parse_assignment("$..missing=1").unwrap()];
+        assert!(set_values(&data, &unmatched).is_err());
+        let scalar = [parse_assignment("/server/host/x=1").unwrap()];
+        assert!(set_values(&data, &scalar).is_err());
+    }
+
+    #[test]
+    fn test_slice() {
+        let data = json!([0, 1, 2, 3, 4, 5]);
+        let sliced = |expr: &str| slice(&data, &Slice::parse(expr).unwrap()).unwrap();
+        assert_eq!(sliced("-2:"), json!([4, 5]));
+        assert_eq!(sliced("1:4"), json!([1, 2, 3]));
+        assert_eq!(sliced(":-4"), json!([0, 1]));
+        assert_eq!(sliced("::2"), json!([0, 2, 4]));
+        assert_eq!(sliced("::-1"), json!([5, 4, 3, 2, 1, 0]));
+        assert_eq!(sliced("4:1:-2"), json!([4, 2]));
+        assert_eq!(sliced("-100:100"), data);
+        assert_eq!(sliced("5:2"), json!([]));
+
+        // Steps and bounds at the ends of i64 don't overflow
+        assert_eq!(sliced("1::9223372036854775807"), json!([1]));
+        assert_eq!(sliced("::-9223372036854775808"), json!([5]));
+        assert_eq!(sliced("-9223372036854775808:9223372036854775807"), data);
+        assert_eq!(sliced("9223372036854775807::-4"), json!([5, 1]));
+
+        assert!(Slice::parse("1").is_err());
+        assert!(Slice::parse("::0").is_err());
+        assert!(Slice::parse("a:").is_err());
+        assert!(slice(&json!({}), &Slice::parse(":").unwrap()).is_err());
+    }
 }
@@ -0,0 +1,486 @@
+//! JSON Schema `$ref` resolution: bundling and dereferencing
+//!
+//! Bundling copies every externally referenced document into the root schema's
+//! `$defs` (or `definitions` for draft-07 and earlier) and rewrites the `$ref`s
+//! to point there. Dereferencing replaces every `$ref` with its target.
+
+use anyhow::{bail, Context, Result};
+use serde_json::{Map, Value as JsonValue};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use crate::core::remote;
+
+/// Loads and caches schema documents by location (canonical path or URL)
+struct Resolver {
+    offline: bool,
+    documents: HashMap<String, JsonValue>,
+}
+
+impl Resolver {
+    fn new(offline: bool) -> Self {
+        Self {
+            offline,
+            documents: HashMap::new(),
+        }
+    }
+
+    fn load(&mut self, location: &str) -> Result<&JsonValue> {
+        if !self.documents.contains_key(location) {
+            let content = if remote::is_url(location) {
+                remote::fetch_cached(location, self.offline)?
+            } else {
+                fs::read_to_string(location)
+                    .with_context(|| format!("Failed to read schema file: {}", location))?
+            };
+            let document = if location.ends_with(".yaml") || location.ends_with(".yml") {
+                serde_yaml::from_str(&content)
+                    .with_context(|| format!("Failed to parse schema as YAML: {}", location))?
+            } else {
+                serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse schema as JSON: {}", location))?
+            };
+            self.documents.insert(location.to_string(), document);
+        }
+        Ok(&self.documents[location])
+    }
+
+    /// Value at a `#` fragment (JSON Pointer) of a document
+    fn target(&mut self, location: &str, fragment: &str) -> Result<JsonValue> {
+        let document = self.load(location)?;
+        let pointer = decode_fragment(fragment)?;
+        document
+            .pointer(&pointer)
+            .cloned()
+            .with_context(|| format!("$ref target '{}#{}' does not exist", location, fragment))
+    }
+}
+
+/// Canonical location of a root schema file or URL
+pub fn root_location(source: &str) -> Result<String> {
+    if remote::is_url(source) {
+        return Ok(source.to_string());
+    }
+    let path = fs::canonicalize(source)
+        .with_context(|| format!("Failed to read schema file: {}", source))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Inline all external `$ref`s into a single self-contained schema
+pub fn bundle(source: &str, offline: bool) -> Result<JsonValue> {
+    let root = root_location(source)?;
+    let mut resolver = Resolver::new(offline);
+    let mut schema = resolver.load(&root)?.clone();
+    let defs_key = definitions_key(&schema);
+
+    let mut bundler = Bundler {
+        root: root.clone(),
+        defs_key,
+        names: HashMap::new(),
+        taken: schema
+            .get(defs_key)
+            .and_then(JsonValue::as_object)
+            .map(|defs| defs.keys().cloned().collect())
+            .unwrap_or_default(),
+        pending: Vec::new(),
+    };
+    bundler.rewrite(&mut schema, &root, None)?;
+
+    let mut bundled = BTreeMap::new();
+    while let Some((location, name)) = bundler.pending.pop() {
+        let mut document = resolver.load(&location)?.clone();
+        if let Some(obj) = document.as_object_mut() {
+            obj.shift_remove("$id");
+            obj.shift_remove("$schema");
+        }
+        let prefix = format!("#/{}/{}", defs_key, escape_pointer(&name));
+        bundler.rewrite(&mut document, &location, Some(&prefix))?;
+        bundled.insert(name, document);
+    }
+
+    if !bundled.is_empty() {
+        let obj = schema
+            .as_object_mut()
+            .context("Root schema must be an object to bundle external $refs")?;
+        let defs = obj
+            .entry(defs_key)
+            .or_insert_with(|| JsonValue::Object(Map::new()))
+            .as_object_mut()
+            .with_context(|| format!("'{}' in the root schema must be an object", defs_key))?;
+        defs.extend(bundled);
+    }
+    Ok(schema)
+}
+
+/// Replace every `$ref` (internal and external) with the schema it points to
+pub fn dereference(source: &str, offline: bool) -> Result<JsonValue> {
+    let root = root_location(source)?;
+    let mut resolver = Resolver::new(offline);
+    let schema = resolver.load(&root)?.clone();
+    let mut stack = Vec::new();
+    let mut result = dereference_value(&schema, &root, &mut resolver, &mut stack)?;
+    if let Some(obj) = result.as_object_mut() {
+        obj.shift_remove("$defs");
+        obj.shift_remove("definitions");
+    }
+    Ok(result)
+}
+
+fn dereference_value(
+    value: &JsonValue,
+    base: &str,
+    resolver: &mut Resolver,
+    stack: &mut Vec<String>,
+) -> Result<JsonValue> {
+    match value {
+        JsonValue::Object(obj) => {
+            if let Some(JsonValue::String(reference)) = obj.get("$ref") {
+                let (location, fragment) = resolve_reference(base, reference)?;
+                let key = format!("{}#{}", location, fragment);
+                if stack.contains(&key) {
+                    bail!(
+                        "Circular $ref '{}' cannot be dereferenced (use bundle instead)",
+                        reference
+                    );
+                }
+                let target = resolver.target(&location, &fragment)?;
+                stack.push(key);
+                let mut resolved = dereference_value(&target, &location, resolver, stack)?;
+                stack.pop();
+
+                // Keywords next to $ref still apply alongside the target
+                let siblings: Vec<_> = obj.iter().filter(|(k, _)| *k != "$ref").collect();
+                if !siblings.is_empty() {
+                    if let JsonValue::Object(resolved_obj) = &mut resolved {
+                        for (k, v) in siblings {
+                            let v = dereference_value(v, base, resolver, stack)?;
+                            resolved_obj.insert(k.clone(), v);
+                        }
+                    }
+                }
+                return Ok(resolved);
+            }
+
+            let mut out = Map::new();
+            for (k, v) in obj {
+                out.insert(k.clone(), dereference_value(v, base, resolver, stack)?);
+            }
+            Ok(JsonValue::Object(out))
+        }
+        JsonValue::Array(items) => items
+            .iter()
+            .map(|item| dereference_value(item, base, resolver, stack))
+            .collect::<Result<Vec<_>>>()
+            .map(JsonValue::Array),
+        other => Ok(other.clone()),
+    }
+}
+
+struct Bundler {
+    root: String,
+    defs_key: &'static str,
+    /// Definition name assigned to each external document
+    names: HashMap<String, String>,
+    taken: Vec<String>,
+    pending: Vec<(String, String)>,
+}
+
+impl Bundler {
+    /// Rewrite `$ref`s in a document located at `base`; `prefix` is where the
+    /// document will live in the bundle (`None` for the root)
+    fn rewrite(&mut self, value: &mut JsonValue, base: &str, prefix: Option<&str>) -> Result<()> {
+        match value {
+            JsonValue::Object(obj) => {
+                if let Some(JsonValue::String(reference)) = obj.get_mut("$ref") {
+                    let rewritten = match (reference.strip_prefix('#'), prefix) {
+                        (Some(_), None) => None,
+                        (Some(fragment), Some(prefix)) => Some(format!("{}{}", prefix, fragment)),
+                        (None, _) => {
+                            let (location, fragment) = resolve_reference(base, reference)?;
+                            if location == self.root {
+                                Some(format!("#{}", fragment))
+                            } else {
+                                let name = self.name_for(&location);
+                                Some(format!(
+                                    "#/{}/{}{}",
+                                    self.defs_key,
+                                    escape_pointer(&name),
+                                    fragment
+                                ))
+                            }
+                        }
+                    };
+                    if let Some(rewritten) = rewritten {
+                        *reference = rewritten;
+                    }
+                }
+                for (key, child) in obj.iter_mut() {
+                    if key != "$ref" {
+                        self.rewrite(child, base, prefix)?;
+                    }
+                }
+            }
+            JsonValue::Array(items) => {
+                for item in items {
+                    self.rewrite(item, base, prefix)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn name_for(&mut self, location: &str) -> String {
+        if let Some(name) = self.names.get(location) {
+            return name.clone();
+        }
+        let stem = definition_stem(location);
+        let mut name = stem.clone();
+        let mut n = 2;
+        while self.taken.contains(&name) {
+            name = format!("{}_{}", stem, n);
+            n += 1;
+        }
+        self.taken.push(name.clone());
+        self.names.insert(location.to_string(), name.clone());
+        self.pending.push((location.to_string(), name.clone()));
+        name
+    }
+}
+
+/// `definitions` for draft-07 and earlier schemas, `$defs` otherwise
+fn definitions_key(schema: &JsonValue) -> &'static str {
+    let draft = schema
+        .get("$schema")
+        .and_then(JsonValue::as_str)
+        .unwrap_or_default();
+    let legacy = ["draft-04", "draft-06", "draft-07"]
+        .iter()
+        .any(|d| draft.contains(d));
+    if legacy || (schema.get("definitions").is_some() && schema.get("$defs").is_none()) {
+        "definitions"
+    } else {
+        "$defs"
+    }
+}
+
+/// Definition name derived from a document's file name, e.g. `address` for `address.schema.json`
+fn definition_stem(location: &str) -> String {
+    let file = location.rsplit(['/', '\\']).next().unwrap_or(location);
+    let mut stem = file;
+    for suffix in [".json", ".yaml", ".yml", ".schema"] {
+        stem = stem.strip_suffix(suffix).unwrap_or(stem);
+    }
+    let name: String = stem
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.is_empty() {
+        "schema".to_string()
+    } else {
+        name
+    }
+}
+
+/// Split a `$ref` into an absolute document location and its fragment
+fn resolve_reference(base: &str, reference: &str) -> Result<(String, String)> {
+    let (document, fragment) = reference.split_once('#').unwrap_or((reference, ""));
+    if document.is_empty() {
+        return Ok((base.to_string(), fragment.to_string()));
+    }
+
+    let location = if remote::is_url(document) {
+        document.to_string()
+    } else if remote::is_url(base) {
+        join_url(base, document)
+    } else {
+        let dir = Path::new(base).parent().unwrap_or(Path::new("."));
+        let path = dir.join(document);
+        fs::canonicalize(&path)
+            .with_context(|| {
+                format!(
+                    "Failed to resolve $ref '{}' from {}",
+                    reference,
+                    Path::new(base).display()
+                )
+            })?
+            .to_string_lossy()
+            .into_owned()
+    };
+    Ok((location, fragment.to_string()))
+}
+
+/// Resolve a relative reference against a base URL
+fn join_url(base: &str, reference: &str) -> String {
+    let scheme_end = base.find("://").map_or(0, |i| i + 3);
+    let authority_end = base[scheme_end..]
+        .find('/')
+        .map_or(base.len(), |i| scheme_end + i);
+    let origin = &base[..authority_end];
+
+    let mut segments: Vec<&str> = if reference.starts_with('/') {
+        Vec::new()
+    } else {
+        let path = &base[authority_end..];
+        let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        dir.split('/').filter(|s| !s.is_empty()).collect()
+    };
+    for segment in reference.split('/').filter(|s| !s.is_empty()) {
+        match segment {
+            "." => {}
+            ".." => {
+                segments.pop();
+            }
+            _ => segments.push(segment),
+        }
+    }
+    format!("{}/{}", origin, segments.join("/"))
+}
+
+/// Percent-decode a `$ref` fragment into a JSON Pointer
+fn decode_fragment(fragment: &str) -> Result<String> {
+    if !fragment.is_empty() && !fragment.starts_with('/') {
+        bail!(
+            "Only JSON Pointer fragments are supported in $ref (found '#{}')",
+            fragment
+        );
+    }
+    let bytes = fragment.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%' && i + 2 < bytes.len())
+            .then(|| std::str::from_utf8(&bytes[i + 1..i + 3]).ok())
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(b) => {
+                decoded.push(b);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).context("Invalid percent-encoding in $ref fragment")
+}
+
+fn escape_pointer(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_schemas(dir: &Path) -> String {
+        fs::create_dir_all(dir.join("common")).unwrap();
+        fs::write(
+            dir.join("root.schema.json"),
+            json!({
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "type": "object",
+                "properties": {
+                    "home": {"$ref": "common/address.schema.json"},
+                    "work": {"$ref": "common/address.schema.json#/properties/street"},
+                    "tags": {"$ref": "#/$defs/tags"}
+                },
+                "$defs": {"tags": {"type": "array", "items": {"type": "string"}}}
+            })
+            .to_string(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("common/address.schema.json"),
+            json!({
+                "$id": "https://example.com/address",
+                "type": "object",
+                "properties": {
+                    "street": {"$ref": "#/$defs/line"},
+                    "country": {"$ref": "../country.json"}
+                },
+                "$defs": {"line": {"type": "string"}}
+            })
+            .to_string(),
+        )
+        .unwrap();
+        fs::write(
+            dir.join("country.json"),
+            json!({"enum": ["JP", "US"]}).to_string(),
+        )
+        .unwrap();
+        dir.join("root.schema.json").to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn test_bundle_and_dereference() {
+        let dir = std::env::temp_dir().join(format!("dtx-refs-{}", std::process::id()));
+        let root = write_schemas(&dir);
+
+        let bundled = bundle(&root, true).unwrap();
+        assert_eq!(bundled["properties"]["home"]["$ref"], "#/$defs/address");
+        assert_eq!(
+            bundled["properties"]["work"]["$ref"],
+            "#/$defs/address/properties/street"
+        );
+        assert_eq!(bundled["properties"]["tags"]["$ref"], "#/$defs/tags");
+        let address = &bundled["$defs"]["address"];
+        assert!(address.get("$id").is_none());
+        assert_eq!(
+            address["properties"]["street"]["$ref"],
+            "#/$defs/address/$defs/line"
+        );
+        assert_eq!(address["properties"]["country"]["$ref"], "#/$defs/country");
+        assert_eq!(bundled["$defs"]["country"], json!({"enum": ["JP", "US"]}));
+
+        let dereferenced = dereference(&root, true).unwrap();
+        assert_eq!(
+            dereferenced["properties"]["home"]["properties"]["country"],
+            json!({"enum": ["JP", "US"]})
+        );
+        assert_eq!(
+            dereferenced["properties"]["work"],
+            json!({"type": "string"})
+        );
+        assert!(dereferenced.get("$defs").is_none());
+
+        fs::write(
+            dir.join("tree.json"),
+            json!({"properties": {"children": {"items": {"$ref": "#"}}}}).to_string(),
+        )
+        .unwrap();
+        let tree = dir.join("tree.json").to_string_lossy().into_owned();
+        assert!(bundle(&tree, true).is_ok());
+        assert!(dereference(&tree, true)
+            .unwrap_err()
+            .to_string()
+            .contains("Circular"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_join_url() {
+        assert_eq!(
+            join_url(
+                "https://example.com/schemas/v1/root.json",
+                "../common/a.json"
+            ),
+            "https://example.com/schemas/common/a.json"
+        );
+        assert_eq!(
+            join_url("https://example.com/schemas/root.json", "/b.json"),
+            "https://example.com/b.json"
+        );
+    }
+}
@@ -0,0 +1,147 @@
+//! Remote resource fetching with an on-disk cache
+//!
+//! Downloaded files are stored under `$DTX_CACHE_DIR`, `$XDG_CACHE_HOME/dtx`
+//! or `~/.cache/dtx` and reused for a day before being refreshed.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::core::batch::fnv1a;
+
+/// How long a cached download is used before it is fetched again
+const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Limits on connecting to a server and on waiting for each read, so an
+/// unresponsive host fails the download instead of hanging
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Longest readable part of a cache file name; the hash keeps truncated names apart
+const MAX_NAME_LEN: usize = 100;
+
+/// Whether a schema/file reference is an HTTP(S) URL
+pub fn is_url(s: &str) -> bool {
+    s.starts_with("https://") || s.starts_with("http://")
+}
+
+/// Root directory for cached downloads
+pub fn cache_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("DTX_CACHE_DIR") {
+        return Some(PathBuf::from(dir));
+    }
+    if let Some(dir) = std::env::var_os("XDG_CACHE_HOME") {
+        return Some(PathBuf::from(dir).join("dtx"));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache").join("dtx"))
+}
+
+/// Cache file name for a URL, e.g.
+/// `json.schemastore.org_github-workflow.json-<hash>`
+///
+/// The readable part flattens the scheme and punctuation, so a hash of the
+/// full URL keeps URLs that flatten alike from sharing a file.
+fn cache_file_name(url: &str) -> String {
+    let stripped = url
+        .trim_start_matches("https://")
+        .trim_start_matches("http://");
+    let readable: String = stripped
+        .chars()
+        .take(MAX_NAME_LEN)
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{}-{:016x}", readable, fnv1a(url.as_bytes()))
+}
+
+/// Fetch a URL as text, using the on-disk cache
+///
+/// In offline mode only the cache is consulted. When a download fails, a
+/// stale cached copy is used if one exists.
+pub fn fetch_cached(url: &str, offline: bool) -> Result<String> {
+    let cache_path = cache_dir().map(|dir| dir.join("schemas").join(cache_file_name(url)));
+    let cached = cache_path.as_ref().filter(|p| p.is_file());
+
+    if offline {
+        let path = cached.with_context(|| format!("{} is not cached (offline mode)", url))?;
+        tracing::debug!(url, path = %path.display(), "using cached download");
+        return fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cache file: {}", path.display()));
+    }
+
+    if let Some(path) = cached {
+        let fresh = fs::metadata(path)
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+            .is_some_and(|age| age < CACHE_TTL);
+        if fresh {
+            tracing::debug!(url, path = %path.display(), "using cached download");
+            return fs::read_to_string(path)
+                .with_context(|| format!("Failed to read cache file: {}", path.display()));
+        }
+    }
+
+    tracing::info!(url, "downloading");
+    let agent = ureq::AgentBuilder::new()
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(READ_TIMEOUT)
+        .build();
+    let body = match agent.get(url).call() {
+        Ok(response) => response
+            .into_string()
+            .with_context(|| format!("Failed to read response from {}", url))?,
+        Err(err) => {
+            if let Some(path) = cached {
+                tracing::warn!(url, error = %err, "download failed; using stale cache");
+                return fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read cache file: {}", path.display()));
+            }
+            return Err(err).with_context(|| format!("Failed to download {}", url));
+        }
+    };
+
+    if let Some(path) = cache_path {
+        // Caching is best effort; a read-only cache directory shouldn't fail the command
+        let written = path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&path, &body));
+        if let Err(err) = written {
+            tracing::warn!(path = %path.display(), error = %err, "failed to write cache");
+        }
+    }
+
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_file_name() {
+        let name = cache_file_name("https://json.schemastore.org/github-workflow.json");
+        assert!(name.starts_with("json.schemastore.org_github-workflow.json-"));
+        assert!(cache_file_name("http://example.com/a/b.json?v=1")
+            .starts_with("example.com_a_b.json_v_1-"));
+
+        assert_ne!(
+            cache_file_name("https://example.com/a.json"),
+            cache_file_name("http://example.com/a.json")
+        );
+        assert_ne!(
+            cache_file_name("https://example.com/a/b.json"),
+            cache_file_name("https://example.com/a_b.json")
+        );
+
+        let long = format!("https://example.com/{}", "x".repeat(500));
+        assert!(cache_file_name(&long).len() < 150);
+    }
+}
@@ -1,6 +1,8 @@
 //! JSON Schema generation from data
 
+use anyhow::Result;
 use serde_json::{json, Map, Value as JsonValue};
+use std::collections::{BTreeMap, BTreeSet};
 
 /// Generate JSON Schema from a JSON value
 pub fn generate_schema(value: &JsonValue) -> JsonValue {
@@ -113,7 +115,7 @@ fn infer_array_schema(arr: &[JsonValue]) -> JsonValue {
     let item_schemas: Vec<JsonValue> = arr.iter().map(infer_type).collect();
 
     // Try to merge schemas
-    let merged = merge_schemas(&item_schemas);
+    let merged = merge_schemas(&item_schemas, RequiredMode::Intersection);
 
     let mut schema = Map::new();
     schema.insert("type".to_string(), json!("array"));
@@ -149,59 +151,125 @@ fn infer_object_schema(obj: &Map<String, JsonValue>) -> JsonValue {
     JsonValue::Object(schema)
 }
 
-fn merge_schemas(schemas: &[JsonValue]) -> JsonValue {
+/// How `required` lists are combined when merging object schemas
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RequiredMode {
+    /// Required only when every schema requires it, so data valid under any input stays valid
+    Intersection,
+    /// Required when any schema requires it
+    Union,
+}
+
+/// Merge schemas into one that accepts values described by any of them
+///
+/// Object properties are combined and merged recursively, differing types are
+/// unioned, and `required` lists are combined according to `required`.
+pub fn merge_schemas(schemas: &[JsonValue], required: RequiredMode) -> JsonValue {
+    // `false` matches nothing, so it contributes nothing to the union
+    let schemas: Vec<&JsonValue> = schemas
+        .iter()
+        .filter(|s| **s != JsonValue::Bool(false))
+        .collect();
     if schemas.is_empty() {
         return json!({});
     }
-
+    if schemas.iter().any(|s| **s == JsonValue::Bool(true)) {
+        return json!(true);
+    }
     if schemas.len() == 1 {
         return schemas[0].clone();
     }
 
-    // Check if all schemas are the same type
-    let types: Vec<&str> = schemas
+    // Group by type; integers are absorbed by numbers when both appear
+    let has_number = schemas.iter().any(|s| type_name(s) == Some("number"));
+    let mut groups: Vec<(Option<&str>, Vec<&JsonValue>)> = Vec::new();
+    for schema in &schemas {
+        let key = match type_name(schema) {
+            Some("integer") if has_number => Some("number"),
+            other => other,
+        };
+        match groups.iter_mut().find(|(k, _)| *k == key && key.is_some()) {
+            Some((_, members)) => members.push(schema),
+            None => groups.push((key, vec![schema])),
+        }
+    }
+
+    let mut merged: Vec<JsonValue> = Vec::new();
+    for (key, members) in groups {
+        let schema = match key {
+            Some("object") => merge_object_schemas(&members, required),
+            Some("array") => merge_array_schemas(&members, required),
+            Some(t) => {
+                let mut result = common_keywords(&members, &["type"]);
+                result.insert("type".to_string(), json!(t));
+                JsonValue::Object(result)
+            }
+            None => (*members[0]).clone(),
+        };
+        if !merged.contains(&schema) {
+            merged.push(schema);
+        }
+    }
+
+    if merged.len() == 1 {
+        return merged.remove(0);
+    }
+
+    // Type-only schemas collapse into a single list of types
+    let type_only: Option<Vec<JsonValue>> = merged
         .iter()
-        .filter_map(|s| s.get("type").and_then(|t| t.as_str()))
+        .map(|s| match s.as_object() {
+            Some(obj) if obj.len() == 1 => obj.get("type").filter(|t| t.is_string()).cloned(),
+            _ => None,
+        })
         .collect();
+    if let Some(mut types) = type_only {
+        types.sort_by(|a, b| a.as_str().cmp(&b.as_str()));
+        return json!({"type": types});
+    }
 
-    let all_same_type = types.windows(2).all(|w| w[0] == w[1]);
+    json!({"anyOf": merged})
+}
 
-    if all_same_type && !types.is_empty() {
-        let base_type = types[0];
+/// Single `type` keyword of a schema
+fn type_name(schema: &JsonValue) -> Option<&str> {
+    schema.get("type").and_then(|t| t.as_str())
+}
 
-        if base_type == "object" {
-            // Merge object schemas
-            return merge_object_schemas(schemas);
-        } else {
-            // For primitives, just return the first schema
-            return schemas[0].clone();
+/// Keywords with the same value in every schema, excluding `skip`
+fn common_keywords(schemas: &[&JsonValue], skip: &[&str]) -> Map<String, JsonValue> {
+    let mut common = Map::new();
+    if let Some(first) = schemas[0].as_object() {
+        for (key, value) in first {
+            if !skip.contains(&key.as_str())
+                && schemas[1..].iter().all(|s| s.get(key) == Some(value))
+            {
+                common.insert(key.clone(), value.clone());
+            }
         }
     }
+    common
+}
 
-    // Different types - use anyOf
-    let unique_schemas: Vec<JsonValue> = schemas
-        .iter()
-        .cloned()
-        .collect::<std::collections::HashSet<_>>()
-        .into_iter()
-        .collect();
+fn merge_array_schemas(schemas: &[&JsonValue], required: RequiredMode) -> JsonValue {
+    let mut result = common_keywords(schemas, &["items"]);
 
-    if unique_schemas.len() == 1 {
-        return unique_schemas[0].clone();
+    // An array schema without `items` accepts any items
+    let items: Option<Vec<JsonValue>> = schemas.iter().map(|s| s.get("items").cloned()).collect();
+    if let Some(items) = items {
+        result.insert("items".to_string(), merge_schemas(&items, required));
     }
 
-    json!({"anyOf": unique_schemas})
+    JsonValue::Object(result)
 }
 
-fn merge_object_schemas(schemas: &[JsonValue]) -> JsonValue {
-    let mut all_properties: std::collections::HashMap<String, Vec<JsonValue>> =
-        std::collections::HashMap::new();
-    let mut all_keys: std::collections::HashSet<String> = std::collections::HashSet::new();
+fn merge_object_schemas(schemas: &[&JsonValue], required: RequiredMode) -> JsonValue {
+    let mut result = common_keywords(schemas, &["properties", "required"]);
 
+    let mut all_properties: BTreeMap<String, Vec<JsonValue>> = BTreeMap::new();
     for schema in schemas {
         if let Some(props) = schema.get("properties").and_then(|p| p.as_object()) {
             for (key, value) in props {
-                all_keys.insert(key.clone());
                 all_properties
                     .entry(key.clone())
                     .or_default()
@@ -210,24 +278,10 @@ fn merge_object_schemas(schemas: &[JsonValue]) -> JsonValue {
         }
     }
 
-    let mut merged_properties = Map::new();
-    let mut required = Vec::new();
-
-    for key in &all_keys {
-        if let Some(prop_schemas) = all_properties.get(key) {
-            let merged = merge_schemas(prop_schemas);
-            merged_properties.insert(key.clone(), merged);
-
-            // Only required if present in all schemas
-            if prop_schemas.len() == schemas.len() {
-                required.push(json!(key));
-            }
-        }
-    }
-
-    let mut result = Map::new();
-    result.insert("type".to_string(), json!("object"));
-
+    let merged_properties: Map<String, JsonValue> = all_properties
+        .iter()
+        .map(|(key, prop_schemas)| (key.clone(), merge_schemas(prop_schemas, required)))
+        .collect();
     if !merged_properties.is_empty() {
         result.insert(
             "properties".to_string(),
@@ -235,8 +289,27 @@ fn merge_object_schemas(schemas: &[JsonValue]) -> JsonValue {
         );
     }
 
-    if !required.is_empty() {
-        result.insert("required".to_string(), JsonValue::Array(required));
+    let required_lists: Vec<Vec<&str>> = schemas
+        .iter()
+        .map(|s| {
+            s.get("required")
+                .and_then(|r| r.as_array())
+                .map(|r| r.iter().filter_map(|v| v.as_str()).collect())
+                .unwrap_or_default()
+        })
+        .collect();
+    let mut merged_required: Vec<&str> = Vec::new();
+    for key in required_lists.iter().flatten() {
+        let keep = match required {
+            RequiredMode::Intersection => required_lists.iter().all(|list| list.contains(key)),
+            RequiredMode::Union => true,
+        };
+        if keep && !merged_required.contains(key) {
+            merged_required.push(key);
+        }
+    }
+    if !merged_required.is_empty() {
+        result.insert("required".to_string(), json!(merged_required));
     }
 
     JsonValue::Object(result)
@@ -275,6 +348,19 @@ fn json_schema_to_ts_type(schema: &JsonValue) -> String {
         return types.join(" | ");
     }
 
+    if let Some(types) = schema.get("type").and_then(|t| t.as_array()) {
+        let mut ts_types: Vec<String> = Vec::new();
+        for t in types {
+            let mut single = schema.clone();
+            single["type"] = t.clone();
+            let ts_type = json_schema_to_ts_type(&single);
+            if !ts_types.contains(&ts_type) {
+                ts_types.push(ts_type);
+            }
+        }
+        return ts_types.join(" | ");
+    }
+
     let type_str = schema.get("type").and_then(|t| t.as_str()).unwrap_or("any");
 
     match type_str {
@@ -304,10 +390,117 @@ fn json_schema_to_ts_type(schema: &JsonValue) -> String {
     }
 }
 
+/// How [`sample_records`] chooses records
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Sampling {
+    /// The first records of the stream
+    First,
+    /// A uniform random sample of the whole stream
+    Random { seed: u64 },
+}
+
+/// Take up to `size` records from a stream, returning them with the number of
+/// records read; `First` stops reading once the sample is full
+pub fn sample_records<I>(
+    records: I,
+    size: usize,
+    sampling: Sampling,
+) -> Result<(Vec<JsonValue>, usize)>
+where
+    I: IntoIterator<Item = Result<JsonValue>>,
+{
+    let mut sample = Vec::with_capacity(size.min(65_536));
+    let mut seen = 0;
+    let mut state = match sampling {
+        Sampling::Random { seed } => seed | 1,
+        Sampling::First => 0,
+    };
+
+    for record in records {
+        if sampling == Sampling::First && seen == size {
+            break;
+        }
+        let record = record?;
+        seen += 1;
+        if sample.len() < size {
+            sample.push(record);
+            continue;
+        }
+        // Reservoir sampling: keep the new record with probability size / seen
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let slot = (state % seen as u64) as usize;
+        if slot < size {
+            sample[slot] = record;
+        }
+    }
+
+    Ok((sample, seen))
+}
+
+/// Number of records in which each field path occurs (`a.b`, `items[].id`)
+pub fn field_counts(records: &[JsonValue]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for record in records {
+        let mut paths = BTreeSet::new();
+        collect_field_paths(record, "", &mut paths);
+        for path in paths {
+            *counts.entry(path).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+fn collect_field_paths(value: &JsonValue, prefix: &str, paths: &mut BTreeSet<String>) {
+    match value {
+        JsonValue::Object(obj) => {
+            for (key, val) in obj {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                collect_field_paths(val, &path, paths);
+                paths.insert(path);
+            }
+        }
+        JsonValue::Array(items) if !prefix.is_empty() => {
+            let path = format!("{}[]", prefix);
+            for item in items {
+                collect_field_paths(item, &path, paths);
+            }
+        }
+        _ => {}
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_sample_records() {
+        let records = (0..100).map(|i| Ok(json!({"id": i})));
+        let (sample, seen) = sample_records(records, 10, Sampling::First).unwrap();
+        assert_eq!((sample.len(), seen), (10, 10));
+        assert_eq!(sample[9], json!({"id": 9}));
+
+        let records = (0..100).map(|i| Ok(json!({"id": i})));
+        let (sample, seen) = sample_records(records, 10, Sampling::Random { seed: 7 }).unwrap();
+        assert_eq!((sample.len(), seen), (10, 100));
+        assert!(sample.iter().any(|r| r["id"].as_u64().unwrap() >= 10));
+
+        let records = vec![
+            json!({"id": 1, "user": {"name": "a"}, "tags": [{"k": 1}]}),
+            json!({"id": 2, "tags": []}),
+        ];
+        let counts = field_counts(&records);
+        assert_eq!(counts["id"], 2);
+        assert_eq!(counts["user.name"], 1);
+        assert_eq!(counts["tags[].k"], 1);
+    }
+
     #[test]
     fn test_generate_schema_primitive() {
         let value = json!(42);
@@ -330,6 +523,47 @@ mod tests {
         assert_eq!(schema.get("type").unwrap(), "array");
     }
 
+    #[test]
+    fn test_merge_schemas() {
+        let a = json!({
+            "type": "object",
+            "properties": {"id": {"type": "integer"}, "tags": {"type": "array", "items": {"type": "string"}}},
+            "required": ["id", "tags"]
+        });
+        let b = json!({
+            "type": "object",
+            "properties": {"id": {"type": "string", "format": "uuid"}, "name": {"type": "string"}},
+            "required": ["id", "name"]
+        });
+
+        let merged = merge_schemas(&[a.clone(), b.clone()], RequiredMode::Intersection);
+        assert_eq!(
+            merged,
+            json!({
+                "type": "object",
+                "properties": {
+                    "id": {"anyOf": [{"type": "integer"}, {"type": "string", "format": "uuid"}]},
+                    "name": {"type": "string"},
+                    "tags": {"type": "array", "items": {"type": "string"}}
+                },
+                "required": ["id"]
+            })
+        );
+
+        let merged = merge_schemas(&[a, b], RequiredMode::Union);
+        assert_eq!(merged["required"], json!(["id", "tags", "name"]));
+
+        let merged = merge_schemas(
+            &[
+                json!({"type": "integer"}),
+                json!({"type": "null"}),
+                json!({"type": "number"}),
+            ],
+            RequiredMode::Intersection,
+        );
+        assert_eq!(merged, json!({"type": ["null", "number"]}));
+    }
+
     #[test]
     fn test_string_format_detection() {
         let email = infer_string_format("test@example.com");
@@ -0,0 +1,169 @@
+//! Tables from arrays of JSON objects
+
+use anyhow::{bail, Result};
+use serde_json::Value as JsonValue;
+
+use crate::formats::csv::CsvData;
+use crate::utils::width;
+
+/// Build table rows from an array of objects
+///
+/// Nested objects are flattened one level into `parent.child` columns; deeper
+/// values are shown as compact JSON. Columns default to every key in order of
+/// first appearance. Cells wider than `max_width` columns are cut with `…`.
+pub fn records_to_table(
+    value: &JsonValue,
+    columns: &[String],
+    max_width: Option<usize>,
+) -> Result<CsvData> {
+    let records = match value {
+        JsonValue::Array(items) => items.as_slice(),
+        JsonValue::Object(_) => std::slice::from_ref(value),
+        _ => bail!("Table input must be an array of objects"),
+    };
+
+    let rows: Vec<Vec<(String, String)>> = records.iter().map(flatten_record).collect();
+
+    let mut all_columns: Vec<String> = Vec::new();
+    for row in &rows {
+        for (column, _) in row {
+            if !all_columns.contains(column) {
+                all_columns.push(column.clone());
+            }
+        }
+    }
+    let headers = if columns.is_empty() {
+        all_columns
+    } else {
+        if let Some(unknown) = columns.iter().find(|c| !all_columns.contains(c)) {
+            bail!(
+                "Unknown column '{}'. Available: {}",
+                unknown,
+                all_columns.join(", ")
+            );
+        }
+        columns.to_vec()
+    };
+
+    let rows = rows
+        .iter()
+        .map(|row| {
+            headers
+                .iter()
+                .map(|header| {
+                    let cell = row
+                        .iter()
+                        .find(|(column, _)| column == header)
+                        .map(|(_, cell)| cell.as_str())
+                        .unwrap_or_default();
+                    truncate(cell, max_width)
+                })
+                .collect()
+        })
+        .collect();
+
+    Ok(CsvData::with_headers(headers, rows))
+}
+
+/// Column/cell pairs of one record, with nested objects flattened one level
+fn flatten_record(record: &JsonValue) -> Vec<(String, String)> {
+    let Some(obj) = record.as_object() else {
+        return vec![("value".to_string(), cell_text(record))];
+    };
+
+    let mut cells = Vec::new();
+    for (key, value) in obj {
+        match value {
+            JsonValue::Object(nested) if !nested.is_empty() => {
+                for (child, value) in nested {
+                    cells.push((format!("{}.{}", key, child), cell_text(value)));
+                }
+            }
+            _ => cells.push((key.clone(), cell_text(value))),
+        }
+    }
+    cells
+}
+
+fn cell_text(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => String::new(),
+        JsonValue::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Cut `cell` to at most `max_width` columns, marking the cut with `…`
+pub fn truncate(cell: &str, max_width: Option<usize>) -> String {
+    match max_width {
+        Some(width) => width::truncate(cell, width, "…"),
+        None => cell.to_string(),
+    }
+}
+
+/// Narrow the widest columns until the rendered table fits in `total_width`
+///
+/// Headers and cells of narrowed columns are cut with `…`; no column is cut
+/// below a few characters, so very narrow terminals still overflow.
+pub fn fit_to_width(data: &mut CsvData, total_width: usize) {
+    const MIN_COLUMN: usize = 4;
+
+    let mut widths: Vec<usize> = Vec::new();
+    for row in data.headers.iter().chain(&data.rows) {
+        if widths.len() < row.len() {
+            widths.resize(row.len(), 0);
+        }
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(width::str_width(cell));
+        }
+    }
+    // `| cell |` borders and padding: three columns per cell plus the last border
+    let table_width = |widths: &[usize]| widths.iter().map(|w| w + 3).sum::<usize>() + 1;
+
+    let natural = widths.clone();
+    while table_width(&widths) > total_width {
+        let Some(widest) = (0..widths.len()).max_by_key(|&i| widths[i]) else {
+            break;
+        };
+        if widths[widest] <= MIN_COLUMN {
+            break;
+        }
+        widths[widest] -= 1;
+    }
+
+    for row in data.headers.iter_mut().chain(data.rows.iter_mut()) {
+        for (i, cell) in row.iter_mut().enumerate() {
+            if widths[i] < natural[i] {
+                *cell = width::truncate(cell, widths[i], "…");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_records_to_table() {
+        let data = json!([
+            {"id": 1, "user": {"name": "Alice", "tags": ["a"]}, "note": "a long note"},
+            {"id": 2, "user": {"name": "Bob"}}
+        ]);
+
+        let table = records_to_table(&data, &[], None).unwrap();
+        assert_eq!(
+            table.headers.unwrap(),
+            vec!["id", "user.name", "user.tags", "note"]
+        );
+        assert_eq!(table.rows[0], vec!["1", "Alice", "[\"a\"]", "a long note"]);
+        assert_eq!(table.rows[1], vec!["2", "Bob", "", ""]);
+
+        let columns = vec!["user.name".to_string(), "note".to_string()];
+        let table = records_to_table(&data, &columns, Some(6)).unwrap();
+        assert_eq!(table.rows[0], vec!["Alice", "a lon…"]);
+
+        assert!(records_to_table(&data, &["missing".to_string()], None).is_err());
+    }
+}
@@ -3,6 +3,9 @@
 use anyhow::{Context, Result};
 use regex::Regex;
 use serde_json::Value as JsonValue;
+use std::fmt;
+
+use crate::utils::diagnostics;
 
 /// Template options
 #[derive(Debug, Clone)]
@@ -13,7 +16,7 @@ pub struct TemplateOptions {
     pub delimiter_end: String,
     /// Fail on missing variables (default: false)
     pub strict: bool,
-    /// Default value for missing variables
+    /// Default value for missing variables without their own fallback
     pub default_value: Option<String>,
 }
 
@@ -28,29 +31,127 @@ impl Default for TemplateOptions {
     }
 }
 
-/// Render a template string with variables
-pub fn render_string(template: &str, vars: &JsonValue, options: &TemplateOptions) -> Result<String> {
+/// Variable missing in strict mode, with the position of its placeholder
+#[derive(Debug, Clone)]
+pub struct MissingVariable {
+    /// Variable path (e.g. `user.name`)
+    pub name: String,
+    /// Placeholder as written (e.g. `{{ user.name }}`)
+    pub placeholder: String,
+    /// Byte offset of the placeholder in the rendered string
+    pub offset: usize,
+    /// Template file, when known
+    pub file: Option<String>,
+    /// 1-based line and column in the template source, once located
+    pub location: Option<(usize, usize)>,
+    /// Source line containing the placeholder
+    pub snippet: Option<String>,
+}
+
+impl MissingVariable {
+    /// Resolve the location within `source`: at `offset` when the rendered string
+    /// is the source itself, otherwise at the first occurrence of the placeholder
+    pub fn locate(mut self, source: &str, file: Option<String>, at_offset: bool) -> Self {
+        let offset = if at_offset {
+            Some(self.offset)
+        } else {
+            source.find(&self.placeholder)
+        };
+        if let Some(offset) = offset.filter(|&o| o <= source.len()) {
+            let (line, column) = line_column(source, offset);
+            self.location = Some((line, column));
+            self.snippet = source.lines().nth(line - 1).map(str::to_string);
+        }
+        self.file = file;
+        self
+    }
+}
+
+impl fmt::Display for MissingVariable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Variable '{}' not found", self.name)?;
+        let Some((line, column)) = self.location else {
+            return Ok(());
+        };
+        match self.file {
+            Some(ref file) => write!(f, " at {}:{}:{}", file, line, column)?,
+            None => write!(f, " at line {}, column {}", line, column)?,
+        }
+        if let Some(ref line_text) = self.snippet {
+            let width = self.placeholder.chars().count();
+            write!(
+                f,
+                "\n{}",
+                diagnostics::snippet(line_text, line, column, width)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for MissingVariable {}
+
+/// 1-based line and column (in characters) of a byte offset
+pub fn line_column(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Placeholder pattern: `{{ name }}`, `{{ name:-fallback }}` or `{{ name | default:"fallback" }}`
+fn placeholder_regex(options: &TemplateOptions) -> Result<Regex> {
     let pattern = format!(
-        "{}\\s*([\\w.\\[\\]]+)\\s*{}",
+        "{}\\s*([\\w.\\[\\]]+)(?:\\s*:-(.*?)|\\s*\\|\\s*default\\s*:\\s*(?:\"([^\"]*)\"|'([^']*)'))?\\s*{}",
         regex::escape(&options.delimiter_start),
         regex::escape(&options.delimiter_end)
     );
-    let re = Regex::new(&pattern).context("Failed to compile template regex")?;
+    Regex::new(&pattern).context("Failed to compile template regex")
+}
+
+/// Per-variable fallback written in a placeholder
+fn placeholder_default<'a>(cap: &regex::Captures<'a>) -> Option<&'a str> {
+    cap.get(2)
+        .or_else(|| cap.get(3))
+        .or_else(|| cap.get(4))
+        .map(|m| m.as_str())
+}
+
+/// Render a template string with variables
+pub fn render_string(
+    template: &str,
+    vars: &JsonValue,
+    options: &TemplateOptions,
+) -> Result<String> {
+    let re = placeholder_regex(options)?;
 
     let mut result = template.to_string();
     let mut replacements: Vec<(String, String)> = Vec::new();
 
     for cap in re.captures_iter(template) {
-        let full_match = cap.get(0).unwrap().as_str();
+        let whole = cap.get(0).unwrap();
+        let full_match = whole.as_str();
         let var_path = cap.get(1).unwrap().as_str();
 
         let value = get_var_value(vars, var_path);
 
-        let replacement = match value {
-            Some(v) => json_value_to_string(v),
-            None => {
+        let default = placeholder_default(&cap);
+        // Like the shell's `${name:-fallback}`, a default also replaces null and ""
+        let value = value.filter(|v| default.is_none() || !(v.is_null() || v.as_str() == Some("")));
+        let replacement = match (value, default) {
+            (Some(v), _) => json_value_to_string(v),
+            (None, Some(fallback)) => fallback.to_string(),
+            (None, None) => {
                 if options.strict {
-                    anyhow::bail!("Variable '{}' not found", var_path);
+                    return Err(MissingVariable {
+                        name: var_path.to_string(),
+                        placeholder: full_match.to_string(),
+                        offset: whole.start(),
+                        file: None,
+                        location: None,
+                        snippet: None,
+                    }
+                    .into());
                 }
                 options
                     .default_value
@@ -139,9 +240,43 @@ fn json_value_to_string(value: &JsonValue) -> String {
 
 /// Parse environment variables into a JSON object
 pub fn env_to_json() -> JsonValue {
+    env_to_json_filtered(&EnvFilter::default())
+}
+
+/// Environment variable filtering options
+#[derive(Debug, Clone, Default)]
+pub struct EnvFilter {
+    /// Only include variables starting with this prefix
+    pub prefix: Option<String>,
+    /// Remove the prefix from variable names
+    pub strip_prefix: bool,
+    /// Lowercase variable names
+    pub lowercase: bool,
+}
+
+/// Parse environment variables into a JSON object, keeping only those matching the filter
+pub fn env_to_json_filtered(filter: &EnvFilter) -> JsonValue {
+    filter_env_vars(std::env::vars(), filter)
+}
+
+fn filter_env_vars(vars: impl Iterator<Item = (String, String)>, filter: &EnvFilter) -> JsonValue {
     let mut map = serde_json::Map::new();
-    for (key, value) in std::env::vars() {
-        map.insert(key, JsonValue::String(value));
+    for (key, value) in vars {
+        let mut name = match filter.prefix.as_deref() {
+            Some(prefix) => match key.strip_prefix(prefix) {
+                Some(rest) if filter.strip_prefix => rest.to_string(),
+                Some(_) => key.clone(),
+                None => continue,
+            },
+            None => key,
+        };
+        if name.is_empty() {
+            continue;
+        }
+        if filter.lowercase {
+            name = name.to_lowercase();
+        }
+        map.insert(name, JsonValue::String(value));
     }
     JsonValue::Object(map)
 }
@@ -161,20 +296,64 @@ pub fn merge_vars(sources: &[&JsonValue]) -> JsonValue {
     JsonValue::Object(result)
 }
 
+/// Variable sets for rendering once per element of the array at `path`
+///
+/// Each set holds the base variables plus `item` (the element) and `index`;
+/// fields of object elements are also available at the top level.
+pub fn each_vars(vars: &JsonValue, path: &str) -> Result<Vec<JsonValue>> {
+    let items = get_var_value(vars, path)
+        .with_context(|| format!("Variable '{}' not found", path))?
+        .as_array()
+        .with_context(|| format!("Variable '{}' is not an array", path))?;
+
+    Ok(items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let extra = serde_json::json!({ "item": item, "index": index });
+            merge_vars(&[vars, &extra, item])
+        })
+        .collect())
+}
+
+/// Variable sets for rendering once per row of a table such as a CSV file
+///
+/// Each set holds the base variables plus `row` (the record) and `index`;
+/// fields of object rows are also available at the top level.
+pub fn row_vars(vars: &JsonValue, rows: &JsonValue) -> Result<Vec<JsonValue>> {
+    let rows = rows
+        .as_array()
+        .context("Rows must be a CSV or NDJSON file or an array of records")?;
+
+    Ok(rows
+        .iter()
+        .enumerate()
+        .map(|(index, row)| {
+            let extra = serde_json::json!({ "row": row, "index": index });
+            merge_vars(&[vars, &extra, row])
+        })
+        .collect())
+}
+
 /// Extract variables from template string
 pub fn extract_variables(template: &str, options: &TemplateOptions) -> Vec<String> {
-    let pattern = format!(
-        "{}\\s*([\\w.\\[\\]]+)\\s*{}",
-        regex::escape(&options.delimiter_start),
-        regex::escape(&options.delimiter_end)
-    );
-    let re = Regex::new(&pattern).unwrap();
+    let re = placeholder_regex(options).unwrap();
 
     re.captures_iter(template)
         .filter_map(|cap| cap.get(1).map(|m| m.as_str().to_string()))
         .collect()
 }
 
+/// Variables in a template string that have no fallback of their own
+fn required_variables(template: &str, options: &TemplateOptions) -> Vec<String> {
+    let re = placeholder_regex(options).unwrap();
+
+    re.captures_iter(template)
+        .filter(|cap| placeholder_default(cap).is_none())
+        .map(|cap| cap[1].to_string())
+        .collect()
+}
+
 /// Validate that all template variables have corresponding values
 pub fn validate_template(template: &JsonValue, vars: &JsonValue, options: &TemplateOptions) -> Result<Vec<String>> {
     let mut missing = Vec::new();
@@ -190,7 +369,7 @@ fn validate_template_recursive(
 ) {
     match template {
         JsonValue::String(s) => {
-            for var in extract_variables(s, options) {
+            for var in required_variables(s, options) {
                 if get_var_value(vars, &var).is_none() && !missing.contains(&var) {
                     missing.push(var);
                 }
@@ -203,7 +382,7 @@ fn validate_template_recursive(
         }
         JsonValue::Object(obj) => {
             for (key, value) in obj {
-                for var in extract_variables(key, options) {
+                for var in required_variables(key, options) {
                     if get_var_value(vars, &var).is_none() && !missing.contains(&var) {
                         missing.push(var);
                     }
@@ -220,6 +399,60 @@ mod tests {
     use super::*;
     use serde_json::json;
 
+    #[test]
+    fn test_each_vars() {
+        let vars = json!({
+            "env": "prod",
+            "services": [{"name": "api"}, {"name": "web"}]
+        });
+        let sets = each_vars(&vars, "services").unwrap();
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[1]["name"], "web");
+        assert_eq!(sets[1]["index"], 1);
+        assert_eq!(sets[1]["env"], "prod");
+
+        let options = TemplateOptions::default();
+        let path = render_string("out/{{ env }}-{{ name }}.yaml", &sets[0], &options).unwrap();
+        assert_eq!(path, "out/prod-api.yaml");
+
+        assert!(each_vars(&vars, "env").is_err());
+    }
+
+    #[test]
+    fn test_row_vars() {
+        let rows = json!([{"host": "a", "port": 80}, {"host": "b", "port": 81}]);
+        let sets = row_vars(&json!({"env": "prod"}), &rows).unwrap();
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[1]["host"], "b");
+        assert_eq!(sets[1]["row"]["port"], 81);
+        assert_eq!(sets[1]["index"], 1);
+        assert_eq!(sets[0]["env"], "prod");
+
+        assert!(row_vars(&json!({}), &json!({"host": "a"})).is_err());
+    }
+
+    #[test]
+    fn test_missing_variable_location() {
+        let source = "{\n  \"greeting\": \"Hello {{ user.nmae }}\"\n}\n";
+        let template: JsonValue = serde_json::from_str(source).unwrap();
+        let options = TemplateOptions {
+            strict: true,
+            ..Default::default()
+        };
+        let err = render_value(&template, &json!({"user": {"name": "A"}}), &options).unwrap_err();
+        let missing = err.downcast::<MissingVariable>().unwrap().locate(
+            source,
+            Some("t.json".to_string()),
+            false,
+        );
+
+        assert_eq!(missing.location, Some((2, 22)));
+        assert_eq!(
+            missing.to_string(),
+            "Variable 'user.nmae' not found at t.json:2:22\n  |\n2 |   \"greeting\": \"Hello {{ user.nmae }}\"\n  |                      ^^^^^^^^^^^^^^^"
+        );
+    }
+
     #[test]
     fn test_render_string() {
         let vars = json!({
@@ -232,6 +465,43 @@ mod tests {
         assert_eq!(result, "Hello, Alice! You are 30 years old.");
     }
 
+    #[test]
+    fn test_render_variable_defaults() {
+        let vars = json!({"region": "eu-west-1"});
+        let options = TemplateOptions {
+            strict: true,
+            ..Default::default()
+        };
+
+        let result = render_string(
+            "{{ region:-us-east-1 }} {{ zone:-a }} {{ tier | default:\"free\" }} {{ name|default:'x y' }}",
+            &vars,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(result, "eu-west-1 a free x y");
+
+        let template = json!({"port": "{{ port:-8080 }}", "host": "{{ host }}"});
+        assert_eq!(
+            validate_template(&template, &vars, &options).unwrap(),
+            vec!["host".to_string()]
+        );
+        assert_eq!(
+            render_value(&json!("{{ port:-8080 }}"), &vars, &options).unwrap(),
+            json!(8080)
+        );
+
+        // Null and empty values take the default too; other falsy values do not
+        let vars = json!({"zone": null, "tier": "", "count": 0, "flag": false});
+        let result = render_string(
+            "{{ zone:-a }} {{ tier | default:\"free\" }} {{ count:-1 }} {{ flag:-true }}",
+            &vars,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(result, "a free 0 false");
+    }
+
     #[test]
     fn test_render_nested_vars() {
         let vars = json!({
@@ -279,6 +549,23 @@ mod tests {
         assert_eq!(result["data"]["age"], 25);
     }
 
+    #[test]
+    fn test_filter_env_vars() {
+        let vars = vec![
+            ("APP_DB_HOST".to_string(), "localhost".to_string()),
+            ("APP_PORT".to_string(), "8080".to_string()),
+            ("HOME".to_string(), "/root".to_string()),
+        ];
+        let filter = EnvFilter {
+            prefix: Some("APP_".to_string()),
+            strip_prefix: true,
+            lowercase: true,
+        };
+
+        let result = filter_env_vars(vars.into_iter(), &filter);
+        assert_eq!(result, json!({"db_host": "localhost", "port": "8080"}));
+    }
+
     #[test]
     fn test_extract_variables() {
         let template = "Hello {{ name }}, your balance is {{ account.balance }}";
@@ -2,25 +2,40 @@
 
 use anyhow::{Context, Result};
 use colored::Colorize;
+use serde::Serialize;
 use serde_json::Value as JsonValue;
+use std::collections::HashMap;
 
+use crate::core::converter::ConvertOptions;
+use crate::core::remote;
 use crate::formats::csv as csv_format;
+use crate::formats::detect::Format;
+use crate::utils::{locate, markdown};
 
 /// Validation result
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ValidationResult {
     pub valid: bool,
     pub errors: Vec<ValidationError>,
     pub warnings: Vec<ValidationWarning>,
+    /// File the error locations refer to (`<stdin>` when read from stdin)
+    #[serde(skip)]
+    pub source: Option<String>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ValidationError {
     pub path: String,
     pub message: String,
+    /// 1-based line of the offending value in the source, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// 1-based column of the offending value in the source, when known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ValidationWarning {
     pub path: String,
     pub message: String,
@@ -32,6 +47,7 @@ impl ValidationResult {
             valid: true,
             errors: Vec::new(),
             warnings: Vec::new(),
+            source: None,
         }
     }
 
@@ -40,9 +56,25 @@ impl ValidationResult {
         self.errors.push(ValidationError {
             path: path.to_string(),
             message: message.to_string(),
+            line: None,
+            column: None,
         });
     }
 
+    /// Resolve the JSON Pointer of each schema error to a line and column of `content`
+    ///
+    /// `line_offset` is added to every line, for content cut out of a larger file
+    /// such as Markdown front matter.
+    pub fn locate_errors(&mut self, content: &str, format: Format, line_offset: usize) {
+        for error in &mut self.errors {
+            let pointer = if error.path == "$" { "" } else { &error.path };
+            if let Some((line, column)) = locate::locate(content, format, pointer) {
+                error.line = Some(line + line_offset);
+                error.column = Some(column);
+            }
+        }
+    }
+
     pub fn add_warning(&mut self, path: &str, message: &str) {
         self.warnings.push(ValidationWarning {
             path: path.to_string(),
@@ -68,6 +100,16 @@ impl ValidationResult {
                     error.path.cyan(),
                     error.message
                 ));
+                if let Some((line, column)) = error.line.zip(error.column) {
+                    let source = self.source.as_deref().unwrap_or("<stdin>");
+                    output.push_str(&format!(
+                        "    {} {}:{}:{}\n",
+                        "-->".blue(),
+                        source,
+                        line,
+                        column
+                    ));
+                }
             }
         }
 
@@ -89,6 +131,55 @@ impl ValidationResult {
 
         output
     }
+
+    /// Markdown summary for posting as a pull request comment
+    ///
+    /// `subject` names what was validated (typically the file path).
+    pub fn format_markdown(&self, subject: &str) -> String {
+        let status = if self.valid {
+            "✅ Validation passed"
+        } else {
+            "❌ Validation failed"
+        };
+        let mut output = format!("### {}: {}\n\n", status, markdown::code_cell(subject));
+        output.push_str(&markdown::table(
+            &["Kind", "Count"],
+            &[
+                vec!["Errors".to_string(), self.errors.len().to_string()],
+                vec!["Warnings".to_string(), self.warnings.len().to_string()],
+            ],
+        ));
+
+        let errors: Vec<_> = self.errors.iter().map(|e| (&e.path, &e.message)).collect();
+        output.push_str(&issues_details("Errors", &errors, true));
+        let warnings: Vec<_> = self
+            .warnings
+            .iter()
+            .map(|w| (&w.path, &w.message))
+            .collect();
+        output.push_str(&issues_details("Warnings", &warnings, false));
+
+        output
+    }
+}
+
+/// Collapsible table of path/message pairs, or nothing when there are none
+fn issues_details(title: &str, issues: &[(&String, &String)], open: bool) -> String {
+    if issues.is_empty() {
+        return String::new();
+    }
+    let rows: Vec<Vec<String>> = issues
+        .iter()
+        .map(|(path, message)| vec![markdown::code_cell(path), markdown::escape_cell(message)])
+        .collect();
+    format!(
+        "\n{}",
+        markdown::details(
+            &format!("{} ({})", title, issues.len()),
+            &markdown::table(&["Path", "Message"], &rows),
+            open,
+        )
+    )
 }
 
 impl Default for ValidationResult {
@@ -117,6 +208,60 @@ pub fn validate_json_schema(data: &JsonValue, schema: &JsonValue) -> Result<Vali
     Ok(result)
 }
 
+/// Load a JSON Schema from a file path or HTTP(S) URL
+pub fn load_schema(source: &str, offline: bool) -> Result<JsonValue> {
+    let content = if remote::is_url(source) {
+        remote::fetch_cached(source, offline)?
+    } else {
+        std::fs::read_to_string(source)
+            .with_context(|| format!("Failed to read schema file: {}", source))?
+    };
+    serde_json::from_str(&content).context("Failed to parse schema as JSON")
+}
+
+/// Validate each NDJSON record against a JSON Schema
+pub fn validate_ndjson_schema(content: &str, schema: &JsonValue) -> Result<ValidationResult> {
+    let mut result = ValidationResult::new();
+
+    for (line_number, line) in ndjson_lines(content) {
+        let location = format!("line {}", line_number);
+        match serde_json::from_str::<JsonValue>(line) {
+            Ok(record) => {
+                for error in validate_json_schema(&record, schema)?.errors {
+                    result.add_error(&format!("{} {}", location, error.path), &error.message);
+                }
+            }
+            Err(err) => result.add_error(&location, &format!("Invalid JSON: {}", err)),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Lint NDJSON, reporting every invalid line rather than stopping at the first
+pub fn lint_ndjson(content: &str) -> Result<ValidationResult> {
+    let mut result = ValidationResult::new();
+
+    for (line_number, line) in ndjson_lines(content) {
+        let location = format!("line {}", line_number);
+        match serde_json::from_str::<JsonValue>(line) {
+            Ok(record) => lint_json_value(&record, &location, &mut result),
+            Err(err) => result.add_error(&location, &format!("Invalid JSON: {}", err)),
+        }
+    }
+
+    Ok(result)
+}
+
+/// Non-blank NDJSON lines with their 1-based line numbers
+fn ndjson_lines(content: &str) -> impl Iterator<Item = (usize, &str)> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty())
+}
+
 /// Lint JSON for common issues
 pub fn lint_json(content: &str) -> Result<ValidationResult> {
     let mut result = ValidationResult::new();
@@ -190,11 +335,9 @@ fn lint_json_value(value: &JsonValue, path: &str, result: &mut ValidationResult)
                 lint_json_value(val, &child_path, result);
             }
         }
-        JsonValue::String(s) => {
-            // Check for potential issues in strings
-            if s.trim().is_empty() && !s.is_empty() {
-                result.add_warning(path, "String contains only whitespace");
-            }
+        // Check for potential issues in strings
+        JsonValue::String(s) if s.trim().is_empty() && !s.is_empty() => {
+            result.add_warning(path, "String contains only whitespace");
         }
         _ => {}
     }
@@ -215,9 +358,23 @@ fn get_json_type(value: &JsonValue) -> &'static str {
 pub fn lint_yaml(content: &str) -> Result<ValidationResult> {
     let mut result = ValidationResult::new();
 
+    // serde_yaml rejects duplicate keys one at a time, so report them all up front
+    let duplicates = duplicate_yaml_keys(content);
+    for dup in &duplicates {
+        result.add_error(
+            &format!("line {}", dup.line),
+            &format!(
+                "Duplicate key '{}' (first defined on line {})",
+                dup.path, dup.first_line
+            ),
+        );
+    }
+
     // Try to parse
-    let _value: serde_yaml::Value =
-        serde_yaml::from_str(content).context("Invalid YAML syntax")?;
+    let parsed = serde_yaml::from_str::<serde_yaml::Value>(content);
+    if duplicates.is_empty() {
+        parsed.context("Invalid YAML syntax")?;
+    }
 
     // Check for tabs (YAML should use spaces)
     for (i, line) in content.lines().enumerate() {
@@ -263,6 +420,391 @@ pub fn lint_yaml(content: &str) -> Result<ValidationResult> {
     Ok(result)
 }
 
+/// A mapping key that appears more than once at the same nesting level
+#[derive(Debug, PartialEq)]
+struct DuplicateKey {
+    path: String,
+    line: usize,
+    first_line: usize,
+}
+
+/// Mapping being scanned, identified by the column of its keys
+struct YamlLevel {
+    indent: usize,
+    path: String,
+    keys: HashMap<String, usize>,
+    last_key: Option<String>,
+    items: usize,
+}
+
+impl YamlLevel {
+    fn child_path(&self) -> String {
+        match &self.last_key {
+            Some(key) if self.path.is_empty() => key.clone(),
+            Some(key) => format!("{}.{}", self.path, key),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// Scan YAML for duplicate mapping keys, document by document
+fn duplicate_yaml_keys(content: &str) -> Vec<DuplicateKey> {
+    let mut duplicates = Vec::new();
+    let mut stack: Vec<YamlLevel> = Vec::new();
+    let mut root_items = 0;
+    let mut block_scalar: Option<usize> = None;
+    let lines: Vec<&str> = content.lines().collect();
+    // Lines before this one belong to a flow collection already scanned
+    let mut flow_end = 0;
+
+    for (i, line) in lines.iter().enumerate() {
+        if i < flow_end {
+            continue;
+        }
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        if let Some(block_indent) = block_scalar {
+            if trimmed.is_empty() || indent > block_indent {
+                continue;
+            }
+            block_scalar = None;
+        }
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if indent == 0 && (trimmed.starts_with("---") || trimmed.starts_with("...")) {
+            stack.clear();
+            root_items = 0;
+            continue;
+        }
+
+        // Step past sequence indicators; each one starts a new item
+        let mut column = indent;
+        let mut rest = trimmed;
+        let mut new_item = false;
+        while let Some(after) = rest.strip_prefix('-') {
+            if !(after.is_empty() || after.starts_with(' ')) {
+                break;
+            }
+            let item = after.trim_start();
+            column += 1 + after.len() - item.len();
+            rest = item;
+            new_item = true;
+        }
+
+        while stack
+            .last()
+            .is_some_and(|level| level.indent > column || (new_item && level.indent == column))
+        {
+            stack.pop();
+        }
+
+        let mut item_path = None;
+        if new_item {
+            item_path = Some(match stack.last_mut() {
+                Some(parent) => {
+                    parent.items += 1;
+                    format!("{}[{}]", parent.child_path(), parent.items - 1)
+                }
+                None => {
+                    root_items += 1;
+                    format!("[{}]", root_items - 1)
+                }
+            });
+        }
+
+        let Some((key, value)) = split_yaml_key(rest) else {
+            if is_block_scalar_indicator(rest) {
+                block_scalar = Some(indent);
+            } else if rest.starts_with(['{', '[']) {
+                let path = item_path
+                    .unwrap_or_else(|| stack.last().map(YamlLevel::child_path).unwrap_or_default());
+                flow_end = i + 1 + FlowScanner::scan(&lines, i, rest, path, &mut duplicates);
+            }
+            continue;
+        };
+        if is_block_scalar_indicator(value) {
+            block_scalar = Some(column);
+        }
+
+        if stack.last().is_none_or(|level| level.indent < column) {
+            let path = item_path
+                .unwrap_or_else(|| stack.last().map(YamlLevel::child_path).unwrap_or_default());
+            stack.push(YamlLevel {
+                indent: column,
+                path,
+                keys: HashMap::new(),
+                last_key: None,
+                items: 0,
+            });
+        }
+        let level = stack.last_mut().expect("level pushed above");
+
+        if key != "<<" {
+            if let Some(&first_line) = level.keys.get(&key) {
+                let path = if level.path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", level.path, key)
+                };
+                duplicates.push(DuplicateKey {
+                    path,
+                    line: i + 1,
+                    first_line,
+                });
+            } else {
+                level.keys.insert(key.clone(), i + 1);
+            }
+        }
+        level.last_key = Some(key);
+        level.items = 0;
+
+        if value.starts_with(['{', '[']) {
+            let path = level.child_path();
+            flow_end = i + 1 + FlowScanner::scan(&lines, i, value, path, &mut duplicates);
+        }
+    }
+
+    duplicates
+}
+
+/// Nesting beyond which flow collections are not scanned further
+const MAX_FLOW_DEPTH: usize = 128;
+
+/// Reads a flow collection (`{a: 1}`, `[x, {b: 2}]`) that may span lines,
+/// reporting duplicate keys of its mappings
+struct FlowScanner<'a> {
+    /// Unread text of the current line
+    rest: &'a str,
+    /// Lines after the current one
+    next_lines: std::slice::Iter<'a, &'a str>,
+    /// 1-based number of the current line
+    line: usize,
+}
+
+impl<'a> FlowScanner<'a> {
+    /// Scan the collection starting at `text` on line `index` of `lines`,
+    /// returning how many more lines it continues onto
+    fn scan(
+        lines: &'a [&'a str],
+        index: usize,
+        text: &'a str,
+        path: String,
+        duplicates: &mut Vec<DuplicateKey>,
+    ) -> usize {
+        let mut scanner = FlowScanner {
+            rest: text,
+            next_lines: lines[index + 1..].iter(),
+            line: index + 1,
+        };
+        scanner.collection(&path, 0, duplicates);
+        scanner.line - (index + 1)
+    }
+
+    /// The next character past whitespace, comments and line breaks
+    fn peek(&mut self) -> Option<char> {
+        loop {
+            self.rest = self.rest.trim_start();
+            match self.rest.chars().next() {
+                Some('#') | None => {
+                    self.rest = self.next_lines.next()?;
+                    self.line += 1;
+                }
+                Some(c) => return Some(c),
+            }
+        }
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.rest.chars().next() {
+            self.rest = &self.rest[c.len_utf8()..];
+        }
+    }
+
+    /// Scan the collection opening at the next character
+    fn collection(&mut self, path: &str, depth: usize, duplicates: &mut Vec<DuplicateKey>) {
+        if depth >= MAX_FLOW_DEPTH {
+            return self.skip_collection();
+        }
+        let Some(open) = self.peek() else {
+            return;
+        };
+        self.bump();
+        let mapping = open == '{';
+        let mut keys: HashMap<String, usize> = HashMap::new();
+        let mut index = 0;
+        loop {
+            match self.peek() {
+                None => return,
+                Some(',') => {
+                    self.bump();
+                    index += 1;
+                }
+                // A mismatched bracket is left for the parser to report
+                Some('}' | ']') => {
+                    self.bump();
+                    return;
+                }
+                Some(_) if !mapping => {
+                    let item = format!("{}[{}]", path, index);
+                    self.node(&item, depth, duplicates);
+                }
+                Some(c) => {
+                    let line = self.line;
+                    let key = if c == '{' || c == '[' {
+                        // Complex keys are not compared
+                        self.skip_collection();
+                        None
+                    } else {
+                        Some(self.scalar())
+                    };
+                    let child = match &key {
+                        Some(key) if path.is_empty() => key.clone(),
+                        Some(key) => format!("{}.{}", path, key),
+                        None => path.to_string(),
+                    };
+                    if let Some(key) = key.filter(|key| key != "<<") {
+                        match keys.get(&key) {
+                            Some(&first_line) => duplicates.push(DuplicateKey {
+                                path: child.clone(),
+                                line,
+                                first_line,
+                            }),
+                            None => {
+                                keys.insert(key, line);
+                            }
+                        }
+                    }
+                    if self.peek() == Some(':') {
+                        self.bump();
+                        if !matches!(self.peek(), Some(',' | '}' | ']') | None) {
+                            self.node(&child, depth, duplicates);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Scan a value: collections are searched, scalars stepped over
+    fn node(&mut self, path: &str, depth: usize, duplicates: &mut Vec<DuplicateKey>) {
+        match self.peek() {
+            Some('{' | '[') => self.collection(path, depth + 1, duplicates),
+            _ => {
+                self.scalar();
+            }
+        }
+    }
+
+    /// Step over a collection too deep to scan, without recursing
+    fn skip_collection(&mut self) {
+        let mut depth = 0usize;
+        while let Some(c) = self.peek() {
+            match c {
+                '{' | '[' => depth += 1,
+                '}' | ']' => depth = depth.saturating_sub(1),
+                '"' | '\'' => {
+                    self.scalar();
+                    continue;
+                }
+                _ => {}
+            }
+            self.bump();
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+
+    /// Read a scalar, unquoted, up to the next flow indicator
+    fn scalar(&mut self) -> String {
+        let Some(quote @ ('"' | '\'')) = self.peek() else {
+            let end = plain_scalar_end(self.rest);
+            let text = self.rest[..end].trim_end().to_string();
+            self.rest = &self.rest[end..];
+            return text;
+        };
+        self.bump();
+        let mut text = String::new();
+        loop {
+            let Some(c) = self.rest.chars().next() else {
+                // Quoted scalars fold onto the next line
+                let Some(line) = self.next_lines.next() else {
+                    return text;
+                };
+                self.rest = line.trim_start();
+                self.line += 1;
+                text.push(' ');
+                continue;
+            };
+            self.bump();
+            match c {
+                '\\' if quote == '"' => {
+                    text.extend(self.rest.chars().next());
+                    self.bump();
+                }
+                '\'' if quote == '\'' && self.rest.starts_with('\'') => {
+                    self.bump();
+                    text.push('\'');
+                }
+                c if c == quote => return text,
+                c => text.push(c),
+            }
+        }
+    }
+}
+
+/// End of a plain scalar in a flow collection, at least one character in so
+/// that scanning always moves forward
+fn plain_scalar_end(text: &str) -> usize {
+    let mut prev = ' ';
+    for (i, c) in text.char_indices() {
+        let next = text[i + c.len_utf8()..].chars().next();
+        let ends = match c {
+            ',' | '[' | ']' | '{' | '}' => true,
+            ':' => next.is_none_or(|n| n.is_whitespace() || ",[]{}".contains(n)),
+            '#' => prev.is_whitespace(),
+            _ => false,
+        };
+        if ends {
+            return i.max(text.chars().next().map_or(0, char::len_utf8));
+        }
+        prev = c;
+    }
+    text.len()
+}
+
+/// Split a block mapping entry into its (unquoted) key and the rest of the line
+fn split_yaml_key(text: &str) -> Option<(String, &str)> {
+    let (key, after) = match text.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = text[1..].find(quote)? + 1;
+            (text[1..end].to_string(), &text[end + 1..])
+        }
+        '{' | '[' | '?' | '&' | '*' | '!' | '|' | '>' | '#' => return None,
+        _ => {
+            let end = text
+                .find(": ")
+                .or_else(|| text.strip_suffix(':').map(str::len))?;
+            (text[..end].trim_end().to_string(), &text[end..])
+        }
+    };
+    let value = after.strip_prefix(':')?;
+    if !(value.is_empty() || value.starts_with(' ')) {
+        return None;
+    }
+    Some((key, value.trim()))
+}
+
+/// Whether a value starts a literal (`|`) or folded (`>`) block scalar
+fn is_block_scalar_indicator(value: &str) -> bool {
+    let value = value.split(" #").next().unwrap_or_default().trim_end();
+    let mut chars = value.chars();
+    matches!(chars.next(), Some('|' | '>'))
+        && chars.all(|c| c == '-' || c == '+' || c.is_ascii_digit())
+}
+
 /// Lint TOML for common issues
 pub fn lint_toml(content: &str) -> Result<ValidationResult> {
     let mut result = ValidationResult::new();
@@ -290,6 +832,32 @@ pub fn lint_toml(content: &str) -> Result<ValidationResult> {
     Ok(result)
 }
 
+/// Lint content in the given format without a schema
+pub fn lint(content: &str, format: Format, has_headers: bool) -> Result<ValidationResult> {
+    match format {
+        Format::Json => lint_json(content),
+        Format::Ndjson => lint_ndjson(content),
+        Format::Yaml => lint_yaml(content),
+        Format::Toml => lint_toml(content),
+        Format::Csv | Format::Tsv => validate_csv(content, has_headers),
+        Format::Xml => {
+            // For XML, just validate it can be parsed
+            crate::formats::xml::validate(content)?;
+            Ok(ValidationResult::new())
+        }
+        _ if format.is_binary() || matches!(format, Format::Html | Format::Sql) => {
+            anyhow::bail!("Linting {} input is not supported", format)
+        }
+        _ => {
+            // Other text formats are checked by parsing them
+            format
+                .handler()?
+                .parse(content.as_bytes(), &ConvertOptions::default())?;
+            Ok(ValidationResult::new())
+        }
+    }
+}
+
 /// Validate CSV structure
 pub fn validate_csv(content: &str, has_headers: bool) -> Result<ValidationResult> {
     let mut result = ValidationResult::new();
@@ -373,6 +941,20 @@ mod tests {
         assert!(!result.valid);
     }
 
+    #[test]
+    fn test_format_markdown() {
+        let mut result = ValidationResult::new();
+        result.add_error("/port", "8080 is not of type \"string\"");
+        result.add_warning("$", "a | b");
+
+        let report = result.format_markdown("config.json");
+        assert!(report.starts_with("### ❌ Validation failed: `config.json`"));
+        assert!(report.contains("| Errors | 1 |\n| Warnings | 1 |"));
+        assert!(report.contains("<details open>\n<summary>Errors (1)</summary>"));
+        assert!(report.contains("<details>\n<summary>Warnings (1)</summary>"));
+        assert!(report.contains("| `$` | a \\| b |"));
+    }
+
     #[test]
     fn test_lint_json() {
         let json = r#"{"name": "test", "items": []}"#;
@@ -380,6 +962,104 @@ mod tests {
         assert!(result.warnings.iter().any(|w| w.message.contains("Empty array")));
     }
 
+    #[test]
+    fn test_lint_yaml_duplicate_keys() {
+        let yaml = "\
+name: app
+env:
+  debug: true
+  script: |
+    debug: ignored inside block scalar
+  debug: false
+servers:
+  - host: a
+    port: 1
+  - host: b
+    host: c
+name: other
+---
+name: separate document
+";
+        let result = lint_yaml(yaml).unwrap();
+        assert!(!result.valid);
+        let messages: Vec<(&str, &str)> = result
+            .errors
+            .iter()
+            .map(|e| (e.path.as_str(), e.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    "line 6",
+                    "Duplicate key 'env.debug' (first defined on line 3)"
+                ),
+                (
+                    "line 11",
+                    "Duplicate key 'servers[1].host' (first defined on line 10)"
+                ),
+                ("line 12", "Duplicate key 'name' (first defined on line 1)"),
+            ]
+        );
+
+        assert!(lint_yaml("a: 1\nb:\n  a: 2\n").unwrap().valid);
+    }
+
+    #[test]
+    fn test_lint_yaml_flow_duplicate_keys() {
+        let yaml = "\
+c: [ {k: 1, k: 2} ]
+inline: {a: 1, \"a\": 2, b: {x: 1, x: 2}}
+list:
+  - {name: a, name: b}
+multi: {
+  one: 1,
+  # one: 3
+  one: 2
+}
+after: 1
+after: 2
+ok: [{k: 1}, {k: 2}, 'k: 1, k: 2']
+text: \"a: {b: 1, b: 2}\"
+";
+        let result = lint_yaml(yaml).unwrap();
+        let messages: Vec<(&str, &str)> = result
+            .errors
+            .iter()
+            .map(|e| (e.path.as_str(), e.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                ("line 1", "Duplicate key 'c[0].k' (first defined on line 1)"),
+                (
+                    "line 2",
+                    "Duplicate key 'inline.a' (first defined on line 2)"
+                ),
+                (
+                    "line 2",
+                    "Duplicate key 'inline.b.x' (first defined on line 2)"
+                ),
+                (
+                    "line 4",
+                    "Duplicate key 'list[0].name' (first defined on line 4)"
+                ),
+                (
+                    "line 8",
+                    "Duplicate key 'multi.one' (first defined on line 6)"
+                ),
+                (
+                    "line 11",
+                    "Duplicate key 'after' (first defined on line 10)"
+                ),
+            ]
+        );
+
+        assert!(lint_yaml("a: {b: [1, {c: 2}], d: 3}\n").unwrap().valid);
+        let deep = format!("a: {}1{}\n", "[".repeat(200), "]".repeat(200));
+        assert!(duplicate_yaml_keys(&deep).is_empty());
+    }
+
     #[test]
     fn test_validate_csv() {
         // Test for duplicate headers
@@ -0,0 +1,361 @@
+//! Lossless intermediate value for JSON, YAML and TOML
+//!
+//! Only conversions between JSON, YAML and TOML pivot through [`DtxValue`]:
+//! TOML dates and times are written back as such (a local time no longer
+//! turns into a string), YAML tags are kept until a format without tags is
+//! written, integers keep every digit of 64 bits, signed or unsigned, and
+//! keys keep their input order.
+//!
+//! Every other conversion pivots through `serde_json::Value`. Its maps keep
+//! insertion order, so keys and XML attributes keep their input order and
+//! integers keep 64 bits there too, but TOML dates and times become strings
+//! and YAML tags are dropped. TOML integers are at most 64-bit signed, so
+//! larger ones are written to TOML as strings holding every digit.
+
+use anyhow::{bail, Result};
+use indexmap::IndexMap;
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_json::Value as JsonValue;
+use serde_yaml::value::{Tag, TaggedValue};
+use toml::value::Datetime;
+
+use crate::formats::binary::float;
+use crate::formats::detect::Format;
+use crate::formats::toml::TomlLayout;
+use crate::formats::yaml::YamlVersion;
+use crate::formats::{json as json_format, toml as toml_format, yaml as yaml_format, OutputStyle};
+
+/// A parsed value that keeps what JSON, YAML and TOML can each express
+#[derive(Debug, Clone, PartialEq)]
+pub enum DtxValue {
+    Null,
+    Bool(bool),
+    /// Any `i64` or `u64`
+    Integer(i128),
+    Float(f64),
+    String(String),
+    /// TOML offset or local date-time, date, or time
+    DateTime(Datetime),
+    /// YAML value with an explicit tag such as `!Ref`, kept without the `!`
+    Tagged(String, Box<DtxValue>),
+    Array(Vec<DtxValue>),
+    Object(IndexMap<String, DtxValue>),
+}
+
+impl DtxValue {
+    /// Whether conversions between `from` and `to` can use this value
+    pub fn supports(from: Format, to: Format) -> bool {
+        let lossless = |format| matches!(format, Format::Json | Format::Yaml | Format::Toml);
+        lossless(from) && lossless(to)
+    }
+
+    /// Parse JSON, YAML or TOML text, YAML by the rules of `version`
+    pub fn parse(content: &str, format: Format, version: YamlVersion) -> Result<DtxValue> {
+        match format {
+            Format::Json => Ok(DtxValue::from(json_format::parse(content)?)),
+            Format::Yaml => Ok(DtxValue::from(yaml_format::parse(content, version)?)),
+            Format::Toml => Ok(DtxValue::from(toml_format::parse(content)?)),
+            _ => bail!("{} input cannot be read as a lossless value", format),
+        }
+    }
+
+    /// Write as pretty-printed JSON, YAML or TOML text
+    pub fn write(
+        &self,
+        format: Format,
+        layout: &TomlLayout,
+        style: &OutputStyle,
+    ) -> Result<String> {
+        match format {
+            Format::Json => json_format::to_pretty(self, style),
+            Format::Yaml => yaml_format::to_pretty(&self.to_yaml(), style),
+            Format::Toml => toml_format::to_pretty_with_layout(&self.to_toml(), layout, style),
+            _ => bail!("{} output cannot be written from a lossless value", format),
+        }
+    }
+
+    /// The JSON value, with dates and times as strings and tags dropped
+    pub fn into_json(self) -> JsonValue {
+        match self {
+            DtxValue::Null => JsonValue::Null,
+            DtxValue::Bool(b) => JsonValue::Bool(b),
+            DtxValue::Integer(i) => integer_to_json(i),
+            DtxValue::Float(f) => float(f),
+            DtxValue::String(s) => JsonValue::String(s),
+            DtxValue::DateTime(dt) => JsonValue::String(dt.to_string()),
+            DtxValue::Tagged(_, value) => value.into_json(),
+            DtxValue::Array(items) => {
+                JsonValue::Array(items.into_iter().map(DtxValue::into_json).collect())
+            }
+            DtxValue::Object(map) => JsonValue::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key, value.into_json()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The YAML value; dates and times become plain scalars
+    pub fn to_yaml(&self) -> serde_yaml::Value {
+        match self {
+            DtxValue::Null => serde_yaml::Value::Null,
+            DtxValue::Bool(b) => serde_yaml::Value::Bool(*b),
+            DtxValue::Integer(i) => match (i64::try_from(*i), u64::try_from(*i)) {
+                (Ok(i), _) => serde_yaml::Value::from(i),
+                (_, Ok(u)) => serde_yaml::Value::from(u),
+                _ => serde_yaml::Value::from(*i as f64),
+            },
+            DtxValue::Float(f) => serde_yaml::Value::from(*f),
+            DtxValue::String(s) => serde_yaml::Value::String(s.clone()),
+            DtxValue::DateTime(dt) => serde_yaml::Value::String(dt.to_string()),
+            DtxValue::Tagged(tag, value) => serde_yaml::Value::Tagged(Box::new(TaggedValue {
+                tag: Tag::new(tag),
+                value: value.to_yaml(),
+            })),
+            DtxValue::Array(items) => {
+                serde_yaml::Value::Sequence(items.iter().map(DtxValue::to_yaml).collect())
+            }
+            DtxValue::Object(map) => serde_yaml::Value::Mapping(
+                map.iter()
+                    .map(|(key, value)| (serde_yaml::Value::String(key.clone()), value.to_yaml()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The TOML value. TOML has no null, so nulls become empty strings, and
+    /// integers beyond `i64` become strings. Only dates and times read from
+    /// TOML become TOML datetimes; strings that look like dates stay strings.
+    pub fn to_toml(&self) -> toml::Value {
+        match self {
+            DtxValue::Null => toml::Value::String(String::new()),
+            DtxValue::Bool(b) => toml::Value::Boolean(*b),
+            DtxValue::Integer(i) => match i64::try_from(*i) {
+                Ok(i) => toml::Value::Integer(i),
+                Err(_) => toml::Value::String(i.to_string()),
+            },
+            DtxValue::Float(f) => toml::Value::Float(*f),
+            DtxValue::String(s) => toml::Value::String(s.clone()),
+            DtxValue::DateTime(dt) => toml::Value::Datetime(*dt),
+            DtxValue::Tagged(_, value) => value.to_toml(),
+            DtxValue::Array(items) => {
+                toml::Value::Array(items.iter().map(DtxValue::to_toml).collect())
+            }
+            DtxValue::Object(map) => toml::Value::Table(
+                map.iter()
+                    .map(|(key, value)| (key.clone(), value.to_toml()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+fn integer_to_json(i: i128) -> JsonValue {
+    match (i64::try_from(i), u64::try_from(i)) {
+        (Ok(i), _) => JsonValue::from(i),
+        (_, Ok(u)) => JsonValue::from(u),
+        _ => float(i as f64),
+    }
+}
+
+impl From<JsonValue> for DtxValue {
+    fn from(json: JsonValue) -> Self {
+        match json {
+            JsonValue::Null => DtxValue::Null,
+            JsonValue::Bool(b) => DtxValue::Bool(b),
+            JsonValue::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => DtxValue::Integer(i.into()),
+                (_, Some(u)) => DtxValue::Integer(u.into()),
+                _ => DtxValue::Float(n.as_f64().unwrap_or_default()),
+            },
+            JsonValue::String(s) => DtxValue::String(s),
+            JsonValue::Array(items) => {
+                DtxValue::Array(items.into_iter().map(DtxValue::from).collect())
+            }
+            JsonValue::Object(map) => DtxValue::Object(
+                map.into_iter()
+                    .map(|(key, value)| (key, DtxValue::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+impl From<serde_yaml::Value> for DtxValue {
+    fn from(yaml: serde_yaml::Value) -> Self {
+        match yaml {
+            serde_yaml::Value::Null => DtxValue::Null,
+            serde_yaml::Value::Bool(b) => DtxValue::Bool(b),
+            serde_yaml::Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => DtxValue::Integer(i.into()),
+                (_, Some(u)) => DtxValue::Integer(u.into()),
+                _ => DtxValue::Float(n.as_f64().unwrap_or_default()),
+            },
+            serde_yaml::Value::String(s) => DtxValue::String(s),
+            serde_yaml::Value::Sequence(items) => {
+                DtxValue::Array(items.into_iter().map(DtxValue::from).collect())
+            }
+            serde_yaml::Value::Mapping(map) => DtxValue::Object(
+                map.into_iter()
+                    .map(|(key, value)| {
+                        let key = match key {
+                            serde_yaml::Value::String(s) => s,
+                            other => serde_yaml::to_string(&other)
+                                .unwrap_or_default()
+                                .trim()
+                                .to_string(),
+                        };
+                        (key, DtxValue::from(value))
+                    })
+                    .collect(),
+            ),
+            serde_yaml::Value::Tagged(tagged) => {
+                let tag = tagged.tag.to_string();
+                let tag = tag.strip_prefix('!').unwrap_or(&tag).to_string();
+                DtxValue::Tagged(tag, Box::new(DtxValue::from(tagged.value)))
+            }
+        }
+    }
+}
+
+impl From<toml::Value> for DtxValue {
+    fn from(toml: toml::Value) -> Self {
+        match toml {
+            toml::Value::String(s) => DtxValue::String(s),
+            toml::Value::Integer(i) => DtxValue::Integer(i.into()),
+            toml::Value::Float(f) => DtxValue::Float(f),
+            toml::Value::Boolean(b) => DtxValue::Bool(b),
+            toml::Value::Datetime(dt) => DtxValue::DateTime(dt),
+            toml::Value::Array(items) => {
+                DtxValue::Array(items.into_iter().map(DtxValue::from).collect())
+            }
+            toml::Value::Table(table) => DtxValue::Object(
+                table
+                    .into_iter()
+                    .map(|(key, value)| (key, DtxValue::from(value)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Serializes as JSON would show it: dates and times as strings, tags dropped
+impl Serialize for DtxValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            DtxValue::Null => serializer.serialize_unit(),
+            DtxValue::Bool(b) => serializer.serialize_bool(*b),
+            DtxValue::Integer(i) => match (i64::try_from(*i), u64::try_from(*i)) {
+                (Ok(i), _) => serializer.serialize_i64(i),
+                (_, Ok(u)) => serializer.serialize_u64(u),
+                _ => serializer.serialize_i128(*i),
+            },
+            DtxValue::Float(f) if f.is_finite() => serializer.serialize_f64(*f),
+            DtxValue::Float(_) => serializer.serialize_unit(),
+            DtxValue::String(s) => serializer.serialize_str(s),
+            DtxValue::DateTime(dt) => serializer.collect_str(dt),
+            DtxValue::Tagged(_, value) => value.serialize(serializer),
+            DtxValue::Array(items) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items {
+                    seq.serialize_element(item)?;
+                }
+                seq.end()
+            }
+            DtxValue::Object(map) => {
+                let mut out = serializer.serialize_map(Some(map.len()))?;
+                for (key, value) in map {
+                    out.serialize_entry(key, value)?;
+                }
+                out.end()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(content: &str, from: Format, to: Format) -> String {
+        DtxValue::parse(content, from, YamlVersion::default())
+            .unwrap()
+            .write(to, &TomlLayout::default(), &OutputStyle::default())
+            .unwrap()
+    }
+
+    #[test]
+    fn test_toml_dates_and_times_round_trip() {
+        let toml = "lunch = 12:30:00\nday = 2024-05-01\nat = 2024-05-01T09:00:00Z\n";
+        let yaml = convert(toml, Format::Toml, Format::Yaml);
+        assert_eq!(
+            yaml,
+            "lunch: 12:30:00\nday: 2024-05-01\nat: 2024-05-01T09:00:00Z\n"
+        );
+        let json = convert(toml, Format::Toml, Format::Json);
+        assert!(json.contains(r#""lunch": "12:30:00""#));
+        assert_eq!(
+            convert(&json, Format::Json, Format::Toml).lines().count(),
+            3
+        );
+
+        // The time stays a TOML time rather than a string
+        let value = DtxValue::parse(toml, Format::Toml, YamlVersion::default()).unwrap();
+        assert_eq!(
+            value.to_toml()["lunch"],
+            toml::Value::Datetime("12:30:00".parse().unwrap())
+        );
+        assert_eq!(convert(toml, Format::Toml, Format::Toml), toml);
+    }
+
+    #[test]
+    fn test_date_like_strings_stay_strings() {
+        let json = r#"{"day": "2024-01-01", "at": "2024-05-01T09:00:00Z"}"#;
+        assert_eq!(
+            convert(json, Format::Json, Format::Toml),
+            "day = \"2024-01-01\"\nat = \"2024-05-01T09:00:00Z\"\n"
+        );
+        let toml = "day = \"2024-01-01\"\n";
+        assert_eq!(convert(toml, Format::Toml, Format::Toml), toml);
+    }
+
+    #[test]
+    fn test_key_order_and_large_integers() {
+        let yaml = "zeta: 18446744073709551615\nalpha: -9223372036854775808\nmid: 1.5\n";
+        let json = convert(yaml, Format::Yaml, Format::Json);
+        assert_eq!(
+            json,
+            "{\n  \"zeta\": 18446744073709551615,\n  \"alpha\": -9223372036854775808,\n  \
+             \"mid\": 1.5\n}"
+        );
+        assert_eq!(convert(&json, Format::Json, Format::Yaml), yaml);
+
+        // TOML stops at i64, so larger integers keep their digits as strings
+        assert_eq!(
+            convert(yaml, Format::Yaml, Format::Toml),
+            "zeta = \"18446744073709551615\"\nalpha = -9223372036854775808\nmid = 1.5\n"
+        );
+    }
+
+    #[test]
+    fn test_yaml_tags() {
+        let yaml = "bucket: !Ref MyBucket\nids: !!set {a: null}\n";
+        let value = DtxValue::parse(yaml, Format::Yaml, YamlVersion::default()).unwrap();
+        let DtxValue::Object(map) = &value else {
+            panic!("expected an object");
+        };
+        assert_eq!(
+            map["bucket"],
+            DtxValue::Tagged("Ref".into(), Box::new(DtxValue::String("MyBucket".into())))
+        );
+        assert!(
+            yaml_format::to_pretty(&value.to_yaml(), &OutputStyle::default())
+                .unwrap()
+                .starts_with("bucket: !Ref MyBucket\n")
+        );
+        assert_eq!(
+            value.into_json(),
+            serde_json::json!({"bucket": "MyBucket", "ids": {"a": null}})
+        );
+    }
+}
@@ -0,0 +1,884 @@
+//! Avro Object Container File handling
+//!
+//! Reading needs nothing but the file, whose header embeds the writer's
+//! schema; writing encodes against a schema given as JSON (an `.avsc` file).
+//! Blocks may be uncompressed or deflate compressed. Union values appear as the
+//! plain value of their branch, bytes and fixed values as base64 text, dates
+//! and timestamps as ISO 8601 strings, and decimals as numbers.
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, SecondsFormat};
+use serde_json::{Map, Number, Value as JsonValue};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::Read;
+
+use super::binary::{float, ByteReader};
+use super::parquet::decimal;
+
+const MAGIC: &[u8] = b"Obj\x01";
+
+/// Nesting deeper than this is treated as corruption
+const MAX_DEPTH: usize = 512;
+
+/// Items an array or map block may declare beyond the bytes left to read;
+/// only zero-sized items (nulls, empty records) need no bytes at all
+const MAX_EMPTY_ITEMS: u64 = 1 << 16;
+
+/// Records per block in written files
+const BLOCK_RECORDS: usize = 1024;
+
+/// A parsed Avro schema
+#[derive(Debug, Clone)]
+pub struct Schema {
+    root: Type,
+    /// Records, enums and fixed types, referred to by [`Type::Named`]
+    named: Vec<Named>,
+    /// Schema JSON embedded in written files
+    json: String,
+}
+
+#[derive(Debug, Clone)]
+enum Type {
+    Null,
+    Boolean,
+    Int,
+    Long,
+    Float,
+    Double,
+    Bytes,
+    String,
+    /// `int` days since the Unix epoch
+    Date,
+    /// `long` milliseconds since the Unix epoch
+    TimestampMillis,
+    /// `long` microseconds since the Unix epoch
+    TimestampMicros,
+    /// `bytes` holding a big-endian two's complement unscaled value
+    Decimal(u32),
+    Array(Box<Type>),
+    Map(Box<Type>),
+    Union(Vec<Type>),
+    Named(usize),
+}
+
+#[derive(Debug, Clone)]
+enum Named {
+    Record {
+        name: String,
+        fields: Vec<Field>,
+    },
+    Enum {
+        name: String,
+        symbols: Vec<String>,
+    },
+    Fixed {
+        name: String,
+        size: usize,
+        decimal: Option<u32>,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Field {
+    name: String,
+    schema: Type,
+    default: Option<JsonValue>,
+}
+
+impl Schema {
+    /// Parse a schema from its JSON form
+    pub fn parse(json: &JsonValue) -> Result<Schema> {
+        let mut parser = SchemaParser::default();
+        let root = parser.parse(json, None)?;
+        Ok(Schema {
+            root,
+            named: parser.named,
+            json: json.to_string(),
+        })
+    }
+
+    fn describe(&self, ty: &Type) -> String {
+        match ty {
+            Type::Null => "null".to_string(),
+            Type::Boolean => "boolean".to_string(),
+            Type::Int => "int".to_string(),
+            Type::Long => "long".to_string(),
+            Type::Float => "float".to_string(),
+            Type::Double => "double".to_string(),
+            Type::Bytes => "bytes (base64)".to_string(),
+            Type::String => "string".to_string(),
+            Type::Date => "date (YYYY-MM-DD or days)".to_string(),
+            Type::TimestampMillis | Type::TimestampMicros => {
+                "timestamp (RFC 3339 or an integer)".to_string()
+            }
+            Type::Decimal(_) => "decimal".to_string(),
+            Type::Array(_) => "array".to_string(),
+            Type::Map(_) => "map (object)".to_string(),
+            Type::Union(branches) => {
+                let names: Vec<String> = branches.iter().map(|b| self.describe(b)).collect();
+                format!("one of [{}]", names.join(", "))
+            }
+            Type::Named(index) => match &self.named[*index] {
+                Named::Record { name, .. } => format!("record {}", name),
+                Named::Enum { name, symbols } => format!("enum {} ({})", name, symbols.join(", ")),
+                Named::Fixed { name, size, .. } => format!("fixed {} ({} bytes)", name, size),
+            },
+        }
+    }
+}
+
+#[derive(Default)]
+struct SchemaParser {
+    named: Vec<Named>,
+    names: HashMap<String, usize>,
+}
+
+impl SchemaParser {
+    fn parse(&mut self, json: &JsonValue, namespace: Option<&str>) -> Result<Type> {
+        match json {
+            JsonValue::String(name) => self.reference(name, namespace),
+            JsonValue::Array(branches) => branches
+                .iter()
+                .map(|branch| self.parse(branch, namespace))
+                .collect::<Result<_>>()
+                .map(Type::Union),
+            JsonValue::Object(obj) => self.complex(obj, namespace),
+            other => bail!("Invalid Avro schema: {}", other),
+        }
+    }
+
+    /// A primitive type, or a named type defined earlier
+    fn reference(&self, name: &str, namespace: Option<&str>) -> Result<Type> {
+        Ok(match name {
+            "null" => Type::Null,
+            "boolean" => Type::Boolean,
+            "int" => Type::Int,
+            "long" => Type::Long,
+            "float" => Type::Float,
+            "double" => Type::Double,
+            "bytes" => Type::Bytes,
+            "string" => Type::String,
+            _ => {
+                let qualified = namespace.map(|ns| format!("{}.{}", ns, name));
+                let index = qualified
+                    .and_then(|qualified| self.names.get(&qualified))
+                    .or_else(|| self.names.get(name))
+                    .with_context(|| format!("Unknown Avro type '{}'", name))?;
+                Type::Named(*index)
+            }
+        })
+    }
+
+    fn complex(&mut self, obj: &Map<String, JsonValue>, namespace: Option<&str>) -> Result<Type> {
+        let kind = obj
+            .get("type")
+            .context("Avro schema object has no \"type\"")?;
+        let JsonValue::String(kind) = kind else {
+            return self.parse(kind, namespace);
+        };
+        let scale = obj.get("scale").and_then(JsonValue::as_u64).unwrap_or(0) as u32;
+        match (
+            kind.as_str(),
+            obj.get("logicalType").and_then(JsonValue::as_str),
+        ) {
+            ("record" | "error" | "enum" | "fixed", logical) => {
+                let decimal = (logical == Some("decimal")).then_some(scale);
+                self.named_type(kind, obj, namespace, decimal)
+            }
+            ("array", _) => {
+                let items = obj
+                    .get("items")
+                    .context("Avro array schema has no \"items\"")?;
+                Ok(Type::Array(Box::new(self.parse(items, namespace)?)))
+            }
+            ("map", _) => {
+                let values = obj
+                    .get("values")
+                    .context("Avro map schema has no \"values\"")?;
+                Ok(Type::Map(Box::new(self.parse(values, namespace)?)))
+            }
+            ("int", Some("date")) => Ok(Type::Date),
+            ("long", Some("timestamp-millis")) => Ok(Type::TimestampMillis),
+            ("long", Some("timestamp-micros")) => Ok(Type::TimestampMicros),
+            ("bytes", Some("decimal")) => Ok(Type::Decimal(scale)),
+            // Other logical types read and write as their underlying type
+            _ => self.reference(kind, namespace),
+        }
+    }
+
+    fn named_type(
+        &mut self,
+        kind: &str,
+        obj: &Map<String, JsonValue>,
+        namespace: Option<&str>,
+        decimal: Option<u32>,
+    ) -> Result<Type> {
+        let name = obj
+            .get("name")
+            .and_then(JsonValue::as_str)
+            .with_context(|| format!("Avro {} schema has no \"name\"", kind))?;
+        let namespace = obj
+            .get("namespace")
+            .and_then(JsonValue::as_str)
+            .or(namespace)
+            .filter(|ns| !ns.is_empty() && !name.contains('.'));
+        let full_name = match namespace {
+            Some(ns) => format!("{}.{}", ns, name),
+            None => name.to_string(),
+        };
+
+        // Registered before the fields are parsed, so a record can contain itself
+        let index = self.named.len();
+        self.named.push(Named::Fixed {
+            name: full_name.clone(),
+            size: 0,
+            decimal: None,
+        });
+        self.names.insert(full_name.clone(), index);
+
+        self.named[index] = match kind {
+            "enum" => Named::Enum {
+                name: full_name,
+                symbols: obj
+                    .get("symbols")
+                    .and_then(JsonValue::as_array)
+                    .context("Avro enum schema has no \"symbols\"")?
+                    .iter()
+                    .map(|symbol| symbol.as_str().map(str::to_string))
+                    .collect::<Option<_>>()
+                    .context("Avro enum symbols must be strings")?,
+            },
+            "fixed" => Named::Fixed {
+                size: obj
+                    .get("size")
+                    .and_then(JsonValue::as_u64)
+                    .context("Avro fixed schema has no \"size\"")? as usize,
+                name: full_name,
+                decimal,
+            },
+            _ => {
+                let inner = full_name.rsplit_once('.').map(|(ns, _)| ns.to_string());
+                let mut fields = Vec::new();
+                let specs = obj
+                    .get("fields")
+                    .and_then(JsonValue::as_array)
+                    .context("Avro record schema has no \"fields\"")?;
+                for spec in specs {
+                    let name = spec
+                        .get("name")
+                        .and_then(JsonValue::as_str)
+                        .context("Avro record field has no \"name\"")?;
+                    let ty = spec
+                        .get("type")
+                        .with_context(|| format!("Avro field '{}' has no \"type\"", name))?;
+                    let schema = self
+                        .parse(ty, inner.as_deref())
+                        .with_context(|| format!("In field '{}' of record {}", name, full_name))?;
+                    fields.push(Field {
+                        name: name.to_string(),
+                        schema,
+                        default: spec.get("default").cloned(),
+                    });
+                }
+                Named::Record {
+                    name: full_name,
+                    fields,
+                }
+            }
+        };
+        Ok(Type::Named(index))
+    }
+}
+
+// ============================================================================
+// Reading
+// ============================================================================
+
+/// Parse an Avro container file into an array of its records
+pub fn parse(bytes: &[u8]) -> Result<JsonValue> {
+    let body = bytes
+        .strip_prefix(MAGIC)
+        .context("Not an Avro container file (missing Obj magic)")?;
+    let mut reader = Decoder::new(body);
+    let metadata = reader
+        .metadata()
+        .context("Failed to read Avro file header")?;
+    let schema_json = metadata
+        .get("avro.schema")
+        .context("Avro file header has no schema")?;
+    let schema = Schema::parse(
+        &serde_json::from_slice(schema_json).context("Avro file schema is not valid JSON")?,
+    )
+    .context("Invalid schema in Avro file header")?;
+    let codec = metadata
+        .get("avro.codec")
+        .map(|codec| String::from_utf8_lossy(codec).into_owned());
+    let sync = reader.input.take(16)?;
+
+    let mut records = Vec::new();
+    while !reader.input.is_empty() {
+        let count = reader.long()?;
+        let size = reader.long()?;
+        let (Ok(count), Ok(size)) = (u64::try_from(count), usize::try_from(size)) else {
+            bail!("Invalid Avro block header");
+        };
+        let data = decompress(codec.as_deref(), reader.input.take(size)?)?;
+        if reader.input.take(16)? != sync {
+            bail!("Avro block does not end with the file's sync marker");
+        }
+        let mut block = Decoder::new(&data);
+        block.check_count(count)?;
+        for _ in 0..count {
+            let record = block
+                .value(&schema, &schema.root, 0)
+                .with_context(|| format!("Failed to read Avro record {}", records.len() + 1))?;
+            records.push(record);
+        }
+    }
+    Ok(JsonValue::Array(records))
+}
+
+fn decompress<'a>(codec: Option<&str>, data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    match codec {
+        None | Some("null") => Ok(Cow::Borrowed(data)),
+        Some("deflate") => {
+            let mut out = Vec::new();
+            flate2::read::DeflateDecoder::new(data)
+                .read_to_end(&mut out)
+                .context("Failed to inflate Avro block")?;
+            Ok(Cow::Owned(out))
+        }
+        Some(other) => bail!(
+            "Avro codec '{}' is not supported; only null and deflate are",
+            other
+        ),
+    }
+}
+
+struct Decoder<'a> {
+    input: ByteReader<'a>,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Decoder {
+            input: ByteReader::new(bytes, "Avro"),
+        }
+    }
+
+    /// Zigzag-encoded variable-length integer
+    fn long(&mut self) -> Result<i64> {
+        let mut raw = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.input.u8()?;
+            raw |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok((raw >> 1) as i64 ^ -((raw & 1) as i64));
+            }
+        }
+        bail!("Avro integer is longer than 10 bytes")
+    }
+
+    fn int(&mut self) -> Result<i32> {
+        let value = self.long()?;
+        i32::try_from(value).with_context(|| format!("Avro int {} is out of range", value))
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8]> {
+        let len = self.long()?;
+        let len = usize::try_from(len).context("Negative Avro length")?;
+        self.input.take(len)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec()).context("Avro string is not valid UTF-8")
+    }
+
+    /// Item count of the next array or map block; 0 after the last one
+    fn block_count(&mut self) -> Result<u64> {
+        let count = self.long()?;
+        if count < 0 {
+            // A negative count is followed by the block's size in bytes
+            self.long()?;
+        }
+        let count = count.unsigned_abs();
+        self.check_count(count)?;
+        Ok(count)
+    }
+
+    fn check_count(&self, count: u64) -> Result<()> {
+        let remaining = self.input.remaining() as u64;
+        if count > remaining + MAX_EMPTY_ITEMS {
+            bail!("Avro block of {} items exceeds the data left", count);
+        }
+        Ok(())
+    }
+
+    /// The `avro.*` header map of byte values
+    fn metadata(&mut self) -> Result<HashMap<String, Vec<u8>>> {
+        let mut metadata = HashMap::new();
+        loop {
+            let count = self.block_count()?;
+            if count == 0 {
+                return Ok(metadata);
+            }
+            for _ in 0..count {
+                let key = self.string()?;
+                metadata.insert(key, self.bytes()?.to_vec());
+            }
+        }
+    }
+
+    fn value(&mut self, schema: &Schema, ty: &Type, depth: usize) -> Result<JsonValue> {
+        if depth > MAX_DEPTH {
+            bail!("Avro data nests deeper than {} levels", MAX_DEPTH);
+        }
+        Ok(match ty {
+            Type::Null => JsonValue::Null,
+            Type::Boolean => JsonValue::Bool(self.input.u8()? != 0),
+            Type::Int => JsonValue::from(self.int()?),
+            Type::Long => JsonValue::from(self.long()?),
+            Type::Float => float(f32::from_le_bytes(self.input.take(4)?.try_into()?) as f64),
+            Type::Double => float(f64::from_le_bytes(self.input.take(8)?.try_into()?)),
+            Type::Bytes => JsonValue::String(BASE64.encode(self.bytes()?)),
+            Type::String => JsonValue::String(self.string()?),
+            Type::Date => {
+                let days = self.int()?;
+                NaiveDate::from_ymd_opt(1970, 1, 1)
+                    .and_then(|epoch| epoch.checked_add_signed(chrono::Duration::days(days.into())))
+                    .map_or(JsonValue::from(days), |date| {
+                        JsonValue::String(date.to_string())
+                    })
+            }
+            Type::TimestampMillis => {
+                let millis = self.long()?;
+                timestamp(DateTime::from_timestamp_millis(millis), millis)
+            }
+            Type::TimestampMicros => {
+                let micros = self.long()?;
+                timestamp(DateTime::from_timestamp_micros(micros), micros)
+            }
+            Type::Decimal(scale) => decimal(from_twos_complement(self.bytes()?)?, *scale as i64),
+            Type::Array(item) => {
+                let mut items = Vec::new();
+                loop {
+                    let count = self.block_count()?;
+                    if count == 0 {
+                        break JsonValue::Array(items);
+                    }
+                    for _ in 0..count {
+                        items.push(self.value(schema, item, depth + 1)?);
+                    }
+                }
+            }
+            Type::Map(values) => {
+                let mut map = Map::new();
+                loop {
+                    let count = self.block_count()?;
+                    if count == 0 {
+                        break JsonValue::Object(map);
+                    }
+                    for _ in 0..count {
+                        let key = self.string()?;
+                        map.insert(key, self.value(schema, values, depth + 1)?);
+                    }
+                }
+            }
+            Type::Union(branches) => {
+                let index = self.long()?;
+                let branch = usize::try_from(index)
+                    .ok()
+                    .and_then(|index| branches.get(index))
+                    .with_context(|| format!("Avro union branch {} does not exist", index))?;
+                self.value(schema, branch, depth + 1)?
+            }
+            Type::Named(index) => match &schema.named[*index] {
+                Named::Record { fields, .. } => {
+                    let mut record = Map::new();
+                    for field in fields {
+                        let value = self.value(schema, &field.schema, depth + 1)?;
+                        record.insert(field.name.clone(), value);
+                    }
+                    JsonValue::Object(record)
+                }
+                Named::Enum { name, symbols } => {
+                    let index = self.int()?;
+                    let symbol = usize::try_from(index)
+                        .ok()
+                        .and_then(|index| symbols.get(index))
+                        .with_context(|| format!("Enum {} has no symbol {}", name, index))?;
+                    JsonValue::String(symbol.clone())
+                }
+                Named::Fixed {
+                    size,
+                    decimal: scale,
+                    ..
+                } => {
+                    let bytes = self.input.take(*size)?;
+                    match scale {
+                        Some(scale) => decimal(from_twos_complement(bytes)?, *scale as i64),
+                        None => JsonValue::String(BASE64.encode(bytes)),
+                    }
+                }
+            },
+        })
+    }
+}
+
+/// An RFC 3339 string, or the raw integer if it is out of chrono's range
+fn timestamp(time: Option<DateTime<chrono::Utc>>, raw: i64) -> JsonValue {
+    time.map_or(JsonValue::from(raw), |time| {
+        JsonValue::String(time.to_rfc3339_opts(SecondsFormat::AutoSi, true))
+    })
+}
+
+fn from_twos_complement(bytes: &[u8]) -> Result<i128> {
+    if bytes.len() > 16 {
+        bail!("Avro decimal of {} bytes is too large", bytes.len());
+    }
+    let sign = match bytes.first() {
+        Some(byte) if byte & 0x80 != 0 => 0xff,
+        _ => 0,
+    };
+    let mut buf = [sign; 16];
+    buf[16 - bytes.len()..].copy_from_slice(bytes);
+    Ok(i128::from_be_bytes(buf))
+}
+
+// ============================================================================
+// Writing
+// ============================================================================
+
+/// Write values as an Avro container file; a top-level array holds the records
+pub fn to_vec(value: &JsonValue, schema: &Schema) -> Result<Vec<u8>> {
+    let records = match value {
+        JsonValue::Array(items) => items.as_slice(),
+        other => std::slice::from_ref(other),
+    };
+
+    let mut out = MAGIC.to_vec();
+    write_long(&mut out, 2);
+    write_bytes(&mut out, b"avro.schema");
+    write_bytes(&mut out, schema.json.as_bytes());
+    write_bytes(&mut out, b"avro.codec");
+    write_bytes(&mut out, b"null");
+    write_long(&mut out, 0);
+    let sync = sync_marker(&schema.json);
+    out.extend(sync);
+
+    for (chunk_index, chunk) in records.chunks(BLOCK_RECORDS).enumerate() {
+        let mut block = Vec::new();
+        for (i, record) in chunk.iter().enumerate() {
+            encode(schema, &schema.root, record, &mut block).with_context(|| {
+                format!(
+                    "Record {} does not match the Avro schema",
+                    chunk_index * BLOCK_RECORDS + i + 1
+                )
+            })?;
+        }
+        write_long(&mut out, chunk.len() as i64);
+        write_long(&mut out, block.len() as i64);
+        out.extend(block);
+        out.extend(sync);
+    }
+    Ok(out)
+}
+
+/// The marker separating blocks; derived from the schema rather than random so
+/// that the same input always gives the same file
+fn sync_marker(schema: &str) -> [u8; 16] {
+    let mut marker = [0u8; 16];
+    for (i, half) in marker.chunks_mut(8).enumerate() {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        (schema, i).hash(&mut hasher);
+        half.copy_from_slice(&hasher.finish().to_le_bytes());
+    }
+    marker
+}
+
+fn encode(schema: &Schema, ty: &Type, value: &JsonValue, out: &mut Vec<u8>) -> Result<()> {
+    match (ty, value) {
+        (Type::Null, JsonValue::Null) => {}
+        (Type::Boolean, JsonValue::Bool(b)) => out.push(u8::from(*b)),
+        (Type::Int, JsonValue::Number(n)) => write_long(out, int(n)?.into()),
+        (Type::Long, JsonValue::Number(n)) => write_long(out, long(n)?),
+        (Type::Float, JsonValue::Number(n)) => {
+            out.extend((n.as_f64().unwrap_or_default() as f32).to_le_bytes())
+        }
+        (Type::Double, JsonValue::Number(n)) => {
+            out.extend(n.as_f64().unwrap_or_default().to_le_bytes())
+        }
+        (Type::Bytes, JsonValue::String(s)) => write_bytes(out, &base64(s)?),
+        (Type::String, JsonValue::String(s)) => write_bytes(out, s.as_bytes()),
+        (Type::Date, JsonValue::Number(n)) => write_long(out, int(n)?.into()),
+        (Type::Date, JsonValue::String(s)) => {
+            let date = NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .with_context(|| format!("'{}' is not a date (YYYY-MM-DD)", s))?;
+            let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid date");
+            write_long(out, (date - epoch).num_days());
+        }
+        (Type::TimestampMillis | Type::TimestampMicros, JsonValue::Number(n)) => {
+            write_long(out, long(n)?)
+        }
+        (Type::TimestampMillis | Type::TimestampMicros, JsonValue::String(s)) => {
+            let time = DateTime::parse_from_rfc3339(s)
+                .with_context(|| format!("'{}' is not an RFC 3339 timestamp", s))?;
+            match ty {
+                Type::TimestampMillis => write_long(out, time.timestamp_millis()),
+                _ => write_long(out, time.timestamp_micros()),
+            }
+        }
+        (Type::Decimal(scale), JsonValue::Number(_) | JsonValue::String(_)) => {
+            write_bytes(out, &twos_complement(unscaled(value, *scale)?, None)?)
+        }
+        (Type::Array(item), JsonValue::Array(items)) => {
+            if !items.is_empty() {
+                write_long(out, items.len() as i64);
+                for (i, value) in items.iter().enumerate() {
+                    encode(schema, item, value, out).with_context(|| format!("In item {}", i))?;
+                }
+            }
+            write_long(out, 0);
+        }
+        (Type::Map(values), JsonValue::Object(map)) => {
+            if !map.is_empty() {
+                write_long(out, map.len() as i64);
+                for (key, value) in map {
+                    write_bytes(out, key.as_bytes());
+                    encode(schema, values, value, out)
+                        .with_context(|| format!("In map key '{}'", key))?;
+                }
+            }
+            write_long(out, 0);
+        }
+        (Type::Union(branches), _) => {
+            // The first branch the value can be written as
+            for (i, branch) in branches.iter().enumerate() {
+                let mut encoded = Vec::new();
+                if encode(schema, branch, value, &mut encoded).is_ok() {
+                    write_long(out, i as i64);
+                    out.extend(encoded);
+                    return Ok(());
+                }
+            }
+            bail!("expected {}, got {}", schema.describe(ty), describe(value));
+        }
+        (Type::Named(index), _) => match (&schema.named[*index], value) {
+            (Named::Record { fields, .. }, JsonValue::Object(obj)) => {
+                for field in fields {
+                    let value = obj
+                        .get(&field.name)
+                        .or(field.default.as_ref())
+                        .unwrap_or(&JsonValue::Null);
+                    encode(schema, &field.schema, value, out)
+                        .with_context(|| format!("In field '{}'", field.name))?;
+                }
+            }
+            (Named::Enum { symbols, .. }, JsonValue::String(s))
+                if symbols.iter().any(|symbol| symbol == s) =>
+            {
+                let index = symbols.iter().position(|symbol| symbol == s).unwrap_or(0);
+                write_long(out, index as i64);
+            }
+            (Named::Fixed { size, decimal, .. }, JsonValue::Number(_) | JsonValue::String(_)) => {
+                let bytes = match decimal {
+                    Some(scale) => twos_complement(unscaled(value, *scale)?, Some(*size))?,
+                    None => base64(value.as_str().unwrap_or_default())?,
+                };
+                if bytes.len() != *size {
+                    bail!("expected {} bytes, got {}", size, bytes.len());
+                }
+                out.extend(bytes);
+            }
+            _ => bail!("expected {}, got {}", schema.describe(ty), describe(value)),
+        },
+        _ => bail!("expected {}, got {}", schema.describe(ty), describe(value)),
+    }
+    Ok(())
+}
+
+fn describe(value: &JsonValue) -> String {
+    match value {
+        JsonValue::Null => "null".to_string(),
+        JsonValue::Bool(b) => b.to_string(),
+        JsonValue::Number(n) => n.to_string(),
+        JsonValue::String(s) => format!("\"{}\"", s),
+        JsonValue::Array(_) => "an array".to_string(),
+        JsonValue::Object(_) => "an object".to_string(),
+    }
+}
+
+fn long(n: &Number) -> Result<i64> {
+    n.as_i64()
+        .with_context(|| format!("expected an integer, got {}", n))
+}
+
+fn int(n: &Number) -> Result<i32> {
+    i32::try_from(long(n)?).with_context(|| format!("{} is out of range for an int", n))
+}
+
+fn base64(text: &str) -> Result<Vec<u8>> {
+    BASE64
+        .decode(text)
+        .with_context(|| format!("'{}' is not base64", text))
+}
+
+/// `value * 10^scale` as an integer; more decimal places than `scale` is an error
+fn unscaled(value: &JsonValue, scale: u32) -> Result<i128> {
+    let text = match value {
+        JsonValue::String(s) => s.trim().to_string(),
+        other => other.to_string(),
+    };
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.as_str()),
+    };
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, ""));
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > scale as usize {
+        bail!("{} has more than {} decimal places", text, scale);
+    }
+    let padded = format!("{}{:0<width$}", whole, fraction, width = scale as usize);
+    let unscaled: i128 = padded
+        .parse()
+        .with_context(|| format!("'{}' is not a decimal number", text))?;
+    Ok(if negative { -unscaled } else { unscaled })
+}
+
+/// Big-endian two's complement in the fewest bytes, or sign-extended to `size`
+fn twos_complement(n: i128, size: Option<usize>) -> Result<Vec<u8>> {
+    let bytes = n.to_be_bytes();
+    let sign = if n < 0 { 0xff } else { 0 };
+    // Leading sign bytes are redundant while the next byte carries the sign bit
+    let mut start = 0;
+    while start < 15 && bytes[start] == sign && (bytes[start + 1] ^ sign) & 0x80 == 0 {
+        start += 1;
+    }
+    let minimal = &bytes[start..];
+    match size {
+        None => Ok(minimal.to_vec()),
+        Some(size) if minimal.len() <= size => {
+            let mut out = vec![sign; size - minimal.len()];
+            out.extend(minimal);
+            Ok(out)
+        }
+        Some(size) => bail!("decimal {} does not fit in {} bytes", n, size),
+    }
+}
+
+fn write_long(out: &mut Vec<u8>, n: i64) {
+    let mut zigzag = ((n << 1) ^ (n >> 63)) as u64;
+    while zigzag >= 0x80 {
+        out.push((zigzag as u8 & 0x7f) | 0x80);
+        zigzag >>= 7;
+    }
+    out.push(zigzag as u8);
+}
+
+fn write_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_long(out, bytes.len() as i64);
+    out.extend(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema(json: JsonValue) -> Schema {
+        Schema::parse(&json).unwrap()
+    }
+
+    #[test]
+    fn test_spec_encodings() {
+        let mut out = Vec::new();
+        for n in [0, -1, 1, -2, 64, -65] {
+            write_long(&mut out, n);
+        }
+        assert_eq!(out, [0x00, 0x01, 0x02, 0x03, 0x80, 0x01, 0x81, 0x01]);
+
+        // The record example from the specification
+        let test = schema(json!({"type": "record", "name": "test", "fields": [
+            {"name": "a", "type": "long"}, {"name": "b", "type": "string"}
+        ]}));
+        let mut out = Vec::new();
+        encode(&test, &test.root, &json!({"a": 27, "b": "foo"}), &mut out).unwrap();
+        assert_eq!(out, [0x36, 0x06, b'f', b'o', b'o']);
+
+        assert_eq!(twos_complement(-129, None).unwrap(), [0xff, 0x7f]);
+        assert_eq!(twos_complement(128, Some(3)).unwrap(), [0x00, 0x00, 0x80]);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let user = schema(json!({
+            "type": "record", "name": "User", "namespace": "com.example",
+            "fields": [
+                {"name": "id", "type": "long"},
+                {"name": "name", "type": ["null", "string"]},
+                {"name": "role", "type": {"type": "enum", "name": "Role", "symbols": ["ADMIN", "USER"]}},
+                {"name": "tags", "type": {"type": "array", "items": "string"}},
+                {"name": "scores", "type": {"type": "map", "values": "double"}},
+                {"name": "born", "type": {"type": "int", "logicalType": "date"}},
+                {"name": "seen", "type": {"type": "long", "logicalType": "timestamp-millis"}},
+                {"name": "balance", "type": {"type": "bytes", "logicalType": "decimal",
+                                             "precision": 9, "scale": 2}},
+                {"name": "hash", "type": {"type": "fixed", "name": "Hash", "size": 2}},
+                {"name": "manager", "type": ["null", "User"], "default": null},
+                {"name": "active", "type": "boolean", "default": true}
+            ]
+        }));
+        let records = json!([
+            {"id": 1, "name": "Ann", "role": "ADMIN", "tags": ["a", "b"], "scores": {"x": 1.5},
+             "born": "1990-05-17", "seen": "2024-01-02T03:04:05.678Z", "balance": -12.5,
+             "hash": "AQI=", "manager": null, "active": false},
+            {"id": 2, "name": null, "role": "USER", "tags": [], "scores": {},
+             "born": "1969-12-31", "seen": "1970-01-01T00:00:00Z", "balance": "0.07",
+             "hash": "AAA=", "manager": {"id": 1, "role": "ADMIN", "tags": [], "scores": {},
+             "born": "2000-01-01", "seen": 0, "balance": 1, "hash": "AAA="}}
+        ]);
+        let bytes = to_vec(&records, &user).unwrap();
+        assert_eq!(to_vec(&records, &user).unwrap(), bytes);
+        let parsed = parse(&bytes).unwrap();
+        assert_eq!(parsed[0]["seen"], json!("2024-01-02T03:04:05.678Z"));
+        assert_eq!(parsed[0]["balance"], json!(-12.5));
+        assert_eq!(parsed[1]["born"], json!("1969-12-31"));
+        assert_eq!(parsed[1]["balance"], json!(0.07));
+        assert_eq!(parsed[1]["manager"]["name"], JsonValue::Null);
+        assert_eq!(parsed[1]["manager"]["active"], json!(true));
+        assert_eq!(parsed[1]["active"], json!(true));
+        assert_eq!(parsed[0]["scores"], json!({"x": 1.5}));
+        assert_eq!(parsed[0]["hash"], json!("AQI="));
+    }
+
+    #[test]
+    fn test_errors() {
+        let point = schema(json!({"type": "record", "name": "Point", "fields": [
+            {"name": "x", "type": "int"}, {"name": "y", "type": "int"}
+        ]}));
+        let err = to_vec(&json!([{"x": 1, "y": 2}, {"x": "one", "y": 2}]), &point).unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("Record 2"), "{}", message);
+        assert!(message.contains("In field 'x'"), "{}", message);
+        assert!(message.contains("expected int, got \"one\""), "{}", message);
+        assert!(to_vec(&json!({"x": 1}), &point).is_err());
+
+        assert!(
+            Schema::parse(&json!({"type": "record", "name": "A", "fields": [
+                {"name": "b", "type": "Missing"}
+            ]}))
+            .is_err()
+        );
+        assert!(parse(b"PAR1").is_err());
+
+        let mut bytes = to_vec(&json!({"x": 1, "y": 2}), &point).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(parse(&bytes).is_err());
+    }
+}
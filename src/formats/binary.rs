@@ -0,0 +1,93 @@
+//! Helpers shared by the binary format readers (Avro, BSON, MessagePack, Parquet)
+
+use anyhow::{Context, Result};
+use serde_json::{Number, Value as JsonValue};
+
+/// JSON has no NaN or infinity, so those become null
+pub fn float(f: f64) -> JsonValue {
+    Number::from_f64(f).map_or(JsonValue::Null, JsonValue::Number)
+}
+
+/// Bounds-checked cursor over the bytes of one format's data
+pub struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    /// Format named in errors, such as "BSON"
+    format: &'static str,
+}
+
+impl<'a> ByteReader<'a> {
+    pub fn new(bytes: &'a [u8], format: &'static str) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            format,
+        }
+    }
+
+    /// Offset of the next unread byte
+    pub fn pos(&self) -> usize {
+        self.pos
+    }
+
+    /// The unread bytes, without consuming them
+    pub fn rest(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.bytes.len()
+    }
+
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.bytes.len())
+            .with_context(|| {
+                format!(
+                    "Unexpected end of {} data at byte {}",
+                    self.format, self.pos
+                )
+            })?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    pub fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        Ok(self.take(N)?.try_into().expect("slice has N bytes"))
+    }
+
+    pub fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_take_past_end() {
+        let mut reader = ByteReader::new(&[1, 2, 3], "Test");
+        assert_eq!(reader.take(2).unwrap(), [1, 2]);
+        let err = reader.take(2).unwrap_err();
+        assert_eq!(err.to_string(), "Unexpected end of Test data at byte 2");
+        assert_eq!(reader.pos(), 2);
+        assert_eq!(reader.u8().unwrap(), 3);
+        assert!(reader.is_empty());
+        assert!(reader.take(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_float() {
+        assert_eq!(float(0.5), JsonValue::from(0.5));
+        assert_eq!(float(f64::NAN), JsonValue::Null);
+        assert_eq!(float(f64::INFINITY), JsonValue::Null);
+    }
+}
@@ -0,0 +1,648 @@
+//! BSON format handling
+//!
+//! A file holds one or more concatenated documents, as written by `mongodump`:
+//! a single document reads as an object and several as an array, and writing
+//! an array emits one document per item. Types JSON lacks use MongoDB's relaxed
+//! Extended JSON forms, such as `{"$oid": "..."}`, `{"$date": "..."}` and
+//! `{"$numberDecimal": "..."}`, and those forms are written back as BSON types.
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, Datelike, SecondsFormat};
+use serde_json::{json, Map, Value as JsonValue};
+
+use super::binary::{float, ByteReader};
+
+/// Nesting deeper than this is rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 512;
+
+/// Exponent bias of IEEE 754 decimal128
+const DECIMAL_BIAS: i32 = 6176;
+
+const DOUBLE: u8 = 0x01;
+const STRING: u8 = 0x02;
+const DOCUMENT: u8 = 0x03;
+const ARRAY: u8 = 0x04;
+const BINARY: u8 = 0x05;
+const UNDEFINED: u8 = 0x06;
+const OBJECT_ID: u8 = 0x07;
+const BOOLEAN: u8 = 0x08;
+const DATETIME: u8 = 0x09;
+const NULL: u8 = 0x0a;
+const REGEX: u8 = 0x0b;
+const DB_POINTER: u8 = 0x0c;
+const CODE: u8 = 0x0d;
+const SYMBOL: u8 = 0x0e;
+const CODE_WITH_SCOPE: u8 = 0x0f;
+const INT32: u8 = 0x10;
+const TIMESTAMP: u8 = 0x11;
+const INT64: u8 = 0x12;
+const DECIMAL128: u8 = 0x13;
+const MIN_KEY: u8 = 0xff;
+const MAX_KEY: u8 = 0x7f;
+
+/// Parse concatenated BSON documents
+pub fn parse(bytes: &[u8]) -> Result<JsonValue> {
+    let mut decoder = Decoder {
+        input: ByteReader::new(bytes, "BSON"),
+    };
+    let mut documents = Vec::new();
+    while !decoder.input.is_empty() {
+        let document = decoder
+            .document(0)
+            .with_context(|| format!("Failed to read BSON document {}", documents.len() + 1))?;
+        documents.push(JsonValue::Object(document));
+    }
+    Ok(match documents.len() {
+        1 => documents.remove(0),
+        _ => JsonValue::Array(documents),
+    })
+}
+
+/// Encode an object as one BSON document, or an array as one per item
+pub fn to_vec(value: &JsonValue) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    match value {
+        JsonValue::Object(obj) => encode_document(obj.iter(), &mut output, 0)?,
+        JsonValue::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                let JsonValue::Object(obj) = item else {
+                    bail!("BSON documents must be objects; item {} is not", i);
+                };
+                encode_document(obj.iter(), &mut output, 0)?;
+            }
+        }
+        _ => bail!("BSON output requires an object or an array of objects"),
+    }
+    Ok(output)
+}
+
+struct Decoder<'a> {
+    input: ByteReader<'a>,
+}
+
+impl<'a> Decoder<'a> {
+    fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_le_bytes(self.input.array()?))
+    }
+
+    fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_le_bytes(self.input.array()?))
+    }
+
+    fn cstring(&mut self) -> Result<String> {
+        let len = self
+            .input
+            .rest()
+            .iter()
+            .position(|&b| b == 0)
+            .context("Unterminated BSON key or C string")?;
+        let text =
+            std::str::from_utf8(self.input.take(len)?).context("BSON key is not valid UTF-8")?;
+        self.input.take(1)?;
+        Ok(text.to_string())
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.i32()?;
+        let len = usize::try_from(len)
+            .ok()
+            .filter(|&len| len > 0)
+            .with_context(|| format!("Invalid BSON string length {}", len))?;
+        let bytes = self.input.take(len)?;
+        let (text, terminator) = bytes.split_at(len - 1);
+        if terminator != [0] {
+            bail!("BSON string is not null-terminated");
+        }
+        String::from_utf8(text.to_vec()).context("BSON string is not valid UTF-8")
+    }
+
+    /// Elements of a document (or array), checked against its length prefix
+    fn elements(&mut self, depth: usize) -> Result<Vec<(String, JsonValue)>> {
+        if depth > MAX_DEPTH {
+            bail!("BSON data nested deeper than {} levels", MAX_DEPTH);
+        }
+        let start = self.input.pos();
+        let available = self.input.remaining();
+        let len = self.i32()?;
+        let end = usize::try_from(len)
+            .ok()
+            .filter(|len| (5..=available).contains(len))
+            .map(|len| start + len)
+            .with_context(|| format!("Invalid BSON document length {} at byte {}", len, start))?;
+        let mut elements = Vec::new();
+        loop {
+            let kind = self.input.u8()?;
+            if kind == 0 {
+                break;
+            }
+            let key = self.cstring()?;
+            let value = self
+                .element(kind, depth)
+                .with_context(|| format!("In field '{}'", key))?;
+            elements.push((key, value));
+        }
+        if self.input.pos() != end {
+            bail!("BSON document at byte {} does not match its length", start);
+        }
+        Ok(elements)
+    }
+
+    fn document(&mut self, depth: usize) -> Result<Map<String, JsonValue>> {
+        Ok(self.elements(depth)?.into_iter().collect())
+    }
+
+    fn element(&mut self, kind: u8, depth: usize) -> Result<JsonValue> {
+        Ok(match kind {
+            DOUBLE => float(f64::from_le_bytes(self.input.array()?)),
+            STRING | SYMBOL => JsonValue::String(self.string()?),
+            DOCUMENT => JsonValue::Object(self.document(depth + 1)?),
+            ARRAY => JsonValue::Array(
+                self.elements(depth + 1)?
+                    .into_iter()
+                    .map(|(_, value)| value)
+                    .collect(),
+            ),
+            BINARY => {
+                let len = usize::try_from(self.i32()?).context("Negative BSON binary length")?;
+                let subtype = self.input.u8()?;
+                let data = self.input.take(len)?;
+                let subtype = format!("{:02x}", subtype);
+                json!({"$binary": {"base64": BASE64.encode(data), "subType": subtype}})
+            }
+            UNDEFINED => json!({"$undefined": true}),
+            OBJECT_ID => json!({"$oid": hex(self.input.take(12)?)}),
+            BOOLEAN => JsonValue::Bool(self.input.u8()? != 0),
+            DATETIME => date(self.i64()?),
+            NULL => JsonValue::Null,
+            REGEX => {
+                let pattern = self.cstring()?;
+                let options = self.cstring()?;
+                json!({"$regularExpression": {"pattern": pattern, "options": options}})
+            }
+            DB_POINTER => {
+                let namespace = self.string()?;
+                let id = hex(self.input.take(12)?);
+                json!({"$dbPointer": {"$ref": namespace, "$id": {"$oid": id}}})
+            }
+            CODE => json!({"$code": self.string()?}),
+            CODE_WITH_SCOPE => {
+                self.i32()?;
+                let code = self.string()?;
+                let scope = self.document(depth + 1)?;
+                json!({"$code": code, "$scope": scope})
+            }
+            INT32 => JsonValue::from(self.i32()?),
+            TIMESTAMP => {
+                let increment = u32::from_le_bytes(self.input.array()?);
+                let time = u32::from_le_bytes(self.input.array()?);
+                json!({"$timestamp": {"t": time, "i": increment}})
+            }
+            INT64 => JsonValue::from(self.i64()?),
+            DECIMAL128 => json!({"$numberDecimal": decimal128_to_string(self.input.array()?)}),
+            MIN_KEY => json!({"$minKey": 1}),
+            MAX_KEY => json!({"$maxKey": 1}),
+            other => bail!("Unknown BSON element type 0x{:02x}", other),
+        })
+    }
+}
+
+/// Relaxed form: an ISO 8601 string for years 1970 to 9999, milliseconds otherwise
+fn date(millis: i64) -> JsonValue {
+    match DateTime::from_timestamp_millis(millis) {
+        Some(time) if (1970..=9999).contains(&time.year()) => {
+            json!({"$date": time.to_rfc3339_opts(SecondsFormat::Millis, true)})
+        }
+        _ => json!({"$date": {"$numberLong": millis.to_string()}}),
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+// ============================================================================
+// Decimal128
+// ============================================================================
+
+/// Render a decimal128 value the way MongoDB tools do
+fn decimal128_to_string(bytes: [u8; 16]) -> String {
+    let low = u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes"));
+    let high = u64::from_le_bytes(bytes[8..].try_into().expect("8 bytes"));
+    let sign = if high >> 63 == 1 { "-" } else { "" };
+    match (high >> 58) & 0x1f {
+        0x1f => return "NaN".to_string(),
+        0x1e => return format!("{}Infinity", sign),
+        _ => {}
+    }
+    let (exponent, coefficient) = if (high >> 61) & 0b11 == 0b11 {
+        // This form's coefficients all exceed 34 digits, which means zero
+        (((high >> 47) & 0x3fff) as i32, 0)
+    } else {
+        let coefficient = (u128::from(high & 0x1_ffff_ffff_ffff) << 64) | u128::from(low);
+        (((high >> 49) & 0x3fff) as i32, coefficient)
+    };
+    let exponent = exponent - DECIMAL_BIAS;
+    let coefficient = if coefficient >= 10u128.pow(34) {
+        0
+    } else {
+        coefficient
+    };
+
+    let digits = coefficient.to_string();
+    let adjusted = exponent + digits.len() as i32 - 1;
+    let text = if exponent <= 0 && adjusted >= -6 {
+        let point = digits.len() as i32 + exponent;
+        if exponent == 0 {
+            digits
+        } else if point <= 0 {
+            format!("0.{}{}", "0".repeat(point.unsigned_abs() as usize), digits)
+        } else {
+            let (whole, fraction) = digits.split_at(point as usize);
+            format!("{}.{}", whole, fraction)
+        }
+    } else {
+        let (first, rest) = digits.split_at(1);
+        let fraction = if rest.is_empty() {
+            String::new()
+        } else {
+            format!(".{}", rest)
+        };
+        format!(
+            "{}{}E{}{}",
+            first,
+            fraction,
+            if adjusted >= 0 { "+" } else { "" },
+            adjusted
+        )
+    };
+    format!("{}{}", sign, text)
+}
+
+fn decimal128_from_str(text: &str) -> Result<[u8; 16]> {
+    let (negative, unsigned) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let sign = if negative { 1u64 << 63 } else { 0 };
+    let high = match unsigned.to_ascii_lowercase().as_str() {
+        "nan" => Some(0x7c00_0000_0000_0000),
+        "inf" | "infinity" => Some(sign | 0x7800_0000_0000_0000),
+        _ => None,
+    };
+    if let Some(high) = high {
+        let mut bytes = [0u8; 16];
+        bytes[8..].copy_from_slice(&high.to_le_bytes());
+        return Ok(bytes);
+    }
+
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent.parse::<i32>().ok()),
+        None => (unsigned, Some(0)),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = format!("{}{}", whole, fraction);
+    let valid = !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit());
+    let (Some(exponent), true) = (exponent, valid) else {
+        bail!("'{}' is not a decimal number", text);
+    };
+    let digits = digits.trim_start_matches('0');
+    if digits.len() > 34 {
+        bail!("'{}' has more than 34 significant digits", text);
+    }
+    let coefficient: u128 = if digits.is_empty() {
+        0
+    } else {
+        digits.parse()?
+    };
+    let exponent = exponent - fraction.len() as i32 + DECIMAL_BIAS;
+    if !(0..=0x2fff).contains(&exponent) {
+        bail!("'{}' is out of the decimal128 exponent range", text);
+    }
+    let high = sign | (exponent as u64) << 49 | (coefficient >> 64) as u64;
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&(coefficient as u64).to_le_bytes());
+    bytes[8..].copy_from_slice(&high.to_le_bytes());
+    Ok(bytes)
+}
+
+// ============================================================================
+// Writing
+// ============================================================================
+
+fn encode_document<'a>(
+    elements: impl Iterator<Item = (&'a String, &'a JsonValue)>,
+    output: &mut Vec<u8>,
+    depth: usize,
+) -> Result<()> {
+    if depth > MAX_DEPTH {
+        bail!("Value nested deeper than {} levels", MAX_DEPTH);
+    }
+    let start = output.len();
+    output.extend([0; 4]);
+    for (key, value) in elements {
+        encode_element(key, value, output, depth).with_context(|| format!("In field '{}'", key))?;
+    }
+    output.push(0);
+    let len = i32::try_from(output.len() - start).context("BSON document exceeds 2 GiB")?;
+    output[start..start + 4].copy_from_slice(&len.to_le_bytes());
+    Ok(())
+}
+
+fn encode_element(key: &str, value: &JsonValue, output: &mut Vec<u8>, depth: usize) -> Result<()> {
+    let kind_pos = output.len();
+    output.push(0);
+    encode_cstring(key, output)?;
+    let kind = match value {
+        JsonValue::Null => NULL,
+        JsonValue::Bool(b) => {
+            output.push(u8::from(*b));
+            BOOLEAN
+        }
+        JsonValue::Number(n) => match (n.as_i64(), n.as_i64().map(i32::try_from)) {
+            (_, Some(Ok(i))) => {
+                output.extend(i.to_le_bytes());
+                INT32
+            }
+            (Some(i), _) => {
+                output.extend(i.to_le_bytes());
+                INT64
+            }
+            _ => {
+                output.extend(n.as_f64().unwrap_or_default().to_le_bytes());
+                DOUBLE
+            }
+        },
+        JsonValue::String(s) => {
+            encode_string(s, output)?;
+            STRING
+        }
+        JsonValue::Array(items) => {
+            let keys: Vec<String> = (0..items.len()).map(|i| i.to_string()).collect();
+            encode_document(keys.iter().zip(items), output, depth + 1)?;
+            ARRAY
+        }
+        JsonValue::Object(obj) => match encode_extended(obj, output, depth)? {
+            Some(kind) => kind,
+            None => {
+                encode_document(obj.iter(), output, depth + 1)?;
+                DOCUMENT
+            }
+        },
+    };
+    output[kind_pos] = kind;
+    Ok(())
+}
+
+/// Write an Extended JSON wrapper (`{"$oid": ...}` and the like) as its BSON
+/// type; `None` for ordinary objects
+fn encode_extended(
+    obj: &Map<String, JsonValue>,
+    output: &mut Vec<u8>,
+    depth: usize,
+) -> Result<Option<u8>> {
+    let mut entries = obj.iter();
+    let (Some((key, value)), rest) = (entries.next(), entries.next()) else {
+        return Ok(None);
+    };
+    let invalid = || format!("Invalid {} value: {}", key, value);
+    let kind = match (key.as_str(), rest) {
+        ("$oid", None) => {
+            let id = value
+                .as_str()
+                .and_then(unhex)
+                .filter(|id| id.len() == 12)
+                .with_context(invalid)?;
+            output.extend(id);
+            OBJECT_ID
+        }
+        ("$date", None) => {
+            let millis = match value {
+                JsonValue::String(s) => DateTime::parse_from_rfc3339(s)
+                    .ok()
+                    .map(|time| time.timestamp_millis()),
+                JsonValue::Number(n) => n.as_i64(),
+                other => other
+                    .get("$numberLong")
+                    .and_then(JsonValue::as_str)
+                    .and_then(|s| s.parse().ok()),
+            };
+            output.extend(millis.with_context(invalid)?.to_le_bytes());
+            DATETIME
+        }
+        ("$binary", None) => {
+            let data = value
+                .get("base64")
+                .and_then(JsonValue::as_str)
+                .and_then(|s| BASE64.decode(s).ok())
+                .with_context(invalid)?;
+            let subtype = value
+                .get("subType")
+                .and_then(JsonValue::as_str)
+                .and_then(|s| u8::from_str_radix(s, 16).ok())
+                .with_context(invalid)?;
+            let len = i32::try_from(data.len()).context("BSON binary exceeds 2 GiB")?;
+            output.extend(len.to_le_bytes());
+            output.push(subtype);
+            output.extend(data);
+            BINARY
+        }
+        ("$numberDecimal", None) => {
+            output.extend(decimal128_from_str(value.as_str().with_context(invalid)?)?);
+            DECIMAL128
+        }
+        ("$numberLong", None) => {
+            let n: i64 = value
+                .as_str()
+                .and_then(|s| s.parse().ok())
+                .with_context(invalid)?;
+            output.extend(n.to_le_bytes());
+            INT64
+        }
+        ("$numberInt", None) => {
+            let n: i32 = value
+                .as_str()
+                .and_then(|s| s.parse().ok())
+                .with_context(invalid)?;
+            output.extend(n.to_le_bytes());
+            INT32
+        }
+        ("$numberDouble", None) => {
+            let f: f64 = match value.as_str() {
+                Some("Infinity") => f64::INFINITY,
+                Some("-Infinity") => f64::NEG_INFINITY,
+                Some("NaN") => f64::NAN,
+                other => other.and_then(|s| s.parse().ok()).with_context(invalid)?,
+            };
+            output.extend(f.to_le_bytes());
+            DOUBLE
+        }
+        ("$timestamp", None) => {
+            let part = |name| {
+                value
+                    .get(name)
+                    .and_then(JsonValue::as_u64)
+                    .and_then(|n| u32::try_from(n).ok())
+            };
+            let (Some(time), Some(increment)) = (part("t"), part("i")) else {
+                bail!(invalid());
+            };
+            output.extend(increment.to_le_bytes());
+            output.extend(time.to_le_bytes());
+            TIMESTAMP
+        }
+        ("$regularExpression", None) => {
+            let part = |name| value.get(name).and_then(JsonValue::as_str);
+            let (Some(pattern), Some(options)) = (part("pattern"), part("options")) else {
+                bail!(invalid());
+            };
+            encode_cstring(pattern, output)?;
+            encode_cstring(options, output)?;
+            REGEX
+        }
+        ("$dbPointer", None) => {
+            let namespace = value.get("$ref").and_then(JsonValue::as_str);
+            let id = value
+                .pointer("/$id/$oid")
+                .and_then(JsonValue::as_str)
+                .and_then(unhex)
+                .filter(|id| id.len() == 12);
+            let (Some(namespace), Some(id)) = (namespace, id) else {
+                bail!(invalid());
+            };
+            encode_string(namespace, output)?;
+            output.extend(id);
+            DB_POINTER
+        }
+        ("$code", None) => {
+            encode_string(value.as_str().with_context(invalid)?, output)?;
+            CODE
+        }
+        ("$code", Some((scope_key, JsonValue::Object(scope))))
+            if scope_key == "$scope" && obj.len() == 2 =>
+        {
+            let start = output.len();
+            output.extend([0; 4]);
+            encode_string(value.as_str().with_context(invalid)?, output)?;
+            encode_document(scope.iter(), output, depth + 1)?;
+            let len = i32::try_from(output.len() - start).context("BSON code exceeds 2 GiB")?;
+            output[start..start + 4].copy_from_slice(&len.to_le_bytes());
+            CODE_WITH_SCOPE
+        }
+        ("$symbol", None) => {
+            encode_string(value.as_str().with_context(invalid)?, output)?;
+            SYMBOL
+        }
+        ("$undefined", None) => UNDEFINED,
+        ("$minKey", None) => MIN_KEY,
+        ("$maxKey", None) => MAX_KEY,
+        _ => return Ok(None),
+    };
+    Ok(Some(kind))
+}
+
+fn encode_cstring(text: &str, output: &mut Vec<u8>) -> Result<()> {
+    if text.contains('\0') {
+        bail!("BSON keys and patterns cannot contain NUL characters");
+    }
+    output.extend(text.as_bytes());
+    output.push(0);
+    Ok(())
+}
+
+fn encode_string(text: &str, output: &mut Vec<u8>) -> Result<()> {
+    let len = i32::try_from(text.len() + 1).context("BSON string exceeds 2 GiB")?;
+    output.extend(len.to_le_bytes());
+    output.extend(text.as_bytes());
+    output.push(0);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_example() {
+        let bytes = b"\x16\x00\x00\x00\x02hello\x00\x06\x00\x00\x00world\x00\x00";
+        assert_eq!(parse(bytes).unwrap(), json!({"hello": "world"}));
+        assert_eq!(to_vec(&json!({"hello": "world"})).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value = json!([
+            {
+                "_id": {"$oid": "507f1f77bcf86cd799439011"},
+                "name": "Ann",
+                "age": 36,
+                "views": 5_000_000_000i64,
+                "score": 9.5,
+                "tags": ["a", {"nested": null}],
+                "created": {"$date": "2024-01-02T03:04:05.678Z"},
+                "ancient": {"$date": {"$numberLong": "-62135596800000"}},
+                "avatar": {"$binary": {"base64": "AQID", "subType": "00"}},
+                "price": {"$numberDecimal": "12.50"},
+                "ts": {"$timestamp": {"t": 1700000000, "i": 3}},
+                "re": {"$regularExpression": {"pattern": "^a", "options": "i"}},
+                "fn": {"$code": "x + 1", "$scope": {"x": 1}},
+                "low": {"$minKey": 1}
+            },
+            {"_id": {"$oid": "507f1f77bcf86cd799439012"}}
+        ]);
+        let bytes = to_vec(&value).unwrap();
+        assert_eq!(parse(&bytes).unwrap(), value);
+
+        // Canonical number wrappers come back as plain numbers
+        let bytes = to_vec(&json!({"a": {"$numberLong": "7"}, "b": {"$numberInt": "-2"}})).unwrap();
+        assert_eq!(parse(&bytes).unwrap(), json!({"a": 7, "b": -2}));
+        assert_eq!(bytes[4], INT64);
+    }
+
+    #[test]
+    fn test_decimal128() {
+        for text in [
+            "0",
+            "1",
+            "-1",
+            "0.1",
+            "12.50",
+            "0.000001",
+            "1E-7",
+            "1.23E+5",
+            "-Infinity",
+        ] {
+            let bytes = decimal128_from_str(text).unwrap();
+            assert_eq!(decimal128_to_string(bytes), text);
+        }
+        let one = decimal128_from_str("1").unwrap();
+        assert_eq!(
+            u64::from_le_bytes(one[8..].try_into().unwrap()),
+            0x3040_0000_0000_0000
+        );
+        assert_eq!(
+            decimal128_to_string(decimal128_from_str("123e3").unwrap()),
+            "1.23E+5"
+        );
+        assert!(decimal128_from_str("1.2.3").is_err());
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(to_vec(&json!([1])).is_err());
+        assert!(to_vec(&json!({"_id": {"$oid": "xyz"}})).is_err());
+        assert!(parse(b"\x05\x00\x00\x00").is_err());
+        assert!(parse(b"\x06\x00\x00\x00\x00\x00").is_err());
+    }
+}
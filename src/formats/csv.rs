@@ -1,10 +1,13 @@
 //! CSV format handling
 
-use anyhow::{Context, Result};
-use std::fs;
-use std::io::{self, Read};
+use anyhow::{bail, Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{NaiveDate, NaiveDateTime};
 use std::path::Path;
 
+use crate::utils::encoding::EncodingOptions;
+use crate::utils::width;
+
 /// CSV data representation
 #[derive(Debug, Clone)]
 pub struct CsvData {
@@ -30,26 +33,196 @@ impl CsvData {
     }
 }
 
-/// Read input from file or stdin
-pub fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
-        Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
+/// Options for reading CSV input
+#[derive(Debug, Clone, Default)]
+pub struct CsvReadOptions {
+    /// Number of leading lines to discard before the header row
+    pub skip_rows: usize,
+    /// Lines starting with this character are ignored
+    pub comment: Option<u8>,
+    /// Formats of date columns to rewrite as ISO 8601
+    pub date_formats: Vec<DateFormat>,
+    /// Field delimiter; `None` sniffs it from the content
+    pub delimiter: Option<u8>,
+}
+
+/// A `--date-format` value: a strftime pattern, optionally bound to one column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateFormat {
+    /// Column the format applies to; `None` tries it on every column
+    pub column: Option<String>,
+    /// strftime pattern such as `%d/%m/%Y`
+    pub format: String,
+}
+
+impl DateFormat {
+    /// Parse `FORMAT` or `COLUMN=FORMAT`
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (column, format) = match spec.split_once('=') {
+            Some((column, format)) if !column.contains('%') => {
+                (Some(column.trim().to_string()), format)
+            }
+            _ => (None, spec),
+        };
+        if format.is_empty() || StrftimeItems::new(format).any(|item| item == Item::Error) {
+            bail!("Invalid date format '{}'", format);
         }
-        None => {
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
+        Ok(Self {
+            column,
+            format: format.to_string(),
+        })
+    }
+
+    /// ISO 8601 form of `cell`, if it matches this format
+    pub fn to_iso(&self, cell: &str) -> Option<String> {
+        let cell = cell.trim();
+        if let Ok(dt) = NaiveDateTime::parse_from_str(cell, &self.format) {
+            return Some(dt.format("%Y-%m-%dT%H:%M:%S%.f").to_string());
         }
+        NaiveDate::parse_from_str(cell, &self.format)
+            .ok()
+            .map(|d| d.format("%Y-%m-%d").to_string())
     }
 }
 
+impl CsvReadOptions {
+    /// Build options from `--skip-rows` and `--comment` values
+    pub fn new(skip_rows: usize, comment: Option<&str>) -> Result<Self> {
+        let comment = match comment {
+            Some(c) if c.len() == 1 && c.is_ascii() => Some(c.as_bytes()[0]),
+            Some(c) => bail!(
+                "Comment marker must be a single ASCII character, got '{}'",
+                c
+            ),
+            None => None,
+        };
+        Ok(Self {
+            skip_rows,
+            comment,
+            date_formats: Vec::new(),
+            delimiter: None,
+        })
+    }
+
+    /// Add a `--delimiter` value
+    pub fn with_delimiter(mut self, delimiter: Option<&str>) -> Result<Self> {
+        self.delimiter = delimiter.map(parse_delimiter).transpose()?;
+        Ok(self)
+    }
+
+    /// Add `--date-format` values
+    pub fn with_date_formats(mut self, specs: &[String]) -> Result<Self> {
+        self.date_formats = specs
+            .iter()
+            .map(|spec| DateFormat::parse(spec))
+            .collect::<Result<_>>()?;
+        Ok(self)
+    }
+
+    /// Rewrite matching date cells as ISO 8601
+    ///
+    /// A cell in a column with its own format must match it; other cells are
+    /// rewritten only when one of the column-independent formats matches.
+    fn normalize_dates(&self, headers: Option<&[String]>, rows: &mut [Vec<String>]) -> Result<()> {
+        if self.date_formats.is_empty() {
+            return Ok(());
+        }
+        for (line, row) in rows.iter_mut().enumerate() {
+            for (i, cell) in row.iter_mut().enumerate() {
+                if cell.trim().is_empty() {
+                    continue;
+                }
+                let column = headers.and_then(|h| h.get(i)).map(String::as_str);
+                let own = self
+                    .date_formats
+                    .iter()
+                    .find(|f| f.column.is_some() && f.column.as_deref() == column);
+                let iso = match own {
+                    Some(format) => Some(format.to_iso(cell).with_context(|| {
+                        format!(
+                            "Row {}, column '{}': '{}' does not match date format '{}'",
+                            line + 1,
+                            column.unwrap_or_default(),
+                            cell,
+                            format.format
+                        )
+                    })?),
+                    None => self
+                        .date_formats
+                        .iter()
+                        .filter(|f| f.column.is_none())
+                        .find_map(|f| f.to_iso(cell)),
+                };
+                if let Some(iso) = iso {
+                    *cell = iso;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Delimiters tried when sniffing, in order of preference
+const SNIFF_DELIMITERS: [u8; 4] = [b',', b'\t', b';', b'|'];
+
+/// Parse a `--delimiter` value: a single ASCII character, `tab` or `\t`
+pub fn parse_delimiter(spec: &str) -> Result<u8> {
+    match spec {
+        "tab" | "\\t" | "\t" => Ok(b'\t'),
+        s if s.len() == 1 && s.is_ascii() && s != "\"" && s != "\n" => Ok(s.as_bytes()[0]),
+        s => bail!(
+            "Delimiter must be a single ASCII character or 'tab', got '{}'",
+            s
+        ),
+    }
+}
+
+/// Guess the field delimiter from the first lines of `content`
+///
+/// A delimiter is chosen when it appears the same, non-zero number of times
+/// on every line sampled.
+pub fn sniff_delimiter(content: &str) -> Option<u8> {
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .take(20)
+        .collect();
+    if lines.is_empty() {
+        return None;
+    }
+
+    SNIFF_DELIMITERS.into_iter().find(|&delimiter| {
+        let count = |line: &str| line.bytes().filter(|&b| b == delimiter).count();
+        let first = count(lines[0]);
+        first > 0 && lines.iter().all(|line| count(line) == first)
+    })
+}
+
+/// Read input from file or stdin
+pub fn read_input(path: Option<&Path>, encoding: &EncodingOptions) -> Result<String> {
+    encoding.read_input(path)
+}
+
 /// Parse CSV string into CsvData
 pub fn parse(content: &str, has_headers: bool) -> Result<CsvData> {
+    parse_with_options(content, has_headers, &CsvReadOptions::default())
+}
+
+/// Parse CSV string into CsvData, skipping preamble and comment lines
+pub fn parse_with_options(
+    content: &str,
+    has_headers: bool,
+    options: &CsvReadOptions,
+) -> Result<CsvData> {
+    let content = skip_lines(content, options.skip_rows);
+    let delimiter = options
+        .delimiter
+        .or_else(|| sniff_delimiter(content))
+        .unwrap_or(b',');
     let mut reader = csv::ReaderBuilder::new()
         .has_headers(has_headers)
+        .delimiter(delimiter)
+        .comment(options.comment)
         .from_reader(content.as_bytes());
 
     let headers = if has_headers {
@@ -70,10 +243,23 @@ pub fn parse(content: &str, has_headers: bool) -> Result<CsvData> {
         let record = result.context("Failed to read CSV record")?;
         rows.push(record.iter().map(|s| s.to_string()).collect());
     }
+    options.normalize_dates(headers.as_deref(), &mut rows)?;
 
     Ok(CsvData { headers, rows })
 }
 
+/// Drop the first `count` lines of `content`
+fn skip_lines(content: &str, count: usize) -> &str {
+    let mut rest = content;
+    for _ in 0..count {
+        match rest.find('\n') {
+            Some(end) => rest = &rest[end + 1..],
+            None => return "",
+        }
+    }
+    rest
+}
+
 /// Convert CsvData to formatted table string
 pub fn to_table(data: &CsvData) -> Result<String> {
     if data.rows.is_empty() && data.headers.is_none() {
@@ -93,7 +279,7 @@ pub fn to_table(data: &CsvData) -> Result<String> {
     if let Some(headers) = &data.headers {
         for (i, h) in headers.iter().enumerate() {
             if i < col_widths.len() {
-                col_widths[i] = col_widths[i].max(h.len());
+                col_widths[i] = col_widths[i].max(width::str_width(h));
             }
         }
     }
@@ -102,7 +288,7 @@ pub fn to_table(data: &CsvData) -> Result<String> {
     for row in &data.rows {
         for (i, cell) in row.iter().enumerate() {
             if i < col_widths.len() {
-                col_widths[i] = col_widths[i].max(cell.len());
+                col_widths[i] = col_widths[i].max(width::str_width(cell));
             }
         }
     }
@@ -127,7 +313,7 @@ pub fn to_table(data: &CsvData) -> Result<String> {
             .enumerate()
             .map(|(i, h)| {
                 let width = col_widths.get(i).copied().unwrap_or(0);
-                format!(" {:^width$} ", h, width = width)
+                format!(" {} ", width::center(h, width))
             })
             .collect::<Vec<_>>()
             .join("|");
@@ -148,7 +334,7 @@ pub fn to_table(data: &CsvData) -> Result<String> {
             .enumerate()
             .map(|(i, cell)| {
                 let width = col_widths.get(i).copied().unwrap_or(0);
-                format!(" {:<width$} ", cell, width = width)
+                format!(" {} ", width::pad(cell, width))
             })
             .collect::<Vec<_>>()
             .join("|");
@@ -163,7 +349,14 @@ pub fn to_table(data: &CsvData) -> Result<String> {
 
 /// Convert CsvData back to CSV format
 pub fn to_csv(data: &CsvData) -> Result<String> {
-    let mut writer = csv::Writer::from_writer(Vec::new());
+    to_delimited(data, b',')
+}
+
+/// Convert CsvData to delimited text, e.g. TSV with `b'\t'`
+pub fn to_delimited(data: &CsvData, delimiter: u8) -> Result<String> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(Vec::new());
 
     if let Some(headers) = &data.headers {
         writer
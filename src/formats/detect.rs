@@ -1,6 +1,13 @@
 //! Format auto-detection
 
+use anyhow::{Context, Result};
 use std::path::Path;
+use std::sync::Arc;
+use tracing::debug;
+
+use super::csv;
+use super::registry::{self, FormatHandler};
+use crate::utils::compression;
 
 /// Supported data formats
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -9,10 +16,68 @@ pub enum Format {
     Yaml,
     Toml,
     Csv,
+    Tsv,
     Xml,
+    Ndjson,
+    MsgPack,
+    Parquet,
+    Xlsx,
+    Html,
+    Sql,
+    Plist,
+    BinaryPlist,
+    Properties,
+    Hcl,
+    Protobuf,
+    Avro,
+    Bson,
+    Sqlite,
+    /// A format added through [`registry::register`]
+    Custom(&'static str),
 }
 
 impl Format {
+    /// Every built-in format, in the order they are listed to users
+    pub const BUILTIN: [Format; 20] = [
+        Format::Json,
+        Format::Yaml,
+        Format::Toml,
+        Format::Csv,
+        Format::Tsv,
+        Format::Xml,
+        Format::Ndjson,
+        Format::MsgPack,
+        Format::Parquet,
+        Format::Xlsx,
+        Format::Html,
+        Format::Sql,
+        Format::Plist,
+        Format::BinaryPlist,
+        Format::Properties,
+        Format::Hcl,
+        Format::Protobuf,
+        Format::Avro,
+        Format::Bson,
+        Format::Sqlite,
+    ];
+
+    /// Look up a format by name or alias (case-insensitive), registered ones included
+    pub fn from_name(name: &str) -> Option<Format> {
+        registry::handler(name).map(|handler| registry::format_of(handler.as_ref()))
+    }
+
+    /// The handler that reads and writes the format
+    pub fn handler(&self) -> Result<Arc<dyn FormatHandler>> {
+        registry::handler(self.as_str())
+            .with_context(|| format!("Format '{}' is not registered", self))
+    }
+
+    /// Names of every format, built-in and registered, for messages
+    pub fn supported_names() -> Vec<&'static str> {
+        let builtin = Format::BUILTIN.iter().map(Format::as_str);
+        builtin.chain(registry::names()).collect()
+    }
+
     /// Get format name as string
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -20,9 +85,30 @@ impl Format {
             Format::Yaml => "yaml",
             Format::Toml => "toml",
             Format::Csv => "csv",
+            Format::Tsv => "tsv",
             Format::Xml => "xml",
+            Format::Ndjson => "ndjson",
+            Format::MsgPack => "msgpack",
+            Format::Parquet => "parquet",
+            Format::Xlsx => "xlsx",
+            Format::Html => "html",
+            Format::Sql => "sql",
+            Format::Plist => "plist",
+            Format::BinaryPlist => "bplist",
+            Format::Properties => "properties",
+            Format::Hcl => "hcl",
+            Format::Protobuf => "proto",
+            Format::Avro => "avro",
+            Format::Bson => "bson",
+            Format::Sqlite => "sqlite",
+            Format::Custom(name) => name,
         }
     }
+
+    /// Whether the format is binary rather than text
+    pub fn is_binary(&self) -> bool {
+        self.handler().is_ok_and(|h| h.is_binary())
+    }
 }
 
 impl std::fmt::Display for Format {
@@ -31,17 +117,11 @@ impl std::fmt::Display for Format {
     }
 }
 
-/// Detect format from file extension
+/// Detect format from file extension, looking past a compression extension
 pub fn detect_from_extension(path: &Path) -> Option<Format> {
-    let ext = path.extension()?.to_str()?.to_lowercase();
-    match ext.as_str() {
-        "json" => Some(Format::Json),
-        "yaml" | "yml" => Some(Format::Yaml),
-        "toml" => Some(Format::Toml),
-        "csv" | "tsv" => Some(Format::Csv),
-        "xml" | "xhtml" | "svg" | "xsd" | "xsl" => Some(Format::Xml),
-        _ => None,
-    }
+    let path = compression::uncompressed_name(path);
+    let ext = path.extension()?.to_str()?;
+    registry::from_extension(ext)
 }
 
 /// Detect format from content by analyzing the structure
@@ -52,40 +132,7 @@ pub fn detect_from_content(content: &str) -> Option<Format> {
         return None;
     }
 
-    // Check for XML (starts with < or XML declaration)
-    if trimmed.starts_with("<?xml") || trimmed.starts_with('<') {
-        // Verify it looks like valid XML structure
-        if trimmed.contains("</") || trimmed.contains("/>") {
-            return Some(Format::Xml);
-        }
-    }
-
-    // Check for JSON (starts with { or [)
-    let first_char = trimmed.chars().next()?;
-    if first_char == '{' || first_char == '[' {
-        // Try to parse as JSON to verify
-        if serde_json::from_str::<serde_json::Value>(trimmed).is_ok() {
-            return Some(Format::Json);
-        }
-    }
-
-    // Check for TOML characteristics
-    // TOML typically has [section] headers or key = "value" patterns
-    if is_likely_toml(trimmed) {
-        return Some(Format::Toml);
-    }
-
-    // Check for CSV (contains commas and consistent column count)
-    if is_likely_csv(trimmed) {
-        return Some(Format::Csv);
-    }
-
-    // Check for YAML (has : with proper spacing, or starts with ---)
-    if is_likely_yaml(trimmed) {
-        return Some(Format::Yaml);
-    }
-
-    None
+    registry::detect(trimmed)
 }
 
 /// Detect format using both file path and content
@@ -93,15 +140,43 @@ pub fn detect(path: Option<&Path>, content: &str) -> Option<Format> {
     // First try to detect from file extension
     if let Some(p) = path {
         if let Some(format) = detect_from_extension(p) {
+            debug!(path = %p.display(), %format, "format detected from file extension");
             return Some(format);
         }
+        debug!(path = %p.display(), "no known file extension, inspecting content");
     }
 
     // Fall back to content-based detection
-    detect_from_content(content)
+    let format = detect_from_content(content);
+    match format {
+        Some(format) => debug!(%format, "format detected from content"),
+        None => debug!("could not detect format from content"),
+    }
+    format
+}
+
+/// XML starts with a tag or declaration and closes a tag
+pub(crate) fn is_likely_xml(content: &str) -> bool {
+    content.starts_with('<') && (content.contains("</") || content.contains("/>"))
+}
+
+/// One JSON object or array
+pub(crate) fn is_likely_json(content: &str) -> bool {
+    content.starts_with(['{', '[']) && serde_json::from_str::<serde_json::Value>(content).is_ok()
+}
+
+/// Several JSON values, one per line
+pub(crate) fn is_likely_ndjson(content: &str) -> bool {
+    content.starts_with(['{', '['])
+        && content.lines().count() > 1
+        && content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .all(|line| serde_json::from_str::<serde_json::Value>(line).is_ok())
 }
 
-fn is_likely_toml(content: &str) -> bool {
+/// TOML typically has [section] headers or key = "value" patterns
+pub(crate) fn is_likely_toml(content: &str) -> bool {
     let lines: Vec<&str> = content.lines().collect();
 
     for line in &lines {
@@ -135,29 +210,19 @@ fn is_likely_toml(content: &str) -> bool {
     false
 }
 
-fn is_likely_csv(content: &str) -> bool {
-    let lines: Vec<&str> = content.lines().filter(|l| !l.trim().is_empty()).collect();
-
-    if lines.len() < 2 {
-        return false;
-    }
-
-    // Count delimiters (comma, tab, semicolon)
-    let delimiters = [',', '\t', ';'];
-
-    for delimiter in delimiters {
-        let counts: Vec<usize> = lines.iter().map(|l| l.matches(delimiter).count()).collect();
+/// The delimiter of CSV or TSV content, consistent on every line
+pub(crate) fn csv_delimiter(content: &str) -> Option<u8> {
+    let lines = content.lines().filter(|l| !l.trim().is_empty()).count();
 
-        // Check if all lines have the same number of delimiters
-        if counts.iter().all(|&c| c > 0 && c == counts[0]) {
-            return true;
-        }
+    if lines < 2 {
+        return None;
     }
 
-    false
+    csv::sniff_delimiter(content)
 }
 
-fn is_likely_yaml(content: &str) -> bool {
+/// YAML has `key: value` lines, list items or starts with `---`
+pub(crate) fn is_likely_yaml(content: &str) -> bool {
     let trimmed = content.trim();
 
     // YAML document separator
@@ -233,6 +298,10 @@ mod tests {
             detect_from_content("a,b,c\n1,2,3\n4,5,6"),
             Some(Format::Csv)
         );
+        assert_eq!(
+            detect_from_content("a\tb\tc\n1\t2\t3\n4\t5\t6"),
+            Some(Format::Tsv)
+        );
     }
 
     #[test]
@@ -257,6 +326,10 @@ mod tests {
             detect_from_extension(Path::new("test.csv")),
             Some(Format::Csv)
         );
+        assert_eq!(
+            detect_from_extension(Path::new("test.tsv")),
+            Some(Format::Tsv)
+        );
         assert_eq!(
             detect_from_extension(Path::new("test.xml")),
             Some(Format::Xml)
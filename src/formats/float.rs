@@ -0,0 +1,198 @@
+//! Text of floating-point numbers in output (`--float-precision`, `--float-sci`,
+//! `--float-trailing-zeros`)
+
+use serde_json::ser::Formatter;
+use serde_json::Number;
+use std::io;
+
+/// How floats are written by the JSON, CSV, XML and TOML serializers
+///
+/// YAML output only gets the rounding; its serializer picks its own notation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FloatFormat {
+    /// Digits after the decimal point, rounding the rest away
+    pub precision: Option<usize>,
+    /// Use scientific notation once the decimal exponent is at least this far
+    /// from zero (3 writes 1500.0 as 1.5e3 and 0.001 as 1e-3)
+    pub sci_threshold: Option<u32>,
+    /// Pad to `precision` digits (1.50) instead of trimming trailing zeros (1.5)
+    pub trailing_zeros: bool,
+}
+
+impl FloatFormat {
+    /// Text of `value`; non-finite values keep their usual form
+    pub fn format(&self, value: f64) -> String {
+        if !value.is_finite() {
+            return value.to_string();
+        }
+        let value = self.round(value);
+        let scientific = match self.sci_threshold {
+            Some(threshold) => value != 0.0 && exponent(value).unsigned_abs() >= threshold,
+            // Same switch-over as the default output
+            None => format!("{:?}", value).contains('e'),
+        };
+        let text = match (scientific, self.precision) {
+            (true, Some(precision)) => format!("{:.*e}", precision, value),
+            (true, None) => format!("{:e}", value),
+            (false, Some(precision)) => format!("{:.*}", precision, value),
+            (false, None) => format!("{:?}", value),
+        };
+        if self.trailing_zeros {
+            return text;
+        }
+
+        match text.split_once('e') {
+            Some((mantissa, exponent)) => {
+                let mantissa = trim_zeros(mantissa);
+                format!("{}e{}", mantissa.trim_end_matches('.'), exponent)
+            }
+            // Keep one decimal so the value still reads as a float (2.0)
+            None if text.ends_with(".0") => text,
+            None => {
+                let trimmed = trim_zeros(&text);
+                match trimmed.strip_suffix('.') {
+                    Some(whole) => format!("{}.0", whole),
+                    None => trimmed.to_string(),
+                }
+            }
+        }
+    }
+
+    /// `value` rounded to `precision` decimal places
+    pub fn round(&self, value: f64) -> f64 {
+        match self.precision {
+            Some(precision) if value.is_finite() => {
+                format!("{:.*}", precision, value).parse().unwrap_or(value)
+            }
+            _ => value,
+        }
+    }
+}
+
+fn trim_zeros(text: &str) -> &str {
+    if text.contains('.') {
+        text.trim_end_matches('0')
+    } else {
+        text
+    }
+}
+
+fn exponent(value: f64) -> i32 {
+    // Read the exponent from the text so 999.9999 rounded up does not land one short
+    format!("{:e}", value)
+        .split_once('e')
+        .and_then(|(_, exponent)| exponent.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Text of a JSON number, applying `format`, if any, to floats
+pub fn number_text(number: &Number, format: Option<FloatFormat>) -> String {
+    match (format, number.is_f64()) {
+        (Some(format), true) => format.format(number.as_f64().unwrap_or_default()),
+        _ => number.to_string(),
+    }
+}
+
+/// serde_json formatter that writes floats with a [`FloatFormat`]
+pub struct FloatFormatter<F> {
+    inner: F,
+    format: FloatFormat,
+}
+
+impl<F: Formatter> FloatFormatter<F> {
+    pub fn new(inner: F, format: FloatFormat) -> Self {
+        Self { inner, format }
+    }
+}
+
+impl<F: Formatter> Formatter for FloatFormatter<F> {
+    fn write_f64<W: ?Sized + io::Write>(&mut self, writer: &mut W, value: f64) -> io::Result<()> {
+        writer.write_all(self.format.format(value).as_bytes())
+    }
+
+    fn begin_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_array(writer)
+    }
+
+    fn end_array<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_array(writer)
+    }
+
+    fn begin_array_value<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.inner.begin_array_value(writer, first)
+    }
+
+    fn end_array_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_array_value(writer)
+    }
+
+    fn begin_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_object(writer)
+    }
+
+    fn end_object<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_object(writer)
+    }
+
+    fn begin_object_key<W: ?Sized + io::Write>(
+        &mut self,
+        writer: &mut W,
+        first: bool,
+    ) -> io::Result<()> {
+        self.inner.begin_object_key(writer, first)
+    }
+
+    fn begin_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.begin_object_value(writer)
+    }
+
+    fn end_object_value<W: ?Sized + io::Write>(&mut self, writer: &mut W) -> io::Result<()> {
+        self.inner.end_object_value(writer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let default = FloatFormat::default();
+        assert_eq!(default.format(1.5), "1.5");
+        assert_eq!(default.format(2.0), "2.0");
+        assert_eq!(default.format(1e-7), "1e-7");
+
+        let rounded = FloatFormat {
+            precision: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(rounded.format(12.3456), "12.35");
+        assert_eq!(rounded.format(2.5), "2.5");
+        assert_eq!(rounded.format(2.001), "2.0");
+        assert_eq!(rounded.round(12.3456), 12.35);
+
+        let padded = FloatFormat {
+            trailing_zeros: true,
+            ..rounded
+        };
+        assert_eq!(padded.format(2.5), "2.50");
+
+        let scientific = FloatFormat {
+            sci_threshold: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(scientific.format(1500.0), "1.5e3");
+        assert_eq!(scientific.format(0.001), "1e-3");
+        assert_eq!(scientific.format(12.5), "12.5");
+
+        let whole = FloatFormat {
+            precision: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(whole.format(2.6), "3");
+    }
+}
@@ -0,0 +1,159 @@
+//! Markdown front matter handling
+//!
+//! Supports YAML (`---`) and TOML (`+++`) front matter blocks at the start
+//! of a Markdown document.
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+use std::path::Path;
+
+use crate::core::converter::{self, ConvertOptions};
+use crate::formats::detect::Format;
+
+/// A Markdown document split into front matter and body
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrontMatter<'a> {
+    /// Format of the front matter block
+    pub format: Format,
+    /// Raw front matter text (without delimiters)
+    pub data: &'a str,
+    /// Everything after the closing delimiter
+    pub body: &'a str,
+}
+
+/// Whether a path looks like a Markdown file
+pub fn is_markdown(path: Option<&Path>) -> bool {
+    path.and_then(|p| p.extension())
+        .and_then(|e| e.to_str())
+        .map(|e| matches!(e.to_lowercase().as_str(), "md" | "markdown" | "mdx"))
+        .unwrap_or(false)
+}
+
+/// Split a document into front matter and body; `None` if it has no front matter
+pub fn split(content: &str) -> Option<FrontMatter<'_>> {
+    let content = content.strip_prefix('\u{feff}').unwrap_or(content);
+    let (first_line, rest) = content.split_once('\n')?;
+
+    let (format, closers): (Format, &[&str]) = match first_line.trim_end() {
+        "---" => (Format::Yaml, &["---", "..."]),
+        "+++" => (Format::Toml, &["+++"]),
+        _ => return None,
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if closers.contains(&line.trim_end()) {
+            return Some(FrontMatter {
+                format,
+                data: &rest[..offset],
+                body: &rest[offset + line.len()..],
+            });
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+/// Extract the front matter of a Markdown document and its format
+pub fn extract(content: &str) -> Result<(String, Format)> {
+    let fm =
+        split(content).context("No front matter found (expected a leading --- or +++ block)")?;
+    Ok((fm.data.to_string(), fm.format))
+}
+
+/// Parse front matter into JSON; an empty block yields an empty object
+pub fn to_json(fm: &FrontMatter, options: &ConvertOptions) -> Result<JsonValue> {
+    if fm.data.trim().is_empty() {
+        return Ok(JsonValue::Object(serde_json::Map::new()));
+    }
+    let json = converter::convert_with_options(fm.data, fm.format, Format::Json, options)?;
+    serde_json::from_str(&json).context("Failed to parse front matter")
+}
+
+/// Render a Markdown document from front matter data and a body
+pub fn render(
+    format: Format,
+    data: &JsonValue,
+    body: &str,
+    options: &ConvertOptions,
+) -> Result<String> {
+    let (delimiter, serialized) = match format {
+        Format::Toml => (
+            "+++",
+            converter::convert_with_options(
+                &data.to_string(),
+                Format::Json,
+                Format::Toml,
+                options,
+            )?,
+        ),
+        Format::Yaml => (
+            "---",
+            converter::convert_with_options(
+                &data.to_string(),
+                Format::Json,
+                Format::Yaml,
+                options,
+            )?,
+        ),
+        _ => anyhow::bail!("Front matter must be YAML or TOML"),
+    };
+
+    let mut output = String::new();
+    output.push_str(delimiter);
+    output.push('\n');
+    output.push_str(&serialized);
+    if !serialized.ends_with('\n') {
+        output.push('\n');
+    }
+    output.push_str(delimiter);
+    output.push('\n');
+    output.push_str(body);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_split_yaml() {
+        let doc = "---\ntitle: Hello\ntags: [a, b]\n---\n# Body\n\nText\n";
+        let fm = split(doc).unwrap();
+        assert_eq!(fm.format, Format::Yaml);
+        assert_eq!(fm.data, "title: Hello\ntags: [a, b]\n");
+        assert_eq!(fm.body, "# Body\n\nText\n");
+        assert_eq!(
+            to_json(&fm, &ConvertOptions::default()).unwrap()["tags"],
+            json!(["a", "b"])
+        );
+    }
+
+    #[test]
+    fn test_split_toml_and_missing() {
+        let doc = "+++\ntitle = \"Hi\"\n+++\nBody";
+        let fm = split(doc).unwrap();
+        assert_eq!(fm.format, Format::Toml);
+        assert_eq!(fm.body, "Body");
+        assert_eq!(
+            to_json(&fm, &ConvertOptions::default()).unwrap()["title"],
+            "Hi"
+        );
+
+        assert!(split("# Just markdown\n").is_none());
+        assert!(split("---\nunterminated: true\n").is_none());
+    }
+
+    #[test]
+    fn test_render_preserves_body() {
+        let doc = "---\ntitle: Old\n---\nBody line\n";
+        let fm = split(doc).unwrap();
+        let mut data = to_json(&fm, &ConvertOptions::default()).unwrap();
+        data["title"] = json!("New");
+
+        let rendered = render(fm.format, &data, fm.body, &ConvertOptions::default()).unwrap();
+        assert_eq!(rendered, "---\ntitle: New\n---\nBody line\n");
+    }
+}
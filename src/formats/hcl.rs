@@ -0,0 +1,64 @@
+//! HCL (HashiCorp Configuration Language) input, read only
+//!
+//! Bodies map to objects the way Terraform's JSON syntax does: attributes
+//! become keys, blocks nest under their type and labels, and repeated blocks
+//! collect into arrays. Expressions that are not literal values (references,
+//! function calls, conditionals) are kept as `${...}` template strings.
+
+use anyhow::{Context, Result};
+use serde_json::Value as JsonValue;
+
+/// Parse an HCL document into a JSON value
+pub fn parse(content: &str) -> Result<JsonValue> {
+    ::hcl::from_str(content).context("Failed to parse HCL")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_terraform() {
+        let content = r#"
+variable "region" {
+  default = "us-east-1"
+}
+
+resource "aws_instance" "web" {
+  ami           = "ami-123"
+  instance_type = "t3.micro"
+  count         = 2
+  tags = {
+    Name = "web-${var.region}"
+  }
+  subnet_id = aws_subnet.main.id
+}
+
+resource "aws_instance" "db" {
+  ami = "ami-456"
+}
+
+ingress { port = 80 }
+ingress { port = 443 }
+"#;
+        let value = parse(content).unwrap();
+        assert_eq!(value["variable"]["region"]["default"], json!("us-east-1"));
+        assert_eq!(value["resource"]["aws_instance"]["web"]["count"], json!(2));
+        assert_eq!(
+            value["resource"]["aws_instance"]["web"]["tags"]["Name"],
+            json!("web-${var.region}")
+        );
+        assert_eq!(
+            value["resource"]["aws_instance"]["web"]["subnet_id"],
+            json!("${aws_subnet.main.id}")
+        );
+        assert_eq!(
+            value["resource"]["aws_instance"]["db"]["ami"],
+            json!("ami-456")
+        );
+        assert_eq!(value["ingress"], json!([{"port": 80}, {"port": 443}]));
+
+        assert!(parse("resource \"x\" {").is_err());
+    }
+}
@@ -0,0 +1,315 @@
+//! HTML table handling (read only)
+//!
+//! Extracts one `<table>` of an HTML page into [`CsvData`], the first row
+//! becoming the headers. The scanner is lenient like a browser: end tags for
+//! cells and rows may be omitted, `colspan` and `rowspan` cells are repeated
+//! into every slot they cover, and scripts, styles and comments are skipped.
+
+use anyhow::{bail, Result};
+
+use super::csv::CsvData;
+
+#[derive(Default)]
+struct Table {
+    rows: Vec<Vec<String>>,
+    row: Option<Vec<String>>,
+    cell: Option<Cell>,
+    /// Cells spanning down from earlier rows: column -> (rows left, text)
+    spans: Vec<Option<(usize, String)>>,
+}
+
+struct Cell {
+    text: String,
+    colspan: usize,
+    rowspan: usize,
+}
+
+impl Table {
+    fn start_row(&mut self) {
+        self.end_row();
+        self.row = Some(Vec::new());
+    }
+
+    fn start_cell(&mut self, colspan: usize, rowspan: usize) {
+        self.end_cell();
+        if self.row.is_none() {
+            self.row = Some(Vec::new());
+        }
+        self.cell = Some(Cell {
+            text: String::new(),
+            colspan,
+            rowspan,
+        });
+    }
+
+    fn end_cell(&mut self) {
+        let (Some(cell), Some(row)) = (self.cell.take(), self.row.as_mut()) else {
+            return;
+        };
+        let text = collapse_whitespace(&cell.text);
+        for _ in 0..cell.colspan {
+            fill_spans(row, &mut self.spans);
+            let column = row.len();
+            if cell.rowspan > 1 {
+                if self.spans.len() <= column {
+                    self.spans.resize(column + 1, None);
+                }
+                self.spans[column] = Some((cell.rowspan - 1, text.clone()));
+            }
+            row.push(text.clone());
+        }
+    }
+
+    fn end_row(&mut self) {
+        self.end_cell();
+        if let Some(mut row) = self.row.take() {
+            fill_spans(&mut row, &mut self.spans);
+            // Trailing spans continue past the last explicit cell
+            while self.spans.get(row.len()).is_some_and(Option::is_some) {
+                row.push(String::new());
+                fill_spans(&mut row, &mut self.spans);
+            }
+            if !row.is_empty() {
+                self.rows.push(row);
+            }
+        }
+    }
+}
+
+/// Take the text of cells spanning down into the next slots of `row`
+fn fill_spans(row: &mut Vec<String>, spans: &mut [Option<(usize, String)>]) {
+    loop {
+        let column = row.len();
+        let Some(slot) = spans.get_mut(column) else {
+            return;
+        };
+        let Some((left, text)) = slot.as_mut() else {
+            return;
+        };
+        row.push(text.clone());
+        *left -= 1;
+        if *left == 0 {
+            *slot = None;
+        }
+    }
+}
+
+/// Read table `index` (1-based; the first when unset) of an HTML document
+pub fn read_table(html: &str, index: Option<usize>) -> Result<CsvData> {
+    let tables = tables(html);
+    if tables.is_empty() {
+        bail!("No <table> found in HTML input");
+    }
+    let wanted = index.unwrap_or(1);
+    let Some(mut rows) = wanted
+        .checked_sub(1)
+        .and_then(|i| tables.into_iter().nth(i))
+    else {
+        bail!(
+            "No table {} in HTML input (tables are numbered from 1)",
+            wanted
+        );
+    };
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize(width, String::new());
+    }
+    if rows.is_empty() {
+        return Ok(CsvData::with_headers(Vec::new(), Vec::new()));
+    }
+    let headers = rows.remove(0);
+    Ok(CsvData::with_headers(headers, rows))
+}
+
+/// Rows of every table, in the order the tables open
+fn tables(html: &str) -> Vec<Vec<Vec<String>>> {
+    let mut finished: Vec<(usize, Vec<Vec<String>>)> = Vec::new();
+    let mut open: Vec<(usize, Table)> = Vec::new();
+    let mut opened = 0;
+    let mut rest = html;
+
+    while let Some(lt) = rest.find('<') {
+        if let Some((_, table)) = open.last_mut() {
+            if let Some(cell) = table.cell.as_mut() {
+                cell.text.push_str(&decode_entities(&rest[..lt]));
+            }
+        }
+        rest = &rest[lt..];
+
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        let Some(gt) = rest.find('>') else {
+            break;
+        };
+        let tag = &rest[1..gt];
+        rest = &rest[gt + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+
+        match (name.as_str(), closing) {
+            ("script" | "style", false) => {
+                let end = format!("</{}", name);
+                let lower = rest.to_ascii_lowercase();
+                rest = lower.find(&end).map_or("", |pos| &rest[pos..]);
+            }
+            ("table", false) => {
+                open.push((opened, Table::default()));
+                opened += 1;
+            }
+            ("table", true) => {
+                if let Some((order, mut table)) = open.pop() {
+                    table.end_row();
+                    finished.push((order, table.rows));
+                }
+            }
+            (_, _) if open.is_empty() => {}
+            ("tr", false) => open.last_mut().unwrap().1.start_row(),
+            ("tr", true) | ("thead" | "tbody" | "tfoot", _) => open.last_mut().unwrap().1.end_row(),
+            ("td" | "th", false) => {
+                let colspan = span_attribute(tag, "colspan");
+                let rowspan = span_attribute(tag, "rowspan");
+                open.last_mut().unwrap().1.start_cell(colspan, rowspan);
+            }
+            ("td" | "th", true) => open.last_mut().unwrap().1.end_cell(),
+            ("br" | "p" | "div" | "li", _) => {
+                if let Some(cell) = open.last_mut().unwrap().1.cell.as_mut() {
+                    cell.text.push(' ');
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Tables left open at the end of the document
+    while let Some((order, mut table)) = open.pop() {
+        table.end_row();
+        finished.push((order, table.rows));
+    }
+    finished.sort_by_key(|(order, _)| *order);
+    finished.into_iter().map(|(_, rows)| rows).collect()
+}
+
+/// A `colspan`/`rowspan` attribute of a tag, 1 when absent or invalid
+fn span_attribute(tag: &str, attribute: &str) -> usize {
+    let lower = tag.to_ascii_lowercase();
+    let Some(pos) = lower.find(attribute) else {
+        return 1;
+    };
+    let value = lower[pos + attribute.len()..]
+        .trim_start()
+        .strip_prefix('=')
+        .unwrap_or_default()
+        .trim_start()
+        .trim_start_matches(['"', '\'']);
+    let digits: String = value.chars().take_while(char::is_ascii_digit).collect();
+    // Browsers cap spans; the cap also bounds the work per cell
+    digits.parse().unwrap_or(1).clamp(1, 1000)
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Decode character references; unknown named entities are kept as written
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|&end| end <= 32)
+            .map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let code = entity.strip_prefix('#')?;
+                let number = match code.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => code.parse().ok()?,
+                };
+                char::from_u32(number)
+            }
+        });
+        match (entity, character) {
+            (Some(entity), Some(c)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_table_lenient_markup() {
+        let html = r#"<html><head><style>td { color: red }</style></head><body>
+            <table id="nav"><tr><td>Home</td></tr></table>
+            <!-- <table><tr><td>hidden</td></tr></table> -->
+            <TABLE class=report>
+              <caption>Q1</caption>
+              <thead><tr><th>Region<th>Sales &amp; returns</thead>
+              <tr><td>East <b>wing</b><td>1,250
+              <tr><td rowspan="2">West</td><td>&#36;900</td></tr>
+              <tr><td colspan=2>n/a</td></tr>
+            </TABLE>
+            <script>document.write("<table>")</script>"#;
+
+        let data = read_table(html, Some(2)).unwrap();
+        assert_eq!(data.headers.unwrap(), ["Region", "Sales & returns", ""]);
+        assert_eq!(
+            data.rows,
+            [
+                vec!["East wing", "1,250"],
+                vec!["West", "$900"],
+                vec!["West", "n/a", "n/a"],
+            ]
+            .map(|row| {
+                let mut row: Vec<String> = row.into_iter().map(String::from).collect();
+                row.resize(3, String::new());
+                row
+            })
+        );
+
+        assert_eq!(read_table(html, None).unwrap().headers.unwrap(), ["Home"]);
+        assert!(read_table(html, Some(3)).is_err());
+        assert!(read_table("<p>none</p>", None).is_err());
+    }
+
+    #[test]
+    fn test_nested_tables_are_separate() {
+        let html = "<table><tr><td>outer<table><tr><td>inner</td></tr></table></td></tr>\
+                    <tr><td>2</td></tr></table>";
+        let outer = read_table(html, Some(1)).unwrap();
+        assert_eq!(outer.headers.unwrap(), ["outer"]);
+        assert_eq!(outer.rows, [vec!["2".to_string()]]);
+        assert_eq!(
+            read_table(html, Some(2)).unwrap().headers.unwrap(),
+            ["inner"]
+        );
+    }
+}
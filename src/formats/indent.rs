@@ -0,0 +1,24 @@
+//! Indentation used by the JSON, XML and TOML serializers (`--indent`, `--tabs`)
+
+/// One level of indentation; two spaces by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Indent {
+    Spaces(usize),
+    Tab,
+}
+
+impl Indent {
+    /// Text of one indentation level
+    pub fn unit(&self) -> String {
+        match self {
+            Indent::Spaces(n) => " ".repeat(*n),
+            Indent::Tab => "\t".to_string(),
+        }
+    }
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
@@ -1,38 +1,246 @@
 //! JSON format handling
 
 use anyhow::{Context, Result};
+use serde::Serialize;
+use serde_json::ser::{CompactFormatter, Formatter, PrettyFormatter};
 use serde_json::Value;
-use std::fs;
-use std::io::{self, Read};
+use std::io::{BufRead, Write};
 use std::path::Path;
 
+use crate::formats::float::FloatFormatter;
+use crate::formats::OutputStyle;
+use crate::utils::diagnostics;
+use crate::utils::encoding::EncodingOptions;
+
 /// Read input from file or stdin
-pub fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
-        Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
-        }
-        None => {
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
-        }
-    }
+pub fn read_input(path: Option<&Path>, encoding: &EncodingOptions) -> Result<String> {
+    encoding.read_input(path)
 }
 
 /// Parse JSON string into Value
 pub fn parse(content: &str) -> Result<Value> {
-    serde_json::from_str(content).context("Failed to parse JSON")
+    serde_json::from_str(content)
+        .map_err(|e| diagnostics::located(e, content, "Failed to parse JSON"))
 }
 
 /// Convert Value to pretty-printed JSON string
-pub fn to_pretty(value: &Value) -> Result<String> {
-    serde_json::to_string_pretty(value).context("Failed to serialize JSON")
+///
+/// Uses the indentation of `style`, two spaces by default.
+pub fn to_pretty<T: Serialize + ?Sized>(value: &T, style: &OutputStyle) -> Result<String> {
+    let unit = style.indent.unwrap_or_default().unit();
+    serialize(value, PrettyFormatter::with_indent(unit.as_bytes()), style)
 }
 
 /// Convert Value to compact JSON string
-pub fn to_compact(value: &Value) -> Result<String> {
-    serde_json::to_string(value).context("Failed to serialize JSON")
+pub fn to_compact(value: &Value, style: &OutputStyle) -> Result<String> {
+    serialize(value, CompactFormatter, style)
+}
+
+/// Write `value` as JSON to `writer` on one line
+pub fn write_compact<W: Write>(writer: &mut W, value: &Value, style: &OutputStyle) -> Result<()> {
+    let result = match style.float {
+        Some(format) => value.serialize(&mut serde_json::Serializer::with_formatter(
+            writer,
+            FloatFormatter::new(CompactFormatter, format),
+        )),
+        None => value.serialize(&mut serde_json::Serializer::new(writer)),
+    };
+    result.context("Failed to serialize JSON")
+}
+
+/// Serialize with `formatter`, writing floats as `style` requests
+fn serialize<T: Serialize + ?Sized, F: Formatter>(
+    value: &T,
+    formatter: F,
+    style: &OutputStyle,
+) -> Result<String> {
+    let mut output = Vec::new();
+    let result = match style.float {
+        Some(format) => value.serialize(&mut serde_json::Serializer::with_formatter(
+            &mut output,
+            FloatFormatter::new(formatter, format),
+        )),
+        None => value.serialize(&mut serde_json::Serializer::with_formatter(
+            &mut output,
+            formatter,
+        )),
+    };
+    result.context("Failed to serialize JSON")?;
+    String::from_utf8(output).context("Failed to serialize JSON")
+}
+
+/// Incremental reader over the elements of a top-level JSON array
+///
+/// Elements are parsed one at a time, so memory use is bounded by the largest
+/// element rather than the whole document.
+pub struct ArrayStream<R: BufRead> {
+    reader: R,
+    started: bool,
+    finished: bool,
+    buffer: Vec<u8>,
+}
+
+impl<R: BufRead> ArrayStream<R> {
+    /// Create a stream over the array read from `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            started: false,
+            finished: false,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<Option<u8>> {
+        let buf = self
+            .reader
+            .fill_buf()
+            .context("Failed to read JSON input")?;
+        let byte = buf.first().copied();
+        if byte.is_some() {
+            self.reader.consume(1);
+        }
+        Ok(byte)
+    }
+
+    fn next_non_whitespace(&mut self) -> Result<Option<u8>> {
+        while let Some(b) = self.next_byte()? {
+            if !b.is_ascii_whitespace() {
+                return Ok(Some(b));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Collect the bytes of the next element; returns the delimiter that ended it
+    fn read_element(&mut self, first: u8) -> Result<u8> {
+        self.buffer.clear();
+        self.buffer.push(first);
+
+        let mut depth = usize::from(first == b'{' || first == b'[');
+        let mut in_string = first == b'"';
+        let mut escaped = false;
+
+        loop {
+            let b = self
+                .next_byte()?
+                .context("Unexpected end of input inside JSON array")?;
+
+            if in_string {
+                self.buffer.push(b);
+                if escaped {
+                    escaped = false;
+                } else if b == b'\\' {
+                    escaped = true;
+                } else if b == b'"' {
+                    in_string = false;
+                }
+                continue;
+            }
+
+            match b {
+                b',' | b']' if depth == 0 => return Ok(b),
+                b'"' => in_string = true,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            self.buffer.push(b);
+        }
+    }
+
+    fn next_element(&mut self) -> Result<Option<Value>> {
+        if self.finished {
+            return Ok(None);
+        }
+
+        if !self.started {
+            self.started = true;
+            match self.next_non_whitespace()? {
+                Some(b'[') => {}
+                _ => anyhow::bail!("Streaming requires a top-level JSON array"),
+            }
+        }
+
+        let first = match self.next_non_whitespace()? {
+            Some(b']') => {
+                self.finished = true;
+                return Ok(None);
+            }
+            Some(b) => b,
+            None => anyhow::bail!("Unexpected end of input inside JSON array"),
+        };
+
+        let delimiter = self.read_element(first)?;
+        if delimiter == b']' {
+            self.finished = true;
+        }
+
+        serde_json::from_slice(&self.buffer)
+            .map(Some)
+            .context("Failed to parse JSON array element")
+    }
+}
+
+impl<R: BufRead> Iterator for ArrayStream<R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_element() {
+            Ok(Some(value)) => Some(Ok(value)),
+            Ok(None) => None,
+            Err(e) => {
+                self.finished = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::indent::Indent;
+    use serde_json::json;
+
+    #[test]
+    fn test_to_pretty_with_indent() {
+        let value = json!({"a": [1]});
+        let style = |indent| OutputStyle {
+            indent: Some(indent),
+            ..Default::default()
+        };
+        assert_eq!(
+            to_pretty(&value, &style(Indent::Spaces(4))).unwrap(),
+            "{\n    \"a\": [\n        1\n    ]\n}"
+        );
+        assert_eq!(
+            to_pretty(&value, &style(Indent::Tab)).unwrap(),
+            "{\n\t\"a\": [\n\t\t1\n\t]\n}"
+        );
+    }
+
+    #[test]
+    fn test_array_stream() {
+        let input = r#" [ {"a": [1, 2], "s": "x,]y\""}, 42, "str", null , [] ] "#;
+        let items: Vec<Value> = ArrayStream::new(input.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(
+            items,
+            vec![
+                json!({"a": [1, 2], "s": "x,]y\""}),
+                json!(42),
+                json!("str"),
+                Value::Null,
+                json!([])
+            ]
+        );
+
+        assert_eq!(ArrayStream::new("[]".as_bytes()).count(), 0);
+        assert!(ArrayStream::new(r#"{"a": 1}"#.as_bytes())
+            .next()
+            .unwrap()
+            .is_err());
+    }
 }
@@ -1,8 +1,39 @@
 //! Format handlers for different data formats
 
+pub mod avro;
+pub mod binary;
+pub mod bson;
 pub mod csv;
 pub mod detect;
+pub mod float;
+pub mod frontmatter;
+pub mod hcl;
+pub mod html;
+pub mod indent;
 pub mod json;
+pub mod msgpack;
+pub mod ndjson;
+pub mod parquet;
+pub mod plist;
+pub mod properties;
+pub mod proto;
+pub mod registry;
+pub mod sql;
+pub mod sqlite;
 pub mod toml;
+pub mod xlsx;
 pub mod xml;
 pub mod yaml;
+
+use float::FloatFormat;
+use indent::Indent;
+
+/// Layout of serialized text output, set by the global output flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputStyle {
+    /// Indentation of pretty-printed output (`--indent`, `--tabs`); each
+    /// serializer's default when unset
+    pub indent: Option<Indent>,
+    /// Float formatting (`--float-*`); each serializer's default when unset
+    pub float: Option<FloatFormat>,
+}
@@ -0,0 +1,342 @@
+//! MessagePack format handling
+//!
+//! Values map onto JSON as closely as MessagePack allows: binary data becomes
+//! an array of byte values, non-string map keys are written as text, and
+//! timestamps (extension type -1) become RFC 3339 strings.
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, SecondsFormat};
+use serde_json::{Map, Value as JsonValue};
+
+use super::binary::{float, ByteReader};
+
+/// Nesting deeper than this is rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 512;
+
+/// Extension type of MessagePack timestamps
+const TIMESTAMP_EXT: i8 = -1;
+
+/// Parse one MessagePack value
+pub fn parse(bytes: &[u8]) -> Result<JsonValue> {
+    let mut decoder = Decoder {
+        input: ByteReader::new(bytes, "MessagePack"),
+    };
+    let value = decoder.value(0)?;
+    if !decoder.input.is_empty() {
+        bail!(
+            "Trailing data after MessagePack value at byte {}",
+            decoder.input.pos()
+        );
+    }
+    Ok(value)
+}
+
+/// Encode a value as MessagePack
+pub fn to_vec(value: &JsonValue) -> Vec<u8> {
+    let mut output = Vec::new();
+    encode(value, &mut output);
+    output
+}
+
+struct Decoder<'a> {
+    input: ByteReader<'a>,
+}
+
+impl<'a> Decoder<'a> {
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_be_bytes(self.input.array()?))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_be_bytes(self.input.array()?))
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        Ok(u64::from_be_bytes(self.input.array()?))
+    }
+
+    fn value(&mut self, depth: usize) -> Result<JsonValue> {
+        if depth > MAX_DEPTH {
+            bail!("MessagePack data nested deeper than {} levels", MAX_DEPTH);
+        }
+        let start = self.input.pos();
+        let marker = self.input.u8()?;
+        let value = match marker {
+            0x00..=0x7f => JsonValue::from(marker),
+            0x80..=0x8f => self.map((marker & 0x0f) as usize, depth)?,
+            0x90..=0x9f => self.seq((marker & 0x0f) as usize, depth)?,
+            0xa0..=0xbf => self.str((marker & 0x1f) as usize)?,
+            0xc0 => JsonValue::Null,
+            0xc2 => JsonValue::Bool(false),
+            0xc3 => JsonValue::Bool(true),
+            0xc4 => {
+                let len = self.input.u8()? as usize;
+                self.bin(len)?
+            }
+            0xc5 => {
+                let len = self.u16()? as usize;
+                self.bin(len)?
+            }
+            0xc6 => {
+                let len = self.u32()? as usize;
+                self.bin(len)?
+            }
+            0xc7 => {
+                let len = self.input.u8()? as usize;
+                self.ext(len)?
+            }
+            0xc8 => {
+                let len = self.u16()? as usize;
+                self.ext(len)?
+            }
+            0xc9 => {
+                let len = self.u32()? as usize;
+                self.ext(len)?
+            }
+            0xca => float(f32::from_be_bytes(self.input.array()?) as f64),
+            0xcb => float(f64::from_be_bytes(self.input.array()?)),
+            0xcc => JsonValue::from(self.input.u8()?),
+            0xcd => JsonValue::from(self.u16()?),
+            0xce => JsonValue::from(self.u32()?),
+            0xcf => JsonValue::from(self.u64()?),
+            0xd0 => JsonValue::from(self.input.u8()? as i8),
+            0xd1 => JsonValue::from(self.u16()? as i16),
+            0xd2 => JsonValue::from(self.u32()? as i32),
+            0xd3 => JsonValue::from(self.u64()? as i64),
+            0xd4 => self.ext(1)?,
+            0xd5 => self.ext(2)?,
+            0xd6 => self.ext(4)?,
+            0xd7 => self.ext(8)?,
+            0xd8 => self.ext(16)?,
+            0xd9 => {
+                let len = self.input.u8()? as usize;
+                self.str(len)?
+            }
+            0xda => {
+                let len = self.u16()? as usize;
+                self.str(len)?
+            }
+            0xdb => {
+                let len = self.u32()? as usize;
+                self.str(len)?
+            }
+            0xdc => {
+                let len = self.u16()? as usize;
+                self.seq(len, depth)?
+            }
+            0xdd => {
+                let len = self.u32()? as usize;
+                self.seq(len, depth)?
+            }
+            0xde => {
+                let len = self.u16()? as usize;
+                self.map(len, depth)?
+            }
+            0xdf => {
+                let len = self.u32()? as usize;
+                self.map(len, depth)?
+            }
+            0xe0..=0xff => JsonValue::from(marker as i8),
+            0xc1 => bail!("Invalid MessagePack marker 0xc1 at byte {}", start),
+        };
+        Ok(value)
+    }
+
+    fn str(&mut self, len: usize) -> Result<JsonValue> {
+        let start = self.input.pos();
+        let bytes = self.input.take(len)?;
+        let text = std::str::from_utf8(bytes)
+            .with_context(|| format!("Invalid UTF-8 in MessagePack string at byte {}", start))?;
+        Ok(JsonValue::String(text.to_string()))
+    }
+
+    fn bin(&mut self, len: usize) -> Result<JsonValue> {
+        Ok(JsonValue::Array(
+            self.input
+                .take(len)?
+                .iter()
+                .map(|&b| JsonValue::from(b))
+                .collect(),
+        ))
+    }
+
+    fn seq(&mut self, len: usize, depth: usize) -> Result<JsonValue> {
+        // Every element takes at least one byte, so a corrupt length can't
+        // reserve more than the input holds
+        let mut items = Vec::with_capacity(len.min(self.input.remaining()));
+        for _ in 0..len {
+            items.push(self.value(depth + 1)?);
+        }
+        Ok(JsonValue::Array(items))
+    }
+
+    fn map(&mut self, len: usize, depth: usize) -> Result<JsonValue> {
+        let mut map = Map::new();
+        for _ in 0..len {
+            let key = match self.value(depth + 1)? {
+                JsonValue::String(s) => s,
+                other => other.to_string(),
+            };
+            let value = self.value(depth + 1)?;
+            map.insert(key, value);
+        }
+        Ok(JsonValue::Object(map))
+    }
+
+    fn ext(&mut self, len: usize) -> Result<JsonValue> {
+        let start = self.input.pos();
+        let kind = self.input.u8()? as i8;
+        let data = self.input.take(len)?;
+        if kind != TIMESTAMP_EXT {
+            return Ok(serde_json::json!({ "ext": kind, "data": data }));
+        }
+
+        let (secs, nanos) = match data.len() {
+            4 => (u32::from_be_bytes(data.try_into()?) as i64, 0),
+            8 => {
+                let raw = u64::from_be_bytes(data.try_into()?);
+                ((raw & 0x3_ffff_ffff) as i64, (raw >> 34) as u32)
+            }
+            12 => (
+                i64::from_be_bytes(data[4..].try_into()?),
+                u32::from_be_bytes(data[..4].try_into()?),
+            ),
+            n => bail!("Invalid {}-byte MessagePack timestamp at byte {}", n, start),
+        };
+        let time = DateTime::from_timestamp(secs, nanos)
+            .with_context(|| format!("MessagePack timestamp out of range at byte {}", start))?;
+        Ok(JsonValue::String(
+            time.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        ))
+    }
+}
+
+fn encode(value: &JsonValue, output: &mut Vec<u8>) {
+    match value {
+        JsonValue::Null => output.push(0xc0),
+        JsonValue::Bool(b) => output.push(if *b { 0xc3 } else { 0xc2 }),
+        JsonValue::Number(n) => {
+            if let Some(u) = n.as_u64() {
+                encode_uint(u, output);
+            } else if let Some(i) = n.as_i64() {
+                encode_int(i, output);
+            } else {
+                output.push(0xcb);
+                output.extend(n.as_f64().unwrap_or_default().to_be_bytes());
+            }
+        }
+        JsonValue::String(s) => {
+            encode_len(s.len(), 0xa0, 31, [0xd9, 0xda, 0xdb], output);
+            output.extend(s.as_bytes());
+        }
+        JsonValue::Array(items) => {
+            encode_len(items.len(), 0x90, 15, [0, 0xdc, 0xdd], output);
+            for item in items {
+                encode(item, output);
+            }
+        }
+        JsonValue::Object(map) => {
+            encode_len(map.len(), 0x80, 15, [0, 0xde, 0xdf], output);
+            for (key, value) in map {
+                encode(&JsonValue::String(key.clone()), output);
+                encode(value, output);
+            }
+        }
+    }
+}
+
+/// Write a length with its fix-size marker when it fits, otherwise the 8/16/32
+/// bit marker (a zero marker means that width does not exist for the type)
+fn encode_len(len: usize, fix: u8, fix_max: usize, markers: [u8; 3], output: &mut Vec<u8>) {
+    if len <= fix_max {
+        output.push(fix | len as u8);
+    } else if len <= u8::MAX as usize && markers[0] != 0 {
+        output.extend([markers[0], len as u8]);
+    } else if len <= u16::MAX as usize {
+        output.push(markers[1]);
+        output.extend((len as u16).to_be_bytes());
+    } else {
+        output.push(markers[2]);
+        output.extend((len as u32).to_be_bytes());
+    }
+}
+
+fn encode_uint(u: u64, output: &mut Vec<u8>) {
+    if u <= 0x7f {
+        output.push(u as u8);
+    } else if u <= u8::MAX as u64 {
+        output.extend([0xcc, u as u8]);
+    } else if u <= u16::MAX as u64 {
+        output.push(0xcd);
+        output.extend((u as u16).to_be_bytes());
+    } else if u <= u32::MAX as u64 {
+        output.push(0xce);
+        output.extend((u as u32).to_be_bytes());
+    } else {
+        output.push(0xcf);
+        output.extend(u.to_be_bytes());
+    }
+}
+
+/// Negative integers only; non-negative ones go through `encode_uint`
+fn encode_int(i: i64, output: &mut Vec<u8>) {
+    if i >= -32 {
+        output.push(i as u8);
+    } else if i >= i8::MIN as i64 {
+        output.extend([0xd0, i as u8]);
+    } else if i >= i16::MIN as i64 {
+        output.push(0xd1);
+        output.extend((i as i16).to_be_bytes());
+    } else if i >= i32::MIN as i64 {
+        output.push(0xd2);
+        output.extend((i as i32).to_be_bytes());
+    } else {
+        output.push(0xd3);
+        output.extend(i.to_be_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_round_trip() {
+        let value = json!({
+            "name": "dtx",
+            "tags": ["a", "b"],
+            "count": 300,
+            "offset": -200,
+            "big": u64::MAX,
+            "ratio": 0.5,
+            "enabled": true,
+            "missing": null,
+            "long": "x".repeat(40),
+        });
+        assert_eq!(parse(&to_vec(&value)).unwrap(), value);
+    }
+
+    #[test]
+    fn test_encoding() {
+        assert_eq!(to_vec(&json!({"a": 1})), [0x81, 0xa1, b'a', 0x01]);
+        assert_eq!(to_vec(&json!(-1)), [0xff]);
+        assert_eq!(to_vec(&json!(200)), [0xcc, 200]);
+        assert_eq!(to_vec(&json!(-100)), [0xd0, 0x9c]);
+    }
+
+    #[test]
+    fn test_parse_msgpack_only_types() {
+        // bin 8, a map with an integer key, and a 32-bit timestamp
+        assert_eq!(parse(&[0xc4, 0x02, 0x01, 0xff]).unwrap(), json!([1, 255]));
+        assert_eq!(parse(&[0x81, 0x01, 0xc3]).unwrap(), json!({"1": true}));
+        assert_eq!(
+            parse(&[0xd6, 0xff, 0x00, 0x00, 0x00, 0x3c]).unwrap(),
+            json!("1970-01-01T00:01:00Z")
+        );
+
+        assert!(parse(&[0x92, 0x01]).is_err());
+        assert!(parse(&[0x01, 0x02]).is_err());
+        assert!(parse(&[0xc1]).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! NDJSON (newline-delimited JSON / JSON Lines) format handling
+
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::io::{BufRead, Write};
+
+use crate::formats::json as json_format;
+use crate::formats::OutputStyle;
+
+/// Parse NDJSON into one value per non-blank line
+pub fn parse(content: &str) -> Result<Vec<Value>> {
+    LineStream::new(content.as_bytes()).collect()
+}
+
+/// Serialize values as NDJSON, one compact value per line
+pub fn to_string(values: &[Value], style: &OutputStyle) -> Result<String> {
+    let mut output = Vec::new();
+    for value in values {
+        write_line(&mut output, value, style)?;
+    }
+    String::from_utf8(output).context("Invalid UTF-8 in NDJSON output")
+}
+
+/// Write a single value as an NDJSON line
+pub fn write_line<W: Write>(writer: &mut W, value: &Value, style: &OutputStyle) -> Result<()> {
+    json_format::write_compact(&mut *writer, value, style)?;
+    writer.write_all(b"\n").context("Failed to write output")
+}
+
+/// Incremental reader yielding one value per NDJSON line
+pub struct LineStream<R: BufRead> {
+    reader: R,
+    line: String,
+    line_number: usize,
+}
+
+impl<R: BufRead> LineStream<R> {
+    /// Create a stream over NDJSON read from `reader`
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: String::new(),
+            line_number: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for LineStream<R> {
+    type Item = Result<Value>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(err) => return Some(Err(err).context("Failed to read NDJSON input")),
+            }
+            self.line_number += 1;
+
+            // Blank lines between records are tolerated
+            let trimmed = self.line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            return Some(
+                serde_json::from_str(trimmed).with_context(|| {
+                    format!("Failed to parse NDJSON at line {}", self.line_number)
+                }),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_and_serialize() {
+        let input = "{\"a\":1}\n\n{\"a\":2,\"b\":[true]}\r\n";
+        let values = parse(input).unwrap();
+        assert_eq!(values, vec![json!({"a": 1}), json!({"a": 2, "b": [true]})]);
+        assert_eq!(
+            to_string(&values, &OutputStyle::default()).unwrap(),
+            "{\"a\":1}\n{\"a\":2,\"b\":[true]}\n"
+        );
+
+        let err = parse("{\"a\":1}\n{oops}\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+}
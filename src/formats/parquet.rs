@@ -0,0 +1,1073 @@
+//! Parquet format handling (read only)
+//!
+//! Reads flat Parquet files into an array of row objects. Columns may be
+//! required or optional; nested and repeated columns are rejected. Pages may be
+//! PLAIN or dictionary encoded, and uncompressed, Snappy or gzip compressed.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, NaiveTime, SecondsFormat};
+use serde_json::{Map, Number, Value as JsonValue};
+use std::borrow::Cow;
+use std::io::Read;
+
+use super::binary::{float, ByteReader};
+
+const MAGIC: &[u8] = b"PAR1";
+
+/// Nesting deeper than this in Thrift metadata is treated as corruption
+const MAX_THRIFT_DEPTH: usize = 64;
+
+/// Julian day number of 1970-01-01, the epoch of INT96 timestamps
+const JULIAN_UNIX_EPOCH: i64 = 2_440_588;
+
+/// Parse a Parquet file into an array of row objects
+pub fn parse(bytes: &[u8]) -> Result<JsonValue> {
+    if bytes.len() < 12 || !bytes.starts_with(MAGIC) || !bytes.ends_with(MAGIC) {
+        bail!("Not a Parquet file (missing PAR1 magic)");
+    }
+    let footer_end = bytes.len() - 8;
+    let footer_len = u32::from_le_bytes(bytes[footer_end..footer_end + 4].try_into()?) as usize;
+    let footer_start = footer_end
+        .checked_sub(footer_len)
+        .filter(|&start| start >= MAGIC.len())
+        .context("Parquet footer length exceeds the file size")?;
+    let metadata = ThriftReader::new(&bytes[footer_start..footer_end])
+        .structure(0)
+        .context("Failed to read Parquet file metadata")?;
+
+    let columns = columns(metadata.list(2))?;
+    let mut rows = Vec::new();
+    for group in metadata.list(4) {
+        let num_rows = group.required_size(3, "row group row count")?;
+        let chunks = group.list(1);
+        if chunks.len() != columns.len() {
+            bail!(
+                "Row group has {} column chunks but the schema has {} columns",
+                chunks.len(),
+                columns.len()
+            );
+        }
+
+        if columns.is_empty() && num_rows > 0 {
+            bail!("Row group has {} rows but no columns", num_rows);
+        }
+
+        let mut values = chunks
+            .iter()
+            .zip(&columns)
+            .map(|(chunk, column)| {
+                read_column(bytes, chunk, column, num_rows)
+                    .with_context(|| format!("Failed to read Parquet column '{}'", column.name))
+                    .map(Vec::into_iter)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for _ in 0..num_rows {
+            let mut row = Map::new();
+            for (column, values) in columns.iter().zip(values.iter_mut()) {
+                row.insert(column.name.clone(), values.next().unwrap_or_default());
+            }
+            rows.push(JsonValue::Object(row));
+        }
+    }
+
+    Ok(JsonValue::Array(rows))
+}
+
+// ============================================================================
+// Schema
+// ============================================================================
+
+/// Physical types from the Parquet `Type` enum
+mod physical {
+    pub const BOOLEAN: i64 = 0;
+    pub const INT32: i64 = 1;
+    pub const INT64: i64 = 2;
+    pub const INT96: i64 = 3;
+    pub const FLOAT: i64 = 4;
+    pub const DOUBLE: i64 = 5;
+    pub const BYTE_ARRAY: i64 = 6;
+    pub const FIXED_LEN_BYTE_ARRAY: i64 = 7;
+}
+
+/// How a column's physical values are presented
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Plain,
+    String,
+    Json,
+    Uuid,
+    Unsigned,
+    Date,
+    Decimal {
+        scale: i64,
+    },
+    /// Time of day in units of `1 / per_second` seconds
+    Time {
+        per_second: i64,
+    },
+    /// Instant since the Unix epoch in units of `1 / per_second` seconds;
+    /// `utc` timestamps get a `Z` suffix
+    Timestamp {
+        per_second: i64,
+        utc: bool,
+    },
+}
+
+#[derive(Debug)]
+struct Column {
+    name: String,
+    physical: i64,
+    type_length: usize,
+    optional: bool,
+    kind: Kind,
+}
+
+/// Leaf columns of a flat schema, in file order
+fn columns(schema: &[Thrift]) -> Result<Vec<Column>> {
+    let (_root, leaves) = schema.split_first().context("Parquet schema is empty")?;
+    leaves
+        .iter()
+        .map(|element| {
+            let name = element.str(4).unwrap_or_default().to_string();
+            if element.int(5).is_some_and(|children| children > 0) {
+                bail!("Nested Parquet column '{}' is not supported", name);
+            }
+            let optional = match element.int(3) {
+                Some(0) | None => false,
+                Some(1) => true,
+                _ => bail!("Repeated Parquet column '{}' is not supported", name),
+            };
+            Ok(Column {
+                physical: element.required_int(1, "column type")?,
+                type_length: element.int(2).unwrap_or_default().max(0) as usize,
+                optional,
+                kind: kind(element),
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Presentation from the logical type, or the older converted type
+fn kind(element: &Thrift) -> Kind {
+    fn per_second(unit: Option<&Thrift>) -> i64 {
+        match unit {
+            Some(unit) if unit.field(1).is_some() => 1_000,
+            Some(unit) if unit.field(2).is_some() => 1_000_000,
+            _ => 1_000_000_000,
+        }
+    }
+
+    if let Some(logical) = element.field(10) {
+        return match logical {
+            l if l.field(1).is_some() || l.field(4).is_some() => Kind::String,
+            l if l.field(12).is_some() => Kind::Json,
+            l if l.field(14).is_some() => Kind::Uuid,
+            l if l.field(6).is_some() => Kind::Date,
+            l => match (l.field(5), l.field(7), l.field(8), l.field(10)) {
+                (Some(decimal), ..) => Kind::Decimal {
+                    scale: decimal.int(1).unwrap_or_default(),
+                },
+                (_, Some(time), ..) => Kind::Time {
+                    per_second: per_second(time.field(2)),
+                },
+                (_, _, Some(timestamp), _) => Kind::Timestamp {
+                    per_second: per_second(timestamp.field(2)),
+                    utc: timestamp.bool(1).unwrap_or(true),
+                },
+                (_, _, _, Some(int)) if int.bool(2) == Some(false) => Kind::Unsigned,
+                _ => Kind::Plain,
+            },
+        };
+    }
+
+    match element.int(6) {
+        Some(0 | 4) => Kind::String,
+        Some(5) => Kind::Decimal {
+            scale: element.int(7).unwrap_or_default(),
+        },
+        Some(6) => Kind::Date,
+        Some(7) => Kind::Time { per_second: 1_000 },
+        Some(8) => Kind::Time {
+            per_second: 1_000_000,
+        },
+        Some(9) => Kind::Timestamp {
+            per_second: 1_000,
+            utc: true,
+        },
+        Some(10) => Kind::Timestamp {
+            per_second: 1_000_000,
+            utc: true,
+        },
+        Some(11..=14) => Kind::Unsigned,
+        Some(19) => Kind::Json,
+        _ => Kind::Plain,
+    }
+}
+
+// ============================================================================
+// Column chunks and pages
+// ============================================================================
+
+/// Page types from the Parquet `PageType` enum
+const DATA_PAGE: i64 = 0;
+const DICTIONARY_PAGE: i64 = 2;
+const DATA_PAGE_V2: i64 = 3;
+
+/// Encodings from the Parquet `Encoding` enum
+const PLAIN: i64 = 0;
+const PLAIN_DICTIONARY: i64 = 2;
+const RLE: i64 = 3;
+const RLE_DICTIONARY: i64 = 8;
+
+/// The values of one column chunk, with nulls for missing optional values
+fn read_column(
+    bytes: &[u8],
+    chunk: &Thrift,
+    column: &Column,
+    num_rows: usize,
+) -> Result<Vec<JsonValue>> {
+    if chunk.field(1).is_some() {
+        bail!("Column chunks in external files are not supported");
+    }
+    let meta = chunk.field(3).context("Column chunk has no metadata")?;
+    let codec = meta.required_int(4, "compression codec")?;
+    let num_values = meta.required_size(5, "value count")?;
+    let size = meta.required_int(7, "compressed size")?;
+    let data_offset = meta.required_int(9, "data page offset")?;
+    let start = match meta.int(11) {
+        Some(dictionary) if dictionary > 0 && dictionary < data_offset => dictionary,
+        _ => data_offset,
+    };
+    let chunk_bytes = usize::try_from(start)
+        .ok()
+        .zip(usize::try_from(size).ok())
+        .and_then(|(start, size)| bytes.get(start..start.checked_add(size)?))
+        .context("Column chunk lies outside the file")?;
+
+    if num_values > num_rows {
+        bail!(
+            "Column chunk has {} values for {} rows",
+            num_values,
+            num_rows
+        );
+    }
+
+    let mut reader = ThriftReader::new(chunk_bytes);
+    let mut dictionary: Option<Vec<JsonValue>> = None;
+    // Sizes come from the file, so allocate no more than its bytes can hold
+    let mut values = Vec::with_capacity(num_rows.min(chunk_bytes.len() * 8));
+    while values.len() < num_values && !reader.input.is_empty() {
+        let header = reader.structure(0).context("Failed to read page header")?;
+        let compressed_size = header.required_size(3, "compressed page size")?;
+        let uncompressed_size = header.required_size(2, "page size")?;
+        let page = reader.input.take(compressed_size)?;
+
+        match header.required_int(1, "page type")? {
+            DICTIONARY_PAGE => {
+                let page_header = header.field(7).context("Missing dictionary page header")?;
+                let count = page_header.required_size(1, "dictionary size")?;
+                let data = decompress(codec, page, uncompressed_size)?;
+                dictionary = Some(decode_plain(&data, column, count)?);
+            }
+            DATA_PAGE => {
+                let page_header = header.field(5).context("Missing data page header")?;
+                let count = page_header.required_size(1, "page value count")?;
+                if count > num_values - values.len() {
+                    bail!("Page holds more values than its column chunk");
+                }
+                let encoding = page_header.required_int(2, "page encoding")?;
+                let data = decompress(codec, page, uncompressed_size)?;
+                let (present, data) = if column.optional {
+                    if page_header.int(3) != Some(RLE) {
+                        bail!("Only RLE definition levels are supported");
+                    }
+                    let (levels, rest) = length_prefixed(&data)?;
+                    (Some(rle_hybrid(levels, 1, count)?), rest)
+                } else {
+                    (None, &data[..])
+                };
+                let page_values = decode_page(
+                    data,
+                    encoding,
+                    column,
+                    present.as_deref(),
+                    count,
+                    dictionary.as_deref(),
+                )?;
+                values.extend(page_values);
+            }
+            DATA_PAGE_V2 => {
+                let page_header = header.field(8).context("Missing data page header")?;
+                let count = page_header.required_size(1, "page value count")?;
+                if count > num_values - values.len() {
+                    bail!("Page holds more values than its column chunk");
+                }
+                let encoding = page_header.required_int(4, "page encoding")?;
+                let definition_len = page_header.required_size(5, "definition levels length")?;
+                let repetition_len = page_header.required_size(6, "repetition levels length")?;
+                let levels_len = repetition_len.saturating_add(definition_len);
+                if levels_len > page.len() {
+                    bail!("Page levels exceed the page size");
+                }
+                // Levels are never compressed in v2 pages, only the values after them
+                let present = (column.optional && definition_len > 0)
+                    .then(|| rle_hybrid(&page[repetition_len..levels_len], 1, count))
+                    .transpose()?;
+                let data = if page_header.bool(7).unwrap_or(true) {
+                    decompress(
+                        codec,
+                        &page[levels_len..],
+                        uncompressed_size.saturating_sub(levels_len),
+                    )?
+                } else {
+                    Cow::Borrowed(&page[levels_len..])
+                };
+                let page_values = decode_page(
+                    &data,
+                    encoding,
+                    column,
+                    present.as_deref(),
+                    count,
+                    dictionary.as_deref(),
+                )?;
+                values.extend(page_values);
+            }
+            // Index pages carry nothing to read
+            _ => {}
+        }
+    }
+
+    if values.len() != num_rows {
+        bail!("Found {} values for {} rows", values.len(), num_rows);
+    }
+    Ok(values)
+}
+
+/// `count` values of a data page, with nulls where the definition level is 0
+fn decode_page(
+    data: &[u8],
+    encoding: i64,
+    column: &Column,
+    present: Option<&[u32]>,
+    count: usize,
+    dictionary: Option<&[JsonValue]>,
+) -> Result<Vec<JsonValue>> {
+    let non_null = present.map_or(count, |levels| levels.iter().filter(|&&l| l == 1).count());
+
+    let decoded = match encoding {
+        PLAIN => decode_plain(data, column, non_null)?,
+        PLAIN_DICTIONARY | RLE_DICTIONARY => {
+            let dictionary =
+                dictionary.context("Dictionary-encoded page without a dictionary page")?;
+            let (&bit_width, indices) = data
+                .split_first()
+                .context("Dictionary-encoded page is empty")?;
+            rle_hybrid(indices, bit_width as usize, non_null)?
+                .into_iter()
+                .map(|i| {
+                    dictionary
+                        .get(i as usize)
+                        .cloned()
+                        .with_context(|| format!("Dictionary index {} out of range", i))
+                })
+                .collect::<Result<_>>()?
+        }
+        RLE if column.physical == physical::BOOLEAN => {
+            let (bits, _) = length_prefixed(data)?;
+            rle_hybrid(bits, 1, non_null)?
+                .into_iter()
+                .map(|b| JsonValue::Bool(b == 1))
+                .collect()
+        }
+        other => bail!("Parquet encoding {} is not supported", encoding_name(other)),
+    };
+
+    Ok(match present {
+        None => decoded,
+        Some(levels) => {
+            let mut decoded = decoded.into_iter();
+            levels
+                .iter()
+                .map(|&level| match level {
+                    1 => decoded.next().unwrap_or_default(),
+                    _ => JsonValue::Null,
+                })
+                .collect()
+        }
+    })
+}
+
+fn encoding_name(encoding: i64) -> Cow<'static, str> {
+    match encoding {
+        4 => "BIT_PACKED".into(),
+        5 => "DELTA_BINARY_PACKED".into(),
+        6 => "DELTA_LENGTH_BYTE_ARRAY".into(),
+        7 => "DELTA_BYTE_ARRAY".into(),
+        9 => "BYTE_STREAM_SPLIT".into(),
+        other => other.to_string().into(),
+    }
+}
+
+/// Split off a section prefixed with its 4-byte little-endian length
+fn length_prefixed(data: &[u8]) -> Result<(&[u8], &[u8])> {
+    let len = data
+        .get(..4)
+        .map(|len| u32::from_le_bytes(len.try_into().expect("4 bytes")) as usize)
+        .context("Truncated length prefix")?;
+    let rest = &data[4..];
+    if len > rest.len() {
+        bail!("Length prefix {} exceeds the page size", len);
+    }
+    Ok(rest.split_at(len))
+}
+
+/// Decode `count` values of the RLE / bit-packing hybrid encoding
+fn rle_hybrid(data: &[u8], bit_width: usize, count: usize) -> Result<Vec<u32>> {
+    if bit_width > 32 {
+        bail!("Invalid bit width {}", bit_width);
+    }
+    let mut reader = ThriftReader::new(data);
+    let mut values = Vec::with_capacity(count.min(data.len() * 8));
+    while values.len() < count {
+        let header = reader.varint().context("Truncated RLE data")? as usize;
+        if header & 1 == 0 {
+            let run = header >> 1;
+            if run == 0 {
+                bail!("Empty RLE run");
+            }
+            let mut value = [0u8; 4];
+            value[..bit_width.div_ceil(8)]
+                .copy_from_slice(reader.input.take(bit_width.div_ceil(8))?);
+            let value = u32::from_le_bytes(value);
+            let run = run.min(count - values.len());
+            values
+                .try_reserve(run)
+                .map_err(|_| anyhow!("RLE run of {} values is too large", run))?;
+            values.extend(std::iter::repeat_n(value, run));
+        } else {
+            let groups = header >> 1;
+            if groups == 0 {
+                bail!("Empty bit-packed run");
+            }
+            let packed = reader.input.take(groups * bit_width)?;
+            for i in 0..(groups * 8).min(count - values.len()) {
+                values.push(unpack(packed, i * bit_width, bit_width));
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// The `width` bits starting at bit `start`, least significant bit first
+fn unpack(data: &[u8], start: usize, width: usize) -> u32 {
+    let mut window = [0u8; 8];
+    let first = start / 8;
+    let available = data.len().saturating_sub(first).min(8);
+    window[..available].copy_from_slice(&data[first..first + available]);
+    let bits = u64::from_le_bytes(window) >> (start % 8);
+    (bits & ((1u64 << width) - 1)) as u32
+}
+
+fn decompress(codec: i64, data: &[u8], uncompressed_size: usize) -> Result<Cow<'_, [u8]>> {
+    match codec {
+        0 => Ok(Cow::Borrowed(data)),
+        1 => Ok(Cow::Owned(snappy(data, uncompressed_size)?)),
+        2 => {
+            let mut output = Vec::with_capacity(uncompressed_size.min(data.len() * 8));
+            flate2::read::MultiGzDecoder::new(data)
+                .take(uncompressed_size as u64 + 1)
+                .read_to_end(&mut output)
+                .context("Invalid gzip page")?;
+            if output.len() > uncompressed_size {
+                bail!("Gzip page holds more than its {} bytes", uncompressed_size);
+            }
+            Ok(Cow::Owned(output))
+        }
+        3 => bail!("Parquet LZO compression is not supported"),
+        4 => bail!("Parquet Brotli compression is not supported"),
+        5 | 7 => bail!("Parquet LZ4 compression is not supported"),
+        6 => bail!("Parquet Zstandard compression is not supported"),
+        other => bail!("Unknown Parquet compression codec {}", other),
+    }
+}
+
+/// Decompress a raw (unframed) Snappy block
+fn snappy(data: &[u8], limit: usize) -> Result<Vec<u8>> {
+    let mut reader = ThriftReader::new(data);
+    let len = reader.varint().context("Truncated Snappy block")? as usize;
+    if len > limit {
+        bail!("Snappy block claims {} bytes, more than the page size", len);
+    }
+    let mut output = Vec::with_capacity(len.min(data.len() * 8));
+    while output.len() < len {
+        let tag = reader.input.u8().context("Truncated Snappy block")?;
+        let (copy_len, offset) = match tag & 3 {
+            0 => {
+                let literal_len = match tag >> 2 {
+                    n @ 0..=59 => n as usize + 1,
+                    n => {
+                        let bytes = reader.input.take(n as usize - 59)?;
+                        let mut value = [0u8; 4];
+                        value[..bytes.len()].copy_from_slice(bytes);
+                        u32::from_le_bytes(value) as usize + 1
+                    }
+                };
+                output.extend_from_slice(reader.input.take(literal_len)?);
+                continue;
+            }
+            1 => (
+                ((tag >> 2) & 7) as usize + 4,
+                ((tag as usize >> 5) << 8) | reader.input.u8()? as usize,
+            ),
+            2 => (
+                (tag >> 2) as usize + 1,
+                u16::from_le_bytes(reader.input.array()?) as usize,
+            ),
+            _ => (
+                (tag >> 2) as usize + 1,
+                u32::from_le_bytes(reader.input.array()?) as usize,
+            ),
+        };
+        if offset == 0 || offset > output.len() {
+            bail!("Invalid Snappy copy offset {}", offset);
+        }
+        // Copies may overlap the bytes they produce, so go one byte at a time
+        let from = output.len() - offset;
+        for i in 0..copy_len {
+            output.push(output[from + i]);
+        }
+    }
+    if output.len() != len {
+        bail!("Snappy block decompressed to the wrong length");
+    }
+    Ok(output)
+}
+
+// ============================================================================
+// Values
+// ============================================================================
+
+/// Decode `count` PLAIN-encoded values
+fn decode_plain(data: &[u8], column: &Column, count: usize) -> Result<Vec<JsonValue>> {
+    let mut reader = ByteReader::new(data, "Parquet");
+    let mut values = Vec::with_capacity(count.min(data.len() * 8));
+    for i in 0..count {
+        let value = match column.physical {
+            physical::BOOLEAN => {
+                let byte = data.get(i / 8).context("Truncated boolean values")?;
+                JsonValue::Bool((byte >> (i % 8)) & 1 == 1)
+            }
+            physical::INT32 => {
+                let v = i32::from_le_bytes(reader.array()?);
+                column.integer(v as i64)
+            }
+            physical::INT64 => {
+                let v = i64::from_le_bytes(reader.array()?);
+                column.integer(v)
+            }
+            physical::INT96 => int96(reader.take(12)?),
+            physical::FLOAT => float(f32::from_le_bytes(reader.array()?) as f64),
+            physical::DOUBLE => float(f64::from_le_bytes(reader.array()?)),
+            physical::BYTE_ARRAY => {
+                let len = u32::from_le_bytes(reader.array()?) as usize;
+                column.binary(reader.take(len)?)
+            }
+            physical::FIXED_LEN_BYTE_ARRAY => column.binary(reader.take(column.type_length)?),
+            other => bail!("Unknown Parquet physical type {}", other),
+        };
+        values.push(value);
+    }
+    Ok(values)
+}
+
+impl Column {
+    fn integer(&self, v: i64) -> JsonValue {
+        match self.kind {
+            Kind::Unsigned if self.physical == physical::INT32 => JsonValue::from(v as u32),
+            Kind::Unsigned => JsonValue::from(v as u64),
+            Kind::Date => v
+                .checked_mul(86_400)
+                .and_then(|secs| DateTime::from_timestamp(secs, 0))
+                .map_or(JsonValue::from(v), |d| {
+                    JsonValue::String(d.format("%Y-%m-%d").to_string())
+                }),
+            Kind::Decimal { scale } => decimal(v as i128, scale),
+            Kind::Time { per_second } => {
+                let nanos = (v.rem_euclid(per_second) * (1_000_000_000 / per_second)) as u32;
+                NaiveTime::from_num_seconds_from_midnight_opt(
+                    v.div_euclid(per_second) as u32,
+                    nanos,
+                )
+                .map_or(JsonValue::from(v), |t| {
+                    JsonValue::String(t.format("%H:%M:%S%.f").to_string())
+                })
+            }
+            Kind::Timestamp { per_second, utc } => {
+                let nanos = (v.rem_euclid(per_second) * (1_000_000_000 / per_second)) as u32;
+                timestamp(v.div_euclid(per_second), nanos, utc).unwrap_or(JsonValue::from(v))
+            }
+            _ => JsonValue::from(v),
+        }
+    }
+
+    fn binary(&self, bytes: &[u8]) -> JsonValue {
+        match self.kind {
+            Kind::String => JsonValue::String(String::from_utf8_lossy(bytes).into_owned()),
+            Kind::Json => serde_json::from_slice(bytes)
+                .unwrap_or_else(|_| JsonValue::String(String::from_utf8_lossy(bytes).into_owned())),
+            Kind::Decimal { scale } if bytes.len() <= 16 => {
+                // Big-endian two's complement, sign-extended to 128 bits
+                let fill = if bytes.first().is_some_and(|&b| b & 0x80 != 0) {
+                    0xff
+                } else {
+                    0
+                };
+                let mut wide = [fill; 16];
+                wide[16 - bytes.len()..].copy_from_slice(bytes);
+                decimal(i128::from_be_bytes(wide), scale)
+            }
+            Kind::Uuid if bytes.len() == 16 => {
+                let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+                JsonValue::String(format!(
+                    "{}-{}-{}-{}-{}",
+                    &hex[..8],
+                    &hex[8..12],
+                    &hex[12..16],
+                    &hex[16..20],
+                    &hex[20..]
+                ))
+            }
+            _ => match std::str::from_utf8(bytes) {
+                Ok(text) => JsonValue::String(text.to_string()),
+                Err(_) => JsonValue::Array(bytes.iter().map(|&b| JsonValue::from(b)).collect()),
+            },
+        }
+    }
+}
+
+/// Legacy INT96 timestamp: nanoseconds of the day, then the Julian day
+fn int96(bytes: &[u8]) -> JsonValue {
+    let nanos_of_day = u64::from_le_bytes(bytes[..8].try_into().expect("8 bytes")) as i64;
+    let julian_day = u32::from_le_bytes(bytes[8..].try_into().expect("4 bytes")) as i64;
+    let secs = (julian_day - JULIAN_UNIX_EPOCH) * 86_400 + nanos_of_day / 1_000_000_000;
+    timestamp(secs, (nanos_of_day % 1_000_000_000) as u32, true).unwrap_or_default()
+}
+
+fn timestamp(secs: i64, nanos: u32, utc: bool) -> Option<JsonValue> {
+    let time = DateTime::from_timestamp(secs, nanos)?;
+    Some(JsonValue::String(if utc {
+        time.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+    } else {
+        time.naive_utc().format("%Y-%m-%dT%H:%M:%S%.f").to_string()
+    }))
+}
+
+/// `unscaled * 10^-scale` as a JSON number
+pub(crate) fn decimal(unscaled: i128, scale: i64) -> JsonValue {
+    let digits = unscaled.unsigned_abs().to_string();
+    let scale = scale.max(0) as usize;
+    let mut text = if scale == 0 {
+        digits
+    } else {
+        let digits = format!("{:0>width$}", digits, width = scale + 1);
+        let (whole, fraction) = digits.split_at(digits.len() - scale);
+        format!("{}.{}", whole, fraction)
+    };
+    if unscaled < 0 {
+        text.insert(0, '-');
+    }
+    serde_json::from_str::<Number>(&text).map_or(JsonValue::String(text), JsonValue::Number)
+}
+
+// ============================================================================
+// Thrift compact protocol
+// ============================================================================
+
+/// A decoded Thrift value; all integer widths become `Int`, and doubles (which
+/// no field read here uses) are skipped
+#[derive(Debug)]
+enum Thrift<'a> {
+    Bool(bool),
+    Int(i64),
+    Double,
+    Binary(&'a [u8]),
+    List(Vec<Thrift<'a>>),
+    Struct(Vec<(i16, Thrift<'a>)>),
+}
+
+impl<'a> Thrift<'a> {
+    fn field(&self, id: i16) -> Option<&Thrift<'a>> {
+        match self {
+            Thrift::Struct(fields) => fields.iter().find(|(f, _)| *f == id).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    fn int(&self, id: i16) -> Option<i64> {
+        match self.field(id)? {
+            Thrift::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn required_int(&self, id: i16, what: &str) -> Result<i64> {
+        self.int(id)
+            .with_context(|| format!("Parquet metadata is missing the {}", what))
+    }
+
+    /// A count or size, which must not be negative
+    fn required_size(&self, id: i16, what: &str) -> Result<usize> {
+        let value = self.required_int(id, what)?;
+        usize::try_from(value).map_err(|_| anyhow!("Invalid Parquet {}: {}", what, value))
+    }
+
+    fn bool(&self, id: i16) -> Option<bool> {
+        match self.field(id)? {
+            Thrift::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    fn str(&self, id: i16) -> Option<&'a str> {
+        match self.field(id)? {
+            Thrift::Binary(bytes) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+
+    fn list(&self, id: i16) -> &[Thrift<'a>] {
+        match self.field(id) {
+            Some(Thrift::List(items)) => items,
+            _ => &[],
+        }
+    }
+}
+
+/// Reader of compact-protocol bytes, whose varints the RLE and Snappy decoders also use
+struct ThriftReader<'a> {
+    input: ByteReader<'a>,
+}
+
+impl<'a> ThriftReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            input: ByteReader::new(bytes, "Parquet"),
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.input.u8()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("Varint is too long")
+    }
+
+    fn zigzag(&mut self) -> Result<i64> {
+        let v = self.varint()?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    fn structure(&mut self, depth: usize) -> Result<Thrift<'a>> {
+        if depth > MAX_THRIFT_DEPTH {
+            bail!("Thrift structures nested too deeply");
+        }
+        let mut fields = Vec::new();
+        let mut last = 0i16;
+        loop {
+            let header = self.input.u8()?;
+            if header == 0 {
+                return Ok(Thrift::Struct(fields));
+            }
+            let id = match header >> 4 {
+                0 => self.zigzag()? as i16,
+                delta => last.wrapping_add(delta as i16),
+            };
+            last = id;
+            fields.push((id, self.value(header & 0x0f, depth + 1)?));
+        }
+    }
+
+    fn value(&mut self, kind: u8, depth: usize) -> Result<Thrift<'a>> {
+        Ok(match kind {
+            1 => Thrift::Bool(true),
+            2 => Thrift::Bool(false),
+            3 => Thrift::Int(self.input.u8()? as i8 as i64),
+            4..=6 => Thrift::Int(self.zigzag()?),
+            7 => {
+                self.input.take(8)?;
+                Thrift::Double
+            }
+            8 => {
+                let len = self.varint()? as usize;
+                Thrift::Binary(self.input.take(len)?)
+            }
+            9 | 10 => {
+                let header = self.input.u8()?;
+                let len = match header >> 4 {
+                    15 => self.varint()? as usize,
+                    n => n as usize,
+                };
+                self.items(header & 0x0f, len, depth)?
+            }
+            11 => {
+                // Maps become a flat list of keys and values
+                let len = self.varint()? as usize;
+                if len == 0 {
+                    return Ok(Thrift::List(Vec::new()));
+                }
+                let types = self.input.u8()?;
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.push(self.element(types >> 4, depth)?);
+                    items.push(self.element(types & 0x0f, depth)?);
+                }
+                Thrift::List(items)
+            }
+            12 => self.structure(depth)?,
+            other => bail!("Unknown Thrift type {}", other),
+        })
+    }
+
+    fn items(&mut self, kind: u8, len: usize, depth: usize) -> Result<Thrift<'a>> {
+        let mut items = Vec::with_capacity(len.min(self.input.remaining()));
+        for _ in 0..len {
+            items.push(self.element(kind, depth)?);
+        }
+        Ok(Thrift::List(items))
+    }
+
+    /// A collection element; booleans take a byte there instead of living in the type
+    fn element(&mut self, kind: u8, depth: usize) -> Result<Thrift<'a>> {
+        match kind {
+            1 | 2 => Ok(Thrift::Bool(self.input.u8()? == 1)),
+            _ => self.value(kind, depth + 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Minimal compact-protocol writer for building test files
+    #[derive(Default)]
+    struct Writer {
+        bytes: Vec<u8>,
+        last: Vec<i16>,
+    }
+
+    impl Writer {
+        fn varint(&mut self, mut v: u64) {
+            while v >= 0x80 {
+                self.bytes.push(v as u8 | 0x80);
+                v >>= 7;
+            }
+            self.bytes.push(v as u8);
+        }
+
+        fn header(&mut self, id: i16, kind: u8) {
+            let last = self.last.last_mut().unwrap();
+            self.bytes.push((((id - *last) as u8) << 4) | kind);
+            *last = id;
+        }
+
+        fn int(&mut self, id: i16, v: i64) -> &mut Self {
+            self.header(id, 6);
+            self.varint(((v << 1) ^ (v >> 63)) as u64);
+            self
+        }
+
+        fn string(&mut self, id: i16, s: &str) -> &mut Self {
+            self.header(id, 8);
+            self.varint(s.len() as u64);
+            self.bytes.extend(s.as_bytes());
+            self
+        }
+
+        fn begin(&mut self, id: i16) -> &mut Self {
+            self.header(id, 12);
+            self.last.push(0);
+            self
+        }
+
+        fn list(&mut self, id: i16, len: usize) -> &mut Self {
+            self.header(id, 9);
+            self.bytes.push(((len as u8) << 4) | 12);
+            self
+        }
+
+        /// Start a struct that is a list element
+        fn item(&mut self) -> &mut Self {
+            self.last.push(0);
+            self
+        }
+
+        fn end(&mut self) -> &mut Self {
+            self.bytes.push(0);
+            self.last.pop();
+            self
+        }
+
+        fn done(&mut self) -> Vec<u8> {
+            std::mem::take(&mut self.bytes)
+        }
+    }
+
+    fn page_header(page_type: i64, size: usize, body: impl Fn(&mut Writer)) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.last.push(0);
+        w.int(1, page_type).int(2, size as i64).int(3, size as i64);
+        body(&mut w);
+        w.end();
+        w.done()
+    }
+
+    fn column_meta(w: &mut Writer, kind: i64, codec: i64, offsets: (usize, usize)) {
+        let (start, end) = offsets;
+        w.item()
+            .begin(3)
+            .int(1, kind)
+            .list(3, 0)
+            .int(4, codec)
+            .int(5, 3);
+        w.int(7, (end - start) as i64)
+            .int(9, start as i64)
+            .end()
+            .end();
+    }
+
+    /// A file with columns `id` and `name`, whose row group claims `num_rows` rows
+    fn flat_file(num_rows: i64) -> Vec<u8> {
+        let mut file = MAGIC.to_vec();
+
+        // id: required INT64, one PLAIN data page, Snappy-compressed as one literal
+        let ids: Vec<u8> = [1i64, 2, 3].iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut snappy = vec![ids.len() as u8, ((ids.len() - 1) as u8) << 2];
+        snappy.extend(&ids);
+        let id_start = file.len();
+        file.extend(page_header(DATA_PAGE, snappy.len(), |w| {
+            w.begin(5).int(1, 3).int(2, PLAIN).end();
+        }));
+        file.extend(&snappy);
+        let id_end = file.len();
+
+        // name: optional STRING, dictionary page then a dictionary-encoded page
+        // whose second value is null
+        let mut dictionary = Vec::new();
+        for s in ["ann", "bob"] {
+            dictionary.extend((s.len() as u32).to_le_bytes());
+            dictionary.extend(s.as_bytes());
+        }
+        let name_start = file.len();
+        file.extend(page_header(DICTIONARY_PAGE, dictionary.len(), |w| {
+            w.begin(7).int(1, 2).int(2, PLAIN).end();
+        }));
+        file.extend(&dictionary);
+        // Definition levels 1,0,1 bit-packed, then indices 1,0 at bit width 1
+        let mut data = vec![2, 0, 0, 0, 0b11, 0b101];
+        data.extend([1, 0b11, 0b01]);
+        file.extend(page_header(DATA_PAGE, data.len(), |w| {
+            w.begin(5)
+                .int(1, 3)
+                .int(2, RLE_DICTIONARY)
+                .int(3, RLE)
+                .end();
+        }));
+        file.extend(&data);
+        let name_end = file.len();
+
+        let mut w = Writer::default();
+        w.last.push(0);
+        w.int(1, 1).list(2, 3);
+        w.item().string(4, "schema").int(5, 2).end();
+        w.item()
+            .int(1, physical::INT64)
+            .int(3, 0)
+            .string(4, "id")
+            .end();
+        w.item()
+            .int(1, physical::BYTE_ARRAY)
+            .int(3, 1)
+            .string(4, "name");
+        w.int(6, 0).end();
+        w.int(3, 3).list(4, 1).item().list(1, 2);
+        column_meta(&mut w, physical::INT64, 1, (id_start, id_end));
+        column_meta(&mut w, physical::BYTE_ARRAY, 0, (name_start, name_end));
+        w.int(3, num_rows).end().end();
+        let metadata = w.done();
+
+        file.extend(&metadata);
+        file.extend((metadata.len() as u32).to_le_bytes());
+        file.extend(MAGIC);
+        file
+    }
+
+    #[test]
+    fn test_parse_flat_file() {
+        assert_eq!(
+            parse(&flat_file(3)).unwrap(),
+            json!([
+                {"id": 1, "name": "bob"},
+                {"id": 2, "name": null},
+                {"id": 3, "name": "ann"},
+            ])
+        );
+        assert!(parse(b"PAR1garbagePAR1").is_err());
+    }
+
+    #[test]
+    fn test_lying_footer() {
+        // Counts the data cannot hold are errors, not allocation failures
+        assert!(parse(&flat_file(1 << 40)).is_err());
+        assert!(parse(&flat_file(-1)).is_err());
+
+        let mut file = flat_file(3);
+        let footer_end = file.len() - 8;
+        file[footer_end..footer_end + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(parse(&file).is_err());
+
+        let file = flat_file(3);
+        let truncated = [&file[..file.len() - 20], &file[file.len() - 8..]].concat();
+        assert!(parse(&truncated).is_err());
+
+        let huge_run = [0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f];
+        assert!(rle_hybrid(&huge_run, 0, usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_snappy_overlapping_copy() {
+        // Literal "a", then a 2-byte-offset copy of 15 bytes at distance 1
+        let block = [16, 0x00, b'a', (14 << 2) | 2, 1, 0];
+        assert_eq!(snappy(&block, 16).unwrap(), vec![b'a'; 16]);
+        assert!(snappy(&[4, (3 << 2) | 2, 1, 0], 4).is_err());
+    }
+
+    #[test]
+    fn test_logical_values() {
+        assert_eq!(decimal(12345, 2), json!(123.45));
+        assert_eq!(decimal(-5, 3), json!(-0.005));
+        let date = Column {
+            name: "d".into(),
+            physical: physical::INT32,
+            type_length: 0,
+            optional: false,
+            kind: Kind::Date,
+        };
+        assert_eq!(date.integer(19_000), json!("2022-01-08"));
+        let ts = Column {
+            kind: Kind::Timestamp {
+                per_second: 1_000,
+                utc: true,
+            },
+            ..date
+        };
+        assert_eq!(ts.integer(1_500), json!("1970-01-01T00:00:01.500Z"));
+    }
+}
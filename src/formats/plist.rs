@@ -0,0 +1,733 @@
+//! Apple property list handling (XML and binary)
+//!
+//! Property lists map onto the intermediate JSON value as follows: dates become
+//! RFC 3339 strings, data becomes base64 text, and UIDs (from keyed archives)
+//! become `{"CF$UID": n}` objects. Plists have no null, so nulls are written as
+//! empty strings, like in TOML.
+
+use anyhow::{bail, Context, Result};
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde_json::{Map, Number, Value as JsonValue};
+
+/// Leading bytes of a binary property list
+pub const BINARY_MAGIC: &[u8] = b"bplist00";
+
+/// Unix time of 2001-01-01T00:00:00Z, the epoch of plist dates
+const APPLE_EPOCH: f64 = 978_307_200.0;
+
+/// Nesting deeper than this is treated as corruption (or a reference cycle)
+const MAX_DEPTH: usize = 512;
+
+/// Shared objects of a binary plist are expanded at most this many times in total
+const MAX_VALUES: usize = 1 << 22;
+
+const XML_HEADER: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+    <!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \
+    \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n\
+    <plist version=\"1.0\">\n";
+
+/// A property list value
+#[derive(Debug, Clone, PartialEq)]
+enum Plist {
+    Bool(bool),
+    Integer(i128),
+    Real(f64),
+    String(String),
+    /// Seconds since 2001-01-01T00:00:00Z
+    Date(f64),
+    Data(Vec<u8>),
+    Uid(u64),
+    Array(Vec<Plist>),
+    Dict(Vec<(String, Plist)>),
+}
+
+/// Parse an XML or binary property list
+pub fn parse(input: &[u8]) -> Result<JsonValue> {
+    Ok(to_json(read(input)?))
+}
+
+/// Parse an XML property list
+pub fn parse_str(content: &str) -> Result<JsonValue> {
+    Ok(to_json(parse_xml(content)?))
+}
+
+/// Rewrite a binary property list as the equivalent XML property list
+pub fn binary_to_xml(input: &[u8]) -> Result<String> {
+    let plist = BinaryReader::new(input)?.root()?;
+    Ok(write_xml(&plist))
+}
+
+/// Re-encode an XML or binary property list as XML or binary, keeping dates
+/// and data that would become strings in the intermediate value
+pub fn transcode(input: &[u8], binary: bool) -> Result<Vec<u8>> {
+    let plist = read(input)?;
+    Ok(match binary {
+        true => BinaryWriter::write(&plist),
+        false => write_xml(&plist).into_bytes(),
+    })
+}
+
+fn read(input: &[u8]) -> Result<Plist> {
+    match input.starts_with(BINARY_MAGIC) {
+        true => BinaryReader::new(input)?.root(),
+        false => parse_xml(std::str::from_utf8(input).context("Plist is not valid UTF-8")?),
+    }
+}
+
+/// Write a value as an XML property list
+pub fn to_xml(value: &JsonValue) -> String {
+    write_xml(&from_json(value))
+}
+
+/// Write a value as a binary property list
+pub fn to_binary(value: &JsonValue) -> Vec<u8> {
+    BinaryWriter::write(&from_json(value))
+}
+
+// ============================================================================
+// JSON mapping
+// ============================================================================
+
+fn to_json(plist: Plist) -> JsonValue {
+    match plist {
+        Plist::Bool(b) => JsonValue::Bool(b),
+        Plist::Integer(i) => match (i64::try_from(i), u64::try_from(i)) {
+            (Ok(i), _) => JsonValue::from(i),
+            (_, Ok(u)) => JsonValue::from(u),
+            _ => JsonValue::String(i.to_string()),
+        },
+        Plist::Real(f) => Number::from_f64(f)
+            .map(JsonValue::Number)
+            .unwrap_or_else(|| JsonValue::String(f.to_string())),
+        Plist::String(s) => JsonValue::String(s),
+        Plist::Date(seconds) => JsonValue::String(date_to_rfc3339(seconds)),
+        Plist::Data(bytes) => JsonValue::String(BASE64.encode(bytes)),
+        Plist::Uid(uid) => {
+            let mut obj = Map::new();
+            obj.insert("CF$UID".to_string(), JsonValue::from(uid));
+            JsonValue::Object(obj)
+        }
+        Plist::Array(items) => JsonValue::Array(items.into_iter().map(to_json).collect()),
+        Plist::Dict(entries) => {
+            JsonValue::Object(entries.into_iter().map(|(k, v)| (k, to_json(v))).collect())
+        }
+    }
+}
+
+fn from_json(value: &JsonValue) -> Plist {
+    match value {
+        JsonValue::Null => Plist::String(String::new()),
+        JsonValue::Bool(b) => Plist::Bool(*b),
+        JsonValue::Number(n) => match (n.as_i64(), n.as_u64()) {
+            (Some(i), _) => Plist::Integer(i.into()),
+            (_, Some(u)) => Plist::Integer(u.into()),
+            _ => Plist::Real(n.as_f64().unwrap_or_default()),
+        },
+        JsonValue::String(s) => Plist::String(s.clone()),
+        JsonValue::Array(items) => Plist::Array(items.iter().map(from_json).collect()),
+        JsonValue::Object(obj) => match (obj.len(), obj.get("CF$UID").and_then(|u| u.as_u64())) {
+            (1, Some(uid)) => Plist::Uid(uid),
+            _ => Plist::Dict(obj.iter().map(|(k, v)| (k.clone(), from_json(v))).collect()),
+        },
+    }
+}
+
+fn date_to_rfc3339(seconds: f64) -> String {
+    let unix = seconds + APPLE_EPOCH;
+    let whole = unix.floor();
+    let nanos = ((unix - whole) * 1e9).round().min(999_999_999.0) as u32;
+    match DateTime::from_timestamp(whole as i64, nanos) {
+        Some(date) => date.to_rfc3339_opts(SecondsFormat::AutoSi, true),
+        None => seconds.to_string(),
+    }
+}
+
+fn parse_date(text: &str) -> Result<f64> {
+    let date = DateTime::parse_from_rfc3339(text.trim())
+        .with_context(|| format!("Invalid plist date: {}", text))?;
+    let nanos = date.timestamp_subsec_nanos() as f64 / 1e9;
+    Ok(date.timestamp() as f64 + nanos - APPLE_EPOCH)
+}
+
+// ============================================================================
+// XML property lists
+// ============================================================================
+
+/// A container being filled while reading XML
+enum Open {
+    Array(Vec<Plist>),
+    Dict(Vec<(String, Plist)>, Option<String>),
+}
+
+fn parse_xml(content: &str) -> Result<Plist> {
+    let mut reader = Reader::from_str(content);
+    let mut stack: Vec<Open> = Vec::new();
+    let mut root = None;
+    let mut text = String::new();
+
+    loop {
+        let event = reader.read_event().context("Failed to parse plist XML")?;
+        let finished = match event {
+            Event::Start(e) => {
+                text.clear();
+                match e.local_name().as_ref() {
+                    b"array" => stack.push(Open::Array(Vec::new())),
+                    b"dict" => stack.push(Open::Dict(Vec::new(), None)),
+                    _ => {}
+                }
+                None
+            }
+            Event::Empty(e) => match e.local_name().as_ref() {
+                b"true" => Some(Plist::Bool(true)),
+                b"false" => Some(Plist::Bool(false)),
+                b"array" => Some(Plist::Array(Vec::new())),
+                b"dict" => Some(Plist::Dict(Vec::new())),
+                b"string" => Some(Plist::String(String::new())),
+                b"data" => Some(Plist::Data(Vec::new())),
+                b"key" => {
+                    match stack.last_mut() {
+                        Some(Open::Dict(_, key)) => *key = Some(String::new()),
+                        _ => bail!("Plist <key> outside of a <dict>"),
+                    }
+                    None
+                }
+                _ => None,
+            },
+            Event::Text(t) => {
+                text.push_str(&t.unescape()?);
+                None
+            }
+            Event::CData(t) => {
+                text.push_str(&String::from_utf8_lossy(&t));
+                None
+            }
+            Event::End(e) => {
+                let value = std::mem::take(&mut text);
+                match e.local_name().as_ref() {
+                    b"key" => {
+                        match stack.last_mut() {
+                            Some(Open::Dict(_, key)) => *key = Some(value),
+                            _ => bail!("Plist <key> outside of a <dict>"),
+                        }
+                        None
+                    }
+                    b"string" => Some(Plist::String(value)),
+                    b"integer" => {
+                        let trimmed = value.trim();
+                        let parsed = match trimmed.strip_prefix("0x") {
+                            Some(hex) => i128::from_str_radix(hex, 16).ok(),
+                            None => trimmed.parse().ok(),
+                        };
+                        let integer = parsed
+                            .with_context(|| format!("Invalid plist integer: {}", trimmed))?;
+                        Some(Plist::Integer(integer))
+                    }
+                    b"real" => {
+                        let trimmed = value.trim();
+                        let real = match trimmed {
+                            "nan" => f64::NAN,
+                            "+infinity" | "inf" => f64::INFINITY,
+                            "-infinity" | "-inf" => f64::NEG_INFINITY,
+                            _ => trimmed
+                                .parse()
+                                .with_context(|| format!("Invalid plist real: {}", trimmed))?,
+                        };
+                        Some(Plist::Real(real))
+                    }
+                    b"date" => Some(Plist::Date(parse_date(&value)?)),
+                    b"data" => {
+                        let compact: String =
+                            value.chars().filter(|c| !c.is_whitespace()).collect();
+                        let bytes = BASE64
+                            .decode(compact)
+                            .context("Invalid base64 in plist <data>")?;
+                        Some(Plist::Data(bytes))
+                    }
+                    b"array" | b"dict" => match stack.pop() {
+                        Some(Open::Array(items)) => Some(Plist::Array(items)),
+                        Some(Open::Dict(entries, _)) => Some(Plist::Dict(entries)),
+                        None => bail!("Unbalanced plist XML"),
+                    },
+                    _ => None,
+                }
+            }
+            Event::Eof => break,
+            _ => None,
+        };
+
+        if let Some(value) = finished {
+            match stack.last_mut() {
+                Some(Open::Array(items)) => items.push(value),
+                Some(Open::Dict(entries, key)) => {
+                    let key = key.take().context("Plist <dict> value without a <key>")?;
+                    entries.push((key, value));
+                }
+                None if root.is_none() => root = Some(value),
+                None => bail!("Plist has more than one root value"),
+            }
+        }
+    }
+
+    if !stack.is_empty() {
+        bail!("Unexpected end of plist XML");
+    }
+    root.context("Plist has no value")
+}
+
+fn write_xml(plist: &Plist) -> String {
+    let mut out = String::from(XML_HEADER);
+    write_xml_value(&mut out, plist, 0);
+    out.push_str("</plist>\n");
+    out
+}
+
+fn write_xml_value(out: &mut String, plist: &Plist, depth: usize) {
+    let indent = "\t".repeat(depth);
+    match plist {
+        Plist::Bool(true) => out.push_str(&format!("{}<true/>\n", indent)),
+        Plist::Bool(false) => out.push_str(&format!("{}<false/>\n", indent)),
+        Plist::Integer(i) => out.push_str(&format!("{}<integer>{}</integer>\n", indent, i)),
+        Plist::Real(f) => {
+            let text = match f {
+                f if f.is_nan() => "nan".to_string(),
+                f if f.is_infinite() && *f > 0.0 => "+infinity".to_string(),
+                f if f.is_infinite() => "-infinity".to_string(),
+                f => format!("{:?}", f),
+            };
+            out.push_str(&format!("{}<real>{}</real>\n", indent, text));
+        }
+        Plist::String(s) => out.push_str(&format!("{}<string>{}</string>\n", indent, escape(s))),
+        Plist::Date(seconds) => {
+            let date = date_to_rfc3339(*seconds);
+            out.push_str(&format!("{}<date>{}</date>\n", indent, date));
+        }
+        Plist::Data(bytes) => out.push_str(&format!(
+            "{}<data>{}</data>\n",
+            indent,
+            BASE64.encode(bytes)
+        )),
+        Plist::Uid(uid) => {
+            // XML plists spell UIDs as one-entry dictionaries
+            out.push_str(&format!(
+                "{0}<dict>\n{0}\t<key>CF$UID</key>\n{0}\t<integer>{1}</integer>\n{0}</dict>\n",
+                indent, uid
+            ));
+        }
+        Plist::Array(items) if items.is_empty() => out.push_str(&format!("{}<array/>\n", indent)),
+        Plist::Array(items) => {
+            out.push_str(&format!("{}<array>\n", indent));
+            for item in items {
+                write_xml_value(out, item, depth + 1);
+            }
+            out.push_str(&format!("{}</array>\n", indent));
+        }
+        Plist::Dict(entries) if entries.is_empty() => out.push_str(&format!("{}<dict/>\n", indent)),
+        Plist::Dict(entries) => {
+            out.push_str(&format!("{}<dict>\n", indent));
+            for (key, value) in entries {
+                out.push_str(&format!("{}\t<key>{}</key>\n", indent, escape(key)));
+                write_xml_value(out, value, depth + 1);
+            }
+            out.push_str(&format!("{}</dict>\n", indent));
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+// ============================================================================
+// Binary property lists
+// ============================================================================
+
+struct BinaryReader<'a> {
+    bytes: &'a [u8],
+    offsets: Vec<usize>,
+    ref_size: usize,
+    top: usize,
+    expanded: usize,
+}
+
+fn be_uint(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u64)
+}
+
+impl<'a> BinaryReader<'a> {
+    fn new(bytes: &'a [u8]) -> Result<Self> {
+        if bytes.len() < BINARY_MAGIC.len() + 32 || !bytes.starts_with(BINARY_MAGIC) {
+            bail!("Not a binary plist");
+        }
+        let trailer = &bytes[bytes.len() - 32..];
+        let offset_size = trailer[6] as usize;
+        let ref_size = trailer[7] as usize;
+        let count = be_uint(&trailer[8..16]) as usize;
+        let top = be_uint(&trailer[16..24]) as usize;
+        let table = be_uint(&trailer[24..32]) as usize;
+        if !(1..=8).contains(&offset_size) || !(1..=8).contains(&ref_size) || top >= count {
+            bail!("Corrupt binary plist trailer");
+        }
+        let table_bytes = count
+            .checked_mul(offset_size)
+            .and_then(|len| bytes.get(table..table.checked_add(len)?))
+            .context("Binary plist offset table is out of bounds")?;
+        let offsets = table_bytes
+            .chunks(offset_size)
+            .map(|chunk| be_uint(chunk) as usize)
+            .collect();
+        Ok(Self {
+            bytes,
+            offsets,
+            ref_size,
+            top,
+            expanded: 0,
+        })
+    }
+
+    fn root(mut self) -> Result<Plist> {
+        self.object(self.top, 0)
+    }
+
+    fn slice(&self, start: usize, len: usize) -> Result<&'a [u8]> {
+        start
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(start..end))
+            .context("Binary plist object is out of bounds")
+    }
+
+    /// Length from a marker's low nibble, or the integer following it
+    fn length(&self, info: u8, pos: usize) -> Result<(usize, usize)> {
+        if info != 0x0F {
+            return Ok((info as usize, pos + 1));
+        }
+        let marker = *self.slice(pos + 1, 1)?.first().unwrap_or(&0);
+        if marker >> 4 != 0x1 {
+            bail!("Corrupt binary plist length");
+        }
+        let size = 1usize << (marker & 0x0F).min(3);
+        let len = be_uint(self.slice(pos + 2, size)?) as usize;
+        Ok((len, pos + 2 + size))
+    }
+
+    fn object(&mut self, index: usize, depth: usize) -> Result<Plist> {
+        if depth > MAX_DEPTH {
+            bail!("Binary plist nesting exceeds {} levels", MAX_DEPTH);
+        }
+        self.expanded += 1;
+        if self.expanded > MAX_VALUES {
+            bail!("Binary plist expands to more than {} values", MAX_VALUES);
+        }
+        let pos = *self
+            .offsets
+            .get(index)
+            .context("Binary plist object reference is out of range")?;
+        let marker = *self.slice(pos, 1)?.first().unwrap_or(&0);
+        let (kind, info) = (marker >> 4, marker & 0x0F);
+
+        Ok(match (kind, info) {
+            (0x0, 0x8) => Plist::Bool(false),
+            (0x0, 0x9) => Plist::Bool(true),
+            (0x1, 0..=3) => {
+                let bytes = self.slice(pos + 1, 1 << info)?;
+                match info {
+                    3 => Plist::Integer(be_uint(bytes) as i64 as i128),
+                    _ => Plist::Integer(be_uint(bytes) as i128),
+                }
+            }
+            (0x1, 4) => {
+                let bytes = self.slice(pos + 1, 16)?;
+                Plist::Integer(i128::from_be_bytes(bytes.try_into()?))
+            }
+            (0x2, 2) => Plist::Real(f32::from_be_bytes(self.slice(pos + 1, 4)?.try_into()?) as f64),
+            (0x2, 3) => Plist::Real(f64::from_be_bytes(self.slice(pos + 1, 8)?.try_into()?)),
+            (0x3, 3) => Plist::Date(f64::from_be_bytes(self.slice(pos + 1, 8)?.try_into()?)),
+            (0x4, _) => {
+                let (len, start) = self.length(info, pos)?;
+                Plist::Data(self.slice(start, len)?.to_vec())
+            }
+            (0x5, _) => {
+                let (len, start) = self.length(info, pos)?;
+                let bytes = self.slice(start, len)?;
+                Plist::String(bytes.iter().map(|&b| b as char).collect())
+            }
+            (0x6, _) => {
+                let (len, start) = self.length(info, pos)?;
+                let units: Vec<u16> = self
+                    .slice(start, len.checked_mul(2).context("Corrupt binary plist")?)?
+                    .chunks(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                    .collect();
+                Plist::String(String::from_utf16_lossy(&units))
+            }
+            (0x8, _) => Plist::Uid(be_uint(self.slice(pos + 1, info as usize + 1)?)),
+            // Arrays and sets
+            (0xA | 0xC, _) => {
+                let (len, start) = self.length(info, pos)?;
+                let refs = self.refs(start, len)?;
+                let items = refs
+                    .into_iter()
+                    .map(|r| self.object(r, depth + 1))
+                    .collect::<Result<_>>()?;
+                Plist::Array(items)
+            }
+            (0xD, _) => {
+                let (len, start) = self.length(info, pos)?;
+                let refs = self.refs(start, len.checked_mul(2).context("Corrupt binary plist")?)?;
+                let (keys, values) = refs.split_at(len);
+                let mut entries = Vec::with_capacity(len);
+                for (&k, &v) in keys.iter().zip(values) {
+                    let key = match self.object(k, depth + 1)? {
+                        Plist::String(key) => key,
+                        _ => bail!("Binary plist dictionary key is not a string"),
+                    };
+                    entries.push((key, self.object(v, depth + 1)?));
+                }
+                Plist::Dict(entries)
+            }
+            _ => bail!("Unsupported binary plist object type 0x{:02x}", marker),
+        })
+    }
+
+    fn refs(&self, start: usize, count: usize) -> Result<Vec<usize>> {
+        let len = count
+            .checked_mul(self.ref_size)
+            .context("Corrupt binary plist")?;
+        Ok(self
+            .slice(start, len)?
+            .chunks(self.ref_size)
+            .map(|chunk| be_uint(chunk) as usize)
+            .collect())
+    }
+}
+
+struct BinaryWriter {
+    objects: Vec<Vec<u8>>,
+    ref_size: usize,
+}
+
+/// Bytes needed to hold `n` as an unsigned big-endian integer (1, 2, 4 or 8)
+fn uint_size(n: u64) -> usize {
+    match n {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFFFF_FFFF => 4,
+        _ => 8,
+    }
+}
+
+fn push_uint(out: &mut Vec<u8>, n: u64, size: usize) {
+    out.extend_from_slice(&n.to_be_bytes()[8 - size..]);
+}
+
+impl BinaryWriter {
+    fn write(plist: &Plist) -> Vec<u8> {
+        let count = count_objects(plist);
+        let mut writer = Self {
+            objects: Vec::with_capacity(count),
+            ref_size: uint_size(count as u64),
+        };
+        writer.add(plist);
+
+        let mut out = BINARY_MAGIC.to_vec();
+        let mut offsets = Vec::with_capacity(writer.objects.len());
+        for object in &writer.objects {
+            offsets.push(out.len() as u64);
+            out.extend_from_slice(object);
+        }
+        let table = out.len() as u64;
+        let offset_size = uint_size(table);
+        for offset in offsets {
+            push_uint(&mut out, offset, offset_size);
+        }
+        out.extend_from_slice(&[0; 6]);
+        out.push(offset_size as u8);
+        out.push(writer.ref_size as u8);
+        out.extend_from_slice(&(writer.objects.len() as u64).to_be_bytes());
+        out.extend_from_slice(&0u64.to_be_bytes());
+        out.extend_from_slice(&table.to_be_bytes());
+        out
+    }
+
+    /// Append `plist` (and its children) as objects; returns its index
+    fn add(&mut self, plist: &Plist) -> u64 {
+        let index = self.objects.len();
+        self.objects.push(Vec::new());
+        let mut bytes = Vec::new();
+        match plist {
+            Plist::Bool(b) => bytes.push(if *b { 0x09 } else { 0x08 }),
+            Plist::Integer(i) => push_integer(&mut bytes, *i),
+            Plist::Real(f) => {
+                bytes.push(0x23);
+                bytes.extend_from_slice(&f.to_be_bytes());
+            }
+            Plist::Date(seconds) => {
+                bytes.push(0x33);
+                bytes.extend_from_slice(&seconds.to_be_bytes());
+            }
+            Plist::Data(data) => {
+                push_marker(&mut bytes, 0x4, data.len());
+                bytes.extend_from_slice(data);
+            }
+            Plist::String(s) if s.is_ascii() => {
+                push_marker(&mut bytes, 0x5, s.len());
+                bytes.extend_from_slice(s.as_bytes());
+            }
+            Plist::String(s) => {
+                let units: Vec<u16> = s.encode_utf16().collect();
+                push_marker(&mut bytes, 0x6, units.len());
+                for unit in units {
+                    bytes.extend_from_slice(&unit.to_be_bytes());
+                }
+            }
+            Plist::Uid(uid) => {
+                let size = uint_size(*uid);
+                bytes.push(0x80 | (size as u8 - 1));
+                push_uint(&mut bytes, *uid, size);
+            }
+            Plist::Array(items) => {
+                push_marker(&mut bytes, 0xA, items.len());
+                let refs: Vec<u64> = items.iter().map(|item| self.add(item)).collect();
+                for r in refs {
+                    push_uint(&mut bytes, r, self.ref_size);
+                }
+            }
+            Plist::Dict(entries) => {
+                push_marker(&mut bytes, 0xD, entries.len());
+                let keys: Vec<u64> = entries
+                    .iter()
+                    .map(|(key, _)| self.add(&Plist::String(key.clone())))
+                    .collect();
+                let values: Vec<u64> = entries.iter().map(|(_, value)| self.add(value)).collect();
+                for r in keys.into_iter().chain(values) {
+                    push_uint(&mut bytes, r, self.ref_size);
+                }
+            }
+        }
+        self.objects[index] = bytes;
+        index as u64
+    }
+}
+
+fn count_objects(plist: &Plist) -> usize {
+    match plist {
+        Plist::Array(items) => 1 + items.iter().map(count_objects).sum::<usize>(),
+        Plist::Dict(entries) => {
+            1 + entries
+                .iter()
+                .map(|(_, v)| 1 + count_objects(v))
+                .sum::<usize>()
+        }
+        _ => 1,
+    }
+}
+
+/// Type marker with the length in its low nibble, or in a following integer
+fn push_marker(out: &mut Vec<u8>, kind: u8, len: usize) {
+    if len < 0x0F {
+        out.push(kind << 4 | len as u8);
+    } else {
+        out.push(kind << 4 | 0x0F);
+        push_integer(out, len as i128);
+    }
+}
+
+fn push_integer(out: &mut Vec<u8>, i: i128) {
+    match (u32::try_from(i), i64::try_from(i)) {
+        // Integers of 1, 2 and 4 bytes are unsigned, 8 bytes signed
+        (Ok(u), _) => {
+            let size = uint_size(u as u64);
+            out.push(0x10 | size.trailing_zeros() as u8);
+            push_uint(out, u as u64, size);
+        }
+        (_, Ok(signed)) => {
+            out.push(0x13);
+            out.extend_from_slice(&signed.to_be_bytes());
+        }
+        _ => {
+            out.push(0x14);
+            out.extend_from_slice(&i.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>CFBundleName</key>
+	<string>Caf&#233; &amp; Co</string>
+	<key>Build</key>
+	<integer>42</integer>
+	<key>Scale</key>
+	<real>1.5</real>
+	<key>Released</key>
+	<date>2024-01-02T03:04:05Z</date>
+	<key>Icon</key>
+	<data>
+	AAEC/w==
+	</data>
+	<key>Flags</key>
+	<array>
+		<true/>
+		<false/>
+	</array>
+	<key>Empty</key>
+	<dict/>
+</dict>
+</plist>
+"#;
+
+    #[test]
+    fn test_parse_xml() {
+        let value = parse_str(XML).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "CFBundleName": "Café & Co",
+                "Build": 42,
+                "Scale": 1.5,
+                "Released": "2024-01-02T03:04:05Z",
+                "Icon": "AAEC/w==",
+                "Flags": [true, false],
+                "Empty": {}
+            })
+        );
+        assert_eq!(parse_str(&to_xml(&value)).unwrap(), value);
+        assert!(parse_str("<plist><dict><string>x</string></dict></plist>").is_err());
+    }
+
+    #[test]
+    fn test_binary_round_trip() {
+        let plist = parse_xml(XML).unwrap();
+        let mut keys: Vec<(String, Plist)> = (0..20)
+            .map(|i| (format!("key{}", i), Plist::Integer(-(i as i128) << 40)))
+            .collect();
+        keys.push(("uid".to_string(), Plist::Uid(7)));
+        keys.push(("big".to_string(), Plist::Integer(u64::MAX as i128)));
+        keys.push(("text".to_string(), Plist::String("日本語 ✓".to_string())));
+        let extended = Plist::Array(vec![plist.clone(), Plist::Dict(keys)]);
+
+        let bytes = BinaryWriter::write(&extended);
+        assert!(bytes.starts_with(BINARY_MAGIC));
+        assert_eq!(BinaryReader::new(&bytes).unwrap().root().unwrap(), extended);
+
+        let xml = binary_to_xml(&BinaryWriter::write(&plist)).unwrap();
+        assert_eq!(parse_xml(&xml).unwrap(), plist);
+        assert_eq!(
+            parse(&to_binary(&json!({"a": [1, "x"]}))).unwrap(),
+            json!({"a": [1, "x"]})
+        );
+        assert!(parse(b"bplist00 truncated").is_err());
+    }
+}
@@ -0,0 +1,355 @@
+//! Java `.properties` format handling
+//!
+//! Follows `java.util.Properties`: `key=value`, `key: value` or `key value`
+//! lines, `#`/`!` comments, backslash line continuations and `\uXXXX` escapes.
+//! Dotted keys expand to nested objects and `key[0]` indices (as in Spring) to
+//! arrays; writing flattens values the same way. Numbers and booleans are
+//! typed, like CSV cells and XML text.
+
+use anyhow::{bail, Result};
+use serde_json::{Map, Value as JsonValue};
+
+/// Parse properties into a nested object
+pub fn parse(content: &str) -> Result<JsonValue> {
+    let mut root = JsonValue::Object(Map::new());
+    for (line_number, line) in logical_lines(content) {
+        let (key, value) = split_entry(&line);
+        let key = unescape(key);
+        let path = key_path(&key);
+        insert(&mut root, &path, scalar(&unescape(value)))
+            .map_err(|err| anyhow::anyhow!("Line {}: {} (key '{}')", line_number, err, key))?;
+    }
+    Ok(root)
+}
+
+/// Write a nested object as properties, one flattened key per line
+pub fn to_string(value: &JsonValue) -> Result<String> {
+    let JsonValue::Object(obj) = value else {
+        bail!("Properties output requires an object at the top level");
+    };
+    let mut out = String::new();
+    for (key, value) in obj {
+        write_entry(&mut out, &escape_key(key), value);
+    }
+    Ok(out)
+}
+
+/// Lines with continuations joined and comments dropped, with their line numbers
+fn logical_lines(content: &str) -> Vec<(usize, String)> {
+    let mut lines = Vec::new();
+    let mut current: Option<(usize, String)> = None;
+    for (i, raw) in content.lines().enumerate() {
+        let line = raw.trim_start();
+        let line = match current {
+            Some(_) => line,
+            None if line.is_empty() || line.starts_with(['#', '!']) => continue,
+            None => line,
+        };
+        let trailing = line.len() - line.trim_end_matches('\\').len();
+        let continued = trailing % 2 == 1;
+        let text = if continued {
+            &line[..line.len() - 1]
+        } else {
+            line
+        };
+        let (_, joined) = current.get_or_insert_with(|| (i + 1, String::new()));
+        joined.push_str(text);
+        if !continued {
+            lines.extend(current.take());
+        }
+    }
+    lines.extend(current);
+    lines
+}
+
+/// Split at the first unescaped `=`, `:` or whitespace
+fn split_entry(line: &str) -> (&str, &str) {
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '=' | ':' => return (&line[..i], line[i + 1..].trim_start()),
+            c if c.is_whitespace() => {
+                let rest = line[i..].trim_start();
+                let rest = rest.strip_prefix(['=', ':']).unwrap_or(rest);
+                return (&line[..i], rest.trim_start());
+            }
+            _ => {}
+        }
+    }
+    (line, "")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('f') => out.push('\x0c'),
+            Some('u') => {
+                let hex: String = chars.clone().take(4).collect();
+                match u16::from_str_radix(&hex, 16) {
+                    Ok(unit) if hex.len() == 4 => {
+                        chars.nth(3);
+                        out.push_str(&decode_utf16_unit(unit, &mut chars));
+                    }
+                    _ => out.push('u'),
+                }
+            }
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Decode one UTF-16 unit, taking a following `\uXXXX` low surrogate if needed
+fn decode_utf16_unit(unit: u16, chars: &mut std::str::Chars) -> String {
+    if (0xD800..0xDC00).contains(&unit) {
+        let ahead = chars.as_str();
+        if let Some(low) = ahead
+            .strip_prefix("\\u")
+            .and_then(|rest| rest.get(..4))
+            .and_then(|hex| u16::from_str_radix(hex, 16).ok())
+        {
+            if let Some(Ok(c)) = char::decode_utf16([unit, low]).next() {
+                chars.nth(5);
+                return c.to_string();
+            }
+        }
+    }
+    String::from_utf16_lossy(&[unit])
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// `a.b[0].c` -> `a`, `b`, `0`, `c`; brackets without a number stay in the key
+fn key_path(key: &str) -> Vec<Segment<'_>> {
+    let mut path = Vec::new();
+    for part in key.split('.') {
+        let mut name = part;
+        let mut indices = Vec::new();
+        while let Some(open) = name.rfind('[') {
+            let Some(index) = name[open..]
+                .strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+                .and_then(|digits| digits.parse().ok())
+            else {
+                break;
+            };
+            indices.push(index);
+            name = &name[..open];
+        }
+        path.push(Segment::Key(name));
+        path.extend(indices.into_iter().rev().map(Segment::Index));
+    }
+    path
+}
+
+fn insert(target: &mut JsonValue, path: &[Segment], value: JsonValue) -> Result<()> {
+    let Some((segment, rest)) = path.split_first() else {
+        *target = value;
+        return Ok(());
+    };
+    let slot = match (segment, target) {
+        (Segment::Key(key), JsonValue::Object(obj)) => {
+            let empty = match rest.first() {
+                Some(Segment::Index(_)) => JsonValue::Array(Vec::new()),
+                Some(Segment::Key(_)) => JsonValue::Object(Map::new()),
+                None => JsonValue::Null,
+            };
+            obj.entry(key.to_string()).or_insert(empty)
+        }
+        (Segment::Index(index), JsonValue::Array(items)) => {
+            if *index > items.len() + 10_000 {
+                bail!("array index {} is too far past the end", index);
+            }
+            if items.len() <= *index {
+                items.resize(index + 1, JsonValue::Null);
+            }
+            let slot = &mut items[*index];
+            if slot.is_null() {
+                *slot = match rest.first() {
+                    Some(Segment::Index(_)) => JsonValue::Array(Vec::new()),
+                    Some(Segment::Key(_)) => JsonValue::Object(Map::new()),
+                    None => JsonValue::Null,
+                };
+            }
+            slot
+        }
+        _ => bail!("conflicts with another key that sets a value at this path"),
+    };
+    if rest.is_empty() && (slot.is_object() || slot.is_array()) && !is_empty_container(slot) {
+        bail!("conflicts with nested keys below it");
+    }
+    insert(slot, rest, value)
+}
+
+fn is_empty_container(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Object(obj) => obj.is_empty(),
+        JsonValue::Array(items) => items.is_empty(),
+        _ => false,
+    }
+}
+
+/// Type a value like CSV cells: numbers and booleans, otherwise a string
+fn scalar(text: &str) -> JsonValue {
+    if let Ok(n) = text.parse::<i64>() {
+        JsonValue::Number(n.into())
+    } else if let Some(n) = text
+        .parse::<f64>()
+        .ok()
+        .filter(|f| f.is_finite())
+        .and_then(serde_json::Number::from_f64)
+    {
+        JsonValue::Number(n)
+    } else if text.eq_ignore_ascii_case("true") {
+        JsonValue::Bool(true)
+    } else if text.eq_ignore_ascii_case("false") {
+        JsonValue::Bool(false)
+    } else {
+        JsonValue::String(text.to_string())
+    }
+}
+
+fn write_entry(out: &mut String, key: &str, value: &JsonValue) {
+    match value {
+        JsonValue::Object(obj) => {
+            for (child, value) in obj {
+                write_entry(out, &format!("{}.{}", key, escape_key(child)), value);
+            }
+        }
+        JsonValue::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                write_entry(out, &format!("{}[{}]", key, i), item);
+            }
+        }
+        JsonValue::Null => out.push_str(&format!("{}=\n", key)),
+        JsonValue::String(s) => out.push_str(&format!("{}={}\n", key, escape_value(s))),
+        other => out.push_str(&format!("{}={}\n", key, other)),
+    }
+}
+
+fn escape_key(key: &str) -> String {
+    let mut out = String::with_capacity(key.len());
+    for (i, c) in key.chars().enumerate() {
+        match c {
+            ' ' | '=' | ':' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '#' | '!' if i == 0 => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => push_escaped(&mut out, c),
+        }
+    }
+    out
+}
+
+fn escape_value(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for (i, c) in value.chars().enumerate() {
+        match c {
+            // Leading whitespace would otherwise be taken as part of the separator
+            ' ' if i == 0 => out.push_str("\\ "),
+            _ => push_escaped(&mut out, c),
+        }
+    }
+    out
+}
+
+/// Escape control characters and backslashes; non-ASCII becomes `\uXXXX` so
+/// the output also reads correctly as ISO-8859-1, the historical encoding
+fn push_escaped(out: &mut String, c: char) {
+    match c {
+        '\\' => out.push_str("\\\\"),
+        '\t' => out.push_str("\\t"),
+        '\n' => out.push_str("\\n"),
+        '\r' => out.push_str("\\r"),
+        '\x0c' => out.push_str("\\f"),
+        c if c.is_ascii() && !c.is_ascii_control() => out.push(c),
+        c => {
+            let mut units = [0u16; 2];
+            for unit in c.encode_utf16(&mut units) {
+                out.push_str(&format!("\\u{:04X}", unit));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse() {
+        let content = r#"
+# Server settings
+! also a comment
+server.port = 8080
+server.host:localhost
+server.ssl.enabled true
+app.name=Caf\u00e9 \
+         Service
+app.servers[0]=alpha
+app.servers[1]=beta
+app.users[0].name=ann
+path=C:\\temp
+key\ with\ spaces=a=b
+emoji=\uD83D\uDE00
+empty=
+"#;
+        assert_eq!(
+            parse(content).unwrap(),
+            json!({
+                "server": {"port": 8080, "host": "localhost", "ssl": {"enabled": true}},
+                "app": {
+                    "name": "Café Service",
+                    "servers": ["alpha", "beta"],
+                    "users": [{"name": "ann"}]
+                },
+                "path": "C:\\temp",
+                "key with spaces": "a=b",
+                "emoji": "😀",
+                "empty": ""
+            })
+        );
+
+        let err = parse("a.b=1\na=2\n").unwrap_err().to_string();
+        assert!(err.contains("Line 2"), "{}", err);
+        assert!(parse("a=1\na.b=2\n").is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value = json!({
+            "db": {"url": "jdbc:h2:mem", "pool": [{"size": 5}, {"size": 1.5}]},
+            "greeting": " héllo\n",
+            "a=b": true
+        });
+        let text = to_string(&value).unwrap();
+        assert_eq!(
+            text,
+            "db.url=jdbc:h2:mem\ndb.pool[0].size=5\ndb.pool[1].size=1.5\n\
+             greeting=\\ h\\u00E9llo\\n\na\\=b=true\n"
+        );
+        assert_eq!(parse(&text).unwrap(), value);
+        assert!(to_string(&json!([1])).is_err());
+    }
+}
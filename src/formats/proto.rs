@@ -0,0 +1,125 @@
+//! Protocol Buffers handling via descriptor sets
+//!
+//! Binary payloads carry no field names, so reading and writing them needs the
+//! message type from a descriptor set (`protoc --descriptor_set_out=set.desc
+//! --include_imports`). Values follow the proto3 JSON mapping, except that field
+//! names are kept as written in the `.proto` file and 64-bit integers stay
+//! numbers; encoding accepts the lowerCamelCase names too.
+
+use anyhow::{bail, Context, Result};
+use prost_reflect::prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, SerializeOptions};
+use serde_json::Value as JsonValue;
+
+pub use prost_reflect::MessageDescriptor;
+
+/// Look up a message type (e.g. `my.pkg.Msg`) in an encoded descriptor set
+pub fn load_message(descriptor_set: &[u8], name: &str) -> Result<MessageDescriptor> {
+    let pool =
+        DescriptorPool::decode(descriptor_set).context("Failed to read protobuf descriptor set")?;
+    let name = name.trim_start_matches('.');
+    if let Some(message) = pool.get_message_by_name(name) {
+        return Ok(message);
+    }
+    let available: Vec<String> = pool
+        .all_messages()
+        .map(|message| message.full_name().to_string())
+        .collect();
+    if available.is_empty() {
+        bail!(
+            "Message type '{}' not found; the descriptor set has no messages",
+            name
+        );
+    }
+    bail!(
+        "Message type '{}' not found. Available: {}",
+        name,
+        available.join(", ")
+    )
+}
+
+/// Decode a binary payload of the given message type
+pub fn decode(bytes: &[u8], message: &MessageDescriptor) -> Result<JsonValue> {
+    let decoded = DynamicMessage::decode(message.clone(), bytes)
+        .with_context(|| format!("Failed to decode protobuf message {}", message.full_name()))?;
+    let options = SerializeOptions::new()
+        .stringify_64_bit_integers(false)
+        .use_proto_field_name(true);
+    decoded
+        .serialize_with_options(serde_json::value::Serializer, &options)
+        .context("Failed to convert protobuf message to JSON")
+}
+
+/// Encode a value as a binary payload of the given message type
+pub fn encode(value: &JsonValue, message: &MessageDescriptor) -> Result<Vec<u8>> {
+    let encoded = DynamicMessage::deserialize(message.clone(), value).with_context(|| {
+        format!(
+            "Value does not match protobuf message {}",
+            message.full_name()
+        )
+    })?;
+    Ok(encoded.encode_to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_reflect::prost_types::{
+        field_descriptor_proto::{Label, Type},
+        DescriptorProto, FieldDescriptorProto, FileDescriptorProto, FileDescriptorSet,
+    };
+    use serde_json::json;
+
+    fn field(name: &str, number: i32, kind: Type, label: Label) -> FieldDescriptorProto {
+        FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            r#type: Some(kind as i32),
+            label: Some(label as i32),
+            ..Default::default()
+        }
+    }
+
+    fn descriptor_set() -> Vec<u8> {
+        let file = FileDescriptorProto {
+            name: Some("user.proto".to_string()),
+            package: Some("my.pkg".to_string()),
+            syntax: Some("proto3".to_string()),
+            message_type: vec![DescriptorProto {
+                name: Some("User".to_string()),
+                field: vec![
+                    field("user_id", 1, Type::Int64, Label::Optional),
+                    field("name", 2, Type::String, Label::Optional),
+                    field("tags", 3, Type::String, Label::Repeated),
+                    field("active", 4, Type::Bool, Label::Optional),
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        FileDescriptorSet { file: vec![file] }.encode_to_vec()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let message = load_message(&descriptor_set(), ".my.pkg.User").unwrap();
+        let value = json!({"user_id": 42, "name": "Ann", "tags": ["a", "b"], "active": true});
+        let bytes = encode(&value, &message).unwrap();
+        assert_eq!(&bytes[..2], &[0x08, 42]);
+        assert_eq!(decode(&bytes, &message).unwrap(), value);
+
+        // lowerCamelCase names from the canonical mapping are accepted too
+        let bytes = encode(&json!({"userId": 7}), &message).unwrap();
+        assert_eq!(decode(&bytes, &message).unwrap(), json!({"user_id": 7}));
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = load_message(&descriptor_set(), "my.pkg.Missing").unwrap_err();
+        assert!(err.to_string().contains("Available: my.pkg.User"));
+
+        let message = load_message(&descriptor_set(), "my.pkg.User").unwrap();
+        assert!(encode(&json!({"nope": 1}), &message).is_err());
+        assert!(decode(&[0x0a, 0x05, b'x'], &message).is_err());
+    }
+}
@@ -0,0 +1,303 @@
+//! Registry of format handlers
+//!
+//! Every format is a [`FormatHandler`] that names, detects, reads and writes
+//! it through the JSON value model. The built-in formats, variants of
+//! [`Format`], are registered from the start. Any other format is registered
+//! once, after which it is a [`Format::Custom`] that every command can use
+//! like a built-in one.
+//!
+//! ```
+//! use anyhow::Result;
+//! use dtx::core::converter::ConvertOptions;
+//! use dtx::formats::registry::{self, FormatHandler};
+//! use serde_json::Value;
+//!
+//! struct Lines;
+//!
+//! impl FormatHandler for Lines {
+//!     fn name(&self) -> &'static str {
+//!         "lines"
+//!     }
+//!
+//!     fn extensions(&self) -> &[&'static str] {
+//!         &["lines"]
+//!     }
+//!
+//!     fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<Value> {
+//!         let text = std::str::from_utf8(input)?;
+//!         Ok(text.lines().map(Value::from).collect())
+//!     }
+//! }
+//!
+//! let format = registry::register(Lines).unwrap();
+//! assert_eq!(format.as_str(), "lines");
+//! ```
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value as JsonValue;
+use std::sync::{Arc, OnceLock, PoisonError, RwLock};
+
+use super::detect::Format;
+use crate::core::converter::{self, ConvertOptions};
+
+/// Detection, parse and serialize hooks of a format
+pub trait FormatHandler: Send + Sync {
+    /// Name accepted by `--from` and `--to` and shown in messages
+    fn name(&self) -> &'static str;
+
+    /// Other names accepted by `--from` and `--to`
+    fn aliases(&self) -> &[&'static str] {
+        &[]
+    }
+
+    /// File extensions, without the dot, that select the format
+    fn extensions(&self) -> &[&'static str] {
+        &[]
+    }
+
+    /// Whether content is arbitrary bytes rather than UTF-8 text
+    fn is_binary(&self) -> bool {
+        false
+    }
+
+    /// Media type used when serving the format over HTTP
+    fn media_type(&self) -> &'static str {
+        if self.is_binary() {
+            "application/octet-stream"
+        } else {
+            "text/plain"
+        }
+    }
+
+    /// Whether trimmed text content is in this format; handlers are asked in
+    /// order, built-in ones first
+    fn detect(&self, _content: &str) -> bool {
+        false
+    }
+
+    /// Parse content into a value
+    fn parse(&self, input: &[u8], options: &ConvertOptions) -> Result<JsonValue>;
+
+    /// Write a value; formats that can only be read keep the default
+    fn serialize(&self, _value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+        bail!(
+            "Writing {} is not supported; it can only be read",
+            self.name()
+        )
+    }
+
+    /// Rewrite text content in the same format, by default through a value
+    fn reformat(&self, content: &str, options: &ConvertOptions) -> Result<String> {
+        let value = self.parse(content.as_bytes(), options)?;
+        String::from_utf8(self.serialize(&value, options)?)
+            .with_context(|| format!("{} output is not valid UTF-8", self.name()))
+    }
+}
+
+static BUILTIN: OnceLock<Vec<Arc<dyn FormatHandler>>> = OnceLock::new();
+
+static HANDLERS: RwLock<Vec<Arc<dyn FormatHandler>>> = RwLock::new(Vec::new());
+
+/// Register a format, returning the [`Format`] that names it. Its name and
+/// aliases must not clash with those of built-in or registered formats.
+pub fn register(handler: impl FormatHandler + 'static) -> Result<Format> {
+    let name = handler.name();
+    let names = std::iter::once(name).chain(handler.aliases().iter().copied());
+    for name in names.clone() {
+        if name.is_empty() || name.contains(|c: char| c == ',' || c.is_whitespace()) {
+            bail!("Invalid format name '{}'", name);
+        }
+    }
+    for name in names.clone() {
+        if builtin().iter().any(|h| answers_to(h.as_ref(), name)) {
+            bail!("Format '{}' is built in and cannot be registered", name);
+        }
+    }
+    let mut handlers = HANDLERS.write().unwrap_or_else(PoisonError::into_inner);
+    for name in names {
+        if handlers.iter().any(|h| answers_to(h.as_ref(), name)) {
+            bail!("Format '{}' is already registered", name);
+        }
+    }
+    handlers.push(Arc::new(handler));
+    Ok(Format::Custom(name))
+}
+
+/// The handler of a format, by name or alias (case-insensitive)
+pub fn handler(name: &str) -> Option<Arc<dyn FormatHandler>> {
+    let found = |h: &&Arc<dyn FormatHandler>| answers_to(h.as_ref(), name);
+    match builtin().iter().find(found) {
+        Some(handler) => Some(handler.clone()),
+        None => registered().iter().find(found).cloned(),
+    }
+}
+
+/// Names of the formats registered at runtime, in registration order
+pub fn names() -> Vec<&'static str> {
+    registered().iter().map(|h| h.name()).collect()
+}
+
+/// The format a handler reads and writes
+pub(crate) fn format_of(handler: &dyn FormatHandler) -> Format {
+    let name = handler.name();
+    Format::BUILTIN
+        .into_iter()
+        .find(|format| format.as_str() == name)
+        .unwrap_or(Format::Custom(name))
+}
+
+/// The format using a file extension
+pub(crate) fn from_extension(ext: &str) -> Option<Format> {
+    handlers()
+        .iter()
+        .find(|h| h.extensions().iter().any(|e| e.eq_ignore_ascii_case(ext)))
+        .map(|h| format_of(h.as_ref()))
+}
+
+/// The first format claiming trimmed text content
+pub(crate) fn detect(content: &str) -> Option<Format> {
+    handlers()
+        .iter()
+        .find(|h| h.detect(content))
+        .map(|h| format_of(h.as_ref()))
+}
+
+fn answers_to(handler: &dyn FormatHandler, name: &str) -> bool {
+    handler.name().eq_ignore_ascii_case(name)
+        || handler
+            .aliases()
+            .iter()
+            .any(|a| a.eq_ignore_ascii_case(name))
+}
+
+fn builtin() -> &'static [Arc<dyn FormatHandler>] {
+    BUILTIN.get_or_init(converter::builtin_handlers)
+}
+
+fn registered() -> Vec<Arc<dyn FormatHandler>> {
+    HANDLERS
+        .read()
+        .unwrap_or_else(PoisonError::into_inner)
+        .clone()
+}
+
+/// Built-in handlers, then registered ones
+fn handlers() -> Vec<Arc<dyn FormatHandler>> {
+    let mut handlers = builtin().to_vec();
+    handlers.extend(registered());
+    handlers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::detect::{detect_from_content, detect_from_extension};
+    use serde_json::json;
+
+    /// `key: value` lines after a `%colon` marker line
+    struct Colon;
+
+    impl FormatHandler for Colon {
+        fn name(&self) -> &'static str {
+            "colon-test"
+        }
+
+        fn extensions(&self) -> &[&'static str] {
+            &["colon"]
+        }
+
+        fn detect(&self, content: &str) -> bool {
+            content.starts_with("%colon")
+        }
+
+        fn parse(&self, input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+            let mut map = serde_json::Map::new();
+            for line in std::str::from_utf8(input)?.lines().skip(1) {
+                let (key, value) = line.split_once(": ").context("Expected 'key: value'")?;
+                map.insert(key.to_string(), json!(value));
+            }
+            Ok(JsonValue::Object(map))
+        }
+
+        fn serialize(&self, value: &JsonValue, _options: &ConvertOptions) -> Result<Vec<u8>> {
+            let obj = value.as_object().context("Expected an object")?;
+            let mut out = String::from("%colon\n");
+            for (key, value) in obj {
+                out.push_str(&format!(
+                    "{}: {}\n",
+                    key,
+                    value.as_str().unwrap_or_default()
+                ));
+            }
+            Ok(out.into_bytes())
+        }
+    }
+
+    #[test]
+    fn test_register_and_use() {
+        let format = register(Colon).unwrap();
+        assert_eq!(format, Format::Custom("colon-test"));
+        assert_eq!(Format::from_name("COLON-TEST"), Some(format));
+        assert!(names().contains(&"colon-test"));
+        assert!(register(Colon).is_err());
+        assert!(!format.is_binary());
+
+        let path = std::path::Path::new("data.colon");
+        assert_eq!(detect_from_extension(path), Some(format));
+        // Built-in formats are asked first, so YAML claims `key: value` lines
+        assert_eq!(detect_from_content("%colon\na: 1"), Some(Format::Yaml));
+        assert_eq!(detect_from_content("%colon\na:1"), Some(format));
+
+        let options = ConvertOptions::default();
+        let colon = format.handler().unwrap();
+        let value = colon.parse(b"%colon\nb: 2\na: 1", &options).unwrap();
+        assert_eq!(value, json!({"b": "2", "a": "1"}));
+        assert_eq!(
+            colon.serialize(&value, &options).unwrap(),
+            b"%colon\nb: 2\na: 1\n"
+        );
+        assert!(colon.parse(b"%colon\nbad", &options).is_err());
+        assert!(handler("missing").is_none());
+    }
+
+    #[test]
+    fn test_builtin_formats_have_handlers() {
+        for format in Format::BUILTIN {
+            let handler = handler(format.as_str()).unwrap();
+            assert_eq!(format_of(handler.as_ref()), format);
+            assert_eq!(handler.is_binary(), format.is_binary());
+        }
+        assert_eq!(Format::from_name("YML"), Some(Format::Yaml));
+        assert_eq!(Format::from_name("protobuf"), Some(Format::Protobuf));
+        assert_eq!(from_extension("tfvars"), Some(Format::Hcl));
+    }
+
+    #[test]
+    fn test_builtin_names_are_reserved() {
+        struct Yml;
+        impl FormatHandler for Yml {
+            fn name(&self) -> &'static str {
+                "yml"
+            }
+            fn parse(&self, _input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+                Ok(JsonValue::Null)
+            }
+        }
+        assert!(register(Yml).is_err());
+
+        struct Lines;
+        impl FormatHandler for Lines {
+            fn name(&self) -> &'static str {
+                "lines-test"
+            }
+            fn aliases(&self) -> &[&'static str] {
+                &["JSONL"]
+            }
+            fn parse(&self, _input: &[u8], _options: &ConvertOptions) -> Result<JsonValue> {
+                Ok(JsonValue::Null)
+            }
+        }
+        assert!(register(Lines).is_err());
+        assert!(handler("lines-test").is_none());
+    }
+}
@@ -0,0 +1,237 @@
+//! SQL output (write only)
+//!
+//! Renders an array of objects as a `CREATE TABLE` statement followed by one
+//! `INSERT` per row. Column types are inferred from the values in each column;
+//! columns mixing types become text, and nested values are stored as JSON.
+
+use anyhow::{bail, Result};
+use serde_json::Value as JsonValue;
+
+/// Table name used when none is given
+pub const DEFAULT_TABLE: &str = "data";
+
+/// SQL dialect of the generated statements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SqlDialect {
+    #[default]
+    Sqlite,
+    Postgres,
+    Mysql,
+}
+
+impl SqlDialect {
+    /// Parse a dialect name as accepted by `--dialect`
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "sqlite" => Ok(SqlDialect::Sqlite),
+            "postgres" | "postgresql" => Ok(SqlDialect::Postgres),
+            "mysql" => Ok(SqlDialect::Mysql),
+            _ => bail!(
+                "Unknown SQL dialect: {}. Use: sqlite, postgres, mysql",
+                name
+            ),
+        }
+    }
+}
+
+/// Table name and dialect for SQL output
+#[derive(Debug, Clone, Default)]
+pub struct SqlOptions {
+    /// Name of the created table; [`DEFAULT_TABLE`] when unset
+    pub table: Option<String>,
+    pub dialect: SqlDialect,
+}
+
+/// Inferred type of a column
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ColumnType {
+    Null,
+    Integer,
+    Real,
+    Boolean,
+    Text,
+    Json,
+}
+
+impl ColumnType {
+    fn of(value: &JsonValue) -> Self {
+        match value {
+            JsonValue::Null => ColumnType::Null,
+            JsonValue::Bool(_) => ColumnType::Boolean,
+            JsonValue::Number(n) if n.is_f64() => ColumnType::Real,
+            JsonValue::Number(_) => ColumnType::Integer,
+            JsonValue::String(_) => ColumnType::Text,
+            JsonValue::Array(_) | JsonValue::Object(_) => ColumnType::Json,
+        }
+    }
+
+    /// The type able to hold values of both `self` and `other`
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (ColumnType::Null, t) | (t, ColumnType::Null) => t,
+            (ColumnType::Integer, ColumnType::Real) | (ColumnType::Real, ColumnType::Integer) => {
+                ColumnType::Real
+            }
+            _ => ColumnType::Text,
+        }
+    }
+
+    fn sql_name(self, dialect: SqlDialect) -> &'static str {
+        match (self, dialect) {
+            (ColumnType::Integer, SqlDialect::Sqlite) => "INTEGER",
+            (ColumnType::Integer, _) => "BIGINT",
+            (ColumnType::Real, SqlDialect::Sqlite) => "REAL",
+            (ColumnType::Real, SqlDialect::Postgres) => "DOUBLE PRECISION",
+            (ColumnType::Real, SqlDialect::Mysql) => "DOUBLE",
+            (ColumnType::Boolean, SqlDialect::Sqlite) => "INTEGER",
+            (ColumnType::Boolean, _) => "BOOLEAN",
+            (ColumnType::Json, SqlDialect::Postgres) => "JSONB",
+            (ColumnType::Json, SqlDialect::Mysql) => "JSON",
+            (ColumnType::Null | ColumnType::Text | ColumnType::Json, _) => "TEXT",
+        }
+    }
+}
+
+/// Render rows (an array of objects, or a single object) as SQL statements
+pub fn to_string(value: &JsonValue, options: &SqlOptions) -> Result<String> {
+    let rows = match value {
+        JsonValue::Array(items) => items.as_slice(),
+        JsonValue::Object(_) => std::slice::from_ref(value),
+        _ => bail!("SQL output requires an array of objects"),
+    };
+
+    // Columns in order of first appearance, with the type fitting every value
+    let mut columns: Vec<(&str, ColumnType)> = Vec::new();
+    for (i, row) in rows.iter().enumerate() {
+        let JsonValue::Object(obj) = row else {
+            bail!(
+                "SQL output requires an array of objects; item {} is not an object",
+                i
+            );
+        };
+        for (key, value) in obj {
+            match columns.iter_mut().find(|(name, _)| name == key) {
+                Some((_, column_type)) => *column_type = column_type.merge(ColumnType::of(value)),
+                None => columns.push((key, ColumnType::of(value))),
+            }
+        }
+    }
+    if columns.is_empty() {
+        bail!("SQL output requires at least one column");
+    }
+
+    let dialect = options.dialect;
+    let table = quote_identifier(options.table.as_deref().unwrap_or(DEFAULT_TABLE), dialect);
+    let names: Vec<String> = columns
+        .iter()
+        .map(|(name, _)| quote_identifier(name, dialect))
+        .collect();
+
+    let mut sql = format!("CREATE TABLE {} (\n", table);
+    for (i, (name, (_, column_type))) in names.iter().zip(&columns).enumerate() {
+        let separator = if i + 1 < names.len() { "," } else { "" };
+        sql.push_str(&format!(
+            "  {} {}{}\n",
+            name,
+            column_type.sql_name(dialect),
+            separator
+        ));
+    }
+    sql.push_str(");\n");
+
+    let column_list = names.join(", ");
+    for row in rows {
+        let values: Vec<String> = columns
+            .iter()
+            .map(|(name, column_type)| {
+                let value = row.get(*name).unwrap_or(&JsonValue::Null);
+                literal(value, *column_type, dialect)
+            })
+            .collect();
+        sql.push_str(&format!(
+            "INSERT INTO {} ({}) VALUES ({});\n",
+            table,
+            column_list,
+            values.join(", ")
+        ));
+    }
+    Ok(sql)
+}
+
+fn quote_identifier(name: &str, dialect: SqlDialect) -> String {
+    match dialect {
+        SqlDialect::Mysql => format!("`{}`", name.replace('`', "``")),
+        _ => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+fn quote_string(text: &str, dialect: SqlDialect) -> String {
+    let escaped = text.replace('\'', "''");
+    match dialect {
+        // MySQL treats backslashes in string literals as escapes by default
+        SqlDialect::Mysql => format!("'{}'", escaped.replace('\\', "\\\\")),
+        _ => format!("'{}'", escaped),
+    }
+}
+
+fn literal(value: &JsonValue, column_type: ColumnType, dialect: SqlDialect) -> String {
+    match (value, column_type) {
+        (JsonValue::Null, _) => "NULL".to_string(),
+        (JsonValue::Bool(b), ColumnType::Boolean) => match (dialect, b) {
+            (SqlDialect::Sqlite, true) => "1".to_string(),
+            (SqlDialect::Sqlite, false) => "0".to_string(),
+            (_, true) => "TRUE".to_string(),
+            (_, false) => "FALSE".to_string(),
+        },
+        (JsonValue::Number(n), ColumnType::Integer | ColumnType::Real) => n.to_string(),
+        (JsonValue::String(s), _) => quote_string(s, dialect),
+        (other, _) => quote_string(&other.to_string(), dialect),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_create_and_insert() {
+        let rows = json!([
+            {"id": 1, "name": "O'Brien", "score": 9, "tags": ["a"], "ok": true},
+            {"id": 2, "score": 7.5, "ok": false, "note": "x"}
+        ]);
+        let sql = to_string(&rows, &SqlOptions::default()).unwrap();
+        assert_eq!(
+            sql,
+            "CREATE TABLE \"data\" (\n  \"id\" INTEGER,\n  \"name\" TEXT,\n  \"score\" REAL,\n  \
+             \"tags\" TEXT,\n  \"ok\" INTEGER,\n  \"note\" TEXT\n);\n\
+             INSERT INTO \"data\" (\"id\", \"name\", \"score\", \"tags\", \"ok\", \"note\") \
+             VALUES (1, 'O''Brien', 9, '[\"a\"]', 1, NULL);\n\
+             INSERT INTO \"data\" (\"id\", \"name\", \"score\", \"tags\", \"ok\", \"note\") \
+             VALUES (2, NULL, 7.5, NULL, 0, 'x');\n"
+        );
+    }
+
+    #[test]
+    fn test_dialects() {
+        let rows = json!([{"flag": true, "meta": {"k": 1}, "mixed": 1, "path": "C:\\tmp"},
+                          {"mixed": "two"}]);
+        let options = SqlOptions {
+            table: Some("my`table".to_string()),
+            dialect: SqlDialect::Mysql,
+        };
+        let sql = to_string(&rows, &options).unwrap();
+        assert!(sql.starts_with("CREATE TABLE `my``table` (\n  `flag` BOOLEAN,\n  `meta` JSON,"));
+        assert!(sql.contains("VALUES (TRUE, '{\"k\":1}', '1', 'C:\\\\tmp');"));
+
+        let options = SqlOptions {
+            table: None,
+            dialect: SqlDialect::from_name("postgresql").unwrap(),
+        };
+        let sql = to_string(&rows, &options).unwrap();
+        assert!(sql.contains("\"meta\" JSONB,\n  \"mixed\" TEXT"));
+        assert!(to_string(&json!([1, 2]), &options).is_err());
+        assert!(SqlDialect::from_name("oracle").is_err());
+    }
+}
@@ -0,0 +1,182 @@
+//! SQLite database input, read only
+//!
+//! A table or view becomes an array of row objects keyed by column name;
+//! without one, every table is read into an object keyed by table name.
+//! Integers and reals stay numbers and blobs become base64 text.
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, MAIN_DB};
+use serde_json::{Map, Number, Value as JsonValue};
+
+/// Every database file starts with this header string
+const MAGIC: &[u8] = b"SQLite format 3\0";
+
+/// Offsets of the file format read and write versions; 2 means WAL mode
+const FORMAT_VERSIONS: std::ops::Range<usize> = 18..20;
+
+struct Table {
+    name: String,
+    is_view: bool,
+}
+
+/// Read the table or view selected by `table` (a name or 1-based position
+/// among the tables), or every table keyed by name when unset
+pub fn read(bytes: &[u8], table: Option<&str>) -> Result<JsonValue> {
+    let conn = open(bytes)?;
+    let tables = list_tables(&conn)?;
+    if let Some(wanted) = table {
+        return read_table(&conn, select_table(&tables, wanted)?);
+    }
+    let mut all = Map::new();
+    for table in tables.iter().filter(|t| !t.is_view) {
+        all.insert(table.name.clone(), read_table(&conn, &table.name)?);
+    }
+    Ok(JsonValue::Object(all))
+}
+
+fn open(bytes: &[u8]) -> Result<Connection> {
+    if !bytes.starts_with(MAGIC) {
+        bail!("Not a SQLite database");
+    }
+    // The WAL file is not part of the input, so read the database as a
+    // rollback journal one; in-memory databases cannot open WAL mode
+    let mut data = bytes.to_vec();
+    if let Some(versions) = data.get_mut(FORMAT_VERSIONS) {
+        if versions == [2, 2] {
+            versions.copy_from_slice(&[1, 1]);
+        }
+    }
+    let mut conn = Connection::open_in_memory().context("Failed to open SQLite")?;
+    conn.deserialize_read_exact(MAIN_DB, data.as_slice(), data.len(), true)
+        .context("Failed to read SQLite database")?;
+    Ok(conn)
+}
+
+fn list_tables(conn: &Connection) -> Result<Vec<Table>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT name, type = 'view' FROM sqlite_master \
+             WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite\\_%' ESCAPE '\\' \
+             ORDER BY rowid",
+        )
+        .context("Failed to read SQLite schema")?;
+    let tables = stmt
+        .query_map([], |row| {
+            Ok(Table {
+                name: row.get(0)?,
+                is_view: row.get(1)?,
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()
+        .context("Failed to read SQLite schema")?;
+    Ok(tables)
+}
+
+fn select_table<'a>(tables: &'a [Table], wanted: &str) -> Result<&'a str> {
+    // Identifiers in SQLite are case-insensitive
+    if let Some(found) = tables.iter().find(|t| t.name.eq_ignore_ascii_case(wanted)) {
+        return Ok(&found.name);
+    }
+    if let Ok(index) = wanted.parse::<usize>() {
+        let found = index
+            .checked_sub(1)
+            .and_then(|i| tables.iter().filter(|t| !t.is_view).nth(i));
+        if let Some(found) = found {
+            return Ok(&found.name);
+        }
+    }
+    let names: Vec<&str> = tables.iter().map(|t| t.name.as_str()).collect();
+    if names.is_empty() {
+        bail!("Table '{}' not found; the database has no tables", wanted);
+    }
+    bail!(
+        "Table '{}' not found. Available: {}",
+        wanted,
+        names.join(", ")
+    )
+}
+
+fn read_table(conn: &Connection, name: &str) -> Result<JsonValue> {
+    let sql = format!("SELECT * FROM \"{}\"", name.replace('"', "\"\""));
+    let mut stmt = conn
+        .prepare(&sql)
+        .with_context(|| format!("Failed to read table '{}'", name))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = stmt.query([])?;
+    let mut records = Vec::new();
+    while let Some(row) = rows
+        .next()
+        .with_context(|| format!("Failed to read table '{}'", name))?
+    {
+        let mut record = Map::new();
+        for (i, column) in columns.iter().enumerate() {
+            record.insert(column.clone(), to_json(row.get_ref(i)?));
+        }
+        records.push(JsonValue::Object(record));
+    }
+    Ok(JsonValue::Array(records))
+}
+
+fn to_json(value: ValueRef) -> JsonValue {
+    match value {
+        ValueRef::Null => JsonValue::Null,
+        ValueRef::Integer(i) => JsonValue::from(i),
+        ValueRef::Real(f) => Number::from_f64(f).map_or(JsonValue::Null, JsonValue::Number),
+        ValueRef::Text(text) => JsonValue::String(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(blob) => {
+            JsonValue::String(base64::engine::general_purpose::STANDARD.encode(blob))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn database() -> Vec<u8> {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, score REAL, avatar BLOB);
+             INSERT INTO users VALUES (1, 'Ann', 9.5, x'DEADBEEF'), (2, NULL, NULL, NULL);
+             CREATE TABLE \"odd \"\"name\" (x);
+             INSERT INTO \"odd \"\"name\" VALUES (7);
+             CREATE VIEW names AS SELECT name FROM users WHERE name IS NOT NULL;",
+        )
+        .unwrap();
+        conn.serialize(MAIN_DB).unwrap().to_vec()
+    }
+
+    #[test]
+    fn test_read_tables() {
+        let bytes = database();
+        let users = json!([
+            {"id": 1, "name": "Ann", "score": 9.5, "avatar": "3q2+7w=="},
+            {"id": 2, "name": null, "score": null, "avatar": null}
+        ]);
+        assert_eq!(read(&bytes, Some("USERS")).unwrap(), users);
+        assert_eq!(read(&bytes, Some("2")).unwrap(), json!([{"x": 7}]));
+        assert_eq!(
+            read(&bytes, Some("names")).unwrap(),
+            json!([{"name": "Ann"}])
+        );
+        assert_eq!(
+            read(&bytes, None).unwrap(),
+            json!({"users": users, "odd \"name": [{"x": 7}]})
+        );
+
+        let err = read(&bytes, Some("missing")).unwrap_err().to_string();
+        assert!(err.contains("Available: users, odd \"name, names"));
+        assert!(read(b"not a database", None).is_err());
+    }
+
+    #[test]
+    fn test_read_wal_mode() {
+        let mut bytes = database();
+        bytes[18] = 2;
+        bytes[19] = 2;
+        assert_eq!(read(&bytes, Some("odd \"name")).unwrap(), json!([{"x": 7}]));
+    }
+}
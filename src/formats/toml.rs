@@ -1,38 +1,233 @@
 //! TOML format handling
 
 use anyhow::{Context, Result};
-use std::fs;
-use std::io::{self, Read};
 use std::path::Path;
 use toml::Value;
 
-/// Read input from file or stdin
-pub fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
-        Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
-        }
-        None => {
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
-        }
+use crate::formats::float::FloatFormat;
+use crate::formats::OutputStyle;
+use crate::utils::diagnostics;
+use crate::utils::encoding::EncodingOptions;
+
+/// Placement of nested tables in TOML output
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TomlLayout {
+    /// Tables nested deeper than this are written inline (`{ ... }`) instead of
+    /// under a `[section]` header; the root is depth 0
+    pub inline_depth: Option<usize>,
+    /// Write arrays of tables as inline arrays instead of `[[name]]` sections
+    pub inline_arrays: bool,
+}
+
+impl TomlLayout {
+    fn is_default(&self) -> bool {
+        self.inline_depth.is_none() && !self.inline_arrays
     }
 }
 
+/// Read input from file or stdin
+pub fn read_input(path: Option<&Path>, encoding: &EncodingOptions) -> Result<String> {
+    encoding.read_input(path)
+}
+
 /// Parse TOML string into Value
 pub fn parse(content: &str) -> Result<Value> {
-    content.parse::<Value>().context("Failed to parse TOML")
+    content
+        .parse::<Value>()
+        .map_err(|e| diagnostics::located(e, content, "Failed to parse TOML"))
 }
 
 /// Convert Value to pretty-printed TOML string
-pub fn to_pretty(value: &Value) -> Result<String> {
-    toml::to_string_pretty(value).context("Failed to serialize TOML")
+pub fn to_pretty(value: &Value, style: &OutputStyle) -> Result<String> {
+    to_pretty_with_layout(value, &TomlLayout::default(), style)
+}
+
+/// Convert Value to pretty-printed TOML string with the given table layout
+///
+/// Multi-line arrays use the indentation and floats the formatting of `style`
+/// when one is set.
+pub fn to_pretty_with_layout(
+    value: &Value,
+    layout: &TomlLayout,
+    style: &OutputStyle,
+) -> Result<String> {
+    let pretty = toml::to_string_pretty(value).context("Failed to serialize TOML")?;
+    let array_indent = style.indent;
+    let float_format = style.float;
+    if layout.is_default() && array_indent.is_none() && float_format.is_none() {
+        return Ok(pretty);
+    }
+    let mut document: toml_edit::DocumentMut =
+        pretty.parse().context("Failed to serialize TOML")?;
+    apply_layout(document.as_table_mut(), 0, layout);
+    if let Some(array_indent) = array_indent {
+        indent_arrays(document.as_table_mut(), &array_indent.unit());
+    }
+    if let Some(float_format) = float_format {
+        for (_, item) in document.as_table_mut().iter_mut() {
+            format_floats(item, &float_format);
+        }
+    }
+    Ok(document.to_string())
+}
+
+/// Re-indent the elements of multi-line arrays with `unit` per nesting level
+fn indent_arrays(table: &mut toml_edit::Table, unit: &str) {
+    for (_, item) in table.iter_mut() {
+        match item {
+            toml_edit::Item::Table(child) => indent_arrays(child, unit),
+            toml_edit::Item::ArrayOfTables(array) => {
+                for child in array.iter_mut() {
+                    indent_arrays(child, unit);
+                }
+            }
+            toml_edit::Item::Value(value) => indent_value(value, unit, 1),
+            toml_edit::Item::None => {}
+        }
+    }
+}
+
+fn indent_value(value: &mut toml_edit::Value, unit: &str, depth: usize) {
+    match value {
+        toml_edit::Value::Array(array) => {
+            let multiline = array.iter().any(|item| {
+                item.decor()
+                    .prefix()
+                    .and_then(|prefix| prefix.as_str())
+                    .is_some_and(|prefix| prefix.contains('\n'))
+            });
+            for item in array.iter_mut() {
+                if multiline {
+                    item.decor_mut()
+                        .set_prefix(format!("\n{}", unit.repeat(depth)));
+                }
+                indent_value(item, unit, depth + 1);
+            }
+            if multiline {
+                array.set_trailing(format!("\n{}", unit.repeat(depth - 1)));
+            }
+        }
+        toml_edit::Value::InlineTable(table) => {
+            for (_, item) in table.iter_mut() {
+                indent_value(item, unit, depth);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Rewrite float literals with the requested `--float-*` formatting
+fn format_floats(item: &mut toml_edit::Item, format: &FloatFormat) {
+    match item {
+        toml_edit::Item::Table(table) => {
+            for (_, item) in table.iter_mut() {
+                format_floats(item, format);
+            }
+        }
+        toml_edit::Item::ArrayOfTables(array) => {
+            for table in array.iter_mut() {
+                for (_, item) in table.iter_mut() {
+                    format_floats(item, format);
+                }
+            }
+        }
+        toml_edit::Item::Value(value) => format_float_value(value, format),
+        toml_edit::Item::None => {}
+    }
+}
+
+fn format_float_value(value: &mut toml_edit::Value, format: &FloatFormat) {
+    match value {
+        toml_edit::Value::Float(float) => {
+            let mut text = format.format(*float.value());
+            // `3` would read back as an integer
+            if !text.contains(['.', 'e']) {
+                text.push_str(".0");
+            }
+            if let Ok(mut formatted) = text.parse::<toml_edit::Value>() {
+                *formatted.decor_mut() = float.decor().clone();
+                *value = formatted;
+            }
+        }
+        toml_edit::Value::Array(array) => {
+            for item in array.iter_mut() {
+                format_float_value(item, format);
+            }
+        }
+        toml_edit::Value::InlineTable(table) => {
+            for (_, item) in table.iter_mut() {
+                format_float_value(item, format);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn apply_layout(table: &mut toml_edit::Table, depth: usize, layout: &TomlLayout) {
+    let inline = layout.inline_depth.is_some_and(|max| depth + 1 > max);
+    for (mut key, item) in table.iter_mut() {
+        match item {
+            toml_edit::Item::Table(child) if !inline => apply_layout(child, depth + 1, layout),
+            toml_edit::Item::ArrayOfTables(array) if !inline && !layout.inline_arrays => {
+                for child in array.iter_mut() {
+                    apply_layout(child, depth + 1, layout);
+                }
+            }
+            toml_edit::Item::Table(_) | toml_edit::Item::ArrayOfTables(_) => {
+                item.make_value();
+                // Header keys carry no spacing; restore the default ` = ` around them
+                key.leaf_decor_mut().clear();
+            }
+            _ => {}
+        }
+    }
 }
 
 /// Convert Value to compact TOML string
 pub fn to_compact(value: &Value) -> Result<String> {
     toml::to_string(value).context("Failed to serialize TOML")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        let value = parse(
+            "name = \"app\"\n[server]\nport = 80\n[server.tls]\ncert = \"a.pem\"\n[[users]]\nid = 1\n",
+        )
+        .unwrap();
+
+        let layout = TomlLayout {
+            inline_depth: Some(1),
+            ..Default::default()
+        };
+        let output = to_pretty_with_layout(&value, &layout, &OutputStyle::default()).unwrap();
+        assert!(output.contains("[server]"));
+        assert!(output.contains("tls = { cert = \"a.pem\" }"));
+        assert!(output.contains("[[users]]"));
+
+        let layout = TomlLayout {
+            inline_arrays: true,
+            ..Default::default()
+        };
+        let output = to_pretty_with_layout(&value, &layout, &OutputStyle::default()).unwrap();
+        assert!(output.contains("[server.tls]"));
+        assert!(output.contains("users = [{ id = 1 }]"));
+        assert_eq!(parse(&output).unwrap(), value);
+    }
+
+    #[test]
+    fn test_indent_arrays() {
+        let value = parse("a = [1, [2, 3]]\ns = \"\"\"\nx\n    y\"\"\"\n").unwrap();
+        let mut document: toml_edit::DocumentMut =
+            toml::to_string_pretty(&value).unwrap().parse().unwrap();
+        indent_arrays(document.as_table_mut(), "\t");
+        let output = document.to_string();
+
+        assert!(output.starts_with("a = [\n\t1,\n\t[\n\t\t2,\n\t\t3,\n\t],\n]\n"));
+        assert!(output.contains("\n    y"));
+        assert_eq!(parse(&output).unwrap(), value);
+    }
+}
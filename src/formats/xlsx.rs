@@ -0,0 +1,796 @@
+//! Excel workbook handling
+//!
+//! Reads one worksheet of an `.xlsx` file into [`CsvData`], the first row
+//! becoming the headers. Cells keep the text Excel would show for shared,
+//! inline and formula strings; numbers formatted as dates or times become ISO
+//! 8601 text.
+//!
+//! Writing produces a one-sheet workbook with a bold, frozen header row.
+//! Numbers and booleans keep their cell types; everything else is text.
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{Duration, NaiveDate};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use super::csv::CsvData;
+use crate::utils::zip::{ZipArchive, ZipWriter};
+
+/// Built-in number formats that display a date or time
+const DATE_FORMAT_IDS: &[u32] = &[14, 15, 16, 17, 18, 19, 20, 21, 22, 45, 46, 47];
+
+/// Worksheet limits Excel enforces when opening a file
+const MAX_ROWS: usize = 1_048_576;
+const MAX_COLUMNS: usize = 16_384;
+const MAX_CELL_CHARS: usize = 32_767;
+
+/// Integers beyond this lose precision as Excel numbers and are written as text
+const MAX_SAFE_INTEGER: u64 = (1 << 53) - 1;
+
+const MAIN_NS: &str = "http://schemas.openxmlformats.org/spreadsheetml/2006/main";
+const XML_DECLARATION: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>"#;
+
+const CONTENT_TYPES: &str = concat!(
+    r#"<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types">"#,
+    r#"<Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/>"#,
+    r#"<Default Extension="xml" ContentType="application/xml"/>"#,
+    r#"<Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/>"#,
+    r#"<Override PartName="/xl/worksheets/sheet1.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+    r#"<Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>"#,
+    r#"</Types>"#
+);
+
+const ROOT_RELS: &str = concat!(
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/>"#,
+    r#"</Relationships>"#
+);
+
+const WORKBOOK_RELS: &str = concat!(
+    r#"<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    r#"<Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet1.xml"/>"#,
+    r#"<Relationship Id="rId2" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/>"#,
+    r#"</Relationships>"#
+);
+
+/// Default font as style 0, bold as style 1 for the header row
+const STYLES: &str = concat!(
+    r#"<fonts count="2"><font><sz val="11"/><name val="Calibri"/></font>"#,
+    r#"<font><b/><sz val="11"/><name val="Calibri"/></font></fonts>"#,
+    r#"<fills count="2"><fill><patternFill patternType="none"/></fill>"#,
+    r#"<fill><patternFill patternType="gray125"/></fill></fills>"#,
+    r#"<borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders>"#,
+    r#"<cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs>"#,
+    r#"<cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/>"#,
+    r#"<xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs>"#,
+    r#"<cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles>"#
+);
+
+struct Sheet {
+    name: String,
+    relationship: String,
+}
+
+/// Read the worksheet selected by `sheet` (a name or 1-based index; the first
+/// sheet when unset)
+pub fn read(bytes: &[u8], sheet: Option<&str>) -> Result<CsvData> {
+    let archive = ZipArchive::new(bytes).context("Not an XLSX workbook")?;
+    let part = |name: &str| -> Result<Option<String>> {
+        archive
+            .read(name)?
+            .map(|data| String::from_utf8(data).with_context(|| format!("{} is not UTF-8", name)))
+            .transpose()
+    };
+
+    let workbook = part("xl/workbook.xml")?.context("XLSX workbook has no xl/workbook.xml")?;
+    let (sheets, date1904) = read_workbook(&workbook)?;
+    let selected = select_sheet(&sheets, sheet)?;
+
+    let rels = part("xl/_rels/workbook.xml.rels")?.unwrap_or_default();
+    let target = relationship_targets(&rels)?
+        .remove(&selected.relationship)
+        .with_context(|| format!("Sheet '{}' has no worksheet part", selected.name))?;
+    let path = match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => format!("xl/{}", target),
+    };
+
+    let shared = match part("xl/sharedStrings.xml")? {
+        Some(xml) => shared_strings(&xml)?,
+        None => Vec::new(),
+    };
+    let date_styles = match part("xl/styles.xml")? {
+        Some(xml) => date_styles(&xml)?,
+        None => Vec::new(),
+    };
+    let xml = part(&path)?.with_context(|| format!("XLSX workbook is missing {}", path))?;
+    let mut rows = worksheet_rows(&xml, &shared, &date_styles, date1904)
+        .with_context(|| format!("Failed to read sheet '{}'", selected.name))?;
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    for row in &mut rows {
+        row.resize(width, String::new());
+    }
+    if rows.is_empty() {
+        return Ok(CsvData::with_headers(Vec::new(), Vec::new()));
+    }
+    let headers = rows.remove(0);
+    Ok(CsvData::with_headers(headers, rows))
+}
+
+fn select_sheet<'a>(sheets: &'a [Sheet], sheet: Option<&str>) -> Result<&'a Sheet> {
+    let Some(wanted) = sheet else {
+        return sheets.first().context("XLSX workbook has no sheets");
+    };
+    if let Some(found) = sheets.iter().find(|s| s.name == wanted) {
+        return Ok(found);
+    }
+    if let Ok(index) = wanted.parse::<usize>() {
+        if let Some(found) = index.checked_sub(1).and_then(|i| sheets.get(i)) {
+            return Ok(found);
+        }
+    }
+    let names: Vec<&str> = sheets.iter().map(|s| s.name.as_str()).collect();
+    bail!(
+        "No sheet '{}' in workbook (sheets: {})",
+        wanted,
+        names.join(", ")
+    )
+}
+
+fn attribute(e: &BytesStart, name: &[u8]) -> Result<Option<String>> {
+    for attr in e.attributes().flatten() {
+        if attr.key.local_name().as_ref() == name {
+            return Ok(Some(attr.unescape_value()?.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn read_workbook(xml: &str) -> Result<(Vec<Sheet>, bool)> {
+    let mut reader = Reader::from_str(xml);
+    let mut sheets = Vec::new();
+    let mut date1904 = false;
+    loop {
+        match reader
+            .read_event()
+            .context("Failed to parse xl/workbook.xml")?
+        {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"sheet" => sheets.push(Sheet {
+                    name: attribute(&e, b"name")?.unwrap_or_default(),
+                    relationship: attribute(&e, b"id")?.unwrap_or_default(),
+                }),
+                b"workbookPr" => {
+                    date1904 = matches!(attribute(&e, b"date1904")?.as_deref(), Some("1" | "true"));
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok((sheets, date1904))
+}
+
+fn relationship_targets(xml: &str) -> Result<HashMap<String, String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut targets = HashMap::new();
+    loop {
+        match reader
+            .read_event()
+            .context("Failed to parse workbook relationships")?
+        {
+            Event::Start(e) | Event::Empty(e) if e.local_name().as_ref() == b"Relationship" => {
+                if let (Some(id), Some(target)) = (attribute(&e, b"Id")?, attribute(&e, b"Target")?)
+                {
+                    targets.insert(id, target);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(targets)
+}
+
+/// Text of each `<si>` item; phonetic runs (`<rPh>`) are left out
+fn shared_strings(xml: &str) -> Result<Vec<String>> {
+    let mut reader = Reader::from_str(xml);
+    let mut strings = Vec::new();
+    let mut current = String::new();
+    let (mut in_text, mut in_phonetic) = (false, false);
+    loop {
+        match reader
+            .read_event()
+            .context("Failed to parse xl/sharedStrings.xml")?
+        {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"t" => in_text = true,
+                b"rPh" => in_phonetic = true,
+                _ => {}
+            },
+            Event::End(e) => match e.local_name().as_ref() {
+                b"t" => in_text = false,
+                b"rPh" => in_phonetic = false,
+                b"si" => strings.push(std::mem::take(&mut current)),
+                _ => {}
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"si" => strings.push(String::new()),
+            Event::Text(t) if in_text && !in_phonetic => current.push_str(&t.unescape()?),
+            Event::CData(t) if in_text && !in_phonetic => {
+                current.push_str(&String::from_utf8_lossy(&t))
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(strings)
+}
+
+/// For each cell style (`cellXfs` entry), whether it displays a date or time
+fn date_styles(xml: &str) -> Result<Vec<bool>> {
+    let mut reader = Reader::from_str(xml);
+    let mut custom = HashMap::new();
+    let mut styles = Vec::new();
+    let mut in_cell_xfs = false;
+    loop {
+        match reader
+            .read_event()
+            .context("Failed to parse xl/styles.xml")?
+        {
+            Event::Start(e) | Event::Empty(e) => match e.local_name().as_ref() {
+                b"numFmt" => {
+                    if let (Some(id), Some(code)) =
+                        (attribute(&e, b"numFmtId")?, attribute(&e, b"formatCode")?)
+                    {
+                        custom.insert(id, is_date_format(&code));
+                    }
+                }
+                b"cellXfs" => in_cell_xfs = true,
+                b"xf" if in_cell_xfs => {
+                    let id = attribute(&e, b"numFmtId")?.unwrap_or_default();
+                    let date = match custom.get(&id) {
+                        Some(&date) => date,
+                        None => id.parse().is_ok_and(|id| DATE_FORMAT_IDS.contains(&id)),
+                    };
+                    styles.push(date);
+                }
+                _ => {}
+            },
+            Event::End(e) if e.local_name().as_ref() == b"cellXfs" => in_cell_xfs = false,
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(styles)
+}
+
+/// Whether a number format code shows a date or time, ignoring quoted
+/// literals, escaped characters and bracketed colours or conditions
+fn is_date_format(code: &str) -> bool {
+    let mut chars = code.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                chars.by_ref().find(|&c| c == '"');
+            }
+            '\\' | '_' | '*' => {
+                chars.next();
+            }
+            '[' => {
+                let inner: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                // Elapsed time such as [h]:mm
+                if inner
+                    .chars()
+                    .all(|c| matches!(c, 'h' | 'H' | 'm' | 'M' | 's' | 'S'))
+                    && !inner.is_empty()
+                {
+                    return true;
+                }
+            }
+            'd' | 'D' | 'm' | 'M' | 'y' | 'Y' | 'h' | 'H' | 's' | 'S' => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Zero-based column of a cell reference such as `AB12`, or `None` without
+/// column letters; references past the last column (XFD) or row are errors
+fn column_index(reference: &str) -> Result<Option<usize>> {
+    let letters = reference
+        .bytes()
+        .take_while(u8::is_ascii_alphabetic)
+        .count();
+    let (column, row) = reference.split_at(letters);
+    let outside = || anyhow!("Cell reference {} is outside the worksheet", reference);
+
+    if !row.is_empty() && row.bytes().all(|b| b.is_ascii_digit()) {
+        row.parse::<usize>()
+            .ok()
+            .filter(|row| (1..=MAX_ROWS).contains(row))
+            .ok_or_else(outside)?;
+    }
+    if column.is_empty() {
+        return Ok(None);
+    }
+    let mut index = 0usize;
+    for b in column.bytes() {
+        index = index
+            .checked_mul(26)
+            .and_then(|i| i.checked_add((b.to_ascii_uppercase() - b'A') as usize + 1))
+            .filter(|&i| i <= MAX_COLUMNS)
+            .ok_or_else(outside)?;
+    }
+    Ok(Some(index - 1))
+}
+
+/// Excel serial date as ISO 8601 text: a date, a time, or both
+fn serial_to_iso(serial: f64, date1904: bool) -> Option<String> {
+    if !serial.is_finite() || serial < 0.0 {
+        return None;
+    }
+    let days = serial.trunc() as i64;
+    let seconds = ((serial - serial.trunc()) * 86_400.0).round() as i64;
+    let base = match (date1904, days) {
+        (true, _) => NaiveDate::from_ymd_opt(1904, 1, 1)?,
+        // Excel counts a 29 February 1900 that never existed
+        (false, 0..=60) => NaiveDate::from_ymd_opt(1899, 12, 31)?,
+        (false, _) => NaiveDate::from_ymd_opt(1899, 12, 30)?,
+    };
+    let datetime = base
+        .and_hms_opt(0, 0, 0)?
+        .checked_add_signed(Duration::try_days(days)? + Duration::try_seconds(seconds)?)?;
+    Some(match (days, seconds) {
+        (0, _) if !date1904 => datetime.format("%H:%M:%S").to_string(),
+        (_, 0) => datetime.format("%Y-%m-%d").to_string(),
+        _ => datetime.format("%Y-%m-%dT%H:%M:%S").to_string(),
+    })
+}
+
+struct Cell {
+    column: Option<usize>,
+    kind: String,
+    style: usize,
+    value: String,
+}
+
+fn worksheet_rows(
+    xml: &str,
+    shared: &[String],
+    date_styles: &[bool],
+    date1904: bool,
+) -> Result<Vec<Vec<String>>> {
+    let mut reader = Reader::from_str(xml);
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut cell: Option<Cell> = None;
+    let mut in_value = false;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) => match e.local_name().as_ref() {
+                b"row" => row.clear(),
+                b"c" => cell = Some(new_cell(&e)?),
+                b"v" | b"t" if cell.is_some() => in_value = true,
+                _ => {}
+            },
+            Event::Empty(e) if e.local_name().as_ref() == b"c" => {
+                place(&mut row, new_cell(&e)?, String::new())?;
+            }
+            Event::Text(t) if in_value => {
+                if let Some(cell) = cell.as_mut() {
+                    cell.value.push_str(&t.unescape()?);
+                }
+            }
+            Event::End(e) => match e.local_name().as_ref() {
+                b"v" | b"t" => in_value = false,
+                b"c" => {
+                    if let Some(cell) = cell.take() {
+                        let text = cell_text(&cell, shared, date_styles, date1904)?;
+                        place(&mut row, cell, text)?;
+                    }
+                }
+                b"row" if row.iter().any(|c| !c.is_empty()) => {
+                    rows.push(std::mem::take(&mut row));
+                }
+                _ => {}
+            },
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    Ok(rows)
+}
+
+fn new_cell(e: &BytesStart) -> Result<Cell> {
+    Ok(Cell {
+        column: match attribute(e, b"r")? {
+            Some(reference) => column_index(&reference)?,
+            None => None,
+        },
+        kind: attribute(e, b"t")?.unwrap_or_else(|| "n".to_string()),
+        style: attribute(e, b"s")?
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0),
+        value: String::new(),
+    })
+}
+
+/// Put a cell at its referenced column, or after the previous one
+fn place(row: &mut Vec<String>, cell: Cell, text: String) -> Result<()> {
+    let column = cell.column.unwrap_or(row.len());
+    if column >= MAX_COLUMNS {
+        bail!("Row has more than {} cells", MAX_COLUMNS);
+    }
+    if row.len() <= column {
+        row.resize(column + 1, String::new());
+    }
+    row[column] = text;
+    Ok(())
+}
+
+fn cell_text(
+    cell: &Cell,
+    shared: &[String],
+    date_styles: &[bool],
+    date1904: bool,
+) -> Result<String> {
+    let value = cell.value.as_str();
+    Ok(match cell.kind.as_str() {
+        "s" => {
+            let index: usize = value
+                .trim()
+                .parse()
+                .context("Invalid shared string index")?;
+            shared
+                .get(index)
+                .with_context(|| format!("Shared string {} does not exist", index))?
+                .clone()
+        }
+        "b" => match value.trim() {
+            "1" => "true".to_string(),
+            _ => "false".to_string(),
+        },
+        "n" if date_styles.get(cell.style).copied().unwrap_or(false) => value
+            .trim()
+            .parse()
+            .ok()
+            .and_then(|serial| serial_to_iso(serial, date1904))
+            .unwrap_or_else(|| value.to_string()),
+        _ => value.to_string(),
+    })
+}
+
+/// Write rows as a one-sheet workbook named `sheet` (default `Sheet1`). An
+/// array of objects (or a single object) gets a header row of their keys in
+/// order of first appearance; an array of arrays is written as is.
+pub fn write(value: &JsonValue, sheet: Option<&str>) -> Result<Vec<u8>> {
+    let name = sheet.unwrap_or("Sheet1");
+    validate_sheet_name(name)?;
+
+    let items = match value {
+        JsonValue::Array(items) => items.as_slice(),
+        JsonValue::Object(_) => std::slice::from_ref(value),
+        _ => bail!("XLSX output requires an array of objects or arrays"),
+    };
+    let (header, rows) = if items.iter().all(JsonValue::is_array) {
+        let rows: Vec<Vec<&JsonValue>> = items
+            .iter()
+            .filter_map(JsonValue::as_array)
+            .map(|row| row.iter().collect())
+            .collect();
+        (None, rows)
+    } else {
+        let mut columns: Vec<&str> = Vec::new();
+        for (i, item) in items.iter().enumerate() {
+            let JsonValue::Object(obj) = item else {
+                bail!(
+                    "XLSX output requires an array of objects; item {} is not an object",
+                    i
+                );
+            };
+            for key in obj.keys() {
+                if !columns.contains(&key.as_str()) {
+                    columns.push(key);
+                }
+            }
+        }
+        let rows = items
+            .iter()
+            .map(|item| {
+                columns
+                    .iter()
+                    .map(|column| item.get(column).unwrap_or(&JsonValue::Null))
+                    .collect()
+            })
+            .collect();
+        (Some(columns), rows)
+    };
+
+    let row_count = rows.len() + usize::from(header.is_some());
+    if row_count > MAX_ROWS {
+        bail!(
+            "XLSX output has {} rows; a worksheet holds at most {}",
+            row_count,
+            MAX_ROWS
+        );
+    }
+    let width = header
+        .as_ref()
+        .map(Vec::len)
+        .into_iter()
+        .chain(rows.iter().map(Vec::len))
+        .max()
+        .unwrap_or(0);
+    if width > MAX_COLUMNS {
+        bail!(
+            "XLSX output has {} columns; a worksheet holds at most {}",
+            width,
+            MAX_COLUMNS
+        );
+    }
+
+    let mut xml = format!(r#"{}<worksheet xmlns="{}">"#, XML_DECLARATION, MAIN_NS);
+    if header.is_some() {
+        xml.push_str(concat!(
+            r#"<sheetViews><sheetView workbookViewId="0">"#,
+            r#"<pane ySplit="1" topLeftCell="A2" activePane="bottomLeft" state="frozen"/>"#,
+            r#"</sheetView></sheetViews>"#
+        ));
+    }
+    xml.push_str("<sheetData>");
+    let mut number = 0;
+    if let Some(columns) = &header {
+        number += 1;
+        write!(xml, r#"<row r="{}">"#, number)?;
+        for (i, column) in columns.iter().enumerate() {
+            write_text_cell(&mut xml, &cell_reference(i, number), column, true)?;
+        }
+        xml.push_str("</row>");
+    }
+    for row in &rows {
+        number += 1;
+        write!(xml, r#"<row r="{}">"#, number)?;
+        for (i, value) in row.iter().enumerate() {
+            write_cell(&mut xml, &cell_reference(i, number), value)?;
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+
+    let workbook = format!(
+        r#"{}<workbook xmlns="{}" xmlns:r="{}"><sheets><sheet name="{}" sheetId="1" r:id="rId1"/></sheets></workbook>"#,
+        XML_DECLARATION,
+        MAIN_NS,
+        "http://schemas.openxmlformats.org/officeDocument/2006/relationships",
+        escape_xml(name)
+    );
+    let part = |body: &str| format!("{}{}", XML_DECLARATION, body);
+    let mut zip = ZipWriter::default();
+    zip.add("[Content_Types].xml", part(CONTENT_TYPES).as_bytes())?;
+    zip.add("_rels/.rels", part(ROOT_RELS).as_bytes())?;
+    zip.add("xl/workbook.xml", workbook.as_bytes())?;
+    zip.add("xl/_rels/workbook.xml.rels", part(WORKBOOK_RELS).as_bytes())?;
+    let styles = format!(r#"<styleSheet xmlns="{}">{}</styleSheet>"#, MAIN_NS, STYLES);
+    zip.add("xl/styles.xml", part(&styles).as_bytes())?;
+    zip.add("xl/worksheets/sheet1.xml", xml.as_bytes())?;
+    zip.finish()
+}
+
+fn validate_sheet_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.chars().count() > 31 {
+        bail!("Sheet name '{}' must be 1 to 31 characters long", name);
+    }
+    if let Some(c) = name.chars().find(|c| r"[]:*?/\".contains(*c)) {
+        bail!("Sheet name '{}' cannot contain '{}'", name, c);
+    }
+    if name.starts_with('\'') || name.ends_with('\'') {
+        bail!(
+            "Sheet name '{}' cannot start or end with an apostrophe",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// `A1`-style reference for a 0-based column and 1-based row
+fn cell_reference(column: usize, row: usize) -> String {
+    let mut letters = Vec::new();
+    let mut n = column + 1;
+    while n > 0 {
+        let rem = (n - 1) % 26;
+        letters.push(b'A' + rem as u8);
+        n = (n - 1) / 26;
+    }
+    letters.reverse();
+    format!("{}{}", String::from_utf8(letters).unwrap_or_default(), row)
+}
+
+fn write_cell(xml: &mut String, reference: &str, value: &JsonValue) -> Result<()> {
+    match value {
+        JsonValue::Null => {}
+        JsonValue::Bool(b) => write!(
+            xml,
+            r#"<c r="{}" t="b"><v>{}</v></c>"#,
+            reference,
+            u8::from(*b)
+        )?,
+        JsonValue::Number(n) if fits_number(n) => {
+            write!(xml, r#"<c r="{}"><v>{}</v></c>"#, reference, n)?
+        }
+        JsonValue::Number(n) => write_text_cell(xml, reference, &n.to_string(), false)?,
+        JsonValue::String(s) => write_text_cell(xml, reference, s, false)?,
+        JsonValue::Array(_) | JsonValue::Object(_) => {
+            write_text_cell(xml, reference, &value.to_string(), false)?
+        }
+    }
+    Ok(())
+}
+
+fn fits_number(n: &serde_json::Number) -> bool {
+    if let Some(i) = n.as_i64() {
+        i.unsigned_abs() <= MAX_SAFE_INTEGER
+    } else if let Some(u) = n.as_u64() {
+        u <= MAX_SAFE_INTEGER
+    } else {
+        n.as_f64().is_some_and(f64::is_finite)
+    }
+}
+
+fn write_text_cell(xml: &mut String, reference: &str, text: &str, bold: bool) -> Result<()> {
+    let length = text.chars().count();
+    if length > MAX_CELL_CHARS {
+        bail!(
+            "Cell {} has {} characters; an XLSX cell holds at most {}",
+            reference,
+            length,
+            MAX_CELL_CHARS
+        );
+    }
+    let style = if bold { r#" s="1""# } else { "" };
+    write!(
+        xml,
+        r#"<c r="{}"{} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+        reference,
+        style,
+        escape_xml(text)
+    )?;
+    Ok(())
+}
+
+/// Escape text for XML; control characters XML cannot carry use Excel's
+/// `_xHHHH_` notation
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {
+                let _ = write!(escaped, "_x{:04X}_", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::zip::stored_archive;
+
+    const WORKBOOK: &str = r#"<workbook xmlns:r="urn:r"><workbookPr/><sheets>
+        <sheet name="Summary" sheetId="1" r:id="rId1"/>
+        <sheet name="Orders" sheetId="2" r:id="rId2"/></sheets></workbook>"#;
+    const RELS: &str = r#"<Relationships>
+        <Relationship Id="rId1" Target="worksheets/sheet1.xml"/>
+        <Relationship Id="rId2" Target="/xl/worksheets/sheet2.xml"/></Relationships>"#;
+    const SHARED: &str = r#"<sst><si><t>id</t></si><si><r><t>na</t></r><r><t>me</t></r>
+        <rPh><t>x</t></rPh></si><si><t>when</t></si><si><t>Widget &amp; Co</t></si></sst>"#;
+    const STYLES: &str = r#"<styleSheet>
+        <numFmts><numFmt numFmtId="164" formatCode="&quot;Day &quot;0"/></numFmts>
+        <cellStyleXfs><xf numFmtId="14"/></cellStyleXfs>
+        <cellXfs><xf numFmtId="0"/><xf numFmtId="14"/><xf numFmtId="164"/></cellXfs>
+        </styleSheet>"#;
+    const ORDERS: &str = r#"<worksheet><sheetData>
+        <row r="1"><c r="A1" t="s"><v>0</v></c><c r="B1" t="s"><v>1</v></c>
+            <c r="C1" t="s"><v>2</v></c><c r="D1" t="inlineStr"><is><t>ok</t></is></c></row>
+        <row r="2"><c r="A2"><v>1</v></c><c r="B2" t="s"><v>3</v></c>
+            <c r="C2" s="1"><v>45292</v></c><c r="D2" t="b"><v>1</v></c></row>
+        <row r="3"><c r="A3" s="2"><v>2</v></c><c r="C3" s="1"><v>45292.5</v></c></row>
+        </sheetData></worksheet>"#;
+
+    fn workbook() -> Vec<u8> {
+        stored_archive(&[
+            ("xl/workbook.xml", WORKBOOK.as_bytes()),
+            ("xl/_rels/workbook.xml.rels", RELS.as_bytes()),
+            ("xl/sharedStrings.xml", SHARED.as_bytes()),
+            ("xl/styles.xml", STYLES.as_bytes()),
+            (
+                "xl/worksheets/sheet1.xml",
+                b"<worksheet><sheetData/></worksheet>",
+            ),
+            ("xl/worksheets/sheet2.xml", ORDERS.as_bytes()),
+        ])
+    }
+
+    #[test]
+    fn test_read_sheet_by_name_and_index() {
+        let bytes = workbook();
+        let data = read(&bytes, Some("Orders")).unwrap();
+        assert_eq!(data.headers.unwrap(), ["id", "name", "when", "ok"]);
+        assert_eq!(data.rows[0], ["1", "Widget & Co", "2024-01-01", "true"]);
+        assert_eq!(data.rows[1], ["2", "", "2024-01-01T12:00:00", ""]);
+
+        assert_eq!(read(&bytes, Some("2")).unwrap().rows.len(), 2);
+        assert!(read(&bytes, None).unwrap().rows.is_empty());
+        let err = read(&bytes, Some("Missing")).unwrap_err().to_string();
+        assert!(err.contains("Summary, Orders"));
+    }
+
+    #[test]
+    fn test_dates_and_references() {
+        assert!(is_date_format("yyyy-mm-dd"));
+        assert!(is_date_format("[h]:mm"));
+        assert!(!is_date_format("\"Day \"0"));
+        assert!(!is_date_format("[Red]0.00"));
+        assert_eq!(column_index("A1").unwrap(), Some(0));
+        assert_eq!(column_index("AB12").unwrap(), Some(27));
+        assert_eq!(column_index("XFD1048576").unwrap(), Some(16_383));
+        assert_eq!(column_index("12").unwrap(), None);
+        assert_eq!(serial_to_iso(1.0, false).unwrap(), "1900-01-01");
+        assert_eq!(serial_to_iso(61.0, false).unwrap(), "1900-03-01");
+        assert_eq!(serial_to_iso(0.25, false).unwrap(), "06:00:00");
+        assert_eq!(serial_to_iso(0.0, true).unwrap(), "1904-01-01");
+    }
+
+    #[test]
+    fn test_references_outside_the_sheet() {
+        for reference in [
+            "XFE1",
+            "A0",
+            "A1048577",
+            "A99999999999999999999",
+            &"Z".repeat(40),
+        ] {
+            assert!(column_index(reference).is_err(), "{}", reference);
+        }
+        let sheet = r#"<worksheet><sheetData>
+            <row r="2"><c r="ZZZZZZZ2"><v>1</v></c></row>
+            </sheetData></worksheet>"#;
+        let err = worksheet_rows(sheet, &[], &[], false).unwrap_err();
+        assert!(err.to_string().contains("ZZZZZZZ2"));
+    }
+    #[test]
+    fn test_write_round_trip() {
+        let value = serde_json::json!([
+            {"id": 1, "name": "Ann & <Bob>", "ok": true},
+            {"id": 2.5, "tags": ["a"], "big": 9007199254740993u64, "name": null}
+        ]);
+        let bytes = write(&value, Some("Users")).unwrap();
+        let data = read(&bytes, Some("Users")).unwrap();
+        assert_eq!(data.headers.unwrap(), ["id", "name", "ok", "tags", "big"]);
+        assert_eq!(data.rows[0], ["1", "Ann & <Bob>", "true", "", ""]);
+        assert_eq!(data.rows[1], ["2.5", "", "", "[\"a\"]", "9007199254740993"]);
+
+        let rows = read(
+            &write(&serde_json::json!([["a", 1], ["b"]]), None).unwrap(),
+            None,
+        );
+        assert_eq!(rows.unwrap().headers.unwrap(), ["a", "1"]);
+
+        assert_eq!(cell_reference(27, 3), "AB3");
+        assert_eq!(escape_xml("a\u{1}"), "a_x0001_");
+        assert!(write(&serde_json::json!(1), None).is_err());
+        assert!(write(&serde_json::json!([{}]), Some("a/b")).is_err());
+    }
+}
@@ -1,55 +1,212 @@
 //! XML format handling
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use quick_xml::events::{BytesDecl, BytesText, Event};
 use quick_xml::{Reader, Writer};
-use std::fs;
-use std::io::{self, Cursor, Read};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::io::Cursor;
 use std::path::Path;
+use std::sync::LazyLock;
 
-/// Read input from file or stdin
-pub fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
-        Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
+use crate::formats::indent::Indent;
+use crate::formats::OutputStyle;
+use crate::utils::diagnostics;
+use crate::utils::encoding::EncodingOptions;
+
+/// When text nodes are written as CDATA sections instead of entity-escaped
+#[derive(Debug, Clone, Default)]
+pub struct CdataOptions {
+    /// Wrap text containing markup characters (`<`, `>`, `&`)
+    pub markup: bool,
+    /// Slash-separated element paths whose text is always CDATA; a path matches
+    /// every element whose full path ends with it (`description`, `book/description`)
+    pub paths: Vec<String>,
+}
+
+impl CdataOptions {
+    /// Whether `text` of the element at `path` (e.g. `library/book/title`) becomes CDATA
+    pub fn applies(&self, path: &str, text: &str) -> bool {
+        (self.markup && text.contains(['<', '>', '&']))
+            || self.paths.iter().any(|p| path_matches(path, p))
+    }
+}
+
+/// Whether the element at `path` (e.g. `library/book/title`) is selected by the
+/// slash-separated `pattern`, which matches every path ending with it
+pub fn path_matches(path: &str, pattern: &str) -> bool {
+    let pattern = pattern.trim_matches('/');
+    path == pattern
+        || path
+            .strip_suffix(pattern)
+            .is_some_and(|rest| rest.ends_with('/'))
+}
+
+/// Wrap text in a CDATA section, splitting any `]]>` it contains
+pub fn cdata(text: &str) -> String {
+    format!("<![CDATA[{}]]>", text.replace("]]>", "]]]]><![CDATA[>"))
+}
+
+/// Entity references may nest at most this deep
+pub const MAX_ENTITY_DEPTH: usize = 16;
+
+/// Text produced by entity references per document, in bytes
+pub const MAX_ENTITY_EXPANSION: usize = 10 * 1024 * 1024;
+
+static ENTITY_DECL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"<!ENTITY\s+(%\s+)?([^\s%]+)\s+(?:"([^"]*)"|'([^']*)'|(SYSTEM|PUBLIC)\b)"#)
+        .expect("valid entity regex")
+});
+
+/// General entities declared in a DOCTYPE internal subset
+///
+/// Internal entities are expanded within [`MAX_ENTITY_DEPTH`] and
+/// [`MAX_ENTITY_EXPANSION`] (billion laughs); external entities are never
+/// loaded, so referencing one is an error (XXE).
+#[derive(Debug, Default)]
+pub struct Entities {
+    internal: HashMap<String, String>,
+    external: HashSet<String>,
+    expanded: usize,
+}
+
+impl Entities {
+    /// Collect the entity declarations of a DOCTYPE (the text after `<!DOCTYPE`)
+    pub fn from_doctype(doctype: &str) -> Self {
+        let mut entities = Self::default();
+        for caps in ENTITY_DECL.captures_iter(doctype) {
+            let name = caps[2].to_string();
+            // Parameter entities only apply within the DTD, which is not processed
+            if caps.get(1).is_some()
+                || entities.internal.contains_key(&name)
+                || entities.external.contains(&name)
+            {
+                continue;
+            }
+            match caps.get(3).or(caps.get(4)) {
+                Some(value) => {
+                    entities.internal.insert(name, value.as_str().to_string());
+                }
+                None => {
+                    entities.external.insert(name);
+                }
+            }
         }
-        None => {
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
+        entities
+    }
+
+    /// Resolve the character and entity references in escaped XML text
+    pub fn unescape(&mut self, raw: &str) -> Result<String> {
+        self.expand(raw, 0)
+    }
+
+    fn expand(&mut self, raw: &str, depth: usize) -> Result<String> {
+        let mut out = String::with_capacity(raw.len());
+        let mut rest = raw;
+        while let Some(start) = rest.find('&') {
+            out.push_str(&rest[..start]);
+            let end = rest[start..]
+                .find(';')
+                .with_context(|| format!("Unterminated entity reference in '{}'", raw))?;
+            let name = &rest[start + 1..start + end];
+            rest = &rest[start + end + 1..];
+
+            match name {
+                "lt" => out.push('<'),
+                "gt" => out.push('>'),
+                "amp" => out.push('&'),
+                "apos" => out.push('\''),
+                "quot" => out.push('"'),
+                _ if name.starts_with('#') => {
+                    let code = match name[1..].strip_prefix('x') {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                        None => name[1..].parse().ok(),
+                    };
+                    out.push(
+                        code.and_then(char::from_u32)
+                            .with_context(|| format!("Invalid character reference '&{};'", name))?,
+                    );
+                }
+                _ => {
+                    if self.external.contains(name) {
+                        bail!(
+                            "Entity '&{};' is external; external entities are never loaded",
+                            name
+                        );
+                    }
+                    let Some(value) = self.internal.get(name).cloned() else {
+                        bail!("Undeclared entity '&{};'", name);
+                    };
+                    if depth >= MAX_ENTITY_DEPTH {
+                        bail!(
+                            "Entity '&{};' nests deeper than {} levels",
+                            name,
+                            MAX_ENTITY_DEPTH
+                        );
+                    }
+                    let value = self.expand(&value, depth + 1)?;
+                    self.expanded += value.len();
+                    if self.expanded > MAX_ENTITY_EXPANSION {
+                        bail!(
+                            "Entity expansion exceeds {} bytes (possible billion laughs attack)",
+                            MAX_ENTITY_EXPANSION
+                        );
+                    }
+                    out.push_str(&value);
+                }
+            }
         }
+        out.push_str(rest);
+        Ok(out)
     }
 }
 
+/// Read input from file or stdin
+pub fn read_input(path: Option<&Path>, encoding: &EncodingOptions) -> Result<String> {
+    encoding.read_input(path)
+}
+
 /// Validate XML by parsing it
 pub fn validate(content: &str) -> Result<()> {
     let mut reader = Reader::from_str(content);
     reader.config_mut().trim_text(true);
+    let mut entities = Entities::default();
 
     loop {
         match reader.read_event() {
             Ok(Event::Eof) => break,
+            Ok(Event::DocType(e)) => {
+                entities = Entities::from_doctype(&String::from_utf8_lossy(&e))
+            }
+            Ok(Event::Text(e)) => {
+                entities.unescape(&String::from_utf8_lossy(&e))?;
+            }
             Ok(_) => continue,
             Err(e) => {
-                return Err(anyhow::anyhow!(
-                    "XML parse error at position {}: {}",
-                    reader.buffer_position(),
-                    e
-                ))
+                let position = reader.error_position() as usize;
+                return Err(diagnostics::located_at(
+                    e,
+                    content,
+                    position,
+                    "Failed to parse XML",
+                ));
             }
         }
     }
     Ok(())
 }
 
-/// Format XML with proper indentation
-pub fn to_pretty(content: &str) -> Result<String> {
+/// Format XML with the indentation of `style`, two spaces by default
+pub fn to_pretty(content: &str, style: &OutputStyle) -> Result<String> {
     let mut reader = Reader::from_str(content);
     reader.config_mut().trim_text(true);
 
-    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), b' ', 2);
+    let (indent_char, indent_size) = match style.indent.unwrap_or_default() {
+        Indent::Spaces(n) => (b' ', n),
+        Indent::Tab => (b'\t', 1),
+    };
+    let mut writer = Writer::new_with_indent(Cursor::new(Vec::new()), indent_char, indent_size);
+    let mut entities = Entities::default();
 
     loop {
         match reader.read_event() {
@@ -69,11 +226,13 @@ pub fn to_pretty(content: &str) -> Result<String> {
                     .context("Failed to write XML empty element")?;
             }
             Ok(Event::Text(e)) => {
-                let text = e.unescape().context("Failed to unescape XML text")?;
-                let trimmed = text.trim();
+                // Keep entity references as written; the DOCTYPE declaring them is kept too
+                let raw = std::str::from_utf8(&e).context("Invalid UTF-8 in XML text")?;
+                entities.unescape(raw)?;
+                let trimmed = raw.trim();
                 if !trimmed.is_empty() {
                     writer
-                        .write_event(Event::Text(BytesText::new(trimmed)))
+                        .write_event(Event::Text(BytesText::from_escaped(trimmed)))
                         .context("Failed to write XML text")?;
                 }
             }
@@ -119,17 +278,20 @@ pub fn to_pretty(content: &str) -> Result<String> {
                     .context("Failed to write XML processing instruction")?;
             }
             Ok(Event::DocType(e)) => {
+                entities = Entities::from_doctype(&String::from_utf8_lossy(&e));
                 writer
                     .write_event(Event::DocType(e))
                     .context("Failed to write XML doctype")?;
             }
             Ok(Event::Eof) => break,
             Err(e) => {
-                return Err(anyhow::anyhow!(
-                    "XML parse error at position {}: {}",
-                    reader.buffer_position(),
-                    e
-                ))
+                let position = reader.error_position() as usize;
+                return Err(diagnostics::located_at(
+                    e,
+                    content,
+                    position,
+                    "Failed to parse XML",
+                ));
             }
         }
     }
@@ -144,6 +306,7 @@ pub fn to_compact(content: &str) -> Result<String> {
     reader.config_mut().trim_text(true);
 
     let mut writer = Writer::new(Cursor::new(Vec::new()));
+    let mut entities = Entities::default();
 
     loop {
         match reader.read_event() {
@@ -163,11 +326,13 @@ pub fn to_compact(content: &str) -> Result<String> {
                     .context("Failed to write XML element")?;
             }
             Ok(Event::Text(e)) => {
-                let text = e.unescape().context("Failed to unescape XML text")?;
-                let trimmed = text.trim();
+                // Keep entity references as written; the DOCTYPE declaring them is kept too
+                let raw = std::str::from_utf8(&e).context("Invalid UTF-8 in XML text")?;
+                entities.unescape(raw)?;
+                let trimmed = raw.trim();
                 if !trimmed.is_empty() {
                     writer
-                        .write_event(Event::Text(BytesText::new(trimmed)))
+                        .write_event(Event::Text(BytesText::from_escaped(trimmed)))
                         .context("Failed to write XML text")?;
                 }
             }
@@ -176,6 +341,12 @@ pub fn to_compact(content: &str) -> Result<String> {
                     .write_event(Event::Decl(e))
                     .context("Failed to write XML declaration")?;
             }
+            Ok(Event::DocType(e)) => {
+                entities = Entities::from_doctype(&String::from_utf8_lossy(&e));
+                writer
+                    .write_event(Event::DocType(e))
+                    .context("Failed to write XML doctype")?;
+            }
             Ok(Event::Eof) => break,
             Ok(event) => {
                 writer
@@ -183,11 +354,13 @@ pub fn to_compact(content: &str) -> Result<String> {
                     .context("Failed to write XML event")?;
             }
             Err(e) => {
-                return Err(anyhow::anyhow!(
-                    "XML parse error at position {}: {}",
-                    reader.buffer_position(),
-                    e
-                ))
+                let position = reader.error_position() as usize;
+                return Err(diagnostics::located_at(
+                    e,
+                    content,
+                    position,
+                    "Failed to parse XML",
+                ));
             }
         }
     }
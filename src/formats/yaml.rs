@@ -1,33 +1,277 @@
 //! YAML format handling
 
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use serde_yaml::Value;
-use std::fs;
-use std::io::{self, Read};
 use std::path::Path;
 
+use crate::formats::float::FloatFormat;
+use crate::formats::OutputStyle;
+use crate::utils::diagnostics;
+use crate::utils::encoding::EncodingOptions;
+
+/// Values accepted by `--yaml-version`
+pub const YAML_VERSIONS: [&str; 2] = ["1.1", "1.2"];
+
+/// YAML revision whose rules decide the type of plain scalars
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum YamlVersion {
+    /// `yes`/`no`/`on`/`off` are booleans and `1:30` is a base-60 number
+    V1_1,
+    /// Only `true`/`false` are booleans; `yes`, `on` and `1:30` stay strings
+    #[default]
+    V1_2,
+}
+
+impl YamlVersion {
+    /// Parse a `--yaml-version` value
+    pub fn from_name(name: &str) -> Result<Self> {
+        Ok(match name {
+            "1.1" => YamlVersion::V1_1,
+            "1.2" => YamlVersion::V1_2,
+            _ => bail!(
+                "Unknown YAML version: {}. Use: {}",
+                name,
+                YAML_VERSIONS.join(", ")
+            ),
+        })
+    }
+}
+
 /// Read input from file or stdin
-pub fn read_input(path: Option<&Path>) -> Result<String> {
-    match path {
-        Some(p) => {
-            fs::read_to_string(p).with_context(|| format!("Failed to read file: {}", p.display()))
+pub fn read_input(path: Option<&Path>, encoding: &EncodingOptions) -> Result<String> {
+    encoding.read_input(path)
+}
+
+/// Parse YAML string into Value
+///
+/// Under YAML 1.1, plain block scalars that 1.1 types differently are resolved
+/// first; quoted scalars, keys and flow collections are left as they are.
+pub fn parse(content: &str, version: YamlVersion) -> Result<Value> {
+    match version {
+        YamlVersion::V1_2 => serde_yaml::from_str(content),
+        YamlVersion::V1_1 => serde_yaml::from_str(&resolve_1_1(content)),
+    }
+    .map_err(|e| diagnostics::located(e, content, "Failed to parse YAML"))
+}
+
+/// Convert Value to pretty-printed YAML string
+///
+/// Floats are rounded to the precision of `style`, if one is set.
+pub fn to_pretty(value: &Value, style: &OutputStyle) -> Result<String> {
+    match style.float {
+        Some(format) => serde_yaml::to_string(&round_floats(value.clone(), &format)),
+        None => serde_yaml::to_string(value),
+    }
+    .context("Failed to serialize YAML")
+}
+
+fn round_floats(value: Value, format: &FloatFormat) -> Value {
+    match value {
+        Value::Number(n) if n.is_f64() => Value::from(format.round(n.as_f64().unwrap_or_default())),
+        Value::Sequence(items) => Value::Sequence(
+            items
+                .into_iter()
+                .map(|item| round_floats(item, format))
+                .collect(),
+        ),
+        Value::Mapping(map) => Value::Mapping(
+            map.into_iter()
+                .map(|(key, item)| (key, round_floats(item, format)))
+                .collect(),
+        ),
+        Value::Tagged(mut tagged) => {
+            tagged.value = round_floats(tagged.value, format);
+            Value::Tagged(tagged)
         }
+        other => other,
+    }
+}
+
+/// Byte offset of the `:` that separates a mapping key from its value, if any
+pub fn find_mapping_colon(line: &str) -> Option<usize> {
+    let mut in_quotes = false;
+    let mut quote_char = ' ';
+
+    for (i, ch) in line.char_indices() {
+        match ch {
+            '"' | '\'' if !in_quotes => {
+                in_quotes = true;
+                quote_char = ch;
+            }
+            c if in_quotes && c == quote_char => {
+                in_quotes = false;
+            }
+            ':' if !in_quotes => {
+                // Check if it's followed by space, newline, or end of string
+                let next_char = line[i + 1..].chars().next();
+                if next_char.is_none() || next_char == Some(' ') || next_char == Some('\n') {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Canonical YAML 1.2 text of a plain scalar that YAML 1.1 reads as a boolean
+/// or base-60 number, e.g. `yes` → `true` and `1:30` → `90`
+pub fn resolve_scalar_1_1(scalar: &str) -> Option<String> {
+    match scalar {
+        "y" | "Y" | "yes" | "Yes" | "YES" | "on" | "On" | "ON" => Some("true".to_string()),
+        "n" | "N" | "no" | "No" | "NO" | "off" | "Off" | "OFF" => Some("false".to_string()),
+        _ => sexagesimal(scalar),
+    }
+}
+
+/// Value of a base-60 number such as `190:20:30` or `-1:30.5`
+fn sexagesimal(scalar: &str) -> Option<String> {
+    let (negative, digits) = match scalar.as_bytes().first()? {
+        b'-' => (true, &scalar[1..]),
+        b'+' => (false, &scalar[1..]),
+        _ => (false, scalar),
+    };
+    let mut parts = digits.split(':');
+    let first = parts.next()?.replace('_', "");
+    if first.is_empty() || !first.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let rest: Vec<&str> = parts.collect();
+    let (last, middle) = rest.split_last()?;
+    let (last, fraction) = match last.split_once('.') {
+        Some((last, fraction)) => (last, Some(fraction.replace('_', ""))),
+        None => (*last, None),
+    };
+    let is_base_60 = |part: &str| {
+        (1..=2).contains(&part.len())
+            && part.bytes().all(|b| b.is_ascii_digit())
+            && part.parse::<u8>().is_ok_and(|n| n < 60)
+    };
+    if !middle.iter().all(|part| is_base_60(part)) || !is_base_60(last) {
+        return None;
+    }
+    let sign = if negative { "-" } else { "" };
+    match fraction {
+        // 1.1 integers may not start with 0 (that would be octal); floats may
+        None if first.starts_with('0') => None,
         None => {
-            let mut buffer = String::new();
-            io::stdin()
-                .read_to_string(&mut buffer)
-                .context("Failed to read from stdin")?;
-            Ok(buffer)
+            let value = middle
+                .iter()
+                .chain(std::iter::once(&last))
+                .try_fold(first.parse::<i64>().ok()?, |acc, part| {
+                    acc.checked_mul(60)?.checked_add(part.parse::<i64>().ok()?)
+                })?;
+            Some(format!("{}{}", sign, value))
+        }
+        Some(fraction) if fraction.bytes().all(|b| b.is_ascii_digit()) => {
+            let whole = middle
+                .iter()
+                .chain(std::iter::once(&last))
+                .fold(first.parse::<f64>().ok()?, |acc, part| {
+                    acc * 60.0 + part.parse::<f64>().unwrap_or_default()
+                });
+            let value = whole + format!("0.{}", fraction).parse::<f64>().ok()?;
+            Some(format!("{}{:?}", sign, value))
         }
+        Some(_) => None,
     }
 }
 
-/// Parse YAML string into Value
-pub fn parse(content: &str) -> Result<Value> {
-    serde_yaml::from_str(content).context("Failed to parse YAML")
+/// Rewrite plain block scalars that YAML 1.1 types differently into their 1.2 form
+fn resolve_1_1(content: &str) -> String {
+    let mut output = String::with_capacity(content.len());
+    // Indentation of the line that opened a `|` or `>` block scalar
+    let mut block_scalar: Option<usize> = None;
+
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let indent = text.len() - text.trim_start_matches(' ').len();
+
+        if let Some(parent) = block_scalar {
+            if text.trim().is_empty() || indent > parent {
+                output.push_str(line);
+                continue;
+            }
+            block_scalar = None;
+        }
+
+        let Some(start) = value_start(text) else {
+            output.push_str(line);
+            continue;
+        };
+        let value = &text[start..];
+        let value = match value.find(" #") {
+            Some(comment) => &value[..comment],
+            None => value,
+        };
+        let trimmed = value.trim();
+        if trimmed.starts_with(['|', '>']) {
+            block_scalar = Some(indent);
+        }
+
+        match resolve_scalar_1_1(trimmed) {
+            Some(resolved) => {
+                let offset = start + (value.len() - value.trim_start().len());
+                output.push_str(&line[..offset]);
+                output.push_str(&resolved);
+                output.push_str(&line[offset + trimmed.len()..]);
+            }
+            None => output.push_str(line),
+        }
+    }
+    output
 }
 
-/// Convert Value to pretty-printed YAML string
-pub fn to_pretty(value: &Value) -> Result<String> {
-    serde_yaml::to_string(value).context("Failed to serialize YAML")
+/// Byte offset where the scalar of a `key: value` or `- value` line begins
+fn value_start(line: &str) -> Option<usize> {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with(['#', '%']) || trimmed.starts_with("---") || trimmed.starts_with("...") {
+        return None;
+    }
+
+    // Skip sequence markers, including nested ones such as `- - value`
+    let mut start = line.len() - trimmed.len();
+    let mut is_item = false;
+    while let Some(rest) = line[start..].strip_prefix('-') {
+        if !(rest.is_empty() || rest.starts_with(' ')) {
+            break;
+        }
+        is_item = true;
+        start = line.len() - rest.trim_start().len();
+    }
+
+    match find_mapping_colon(&line[start..]) {
+        Some(colon) => Some(start + colon + 1),
+        None if is_item => Some(start),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_scalar_1_1() {
+        assert_eq!(resolve_scalar_1_1("yes").as_deref(), Some("true"));
+        assert_eq!(resolve_scalar_1_1("OFF").as_deref(), Some("false"));
+        assert_eq!(resolve_scalar_1_1("190:20:30").as_deref(), Some("685230"));
+        assert_eq!(resolve_scalar_1_1("-1:30").as_deref(), Some("-90"));
+        assert_eq!(resolve_scalar_1_1("1:30.5").as_deref(), Some("90.5"));
+        assert_eq!(resolve_scalar_1_1("12:60"), None);
+        assert_eq!(resolve_scalar_1_1("01:30"), None);
+        assert_eq!(resolve_scalar_1_1("true"), None);
+        assert_eq!(resolve_scalar_1_1("maybe"), None);
+    }
+
+    #[test]
+    fn test_resolve_1_1() {
+        let yaml = "on: yes\nquoted: \"no\"\ntime: 1:30 # comment\nitems:\n  - off\n  - name: n\n\
+                    text: |\n  enabled: yes\nlast: Off\n";
+        assert_eq!(
+            resolve_1_1(yaml),
+            "on: true\nquoted: \"no\"\ntime: 90 # comment\nitems:\n  - false\n  - name: false\n\
+             text: |\n  enabled: yes\nlast: false\n"
+        );
+    }
 }
@@ -2,37 +2,91 @@
 
 use anyhow::Result;
 use clap::Parser;
+use std::ffi::OsString;
 
-use dtx::cli::args::{Cli, Commands};
+use dtx::cli::args::{self, Cli, Commands, ErrorFormat};
 use dtx::cli::commands::{
-    auto, batch, completions, convert, csv, diff, json, merge, patch, query, schema, template,
-    toml, validate, xml, yaml,
+    auto, batch, bench, completions, convert, csv, diff, frontmatter, json, merge, patch, query,
+    schema, serve, table, template, toml, validate, xml, yaml,
 };
+use dtx::cli::config::Config;
+use dtx::cli::globals::GlobalOptions;
+use dtx::cli::logging;
+use dtx::cli::output::{self, write_error};
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
+fn main() {
+    let args = match expand_aliases(std::env::args_os().collect()) {
+        Ok(args) => args,
+        Err(err) => {
+            write_error(&err, ErrorFormat::Text, None);
+            std::process::exit(1);
+        }
+    };
+    let cli = Cli::parse_from(args::expand_template_vars(args));
+
+    let globals = match GlobalOptions::from_cli(&cli) {
+        Ok(globals) => globals,
+        Err(err) => {
+            write_error(&err, cli.error_format, None);
+            std::process::exit(1);
+        }
+    };
 
     // Handle global --no-color flag
-    if cli.no_color {
+    if cli.no_color || cli.clipboard || cli.porcelain.is_some() {
         colored::control::set_override(false);
     }
+    if cli.clipboard {
+        output::capture_for_clipboard();
+    }
+
+    logging::init(cli.verbose, cli.log_format);
+
+    let error_format = cli.error_format;
+    let input = cli.command.input_path().map(|p| p.to_path_buf());
+
+    let result = run(cli.command, &globals).and_then(|()| output::flush_clipboard());
+    if let Some(ref timings) = globals.timings {
+        eprintln!("{}", timings.report());
+    }
+    if let Err(err) = result {
+        write_error(&err, error_format, input.as_deref());
+        std::process::exit(1);
+    }
+}
+
+/// Expand `@alias` query arguments, reading config files only when one is used
+fn expand_aliases(args: Vec<OsString>) -> Result<Vec<OsString>> {
+    if !args
+        .iter()
+        .any(|arg| arg.to_str().is_some_and(|s| s.starts_with('@')))
+    {
+        return Ok(args);
+    }
+    Config::load()?.expand_aliases(args)
+}
 
-    match cli.command {
-        Commands::Json(args) => json::execute(args)?,
-        Commands::Yaml(args) => yaml::execute(args)?,
-        Commands::Toml(args) => toml::execute(args)?,
-        Commands::Csv(args) => csv::execute(args)?,
-        Commands::Xml(args) => xml::execute(args)?,
-        Commands::Auto(args) => auto::execute(args)?,
-        Commands::Convert(args) => convert::execute(args)?,
-        Commands::Query(args) => query::execute(args)?,
-        Commands::Validate(args) => validate::execute(args)?,
-        Commands::Diff(args) => diff::execute(args)?,
-        Commands::Schema(args) => schema::execute(args)?,
-        Commands::Merge(args) => merge::execute(args)?,
-        Commands::Patch(args) => patch::execute(args)?,
-        Commands::Template(args) => template::execute(args)?,
-        Commands::Batch(args) => batch::execute(args)?,
+fn run(command: Commands, globals: &GlobalOptions) -> Result<()> {
+    match command {
+        Commands::Json(args) => json::execute(args, globals)?,
+        Commands::Yaml(args) => yaml::execute(args, globals)?,
+        Commands::Toml(args) => toml::execute(args, globals)?,
+        Commands::Csv(args) => csv::execute(args, globals)?,
+        Commands::Xml(args) => xml::execute(args, globals)?,
+        Commands::Auto(args) => auto::execute(args, globals)?,
+        Commands::Convert(args) => convert::execute(args, globals)?,
+        Commands::Table(args) => table::execute(args, globals)?,
+        Commands::Query(args) => query::execute(args, globals)?,
+        Commands::Validate(args) => validate::execute(args, globals)?,
+        Commands::Diff(args) => diff::execute(args, globals)?,
+        Commands::Schema(args) => schema::execute(args, globals)?,
+        Commands::Merge(args) => merge::execute(args, globals)?,
+        Commands::Patch(args) => patch::execute(args, globals)?,
+        Commands::Frontmatter(args) => frontmatter::execute(args, globals)?,
+        Commands::Template(args) => template::execute(args, globals)?,
+        Commands::Batch(args) => batch::execute(args, globals)?,
+        Commands::Serve(args) => serve::execute(args, globals)?,
+        Commands::Bench(args) => bench::execute(args, globals)?,
         Commands::Completions(args) => completions::execute(args)?,
     }
 
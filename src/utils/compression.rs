@@ -0,0 +1,232 @@
+//! Transparent gzip, zstd and bzip2 compression of input and output files
+//!
+//! Input is decompressed when its extension (`.gz`, `.zst`, `.bz2`) or its
+//! leading magic bytes say it is compressed, so stdin works too. Output is
+//! compressed when the target path has a compression extension. Formats are
+//! detected from the extension underneath: `logs.ndjson.gz` is NDJSON.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// Supported compression formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b, 0x08];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+/// Magic of the first bzip2 block, or of the end of an empty stream
+const BZIP2_BLOCK_MAGIC: [&[u8]; 2] = [
+    &[0x31, 0x41, 0x59, 0x26, 0x53, 0x59],
+    &[0x17, 0x72, 0x45, 0x38, 0x50, 0x90],
+];
+
+impl Compression {
+    /// Compression named by a path's extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+
+    /// Compression of data starting with `head`, from its magic bytes
+    pub fn detect(head: &[u8]) -> Option<Self> {
+        if head.starts_with(GZIP_MAGIC) {
+            Some(Compression::Gzip)
+        } else if head.starts_with(ZSTD_MAGIC) {
+            Some(Compression::Zstd)
+        } else if head.len() >= 10
+            && head.starts_with(b"BZh")
+            && (b'1'..=b'9').contains(&head[3])
+            && BZIP2_BLOCK_MAGIC.contains(&&head[4..10])
+        {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Bzip2 => "bzip2",
+        }
+    }
+
+    /// Decompress a whole stream
+    pub fn decompress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let mut out = Vec::new();
+        self.reader(bytes)?
+            .read_to_end(&mut out)
+            .with_context(|| format!("Failed to decompress {} input", self.as_str()))?;
+        Ok(out)
+    }
+
+    /// Compress a whole stream
+    pub fn compress(self, bytes: &[u8]) -> Result<Vec<u8>> {
+        let mut encoder = self.encoder(Vec::new())?;
+        encoder.write_all(bytes)?;
+        encoder
+            .finish()
+            .with_context(|| format!("Failed to compress {} output", self.as_str()))
+    }
+
+    fn reader<'a, R: BufRead + 'a>(self, reader: R) -> Result<Box<dyn Read + 'a>> {
+        Ok(match self {
+            // Multi-member decoders read concatenated streams, as `zcat` does
+            Compression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+            Compression::Zstd => Box::new(
+                zstd::stream::read::Decoder::with_buffer(reader)
+                    .context("Failed to start zstd decompression")?,
+            ),
+            Compression::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(reader)),
+        })
+    }
+
+    /// Start compressing into `writer`
+    pub fn encoder<W: Write>(self, writer: W) -> Result<Encoder<W>> {
+        Ok(match self {
+            Compression::Gzip => Encoder::Gzip(flate2::write::GzEncoder::new(
+                writer,
+                flate2::Compression::default(),
+            )),
+            Compression::Zstd => Encoder::Zstd(
+                zstd::stream::write::Encoder::new(writer, 0)
+                    .context("Failed to start zstd compression")?,
+            ),
+            Compression::Bzip2 => Encoder::Bzip2(bzip2::write::BzEncoder::new(
+                writer,
+                bzip2::Compression::default(),
+            )),
+        })
+    }
+}
+
+/// Streaming compressor; [`Encoder::finish`] must be called to end the stream
+pub enum Encoder<W: Write> {
+    Gzip(flate2::write::GzEncoder<W>),
+    Zstd(zstd::stream::write::Encoder<'static, W>),
+    Bzip2(bzip2::write::BzEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Write the end of the compressed stream and return the inner writer
+    pub fn finish(self) -> io::Result<W> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.finish(),
+            Encoder::Zstd(encoder) => encoder.finish(),
+            Encoder::Bzip2(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+            Encoder::Bzip2(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+            Encoder::Bzip2(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// The file name without its compression extension, for format detection:
+/// `logs.ndjson.gz` gives `logs.ndjson`
+pub fn uncompressed_name(path: &Path) -> &Path {
+    match Compression::from_path(path) {
+        Some(_) => path.file_stem().map_or(path, Path::new),
+        None => path,
+    }
+}
+
+/// Decompress input if its path's extension or its magic bytes say it is
+/// compressed; other input is returned unchanged
+pub fn decompress(bytes: Vec<u8>, path: Option<&Path>) -> Result<Vec<u8>> {
+    match path
+        .and_then(Compression::from_path)
+        .or_else(|| Compression::detect(&bytes))
+    {
+        Some(compression) => compression.decompress(&bytes),
+        None => Ok(bytes),
+    }
+}
+
+/// Wrap a streaming reader so compressed input is decompressed on the fly
+pub fn reader<'a>(mut reader: Box<dyn BufRead + 'a>) -> Result<Box<dyn BufRead + 'a>> {
+    let head = reader.fill_buf().context("Failed to read input")?;
+    match Compression::detect(head) {
+        Some(compression) => Ok(Box::new(BufReader::new(compression.reader(reader)?))),
+        None => Ok(reader),
+    }
+}
+
+/// Read a file, decompressing it if its name or content says it is compressed
+pub fn read_file(path: &Path) -> Result<Vec<u8>> {
+    let bytes =
+        fs::read(path).with_context(|| format!("Failed to read file: {}", path.display()))?;
+    decompress(bytes, Some(path)).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+/// Write a file, compressing it if its extension names a compression format
+pub fn write_file(path: &Path, contents: &[u8]) -> Result<()> {
+    let compressed;
+    let contents = match Compression::from_path(path) {
+        Some(compression) => {
+            compressed = compression.compress(contents)?;
+            &compressed
+        }
+        None => contents,
+    };
+    fs::write(path, contents).with_context(|| format!("Failed to write to {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = b"{\"name\": \"dtx\"}\n".repeat(100);
+        for compression in [Compression::Gzip, Compression::Zstd, Compression::Bzip2] {
+            let compressed = compression.compress(&text).unwrap();
+            assert_eq!(Compression::detect(&compressed), Some(compression));
+            assert_eq!(decompress(compressed.clone(), None).unwrap(), text);
+
+            let mut streamed = Vec::new();
+            reader(Box::new(compressed.as_slice()))
+                .unwrap()
+                .read_to_end(&mut streamed)
+                .unwrap();
+            assert_eq!(streamed, text);
+        }
+        assert_eq!(decompress(text.clone(), None).unwrap(), text);
+        assert!(decompress(text, Some(Path::new("data.json.gz"))).is_err());
+    }
+
+    #[test]
+    fn test_names() {
+        let path = Path::new("dir/logs.ndjson.GZ");
+        assert_eq!(Compression::from_path(path), Some(Compression::Gzip));
+        assert_eq!(uncompressed_name(path), Path::new("logs.ndjson"));
+        assert_eq!(uncompressed_name(Path::new("a.json")), Path::new("a.json"));
+        assert_eq!(Compression::detect(b"BZh9 is text"), None);
+    }
+}
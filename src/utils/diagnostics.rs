@@ -0,0 +1,244 @@
+//! Source snippets for parse errors
+//!
+//! Parsers wrap their errors in a [`ParseError`] holding the position and the
+//! offending line of the text they were given, so the error can be shown
+//! against its input without reading the input again. Commands reading several
+//! inputs name the one that failed with [`SourceContext::in_source`].
+
+use std::fmt;
+use std::path::Path;
+
+/// Position of a parse error within an input
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    /// 1-based line
+    pub line: usize,
+    /// 1-based column
+    pub column: usize,
+    /// Byte offset from the start of the input
+    pub offset: usize,
+    /// Text of the offending line
+    pub line_text: String,
+}
+
+impl Span {
+    /// Span of the 1-based `line` and `column` in `text`; the line after the
+    /// last is empty, for errors at the end of the input
+    pub fn at_line(text: &str, line: usize, column: usize) -> Option<Self> {
+        let line_text = match text.lines().nth(line.checked_sub(1)?) {
+            Some(line_text) => line_text.to_string(),
+            None if line == text.lines().count() + 1 => String::new(),
+            None => return None,
+        };
+        let line_start: usize = text
+            .split_inclusive('\n')
+            .take(line - 1)
+            .map(str::len)
+            .sum();
+        Some(Span {
+            line,
+            column,
+            offset: line_start + column.saturating_sub(1),
+            line_text,
+        })
+    }
+
+    /// Span of the byte `offset` in `text`
+    pub fn at_offset(text: &str, offset: usize) -> Self {
+        let bytes = text.as_bytes();
+        let offset = offset.min(bytes.len());
+        let line_start = bytes[..offset]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        let line_end = bytes[offset..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |i| offset + i);
+        let line_text = String::from_utf8_lossy(&bytes[line_start..line_end]);
+        Span {
+            line: bytes[..line_start].iter().filter(|&&b| b == b'\n').count() + 1,
+            column: String::from_utf8_lossy(&bytes[line_start..offset])
+                .chars()
+                .count()
+                + 1,
+            offset,
+            line_text: line_text.trim_end_matches('\r').to_string(),
+        }
+    }
+}
+
+/// A parse error with its location, attached as context where text is parsed
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    /// What failed, e.g. "Failed to parse JSON"
+    pub message: String,
+    /// File path or `<stdin>`, once the input is known
+    pub source: Option<String>,
+    pub span: Span,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Wrap an error from parsing `text` with `message` and, when the parser
+/// reports one, the position of the error
+pub fn located(err: impl Into<anyhow::Error>, text: &str, message: &str) -> anyhow::Error {
+    let err = err.into();
+    match error_span(&err, text) {
+        Some(span) => err.context(ParseError {
+            message: message.to_string(),
+            source: None,
+            span,
+        }),
+        None => err.context(message.to_string()),
+    }
+}
+
+/// Wrap an error from parsing `text` at byte `offset` with `message`
+pub fn located_at(
+    err: impl Into<anyhow::Error>,
+    text: &str,
+    offset: usize,
+    message: &str,
+) -> anyhow::Error {
+    err.into().context(ParseError {
+        message: message.to_string(),
+        source: None,
+        span: Span::at_offset(text, offset),
+    })
+}
+
+/// Position reported by a JSON, YAML or TOML parser
+fn error_span(err: &anyhow::Error, text: &str) -> Option<Span> {
+    err.chain().find_map(|cause| {
+        if let Some(e) = cause.downcast_ref::<serde_json::Error>() {
+            Span::at_line(text, e.line(), e.column())
+        } else if let Some(e) = cause.downcast_ref::<serde_yaml::Error>() {
+            e.location()
+                .and_then(|l| Span::at_line(text, l.line(), l.column()))
+        } else if let Some(e) = cause.downcast_ref::<toml::de::Error>() {
+            e.span().map(|span| Span::at_offset(text, span.start))
+        } else {
+            None
+        }
+    })
+}
+
+/// Name the input a parse error came from
+pub trait SourceContext<T> {
+    /// Record `path`, or stdin when `None`, as the input of a [`ParseError`]
+    fn in_source(self, path: Option<&Path>) -> anyhow::Result<T>;
+}
+
+impl<T> SourceContext<T> for anyhow::Result<T> {
+    fn in_source(self, path: Option<&Path>) -> anyhow::Result<T> {
+        self.map_err(|mut err| {
+            if let Some(e) = err.downcast_mut::<ParseError>() {
+                e.source.get_or_insert_with(|| source_label(path));
+            }
+            err
+        })
+    }
+}
+
+fn source_label(path: Option<&Path>) -> String {
+    path.map_or_else(|| "<stdin>".to_string(), |p| p.display().to_string())
+}
+
+/// Location of a parse error, ready to show
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// File path, or `<stdin>`
+    pub label: String,
+    pub span: Span,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = &self.span;
+        writeln!(
+            f,
+            "  --> {}:{}:{} (byte {})",
+            self.label, span.line, span.column, span.offset
+        )?;
+        write!(f, "{}", snippet(&span.line_text, span.line, span.column, 1))
+    }
+}
+
+/// Source line with a gutter and a caret of `width` under `column`
+pub fn snippet(line_text: &str, line: usize, column: usize, width: usize) -> String {
+    let gutter = " ".repeat(line.to_string().len());
+    format!(
+        "{} |\n{} | {}\n{} | {}{}",
+        gutter,
+        line,
+        line_text,
+        gutter,
+        " ".repeat(column.saturating_sub(1)),
+        "^".repeat(width.max(1))
+    )
+}
+
+/// Locate a parse error; `input` names the input when the error does not
+pub fn diagnose(err: &anyhow::Error, input: Option<&Path>) -> Option<Diagnostic> {
+    let e = err.downcast_ref::<ParseError>()?;
+    Some(Diagnostic {
+        label: e.source.clone().unwrap_or_else(|| source_label(input)),
+        span: e.span.clone(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_diagnose_json() {
+        let text = "{\n  \"a\": 1,\n  \"b\": ]\n}\n";
+        let err = serde_json::from_str::<serde_json::Value>(text)
+            .map_err(|e| located(e, text, "Failed to parse JSON"))
+            .context("Failed to read config")
+            .unwrap_err();
+
+        let diagnostic = diagnose(&err, None).unwrap();
+        assert_eq!(diagnostic.label, "<stdin>");
+        assert_eq!((diagnostic.span.line, diagnostic.span.column), (3, 8));
+        assert_eq!(
+            &text[diagnostic.span.offset..diagnostic.span.offset + 1],
+            "]"
+        );
+        assert_eq!(
+            diagnostic.to_string(),
+            "  --> <stdin>:3:8 (byte 19)\n  |\n3 |   \"b\": ]\n  |        ^"
+        );
+    }
+
+    #[test]
+    fn test_diagnose_named_source() {
+        let text = "a = 1\nb = = 2\n";
+        let err: anyhow::Result<()> = Err(located(
+            text.parse::<toml::Value>().unwrap_err(),
+            text,
+            "Failed to parse TOML",
+        ));
+        let err = err.in_source(Some(Path::new("b.toml"))).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to parse TOML");
+
+        let diagnostic = diagnose(&err, Some(Path::new("a.toml"))).unwrap();
+        assert_eq!(diagnostic.label, "b.toml");
+        assert_eq!(diagnostic.span.line, 2);
+        assert_eq!(diagnostic.span.line_text, "b = = 2");
+    }
+
+    #[test]
+    fn test_span_at_offset() {
+        let span = Span::at_offset("<a>\r\n  <b></c>\r\n</a>", 11);
+        assert_eq!((span.line, span.column), (2, 7));
+        assert_eq!(span.line_text, "  <b></c>");
+    }
+}
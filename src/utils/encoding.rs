@@ -0,0 +1,491 @@
+//! Character encodings and line endings for input and output
+//!
+//! Input is decoded to UTF-8 before parsing: a byte order mark selects UTF-8 or
+//! UTF-16, otherwise the `--encoding` flag (or UTF-8) is used. Output written
+//! through [`EncodingOptions::write_file`] and `write_output` is re-encoded with
+//! `--output-encoding` and uses the line endings chosen by `--eol`, or those of
+//! the first input read.
+//! Binary property lists are the one binary input decoded to text (as XML).
+//! Compressed input and output go through [`compression`].
+
+use anyhow::{bail, Context, Result};
+use std::borrow::Cow;
+use std::io::{self, BufRead, Cursor, Read, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::formats::plist;
+use crate::utils::compression;
+
+/// Encoding names accepted by `--encoding` and `--output-encoding`
+pub const ENCODING_NAMES: [&str; 7] = [
+    "utf-8",
+    "utf-8-bom",
+    "utf-16",
+    "utf-16le",
+    "utf-16be",
+    "latin1",
+    "windows-1252",
+];
+
+/// Supported character encodings
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    /// UTF-8 written with a byte order mark
+    Utf8Bom,
+    /// UTF-16 with a byte order mark (little-endian when writing)
+    Utf16,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1
+    Latin1,
+    Windows1252,
+}
+
+impl Encoding {
+    /// Parse an encoding name (case-insensitive, common aliases accepted)
+    pub fn from_name(name: &str) -> Result<Self> {
+        let normalized = name.to_lowercase().replace('_', "-");
+        Ok(match normalized.as_str() {
+            "utf-8" | "utf8" => Encoding::Utf8,
+            "utf-8-bom" | "utf8-bom" | "utf-8-sig" => Encoding::Utf8Bom,
+            "utf-16" | "utf16" => Encoding::Utf16,
+            "utf-16le" | "utf16le" => Encoding::Utf16Le,
+            "utf-16be" | "utf16be" => Encoding::Utf16Be,
+            "latin1" | "latin-1" | "iso-8859-1" => Encoding::Latin1,
+            "windows-1252" | "cp1252" => Encoding::Windows1252,
+            _ => bail!(
+                "Unknown encoding: {}. Use: {}",
+                name,
+                ENCODING_NAMES.join(", ")
+            ),
+        })
+    }
+
+    /// Whether text in this encoding is plain UTF-8 without a byte order mark
+    fn is_utf8(self) -> bool {
+        self == Encoding::Utf8
+    }
+}
+
+/// Line ending names accepted by `--eol`
+pub const EOL_NAMES: [&str; 3] = ["lf", "crlf", "native"];
+
+/// Line ending style
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    /// Parse a `--eol` value; `native` is CRLF on Windows and LF elsewhere
+    pub fn from_name(name: &str) -> Result<Self> {
+        Ok(match name.to_lowercase().as_str() {
+            "lf" => LineEnding::Lf,
+            "crlf" => LineEnding::Crlf,
+            "native" if cfg!(windows) => LineEnding::Crlf,
+            "native" => LineEnding::Lf,
+            _ => bail!(
+                "Unknown line ending: {}. Use: {}",
+                name,
+                EOL_NAMES.join(", ")
+            ),
+        })
+    }
+
+    /// Line ending of the first line in `text`, if it has one
+    pub fn detect(text: &[u8]) -> Option<Self> {
+        let newline = text.iter().position(|&b| b == b'\n')?;
+        Some(if newline > 0 && text[newline - 1] == b'\r' {
+            LineEnding::Crlf
+        } else {
+            LineEnding::Lf
+        })
+    }
+}
+
+/// Encodings and line endings selected with `--encoding`, `--output-encoding`
+/// and `--eol`
+#[derive(Debug, Clone, Default)]
+pub struct EncodingOptions {
+    /// Encoding assumed for input without a byte order mark
+    pub input: Option<Encoding>,
+    /// Encoding used for output
+    pub output: Option<Encoding>,
+    /// Line ending used for output instead of the input's
+    pub eol: Option<LineEnding>,
+    /// Line ending of the first input read that has one
+    input_eol: OnceLock<LineEnding>,
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Windows-1252 characters for bytes 0x80-0x9F (undefined bytes map to C1 controls)
+const WINDOWS_1252_HIGH: [char; 32] = [
+    '\u{20AC}', '\u{81}', '\u{201A}', '\u{192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+    '\u{2C6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8D}', '\u{17D}', '\u{8F}',
+    '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+    '\u{2DC}', '\u{2122}', '\u{161}', '\u{203A}', '\u{153}', '\u{9D}', '\u{17E}', '\u{178}',
+];
+
+/// Encoding indicated by a byte order mark, or by the zero bytes of UTF-16 ASCII text
+fn sniff(bytes: &[u8]) -> Option<Encoding> {
+    if bytes.starts_with(UTF8_BOM) {
+        Some(Encoding::Utf8)
+    } else if bytes.starts_with(UTF16LE_BOM) {
+        Some(Encoding::Utf16Le)
+    } else if bytes.starts_with(UTF16BE_BOM) {
+        Some(Encoding::Utf16Be)
+    } else if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        None
+    } else if bytes[0] != 0 && bytes[1] == 0 {
+        Some(Encoding::Utf16Le)
+    } else if bytes[0] == 0 && bytes[1] != 0 {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Decode bytes to a string
+///
+/// A byte order mark takes precedence; without one `encoding` is used, and
+/// when that is `None` UTF-16 is sniffed before falling back to UTF-8. Binary
+/// property lists are recognized by their magic bytes and rewritten as XML.
+pub fn decode(bytes: &[u8], encoding: Option<Encoding>) -> Result<String> {
+    if bytes.starts_with(plist::BINARY_MAGIC) {
+        return plist::binary_to_xml(bytes);
+    }
+
+    let has_bom = [UTF8_BOM, UTF16LE_BOM, UTF16BE_BOM]
+        .iter()
+        .any(|bom| bytes.starts_with(bom));
+    let encoding = match (sniff(bytes), encoding) {
+        (Some(sniffed), _) if has_bom => sniffed,
+        (_, Some(Encoding::Utf16)) => Encoding::Utf16Le,
+        (_, Some(encoding)) => encoding,
+        (sniffed, None) => sniffed.unwrap_or(Encoding::Utf8),
+    };
+
+    match encoding {
+        Encoding::Utf8 | Encoding::Utf8Bom => {
+            let bytes = bytes.strip_prefix(UTF8_BOM).unwrap_or(bytes);
+            String::from_utf8(bytes.to_vec()).map_err(|e| {
+                anyhow::anyhow!(
+                    "Input is not valid UTF-8 (invalid byte at offset {}); use --encoding to \
+                     specify its encoding (e.g. latin1, windows-1252, utf-16)",
+                    e.utf8_error().valid_up_to()
+                )
+            })
+        }
+        Encoding::Utf16 | Encoding::Utf16Le => decode_utf16(bytes, UTF16LE_BOM, u16::from_le_bytes),
+        Encoding::Utf16Be => decode_utf16(bytes, UTF16BE_BOM, u16::from_be_bytes),
+        Encoding::Latin1 => Ok(bytes.iter().map(|&b| b as char).collect()),
+        Encoding::Windows1252 => Ok(bytes
+            .iter()
+            .map(|&b| match b {
+                0x80..=0x9F => WINDOWS_1252_HIGH[(b - 0x80) as usize],
+                _ => b as char,
+            })
+            .collect()),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], bom: &[u8], unit: fn([u8; 2]) -> u16) -> Result<String> {
+    let bytes = bytes.strip_prefix(bom).unwrap_or(bytes);
+    if !bytes.len().is_multiple_of(2) {
+        bail!("Input is not valid UTF-16 (odd number of bytes)");
+    }
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| unit([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).context("Input is not valid UTF-16 (unpaired surrogate)")
+}
+
+/// Encode a string, with a byte order mark for `utf-8-bom` and `utf-16` when `bom` is set
+pub fn encode(text: &str, encoding: Encoding, bom: bool) -> Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(text.len());
+    match encoding {
+        Encoding::Utf8 => bytes.extend_from_slice(text.as_bytes()),
+        Encoding::Utf8Bom => {
+            if bom {
+                bytes.extend_from_slice(UTF8_BOM);
+            }
+            bytes.extend_from_slice(text.as_bytes());
+        }
+        Encoding::Utf16 | Encoding::Utf16Le => {
+            if bom && encoding == Encoding::Utf16 {
+                bytes.extend_from_slice(UTF16LE_BOM);
+            }
+            for unit in text.encode_utf16() {
+                bytes.extend_from_slice(&unit.to_le_bytes());
+            }
+        }
+        Encoding::Utf16Be => {
+            for unit in text.encode_utf16() {
+                bytes.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+        Encoding::Latin1 | Encoding::Windows1252 => {
+            for c in text.chars() {
+                let code = c as u32;
+                let byte = if encoding == Encoding::Windows1252 {
+                    match WINDOWS_1252_HIGH.iter().position(|&h| h == c) {
+                        Some(i) => Some(0x80 + i as u8),
+                        None if code <= 0xFF && !(0x80..=0x9F).contains(&code) => Some(code as u8),
+                        None => None,
+                    }
+                } else {
+                    u8::try_from(code).ok()
+                };
+                match byte {
+                    Some(b) => bytes.push(b),
+                    None => bail!("Character '{}' cannot be encoded as {:?}", c, encoding),
+                }
+            }
+        }
+    }
+    Ok(bytes)
+}
+
+/// Read input from file or stdin as raw bytes, for binary formats
+pub fn read_bytes(path: Option<&Path>) -> Result<Vec<u8>> {
+    match path {
+        Some(p) => {
+            let bytes = compression::read_file(p)?;
+            Ok(bytes)
+        }
+        None => {
+            let mut buffer = Vec::new();
+            io::stdin()
+                .read_to_end(&mut buffer)
+                .context("Failed to read from stdin")?;
+            compression::decompress(buffer, None)
+        }
+    }
+}
+
+impl EncodingOptions {
+    /// Options with the given encodings and output line ending
+    pub fn new(input: Option<Encoding>, output: Option<Encoding>, eol: Option<LineEnding>) -> Self {
+        EncodingOptions {
+            input,
+            output,
+            eol,
+            input_eol: OnceLock::new(),
+        }
+    }
+
+    /// Output encoding, if it differs from plain UTF-8
+    pub fn output_encoding(&self) -> Option<Encoding> {
+        self.output.filter(|e| !e.is_utf8())
+    }
+
+    /// Read a file and decode it with the input encoding
+    pub fn read_file(&self, path: &Path) -> Result<String> {
+        let bytes = compression::read_file(path)?;
+        let text = decode(&bytes, self.input)
+            .with_context(|| format!("Failed to decode file: {}", path.display()))?;
+        self.record_input_line_ending(text.as_bytes());
+        Ok(text)
+    }
+
+    /// Read input from file or stdin, decoded with the input encoding
+    pub fn read_input(&self, path: Option<&Path>) -> Result<String> {
+        match path {
+            Some(p) => self.read_file(p),
+            None => {
+                let mut buffer = Vec::new();
+                io::stdin()
+                    .read_to_end(&mut buffer)
+                    .context("Failed to read from stdin")?;
+                let buffer = compression::decompress(buffer, None)?;
+                let text = decode(&buffer, self.input).context("Failed to decode stdin")?;
+                self.record_input_line_ending(text.as_bytes());
+                Ok(text)
+            }
+        }
+    }
+
+    /// Wrap a streaming reader so it yields UTF-8
+    ///
+    /// Compressed input is decompressed on the fly. Plain UTF-8 input keeps
+    /// streaming (minus a byte order mark); anything else is read fully and
+    /// decoded first.
+    pub fn utf8_reader<'a>(&self, reader: Box<dyn BufRead + 'a>) -> Result<Box<dyn BufRead + 'a>> {
+        let mut reader = compression::reader(reader)?;
+        let transcode = match self.input {
+            Some(encoding) if !encoding.is_utf8() => true,
+            _ => {
+                let head = reader.fill_buf().context("Failed to read input")?;
+                self.record_input_line_ending(head);
+                if head.starts_with(UTF8_BOM) {
+                    reader.consume(UTF8_BOM.len());
+                    false
+                } else {
+                    head.starts_with(UTF16LE_BOM) || head.starts_with(UTF16BE_BOM)
+                }
+            }
+        };
+        if !transcode {
+            return Ok(reader);
+        }
+
+        let mut buffer = Vec::new();
+        reader
+            .read_to_end(&mut buffer)
+            .context("Failed to read input")?;
+        let text = decode(&buffer, self.input)?;
+        self.record_input_line_ending(text.as_bytes());
+        Ok(Box::new(Cursor::new(text.into_bytes())))
+    }
+
+    /// Remember the line ending of the first input read
+    fn record_input_line_ending(&self, text: &[u8]) {
+        if let Some(eol) = LineEnding::detect(text) {
+            let _ = self.input_eol.set(eol);
+        }
+    }
+
+    /// Line ending for output: `--eol` if given, otherwise that of the input
+    pub fn output_line_ending(&self) -> LineEnding {
+        self.eol
+            .or(self.input_eol.get().copied())
+            .unwrap_or(LineEnding::Lf)
+    }
+
+    /// Convert text to the output line ending
+    pub fn with_line_endings<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self.output_line_ending() {
+            LineEnding::Crlf if text.contains('\n') => {
+                Cow::Owned(text.replace("\r\n", "\n").replace('\n', "\r\n"))
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+
+    /// Wrap a streaming writer so it uses the output line ending
+    pub fn eol_writer<'a>(&self, writer: Box<dyn Write + 'a>) -> Box<dyn Write + 'a> {
+        match self.output_line_ending() {
+            LineEnding::Crlf => Box::new(CrlfWriter {
+                inner: writer,
+                after_cr: false,
+            }),
+            LineEnding::Lf => writer,
+        }
+    }
+
+    /// Write a file in the output encoding, compressed if its extension says so
+    pub fn write_file(&self, path: &Path, contents: &str) -> Result<()> {
+        compression::write_file(path, &self.encode_output(contents)?)
+    }
+
+    /// Bytes written for `contents`, with the output line ending and encoding
+    pub fn encode_output(&self, contents: &str) -> Result<Vec<u8>> {
+        let contents = self.with_line_endings(contents);
+        match self.output_encoding() {
+            Some(encoding) => encode(&contents, encoding, true),
+            None => Ok(contents.into_owned().into_bytes()),
+        }
+    }
+}
+
+/// Writer translating `\n` to `\r\n`, leaving existing `\r\n` intact
+struct CrlfWriter<'a> {
+    inner: Box<dyn Write + 'a>,
+    after_cr: bool,
+}
+
+impl Write for CrlfWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut translated = Vec::with_capacity(buf.len() + buf.len() / 16);
+        for &b in buf {
+            if b == b'\n' && !self.after_cr {
+                translated.push(b'\r');
+            }
+            translated.push(b);
+            self.after_cr = b == b'\r';
+        }
+        self.inner.write_all(&translated)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"\xEF\xBB\xBFid,name", None).unwrap(), "id,name");
+        assert_eq!(decode(b"\xFF\xFEa\x00\xE9\x00", None).unwrap(), "aé");
+        assert_eq!(decode(b"\x00<\x00a", None).unwrap(), "<a");
+        assert_eq!(decode(b"caf\xE9", Some(Encoding::Latin1)).unwrap(), "café");
+        assert_eq!(
+            decode(b"\x93quoted\x94 \x80", Some(Encoding::Windows1252)).unwrap(),
+            "\u{201C}quoted\u{201D} €"
+        );
+
+        let err = decode(b"caf\xE9", None).unwrap_err().to_string();
+        assert!(err.contains("offset 3") && err.contains("--encoding"));
+    }
+
+    #[test]
+    fn test_line_endings() {
+        assert_eq!(LineEnding::detect(b"a\r\nb\n"), Some(LineEnding::Crlf));
+        assert_eq!(LineEnding::detect(b"a\nb\r\n"), Some(LineEnding::Lf));
+        assert_eq!(LineEnding::detect(b"single line"), None);
+
+        // The first input with a line ending decides, unless --eol is given
+        let options = EncodingOptions::default();
+        options.record_input_line_ending(b"single line");
+        options.record_input_line_ending(b"a\r\nb");
+        options.record_input_line_ending(b"a\nb");
+        assert_eq!(options.with_line_endings("a\nb"), "a\r\nb");
+        let options = EncodingOptions::new(None, None, Some(LineEnding::Lf));
+        options.record_input_line_ending(b"a\r\nb");
+        assert_eq!(options.output_line_ending(), LineEnding::Lf);
+
+        let mut out = Vec::new();
+        {
+            let mut writer = CrlfWriter {
+                inner: Box::new(&mut out),
+                after_cr: false,
+            };
+            writer.write_all(b"a\nb\r").unwrap();
+            writer.write_all(b"\nc\n").unwrap();
+        }
+        assert_eq!(out, b"a\r\nb\r\nc\r\n");
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let text = "name: Zoë € \u{1F600}";
+        for encoding in [
+            Encoding::Utf8,
+            Encoding::Utf8Bom,
+            Encoding::Utf16,
+            Encoding::Utf16Le,
+            Encoding::Utf16Be,
+        ] {
+            let bytes = encode(text, encoding, true).unwrap();
+            assert_eq!(decode(&bytes, Some(encoding)).unwrap(), text);
+        }
+
+        assert_eq!(
+            encode("€", Encoding::Windows1252, true).unwrap(),
+            vec![0x80]
+        );
+        assert!(encode("€", Encoding::Latin1, true).is_err());
+        assert_eq!(encode("a", Encoding::Utf16, false).unwrap(), b"a\x00");
+    }
+}
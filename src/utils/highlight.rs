@@ -2,6 +2,8 @@
 
 use colored::Colorize;
 
+use crate::formats::yaml::{self, YamlVersion};
+
 /// Highlight JSON output with colors
 pub fn highlight_json(json: &str) -> String {
     let mut result = String::new();
@@ -124,12 +126,12 @@ pub fn highlight_json(json: &str) -> String {
     result
 }
 
-/// Highlight YAML output with colors
-pub fn highlight_yaml(yaml: &str) -> String {
+/// Highlight YAML output with colors, typing plain scalars by the rules of `version`
+pub fn highlight_yaml(yaml: &str, version: YamlVersion) -> String {
     let mut result = String::new();
 
     for line in yaml.lines() {
-        let highlighted_line = highlight_yaml_line(line);
+        let highlighted_line = highlight_yaml_line(line, version);
         result.push_str(&highlighted_line);
         result.push('\n');
     }
@@ -142,7 +144,7 @@ pub fn highlight_yaml(yaml: &str) -> String {
     result
 }
 
-fn highlight_yaml_line(line: &str) -> String {
+fn highlight_yaml_line(line: &str, version: YamlVersion) -> String {
     // Handle comments
     if line.trim_start().starts_with('#') {
         return line.dimmed().to_string();
@@ -154,7 +156,7 @@ fn highlight_yaml_line(line: &str) -> String {
     }
 
     // Check for key-value pairs
-    if let Some(colon_pos) = find_yaml_colon(line) {
+    if let Some(colon_pos) = yaml::find_mapping_colon(line) {
         let (key_part, rest) = line.split_at(colon_pos);
         let colon_and_value = rest;
 
@@ -181,7 +183,7 @@ fn highlight_yaml_line(line: &str) -> String {
         // Highlight value if present
         if colon_and_value.len() > 1 {
             let value = &colon_and_value[1..];
-            result.push_str(&highlight_yaml_value(value));
+            result.push_str(&highlight_yaml_value(value, version));
         }
 
         result
@@ -191,50 +193,34 @@ fn highlight_yaml_line(line: &str) -> String {
         let indent = &line[..line.len() - trimmed.len()];
         let value = &trimmed[2..];
 
-        format!("{}{}{}", indent, "- ".yellow(), highlight_yaml_value(value))
+        format!(
+            "{}{}{}",
+            indent,
+            "- ".yellow(),
+            highlight_yaml_value(value, version)
+        )
     } else {
         line.to_string()
     }
 }
 
-fn find_yaml_colon(line: &str) -> Option<usize> {
-    let mut in_quotes = false;
-    let mut quote_char = ' ';
-
-    for (i, ch) in line.char_indices() {
-        match ch {
-            '"' | '\'' if !in_quotes => {
-                in_quotes = true;
-                quote_char = ch;
-            }
-            c if in_quotes && c == quote_char => {
-                in_quotes = false;
-            }
-            ':' if !in_quotes => {
-                // Check if it's followed by space, newline, or end of string
-                let next_char = line.chars().nth(i + 1);
-                if next_char.is_none() || next_char == Some(' ') || next_char == Some('\n') {
-                    return Some(i);
-                }
-            }
-            _ => {}
-        }
-    }
-    None
-}
-
-fn highlight_yaml_value(value: &str) -> String {
+fn highlight_yaml_value(value: &str, version: YamlVersion) -> String {
     let trimmed = value.trim();
 
     // Preserve leading space
     let leading_space = if value.starts_with(' ') { " " } else { "" };
 
     let highlighted = match trimmed {
-        "true" | "false" | "yes" | "no" | "on" | "off" => trimmed.blue().bold().to_string(),
+        "true" | "false" => trimmed.blue().bold().to_string(),
         "null" | "~" => trimmed.red().to_string(),
         s if s.starts_with('"') && s.ends_with('"') => s.green().to_string(),
         s if s.starts_with('\'') && s.ends_with('\'') => s.green().to_string(),
         s if s.parse::<f64>().is_ok() => s.magenta().to_string(),
+        s if version == YamlVersion::V1_1 => match yaml::resolve_scalar_1_1(s).as_deref() {
+            Some("true" | "false") => s.blue().bold().to_string(),
+            Some(_) => s.magenta().to_string(),
+            None => s.green().to_string(),
+        },
         "" => String::new(),
         s => s.green().to_string(),
     };
@@ -611,7 +597,7 @@ mod tests {
     #[test]
     fn test_highlight_yaml_basic() {
         let yaml = "name: test\nvalue: 42\nactive: true";
-        let _ = highlight_yaml(yaml);
+        let _ = highlight_yaml(yaml, YamlVersion::default());
     }
 
     #[test]
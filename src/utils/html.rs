@@ -0,0 +1,97 @@
+//! HTML export of syntax-highlighted output
+
+/// Highlight `text` and render it as a `<pre>` block with inline-styled spans
+///
+/// Reuses the terminal highlighters by forcing ANSI colors on and translating
+/// the escape sequences, so HTML and terminal output always agree.
+pub fn highlighted_html(text: &str, highlight: impl Fn(&str) -> String) -> String {
+    colored::control::set_override(true);
+    let ansi = highlight(text);
+    colored::control::unset_override();
+    ansi_to_html(&ansi)
+}
+
+/// Convert text containing ANSI SGR color sequences to HTML
+pub fn ansi_to_html(ansi: &str) -> String {
+    let mut html = String::from("<pre class=\"dtx-highlight\"><code>");
+    let mut span_open = false;
+    let mut rest = ansi;
+
+    while let Some(pos) = rest.find("\x1b[") {
+        push_escaped(&mut html, &rest[..pos]);
+        let after = &rest[pos + 2..];
+        let Some(end) = after.find('m') else {
+            rest = after;
+            continue;
+        };
+
+        if span_open {
+            html.push_str("</span>");
+            span_open = false;
+        }
+        let style = sgr_style(&after[..end]);
+        if !style.is_empty() {
+            html.push_str(&format!("<span style=\"{}\">", style));
+            span_open = true;
+        }
+        rest = &after[end + 1..];
+    }
+    push_escaped(&mut html, rest);
+
+    if span_open {
+        html.push_str("</span>");
+    }
+    html.push_str("</code></pre>");
+    html
+}
+
+/// CSS for a `;`-separated list of SGR parameters
+fn sgr_style(params: &str) -> String {
+    let mut styles = Vec::new();
+    for param in params.split(';') {
+        let style = match param {
+            "1" => "font-weight:bold",
+            "2" => "opacity:0.6",
+            "3" => "font-style:italic",
+            "4" => "text-decoration:underline",
+            "31" | "91" => "color:#d73a49",
+            "32" | "92" => "color:#22863a",
+            "33" | "93" => "color:#b08800",
+            "34" | "94" => "color:#005cc5",
+            "35" | "95" => "color:#6f42c1",
+            "36" | "96" => "color:#0086b3",
+            _ => continue,
+        };
+        styles.push(style);
+    }
+    styles.join(";")
+}
+
+fn push_escaped(html: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' => html.push_str("&quot;"),
+            _ => html.push(ch),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ansi_to_html() {
+        let html = ansi_to_html("\x1b[36m\"a\"\x1b[0m: \x1b[1;32m\"<b>\"\x1b[0m");
+        assert_eq!(
+            html,
+            "<pre class=\"dtx-highlight\"><code>\
+             <span style=\"color:#0086b3\">&quot;a&quot;</span>: \
+             <span style=\"font-weight:bold;color:#22863a\">&quot;&lt;b&gt;&quot;</span>\
+             </code></pre>"
+        );
+    }
+}
@@ -0,0 +1,427 @@
+//! Source positions of values addressed by JSON Pointer
+//!
+//! Parsed values carry no positions, so the text is scanned again to find where
+//! the value at a path begins. When a path cannot be followed to the end (flow
+//! collections in YAML, implicit tables in TOML), the nearest enclosing value
+//! that could be found is reported instead.
+
+use crate::formats::detect::Format;
+
+/// 1-based line and column of the value at `pointer` in `content`
+pub fn locate(content: &str, format: Format, pointer: &str) -> Option<(usize, usize)> {
+    let segments = pointer_segments(pointer);
+    let offset = match format {
+        Format::Json => json_offset(content, &segments),
+        Format::Yaml => yaml_offset(content, &segments),
+        Format::Toml => toml_offset(content, &segments),
+        _ => None,
+    }?;
+    Some(line_column(content, offset))
+}
+
+/// Unescaped reference tokens of a JSON Pointer (`/a~1b/0` → `["a/b", "0"]`)
+fn pointer_segments(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line = before.matches('\n').count() + 1;
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Byte offset of the value at `segments` in a JSON document
+fn json_offset(content: &str, segments: &[String]) -> Option<usize> {
+    let mut scanner = JsonScanner {
+        bytes: content.as_bytes(),
+        pos: 0,
+    };
+    let mut found = None;
+    scanner.skip_whitespace();
+    scanner.find(segments, &mut found);
+    found
+}
+
+struct JsonScanner<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl JsonScanner<'_> {
+    /// Scan the value at the current position, recording the deepest match of `segments`
+    fn find(&mut self, segments: &[String], found: &mut Option<usize>) -> Option<()> {
+        *found = Some(self.pos);
+        match self.bytes.get(self.pos)? {
+            b'{' => {
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    if self.bytes.get(self.pos)? == &b'}' {
+                        self.pos += 1;
+                        return Some(());
+                    }
+                    let key = self.string()?;
+                    self.skip_whitespace();
+                    self.expect(b':')?;
+                    self.skip_whitespace();
+                    match segments.split_first() {
+                        Some((first, rest)) if *first == key => return self.find(rest, found),
+                        _ => self.skip_value()?,
+                    }
+                    self.skip_whitespace();
+                    if self.bytes.get(self.pos)? == &b',' {
+                        self.pos += 1;
+                    }
+                }
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut index = 0;
+                loop {
+                    self.skip_whitespace();
+                    if self.bytes.get(self.pos)? == &b']' {
+                        self.pos += 1;
+                        return Some(());
+                    }
+                    match segments.split_first() {
+                        Some((first, rest)) if *first == index.to_string() => {
+                            return self.find(rest, found)
+                        }
+                        _ => self.skip_value()?,
+                    }
+                    index += 1;
+                    self.skip_whitespace();
+                    if self.bytes.get(self.pos)? == &b',' {
+                        self.pos += 1;
+                    }
+                }
+            }
+            _ => self.skip_value(),
+        }
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.bytes.get(self.pos)? {
+            b'"' => self.string().map(|_| ()),
+            b'{' | b'[' => {
+                let mut depth = 0usize;
+                while let Some(&byte) = self.bytes.get(self.pos) {
+                    match byte {
+                        b'"' => {
+                            self.string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => {
+                            depth -= 1;
+                            if depth == 0 {
+                                self.pos += 1;
+                                return Some(());
+                            }
+                        }
+                        _ => {}
+                    }
+                    self.pos += 1;
+                }
+                None
+            }
+            _ => {
+                while let Some(byte) = self.bytes.get(self.pos) {
+                    if matches!(byte, b',' | b'}' | b']') || byte.is_ascii_whitespace() {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                Some(())
+            }
+        }
+    }
+
+    /// Decoded string starting at the current `"`
+    fn string(&mut self) -> Option<String> {
+        let start = self.pos;
+        self.expect(b'"')?;
+        while let Some(&byte) = self.bytes.get(self.pos) {
+            self.pos += 1;
+            match byte {
+                b'\\' => self.pos += 1,
+                b'"' => {
+                    let text = std::str::from_utf8(&self.bytes[start..self.pos]).ok()?;
+                    return serde_json::from_str(text).ok();
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn expect(&mut self, byte: u8) -> Option<()> {
+        (self.bytes.get(self.pos)? == &byte).then(|| self.pos += 1)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+}
+
+/// A line of block YAML that holds content
+struct YamlLine<'a> {
+    /// Byte offset of the line in the document
+    start: usize,
+    indent: usize,
+    text: &'a str,
+}
+
+/// Byte offset of the value at `segments` in the first document of block YAML
+fn yaml_offset(content: &str, segments: &[String]) -> Option<usize> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\n', '\r']);
+        let trimmed = text.trim_start();
+        if trimmed.starts_with("---") || trimmed.starts_with("...") {
+            if !lines.is_empty() {
+                break;
+            }
+        } else if !trimmed.is_empty() && !trimmed.starts_with(['#', '%']) {
+            lines.push(YamlLine {
+                start,
+                indent: text.len() - trimmed.len(),
+                text,
+            });
+        }
+        start += line.len();
+    }
+
+    let first = lines.first()?;
+    let mut node = YamlNode {
+        line: 0,
+        content: Some(first.indent),
+    };
+    let mut found = first.start + first.indent;
+    for segment in segments {
+        let Some((child, column)) = yaml_child(&lines, &node, segment) else {
+            break;
+        };
+        found = lines[child.line].start + column;
+        node = child;
+    }
+    Some(found)
+}
+
+/// Where a YAML node is written
+struct YamlNode {
+    /// Index of the line the node starts on
+    line: usize,
+    /// Column its content starts at on that line, `None` when the content is on
+    /// the lines below (`key:` followed by a nested block)
+    content: Option<usize>,
+}
+
+/// The entry `segment` of `node` and the column to report for it
+fn yaml_child(lines: &[YamlLine], node: &YamlNode, segment: &str) -> Option<(YamlNode, usize)> {
+    let own = &lines[node.line];
+    // Entries start on the node's own line (`- name: x`) or on the lines below it
+    let (first, entry_column) = match node.content {
+        Some(column) => (node.line, column),
+        None => {
+            let next = lines.get(node.line + 1)?;
+            let is_sequence = next.text[next.indent..].starts_with('-');
+            if next.indent < own.indent || (next.indent == own.indent && !is_sequence) {
+                return None;
+            }
+            (node.line + 1, next.indent)
+        }
+    };
+    let is_sequence = lines[first].text[entry_column..].starts_with('-');
+
+    let mut index = 0;
+    for (i, entry) in lines.iter().enumerate().skip(first) {
+        let entry_start = if i == first {
+            entry_column
+        } else {
+            entry.indent
+        };
+        if entry_start < entry_column {
+            break;
+        }
+        if entry_start != entry_column {
+            continue;
+        }
+        let text = &entry.text[entry_start..];
+        match text.strip_prefix('-') {
+            Some(item) if is_sequence && (item.is_empty() || item.starts_with(' ')) => {
+                if segment == index.to_string() {
+                    return Some(match is_nested_value(item) {
+                        true => (
+                            YamlNode {
+                                line: i,
+                                content: None,
+                            },
+                            entry_start,
+                        ),
+                        false => {
+                            let column = entry_start + 1 + (item.len() - item.trim_start().len());
+                            (
+                                YamlNode {
+                                    line: i,
+                                    content: Some(column),
+                                },
+                                column,
+                            )
+                        }
+                    });
+                }
+                index += 1;
+            }
+            _ if is_sequence => break,
+            _ => {
+                let colon = crate::formats::yaml::find_mapping_colon(text)?;
+                let key = text[..colon].trim();
+                let key = key
+                    .strip_prefix(['"', '\''])
+                    .and_then(|k| k.strip_suffix(['"', '\'']))
+                    .unwrap_or(key);
+                if key != segment {
+                    continue;
+                }
+                let value = &text[colon + 1..];
+                return Some(match is_nested_value(value) {
+                    true => (
+                        YamlNode {
+                            line: i,
+                            content: None,
+                        },
+                        entry_start,
+                    ),
+                    false => {
+                        let column =
+                            entry_start + colon + 1 + (value.len() - value.trim_start().len());
+                        (
+                            YamlNode {
+                                line: i,
+                                content: Some(column),
+                            },
+                            column,
+                        )
+                    }
+                });
+            }
+        }
+    }
+    None
+}
+
+/// Whether a value leaves its content to the following lines (empty or a comment)
+fn is_nested_value(value: &str) -> bool {
+    let value = value.trim();
+    value.is_empty() || value.starts_with('#')
+}
+
+/// Byte offset of the value at `segments` in a TOML document
+fn toml_offset(content: &str, segments: &[String]) -> Option<usize> {
+    let document = toml_edit::ImDocument::parse(content).ok()?;
+    let mut node = TomlNode::Table(document.as_table());
+    let mut found = 0;
+    for segment in segments {
+        let Some((child, span)) = node.child(segment) else {
+            break;
+        };
+        // Implicit tables (`[a.b]` without `[a]`) have no span of their own
+        if let Some(span) = span {
+            found = span.start;
+        }
+        node = child;
+    }
+    Some(found)
+}
+
+/// A TOML value that may hold children
+enum TomlNode<'a> {
+    Table(&'a toml_edit::Table),
+    Tables(&'a toml_edit::ArrayOfTables),
+    Value(&'a toml_edit::Value),
+}
+
+impl<'a> TomlNode<'a> {
+    fn from_item(item: &'a toml_edit::Item) -> Option<Self> {
+        match item {
+            toml_edit::Item::Table(table) => Some(TomlNode::Table(table)),
+            toml_edit::Item::ArrayOfTables(tables) => Some(TomlNode::Tables(tables)),
+            toml_edit::Item::Value(value) => Some(TomlNode::Value(value)),
+            toml_edit::Item::None => None,
+        }
+    }
+
+    /// The child at `segment` and the span to report for it
+    fn child(&self, segment: &str) -> Option<(Self, Option<std::ops::Range<usize>>)> {
+        match self {
+            TomlNode::Table(table) => {
+                let item = table.get(segment)?;
+                let key_span = table.key(segment).and_then(|key| key.span());
+                Some((Self::from_item(item)?, item.span().or(key_span)))
+            }
+            TomlNode::Tables(tables) => {
+                let table = tables.get(segment.parse().ok()?)?;
+                Some((TomlNode::Table(table), table.span()))
+            }
+            TomlNode::Value(toml_edit::Value::InlineTable(table)) => {
+                let value = table.get(segment)?;
+                Some((TomlNode::Value(value), value.span()))
+            }
+            TomlNode::Value(toml_edit::Value::Array(array)) => {
+                let value = array.get(segment.parse().ok()?)?;
+                Some((TomlNode::Value(value), value.span()))
+            }
+            TomlNode::Value(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_locate_json() {
+        let json =
+            "{\n  \"a/b\": 1,\n  \"items\": [\n    {\"id\": 1},\n    {\"id\": \"x\"}\n  ]\n}";
+        assert_eq!(locate(json, Format::Json, ""), Some((1, 1)));
+        assert_eq!(locate(json, Format::Json, "/a~1b"), Some((2, 10)));
+        assert_eq!(locate(json, Format::Json, "/items/1/id"), Some((5, 12)));
+        assert_eq!(locate(json, Format::Json, "/items/7"), Some((3, 12)));
+    }
+
+    #[test]
+    fn test_locate_yaml() {
+        let yaml =
+            "# config\nserver:\n  port: \"80\"\n  hosts:\n  - a\n  - name: b\n    tls: yes\n";
+        assert_eq!(locate(yaml, Format::Yaml, "/server"), Some((2, 1)));
+        assert_eq!(locate(yaml, Format::Yaml, "/server/port"), Some((3, 9)));
+        assert_eq!(locate(yaml, Format::Yaml, "/server/hosts/0"), Some((5, 5)));
+        assert_eq!(
+            locate(yaml, Format::Yaml, "/server/hosts/1/tls"),
+            Some((7, 10))
+        );
+        assert_eq!(locate(yaml, Format::Yaml, "/server/missing"), Some((2, 1)));
+    }
+
+    #[test]
+    fn test_locate_toml() {
+        let toml = "title = \"x\"\n\n[server]\nport = \"80\"\n\n[[items]]\nid = 1\n\n[[items]]\nid = [1, 2]\n";
+        assert_eq!(locate(toml, Format::Toml, "/server/port"), Some((4, 8)));
+        assert_eq!(locate(toml, Format::Toml, "/items/1"), Some((9, 1)));
+        assert_eq!(locate(toml, Format::Toml, "/items/1/id/1"), Some((10, 10)));
+    }
+}
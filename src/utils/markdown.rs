@@ -0,0 +1,58 @@
+//! Markdown building blocks for CI-friendly reports
+
+/// Escape text for use inside a Markdown table cell
+pub fn escape_cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace('\n', "<br>")
+}
+
+/// Inline code span for a table cell, widening the fence when `text` contains backticks
+pub fn code_cell(text: &str) -> String {
+    if text.is_empty() {
+        return String::new();
+    }
+    let escaped = text.replace('|', "\\|").replace('\n', " ");
+    if escaped.contains('`') {
+        format!("`` {} ``", escaped)
+    } else {
+        format!("`{}`", escaped)
+    }
+}
+
+/// Render a Markdown table; cells are expected to be escaped already
+pub fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut output = format!("| {} |\n", headers.join(" | "));
+    output.push_str(&format!("|{}\n", "---|".repeat(headers.len())));
+    for row in rows {
+        output.push_str(&format!("| {} |\n", row.join(" | ")));
+    }
+    output
+}
+
+/// Wrap `body` in a collapsible `<details>` block
+pub fn details(summary: &str, body: &str, open: bool) -> String {
+    format!(
+        "<details{}>\n<summary>{}</summary>\n\n{}\n\n</details>\n",
+        if open { " open" } else { "" },
+        summary,
+        body.trim_end()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_escaping() {
+        let rows = vec![vec![code_cell("/a|b"), escape_cell("x | y\nz")]];
+        assert_eq!(
+            table(&["Path", "Message"], &rows),
+            "| Path | Message |\n|---|---|\n| `/a\\|b` | x \\| y<br>z |\n"
+        );
+        assert_eq!(code_cell("a`b"), "`` a`b ``");
+        assert_eq!(code_cell(""), "");
+    }
+}
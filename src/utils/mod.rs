@@ -1,3 +1,13 @@
 //! Utility modules
 
+pub mod compression;
+pub mod diagnostics;
+pub mod encoding;
 pub mod highlight;
+pub mod html;
+pub mod locate;
+pub mod markdown;
+pub mod paths;
+pub mod timing;
+pub mod width;
+pub mod zip;
@@ -0,0 +1,103 @@
+//! Path and glob helpers
+
+use anyhow::{bail, Context, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// Whether an input argument is a glob pattern rather than a plain path
+pub fn is_glob(pattern: &str) -> bool {
+    pattern.contains(['*', '?', '['])
+}
+
+/// Expand a glob pattern into the matching files, sorted
+pub fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in
+        glob::glob(pattern).with_context(|| format!("Invalid glob pattern: {}", pattern))?
+    {
+        let path = entry.context("Failed to read glob match")?;
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Expand the glob patterns among input arguments, keeping argument order;
+/// each pattern's matches are sorted and a pattern matching nothing is an
+/// error. Existing files are never treated as patterns.
+pub fn expand_inputs(inputs: &[PathBuf]) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for input in inputs {
+        let pattern = input.to_string_lossy();
+        if !is_glob(&pattern) || input.exists() {
+            files.push(input.clone());
+            continue;
+        }
+        let matches = expand_glob(&pattern)?;
+        if matches.is_empty() {
+            bail!("No files match {}", pattern);
+        }
+        files.extend(matches);
+    }
+    Ok(files)
+}
+
+/// Leading directory of a glob pattern that contains no wildcards
+/// (e.g. `data/**/*.json` -> `data`)
+pub fn glob_base(pattern: &str) -> PathBuf {
+    let mut base = PathBuf::new();
+    for component in Path::new(pattern).components() {
+        if let Component::Normal(part) = component {
+            if is_glob(&part.to_string_lossy()) {
+                break;
+            }
+        }
+        base.push(component);
+    }
+    // A pattern without wildcards names a file; its base is the parent directory
+    if !is_glob(pattern) {
+        base.pop();
+    }
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_base() {
+        assert_eq!(glob_base("data/**/*.json"), PathBuf::from("data"));
+        assert_eq!(glob_base("/srv/a/*/b.yaml"), PathBuf::from("/srv/a"));
+        assert_eq!(glob_base("*.json"), PathBuf::new());
+        assert_eq!(glob_base("data/file.json"), PathBuf::from("data"));
+    }
+
+    #[test]
+    fn test_expand_inputs() {
+        let dir = std::env::temp_dir().join(format!("dtx-expand-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        for name in ["b.yaml", "a.yaml", "c.json", "sub/d.yaml"] {
+            std::fs::write(dir.join(name), "").unwrap();
+        }
+
+        let inputs = [
+            dir.join("c.json"),
+            dir.join("**/*.yaml"),
+            PathBuf::from("missing.json"),
+        ];
+        let files = expand_inputs(&inputs).unwrap();
+        let expected = [
+            dir.join("c.json"),
+            dir.join("a.yaml"),
+            dir.join("b.yaml"),
+            dir.join("sub/d.yaml"),
+            PathBuf::from("missing.json"),
+        ];
+        assert_eq!(files, expected);
+        assert!(expand_inputs(&[dir.join("*.toml")]).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
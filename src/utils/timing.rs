@@ -0,0 +1,98 @@
+//! Per-phase durations reported by `--timing`
+//!
+//! Phases are recorded into a [`Timings`] passed to [`time`]; repeated phases
+//! (one per input file or target format) are summed and listed in order of
+//! first use.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Phase durations recorded since the timings were created
+#[derive(Debug)]
+pub struct Timings {
+    started: Instant,
+    phases: Mutex<Vec<(&'static str, Duration, usize)>>,
+}
+
+impl Timings {
+    /// Start recording phase durations
+    pub fn new() -> Self {
+        Timings {
+            started: Instant::now(),
+            phases: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Table of recorded phases and the total run time
+    pub fn report(&self) -> String {
+        let phases = self.phases.lock().unwrap_or_else(|e| e.into_inner());
+        format_report(&phases, self.started.elapsed())
+    }
+
+    fn record(&self, phase: &'static str, elapsed: Duration) {
+        let mut phases = self.phases.lock().unwrap_or_else(|e| e.into_inner());
+        match phases.iter_mut().find(|(name, _, _)| *name == phase) {
+            Some((_, total, count)) => {
+                *total += elapsed;
+                *count += 1;
+            }
+            None => phases.push((phase, elapsed, 1)),
+        }
+    }
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Run `f`, adding its duration to `phase` when `timings` is given
+pub fn time<T>(timings: Option<&Timings>, phase: &'static str, f: impl FnOnce() -> T) -> T {
+    let Some(timings) = timings else {
+        return f();
+    };
+
+    let start = Instant::now();
+    let result = f();
+    timings.record(phase, start.elapsed());
+    result
+}
+
+fn format_report(phases: &[(&'static str, Duration, usize)], total: Duration) -> String {
+    let mut output = String::from("Timing:\n");
+    for (phase, duration, count) in phases {
+        output.push_str(&format!(
+            "  {:<10} {:>10}",
+            phase,
+            format_duration(*duration)
+        ));
+        if *count > 1 {
+            output.push_str(&format!("  ({} runs)", count));
+        }
+        output.push('\n');
+    }
+    output.push_str(&format!("  {:<10} {:>10}", "total", format_duration(total)));
+    output
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2} ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_report() {
+        let phases = [
+            ("read", Duration::from_micros(1500), 1),
+            ("parse", Duration::from_millis(12), 3),
+        ];
+        assert_eq!(
+            format_report(&phases, Duration::from_millis(20)),
+            "Timing:\n  read          1.50 ms\n  parse        12.00 ms  (3 runs)\n  total        20.00 ms"
+        );
+    }
+}
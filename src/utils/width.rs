@@ -0,0 +1,198 @@
+//! Display width of text in terminal columns
+//!
+//! Wide East Asian characters and most emoji take two columns, combining marks
+//! and zero-width characters none. Truncation and padding work on these widths
+//! so multi-byte text is never cut mid-character and CJK columns line up.
+
+use std::io::{self, IsTerminal};
+
+/// Code point ranges drawn two columns wide
+const WIDE: &[(u32, u32)] = &[
+    (0x1100, 0x115F),
+    (0x231A, 0x231B),
+    (0x2329, 0x232A),
+    (0x23E9, 0x23EC),
+    (0x23F0, 0x23F0),
+    (0x23F3, 0x23F3),
+    (0x25FD, 0x25FE),
+    (0x2614, 0x2615),
+    (0x2648, 0x2653),
+    (0x267F, 0x267F),
+    (0x2693, 0x2693),
+    (0x26A1, 0x26A1),
+    (0x26AA, 0x26AB),
+    (0x26BD, 0x26BE),
+    (0x26C4, 0x26C5),
+    (0x26CE, 0x26CE),
+    (0x26D4, 0x26D4),
+    (0x26EA, 0x26EA),
+    (0x26F2, 0x26F5),
+    (0x26FA, 0x26FD),
+    (0x2705, 0x2705),
+    (0x270A, 0x270B),
+    (0x2728, 0x2728),
+    (0x274C, 0x274E),
+    (0x2753, 0x2757),
+    (0x2795, 0x2797),
+    (0x27B0, 0x27B0),
+    (0x27BF, 0x27BF),
+    (0x2B1B, 0x2B1C),
+    (0x2B50, 0x2B55),
+    (0x2E80, 0x303E),
+    (0x3041, 0x33FF),
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xA000, 0xA4CF),
+    (0xA960, 0xA97F),
+    (0xAC00, 0xD7A3),
+    (0xF900, 0xFAFF),
+    (0xFE10, 0xFE19),
+    (0xFE30, 0xFE6F),
+    (0xFF00, 0xFF60),
+    (0xFFE0, 0xFFE6),
+    (0x1F004, 0x1F004),
+    (0x1F0CF, 0x1F0CF),
+    (0x1F18E, 0x1F18E),
+    (0x1F191, 0x1F19A),
+    (0x1F200, 0x1F251),
+    (0x1F300, 0x1F64F),
+    (0x1F680, 0x1F6FF),
+    (0x1F900, 0x1F9FF),
+    (0x1FA70, 0x1FAFF),
+    (0x20000, 0x2FFFD),
+    (0x30000, 0x3FFFD),
+];
+
+/// Code point ranges that take no column of their own
+const ZERO_WIDTH: &[(u32, u32)] = &[
+    (0x0300, 0x036F),
+    (0x0483, 0x0489),
+    (0x0591, 0x05BD),
+    (0x0610, 0x061A),
+    (0x064B, 0x065F),
+    (0x1AB0, 0x1AFF),
+    (0x1DC0, 0x1DFF),
+    (0x200B, 0x200F),
+    (0x2028, 0x202E),
+    (0x2060, 0x2064),
+    (0x20D0, 0x20FF),
+    (0x302A, 0x302F),
+    (0x3099, 0x309A),
+    (0xFE00, 0xFE0F),
+    (0xFE20, 0xFE2F),
+    (0xFEFF, 0xFEFF),
+    (0xE0100, 0xE01EF),
+];
+
+fn in_ranges(ranges: &[(u32, u32)], code: u32) -> bool {
+    ranges
+        .binary_search_by(|&(start, end)| {
+            if end < code {
+                std::cmp::Ordering::Less
+            } else if start > code {
+                std::cmp::Ordering::Greater
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+/// Columns `c` occupies in a terminal
+pub fn char_width(c: char) -> usize {
+    let code = c as u32;
+    if code < 0x20 || (0x7F..0xA0).contains(&code) || in_ranges(ZERO_WIDTH, code) {
+        0
+    } else if in_ranges(WIDE, code) {
+        2
+    } else {
+        1
+    }
+}
+
+/// Columns `text` occupies in a terminal
+pub fn str_width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// `text` cut to at most `width` columns, ending with `marker` when cut
+pub fn truncate(text: &str, width: usize, marker: &str) -> String {
+    if str_width(text) <= width {
+        return text.to_string();
+    }
+    let limit = width.saturating_sub(str_width(marker));
+    let mut output = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = char_width(c);
+        if used + w > limit {
+            break;
+        }
+        used += w;
+        output.push(c);
+    }
+    // A wide character that did not fit leaves a column to fill
+    output.push_str(&" ".repeat(limit - used));
+    output.push_str(marker);
+    output
+}
+
+/// `text` followed by spaces up to `width` columns
+pub fn pad(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(width.saturating_sub(str_width(text)))
+    )
+}
+
+/// `text` centered in `width` columns
+pub fn center(text: &str, width: usize) -> String {
+    let space = width.saturating_sub(str_width(text));
+    format!(
+        "{}{}{}",
+        " ".repeat(space / 2),
+        text,
+        " ".repeat(space - space / 2)
+    )
+}
+
+/// Width of the terminal stdout writes to, from `COLUMNS` or the terminal
+/// itself; `None` when output is redirected
+pub fn terminal_width() -> Option<usize> {
+    if let Some(columns) = std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .filter(|&c| c > 0)
+    {
+        return Some(columns);
+    }
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    crossterm::terminal::size()
+        .ok()
+        .map(|(width, _)| width as usize)
+        .filter(|&width| width > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_widths() {
+        assert_eq!(str_width("abc"), 3);
+        assert_eq!(str_width("日本語"), 6);
+        assert_eq!(str_width("e\u{301}"), 1);
+        assert_eq!(str_width("한글"), 4);
+
+        assert_eq!(truncate("short", 10, "…"), "short");
+        assert_eq!(truncate("日本語テキスト", 7, "…"), "日本語…");
+        assert_eq!(truncate("日本語テキスト", 8, "…"), "日本語 …");
+        assert_eq!(truncate("héllo wörld", 8, "..."), "héllo...");
+
+        assert_eq!(pad("日本", 6), "日本  ");
+        assert_eq!(center("ab", 5), " ab  ");
+    }
+}
@@ -0,0 +1,308 @@
+//! Minimal ZIP archive reading and writing
+//!
+//! Enough of the format for office documents: entries are found through the
+//! central directory and may be stored or deflated. ZIP64 and encrypted
+//! archives are rejected. Written archives deflate every entry.
+
+use anyhow::{bail, Context, Result};
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use flate2::{Compression, Crc};
+use std::io::{Read, Write};
+
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const CENTRAL_DIRECTORY_ENTRY: u32 = 0x0201_4b50;
+const LOCAL_FILE_HEADER: u32 = 0x0403_4b50;
+
+/// Compression methods
+const STORED: u16 = 0;
+const DEFLATED: u16 = 8;
+
+/// Version 2.0 of the format: deflate and directories
+const VERSION: u16 = 20;
+
+/// General purpose flag marking entry names as UTF-8
+const UTF8_NAMES: u16 = 1 << 11;
+
+/// 1980-01-01 00:00 in MS-DOS format, so the same entries give the same archive
+const DOS_TIME: u16 = 0;
+const DOS_DATE: u16 = 0x0021;
+
+/// A ZIP archive held in memory
+pub struct ZipArchive<'a> {
+    bytes: &'a [u8],
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    name: String,
+    method: u16,
+    flags: u16,
+    compressed_size: usize,
+    size: usize,
+    header_offset: usize,
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> Result<u16> {
+    let b = bytes.get(pos..pos + 2).context("Truncated ZIP archive")?;
+    Ok(u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> Result<u32> {
+    let b = bytes.get(pos..pos + 4).context("Truncated ZIP archive")?;
+    Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+impl<'a> ZipArchive<'a> {
+    /// Read the central directory of `bytes`
+    pub fn new(bytes: &'a [u8]) -> Result<Self> {
+        // The end record sits in the last 22 bytes plus up to 64 KiB of comment
+        let search_start = bytes.len().saturating_sub(22 + u16::MAX as usize);
+        let end = (search_start..bytes.len().saturating_sub(21))
+            .rev()
+            .find(|&pos| u32_at(bytes, pos).ok() == Some(END_OF_CENTRAL_DIRECTORY))
+            .context("Not a ZIP archive (no end of central directory)")?;
+
+        let count = u16_at(bytes, end + 10)? as usize;
+        let directory_offset = u32_at(bytes, end + 16)?;
+        if directory_offset == u32::MAX {
+            bail!("ZIP64 archives are not supported");
+        }
+
+        let mut entries = Vec::with_capacity(count);
+        let mut pos = directory_offset as usize;
+        for _ in 0..count {
+            if u32_at(bytes, pos)? != CENTRAL_DIRECTORY_ENTRY {
+                bail!("Corrupt ZIP central directory at byte {}", pos);
+            }
+            let name_len = u16_at(bytes, pos + 28)? as usize;
+            let extra_len = u16_at(bytes, pos + 30)? as usize;
+            let comment_len = u16_at(bytes, pos + 32)? as usize;
+            let name = bytes
+                .get(pos + 46..pos + 46 + name_len)
+                .context("Truncated ZIP archive")?;
+            entries.push(Entry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                flags: u16_at(bytes, pos + 8)?,
+                method: u16_at(bytes, pos + 10)?,
+                compressed_size: u32_at(bytes, pos + 20)? as usize,
+                size: u32_at(bytes, pos + 24)? as usize,
+                header_offset: u32_at(bytes, pos + 42)? as usize,
+            });
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+
+        Ok(Self { bytes, entries })
+    }
+
+    /// Names of all entries, in archive order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|e| e.name.as_str())
+    }
+
+    /// Contents of the entry called `name`, or `None` when there is none
+    pub fn read(&self, name: &str) -> Result<Option<Vec<u8>>> {
+        let Some(entry) = self.entries.iter().find(|e| e.name == name) else {
+            return Ok(None);
+        };
+        if entry.flags & 1 != 0 {
+            bail!("ZIP entry '{}' is encrypted", name);
+        }
+
+        let pos = entry.header_offset;
+        if u32_at(self.bytes, pos)? != LOCAL_FILE_HEADER {
+            bail!("Corrupt ZIP entry '{}'", name);
+        }
+        let start = pos
+            + 30
+            + u16_at(self.bytes, pos + 26)? as usize
+            + u16_at(self.bytes, pos + 28)? as usize;
+        let data = start
+            .checked_add(entry.compressed_size)
+            .and_then(|end| self.bytes.get(start..end))
+            .with_context(|| format!("Truncated ZIP entry '{}'", name))?;
+
+        match entry.method {
+            STORED => Ok(Some(data.to_vec())),
+            DEFLATED => {
+                // Sizes come from the archive, so allocate no more than the
+                // compressed bytes suggest and stop inflating past the declared size
+                let mut output = Vec::with_capacity(entry.size.min(data.len() * 8));
+                DeflateDecoder::new(data)
+                    .take(entry.size as u64 + 1)
+                    .read_to_end(&mut output)
+                    .with_context(|| format!("Failed to inflate ZIP entry '{}'", name))?;
+                if output.len() > entry.size {
+                    bail!(
+                        "ZIP entry '{}' inflates past its declared {} bytes",
+                        name,
+                        entry.size
+                    );
+                }
+                Ok(Some(output))
+            }
+            method => bail!(
+                "ZIP entry '{}' uses unsupported compression method {}",
+                name,
+                method
+            ),
+        }
+    }
+}
+
+/// A ZIP archive being built in memory
+#[derive(Default)]
+pub struct ZipWriter {
+    bytes: Vec<u8>,
+    directory: Vec<u8>,
+    count: u16,
+}
+
+impl ZipWriter {
+    /// Append a deflated entry
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        let mut crc = Crc::new();
+        crc.update(data);
+
+        let too_large = || format!("ZIP entry '{}' is too large", name);
+        let size = u32::try_from(data.len()).with_context(too_large)?;
+        let compressed_size = u32::try_from(compressed.len()).with_context(too_large)?;
+        let offset = u32::try_from(self.bytes.len()).context("ZIP archive is too large")?;
+        let name_len = u16::try_from(name.len()).context("ZIP entry name is too long")?;
+        self.count = self
+            .count
+            .checked_add(1)
+            .context("ZIP archive has too many entries")?;
+
+        // Fields shared by the local header and the central directory entry
+        let mut common = Vec::with_capacity(26);
+        for field in [VERSION, UTF8_NAMES, DEFLATED, DOS_TIME, DOS_DATE] {
+            common.extend(field.to_le_bytes());
+        }
+        for field in [crc.sum(), compressed_size, size] {
+            common.extend(field.to_le_bytes());
+        }
+        common.extend(name_len.to_le_bytes());
+        common.extend([0u8; 2]);
+
+        self.bytes.extend(LOCAL_FILE_HEADER.to_le_bytes());
+        self.bytes.extend(&common);
+        self.bytes.extend(name.as_bytes());
+        self.bytes.extend(compressed);
+
+        self.directory.extend(CENTRAL_DIRECTORY_ENTRY.to_le_bytes());
+        self.directory.extend(VERSION.to_le_bytes());
+        self.directory.extend(&common);
+        // Comment length, disk number, internal and external attributes
+        self.directory.extend([0u8; 10]);
+        self.directory.extend(offset.to_le_bytes());
+        self.directory.extend(name.as_bytes());
+        Ok(())
+    }
+
+    /// The archive with its central directory
+    pub fn finish(mut self) -> Result<Vec<u8>> {
+        let offset = u32::try_from(self.bytes.len()).context("ZIP archive is too large")?;
+        let size = u32::try_from(self.directory.len()).context("ZIP archive is too large")?;
+        self.bytes.append(&mut self.directory);
+        self.bytes.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+        self.bytes.extend([0u8; 4]);
+        self.bytes.extend(self.count.to_le_bytes());
+        self.bytes.extend(self.count.to_le_bytes());
+        self.bytes.extend(size.to_le_bytes());
+        self.bytes.extend(offset.to_le_bytes());
+        self.bytes.extend([0u8; 2]);
+        Ok(self.bytes)
+    }
+}
+
+/// Build an archive of stored entries (without checksums, which `read` ignores)
+#[cfg(test)]
+pub(crate) fn stored_archive(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut directory = Vec::new();
+    for (name, data) in entries {
+        let offset = bytes.len() as u32;
+        let sizes = [(data.len() as u32).to_le_bytes(); 2].concat();
+        bytes.extend(LOCAL_FILE_HEADER.to_le_bytes());
+        bytes.extend([0u8; 14]);
+        bytes.extend(&sizes);
+        bytes.extend((name.len() as u16).to_le_bytes());
+        bytes.extend([0u8; 2]);
+        bytes.extend(name.as_bytes());
+        bytes.extend(*data);
+
+        directory.extend(CENTRAL_DIRECTORY_ENTRY.to_le_bytes());
+        directory.extend([0u8; 16]);
+        directory.extend(&sizes);
+        directory.extend((name.len() as u16).to_le_bytes());
+        directory.extend([0u8; 12]);
+        directory.extend(offset.to_le_bytes());
+        directory.extend(name.as_bytes());
+    }
+    let directory_offset = bytes.len() as u32;
+    let count = (entries.len() as u16).to_le_bytes();
+    bytes.extend(&directory);
+    bytes.extend(END_OF_CENTRAL_DIRECTORY.to_le_bytes());
+    bytes.extend([0u8; 4]);
+    bytes.extend(count);
+    bytes.extend(count);
+    bytes.extend((directory.len() as u32).to_le_bytes());
+    bytes.extend(directory_offset.to_le_bytes());
+    bytes.extend([0u8; 2]);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_stored_entries() {
+        let bytes = stored_archive(&[("a.txt", b"hello"), ("dir/b.xml", b"<b/>")]);
+        let archive = ZipArchive::new(&bytes).unwrap();
+        assert_eq!(archive.names().collect::<Vec<_>>(), ["a.txt", "dir/b.xml"]);
+        assert_eq!(archive.read("dir/b.xml").unwrap().unwrap(), b"<b/>");
+        assert!(archive.read("missing").unwrap().is_none());
+        assert!(ZipArchive::new(b"not a zip").is_err());
+    }
+
+    #[test]
+    fn test_write_deflated_entries() {
+        let text = "repeated text ".repeat(100);
+        let mut writer = ZipWriter::default();
+        writer.add("docs/ü.txt", text.as_bytes()).unwrap();
+        writer.add("empty", b"").unwrap();
+        let bytes = writer.finish().unwrap();
+        assert!(bytes.len() < text.len());
+
+        let archive = ZipArchive::new(&bytes).unwrap();
+        assert_eq!(archive.names().collect::<Vec<_>>(), ["docs/ü.txt", "empty"]);
+        assert_eq!(
+            archive.read("docs/ü.txt").unwrap().unwrap(),
+            text.as_bytes()
+        );
+        assert_eq!(archive.read("empty").unwrap().unwrap(), b"");
+    }
+
+    #[test]
+    fn test_inflating_past_declared_size() {
+        let mut writer = ZipWriter::default();
+        writer.add("bomb.xml", &[b'x'; 100_000]).unwrap();
+        let mut bytes = writer.finish().unwrap();
+        let directory = bytes
+            .windows(4)
+            .position(|w| w == CENTRAL_DIRECTORY_ENTRY.to_le_bytes())
+            .unwrap();
+        bytes[directory + 24..directory + 28].copy_from_slice(&10u32.to_le_bytes());
+
+        let err = ZipArchive::new(&bytes)
+            .unwrap()
+            .read("bomb.xml")
+            .unwrap_err();
+        assert!(err.to_string().contains("declared 10 bytes"));
+    }
+}